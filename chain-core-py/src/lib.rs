@@ -0,0 +1,414 @@
+//! Python bindings for `chain-core`.
+//!
+//! Built with maturin into a `chain_core` extension module so notebooks can create
+//! chains, mine signed blocks, verify them and look at the materialized state:
+//!
+//! ```python
+//! import chain_core
+//!
+//! signer = chain_core.KeyPair()
+//! chain = chain_core.Chain(difficulty=3)
+//! chain.put("user", "alice", signer)
+//! chain.verify()
+//! chain.materialize()  # {'user': 'alice'}
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chain_core::{Block, CHAIN_FORMAT_VERSION, Chain, HashScheme, Op, Progress, SignatureDomain, keys};
+use ed25519_dalek::SigningKey;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// A `put` or `delete` on the key/value state, or a signing-key `rotate`
+#[pyclass(name = "Op", module = "chain_core", frozen, eq)]
+#[derive(Clone, PartialEq)]
+struct PyOp(Op);
+
+#[pymethods]
+impl PyOp {
+    /// Set `key` to `value`
+    #[staticmethod]
+    fn put(key: String, value: String) -> Self {
+        Self(Op::put(key, value))
+    }
+
+    /// Remove `key`
+    #[staticmethod]
+    fn delete(key: String) -> Self {
+        Self(Op::del(key))
+    }
+
+    /// Require blocks after this one to be signed by `new_pubkey` (hex)
+    #[staticmethod]
+    fn rotate(new_pubkey: String) -> Self {
+        Self(Op::Rotate { new_pubkey })
+    }
+
+    /// `"put"`, `"del"` or `"rotate"`
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.0 {
+            Op::Put { .. } => "put",
+            Op::Del { .. } => "del",
+            Op::Rotate { .. } => "rotate",
+        }
+    }
+
+    /// The key a put or delete changes, `None` for a rotation
+    #[getter]
+    fn key(&self) -> Option<&str> {
+        match &self.0 {
+            Op::Put { key, .. } | Op::Del { key, .. } => Some(key),
+            Op::Rotate { .. } => None,
+        }
+    }
+
+    /// The new value for a put, `None` otherwise
+    #[getter]
+    fn value(&self) -> Option<&str> {
+        match &self.0 {
+            Op::Put { value, .. } => Some(value),
+            Op::Del { .. } | Op::Rotate { .. } => None,
+        }
+    }
+
+    /// The rotated-in public key for a rotation, `None` otherwise
+    #[getter]
+    fn new_pubkey(&self) -> Option<&str> {
+        match &self.0 {
+            Op::Rotate { new_pubkey } => Some(new_pubkey),
+            Op::Put { .. } | Op::Del { .. } => None,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.0 {
+            Op::Put { key, value, .. } => format!("Op.put({key:?}, {value:?})"),
+            Op::Del { key, .. } => format!("Op.delete({key:?})"),
+            Op::Rotate { new_pubkey } => format!("Op.rotate({new_pubkey:?})"),
+        }
+    }
+}
+
+/// An Ed25519 signing key
+#[pyclass(name = "KeyPair", module = "chain_core", frozen)]
+struct PyKeyPair(SigningKey);
+
+#[pymethods]
+impl PyKeyPair {
+    /// A fresh random key
+    #[new]
+    fn new() -> Self {
+        Self(keys::generate())
+    }
+
+    /// Read a key file written by `generate_file` or the chain_kv `keygen` command
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        Ok(Self(keys::load_key_from_file(&path.to_string_lossy())?))
+    }
+
+    /// Generate a key, write it to `path` and return it
+    #[staticmethod]
+    fn generate_file(path: PathBuf) -> PyResult<Self> {
+        let path = path.to_string_lossy();
+        keys::keygen_to_file(&path)?;
+        Ok(Self(keys::load_key_from_file(&path)?))
+    }
+
+    /// Hex public key, as it appears in `Block.signer_pubkey`
+    #[getter]
+    fn public_hex(&self) -> String {
+        keys::public_hex(&self.0)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("KeyPair(public_hex={:?})", self.public_hex())
+    }
+}
+
+/// A mined block; a snapshot, so changing the chain afterwards does not affect it
+#[pyclass(name = "Block", module = "chain_core", frozen)]
+struct PyBlock(Block);
+
+#[pymethods]
+impl PyBlock {
+    #[getter]
+    fn index(&self) -> u64 {
+        self.0.index
+    }
+
+    /// Unix timestamp (seconds) at which mining started
+    #[getter]
+    fn timestamp(&self) -> i64 {
+        self.0.timestamp
+    }
+
+    #[getter]
+    fn ops(&self) -> Vec<PyOp> {
+        self.0.ops.iter().cloned().map(PyOp).collect()
+    }
+
+    #[getter]
+    fn prev_hash(&self) -> &str {
+        &self.0.prev_hash
+    }
+
+    #[getter]
+    fn merkle_root(&self) -> &str {
+        &self.0.merkle_root
+    }
+
+    #[getter]
+    fn nonce(&self) -> u64 {
+        self.0.nonce
+    }
+
+    #[getter]
+    fn hash(&self) -> &str {
+        &self.0.hash
+    }
+
+    #[getter]
+    fn signature(&self) -> Option<&str> {
+        self.0.signature.as_deref()
+    }
+
+    #[getter]
+    fn signer_pubkey(&self) -> Option<&str> {
+        self.0.signer_pubkey.as_deref()
+    }
+
+    /// Check link, hash, proof-of-work and signature; raises `ValueError` on failure.
+    ///
+    /// Pass the chain's `chain_id`; `None` checks a version 1 signature over the bare hash.
+    /// `version` is the chain's format version, which picks how the hash is computed and
+    /// how the signature binds the chain id.
+    #[pyo3(signature = (prev_hash, difficulty, chain_id = None, version = CHAIN_FORMAT_VERSION))]
+    fn verify(&self, prev_hash: &str, difficulty: usize, chain_id: Option<&str>, version: u32) -> PyResult<()> {
+        let domain = chain_id.map_or(SignatureDomain::Bare, |id| SignatureDomain::for_version(version, id));
+        self.0
+            .verify(prev_hash, difficulty, &domain, HashScheme::for_version(version))
+            .map_err(PyValueError::new_err)
+    }
+
+    /// The block as JSON, in the same format as chain files
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Block(index={}, ops={}, hash={:?})", self.0.index, self.0.ops.len(), self.0.hash)
+    }
+}
+
+/// A proof-of-work chain of signed key/value blocks
+#[pyclass(name = "Chain", module = "chain_core")]
+struct PyChain(Chain);
+
+#[pymethods]
+impl PyChain {
+    /// A chain holding only the genesis block; hashes need `difficulty` leading hex zeros,
+    /// and signatures name `chain_id` (random if not given) so blocks cannot be copied
+    /// between chains
+    #[new]
+    #[pyo3(signature = (difficulty = 3, chain_id = None))]
+    fn new(difficulty: usize, chain_id: Option<String>) -> Self {
+        Self(match chain_id {
+            Some(id) => Chain::genesis_with_id(id, difficulty),
+            None => Chain::genesis(difficulty),
+        })
+    }
+
+    /// Load a JSON or `.zst` chain file
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        Ok(Self(Chain::load(&path.to_string_lossy())?))
+    }
+
+    /// Save as JSON, or zstd-compressed JSON when `path` ends in `.zst`
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        Ok(self.0.save(&path.to_string_lossy())?)
+    }
+
+    #[getter]
+    fn difficulty(&self) -> usize {
+        self.0.difficulty
+    }
+
+    /// Chain file format version; pass it to `Block.verify` and `merkle_root`
+    #[getter]
+    fn version(&self) -> u32 {
+        self.0.version
+    }
+
+    /// Chain id signatures are bound to, `None` for a version 1 chain
+    #[getter]
+    fn chain_id(&self) -> Option<String> {
+        self.0.signature_domain().chain_id().map(str::to_string)
+    }
+
+    #[getter]
+    fn blocks(&self) -> Vec<PyBlock> {
+        self.0.blocks.iter().cloned().map(PyBlock).collect()
+    }
+
+    #[getter]
+    fn last_hash(&self) -> String {
+        self.0.last_hash()
+    }
+
+    /// Mine, sign and append a block holding `ops`.
+    ///
+    /// `progress`, if given, is called with `(nonce, candidate_hash, hashes_per_sec)`
+    /// about twice a second while mining.
+    #[pyo3(signature = (ops, signer, progress = None))]
+    fn append(
+        &mut self,
+        py: Python<'_>,
+        ops: Vec<PyOp>,
+        signer: &PyKeyPair,
+        progress: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<PyBlock> {
+        let ops = ops.into_iter().map(|op| op.0).collect();
+        let chain = &mut self.0;
+        mine(py, progress, |report| chain.append_signed(ops, &signer.0, report).cloned())?
+            .map(PyBlock)
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Mine a block that sets `key` to `value`
+    #[pyo3(signature = (key, value, signer, progress = None))]
+    fn put(
+        &mut self,
+        py: Python<'_>,
+        key: String,
+        value: String,
+        signer: &PyKeyPair,
+        progress: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<PyBlock> {
+        self.append(py, vec![PyOp::put(key, value)], signer, progress)
+    }
+
+    /// Mine a block that removes `key`
+    #[pyo3(signature = (key, signer, progress = None))]
+    fn delete(
+        &mut self,
+        py: Python<'_>,
+        key: String,
+        signer: &PyKeyPair,
+        progress: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<PyBlock> {
+        self.append(py, vec![PyOp::delete(key)], signer, progress)
+    }
+
+    /// Mine a block, signed by `current`, that hands signing over to `next`
+    #[pyo3(signature = (current, next, progress = None))]
+    fn rotate_key(
+        &mut self,
+        py: Python<'_>,
+        current: &PyKeyPair,
+        next: &PyKeyPair,
+        progress: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<PyBlock> {
+        let chain = &mut self.0;
+        let next = next.0.verifying_key();
+        mine(py, progress, |report| chain.rotate_key(&current.0, &next, report).cloned())?
+            .map(PyBlock)
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Hex public key that must sign the next block, or `None` if the chain was never rotated
+    #[getter]
+    fn required_signer(&self) -> Option<&str> {
+        self.0.required_signer()
+    }
+
+    /// Current key/value state after replaying every block
+    fn materialize(&self) -> HashMap<String, String> {
+        self.0.materialize()
+    }
+
+    /// Verify every block; raises `ValueError` naming the first problem
+    fn verify(&self) -> PyResult<()> {
+        self.0.verify_all().map_err(PyValueError::new_err)
+    }
+
+    /// Like `verify`, but returns `False` instead of raising
+    fn is_valid(&self) -> bool {
+        self.0.verify_all().is_ok()
+    }
+
+    /// The chain as JSON, in the same format as chain files
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.blocks.len()
+    }
+
+    fn __getitem__(&self, index: isize) -> PyResult<PyBlock> {
+        let len = self.0.blocks.len() as isize;
+        let i = if index < 0 { index + len } else { index };
+        if !(0..len).contains(&i) {
+            return Err(pyo3::exceptions::PyIndexError::new_err("block index out of range"));
+        }
+        Ok(PyBlock(self.0.blocks[i as usize].clone()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Chain(difficulty={}, blocks={})", self.0.difficulty, self.0.blocks.len())
+    }
+}
+
+/// Run a mining call, releasing the GIL unless a Python progress callback needs it.
+///
+/// An exception raised by the callback stops further reports and is re-raised once
+/// mining finishes.
+fn mine<T: Send>(
+    py: Python<'_>,
+    progress: Option<Bound<'_, PyAny>>,
+    f: impl for<'a> FnOnce(Option<Progress<'a>>) -> T + Send,
+) -> PyResult<T> {
+    let Some(callback) = progress else {
+        return Ok(py.allow_threads(|| f(None)));
+    };
+    let error = RefCell::new(None);
+    let report = |nonce: u64, hash: &str, hps: f64| {
+        if error.borrow().is_none()
+            && let Err(e) = callback.call1((nonce, hash, hps))
+        {
+            *error.borrow_mut() = Some(e);
+        }
+    };
+    let result = f(Some(&report));
+    match error.into_inner() {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// Merkle root over `ops`, as computed for `Block.merkle_root` on a chain of format `version`
+#[pyfunction]
+#[pyo3(signature = (ops, version = CHAIN_FORMAT_VERSION))]
+fn merkle_root(ops: Vec<PyOp>, version: u32) -> String {
+    let ops: Vec<Op> = ops.into_iter().map(|op| op.0).collect();
+    chain_core::merkle_root(&ops, HashScheme::for_version(version))
+}
+
+#[pymodule]
+#[pyo3(name = "chain_core")]
+fn chain_core_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOp>()?;
+    m.add_class::<PyKeyPair>()?;
+    m.add_class::<PyBlock>()?;
+    m.add_class::<PyChain>()?;
+    m.add_function(wrap_pyfunction!(merkle_root, m)?)?;
+    m.add("GENESIS_KEY", chain_core::GENESIS_KEY)?;
+    m.add("CHAIN_FORMAT_VERSION", CHAIN_FORMAT_VERSION)?;
+    Ok(())
+}
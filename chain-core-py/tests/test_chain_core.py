@@ -0,0 +1,84 @@
+import json
+import os
+import tempfile
+import unittest
+
+import chain_core
+from chain_core import Chain, KeyPair, Op
+
+
+class ChainTest(unittest.TestCase):
+    def setUp(self):
+        self.signer = KeyPair()
+
+    def test_mine_and_materialize(self):
+        chain = Chain(difficulty=1)
+        chain.put("a", "1", self.signer)
+        block = chain.append([Op.delete("a"), Op.put("b", "2")], self.signer)
+
+        self.assertEqual(len(chain), 3)
+        self.assertEqual(block.index, 2)
+        self.assertTrue(block.hash.startswith("0"))
+        self.assertEqual(block.signer_pubkey, self.signer.public_hex)
+        self.assertEqual(block.merkle_root, chain_core.merkle_root(block.ops))
+        self.assertEqual(chain.version, chain_core.CHAIN_FORMAT_VERSION)
+        self.assertNotEqual(block.merkle_root, chain_core.merkle_root(block.ops, version=2))
+        self.assertEqual(chain.materialize(), {"b": "2"})
+        chain.verify()
+
+    def test_tampering_is_detected(self):
+        chain = Chain(difficulty=1)
+        chain.put("a", "1", self.signer)
+        data = json.loads(chain.to_json())
+        data["blocks"][1]["nonce"] += 1
+
+        with tempfile.TemporaryDirectory() as tmp:
+            path = os.path.join(tmp, "chain.json")
+            with open(path, "w") as f:
+                json.dump(data, f)
+            tampered = Chain.load(path)
+
+        self.assertFalse(tampered.is_valid())
+        with self.assertRaisesRegex(ValueError, "hash mismatch"):
+            tampered.verify()
+
+    def test_save_load_and_keys(self):
+        chain = Chain(difficulty=1)
+        with tempfile.TemporaryDirectory() as tmp:
+            signer = KeyPair.generate_file(os.path.join(tmp, "key.json"))
+            self.assertEqual(KeyPair.load(os.path.join(tmp, "key.json")).public_hex, signer.public_hex)
+
+            chain.put("k", "v", signer)
+            for name in ("chain.json", "chain.json.zst"):
+                chain.save(os.path.join(tmp, name))
+                loaded = Chain.load(os.path.join(tmp, name))
+                self.assertEqual(loaded[-1].hash, chain[-1].hash)
+                self.assertEqual(loaded.materialize(), {"k": "v"})
+
+    def test_key_rotation(self):
+        chain = Chain(difficulty=1)
+        successor = KeyPair()
+        block = chain.rotate_key(self.signer, successor)
+
+        self.assertEqual(block.ops, [Op.rotate(successor.public_hex)])
+        self.assertEqual(block.ops[0].kind, "rotate")
+        self.assertEqual(chain.required_signer, successor.public_hex)
+        with self.assertRaisesRegex(ValueError, "rotated-in key"):
+            chain.put("k", "v", self.signer)
+        chain.put("k", "v", successor)
+        chain.verify()
+
+    def test_progress_callback(self):
+        reports = []
+        block = Chain(difficulty=2).put("k", "v", self.signer, progress=lambda *r: reports.append(r))
+        self.assertEqual(reports[-1][:2], (block.nonce, block.hash))
+
+        def fail(*_):
+            raise RuntimeError("stop")
+
+        with self.assertRaisesRegex(RuntimeError, "stop"):
+            Chain(difficulty=1).put("k", "v", self.signer, progress=fail)
+
+
+if __name__ == "__main__":
+    unittest.main()
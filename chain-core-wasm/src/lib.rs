@@ -0,0 +1,153 @@
+//! Client-side verification of chain_kv chains, compiled to WebAssembly.
+//!
+//! A web page can check an uploaded chain file, or a Merkle proof that an op is in a
+//! block, without sending anything to a server. Build with
+//! `wasm-pack build --target web` and see `www/index.html` for a verifier page.
+
+use chain_core::{Chain, Clock, HashScheme, Op, ProofStep};
+use wasm_bindgen::prelude::*;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Result of verifying a chain file
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainReport {
+    /// Every block links to its predecessor, meets the difficulty and carries a valid signature
+    pub valid: bool,
+    /// Number of blocks, including genesis
+    pub blocks: usize,
+    pub difficulty: usize,
+    pub last_hash: String,
+    /// The first problem found, when `valid` is false
+    pub error: Option<String>,
+}
+
+/// Verify a chain file (the JSON written by `save`).
+///
+/// Throws if the bytes are not a chain file; a chain that parses but fails
+/// verification is reported through `ChainReport.valid` and `error`.
+#[wasm_bindgen]
+pub fn verify_chain(bytes: &[u8]) -> Result<ChainReport, JsError> {
+    check_chain(bytes).map_err(|e| JsError::new(&e))
+}
+
+/// Check that `op` (JSON, e.g. `{"Put":{"key":"k","value":"v"}}`) is committed to by the
+/// Merkle `root` through `proof` (JSON array of `{"sibling": hex, "left": bool}`), on a
+/// chain of format `version` (the chain file's `version`; hashing changed in version 3)
+#[wasm_bindgen]
+pub fn verify_proof(op: &str, proof: &str, root: &str, version: u32) -> Result<bool, JsError> {
+    check_proof(op, proof, root, version).map_err(|e| JsError::new(&e))
+}
+
+/// Merkle proof (JSON) for op `op_index` of block `block_index` in a chain file
+#[wasm_bindgen]
+pub fn merkle_proof(bytes: &[u8], block_index: usize, op_index: usize) -> Result<String, JsError> {
+    build_proof(bytes, block_index, op_index).map_err(|e| JsError::new(&e))
+}
+
+fn parse_chain(bytes: &[u8]) -> Result<Chain, String> {
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        return Err("zstd-compressed chain files are not supported here; save the chain as .json".into());
+    }
+    serde_json::from_slice(bytes).map_err(|e| format!("not a chain file: {e}"))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn date_now() -> f64;
+}
+
+/// The browser's clock; the system clock is out of reach in `wasm32-unknown-unknown`
+struct BrowserClock;
+
+impl Clock for BrowserClock {
+    #[cfg(target_arch = "wasm32")]
+    fn now(&self) -> i64 {
+        (date_now() / 1000.0) as i64
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now(&self) -> i64 {
+        chain_core::SystemClock.now()
+    }
+}
+
+fn check_chain(bytes: &[u8]) -> Result<ChainReport, String> {
+    let chain = parse_chain(bytes)?;
+    let error = chain.verify_all_at(&BrowserClock).err();
+    Ok(ChainReport {
+        valid: error.is_none(),
+        blocks: chain.blocks.len(),
+        difficulty: chain.difficulty,
+        last_hash: chain.last_hash(),
+        error,
+    })
+}
+
+fn check_proof(op: &str, proof: &str, root: &str, version: u32) -> Result<bool, String> {
+    let op: Op = serde_json::from_str(op).map_err(|e| format!("bad op: {e}"))?;
+    let proof: Vec<ProofStep> = serde_json::from_str(proof).map_err(|e| format!("bad proof: {e}"))?;
+    Ok(chain_core::verify_proof(&op, &proof, root, HashScheme::for_version(version)))
+}
+
+fn build_proof(bytes: &[u8], block_index: usize, op_index: usize) -> Result<String, String> {
+    let chain = parse_chain(bytes)?;
+    let block = chain.blocks.get(block_index).ok_or(format!("no block {block_index}"))?;
+    let proof = chain_core::merkle_proof(&block.ops, op_index, chain.hash_scheme())
+        .ok_or(format!("block {block_index} has no op {op_index}"))?;
+    serde_json::to_string(&proof).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_core::Block;
+    use ed25519_dalek::SigningKey;
+
+    fn chain_bytes() -> Vec<u8> {
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let mut chain = Chain::genesis_with_id("wasm-test", 1);
+        let ops = vec![
+            Op::put("a", "1"),
+            Op::put("b", "2"),
+            Op::del("c"),
+        ];
+        chain.append_signed(ops, &key, None).unwrap();
+        serde_json::to_vec(&chain).unwrap()
+    }
+
+    #[test]
+    fn verifies_chain_files() {
+        let bytes = chain_bytes();
+        let report = check_chain(&bytes).unwrap();
+        assert!(report.valid);
+        assert_eq!(report.blocks, 2);
+
+        let mut chain: Chain = serde_json::from_slice(&bytes).unwrap();
+        chain.blocks[1].nonce += 1;
+        let report = check_chain(&serde_json::to_vec(&chain).unwrap()).unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.error.as_deref(), Some("block 1: hash mismatch"));
+
+        assert!(check_chain(b"{}").is_err());
+        assert!(check_chain(&ZSTD_MAGIC).is_err());
+    }
+
+    #[test]
+    fn proofs_roundtrip_through_json() {
+        let bytes = chain_bytes();
+        let chain: Chain = serde_json::from_slice(&bytes).unwrap();
+        let block: &Block = &chain.blocks[1];
+
+        let proof = build_proof(&bytes, 1, 2).unwrap();
+        let op = serde_json::to_string(&block.ops[2]).unwrap();
+        assert_eq!(check_proof(&op, &proof, &block.merkle_root, chain.version), Ok(true));
+        assert_eq!(check_proof(&op, &proof, &block.merkle_root, 2), Ok(false));
+        assert_eq!(check_proof(r#"{"Del":{"key":"x"}}"#, &proof, &block.merkle_root, chain.version), Ok(false));
+        assert!(build_proof(&bytes, 1, 3).is_err());
+        assert!(check_proof("nope", &proof, &block.merkle_root, chain.version).is_err());
+    }
+}
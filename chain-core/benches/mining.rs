@@ -0,0 +1,45 @@
+//! Nonce search throughput: hashing each candidate header into a hex string and
+//! comparing its prefix (how `Block::find_nonce` used to work) against batches of raw
+//! digests from `CpuHasher`.
+//!
+//! `cargo bench --bench mining`, and `--features asm` for the assembly SHA-256.
+
+use chain_core::HashScheme;
+use chain_core::pow::{CpuHasher, HASH_BATCH, Hasher, meets_difficulty};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const NONCES: u64 = 16 * HASH_BATCH as u64;
+const ROOT: &str = "5f2b6c3a9d8e7f1021324354657687980a1b2c3d4e5f60718293a4b5c6d7e8f9";
+const PREV: &str = "000a7c1e2d3f405162738495a6b7c8d9e0f1021324354657687980a1b2c3d4e5";
+// never met, so every nonce is hashed and checked
+const DIFFICULTY: usize = 64;
+
+fn nonce_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nonce_search");
+    group.throughput(Throughput::Elements(NONCES));
+
+    group.bench_function("hex_strings", |b| {
+        let target = "0".repeat(DIFFICULTY);
+        b.iter(|| {
+            (0..NONCES).any(|nonce| HashScheme::Text.block_hash(7, 1_700_000_000, ROOT, PREV, black_box(nonce)).starts_with(&target))
+        })
+    });
+
+    group.bench_function("batched_digests", |b| {
+        let prefix = HashScheme::CURRENT.header_prefix(7, 1_700_000_000, ROOT, PREV);
+        let mut hasher = CpuHasher::new();
+        let mut digests = [[0u8; 32]; HASH_BATCH];
+        b.iter(|| {
+            (0..NONCES).step_by(HASH_BATCH).any(|start| {
+                hasher.hash_batch(&prefix, black_box(start), &mut digests);
+                digests.iter().any(|d| meets_difficulty(d, DIFFICULTY))
+            })
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, nonce_search);
+criterion_main!(benches);
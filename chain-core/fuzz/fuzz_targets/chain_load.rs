@@ -0,0 +1,14 @@
+//! Chain files come from disk and from replicas' `/blocks` responses: parsing,
+//! verifying and replaying whatever they hold must fail with an error, never panic.
+
+#![no_main]
+
+use chain_core::Chain;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(chain) = Chain::from_bytes(data) else { return };
+    let _ = chain.verify_all();
+    let _ = chain.materialize();
+    let _ = (chain.last_hash(), chain.next_index(), chain.op_count());
+});
@@ -0,0 +1,407 @@
+use crate::pow::{CpuHasher, HASH_BATCH, Hasher, meets_difficulty};
+use crate::scheme::field_digest;
+use crate::{Clock, HashScheme, NextBlock, Op, Signer, SystemClock, merkle_root};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Mining progress callback: `(nonce, last candidate hash, hashes per second)`.
+///
+/// Called about twice a second while searching and once more with the winning hash.
+pub type Progress<'a> = &'a dyn Fn(u64, &str, f64);
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Leads every signing message of a version 2 or 3 chain, see [`Block::signing_message`]
+pub const SIGNATURE_DOMAIN: &str = "chain_kv-block-v2";
+
+/// Leads every signing message from version 4 on, see [`SignatureDomain::Binary`]
+pub const BINARY_SIGNATURE_DOMAIN: &[u8] = b"chain_kv-block-v4";
+
+/// First chain format version whose blocks sign the raw block digest
+pub const BINARY_SIGNATURE_VERSION: u32 = 4;
+
+/// What a block signature is bound to besides the block hash, which a chain's format
+/// version fixes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureDomain {
+    /// Version 1: nothing; the bare hex hash is signed, and any chain sharing the genesis
+    /// block accepts the block
+    Bare,
+    /// Versions 2 and 3: the chain id and index, as the text
+    /// `chain_kv-block-v2:<chain id>:<index>:<hex hash>`
+    Text(String),
+    /// Version 4 on: the chain id and index, as the bytes of [`BINARY_SIGNATURE_DOMAIN`],
+    /// the id's length (`u32`, little-endian), the id, the index (`u64`, little-endian)
+    /// and the 32-byte block digest
+    Binary(String),
+}
+
+impl SignatureDomain {
+    /// The domain of a chain of format `version` with id `chain_id`
+    pub fn for_version(version: u32, chain_id: &str) -> Self {
+        match version {
+            0..=1 => SignatureDomain::Bare,
+            v if v < BINARY_SIGNATURE_VERSION => SignatureDomain::Text(chain_id.to_string()),
+            _ => SignatureDomain::Binary(chain_id.to_string()),
+        }
+    }
+
+    /// Chain id signatures are bound to, or `None` for a version 1 chain
+    pub fn chain_id(&self) -> Option<&str> {
+        match self {
+            SignatureDomain::Bare => None,
+            SignatureDomain::Text(id) | SignatureDomain::Binary(id) => Some(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    pub index: u64,
+    pub timestamp: i64,
+    pub ops: Vec<Op>,
+    pub prev_hash: String,
+    pub merkle_root: String,
+    pub nonce: u64,
+    pub hash: String,
+    pub signature: Option<String>,     // hex-encoded signature over `Block::signing_message`
+    pub signer_pubkey: Option<String>, // hex-encoded 32-byte pubkey
+}
+
+impl Block {
+    /// The fixed, unsigned first block every chain starts from
+    pub fn genesis() -> Self {
+        Self {
+            index: 0,
+            timestamp: 0,
+            ops: vec![Op::put(crate::GENESIS_KEY, "ok")],
+            prev_hash: "0".into(),
+            merkle_root: "GENESIS".into(),
+            nonce: 0,
+            hash: "GENESIS".into(),
+            signature: None,
+            signer_pubkey: None,
+        }
+    }
+
+    /// What the signature of block `index` covers: from version 2 on, the chain id and
+    /// index as well as the hash, so a signature is only valid for one block of one chain.
+    /// See [`SignatureDomain`] for the layout.
+    pub fn signing_message(domain: &SignatureDomain, index: u64, hash: &str) -> Vec<u8> {
+        match domain {
+            SignatureDomain::Bare => hash.as_bytes().to_vec(),
+            SignatureDomain::Text(id) => format!("{SIGNATURE_DOMAIN}:{id}:{index}:{hash}").into_bytes(),
+            SignatureDomain::Binary(id) => {
+                let mut message = Vec::with_capacity(BINARY_SIGNATURE_DOMAIN.len() + 4 + id.len() + 8 + 32);
+                message.extend_from_slice(BINARY_SIGNATURE_DOMAIN);
+                message.extend_from_slice(&(id.len() as u32).to_le_bytes());
+                message.extend_from_slice(id.as_bytes());
+                message.extend_from_slice(&index.to_le_bytes());
+                message.extend_from_slice(&field_digest(hash));
+                message
+            }
+        }
+    }
+
+    /// Hashes a nonce search takes on average at `difficulty`: each leading hex zero is a 1-in-16 chance
+    pub fn expected_hashes(difficulty: usize) -> f64 {
+        16f64.powi(difficulty as i32)
+    }
+
+    /// The difficulty whose average search at `hps` hashes per second comes closest to `secs`
+    /// seconds, by ratio (1s and 16s are equally far from 4s); never below 1
+    pub fn difficulty_for(hps: f64, secs: f64) -> usize {
+        ((hps * secs).log(16.0).round() as usize).max(1)
+    }
+
+    /// Hashes per second this machine manages, measured by searching nonces for `sample`
+    /// the way [`Block::find_nonce`] does
+    pub fn hash_rate(sample: Duration) -> f64 {
+        let prefix = HashScheme::CURRENT.header_prefix(1, 0, "0", "GENESIS");
+        let mut hasher = CpuHasher::new();
+        let mut digests = [[0u8; 32]; HASH_BATCH];
+        let start = Instant::now();
+        let mut nonce = 0u64;
+        while start.elapsed() < sample {
+            hasher.hash_batch(&prefix, nonce, &mut digests);
+            std::hint::black_box(&digests);
+            nonce += HASH_BATCH as u64;
+        }
+        nonce as f64 / start.elapsed().as_secs_f64()
+    }
+
+    /// Search for a nonce whose hash starts with `difficulty` hex zeros, for the header
+    /// whose [`HashScheme::header_prefix`] is `prefix`; returns `(nonce, hash)`
+    pub fn find_nonce(prefix: &[u8], difficulty: usize, progress: Option<Progress>) -> (u64, String) {
+        Self::find_nonce_with(&mut CpuHasher::new(), prefix, difficulty, progress)
+    }
+
+    /// [`Block::find_nonce`] hashing [`HASH_BATCH`] nonces at a time with `hasher`.
+    ///
+    /// Digests stay raw in a buffer reused for every batch; only the winning hash, and the
+    /// candidate passed to `progress`, are hex-encoded.
+    pub fn find_nonce_with(hasher: &mut dyn Hasher, prefix: &[u8], difficulty: usize, progress: Option<Progress>) -> (u64, String) {
+        let mut digests = [[0u8; 32]; HASH_BATCH];
+        let start = Instant::now();
+        let mut last_report = Instant::now();
+        let mut nonce = 0u64;
+        let hps = |hashed: f64| hashed / start.elapsed().as_secs_f64().max(1e-6);
+
+        loop {
+            hasher.hash_batch(prefix, nonce, &mut digests);
+            if let Some(i) = digests.iter().position(|d| meets_difficulty(d, difficulty)) {
+                let found = nonce.wrapping_add(i as u64);
+                let hash = hex::encode(digests[i]);
+                // final progress report
+                if let Some(cb) = progress {
+                    cb(found, &hash, hps(found as f64 + 1.0));
+                }
+                return (found, hash);
+            }
+            nonce = nonce.wrapping_add(HASH_BATCH as u64);
+
+            if let Some(cb) = progress
+                && last_report.elapsed() >= PROGRESS_INTERVAL
+            {
+                cb(nonce, &hex::encode(digests[HASH_BATCH - 1]), hps(nonce as f64));
+                last_report = Instant::now();
+            }
+        }
+    }
+
+    /// Mine the block `next` describes, holding `ops`, and sign it.
+    ///
+    /// Fails if `signer` cannot sign or returns a signature that does not verify.
+    pub fn mine(next: &NextBlock, ops: Vec<Op>, signer: &dyn Signer, progress: Option<Progress>) -> Result<Self, String> {
+        Self::mine_at(next, SystemClock.now(), ops, signer, progress)
+    }
+
+    /// [`Block::mine`] with the block stamped `timestamp` instead of the system time
+    pub fn mine_at(
+        next: &NextBlock,
+        timestamp: i64,
+        ops: Vec<Op>,
+        signer: &dyn Signer,
+        progress: Option<Progress>,
+    ) -> Result<Self, String> {
+        let root = merkle_root(&ops, next.scheme);
+        let prefix = next.scheme.header_prefix(next.index, timestamp, &root, &next.prev_hash);
+        let (nonce, _) = Self::find_nonce(&prefix, next.difficulty, progress);
+        Self::seal(next, timestamp, ops, nonce, signer)
+    }
+
+    /// Sign the block `next` describes, holding `ops`, with a nonce that was searched for
+    /// elsewhere, such as by [`Block::find_nonce`] or by several miners each given a range
+    /// of nonces.
+    ///
+    /// The signature covers [`Block::signing_message`] for `next.domain`. Proof-of-work is
+    /// not checked here; [`Block::verify`] does that.
+    pub fn seal(next: &NextBlock, timestamp: i64, ops: Vec<Op>, nonce: u64, signer: &dyn Signer) -> Result<Self, String> {
+        let pubkey = signer.verifying_key();
+        let merkle_root = merkle_root(&ops, next.scheme);
+        let hash = next.scheme.block_hash(next.index, timestamp, &merkle_root, &next.prev_hash, nonce);
+        let message = Self::signing_message(&next.domain, next.index, &hash);
+
+        let sig = signer.sign_message(&message).map_err(|e| format!("signing failed: {e}"))?;
+        pubkey
+            .verify(&message, &sig)
+            .map_err(|_| "signer returned a signature that does not verify")?;
+        let sig_hex = hex::encode(sig.to_bytes());
+        let pubkey_hex = hex::encode(pubkey.to_bytes());
+
+        Ok(Self {
+            index: next.index,
+            timestamp,
+            ops,
+            prev_hash: next.prev_hash.clone(),
+            merkle_root,
+            nonce,
+            hash,
+            signature: Some(sig_hex),
+            signer_pubkey: Some(pubkey_hex),
+        })
+    }
+
+    /// Check the link to `prev_hash`, the hash itself under `scheme`, proof-of-work and, when
+    /// present, the signature over [`Block::signing_message`] for `domain`
+    pub fn verify(&self, prev_hash: &str, difficulty: usize, domain: &SignatureDomain, scheme: HashScheme) -> Result<(), String> {
+        if self.prev_hash != prev_hash {
+            return Err("prev_hash mismatch".into());
+        }
+        let recomputed = scheme.block_hash(self.index, self.timestamp, &self.merkle_root, &self.prev_hash, self.nonce);
+        if recomputed != self.hash {
+            return Err("hash mismatch".into());
+        }
+        if self.hash.bytes().take_while(|&b| b == b'0').count() < difficulty {
+            return Err("insufficient PoW".into());
+        }
+        if let (Some(sig_hex), Some(pub_hex)) = (&self.signature, &self.signer_pubkey) {
+            verify_signature(pub_hex, sig_hex, &Self::signing_message(domain, self.index, &self.hash))?;
+        }
+        Ok(())
+    }
+
+    /// Check that the block's ops are the ones its Merkle root, and so its hash, commit to.
+    /// Separate from [`Block::verify`] because pruned blocks keep their header but not their ops.
+    pub fn verify_ops(&self, scheme: HashScheme) -> Result<(), String> {
+        if merkle_root(&self.ops, scheme) != self.merkle_root {
+            return Err("ops do not match the merkle root".into());
+        }
+        Ok(())
+    }
+}
+
+/// Check the hex Ed25519 signature `sig_hex` by the hex public key `pub_hex` over `message`
+pub(crate) fn verify_signature(pub_hex: &str, sig_hex: &str, message: &[u8]) -> Result<(), String> {
+    let sig_bytes = hex::decode(sig_hex).map_err(|_| "bad signature hex")?;
+    if sig_bytes.len() != 64 {
+        return Err("signature must be 64 bytes".into());
+    }
+    let sig = Signature::try_from(&sig_bytes[..]).map_err(|_| "bad signature bytes")?;
+    let pk_bytes = hex::decode(pub_hex).map_err(|_| "bad pubkey hex")?;
+    if pk_bytes.len() != 32 {
+        return Err("public key must be 32 bytes".into());
+    }
+    let mut pk_array = [0u8; 32];
+    pk_array.copy_from_slice(&pk_bytes);
+    let pk = VerifyingKey::from_bytes(&pk_array).map_err(|_| "bad pubkey bytes")?;
+    pk.verify(message, &sig).map_err(|_| "signature verify failed".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chain, keys};
+
+    const RAW: HashScheme = HashScheme::Raw;
+
+    fn id() -> SignatureDomain {
+        SignatureDomain::Binary("test".into())
+    }
+
+    fn mined() -> Block {
+        let ops = vec![Op::del("k")];
+        Block::mine(&Chain::test(1).next_block(), ops, &keys::test_key(), None).unwrap()
+    }
+
+    struct WrongKey;
+
+    impl Signer for WrongKey {
+        fn verifying_key(&self) -> VerifyingKey {
+            keys::test_key().verifying_key()
+        }
+
+        fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+            ed25519_dalek::SigningKey::from_bytes(&[9; 32]).sign_message(message)
+        }
+    }
+
+    #[test]
+    fn bad_signers_are_rejected() {
+        let err = Block::mine(&Chain::test(1).next_block(), vec![], &WrongKey, None).unwrap_err();
+        assert_eq!(err, "signer returned a signature that does not verify");
+    }
+
+    #[test]
+    fn mined_block_verifies() {
+        let blk = mined();
+        assert!(blk.hash.starts_with('0'));
+        assert_eq!(blk.verify("GENESIS", 1, &id(), RAW), Ok(()));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let mut blk = mined();
+        blk.nonce += 1;
+        assert_eq!(blk.verify("GENESIS", 1, &id(), RAW), Err("hash mismatch".into()));
+
+        let mut blk = mined();
+        blk.signature = Some("zz".into());
+        assert_eq!(blk.verify("GENESIS", 1, &id(), RAW), Err("bad signature hex".into()));
+
+        assert_eq!(mined().verify("other", 1, &id(), RAW), Err("prev_hash mismatch".into()));
+    }
+
+    #[test]
+    fn sealing_a_found_nonce() {
+        let next = Chain::test(1).next_block();
+        let blk = mined();
+        let sealed = Block::seal(&next, blk.timestamp, blk.ops.clone(), blk.nonce, &keys::test_key()).unwrap();
+        assert_eq!(sealed, blk);
+
+        let sealed = Block::seal(&next, blk.timestamp, blk.ops, blk.nonce + 1, &keys::test_key()).unwrap();
+        assert_ne!(sealed.hash, blk.hash);
+        assert_eq!(sealed.verify("GENESIS", 0, &id(), RAW), Ok(()));
+    }
+
+    #[test]
+    fn signatures_are_bound_to_chain_and_index() {
+        let blk = mined();
+        assert!(blk.verify("GENESIS", 1, &SignatureDomain::Binary("other-chain".into()), RAW).is_err());
+        assert_eq!(blk.verify("GENESIS", 1, &SignatureDomain::Bare, RAW), Err("signature verify failed".into()));
+        assert!(blk.verify("GENESIS", 1, &SignatureDomain::Text("test".into()), RAW).is_err());
+
+        // version 1 blocks sign the bare hash, versions 2 and 3 the text message
+        for domain in [SignatureDomain::Bare, SignatureDomain::Text("test".into())] {
+            let next = NextBlock { domain: domain.clone(), ..Chain::test(1).next_block() };
+            let legacy = Block::seal(&next, blk.timestamp, blk.ops.clone(), blk.nonce, &keys::test_key()).unwrap();
+            assert_eq!(legacy.hash, blk.hash);
+            assert_eq!(legacy.verify("GENESIS", 1, &domain, RAW), Ok(()));
+            assert!(legacy.verify("GENESIS", 1, &id(), RAW).is_err());
+        }
+    }
+
+    #[test]
+    fn signing_messages() {
+        let hash = "00ab".repeat(16);
+        assert_eq!(Block::signing_message(&SignatureDomain::Text("lab".into()), 7, "00ab"), b"chain_kv-block-v2:lab:7:00ab");
+        assert_eq!(Block::signing_message(&SignatureDomain::Bare, 7, "00ab"), b"00ab");
+
+        let message = Block::signing_message(&SignatureDomain::Binary("lab".into()), 7, &hash);
+        let expected = [&b"chain_kv-block-v4"[..], &3u32.to_le_bytes(), b"lab", &7u64.to_le_bytes(), &hex::decode(&hash).unwrap()].concat();
+        assert_eq!(message, expected);
+
+        assert_eq!(SignatureDomain::for_version(1, "lab"), SignatureDomain::Bare);
+        assert_eq!(SignatureDomain::for_version(3, "lab"), SignatureDomain::Text("lab".into()));
+        assert_eq!(SignatureDomain::for_version(crate::CHAIN_FORMAT_VERSION, "lab"), SignatureDomain::Binary("lab".into()));
+    }
+
+    #[test]
+    fn difficulty_estimates() {
+        assert_eq!(Block::expected_hashes(2), 256.0);
+        // 1000 H/s for 4.096s is exactly 16^3 hashes
+        assert_eq!(Block::difficulty_for(1000.0, 4.096), 3);
+        assert_eq!(Block::difficulty_for(1e6, 5.0), 6);
+        assert_eq!(Block::difficulty_for(0.0, 5.0), 1);
+        assert!(Block::hash_rate(Duration::from_millis(20)) > 0.0);
+    }
+
+    #[test]
+    fn search_runs_on_any_hasher() {
+        struct Counting(CpuHasher, usize);
+        impl Hasher for Counting {
+            fn hash_batch(&mut self, prefix: &[u8], start: u64, out: &mut [[u8; 32]]) {
+                self.1 += 1;
+                self.0.hash_batch(prefix, start, out);
+            }
+        }
+
+        let prefix = RAW.header_prefix(1, 0, "0", "GENESIS");
+        let mut hasher = Counting(CpuHasher::new(), 0);
+        let (nonce, hash) = Block::find_nonce_with(&mut hasher, &prefix, 3, None);
+        assert_eq!((nonce, hash.clone()), Block::find_nonce(&prefix, 3, None));
+        assert_eq!(hash, RAW.block_hash(1, 0, "0", "GENESIS", nonce));
+        assert!(hash.starts_with("000"));
+        assert_eq!(hasher.1, nonce as usize / HASH_BATCH + 1);
+    }
+
+    #[test]
+    fn progress_reports_winning_hash() {
+        let seen = std::cell::RefCell::new(None);
+        let report = |nonce: u64, hash: &str, _hps: f64| *seen.borrow_mut() = Some((nonce, hash.to_string()));
+        let prefix = RAW.header_prefix(1, 0, "0", "GENESIS");
+        let (nonce, hash) = Block::find_nonce(&prefix, 1, Some(&report));
+        assert_eq!(seen.into_inner(), Some((nonce, hash)));
+    }
+}
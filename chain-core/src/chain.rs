@@ -0,0 +1,817 @@
+use crate::diff::diff_states;
+use crate::pow::meets_difficulty;
+use crate::{Block, Clock, HashScheme, Op, Progress, SignatureDomain, Signer, Snapshot, StateDiff, SystemClock, keys, merkle_root};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Key written by the genesis block; it never appears in materialized state
+pub const GENESIS_KEY: &str = "__genesis__";
+
+/// Format of chains created now: version 4 signs the raw block digest
+/// ([`SignatureDomain::Binary`]), version 3 its hex text, version 2 also hashes the hex
+/// text of digests rather than the digests ([`HashScheme::Text`]) and version 1 (files
+/// without a `version`) also signs the bare block hash rather than
+/// [`Block::signing_message`] with the chain id
+pub const CHAIN_FORMAT_VERSION: u32 = 4;
+
+/// Bytes of randomness in the chain id [`Chain::genesis`] picks
+#[cfg(feature = "rand")]
+const CHAIN_ID_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chain {
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+    /// Bound into every block signature from version 2 on; see [`Chain::signature_domain`]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub chain_id: String,
+    pub blocks: Vec<Block>,
+    /// Leading hex zeros required of every block hash
+    pub difficulty: usize,
+    /// Ops collected since `begin_batch`, committed together as one block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    batch: Option<Vec<Op>>,
+    /// State left by blocks whose ops were dropped by [`Chain::prune`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot: Option<Snapshot>,
+    /// Limits on block timestamps, set by whoever verifies the chain. They are never
+    /// saved: a loaded chain gets the defaults, whatever its file says.
+    #[serde(skip)]
+    pub time_rules: TimeRules,
+}
+
+/// How far a block's timestamp may stray, checked by [`Chain::verify_all`] and
+/// [`Chain::append_verified`].
+///
+/// Chains built offline, or replayed long after with fixed timestamps, can loosen or
+/// drop either limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRules {
+    /// Seconds a block may be dated ahead of the verifier's clock; `None` for no limit
+    pub max_future_secs: Option<i64>,
+    /// Seconds a block may be dated before its predecessor; `None` for no limit
+    pub max_backward_secs: Option<i64>,
+}
+
+impl TimeRules {
+    /// No limits: any timestamp verifies
+    pub const OFF: TimeRules = TimeRules { max_future_secs: None, max_backward_secs: None };
+
+    /// Check `blk`, which follows `prev`, against a verifier whose clock reads `now`
+    pub fn check(&self, blk: &Block, prev: &Block, now: i64) -> Result<(), String> {
+        if let Some(max) = self.max_future_secs
+            && blk.timestamp > now.saturating_add(max)
+        {
+            return Err(format!("block {} is dated {}s in the future", blk.index, blk.timestamp.saturating_sub(now)));
+        }
+        if let Some(max) = self.max_backward_secs
+            && blk.timestamp < prev.timestamp.saturating_sub(max)
+        {
+            return Err(format!(
+                "block {} is dated {}s before block {}",
+                blk.index,
+                prev.timestamp.saturating_sub(blk.timestamp),
+                prev.index
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for TimeRules {
+    /// Ten minutes into the future, one minute behind the previous block
+    fn default() -> Self {
+        Self { max_future_secs: Some(10 * 60), max_backward_secs: Some(60) }
+    }
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+impl Chain {
+    /// A chain holding only the genesis block, with a random chain id. Every chain starts
+    /// from the same genesis block, so the id is what keeps its blocks off other chains.
+    #[cfg(feature = "rand")]
+    pub fn genesis(difficulty: usize) -> Self {
+        use rand_core::RngCore;
+        let mut id = [0u8; CHAIN_ID_BYTES];
+        rand_core::OsRng.fill_bytes(&mut id);
+        Self::genesis_with_id(hex::encode(id), difficulty)
+    }
+
+    /// A chain holding only the genesis block, whose blocks verify on no chain with
+    /// another id. Ids should be unique: two chains sharing one accept each other's blocks.
+    pub fn genesis_with_id(chain_id: impl Into<String>, difficulty: usize) -> Self {
+        Self {
+            version: CHAIN_FORMAT_VERSION,
+            chain_id: chain_id.into(),
+            blocks: vec![Block::genesis()],
+            difficulty,
+            batch: None,
+            snapshot: None,
+            time_rules: TimeRules::default(),
+        }
+    }
+
+    pub fn last_hash(&self) -> String {
+        self.blocks.last().map(|b| b.hash.clone()).unwrap_or_else(|| "0".into())
+    }
+
+    pub fn next_index(&self) -> u64 {
+        self.blocks.last().map(|b| b.index.saturating_add(1)).unwrap_or(0)
+    }
+
+    /// What block signatures are bound to, fixed by the version: nothing on a version 1
+    /// chain, the chain id and index after that
+    pub fn signature_domain(&self) -> SignatureDomain {
+        SignatureDomain::for_version(self.version, &self.chain_id)
+    }
+
+    /// How this chain's block and Merkle hashes are computed, fixed by its version
+    pub fn hash_scheme(&self) -> HashScheme {
+        HashScheme::for_version(self.version)
+    }
+
+    fn check_version(&self) -> Result<(), String> {
+        match self.version {
+            1..=CHAIN_FORMAT_VERSION => Ok(()),
+            v => Err(format!("unsupported chain format version {v}")),
+        }
+    }
+
+    /// Key that must sign the next block: the one bound by the latest `Op::Rotate`,
+    /// or `None` (any key) if the chain has never been rotated
+    pub fn required_signer(&self) -> Option<&str> {
+        self.signer_to(self.blocks.len())
+    }
+
+    /// Key required after the first `end` blocks; pruned blocks defer to the snapshot
+    pub(crate) fn signer_to(&self, end: usize) -> Option<&str> {
+        self.blocks[..end]
+            .iter()
+            .rev()
+            .find_map(rotated_to)
+            .or_else(|| self.snapshot.as_ref()?.required_signer.as_deref())
+    }
+
+    /// What mining the block after the current tip needs
+    pub fn next_block(&self) -> NextBlock {
+        NextBlock {
+            index: self.next_index(),
+            prev_hash: self.last_hash(),
+            difficulty: self.difficulty,
+            required_signer: self.required_signer().map(str::to_string),
+            domain: self.signature_domain(),
+            scheme: self.hash_scheme(),
+        }
+    }
+
+    /// Mine, sign and append a block holding `ops`
+    pub fn append_signed(&mut self, ops: Vec<Op>, signer: &dyn Signer, progress: Option<Progress>) -> Result<&Block, String> {
+        let blk = self.next_block().mine(ops, signer, progress)?;
+        self.blocks.push(blk);
+        Ok(self.blocks.last().unwrap())
+    }
+
+    /// Append a block mined elsewhere (e.g. fetched from a primary), verifying it links to our tip.
+    pub fn append_verified(&mut self, blk: Block) -> Result<(), String> {
+        if blk.index != self.next_index() {
+            return Err(format!("expected block {}, got {}", self.next_index(), blk.index));
+        }
+        self.check_version()?;
+        let prev = self.blocks.last().ok_or("empty chain")?;
+        blk.verify(&self.last_hash(), self.difficulty, &self.signature_domain(), self.hash_scheme())
+            .and_then(|_| blk.verify_ops(self.hash_scheme()))
+            .and_then(|_| check_signer(self.required_signer(), blk.signer_pubkey.as_deref(), &blk.ops))
+            .map_err(|e| format!("block {}: {e}", blk.index))?;
+        self.time_rules.check(&blk, prev, SystemClock.now())?;
+        self.blocks.push(blk);
+        Ok(())
+    }
+
+    /// Replay every op to produce the current key/value state, starting from the
+    /// snapshot if the chain was pruned
+    ///
+    /// With the `parallel` feature, chains holding at least `PARALLEL_MIN_OPS` ops are
+    /// replayed on a thread pool by `Chain::materialize_parallel`.
+    pub fn materialize(&self) -> HashMap<String, String> {
+        #[cfg(feature = "parallel")]
+        if self.op_count() >= crate::PARALLEL_MIN_OPS {
+            return self.materialize_parallel();
+        }
+        self.materialize_to(self.blocks.len())
+    }
+
+    /// State after the first `end` blocks
+    pub(crate) fn materialize_to(&self, end: usize) -> HashMap<String, String> {
+        let mut state: HashMap<String, String> = self.snapshot.iter().flat_map(|s| s.state.clone()).collect();
+        for b in &self.blocks[self.pruned_len().min(end)..end] {
+            for op in &b.ops {
+                apply(&mut state, op);
+            }
+        }
+        state
+    }
+
+    /// Ops held by the unpruned blocks
+    pub fn op_count(&self) -> usize {
+        self.blocks[self.pruned_len()..].iter().map(|b| b.ops.len()).sum()
+    }
+
+    /// Verify every block after genesis against its predecessor and its ops against its
+    /// Merkle root, and that blocks after a key rotation are signed by the rotated-in key.
+    /// Errors name the first block that fails.
+    ///
+    /// Pruned blocks are checked by header alone; the snapshot stands in for their ops, and
+    /// must be signed by the signer of the last of them.
+    /// Timestamps are held to [`Chain::time_rules`] against the system clock.
+    pub fn verify_all(&self) -> Result<(), String> {
+        self.verify_all_at(&SystemClock)
+    }
+
+    /// [`Chain::verify_all`] with "now" read from `clock`
+    pub fn verify_all_at(&self, clock: &dyn Clock) -> Result<(), String> {
+        if self.blocks.is_empty() {
+            return Err("empty chain".into());
+        }
+        self.check_version()?;
+        if let Some(height) = self.pruned_height()
+            && height >= self.next_index()
+        {
+            return Err(format!("snapshot at block {height} is beyond the tip"));
+        }
+        let pruned = self.pruned_len();
+        if let Some(snapshot) = &self.snapshot {
+            let last = self.blocks[..pruned].last().ok_or("snapshot precedes the genesis block")?;
+            snapshot.verify(&self.chain_id, last).map_err(|e| format!("snapshot at block {}: {e}", snapshot.height))?;
+        }
+        let now = clock.now();
+        let domain = self.signature_domain();
+        let mut required = match &self.snapshot {
+            Some(s) => s.required_signer.as_deref(),
+            None => rotated_to(&self.blocks[0]),
+        };
+        for i in 1..self.blocks.len() {
+            let prev = &self.blocks[i - 1];
+            let curr = &self.blocks[i];
+            let at_block = |e: String| format!("block {}: {e}", curr.index);
+            curr.verify(&prev.hash, self.difficulty, &domain, self.hash_scheme()).map_err(at_block)?;
+            self.time_rules.check(curr, prev, now)?;
+            if i < pruned {
+                continue;
+            }
+            curr.verify_ops(self.hash_scheme())
+                .and_then(|_| check_signer(required, curr.signer_pubkey.as_deref(), &curr.ops))
+                .map_err(at_block)?;
+            required = rotated_to(curr).or(required);
+        }
+        Ok(())
+    }
+
+    /// Mine a block, signed by `current`, that rotates the chain's key to `next`.
+    /// From then on only `next` can append blocks.
+    pub fn rotate_key(&mut self, current: &dyn Signer, next: &VerifyingKey, progress: Option<Progress>) -> Result<&Block, String> {
+        let op = Op::Rotate { new_pubkey: hex::encode(next.to_bytes()) };
+        self.append_signed(vec![op], current, progress)
+    }
+
+    // batching
+    pub fn batch_active(&self) -> bool {
+        self.batch.is_some()
+    }
+    /// Ops collected so far, if a batch is open
+    pub fn batch(&self) -> Option<&[Op]> {
+        self.batch.as_deref()
+    }
+    pub fn begin_batch(&mut self) -> Result<(), String> {
+        if self.batch.is_some() {
+            return Err("batch already active".into());
+        }
+        self.batch = Some(Vec::new());
+        Ok(())
+    }
+    pub fn add_put(&mut self, key: String, value: String) -> Result<(), String> {
+        self.add_op(Op::put(key, value))
+    }
+    pub fn add_del(&mut self, key: String) -> Result<(), String> {
+        self.add_op(Op::del(key))
+    }
+    /// Batch any op, such as one carrying [`OpMeta`](crate::OpMeta)
+    pub fn add_op(&mut self, op: Op) -> Result<(), String> {
+        self.batch.as_mut().ok_or("no active batch")?.push(op);
+        Ok(())
+    }
+    pub fn abort_batch(&mut self) {
+        self.batch = None;
+    }
+    /// Close the batch and hand over its ops, to mine them without holding the chain;
+    /// give them back with [`Chain::restore_batch`] if that fails
+    pub fn take_batch(&mut self) -> Result<Vec<Op>, String> {
+        self.batch.take().ok_or_else(|| "no active batch".into())
+    }
+    /// Reopen a batch taken with [`Chain::take_batch`], ahead of any ops batched since
+    pub fn restore_batch(&mut self, mut ops: Vec<Op>) {
+        ops.extend(self.batch.take().unwrap_or_default());
+        self.batch = Some(ops);
+    }
+    /// What committing the batch would change, and why the commit would be refused, without
+    /// mining. With `signer_pubkey` (hex), also check that key may sign the next block.
+    pub fn preview_batch(&self, signer_pubkey: Option<&str>) -> Result<BatchPreview, String> {
+        let ops = self.batch.as_ref().ok_or("no active batch")?;
+        let before = self.materialize();
+        let mut after = before.clone();
+        for op in ops {
+            apply(&mut after, op);
+        }
+        let required = signer_pubkey.and(self.required_signer());
+        Ok(BatchPreview {
+            ops: ops.len(),
+            changes: diff_states(before, after),
+            violations: check_signer(required, signer_pubkey, ops).err().into_iter().collect(),
+        })
+    }
+    /// Mine the batched ops as one block; the batch stays open if signing fails
+    pub fn commit_batch(&mut self, signer: &dyn Signer, progress: Option<Progress>) -> Result<&Block, String> {
+        let ops = self.batch.clone().ok_or("no active batch")?;
+        self.append_signed(ops, signer, progress)?;
+        self.batch = None;
+        Ok(self.blocks.last().unwrap())
+    }
+}
+
+/// Outcome of committing the open batch, worked out by [`Chain::preview_batch`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BatchPreview {
+    /// Ops in the batch
+    pub ops: usize,
+    /// How the materialized state would change
+    pub changes: StateDiff,
+    /// Why mining the batch would be refused; empty if it would be accepted
+    pub violations: Vec<String>,
+}
+
+/// The tip a block is mined on, copied out of a [`Chain`] so the proof-of-work can run
+/// while others read (or extend) the chain.
+///
+/// Append the result with [`Chain::append_verified`] once `chain.next_block()` still
+/// equals this; if it does not, the tip moved and the block must be mined again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NextBlock {
+    pub index: u64,
+    pub prev_hash: String,
+    pub difficulty: usize,
+    /// Key bound by the latest rotation, which must sign the block
+    pub required_signer: Option<String>,
+    /// What the signature is bound to besides the hash, from the chain's version and id
+    pub domain: SignatureDomain,
+    /// How the block is hashed, from the chain's version
+    pub scheme: HashScheme,
+}
+
+impl NextBlock {
+    /// Mine and sign a block holding `ops`
+    pub fn mine(&self, ops: Vec<Op>, signer: &dyn Signer, progress: Option<Progress>) -> Result<Block, String> {
+        self.mine_with_clock(&SystemClock, ops, signer, progress)
+    }
+
+    /// [`NextBlock::mine`] with the block stamped by `clock`
+    pub fn mine_with_clock(
+        &self,
+        clock: &dyn Clock,
+        ops: Vec<Op>,
+        signer: &dyn Signer,
+        progress: Option<Progress>,
+    ) -> Result<Block, String> {
+        check_signer(self.required_signer.as_deref(), Some(&keys::public_hex(signer)), &ops)?;
+        Block::mine_at(self, clock.now(), ops, signer, progress)
+    }
+
+    /// Sign the block holding `ops`, stamped `timestamp`, with a nonce found outside this
+    /// process. Fails without asking `signer` if the nonce misses the difficulty target.
+    pub fn seal(&self, timestamp: i64, ops: Vec<Op>, nonce: u64, signer: &dyn Signer) -> Result<Block, String> {
+        check_signer(self.required_signer.as_deref(), Some(&keys::public_hex(signer)), &ops)?;
+        let root = merkle_root(&ops, self.scheme);
+        let digest = self.scheme.block_digest(self.index, timestamp, &root, &self.prev_hash, nonce);
+        if !meets_difficulty(&digest, self.difficulty) {
+            return Err("insufficient PoW".into());
+        }
+        Block::seal(self, timestamp, ops, nonce, signer)
+    }
+}
+
+/// Apply one op to materialized state
+pub(crate) fn apply(state: &mut HashMap<String, String>, op: &Op) {
+    match op {
+        Op::Put { key, value, .. } => {
+            if key != GENESIS_KEY {
+                state.insert(key.clone(), value.clone());
+            }
+        }
+        Op::Del { key, .. } => {
+            state.remove(key);
+        }
+        Op::Rotate { .. } => {}
+    }
+}
+
+/// Key bound by the last `Op::Rotate` in `blk`, if any
+fn rotated_to(blk: &Block) -> Option<&str> {
+    blk.ops.iter().rev().find_map(|op| match op {
+        Op::Rotate { new_pubkey } => Some(new_pubkey.as_str()),
+        _ => None,
+    })
+}
+
+/// Check a block's signer against the key required by earlier rotations, and that any
+/// key it rotates to is a valid Ed25519 public key
+fn check_signer(required: Option<&str>, signer_pubkey: Option<&str>, ops: &[Op]) -> Result<(), String> {
+    if let Some(required) = required
+        && signer_pubkey != Some(required)
+    {
+        return Err(format!("block must be signed by the rotated-in key {required}"));
+    }
+    for op in ops {
+        if let Op::Rotate { new_pubkey } = op {
+            let bytes: [u8; 32] = hex::decode(new_pubkey)
+                .ok()
+                .and_then(|b| b.try_into().ok())
+                .ok_or("bad rotation pubkey")?;
+            VerifyingKey::from_bytes(&bytes).map_err(|_| "bad rotation pubkey")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+impl Chain {
+    /// A chain holding only the genesis block, with the chain id `test`, so tests run
+    /// without the `rand` feature and chains they build accept each other's blocks
+    pub(crate) fn test(difficulty: usize) -> Self {
+        Self::genesis_with_id("test", difficulty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockClock, keys};
+
+    #[test]
+    fn append_and_materialize() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.append_signed(vec![Op::put("a", "1")], &kp, None).unwrap();
+        chain.append_signed(vec![Op::del("a"), Op::put("b", "2")], &kp, None).unwrap();
+
+        assert_eq!(chain.verify_all(), Ok(()));
+        let state = chain.materialize();
+        assert_eq!(state.len(), 1);
+        assert_eq!(state["b"], "2");
+    }
+
+    #[test]
+    fn tampered_ops_fail_verification() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.append_signed(vec![Op::put("a", "1")], &kp, None).unwrap();
+        chain.append_signed(vec![Op::put("b", "2")], &kp, None).unwrap();
+
+        // the header, and so the hash and signature, are untouched
+        let mut tampered = chain.clone();
+        tampered.blocks[2].ops[0] = Op::put("b", "20");
+        assert_eq!(tampered.verify_all(), Err("block 2: ops do not match the merkle root".into()));
+
+        let blk = tampered.blocks.pop().unwrap();
+        assert_eq!(tampered.append_verified(blk).unwrap_err(), "block 2: ops do not match the merkle root");
+    }
+
+    #[test]
+    fn batch_commits_one_block() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        assert!(chain.add_put("a".into(), "1".into()).is_err());
+
+        chain.begin_batch().unwrap();
+        assert!(chain.begin_batch().is_err());
+        chain.add_put("a".into(), "1".into()).unwrap();
+        chain.add_del("b".into()).unwrap();
+        assert_eq!(chain.batch(), Some(&[Op::put("a", "1"), Op::del("b")][..]));
+        let blk = chain.commit_batch(&kp, None).unwrap();
+
+        assert_eq!(blk.ops.len(), 2);
+        assert_eq!(chain.blocks.len(), 2);
+        assert!(!chain.batch_active());
+        assert_eq!(chain.batch(), None);
+    }
+
+    #[test]
+    fn mining_outside_the_chain() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.begin_batch().unwrap();
+        chain.add_put("a".into(), "1".into()).unwrap();
+        let ops = chain.take_batch().unwrap();
+        assert!(!chain.batch_active());
+
+        let next = chain.next_block();
+        let blk = next.mine(ops.clone(), &kp, None).unwrap();
+        // Another block lands first: the mined one no longer fits the tip
+        chain.append_signed(vec![Op::del("b")], &kp, None).unwrap();
+        assert_ne!(chain.next_block(), next);
+        assert!(chain.append_verified(blk).is_err());
+
+        chain.begin_batch().unwrap();
+        chain.add_del("c".into()).unwrap();
+        chain.restore_batch(ops);
+        assert_eq!(chain.take_batch().unwrap().len(), 2);
+
+        let blk = chain.next_block().mine(vec![], &kp, None).unwrap();
+        chain.append_verified(blk).unwrap();
+        assert_eq!(chain.verify_all(), Ok(()));
+    }
+
+    #[test]
+    fn mining_with_a_clock() {
+        let kp = keys::test_key();
+        let clock = MockClock::new(1_700_000_000);
+        let mut chain = Chain::test(1);
+        for _ in 0..2 {
+            let blk = chain.next_block().mine_with_clock(&clock, vec![], &kp, None).unwrap();
+            chain.append_verified(blk).unwrap();
+            clock.advance(60);
+        }
+        let stamps: Vec<i64> = chain.blocks[1..].iter().map(|b| b.timestamp).collect();
+        assert_eq!(stamps, [1_700_000_000, 1_700_000_060]);
+        assert_eq!(chain.verify_all(), Ok(()));
+    }
+
+    #[test]
+    fn timestamps_must_be_plausible() {
+        let kp = keys::test_key();
+        let clock = MockClock::new(1_700_000_000);
+        let mut chain = Chain::test(1);
+        let blk = chain.next_block().mine_with_clock(&clock, vec![], &kp, None).unwrap();
+        chain.append_verified(blk).unwrap();
+
+        // dated year 3000
+        let mut forged = chain.clone();
+        let next = forged.next_block();
+        let blk = next.mine_with_clock(&MockClock::new(32_503_680_000), vec![Op::del("x")], &kp, None).unwrap();
+        assert!(forged.append_verified(blk.clone()).unwrap_err().contains("in the future"));
+        forged.blocks.push(blk);
+        assert!(forged.verify_all().unwrap_err().contains("block 2 is dated"));
+
+        // an hour before its predecessor
+        clock.advance(-3600);
+        let blk = chain.next_block().mine_with_clock(&clock, vec![], &kp, None).unwrap();
+        let err = chain.clone().append_verified(blk.clone()).unwrap_err();
+        assert_eq!(err, "block 2 is dated 3600s before block 1");
+
+        // both limits can be relaxed, and a verifier may bring its own clock
+        chain.time_rules.max_backward_secs = Some(2 * 3600);
+        chain.append_verified(blk).unwrap();
+        forged.time_rules = TimeRules::OFF;
+        assert_eq!(forged.verify_all(), Ok(()));
+        forged.time_rules = TimeRules::default();
+        assert_eq!(forged.verify_all_at(&MockClock::new(32_503_680_000)), Ok(()));
+    }
+
+    #[test]
+    fn time_rules_are_not_read_from_the_file() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.time_rules = TimeRules::OFF;
+        // dated year 3000, which only passes with the checks off
+        let blk = chain.next_block().mine_with_clock(&MockClock::new(32_503_680_000), vec![], &kp, None).unwrap();
+        chain.append_verified(blk).unwrap();
+        let mut json: serde_json::Value = serde_json::to_value(&chain).unwrap();
+        assert!(json.get("time_rules").is_none());
+
+        // a file that tries to switch the checks off
+        json["time_rules"] = serde_json::json!({ "max_future_secs": null, "max_backward_secs": null });
+        let loaded: Chain = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.time_rules, TimeRules::default());
+        assert!(loaded.verify_all().unwrap_err().contains("block 1 is dated"));
+    }
+
+    #[test]
+    fn hostile_chain_files_are_errors() {
+        // values a loaded chain file may hold; none of them may panic
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.append_signed(vec![Op::put("k", "v")], &kp, None).unwrap();
+        chain.difficulty = usize::MAX;
+        assert_eq!(chain.verify_all(), Err("block 1: insufficient PoW".into()));
+
+        let (mut early, mut late) = (chain.blocks[1].clone(), chain.blocks[1].clone());
+        early.timestamp = i64::MIN;
+        late.timestamp = i64::MAX;
+        assert!(TimeRules::default().check(&late, &early, 0).is_err());
+        assert!(TimeRules::default().check(&early, &late, i64::MAX).is_err());
+
+        chain.blocks[1].index = u64::MAX;
+        assert_eq!(chain.next_index(), u64::MAX);
+        chain.difficulty = 1;
+        assert_eq!(chain.verify_all(), Err(format!("block {}: hash mismatch", u64::MAX)));
+    }
+
+    #[test]
+    fn sealing_a_nonce_found_elsewhere() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(2);
+        let next = chain.next_block();
+        let ops = vec![Op::put("a", "1")];
+        let root = merkle_root(&ops, next.scheme);
+        let prefix = next.scheme.header_prefix(next.index, 1_700_000_000, &root, &next.prev_hash);
+        let (nonce, hash) = Block::find_nonce(&prefix, 2, None);
+
+        let miss = (0..).find(|&n| !next.scheme.block_hash(next.index, 1_700_000_000, &root, &next.prev_hash, n).starts_with("00")).unwrap();
+        assert_eq!(next.seal(1_700_000_000, ops.clone(), miss, &Offline), Err("insufficient PoW".into()));
+
+        let blk = next.seal(1_700_000_000, ops, nonce, &kp).unwrap();
+        assert_eq!(blk.hash, hash);
+        chain.append_verified(blk).unwrap();
+        assert_eq!(chain.materialize()["a"], "1");
+    }
+
+    struct Offline;
+
+    impl Signer for Offline {
+        fn verifying_key(&self) -> ed25519_dalek::VerifyingKey {
+            keys::test_key().verifying_key()
+        }
+
+        fn sign_message(&self, _: &[u8]) -> Result<ed25519_dalek::Signature, String> {
+            Err("signer offline".into())
+        }
+    }
+
+    #[test]
+    fn failed_signing_keeps_the_batch() {
+        let mut chain = Chain::test(1);
+        chain.begin_batch().unwrap();
+        chain.add_put("a".into(), "1".into()).unwrap();
+
+        assert_eq!(chain.commit_batch(&Offline, None).unwrap_err(), "signing failed: signer offline");
+        assert!(chain.batch_active());
+        assert_eq!(chain.blocks.len(), 1);
+
+        chain.commit_batch(&keys::test_key(), None).unwrap();
+        assert!(!chain.batch_active());
+    }
+
+    #[test]
+    fn preview_shows_changes_without_mining() {
+        let old = keys::test_key();
+        let new = ed25519_dalek::SigningKey::from_bytes(&[9; 32]);
+        let mut chain = Chain::test(1);
+        assert_eq!(chain.preview_batch(None).unwrap_err(), "no active batch");
+        chain.append_signed(vec![Op::put("a", "1")], &old, None).unwrap();
+        chain.rotate_key(&old, &new.verifying_key(), None).unwrap();
+
+        chain.begin_batch().unwrap();
+        chain.add_put("a".into(), "2".into()).unwrap();
+        chain.add_put("b".into(), "3".into()).unwrap();
+        chain.add_del("b".into()).unwrap();
+        let preview = chain.preview_batch(Some(&keys::public_hex(&new))).unwrap();
+        assert_eq!(preview.ops, 3);
+        assert_eq!(preview.changes.changed["a"], ("1".into(), "2".into()));
+        assert!(preview.changes.added.is_empty() && preview.changes.removed.is_empty());
+        assert!(preview.violations.is_empty());
+        assert_eq!(chain.blocks.len(), 3);
+        assert_eq!(chain.materialize()["a"], "1");
+
+        let refused = chain.preview_batch(Some(&keys::public_hex(&old))).unwrap();
+        assert!(refused.violations[0].contains("rotated-in key"));
+        assert!(chain.batch_active());
+    }
+
+    #[test]
+    fn append_verified_rejects_gaps_and_forgeries() {
+        let kp = keys::test_key();
+        let mut primary = Chain::test(1);
+        primary.append_signed(vec![Op::del("x")], &kp, None).unwrap();
+        primary.append_signed(vec![Op::del("y")], &kp, None).unwrap();
+
+        let mut replica = Chain::test(1);
+        assert!(replica.append_verified(primary.blocks[2].clone()).is_err());
+
+        let mut forged = primary.blocks[1].clone();
+        forged.ops.clear();
+        forged.merkle_root = "0".into();
+        assert!(replica.append_verified(forged).is_err());
+
+        replica.append_verified(primary.blocks[1].clone()).unwrap();
+        replica.append_verified(primary.blocks[2].clone()).unwrap();
+        assert_eq!(replica.last_hash(), primary.last_hash());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn new_chains_get_their_own_id() {
+        let (a, b) = (Chain::genesis(1), Chain::genesis(1));
+        assert_eq!(a.chain_id.len(), 2 * CHAIN_ID_BYTES);
+        assert_ne!(a.chain_id, b.chain_id);
+        assert_eq!(a.signature_domain(), SignatureDomain::Binary(a.chain_id.clone()));
+    }
+
+    #[test]
+    fn blocks_do_not_replay_across_chains() {
+        let kp = keys::test_key();
+        let mut lab1 = Chain::genesis_with_id("lab1", 1);
+        let mut lab2 = Chain::genesis_with_id("lab2", 1);
+        let blk = lab1.append_signed(vec![Op::put("grade", "A")], &kp, None).unwrap().clone();
+
+        // same genesis, same hash, but the signature names lab1
+        assert_eq!(lab2.append_verified(blk.clone()).unwrap_err(), "block 1: signature verify failed");
+        lab2.blocks.push(blk);
+        assert!(lab2.verify_all().is_err());
+    }
+
+    #[test]
+    fn version_1_chains_still_verify() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.append_signed(vec![Op::put("a", "1")], &kp, None).unwrap();
+
+        // a file written before chain ids: no version, blocks sign the bare hash
+        let mut json = serde_json::to_value(&chain).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("version");
+        fields.remove("chain_id");
+        let mut old: Chain = serde_json::from_value(json).unwrap();
+        assert_eq!((old.version, old.signature_domain(), old.hash_scheme()), (1, SignatureDomain::Bare, HashScheme::Text));
+        assert!(old.verify_all().is_err());
+
+        old.blocks.truncate(1);
+        old.append_signed(vec![Op::put("a", "1")], &kp, None).unwrap();
+        old.append_signed(vec![Op::put("b", "2")], &kp, None).unwrap();
+        assert_eq!(old.verify_all(), Ok(()));
+
+        old.version = CHAIN_FORMAT_VERSION + 1;
+        assert_eq!(old.verify_all(), Err("unsupported chain format version 5".into()));
+    }
+
+    #[test]
+    fn version_2_chains_hash_hex_text() {
+        let kp = keys::test_key();
+        let mut old = Chain::test(1);
+        old.version = 2;
+        old.append_signed(vec![Op::put("a", "1")], &kp, None).unwrap();
+        old.append_signed(vec![], &kp, None).unwrap();
+        assert_eq!(old.verify_all(), Ok(()));
+
+        let blk = &old.blocks[1];
+        assert_eq!(blk.merkle_root, merkle_root(&blk.ops, HashScheme::Text));
+        assert_eq!(blk.hash, HashScheme::Text.block_hash(1, blk.timestamp, &blk.merkle_root, "GENESIS", blk.nonce));
+        assert_eq!(old.blocks[2].merkle_root, "0");
+
+        // the same blocks do not verify as a version 3 chain
+        old.version = 3;
+        assert_eq!(old.verify_all(), Err("block 1: hash mismatch".into()));
+    }
+
+    #[test]
+    fn version_4_blocks_reject_text_signatures() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        assert_eq!(chain.version, 4);
+
+        // sealed over the version 2 and 3 text message instead of the raw digest
+        let text = NextBlock { domain: SignatureDomain::Text(chain.chain_id.clone()), ..chain.next_block() };
+        let blk = Block::mine(&text, vec![Op::put("a", "1")], &kp, None).unwrap();
+        assert_eq!(chain.append_verified(blk.clone()).unwrap_err(), "block 1: signature verify failed");
+        chain.blocks.push(blk);
+        assert_eq!(chain.verify_all(), Err("block 1: signature verify failed".into()));
+    }
+
+    #[test]
+    fn rotation_hands_over_signing() {
+        let old = keys::test_key();
+        let new = ed25519_dalek::SigningKey::from_bytes(&[9; 32]);
+        let mut chain = Chain::test(1);
+        chain.append_signed(vec![Op::del("x")], &new, None).unwrap();
+        assert_eq!(chain.required_signer(), None);
+
+        chain.rotate_key(&old, &new.verifying_key(), None).unwrap();
+        let new_hex = keys::public_hex(&new);
+        assert_eq!(chain.required_signer(), Some(new_hex.as_str()));
+        assert!(chain.append_signed(vec![Op::del("x")], &old, None).is_err());
+        chain.append_signed(vec![Op::del("x")], &new, None).unwrap();
+        assert_eq!(chain.verify_all(), Ok(()));
+        assert!(chain.materialize().is_empty());
+
+        // a block the old key signed after the handover does not verify
+        let mut forged = chain.clone();
+        let next = NextBlock { required_signer: None, ..forged.next_block() };
+        let blk = Block::mine(&next, vec![], &old, None).unwrap();
+        forged.blocks.push(blk.clone());
+        assert!(forged.verify_all().unwrap_err().contains("rotated-in key"));
+        assert!(chain.append_verified(blk).is_err());
+
+        let bad = vec![Op::Rotate { new_pubkey: "zz".into() }];
+        assert_eq!(chain.append_signed(bad, &new, None).unwrap_err(), "bad rotation pubkey");
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Source of block timestamps, in Unix seconds.
+///
+/// [`Block::mine`](crate::Block::mine) reads the [`SystemClock`]; pass a clock to
+/// [`Block::mine_at`](crate::Block::mine_at) or [`NextBlock::mine_with_clock`](crate::NextBlock::mine_with_clock)
+/// to pin timestamps in tests, or on targets with no system clock.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> i64;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+}
+
+/// A clock that only moves when told to; clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Arc<AtomicI64>);
+
+impl MockClock {
+    pub fn new(now: i64) -> Self {
+        Self(Arc::new(AtomicI64::new(now)))
+    }
+
+    pub fn set(&self, now: i64) {
+        self.0.store(now, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.0.fetch_add(secs, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
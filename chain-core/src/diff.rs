@@ -0,0 +1,113 @@
+use crate::{Block, Chain};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Where two chains part ways and how their states differ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainDiff<'a> {
+    /// Index of the last block both chains hold; `None` if even their first blocks differ
+    pub fork_point: Option<u64>,
+    /// Blocks after the fork point on the left chain
+    pub left: &'a [Block],
+    /// Blocks after the fork point on the right chain
+    pub right: &'a [Block],
+    pub state: StateDiff,
+}
+
+impl ChainDiff<'_> {
+    /// Same blocks, hence the same state
+    pub fn is_identical(&self) -> bool {
+        self.left.is_empty() && self.right.is_empty()
+    }
+}
+
+/// Differences between two materialized states, keyed and sorted by key
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StateDiff {
+    /// Keys only the right state holds, with their values
+    pub added: BTreeMap<String, String>,
+    /// Keys only the left state holds, with their values
+    pub removed: BTreeMap<String, String>,
+    /// Keys both hold with different values: `(left, right)`
+    pub changed: BTreeMap<String, (String, String)>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Chain {
+    /// Compare with `other`: the last block both share, the blocks each holds beyond it,
+    /// and what turning this chain's state into `other`'s would add, remove and change
+    pub fn diff<'a>(&'a self, other: &'a Chain) -> ChainDiff<'a> {
+        let shared = self.blocks.iter().zip(&other.blocks).take_while(|(a, b)| a.hash == b.hash).count();
+        ChainDiff {
+            fork_point: shared.checked_sub(1).map(|i| self.blocks[i].index),
+            left: &self.blocks[shared..],
+            right: &other.blocks[shared..],
+            state: diff_states(self.materialize(), other.materialize()),
+        }
+    }
+}
+
+pub(crate) fn diff_states(mut left: HashMap<String, String>, right: HashMap<String, String>) -> StateDiff {
+    let mut diff = StateDiff::default();
+    for (key, value) in right {
+        match left.remove(&key) {
+            None => {
+                diff.added.insert(key, value);
+            }
+            Some(old) if old != value => {
+                diff.changed.insert(key, (old, value));
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = left.into_iter().collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Op, keys};
+
+    fn put(key: &str, value: &str) -> Vec<Op> {
+        vec![Op::put(key, value)]
+    }
+
+    #[test]
+    fn diff_finds_fork_and_state_changes() {
+        let kp = keys::test_key();
+        let mut base = Chain::test(1);
+        base.append_signed(put("shared", "1"), &kp, None).unwrap();
+        base.append_signed(put("gone", "x"), &kp, None).unwrap();
+
+        let mut left = base.clone();
+        left.append_signed(put("shared", "2"), &kp, None).unwrap();
+        let mut right = base.clone();
+        right.append_signed(vec![Op::del("gone"), Op::put("new", "n")], &kp, None).unwrap();
+        right.append_signed(put("shared", "3"), &kp, None).unwrap();
+
+        let diff = left.diff(&right);
+        assert_eq!(diff.fork_point, Some(2));
+        assert_eq!(diff.left.len(), 1);
+        assert_eq!(diff.right.iter().map(|b| b.index).collect::<Vec<_>>(), [3, 4]);
+        assert_eq!(diff.state.added, BTreeMap::from([("new".into(), "n".into())]));
+        assert_eq!(diff.state.removed, BTreeMap::from([("gone".into(), "x".into())]));
+        assert_eq!(diff.state.changed, BTreeMap::from([("shared".into(), ("2".into(), "3".into()))]));
+
+        // a chain that only extends the other forks at the shorter tip
+        let diff = base.diff(&left);
+        assert_eq!((diff.fork_point, diff.left.len(), diff.right.len()), (Some(2), 0, 1));
+
+        let same = left.diff(&left);
+        assert!(same.is_identical() && same.state.is_empty());
+
+        let mut alien = Chain::test(1);
+        alien.blocks[0].hash = "OTHER".into();
+        assert_eq!(left.diff(&alien).fork_point, None);
+    }
+}
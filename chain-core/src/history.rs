@@ -0,0 +1,208 @@
+use crate::{Block, Chain, GENESIS_KEY, Op};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// How often one key changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyChurn {
+    pub key: String,
+    /// Puts and deletes of this key
+    pub changes: usize,
+    /// Timestamp of the block holding the latest change
+    pub last_changed: i64,
+}
+
+/// Rate of change of the keys under a prefix, from block timestamps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Churn {
+    /// Puts and deletes of keys under the prefix
+    pub changes: usize,
+    /// Calendar days (UTC) from the first change to the last, inclusive; 0 if nothing changed
+    pub days: u64,
+    /// Every changed key, most-modified first (ties by key)
+    pub keys: Vec<KeyChurn>,
+}
+
+impl Churn {
+    /// Average changes per calendar day over `days`
+    pub fn per_day(&self) -> f64 {
+        self.changes as f64 / self.days.max(1) as f64
+    }
+
+    /// Average changes per week, counting a partial week as a whole one
+    pub fn per_week(&self) -> f64 {
+        self.changes as f64 / self.days.div_ceil(7).max(1) as f64
+    }
+}
+
+/// Blocks one key signed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SignerStats {
+    /// Hex public key the blocks carry
+    pub pubkey: String,
+    pub blocks: usize,
+    /// Index of the first block this key signed
+    pub first_block: u64,
+    /// Index of the latest block this key signed
+    pub last_block: u64,
+    /// Ops in those blocks; pruned blocks count none
+    pub ops: usize,
+}
+
+/// One put or delete of a key, and the block holding it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChange<'a> {
+    pub block: &'a Block,
+    pub op: &'a Op,
+}
+
+impl Chain {
+    /// Every put and delete of `key`, oldest first; pruned blocks have none left to show
+    pub fn history<'a>(&'a self, key: &'a str) -> impl Iterator<Item = KeyChange<'a>> + 'a {
+        self.blocks.iter().flat_map(move |block| {
+            block.ops.iter().filter_map(move |op| match op {
+                Op::Put { key: k, .. } | Op::Del { key: k, .. } if k == key => Some(KeyChange { block, op }),
+                _ => None,
+            })
+        })
+    }
+
+    /// Every key that signed a block, in order of its first block
+    pub fn signers(&self) -> Vec<SignerStats> {
+        let mut signers: Vec<SignerStats> = Vec::new();
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for b in &self.blocks {
+            let Some(pubkey) = b.signer_pubkey.as_deref() else { continue };
+            let i = *seen.entry(pubkey).or_insert_with(|| {
+                signers.push(SignerStats {
+                    pubkey: pubkey.to_string(),
+                    blocks: 0,
+                    first_block: b.index,
+                    last_block: b.index,
+                    ops: 0,
+                });
+                signers.len() - 1
+            });
+            let stats = &mut signers[i];
+            stats.blocks += 1;
+            stats.last_block = b.index;
+            stats.ops += b.ops.len();
+        }
+        signers
+    }
+
+    /// Blocks signed by `pubkey` (hex), oldest first
+    pub fn blocks_signed_by<'a>(&'a self, pubkey: &'a str) -> impl Iterator<Item = &'a Block> + 'a {
+        self.blocks.iter().filter(move |b| b.signer_pubkey.as_deref() == Some(pubkey))
+    }
+
+    /// Count the puts and deletes of keys starting with `prefix` (`""` for every key);
+    /// pruned blocks have no ops left to count
+    pub fn churn(&self, prefix: &str) -> Churn {
+        let mut per_key: HashMap<&str, KeyChurn> = HashMap::new();
+        let (mut first_day, mut last_day) = (i64::MAX, i64::MIN);
+        for b in &self.blocks {
+            for op in &b.ops {
+                let key = match op {
+                    Op::Put { key, .. } | Op::Del { key, .. } => key,
+                    Op::Rotate { .. } => continue,
+                };
+                if key == GENESIS_KEY || !key.starts_with(prefix) {
+                    continue;
+                }
+                let entry = per_key.entry(key).or_insert_with(|| KeyChurn {
+                    key: key.clone(),
+                    changes: 0,
+                    last_changed: b.timestamp,
+                });
+                entry.changes += 1;
+                entry.last_changed = entry.last_changed.max(b.timestamp);
+                let day = b.timestamp.div_euclid(DAY_SECS);
+                first_day = first_day.min(day);
+                last_day = last_day.max(day);
+            }
+        }
+
+        let mut keys: Vec<KeyChurn> = per_key.into_values().collect();
+        keys.sort_by(|a, b| b.changes.cmp(&a.changes).then_with(|| a.key.cmp(&b.key)));
+        Churn {
+            changes: keys.iter().map(|k| k.changes).sum(),
+            days: if keys.is_empty() { 0 } else { (last_day - first_day + 1) as u64 },
+            keys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OpMeta, keys};
+
+    #[test]
+    fn churn_counts_changes_under_prefix() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        let put = |key: &str| Op::put(key, "v");
+        chain.append_signed(vec![put("user:a"), put("user:b"), put("cfg")], &kp, None).unwrap();
+        chain.append_signed(vec![put("user:a")], &kp, None).unwrap();
+        chain.append_signed(vec![Op::del("user:a")], &kp, None).unwrap();
+        // spread the blocks over ten days
+        for (blk, day) in chain.blocks[1..].iter_mut().zip([0, 4, 9]) {
+            blk.timestamp = 1_700_000_000 + day * DAY_SECS;
+        }
+
+        let churn = chain.churn("user:");
+        assert_eq!(churn.changes, 4);
+        assert_eq!(churn.days, 10);
+        assert_eq!(churn.per_day(), 0.4);
+        assert_eq!(churn.per_week(), 2.0);
+        assert_eq!(churn.keys[0], KeyChurn { key: "user:a".into(), changes: 3, last_changed: chain.blocks[3].timestamp });
+        assert_eq!(churn.keys[1].key, "user:b");
+
+        assert_eq!(chain.churn("").changes, 5);
+        let none = chain.churn("nope");
+        assert_eq!((none.changes, none.days, none.per_day()), (0, 0, 0.0));
+    }
+
+    #[test]
+    fn history_lists_changes_with_metadata() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        let why = OpMeta { author: Some("ana".into()), reason: Some("typo".into()), ticket: Some("LAB-7".into()) };
+        chain.append_signed(vec![Op::put("k", "1"), Op::put("other", "x")], &kp, None).unwrap();
+        chain.append_signed(vec![Op::put("k", "2").with_meta(why.clone())], &kp, None).unwrap();
+        chain.append_signed(vec![Op::del("k")], &kp, None).unwrap();
+        assert_eq!(chain.verify_all(), Ok(()));
+
+        let changes: Vec<KeyChange> = chain.history("k").collect();
+        assert_eq!(changes.iter().map(|c| c.block.index).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(changes[1].op.meta(), Some(&why));
+        assert_eq!(changes[2].op.meta(), None);
+        assert_eq!(chain.history("missing").count(), 0);
+    }
+
+    #[test]
+    fn signers_are_attributed_their_blocks() {
+        let alice = keys::test_key();
+        let bob = ed25519_dalek::SigningKey::from_bytes(&[9; 32]);
+        let mut chain = Chain::test(1);
+        let put = |key: &str| Op::put(key, "v");
+        chain.append_signed(vec![put("a"), put("b")], &alice, None).unwrap();
+        chain.append_signed(vec![put("c")], &bob, None).unwrap();
+        chain.append_signed(vec![put("d"), put("e"), put("f")], &alice, None).unwrap();
+
+        let (alice_hex, bob_hex) = (keys::public_hex(&alice), keys::public_hex(&bob));
+        assert_eq!(
+            chain.signers(),
+            [
+                SignerStats { pubkey: alice_hex.clone(), blocks: 2, first_block: 1, last_block: 3, ops: 5 },
+                SignerStats { pubkey: bob_hex.clone(), blocks: 1, first_block: 2, last_block: 2, ops: 1 },
+            ]
+        );
+        let indexes: Vec<u64> = chain.blocks_signed_by(&alice_hex).map(|b| b.index).collect();
+        assert_eq!(indexes, [1, 3]);
+        assert_eq!(chain.blocks_signed_by("nobody").count(), 0);
+    }
+}
@@ -0,0 +1,120 @@
+//! Ed25519 signing keys and the JSON key file format used by the REPLs.
+
+use crate::Signer;
+#[cfg(any(feature = "fs", feature = "rand", test))]
+use ed25519_dalek::SigningKey;
+#[cfg(feature = "fs")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fs")]
+use std::{fs, io};
+
+#[cfg(feature = "fs")]
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    keypair_hex: String, // 32-byte signing key seed, hex
+    public_hex: String,  // convenience copy
+}
+
+/// A fresh random signing key
+#[cfg(feature = "rand")]
+pub fn generate() -> SigningKey {
+    SigningKey::generate(&mut rand_core::OsRng)
+}
+
+/// Generate a key and write it to `path` as a key file
+#[cfg(all(feature = "fs", feature = "rand"))]
+pub fn keygen_to_file(path: &str) -> io::Result<()> {
+    save_key_to_file(path, &generate())
+}
+
+/// Write `key` to `path` as a key file
+#[cfg(feature = "fs")]
+pub fn save_key_to_file(path: &str, key: &SigningKey) -> io::Result<()> {
+    let keypair_hex = hex::encode(key.to_bytes());
+    let public_hex = hex::encode(key.verifying_key().to_bytes());
+    let data = KeyFile { keypair_hex, public_hex };
+    let json = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+/// Read a signing key written by `keygen_to_file`
+#[cfg(feature = "fs")]
+pub fn load_key_from_file(path: &str) -> io::Result<SigningKey> {
+    let s = fs::read_to_string(path)?;
+    let kf: KeyFile = serde_json::from_str(&s)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("key parse error: {e}")))?;
+    let bytes = hex::decode(kf.keypair_hex)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad keypair hex"))?;
+    if bytes.len() != 32 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "expected 32-byte signing key"));
+    }
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&bytes);
+    Ok(SigningKey::from_bytes(&arr))
+}
+
+/// Hex-encoded public key, as stored in `Block::signer_pubkey`
+pub fn public_hex(signer: &dyn Signer) -> String {
+    hex::encode(signer.verifying_key().to_bytes())
+}
+
+/// A new 12-word BIP39 recovery phrase and the signing key derived from it
+#[cfg(feature = "mnemonic")]
+pub fn generate_mnemonic() -> (String, SigningKey) {
+    use rand_core::RngCore;
+
+    let mut entropy = [0u8; 16];
+    rand_core::OsRng.fill_bytes(&mut entropy);
+    let phrase = bip39::Mnemonic::from_entropy(&entropy)
+        .expect("16 bytes is a valid BIP39 entropy length")
+        .to_string();
+    let key = key_from_mnemonic(&phrase).expect("freshly generated phrase is valid");
+    (phrase, key)
+}
+
+/// Restore the signing key for a BIP39 recovery phrase.
+///
+/// The key is the SLIP-0010 Ed25519 master key of the phrase's BIP39 seed (empty
+/// passphrase), so the same words give the same key on every machine.
+#[cfg(feature = "mnemonic")]
+pub fn key_from_mnemonic(phrase: &str) -> Result<SigningKey, String> {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha512;
+
+    let mnemonic = bip39::Mnemonic::parse_normalized(phrase.trim()).map_err(|e| format!("invalid mnemonic: {e}"))?;
+    let mut mac = Hmac::<Sha512>::new_from_slice(b"ed25519 seed").expect("HMAC accepts any key length");
+    mac.update(&mnemonic.to_seed(""));
+    let master = mac.finalize().into_bytes();
+    let mut secret = [0u8; 32];
+    secret.copy_from_slice(&master[..32]);
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+/// Fixed key for tests, available without the `rand` feature
+#[cfg(test)]
+pub(crate) fn test_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+#[cfg(all(test, feature = "mnemonic"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnemonic_restores_the_same_key() {
+        let (phrase, key) = generate_mnemonic();
+        assert_eq!(phrase.split_whitespace().count(), 12);
+        let restored = key_from_mnemonic(&format!("  {phrase}\n")).unwrap();
+        assert_eq!(restored.to_bytes(), key.to_bytes());
+    }
+
+    #[test]
+    fn mnemonic_derivation_is_fixed() {
+        // BIP39 all-zero entropy vector; pins the derivation so keys stay recoverable
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let key = key_from_mnemonic(phrase).unwrap();
+        assert_eq!(public_hex(&key), "e96b1c6b8769fdb0b34fbecfdf85c33b053cecad9517e1ab88cba614335775c1");
+        assert!(key_from_mnemonic("abandon abandon abandon").is_err());
+        assert!(key_from_mnemonic(&phrase.replace("about", "abandon")).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Core of the chain_kv blockchain: a signed, proof-of-work chain of key/value operations.
+//!
+//! The `rust-01`, `rust-02` and `rust-03` binaries are thin front-ends (demo, REPL,
+//! REPL + HTTP) over this crate. Embedding the chain elsewhere only needs these types:
+//!
+//! - [`Op`] — a `Put` or `Del` on the key/value state, or a `Rotate` of the signing key;
+//!   puts and deletes may carry [`OpMeta`] (author, reason, ticket)
+//! - [`merkle_root`], [`merkle_proof`], [`verify_proof`] — commitment to a block's ops
+//!   and proofs that an op is included in it
+//! - [`HashScheme`] — whether hashes cover raw digests or, on chains before version 3,
+//!   their hex text; [`Chain::hash_scheme`] gives a chain's
+//! - [`Block`] — mining, signing and verification of a single block; [`SignatureDomain`]
+//!   is what its signature binds besides the hash
+//! - [`Chain`] — appending, batching, state materialization and whole-chain checks
+//! - [`NextBlock`] — mining the next block without holding the chain
+//! - [`pow`] — batched nonce hashing behind the [`pow::Hasher`] trait, for other hashing hardware
+//! - [`Clock`] — where block timestamps come from; [`MockClock`] pins them in tests
+//! - [`TimeRules`] — how far ahead of the clock, or behind its predecessor, a block may be dated
+//! - [`Churn`] — how often keys under a prefix change, from block timestamps
+//! - [`SignerStats`] — which keys signed how many blocks and ops
+//! - [`ChainDiff`] — where two chains fork and how their states differ
+//! - [`Snapshot`] — the state a pruned chain keeps in place of its oldest ops
+//! - [`Signer`] — anything that can sign blocks, such as an in-process `SigningKey`
+//! - [`keys`] — Ed25519 key files used to sign blocks
+//!
+//! Verification needs no OS services: with `default-features = false` (dropping the
+//! `fs` and `rand` features) the crate builds for `wasm32-unknown-unknown`.
+//!
+//! ```
+//! use chain_core::{Chain, Op, keys};
+//!
+//! let signer = keys::generate();
+//! let mut chain = Chain::genesis(1);
+//! chain.append_signed(vec![Op::put("user", "alice")], &signer, None).unwrap();
+//!
+//! assert!(chain.verify_all().is_ok());
+//! assert_eq!(chain.materialize()["user"], "alice");
+//! ```
+
+mod block;
+mod chain;
+mod clock;
+mod diff;
+mod history;
+pub mod keys;
+mod merkle;
+mod op;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "fs")]
+mod persist;
+pub mod pow;
+mod prune;
+mod scheme;
+mod signer;
+
+pub use block::{BINARY_SIGNATURE_DOMAIN, BINARY_SIGNATURE_VERSION, Block, Progress, SIGNATURE_DOMAIN, SignatureDomain};
+pub use chain::{BatchPreview, CHAIN_FORMAT_VERSION, Chain, GENESIS_KEY, NextBlock, TimeRules};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use diff::{ChainDiff, StateDiff};
+pub use history::{Churn, KeyChange, KeyChurn, SignerStats};
+pub use merkle::{ProofStep, merkle_proof, merkle_root, verify_proof};
+pub use op::{Op, OpMeta};
+#[cfg(feature = "parallel")]
+pub use parallel::PARALLEL_MIN_OPS;
+pub use prune::{SNAPSHOT_DOMAIN, Snapshot};
+pub use scheme::{HashScheme, RAW_HASH_VERSION};
+pub use signer::Signer;
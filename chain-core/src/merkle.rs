@@ -0,0 +1,202 @@
+use crate::scheme::parse_digest;
+use crate::{HashScheme, Op};
+#[cfg(test)]
+use crate::OpMeta;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Merkle root over a block's ops under `scheme`, as lowercase hex.
+///
+/// Leaves are `SHA256("PUT" || key || value)` or `SHA256("DEL" || key)`, followed by
+/// `"META"` and the length-prefixed metadata fields when the op carries [`OpMeta`]; an odd
+/// node is paired with itself. Parents hash their children as [`HashScheme`] says: their
+/// hex text, or their raw digests. An empty op list has the root `"0"` under
+/// [`HashScheme::Text`] and 32 zero bytes under [`HashScheme::Raw`].
+///
+/// [`OpMeta`]: crate::OpMeta
+pub fn merkle_root(ops: &[Op], scheme: HashScheme) -> String {
+    if ops.is_empty() {
+        return match scheme {
+            HashScheme::Text => "0".into(),
+            HashScheme::Raw => hex::encode([0u8; 32]),
+        };
+    }
+    let mut hashes: Vec<[u8; 32]> = ops.iter().map(leaf_hash).collect();
+    while hashes.len() > 1 {
+        hashes = parents(&hashes, scheme);
+    }
+    hex::encode(hashes[0])
+}
+
+/// One level of a Merkle inclusion proof: the sibling hash and which side it sits on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub sibling: String,
+    /// `true` when the sibling is the left input to the parent hash
+    pub left: bool,
+}
+
+/// Proof that `ops[index]` is committed to by `merkle_root(ops, scheme)`; `None` if out of range
+pub fn merkle_proof(ops: &[Op], index: usize, scheme: HashScheme) -> Option<Vec<ProofStep>> {
+    if index >= ops.len() {
+        return None;
+    }
+    let mut hashes: Vec<[u8; 32]> = ops.iter().map(leaf_hash).collect();
+    let mut index = index;
+    let mut proof = Vec::new();
+    while hashes.len() > 1 {
+        let sibling = index ^ 1;
+        proof.push(ProofStep {
+            sibling: hex::encode(hashes.get(sibling).unwrap_or(&hashes[index])), // odd node pairs with itself
+            left: sibling < index,
+        });
+        hashes = parents(&hashes, scheme);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Check that `op` is included under `root` via `proof`, hashed under `scheme`
+pub fn verify_proof(op: &Op, proof: &[ProofStep], root: &str, scheme: HashScheme) -> bool {
+    let mut hash = leaf_hash(op);
+    for step in proof {
+        let Some(sibling) = parse_digest(&step.sibling) else {
+            return false;
+        };
+        hash = if step.left { node_hash(&sibling, &hash, scheme) } else { node_hash(&hash, &sibling, scheme) };
+    }
+    hex::encode(hash) == root
+}
+
+/// The level above `hashes`
+fn parents(hashes: &[[u8; 32]], scheme: HashScheme) -> Vec<[u8; 32]> {
+    hashes.chunks(2).map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0]), scheme)).collect()
+}
+
+fn leaf_hash(op: &Op) -> [u8; 32] {
+    let mut h = Sha256::new();
+    match op {
+        Op::Put { key, value, .. } => {
+            h.update(b"PUT");
+            h.update(key.as_bytes());
+            h.update(value.as_bytes());
+        }
+        Op::Del { key, .. } => {
+            h.update(b"DEL");
+            h.update(key.as_bytes());
+        }
+        Op::Rotate { new_pubkey } => {
+            h.update(b"ROT");
+            h.update(new_pubkey.as_bytes());
+        }
+    }
+    // ops without metadata hash as they always have
+    if let Some(meta) = op.meta() {
+        h.update(b"META");
+        for field in [&meta.author, &meta.reason, &meta.ticket] {
+            match field {
+                Some(s) => {
+                    h.update([1]);
+                    h.update((s.len() as u64).to_le_bytes());
+                    h.update(s.as_bytes());
+                }
+                None => h.update([0]),
+            }
+        }
+    }
+    h.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32], scheme: HashScheme) -> [u8; 32] {
+    match scheme {
+        HashScheme::Text => {
+            let mut text = [0u8; 128];
+            // both halves are exactly 64 bytes, which cannot fail
+            hex::encode_to_slice(left, &mut text[..64]).expect("64 hex digits");
+            hex::encode_to_slice(right, &mut text[64..]).expect("64 hex digits");
+            Sha256::digest(text).into()
+        }
+        HashScheme::Raw => Sha256::new().chain_update(left).chain_update(right).finalize().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMES: [HashScheme; 2] = [HashScheme::Text, HashScheme::Raw];
+
+    fn put(k: &str) -> Op {
+        Op::put(k, "v")
+    }
+
+    fn root(ops: &[Op]) -> String {
+        merkle_root(ops, HashScheme::CURRENT)
+    }
+
+    #[test]
+    fn empty_root() {
+        assert_eq!(merkle_root(&[], HashScheme::Text), "0");
+        assert_eq!(merkle_root(&[], HashScheme::Raw), "0".repeat(64));
+    }
+
+    #[test]
+    fn text_scheme_hashes_hex_children() {
+        let leaves: Vec<String> = ["a", "b"].iter().map(|k| hex::encode(leaf_hash(&put(k)))).collect();
+        let text = hex::encode(Sha256::digest(format!("{}{}", leaves[0], leaves[1])));
+        assert_eq!(merkle_root(&[put("a"), put("b")], HashScheme::Text), text);
+
+        let raw: Vec<u8> = leaves.iter().flat_map(|l| hex::decode(l).unwrap()).collect();
+        assert_eq!(merkle_root(&[put("a"), put("b")], HashScheme::Raw), hex::encode(Sha256::digest(raw)));
+        // a single leaf is its own root either way
+        assert_eq!(merkle_root(&[put("a")], HashScheme::Text), merkle_root(&[put("a")], HashScheme::Raw));
+    }
+
+    #[test]
+    fn odd_leaf_is_duplicated() {
+        for scheme in SCHEMES {
+            let three = merkle_root(&[put("a"), put("b"), put("c")], scheme);
+            let four = merkle_root(&[put("a"), put("b"), put("c"), put("c")], scheme);
+            assert_eq!(three, four);
+        }
+    }
+
+    #[test]
+    fn proofs_verify_for_every_leaf() {
+        for scheme in SCHEMES {
+            for n in 1..=7 {
+                let ops: Vec<Op> = (0..n).map(|i| put(&i.to_string())).collect();
+                let root = merkle_root(&ops, scheme);
+                for (i, op) in ops.iter().enumerate() {
+                    let proof = merkle_proof(&ops, i, scheme).unwrap();
+                    assert!(verify_proof(op, &proof, &root, scheme), "leaf {i} of {n}");
+                    assert!(!verify_proof(&put("x"), &proof, &root, scheme));
+                }
+                assert_eq!(merkle_proof(&ops, n, scheme), None);
+            }
+        }
+        // a proof only verifies under the scheme that built it
+        let ops = [put("a"), put("b"), put("c")];
+        let proof = merkle_proof(&ops, 1, HashScheme::Raw).unwrap();
+        assert!(!verify_proof(&ops[1], &proof, &merkle_root(&ops, HashScheme::Raw), HashScheme::Text));
+    }
+
+    #[test]
+    fn metadata_is_committed() {
+        let meta = |reason: &str| OpMeta { author: Some("ana".into()), reason: Some(reason.into()), ticket: None };
+        let plain = root(&[put("a")]);
+        let tagged = root(&[put("a").with_meta(meta("typo"))]);
+        assert_ne!(plain, tagged);
+        assert_ne!(tagged, root(&[put("a").with_meta(meta("other"))]));
+        // moving text between fields changes the leaf too
+        let shifted = OpMeta { author: None, reason: Some("ana".into()), ticket: Some("typo".into()) };
+        assert_ne!(tagged, root(&[put("a").with_meta(shifted)]));
+        // empty metadata is dropped, leaving the leaf as it was
+        assert_eq!(plain, root(&[put("a").with_meta(OpMeta::default())]));
+    }
+
+    #[test]
+    fn order_matters() {
+        assert_ne!(root(&[put("a"), put("b")]), root(&[put("b"), put("a")]));
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// A single change to the key/value state recorded in a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Op {
+    Put {
+        key: String,
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        meta: Option<OpMeta>,
+    },
+    Del {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        meta: Option<OpMeta>,
+    },
+    /// Hand the chain to a new signing key: blocks after the one holding this op must be
+    /// signed by `new_pubkey` (hex). The block itself is signed by the outgoing key, which
+    /// is what proves the handover.
+    Rotate { new_pubkey: String },
+}
+
+/// Who made a change and why, as told by the client that submitted it.
+///
+/// Committed to by the op's Merkle leaf, so it cannot be altered once mined; it is not
+/// checked against anything, so it is only as trustworthy as the block's signer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Ticket or issue id the change belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket: Option<String>,
+}
+
+impl OpMeta {
+    pub fn is_empty(&self) -> bool {
+        self.author.is_none() && self.reason.is_none() && self.ticket.is_none()
+    }
+}
+
+impl Op {
+    /// `Put` without metadata
+    pub fn put(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Op::Put { key: key.into(), value: value.into(), meta: None }
+    }
+
+    /// `Del` without metadata
+    pub fn del(key: impl Into<String>) -> Self {
+        Op::Del { key: key.into(), meta: None }
+    }
+
+    /// Attach `meta` to a `Put` or `Del`; empty metadata is dropped, and a `Rotate` is
+    /// returned unchanged
+    pub fn with_meta(mut self, meta: OpMeta) -> Self {
+        if let Op::Put { meta: slot, .. } | Op::Del { meta: slot, .. } = &mut self {
+            *slot = Some(meta).filter(|m| !m.is_empty());
+        }
+        self
+    }
+
+    /// Metadata of a `Put` or `Del`, if any was attached
+    pub fn meta(&self) -> Option<&OpMeta> {
+        match self {
+            Op::Put { meta, .. } | Op::Del { meta, .. } => meta.as_ref(),
+            Op::Rotate { .. } => None,
+        }
+    }
+}
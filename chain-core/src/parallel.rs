@@ -0,0 +1,110 @@
+//! Materialization spread over threads with rayon.
+//!
+//! Ops on different keys commute, so the ops are split into partitions by a hash of their
+//! key, each partition is replayed on its own thread in chain order, and the per-partition
+//! states (which share no keys) are merged.
+
+use crate::chain::apply;
+use crate::{Chain, Op};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Ops a chain must hold before [`Chain::materialize`] switches to
+/// [`Chain::materialize_parallel`]; below it, the threads cost more than they save
+pub const PARALLEL_MIN_OPS: usize = 100_000;
+
+/// Ops bucketed together by one task before partitioning
+const CHUNK_OPS: usize = 16_384;
+
+fn partition(key: &str, parts: usize) -> usize {
+    let mut h = DefaultHasher::new();
+    key.hash(&mut h);
+    (h.finish() % parts as u64) as usize
+}
+
+impl Chain {
+    /// [`Chain::materialize`] on the rayon thread pool, whatever the chain's size
+    pub fn materialize_parallel(&self) -> HashMap<String, String> {
+        self.materialize_partitioned(rayon::current_num_threads())
+    }
+
+    fn materialize_partitioned(&self, parts: usize) -> HashMap<String, String> {
+        let parts = parts.max(1);
+        let ops: Vec<&Op> = self.blocks[self.pruned_len()..].iter().flat_map(|b| &b.ops).collect();
+
+        // chunk i's bucket p holds chunk i's ops on partition p, in chain order
+        let buckets: Vec<Vec<Vec<&Op>>> = ops
+            .par_chunks(CHUNK_OPS)
+            .map(|chunk| {
+                let mut by_part = vec![Vec::new(); parts];
+                for &op in chunk {
+                    match op {
+                        Op::Put { key, .. } | Op::Del { key, .. } => by_part[partition(key, parts)].push(op),
+                        Op::Rotate { .. } => {}
+                    }
+                }
+                by_part
+            })
+            .collect();
+
+        let states: Vec<HashMap<String, String>> = (0..parts)
+            .into_par_iter()
+            .map(|p| {
+                let mut state: HashMap<String, String> = self
+                    .snapshot()
+                    .iter()
+                    .flat_map(|s| &s.state)
+                    .filter(|(k, _)| partition(k, parts) == p)
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                for op in buckets.iter().flat_map(|chunk| &chunk[p]) {
+                    apply(&mut state, op);
+                }
+                state
+            })
+            .collect();
+
+        let mut merged = HashMap::with_capacity(states.iter().map(HashMap::len).sum());
+        for state in states {
+            merged.extend(state);
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys;
+
+    fn churning_chain() -> Chain {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        for round in 0..6 {
+            let ops = (0..3_000)
+                .map(|i| match (i + round) % 5 {
+                    0 => Op::del(format!("k{}", i % 700)),
+                    _ => Op::put(format!("k{}", i % 700), format!("{round}-{i}")),
+                })
+                .collect();
+            chain.append_signed(ops, &kp, None).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn partitions_agree_with_sequential_replay() {
+        let mut chain = churning_chain();
+        let sequential = chain.materialize_to(chain.blocks.len());
+        assert!(!sequential.is_empty());
+        for parts in [1, 2, 7, 64] {
+            assert_eq!(chain.materialize_partitioned(parts), sequential, "{parts} partitions");
+        }
+        assert_eq!(chain.materialize_parallel(), sequential);
+
+        // snapshot entries land in their key's partition, where later deletes can reach them
+        chain.prune(3, &keys::test_key()).unwrap();
+        assert_eq!(chain.materialize_partitioned(7), sequential);
+    }
+}
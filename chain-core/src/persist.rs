@@ -0,0 +1,88 @@
+//! Chain files: pretty JSON, or zstd-compressed JSON with the `zstd` feature.
+
+use crate::Chain;
+use std::{fs, io};
+
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 9;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+impl Chain {
+    /// Save as pretty JSON, or as zstd-compressed JSON when the path ends in `.zst`.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        if path.ends_with(".zst") {
+            let json = serde_json::to_vec(self).map_err(io::Error::other)?;
+            return fs::write(path, compress(&json)?);
+        }
+        let s = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, s)
+    }
+
+    /// Load plain or zstd-compressed JSON; compression is detected by magic bytes, not extension.
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Parse the contents of a chain file, as [`Chain::load`] does. The bytes are
+    /// untrusted: a malformed file is an error, never a panic.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let decompressed;
+        let json = if bytes.starts_with(&ZSTD_MAGIC) {
+            decompressed = decompress(bytes)?;
+            &decompressed
+        } else {
+            bytes
+        };
+        serde_json::from_slice(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("parse error: {e}")))
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::encode_all(bytes, ZSTD_LEVEL)
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::decode_all(bytes)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress(_: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "built without zstd support"))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "chain file is zstd-compressed; built without zstd support"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chain, Op, keys};
+
+    #[test]
+    fn save_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("chain-core-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chain.json");
+        let path = path.to_str().unwrap();
+
+        let mut chain = Chain::test(1);
+        chain.append_signed(vec![Op::put("k", "v")], &keys::test_key(), None).unwrap();
+        chain.save(path).unwrap();
+
+        let loaded = Chain::load(path).unwrap();
+        assert_eq!(loaded.blocks, chain.blocks);
+        assert_eq!(loaded.verify_all(), Ok(()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        for bytes in [&b""[..], b"{", b"{\"blocks\": 7}", b"[]", &super::ZSTD_MAGIC] {
+            assert!(Chain::from_bytes(bytes).is_err(), "{bytes:?}");
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Proof-of-work hashing: candidate block hashes as raw digests, computed in batches.
+//!
+//! A block hash is SHA-256 over the header prefix ([`HashScheme::header_prefix`]) followed by
+//! the nonce in little-endian. The prefix is fixed for a whole nonce search, so a
+//! [`Hasher`] is handed it once per batch and fills a caller-owned buffer with one digest
+//! per nonce; nothing is allocated or hex-encoded per hash. [`CpuHasher`] is the
+//! built-in implementation; a GPU or other external worker implements [`Hasher`] to
+//! take its place in [`Block::find_nonce_with`].
+//!
+//! [`HashScheme::header_prefix`]: crate::HashScheme::header_prefix
+//! [`Block::find_nonce_with`]: crate::Block::find_nonce_with
+
+use sha2::digest::generic_array::GenericArray;
+use sha2::{Digest, Sha256};
+
+/// Nonces hashed per [`Hasher::hash_batch`] call by [`Block::find_nonce`](crate::Block::find_nonce)
+pub const HASH_BATCH: usize = 256;
+
+/// Hashes candidate nonces for a block header in batches
+pub trait Hasher {
+    /// Fill `out[i]` with SHA-256 of `prefix` followed by nonce `start + i` (wrapping) in
+    /// little-endian
+    fn hash_batch(&mut self, prefix: &[u8], start: u64, out: &mut [[u8; 32]]);
+}
+
+/// [`Hasher`] on the calling thread.
+///
+/// It keeps the SHA-256 state after absorbing the prefix and only hashes the nonce on
+/// top of a copy of it, so a search costs one compression per nonce instead of a whole
+/// header's worth. sha2 uses the CPU's SHA extensions when it has them, and the `asm`
+/// feature swaps in its assembly implementation elsewhere.
+#[derive(Clone, Default)]
+pub struct CpuHasher {
+    prefix: Vec<u8>,
+    midstate: Sha256,
+}
+
+impl CpuHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Hasher for CpuHasher {
+    fn hash_batch(&mut self, prefix: &[u8], start: u64, out: &mut [[u8; 32]]) {
+        if self.prefix != prefix {
+            self.prefix = prefix.to_vec();
+            self.midstate = Sha256::new_with_prefix(prefix);
+        }
+        let mut nonce = start;
+        for digest in out {
+            let mut hasher = self.midstate.clone();
+            hasher.update(nonce.to_le_bytes());
+            hasher.finalize_into(GenericArray::from_mut_slice(digest));
+            nonce = nonce.wrapping_add(1);
+        }
+    }
+}
+
+/// Whether `hash`, written in hex, starts with `difficulty` zeros
+pub fn meets_difficulty(hash: &[u8; 32], difficulty: usize) -> bool {
+    let (bytes, nibble) = (difficulty / 2, difficulty % 2 == 1);
+    if bytes + usize::from(nibble) > hash.len() {
+        return false;
+    }
+    hash[..bytes].iter().all(|&b| b == 0) && (!nibble || hash[bytes] < 0x10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_match_single_hashes() {
+        let prefix = b"header".to_vec();
+        let mut hasher = CpuHasher::new();
+        let mut out = [[0u8; 32]; 4];
+        hasher.hash_batch(&prefix, u64::MAX - 1, &mut out);
+        for (i, digest) in out.iter().enumerate() {
+            let nonce = (u64::MAX - 1).wrapping_add(i as u64);
+            let expected: [u8; 32] = Sha256::new().chain_update(&prefix).chain_update(nonce.to_le_bytes()).finalize().into();
+            assert_eq!(*digest, expected, "nonce {nonce}");
+        }
+
+        // a new prefix replaces the cached state
+        hasher.hash_batch(b"other", 0, &mut out[..1]);
+        let expected: [u8; 32] = Sha256::new().chain_update(b"other").chain_update(0u64.to_le_bytes()).finalize().into();
+        assert_eq!(out[0], expected);
+    }
+
+    #[test]
+    fn difficulty_counts_hex_zeros() {
+        let mut hash = [0xffu8; 32];
+        assert!(meets_difficulty(&hash, 0));
+        assert!(!meets_difficulty(&hash, 1));
+        hash[0] = 0x0f;
+        assert!(meets_difficulty(&hash, 1));
+        assert!(!meets_difficulty(&hash, 2));
+        hash[0] = 0;
+        hash[1] = 0x01;
+        assert!(meets_difficulty(&hash, 3));
+        assert!(!meets_difficulty(&hash, 4));
+        assert!(meets_difficulty(&[0; 32], 64));
+        assert!(!meets_difficulty(&[0; 32], 65));
+    }
+}
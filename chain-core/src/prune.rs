@@ -0,0 +1,243 @@
+use crate::block::verify_signature;
+use crate::scheme::field_digest;
+use crate::{Block, Chain, Signer, keys};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Leads the message a [`Snapshot`] is signed over, see [`Snapshot::signing_message`]
+pub const SNAPSHOT_DOMAIN: &[u8] = b"chain_kv-snapshot-v1";
+
+/// What a pruned chain remembers of the op bodies it dropped.
+///
+/// The ops that produced `state` and `required_signer` are gone, so nothing else vouches
+/// for them: the key that signed block `height` signs the snapshot too, and
+/// [`Chain::verify_all`] rejects a snapshot whose signature does not check out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Index of the last pruned block; this and every earlier block keeps only its header
+    pub height: u64,
+    /// Materialized state as of the end of block `height`
+    pub state: BTreeMap<String, String>,
+    /// Key bound by the last rotation in the pruned blocks, if any
+    pub required_signer: Option<String>,
+    /// Hex public key of the snapshot's signer, the signer of block `height`
+    #[serde(default)]
+    pub signer_pubkey: String,
+    /// Hex Ed25519 signature over [`Snapshot::signing_message`]
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl Snapshot {
+    /// What the signature covers: [`SNAPSHOT_DOMAIN`] and the SHA-256 of the chain id,
+    /// the height and hash of block `height`, every state entry and the required signer,
+    /// each length-prefixed
+    pub fn signing_message(&self, chain_id: &str, block_hash: &str) -> Vec<u8> {
+        fn field(digest: &mut Sha256, bytes: &[u8]) {
+            digest.update((bytes.len() as u64).to_le_bytes());
+            digest.update(bytes);
+        }
+        let mut digest = Sha256::new();
+        field(&mut digest, chain_id.as_bytes());
+        digest.update(self.height.to_le_bytes());
+        digest.update(field_digest(block_hash));
+        digest.update((self.state.len() as u64).to_le_bytes());
+        for (key, value) in &self.state {
+            field(&mut digest, key.as_bytes());
+            field(&mut digest, value.as_bytes());
+        }
+        match &self.required_signer {
+            Some(key) => field(&mut digest, key.as_bytes()),
+            None => digest.update([0xff; 8]),
+        }
+        [SNAPSHOT_DOMAIN, &digest.finalize()].concat()
+    }
+
+    /// Check that the snapshot is signed by the signer of `last`, the last pruned block
+    pub(crate) fn verify(&self, chain_id: &str, last: &Block) -> Result<(), String> {
+        if last.index != self.height {
+            return Err(format!("no block {} to anchor it", self.height));
+        }
+        if last.signer_pubkey.as_deref() != Some(self.signer_pubkey.as_str()) {
+            return Err(format!("not signed by the signer of block {}", self.height));
+        }
+        verify_signature(&self.signer_pubkey, &self.signature, &self.signing_message(chain_id, &last.hash))
+    }
+}
+
+impl Chain {
+    /// Drop the ops of every block but the newest `keep`, folding them into the snapshot
+    /// that [`Chain::materialize`] starts from. Headers (hash, prev hash, Merkle root,
+    /// signature) stay, so [`Chain::verify_all`] still checks links, proof-of-work and
+    /// signatures over the pruned blocks.
+    ///
+    /// `signer` signs the snapshot and must be the key that signed the last block pruned.
+    /// Returns how many blocks were newly pruned.
+    pub fn prune(&mut self, keep: usize, signer: &dyn Signer) -> Result<usize, String> {
+        let cut = self.blocks.len().saturating_sub(keep);
+        let done = self.pruned_len();
+        if cut <= done {
+            return Ok(0);
+        }
+        let last = &self.blocks[cut - 1];
+        match last.signer_pubkey.as_deref() {
+            None => return Err(format!("block {} is unsigned, so no snapshot can be signed there", last.index)),
+            Some(key) if key != keys::public_hex(signer) => {
+                return Err(format!("block {} was signed by {key}; only that key can sign a snapshot there", last.index));
+            }
+            Some(_) => {}
+        }
+        let mut snapshot = Snapshot {
+            height: last.index,
+            state: self.materialize_to(cut).into_iter().collect(),
+            required_signer: self.signer_to(cut).map(str::to_string),
+            signer_pubkey: keys::public_hex(signer),
+            signature: String::new(),
+        };
+        let message = snapshot.signing_message(&self.chain_id, &last.hash);
+        let sig = signer.sign_message(&message).map_err(|e| format!("signing failed: {e}"))?;
+        snapshot.signature = hex::encode(sig.to_bytes());
+        snapshot.verify(&self.chain_id, last).map_err(|_| "signer returned a signature that does not verify")?;
+
+        for b in &mut self.blocks[done..cut] {
+            b.ops = Vec::new();
+        }
+        self.snapshot = Some(snapshot);
+        Ok(cut - done)
+    }
+
+    /// The state and signer the pruned blocks left behind, if any were pruned
+    pub fn snapshot(&self) -> Option<&Snapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Index of the last block whose ops were pruned
+    pub fn pruned_height(&self) -> Option<u64> {
+        self.snapshot.as_ref().map(|s| s.height)
+    }
+
+    /// Number of leading blocks that keep only their header
+    pub(crate) fn pruned_len(&self) -> usize {
+        self.pruned_height().map_or(0, |h| self.blocks.partition_point(|b| b.index <= h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chain, Op, keys};
+    use ed25519_dalek::SigningKey;
+
+    fn put(key: &str, value: &str) -> Vec<Op> {
+        vec![Op::put(key, value)]
+    }
+
+    #[test]
+    fn pruning_keeps_headers_and_state() {
+        let kp = keys::test_key();
+        let next = SigningKey::from_bytes(&[9; 32]);
+        let mut chain = Chain::test(1);
+        chain.append_signed(put("a", "1"), &kp, None).unwrap();
+        chain.append_signed(put("b", "2"), &kp, None).unwrap();
+        chain.rotate_key(&kp, &next.verifying_key(), None).unwrap();
+        chain.append_signed(vec![Op::del("a")], &next, None).unwrap();
+        let before = chain.clone();
+
+        assert_eq!(chain.prune(1, &kp), Ok(4));
+        assert_eq!(chain.pruned_height(), Some(3));
+        assert!(chain.blocks[..4].iter().all(|b| b.ops.is_empty()));
+        assert_eq!(chain.blocks.len(), before.blocks.len());
+        assert_eq!(chain.last_hash(), before.last_hash());
+        assert_eq!(chain.materialize(), before.materialize());
+        assert_eq!(chain.required_signer(), before.required_signer());
+        assert_eq!(chain.verify_all(), Ok(()));
+
+        // the pruned region still has to link up
+        let mut broken = chain.clone();
+        broken.blocks[2].nonce += 1;
+        assert_eq!(broken.verify_all(), Err("block 2: hash mismatch".into()));
+
+        // pruning less than is already pruned is a no-op; further pruning extends the snapshot
+        assert_eq!(chain.prune(3, &kp), Ok(0));
+        chain.append_signed(put("c", "3"), &next, None).unwrap();
+        assert!(chain.append_signed(put("c", "4"), &kp, None).is_err());
+        assert_eq!(chain.prune(0, &next), Ok(2));
+        assert_eq!(chain.snapshot().unwrap().state.len(), 2);
+        assert_eq!(chain.materialize()["c"], "3");
+        assert_eq!(chain.verify_all(), Ok(()));
+    }
+
+    #[test]
+    fn snapshot_survives_serialization() {
+        let kp = keys::test_key();
+        let mut chain = Chain::test(1);
+        chain.append_signed(put("a", "1"), &kp, None).unwrap();
+        chain.append_signed(put("b", "2"), &kp, None).unwrap();
+        chain.prune(1, &kp).unwrap();
+
+        let json = serde_json::to_string(&chain).unwrap();
+        let loaded: Chain = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.snapshot(), chain.snapshot());
+        assert_eq!(loaded.materialize(), chain.materialize());
+        assert_eq!(loaded.verify_all(), Ok(()));
+
+        // a chain that was never pruned serializes as before
+        assert!(!serde_json::to_string(&Chain::test(1)).unwrap().contains("snapshot"));
+    }
+
+    #[test]
+    fn snapshots_are_signed_by_the_last_pruned_block_signer() {
+        let kp = keys::test_key();
+        let other = SigningKey::from_bytes(&[9; 32]);
+        let mut chain = Chain::test(1);
+        chain.append_signed(put("a", "1"), &kp, None).unwrap();
+        chain.append_signed(put("b", "2"), &kp, None).unwrap();
+
+        let unpruned = chain.clone();
+        assert!(chain.clone().prune(0, &other).unwrap_err().contains("only that key"));
+        assert!(chain.clone().prune(2, &kp).unwrap_err().contains("unsigned"));
+        assert_eq!(chain.blocks, unpruned.blocks);
+
+        chain.prune(1, &kp).unwrap();
+        assert_eq!(chain.verify_all(), Ok(()));
+    }
+
+    #[test]
+    fn edited_snapshots_do_not_verify() {
+        let kp = keys::test_key();
+        let attacker = SigningKey::from_bytes(&[9; 32]);
+        let mut chain = Chain::test(1);
+        chain.append_signed(put("balance", "10"), &kp, None).unwrap();
+        chain.rotate_key(&kp, &kp.verifying_key(), None).unwrap();
+        chain.append_signed(put("b", "2"), &kp, None).unwrap();
+        chain.prune(1, &kp).unwrap();
+        let err = |chain: &Chain| chain.verify_all().unwrap_err();
+
+        // the state, edited in the file
+        let mut edited = chain.clone();
+        edited.snapshot.as_mut().unwrap().state.insert("balance".into(), "1000000".into());
+        assert_eq!(err(&edited), "snapshot at block 2: signature verify failed");
+
+        // the required signer, swapped for the attacker's key
+        let mut edited = chain.clone();
+        edited.snapshot.as_mut().unwrap().required_signer = Some(keys::public_hex(&attacker));
+        assert_eq!(err(&edited), "snapshot at block 2: signature verify failed");
+
+        // and re-signed by the attacker
+        let snapshot = edited.snapshot.as_mut().unwrap();
+        let message = snapshot.signing_message("test", &chain.blocks[2].hash);
+        snapshot.signer_pubkey = keys::public_hex(&attacker);
+        snapshot.signature = hex::encode(ed25519_dalek::Signer::sign(&attacker, &message).to_bytes());
+        assert_eq!(err(&edited), "snapshot at block 2: not signed by the signer of block 2");
+
+        // moved to another block
+        let mut edited = chain.clone();
+        edited.snapshot.as_mut().unwrap().height = 1;
+        assert!(edited.verify_all().is_err());
+
+        // files from before snapshots were signed
+        let mut edited = chain.clone();
+        edited.snapshot.as_mut().unwrap().signature.clear();
+        assert_eq!(err(&edited), "snapshot at block 2: signature must be 64 bytes");
+    }
+}
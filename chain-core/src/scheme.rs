@@ -0,0 +1,105 @@
+//! How block and Merkle hashes are computed, which a chain's format version fixes.
+
+use sha2::{Digest, Sha256};
+
+/// First chain format version whose hashes use [`HashScheme::Raw`]
+pub const RAW_HASH_VERSION: u32 = 3;
+
+/// What goes into block headers and Merkle nodes.
+///
+/// Hashes are stored and shown as lowercase hex either way; the schemes differ in what
+/// is hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashScheme {
+    /// Chain format versions 1 and 2: the hex *text* of the Merkle root and previous
+    /// hash goes into a block header, and of the two children into a Merkle node, so
+    /// every digest is hashed as 64 bytes of text. An empty block's root is `"0"`.
+    Text,
+    /// Version 3 on: the 32-byte digests themselves, as in the usual constructions. A
+    /// header field that is not a digest (the genesis block's `"GENESIS"` hash) goes in
+    /// as the SHA-256 of its text. An empty block's root is 32 zero bytes.
+    Raw,
+}
+
+impl HashScheme {
+    /// The scheme of chains created now
+    pub const CURRENT: HashScheme = HashScheme::Raw;
+
+    /// The scheme a chain of format `version` hashes with
+    pub fn for_version(version: u32) -> Self {
+        if version >= RAW_HASH_VERSION { HashScheme::Raw } else { HashScheme::Text }
+    }
+
+    /// The header bytes hashed ahead of the nonce, which stay the same for a whole nonce search
+    pub fn header_prefix(self, index: u64, timestamp: i64, merkle_root: &str, prev_hash: &str) -> Vec<u8> {
+        let mut prefix = Vec::with_capacity(16 + 2 * 64);
+        prefix.extend_from_slice(&index.to_le_bytes());
+        prefix.extend_from_slice(&timestamp.to_le_bytes());
+        for field in [merkle_root, prev_hash] {
+            match self {
+                HashScheme::Text => prefix.extend_from_slice(field.as_bytes()),
+                HashScheme::Raw => prefix.extend_from_slice(&field_digest(field)),
+            }
+        }
+        prefix
+    }
+
+    /// Block hash over the header fields, as the raw SHA-256 digest
+    pub fn block_digest(self, index: u64, timestamp: i64, merkle_root: &str, prev_hash: &str, nonce: u64) -> [u8; 32] {
+        Sha256::new_with_prefix(self.header_prefix(index, timestamp, merkle_root, prev_hash))
+            .chain_update(nonce.to_le_bytes())
+            .finalize()
+            .into()
+    }
+
+    /// Block hash over the header fields, as lowercase hex
+    pub fn block_hash(self, index: u64, timestamp: i64, merkle_root: &str, prev_hash: &str, nonce: u64) -> String {
+        hex::encode(self.block_digest(index, timestamp, merkle_root, prev_hash, nonce))
+    }
+}
+
+/// `hash` as a digest, if it is 64 lowercase hex digits
+pub(crate) fn parse_digest(hash: &str) -> Option<[u8; 32]> {
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    hex::decode_to_slice(hash, &mut digest).ok()?;
+    Some(digest)
+}
+
+/// A header field under [`HashScheme::Raw`]: the digest it spells, or the SHA-256 of its text
+pub(crate) fn field_digest(field: &str) -> [u8; 32] {
+    parse_digest(field).unwrap_or_else(|| Sha256::digest(field.as_bytes()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_pick_schemes() {
+        assert_eq!(HashScheme::for_version(1), HashScheme::Text);
+        assert_eq!(HashScheme::for_version(2), HashScheme::Text);
+        assert_eq!(HashScheme::for_version(3), HashScheme::Raw);
+        assert_eq!(HashScheme::for_version(crate::CHAIN_FORMAT_VERSION), HashScheme::CURRENT);
+    }
+
+    #[test]
+    fn raw_headers_hold_digests() {
+        let root = "ab".repeat(32);
+        let raw = HashScheme::Raw.header_prefix(1, 2, &root, "GENESIS");
+        assert_eq!(raw.len(), 16 + 64);
+        assert_eq!(&raw[16..48], &[0xab; 32]);
+        assert_eq!(&raw[48..], Sha256::digest(b"GENESIS").as_slice());
+
+        let text = HashScheme::Text.header_prefix(1, 2, &root, "GENESIS");
+        assert_eq!(&text[16..], format!("{root}GENESIS").as_bytes());
+        assert_ne!(HashScheme::Raw.block_hash(1, 2, &root, "GENESIS", 0), HashScheme::Text.block_hash(1, 2, &root, "GENESIS", 0));
+
+        // only lowercase hex spells a digest, so each header has one spelling
+        assert_eq!(parse_digest(&root), Some([0xab; 32]));
+        assert_eq!(parse_digest(&root.to_uppercase()), None);
+        assert_eq!(parse_digest("ab"), None);
+    }
+}
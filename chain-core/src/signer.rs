@@ -0,0 +1,24 @@
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+
+/// Signs blocks for one Ed25519 key.
+///
+/// [`SigningKey`] signs in-process. Front-ends can implement this to keep the private
+/// key elsewhere (a hardware token, a signing service), in which case signing may fail.
+pub trait Signer: Send + Sync {
+    /// Public key recorded in `Block::signer_pubkey`
+    fn verifying_key(&self) -> VerifyingKey;
+
+    /// Ed25519 signature over `message`, a block's
+    /// [`Block::signing_message`](crate::Block::signing_message)
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String>;
+}
+
+impl Signer for SigningKey {
+    fn verifying_key(&self) -> VerifyingKey {
+        SigningKey::verifying_key(self)
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+        Ok(ed25519_dalek::Signer::sign(self, message))
+    }
+}
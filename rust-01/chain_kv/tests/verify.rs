@@ -0,0 +1,71 @@
+//! `chain_kv verify` against the chain files in `tests/chains`: `valid.json` as saved by
+//! `chain_kv demo`, and `corrupt/`, each a copy of it damaged one way. A corrupt file must
+//! be reported, naming what is wrong, never crash the program.
+
+use std::path::Path;
+use std::process::Command;
+
+/// What `verify` must say about each file in `tests/chains/corrupt`
+const CORRUPT: &[(&str, &str)] = &[
+    ("bad_pubkey_hex.json", "verify failed: block 2: bad pubkey hex"),
+    ("bad_signature_hex.json", "verify failed: block 1: bad signature hex"),
+    ("broken_link.json", "verify failed: block 2: prev_hash mismatch"),
+    ("dropped_op.json", "verify failed: block 1: ops do not match the merkle root"),
+    ("empty_blocks.json", "verify failed: empty chain"),
+    ("forged_signature.json", "verify failed: block 2: signature verify failed"),
+    ("future_version.json", "verify failed: unsupported chain format version 99"),
+    ("garbage.json", "load error: parse error: expected value at line 1 column 1"),
+    ("huge_difficulty.json", "verify failed: block 1: insufficient PoW"),
+    ("insufficient_pow.json", "verify failed: block 1: insufficient PoW"),
+    ("negative_index.json", "load error: parse error: invalid value: integer `-1`, expected u64"),
+    ("other_chain_id.json", "verify failed: block 1: signature verify failed"),
+    ("short_pubkey.json", "verify failed: block 2: public key must be 32 bytes"),
+    ("short_signature.json", "verify failed: block 1: signature must be 64 bytes"),
+    ("tampered_hash.json", "verify failed: block 1: hash mismatch"),
+    ("tampered_value.json", "verify failed: block 1: ops do not match the merkle root"),
+    ("truncated.json", "load error: parse error: EOF while parsing"),
+    ("wrong_type.json", "load error: parse error: invalid type: string \"one\", expected u64"),
+];
+
+fn chains() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/chains"))
+}
+
+/// Run `chain_kv verify <paths>`; returns whether it succeeded and what it printed
+fn verify(paths: &[&Path]) -> (bool, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_chain_kv")).arg("verify").args(paths).output().unwrap();
+    assert_ne!(out.status.code(), Some(101), "verify panicked: {}", String::from_utf8_lossy(&out.stderr));
+    (out.status.success(), String::from_utf8(out.stdout).unwrap())
+}
+
+#[test]
+fn valid_chain_verifies() {
+    let (ok, stdout) = verify(&[&chains().join("valid.json")]);
+    assert!(ok, "{stdout}");
+    assert!(stdout.contains("chain ok (3 blocks, difficulty 3)"), "{stdout}");
+}
+
+#[test]
+fn corrupt_chains_are_reported() {
+    let dir = chains().join("corrupt");
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    let listed: Vec<&str> = CORRUPT.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, listed, "every corrupt chain needs an expected report");
+
+    for (name, expected) in CORRUPT {
+        let (ok, stdout) = verify(&[&dir.join(name)]);
+        assert!(!ok, "{name} verified: {stdout}");
+        assert!(stdout.contains(expected), "{name}: expected {expected:?}, got {stdout}");
+    }
+}
+
+#[test]
+fn one_bad_file_fails_the_run() {
+    let (ok, stdout) = verify(&[&chains().join("valid.json"), &chains().join("corrupt/tampered_value.json")]);
+    assert!(!ok);
+    assert_eq!(stdout.lines().count(), 2, "{stdout}");
+}
@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+# Example external signer for `signer cmd`: keeps the Ed25519 key in a PEM file
+# that chain_kv_full never reads.
+#
+#   openssl genpkey -algorithm ed25519 -out signer.pem
+#   > signer cmd ./scripts/openssl-signer.sh signer.pem
+#
+# Usage: openssl-signer.sh <key.pem> pubkey
+#        openssl-signer.sh <key.pem> sign <hex message>
+set -euo pipefail
+
+key=$1
+case $2 in
+pubkey)
+    # The raw 32-byte key is the tail of the DER SubjectPublicKeyInfo
+    openssl pkey -in "$key" -pubout -outform DER | tail -c 32 | od -An -tx1 | tr -d ' \n'
+    ;;
+sign)
+    msg=$(mktemp)
+    trap 'rm -f "$msg"' EXIT
+    printf '%s' "$3" | xxd -r -p > "$msg"
+    openssl pkeyutl -sign -inkey "$key" -rawin -in "$msg" | od -An -tx1 | tr -d ' \n'
+    ;;
+*)
+    echo "usage: $0 <key.pem> pubkey | sign <hex message>" >&2
+    exit 2
+    ;;
+esac
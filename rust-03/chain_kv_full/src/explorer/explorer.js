@@ -0,0 +1,140 @@
+// ChainKV explorer: plain browser JS over the node's own read endpoints.
+const RECENT = 20;
+const $ = (id) => document.getElementById(id);
+
+function esc(s) {
+    return String(s).replace(/[&<>"']/g, (c) => `&#${c.charCodeAt(0)};`);
+}
+
+function short(hex) {
+    return hex ? esc(hex.slice(0, 16)) : '<span class="muted">unsigned</span>';
+}
+
+function ago(ts) {
+    if (!ts) return '';
+    const s = Math.max(0, Math.floor(Date.now() / 1000) - ts);
+    if (s < 60) return `${s}s ago`;
+    if (s < 3600) return `${Math.floor(s / 60)}m ago`;
+    if (s < 86400) return `${Math.floor(s / 3600)}h ago`;
+    return `${Math.floor(s / 86400)}d ago`;
+}
+
+function describeOp(op) {
+    const [kind, body] = Object.entries(op)[0];
+    const meta = body.meta
+        ? ` <span class="meta">${Object.entries(body.meta).map(([k, v]) => `${esc(k)}=${esc(JSON.stringify(v))}`).join(' ')}</span>`
+        : '';
+    switch (kind) {
+        case 'Put': return `PUT <code>${esc(body.key)}</code> = <code>${esc(body.value)}</code>${meta}`;
+        case 'Del': return `DEL <code>${esc(body.key)}</code>${meta}`;
+        case 'Rotate': return `ROTATE signing key to <span class="hash">${short(body.new_pubkey)}</span>`;
+        default: return esc(JSON.stringify(op));
+    }
+}
+
+async function getJson(url) {
+    const resp = await fetch(url);
+    if (!resp.ok) throw new Error(`${url}: ${resp.status} ${await resp.text()}`);
+    return resp.json();
+}
+
+// Fetch the blocks from `from`; a pruned node answers 410 naming the first block it still serves
+async function fetchBlocks(from, limit) {
+    let resp = await fetch(`/blocks?from=${from}&limit=${limit}`);
+    if (resp.status === 410) {
+        const note = await resp.text();
+        $('blocks-note').textContent = note;
+        const first = Number((note.match(/from=(\d+)/) || [])[1]);
+        if (!Number.isFinite(first)) return [];
+        resp = await fetch(`/blocks?from=${first}&limit=${limit}`);
+    }
+    if (!resp.ok) throw new Error(`/blocks: ${resp.status}`);
+    return (await resp.json()).blocks;
+}
+
+let blocks = [];
+
+async function refresh(tip) {
+    $('tip').textContent = `tip: #${tip.index} ${tip.hash.slice(0, 16)}`;
+    const from = Math.max(0, tip.index + 1 - RECENT);
+    blocks = (await fetchBlocks(from, RECENT)).reverse();
+    $('blocks').querySelector('tbody').innerHTML = blocks.map((b, i) => `
+        <tr data-i="${i}">
+            <td>${b.index}</td>
+            <td class="hash">${esc(b.hash.slice(0, 16))}</td>
+            <td>${b.ops.length}</td>
+            <td class="hash">${short(b.signer_pubkey)}</td>
+            <td>${ago(b.timestamp)}</td>
+        </tr>`).join('');
+
+    const verify = await getJson('/verify');
+    $('verify').className = `badge ${verify.ok ? 'ok' : 'bad'}`;
+    $('verify').textContent = verify.ok ? '✓ chain verifies' : `✗ ${verify.error}`;
+}
+
+function showBlock(b) {
+    const ops = b.ops.length
+        ? `<ol start="0">${b.ops.map((op) => `<li>${describeOp(op)}</li>`).join('')}</ol>`
+        : '<p class="muted">no ops (empty or pruned block)</p>';
+    $('block').innerHTML = `
+        <h2>Block #${b.index}</h2>
+        <dl>
+            <dt>hash</dt><dd class="hash">${esc(b.hash)}</dd>
+            <dt>prev hash</dt><dd class="hash">${esc(b.prev_hash)}</dd>
+            <dt>merkle root</dt><dd class="hash">${esc(b.merkle_root)}</dd>
+            <dt>nonce</dt><dd>${b.nonce}</dd>
+            <dt>mined</dt><dd>${b.timestamp ? new Date(b.timestamp * 1000).toLocaleString() : ''} ${ago(b.timestamp)}</dd>
+            <dt>signer</dt><dd class="hash">${esc(b.signer_pubkey || 'unsigned')}</dd>
+        </dl>
+        <h2>Ops</h2>${ops}`;
+    $('block').hidden = false;
+}
+
+$('blocks').addEventListener('click', (e) => {
+    const row = e.target.closest('tr[data-i]');
+    if (!row) return;
+    document.querySelectorAll('#blocks tr.selected').forEach((r) => r.classList.remove('selected'));
+    row.classList.add('selected');
+    showBlock(blocks[row.dataset.i]);
+});
+
+$('search').addEventListener('submit', async (e) => {
+    e.preventDefault();
+    const key = $('key').value.trim();
+    if (!key) return;
+    const path = encodeURIComponent(key);
+    try {
+        const [value, history] = await Promise.all([getJson(`/get/${path}`), getJson(`/history/${path}`)]);
+        const current = value === null
+            ? `<p><code>${esc(key)}</code> is not set</p>`
+            : `<p><code>${esc(key)}</code> = <code>${esc(value)}</code></p>`;
+        const rows = history.map((c) => `
+            <tr><td>${c.block}</td><td>${describeOp(c.op)}</td><td class="hash">${short(c.signer)}</td><td>${ago(c.timestamp)}</td></tr>`);
+        $('key-result').innerHTML = current + (rows.length
+            ? `<table><thead><tr><th>#</th><th>change</th><th>signer</th><th>mined</th></tr></thead><tbody>${rows.join('')}</tbody></table>`
+            : '<p class="muted">no changes on record</p>');
+    } catch (err) {
+        $('key-result').textContent = err.message;
+    }
+});
+
+// Long-poll /tip and redraw whenever a block lands
+async function follow() {
+    let since = '';
+    for (;;) {
+        try {
+            // the first request answers at once; later ones wait for the tip to move
+            const tip = await getJson(since ? `/tip?wait=30000&since=${since}` : '/tip');
+            if (tip.hash !== since) {
+                since = tip.hash;
+                await refresh(tip);
+            }
+        } catch (err) {
+            $('verify').className = 'badge bad';
+            $('verify').textContent = 'node unreachable';
+            await new Promise((r) => setTimeout(r, 5000));
+        }
+    }
+}
+
+follow();
@@ -1,448 +1,1504 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    response::{Html, IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
-use chrono::Utc;
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
-use rand_core::OsRng;
+use chain_core::{
+    keys::{generate_mnemonic, key_from_mnemonic, keygen_to_file, load_key_from_file, public_hex, save_key_to_file},
+    BatchPreview, Block, Chain, ChainDiff, Churn, Clock, NextBlock, Op, OpMeta, Signer, SignerStats, StateDiff,
+    SystemClock, TimeRules, CHAIN_FORMAT_VERSION,
+};
+use ed25519_dalek::{Signature, VerifyingKey};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block as Pane, List, Paragraph, Row, Table as TuiTable},
+    DefaultTerminal, Frame,
+};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, VecDeque},
     fs,
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
+    ops::{Deref, DerefMut},
     path::Path as FsPath,
+    process::Command,
     sync::{Arc, Mutex},
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::{
+    sync::{watch, OnceCell, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    task,
+};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{AllowOrigin, CorsLayer},
 };
-use tokio::task;
 
-/* ---------------- Domain Types ---------------- */
+/* ---------------- Mining Output ---------------- */
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-enum Op {
-    Put { key: String, value: String },
-    Del { key: String },
+/// Progress line drawn on stderr while the REPL mines.
+fn print_mining_progress(nonce: u64, cand: &str, hps: f64) {
+    eprint!("\r⛏️  mining… nonce={:<12} rate={:.0} H/s last={}", nonce, hps, &cand[..8]);
 }
 
-fn merkle_root(ops: &[Op]) -> String {
-    if ops.is_empty() {
-        return "0".into();
+/// How long `calibrate` hashes to measure this machine's rate
+const CALIBRATE_SAMPLE: Duration = Duration::from_secs(2);
+/// Block time `calibrate` aims for unless given one, in seconds
+const DEFAULT_BLOCK_TIME: f64 = 5.0;
+
+/// Average time to mine a block at `difficulty` on a machine doing `hps` hashes per second
+fn expected_time(difficulty: usize, hps: f64) -> String {
+    match Block::expected_hashes(difficulty) / hps.max(1e-9) {
+        s if s < 0.001 => "<1ms".into(),
+        s if s < 1.0 => format!("{:.0}ms", s * 1000.0),
+        s if s < 120.0 => format!("{s:.1}s"),
+        s if s < 7200.0 => format!("{:.0}min", s / 60.0),
+        s if s < 172_800.0 => format!("{:.1}h", s / 3600.0),
+        s => format!("{:.1}d", s / 86_400.0),
     }
-    let mut hashes: Vec<String> = ops
-        .iter()
-        .map(|op| {
-            let mut h = Sha256::new();
-            match op {
-                Op::Put { key, value } => {
-                    h.update(b"PUT");
-                    h.update(key.as_bytes());
-                    h.update(value.as_bytes());
-                }
-                Op::Del { key } => {
-                    h.update(b"DEL");
-                    h.update(key.as_bytes());
-                }
+}
+
+/// Expected block time at every difficulty `difficulty` accepts, marking the current and suggested ones
+fn print_calibration(hps: f64, current: usize, suggested: usize, out: Output) {
+    let mut table = Table::new(&["difficulty", "avg hashes", "avg time", ""]);
+    for d in 1..=9 {
+        let note = match (d == current, d == suggested) {
+            (true, true) => "current, suggested",
+            (true, false) => "current",
+            (false, true) => "suggested",
+            _ => "",
+        };
+        table.row(vec![d.to_string(), format!("{:.0}", Block::expected_hashes(d)), expected_time(d, hps), note.into()]);
+    }
+    table.print(out);
+}
+
+fn report_mined(blk: &Block) {
+    eprintln!();
+    println!("✅ mined block {} (nonce {})", blk.index, blk.nonce);
+}
+
+fn report_mining_failed(e: &str) {
+    eprintln!();
+    println!("❌ {e}");
+}
+
+/* ---------------- Output Formatting ---------------- */
+
+/// Characters of a table cell shown before it is cut short with `…`
+const CELL_WIDTH: usize = 40;
+
+/// Commands that only print, and so accept `--json` and `--full` (also `signer blocks` and bare `meta`)
+const READ_CMDS: &[&str] = &["get", "state", "history", "churn", "verify", "signers", "whoami", "diff", "preview"];
+
+/// How a read command prints what it found
+#[derive(Clone, Copy)]
+struct Output {
+    /// One JSON document instead of text, for scripts
+    json: bool,
+    /// No cutting: whole cells, hashes and keys, and every entry of long lists
+    full: bool,
+    /// ANSI colors, only on a terminal and never when `NO_COLOR` is set
+    color: bool,
+}
+
+impl Output {
+    /// Take `--json` and `--full` off a read command's arguments
+    fn take(parts: &mut Vec<&str>) -> Self {
+        let read = READ_CMDS.contains(&parts[0])
+            || (parts[0] == "signer" && parts.get(1) == Some(&"blocks"))
+            || (parts[0] == "meta" && parts[1..].iter().all(|p| p.starts_with("--")));
+        let mut flag = |name: &str| {
+            let found = read && parts.contains(&name);
+            parts.retain(|p| !read || *p != name);
+            found
+        };
+        let json = flag("--json");
+        let full = flag("--full");
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Self { json, full, color: !json && !no_color && io::stdout().is_terminal() }
+    }
+
+    fn print_json<T: Serialize + ?Sized>(&self, value: &T) {
+        match serde_json::to_string_pretty(value) {
+            Ok(json) => println!("{json}"),
+            Err(e) => println!("❌ {e}"),
+        }
+    }
+
+    /// `text` in the SGR style `code` (`"1"` bold, `"36"` cyan, …) when colors are on
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color { format!("\x1b[{code}m{text}\x1b[0m") } else { text.to_string() }
+    }
+
+    /// `text` cut to [`CELL_WIDTH`] characters, unless `--full`
+    fn fit(&self, text: &str) -> String {
+        if self.full || text.chars().count() <= CELL_WIDTH {
+            return text.to_string();
+        }
+        let cut: String = text.chars().take(CELL_WIDTH - 1).collect();
+        format!("{cut}…")
+    }
+
+    /// A hash or public key, abbreviated with [`short`] unless `--full`
+    fn id<'a>(&self, hex: &'a str) -> &'a str {
+        if self.full { hex } else { short(hex) }
+    }
+
+    /// How many entries of a list to show, `limit` unless `--full`
+    fn limit(&self, limit: usize) -> usize {
+        if self.full { usize::MAX } else { limit }
+    }
+}
+
+/// Rows printed as left-aligned columns under a bold header; the first column is highlighted
+struct Table {
+    header: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(header: &'static [&'static str]) -> Self {
+        Self { header, rows: Vec::new() }
+    }
+
+    fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    fn print(&self, out: Output) {
+        let header: Vec<String> = self.header.iter().map(|h| h.to_string()).collect();
+        let rows: Vec<Vec<String>> = self.rows.iter().map(|r| r.iter().map(|c| out.fit(c)).collect()).collect();
+        let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
             }
-            hex::encode(h.finalize())
+        }
+        println!("  {}", Self::line(&header, &widths, |_, cell| out.paint("1", cell)));
+        for row in &rows {
+            println!("  {}", Self::line(row, &widths, |i, cell| if i == 0 { out.paint("36", cell) } else { cell.to_string() }));
+        }
+    }
+
+    /// Cells padded to their column widths (padding goes outside the color codes)
+    fn line(cells: &[String], widths: &[usize], paint: impl Fn(usize, &str) -> String) -> String {
+        let last = cells.len().saturating_sub(1);
+        let padded: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let pad = if i == last { 0 } else { widths[i] - cell.chars().count() };
+                format!("{}{}", paint(i, cell), " ".repeat(pad))
+            })
+            .collect();
+        padded.join("  ")
+    }
+}
+
+/* ---------------- Churn ---------------- */
+
+const DEFAULT_CHURN_TOP: usize = 10;
+
+/// A `timerules` limit: seconds, or `off` for none
+fn parse_limit(arg: &str) -> Option<Option<i64>> {
+    match arg {
+        "off" => Some(None),
+        secs => secs.parse::<i64>().ok().filter(|s| *s >= 0).map(Some),
+    }
+}
+
+fn print_time_rules(rules: &TimeRules) {
+    let limit = |secs: Option<i64>| secs.map_or("no limit".to_string(), |s| format!("{s}s"));
+    println!(
+        "🕰️ blocks may be dated {} ahead of the clock and {} behind their predecessor",
+        limit(rules.max_future_secs),
+        limit(rules.max_backward_secs)
+    );
+}
+
+/// Coarse age of a block timestamp, e.g. "3d ago"
+fn ago(timestamp: i64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    match (now - timestamp).max(0) {
+        s if s < 60 => format!("{s}s ago"),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86_400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86_400),
+    }
+}
+
+fn print_churn(prefix: &str, churn: &Churn, top: usize, out: Output) {
+    if out.json {
+        let mut shown = churn.clone();
+        shown.keys.truncate(top);
+        return out.print_json(&shown);
+    }
+    if churn.changes == 0 {
+        println!("(no changes under {prefix:?})");
+        return;
+    }
+    println!(
+        "📈 {} changes to {} keys under {prefix:?} over {} day(s): {:.2}/day, {:.2}/week",
+        churn.changes,
+        churn.keys.len(),
+        churn.days,
+        churn.per_day(),
+        churn.per_week()
+    );
+    let mut table = Table::new(&["#", "key", "changes", "per day", "last change"]);
+    for (rank, k) in churn.keys.iter().take(top).enumerate() {
+        let per_day = k.changes as f64 / churn.days as f64;
+        table.row(vec![(rank + 1).to_string(), k.key.clone(), k.changes.to_string(), format!("{per_day:.2}"), ago(k.last_changed)]);
+    }
+    table.print(out);
+    if churn.keys.len() > top {
+        println!("  … {} more", churn.keys.len() - top);
+    }
+}
+
+/* ---------------- Key History ---------------- */
+
+/// `author=… reason=… ticket=…`, or empty without metadata
+fn describe_meta(meta: Option<&OpMeta>) -> String {
+    let Some(meta) = meta else { return String::new() };
+    [("author", &meta.author), ("reason", &meta.reason), ("ticket", &meta.ticket)]
+        .into_iter()
+        .filter_map(|(name, v)| v.as_ref().map(|v| format!("{name}={v:?}")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn print_meta(meta: &OpMeta, out: Output) {
+    if out.json {
+        out.print_json(meta);
+    } else if meta.is_empty() {
+        println!("🏷️ no metadata; set with: meta author|reason|ticket <text>");
+    } else {
+        println!("🏷️ writes carry {}", describe_meta(Some(meta)));
+    }
+}
+
+/// Every change to `key`, as `history --json` and `/history/{key}` list them
+fn history_entries(chain: &Chain, key: &str) -> Vec<HistoryEntry> {
+    chain
+        .history(key)
+        .map(|ch| HistoryEntry {
+            block: ch.block.index,
+            timestamp: ch.block.timestamp,
+            signer: ch.block.signer_pubkey.clone(),
+            op: ch.op.clone(),
         })
-        .collect();
+        .collect()
+}
 
-    while hashes.len() > 1 {
-        let mut next = Vec::with_capacity((hashes.len() + 1) / 2);
-        for pair in hashes.chunks(2) {
-            let mut h = Sha256::new();
-            h.update(pair[0].as_bytes());
-            if pair.len() == 2 {
-                h.update(pair[1].as_bytes());
-            } else {
-                h.update(pair[0].as_bytes()); // duplicate last if odd
-            }
-            next.push(hex::encode(h.finalize()));
+fn print_history(chain: &Chain, key: &str, out: Output) {
+    if out.json {
+        return out.print_json(&history_entries(chain, key));
+    }
+    let mut table = Table::new(&["block", "mined", "change", "signer", "metadata"]);
+    for change in chain.history(key) {
+        let b = change.block;
+        let what = match change.op {
+            Op::Put { value, .. } => format!("PUT {value:?}"),
+            _ => "DEL".to_string(),
+        };
+        let signer = b.signer_pubkey.as_deref().map_or("unsigned", |s| out.id(s));
+        table.row(vec![format!("#{}", b.index), ago(b.timestamp), what, signer.to_string(), describe_meta(change.op.meta())]);
+    }
+    if table.rows.is_empty() {
+        println!("(no changes to {key:?})");
+    } else {
+        table.print(out);
+    }
+    if let Some(h) = chain.pruned_height() {
+        println!("  (ops up to block {h} are pruned)");
+    }
+}
+
+/* ---------------- Chain Diff ---------------- */
+
+/// Entries shown per list in `diff` output before the rest are summarized
+const DIFF_SHOW: usize = 20;
+
+fn print_more(total: usize, what: &str, out: Output) {
+    if total > out.limit(DIFF_SHOW) {
+        println!("    … {} more {what}", total - DIFF_SHOW);
+    }
+}
+
+/// First 16 characters of a hash or key, for one-line listings
+fn short(hex: &str) -> &str {
+    hex.get(..16).unwrap_or(hex)
+}
+
+/// Blocks as a table of index, hash, op count, signer and age
+fn print_blocks<'a>(blocks: impl IntoIterator<Item = &'a Block>, out: Output) {
+    let mut table = Table::new(&["block", "hash", "ops", "signer", "mined"]);
+    for b in blocks {
+        let signer = b.signer_pubkey.as_deref().map_or("unsigned", |s| out.id(s));
+        table.row(vec![format!("#{}", b.index), out.id(&b.hash).to_string(), b.ops.len().to_string(), signer.to_string(), ago(b.timestamp)]);
+    }
+    table.print(out);
+}
+
+fn print_diverging(name: &str, blocks: &[Block], out: Output) {
+    println!("  {name}: {} block(s) past the fork", blocks.len());
+    if !blocks.is_empty() {
+        print_blocks(blocks.iter().take(out.limit(DIFF_SHOW)), out);
+    }
+    print_more(blocks.len(), "blocks", out);
+}
+
+fn print_diff(a: &str, b: &str, diff: &ChainDiff, out: Output) {
+    if out.json {
+        return out.print_json(diff);
+    }
+    if diff.is_identical() {
+        println!("✅ {a} and {b} hold the same blocks");
+        return;
+    }
+    match diff.fork_point {
+        Some(i) => println!("🔀 chains fork after block {i}"),
+        None => println!("🔀 chains share no blocks (different genesis)"),
+    }
+    print_diverging(a, diff.left, out);
+    print_diverging(b, diff.right, out);
+
+    if diff.state.is_empty() {
+        println!("  states match");
+        return;
+    }
+    print_state_diff(&format!("state of {b} vs {a}"), &diff.state, out);
+}
+
+fn print_state_diff(title: &str, state: &StateDiff, out: Output) {
+    println!(
+        "  {title}: {} added, {} removed, {} changed",
+        state.added.len(),
+        state.removed.len(),
+        state.changed.len()
+    );
+    let show = out.limit(DIFF_SHOW);
+    let mut table = Table::new(&["", "key", "value"]);
+    for (k, v) in state.added.iter().take(show) {
+        table.row(vec!["+".into(), k.clone(), v.clone()]);
+    }
+    for (k, v) in state.removed.iter().take(show) {
+        table.row(vec!["-".into(), k.clone(), v.clone()]);
+    }
+    for (k, (old, new)) in state.changed.iter().take(show) {
+        table.row(vec!["~".into(), k.clone(), format!("{} → {}", out.fit(old), out.fit(new))]);
+    }
+    table.print(out);
+    print_more(state.added.len(), "added", out);
+    print_more(state.removed.len(), "removed", out);
+    print_more(state.changed.len(), "changed", out);
+}
+
+fn print_preview(preview: &BatchPreview, out: Output) {
+    if out.json {
+        return out.print_json(preview);
+    }
+    println!("🔍 batch of {} op(s), not mined", preview.ops);
+    if preview.changes.is_empty() {
+        println!("  no change to state");
+    } else {
+        print_state_diff("state after commit", &preview.changes, out);
+    }
+    for v in &preview.violations {
+        println!("  ⛔ {v}");
+    }
+}
+
+/* ---------------- Signer Attribution ---------------- */
+
+fn print_signers(signers: &[SignerStats], out: Output) {
+    if out.json {
+        return out.print_json(signers);
+    }
+    if signers.is_empty() {
+        println!("(no signed blocks)");
+        return;
+    }
+    let mut table = Table::new(&["signer", "blocks", "ops", "first", "last"]);
+    for s in signers {
+        let cells = [s.blocks, s.ops].map(|n| n.to_string());
+        table.row(vec![out.id(&s.pubkey).to_string(), cells[0].clone(), cells[1].clone(), format!("#{}", s.first_block), format!("#{}", s.last_block)]);
+    }
+    table.print(out);
+}
+
+fn print_signed_blocks(chain: &Chain, pubkey: &str, out: Output) {
+    let blocks: Vec<&Block> = chain.blocks_signed_by(pubkey).collect();
+    if out.json {
+        out.print_json(&blocks);
+    } else if blocks.is_empty() {
+        println!("(no blocks signed by {pubkey})");
+    } else {
+        print_blocks(blocks, out);
+    }
+}
+
+/* ---------------- External Signers ---------------- */
+
+/// The key blocks are signed with: an in-process `SigningKey` or an external signer.
+type SharedSigner = Arc<Mutex<Option<Arc<dyn Signer>>>>;
+
+/// Bearer token sent to HTTP signers, if set
+const SIGNER_TOKEN_ENV: &str = "CHAIN_KV_SIGNER_TOKEN";
+
+fn parse_pubkey(hex_str: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = hex::decode(hex_str.trim())
+        .map_err(|_| "bad pubkey hex")?
+        .try_into()
+        .map_err(|_| "public key must be 32 bytes")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| "bad pubkey bytes".into())
+}
+
+fn parse_signature(hex_str: &str) -> Result<Signature, String> {
+    let bytes = hex::decode(hex_str.trim()).map_err(|_| "bad signature hex")?;
+    Signature::try_from(&bytes[..]).map_err(|_| "signature must be 64 bytes".into())
+}
+
+/// Signs by running a program, so the private key stays with that program (a token
+/// CLI, an HSM client, `ssh` to another machine…).
+///
+/// `<program> [args…] pubkey` must print the hex public key and
+/// `<program> [args…] sign <message>` the hex Ed25519 signature over the message, which
+/// is passed as hex since it is binary.
+struct CommandSigner {
+    program: String,
+    args: Vec<String>,
+    pubkey: VerifyingKey,
+}
+
+impl CommandSigner {
+    fn new(program: &str, args: &[&str]) -> Result<Self, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let pubkey = parse_pubkey(&Self::run(program, &args, &["pubkey"])?)?;
+        Ok(Self { program: program.to_string(), args, pubkey })
+    }
+
+    fn run(program: &str, args: &[String], extra: &[&str]) -> Result<String, String> {
+        let out = Command::new(program)
+            .args(args)
+            .args(extra)
+            .output()
+            .map_err(|e| format!("cannot run {program}: {e}"))?;
+        if !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            return Err(match stderr.trim() {
+                "" => format!("{program} exited with {}", out.status),
+                msg => format!("{program} exited with {}: {msg}", out.status),
+            });
+        }
+        String::from_utf8(out.stdout).map_err(|_| format!("{program} printed non-UTF-8 output"))
+    }
+}
+
+impl Signer for CommandSigner {
+    fn verifying_key(&self) -> VerifyingKey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+        parse_signature(&Self::run(&self.program, &self.args, &["sign", &hex::encode(message)])?)
+    }
+}
+
+#[derive(Deserialize)]
+struct SignerPubkeyResp { pubkey: String }
+
+/// `message` is the hex of the bytes to sign
+#[derive(Serialize)]
+struct SignReq { message: String }
+
+#[derive(Deserialize)]
+struct SignResp { signature: String }
+
+/// Signs through a signing service: `GET <url>/pubkey` → `{"pubkey": hex}`,
+/// `POST <url>/sign {"message": hex}` → `{"signature": hex}`.
+struct HttpSigner {
+    client: reqwest::Client,
+    url: String,
+    pubkey: VerifyingKey,
+}
+
+impl HttpSigner {
+    async fn connect(url: &str) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let url = url.trim_end_matches('/').to_string();
+        let resp: SignerPubkeyResp = Self::authorized(client.get(format!("{url}/pubkey")))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("signer unreachable: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("bad pubkey response: {e}"))?;
+        Ok(Self { pubkey: parse_pubkey(&resp.pubkey)?, client, url })
+    }
+
+    fn authorized(req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match std::env::var(SIGNER_TOKEN_ENV) {
+            Ok(token) => req.bearer_auth(token),
+            Err(_) => req,
         }
-        hashes = next;
     }
-    hashes[0].clone()
+
+    async fn request_signature(&self, message: &[u8]) -> Result<Signature, String> {
+        let req = SignReq { message: hex::encode(message) };
+        let resp: SignResp = Self::authorized(self.client.post(format!("{}/sign", self.url)).json(&req))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("signer request failed: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("bad sign response: {e}"))?;
+        parse_signature(&resp.signature)
+    }
+}
+
+impl Signer for HttpSigner {
+    fn verifying_key(&self) -> VerifyingKey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+        // Mining is synchronous; park this worker while the request runs on the runtime.
+        task::block_in_place(|| tokio::runtime::Handle::current().block_on(self.request_signature(message)))
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Block {
+/* ---------------- RPC Types ---------------- */
+
+#[derive(Deserialize)]
+struct SetReq { key: String, value: String, #[serde(default)] meta: OpMeta }
+
+#[derive(Deserialize)]
+struct DelReq { key: String, #[serde(default)] meta: OpMeta }
+
+#[derive(Deserialize)]
+struct DifficultyReq { n: usize }
+
+#[derive(Deserialize)]
+struct WorkQuery { size: Option<u64> }
+
+/// A candidate block and the slice of nonces one `/mining/work` caller should try
+#[derive(Serialize)]
+struct WorkResp {
+    job: u64,
     index: u64,
+    /// When the range was handed out; sent back with a solution
     timestamp: i64,
-    ops: Vec<Op>,
     prev_hash: String,
     merkle_root: String,
+    /// Hex of the header bytes hashed ahead of the nonce, in the chain's hash scheme
+    header: String,
+    difficulty: usize,
+    /// First nonce of the range, inclusive
+    nonce_start: u64,
+    /// End of the range, exclusive
+    nonce_end: u64,
+}
+
+#[derive(Deserialize)]
+struct FoundReq { job: u64, nonce: u64, timestamp: i64, worker: Option<String> }
+
+#[derive(Serialize)]
+struct VerifyResp { ok: bool, error: Option<String> }
+
+#[derive(Deserialize)]
+struct BlocksQuery { from: Option<u64>, limit: Option<usize> }
+
+#[derive(Serialize, Deserialize)]
+struct BlocksResp {
+    difficulty: usize,
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(default)]
+    chain_id: String,
+    blocks: Vec<Block>,
+}
+
+/// Format of a primary that predates chain ids
+fn legacy_version() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct TipQuery { wait: Option<u64>, since: Option<String> }
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct TipResp { index: u64, hash: String }
+
+/// One change to a key, as listed by `/history/{key}`
+#[derive(Serialize)]
+struct HistoryEntry { block: u64, timestamp: i64, signer: Option<String>, op: Op }
+
+impl TipResp {
+    fn of(chain: &Chain) -> Self {
+        Self { index: chain.next_index().saturating_sub(1), hash: chain.last_hash() }
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    chain: SharedChain,
+    signer: SharedSigner,
+    follower: Arc<Mutex<Option<Follower>>>,
+    idempotency: Arc<IdempotencyCache>,
+    work: Arc<Mutex<MiningJobs>>,
+}
+
+impl AppState {
+    /// Returns the rejection message for mutating RPCs while this node is a read-only replica.
+    fn read_only(&self) -> Option<Json<String>> {
+        replica_of(&self.follower).map(|url| Json(format!("read-only replica following {url}")))
+    }
+}
+
+/* ---------------- Chain Access ---------------- */
+
+/// The chain, shared by the REPL, the RPC server and the replica follower.
+///
+/// Reads share the lock. Mining runs without it and only takes it exclusively to
+/// append the finished block, so `/get`, `/state` and friends never wait on a mine.
+type SharedChain = Arc<ChainHandle>;
+
+/// The chain behind its lock, plus a channel announcing every new tip to `/tip` long polls
+/// and the progress of the latest mine for the dashboard.
+struct ChainHandle {
+    chain: RwLock<Chain>,
+    tip: watch::Sender<TipResp>,
+    mining: Arc<Mutex<Option<MiningStatus>>>,
+    /// How the last block mined here went, for the event log
+    mined: Mutex<Option<MinedStats>>,
+    events: Mutex<Option<EventLog>>,
+}
+
+/// A mine in flight, as of its last progress report
+#[derive(Clone)]
+struct MiningStatus {
+    index: u64,
     nonce: u64,
+    hps: f64,
+    last: String,
+    started: Instant,
+}
+
+impl ChainHandle {
+    fn new(chain: Chain) -> Self {
+        let (tip, _) = watch::channel(TipResp::of(&chain));
+        Self { chain: RwLock::new(chain), tip, mining: Arc::default(), mined: Mutex::default(), events: Mutex::default() }
+    }
+
+    async fn read(&self) -> RwLockReadGuard<'_, Chain> {
+        self.chain.read().await
+    }
+
+    async fn write(&self) -> ChainWriteGuard<'_> {
+        ChainWriteGuard { chain: self.chain.write().await, handle: self }
+    }
+
+    fn subscribe(&self) -> watch::Receiver<TipResp> {
+        self.tip.subscribe()
+    }
+}
+
+/// Exclusive access to the chain that announces the tip on release if it moved, so
+/// every way of extending or replacing the chain wakes `/tip` waiters and reaches the
+/// event log.
+struct ChainWriteGuard<'a> {
+    chain: RwLockWriteGuard<'a, Chain>,
+    handle: &'a ChainHandle,
+}
+
+impl Deref for ChainWriteGuard<'_> {
+    type Target = Chain;
+
+    fn deref(&self) -> &Chain {
+        &self.chain
+    }
+}
+
+impl DerefMut for ChainWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Chain {
+        &mut self.chain
+    }
+}
+
+impl Drop for ChainWriteGuard<'_> {
+    fn drop(&mut self) {
+        let tip = TipResp::of(&self.chain);
+        let mut previous = None;
+        self.handle.tip.send_if_modified(|current| {
+            let moved = *current != tip;
+            if moved {
+                previous = Some(std::mem::replace(current, tip));
+            }
+            moved
+        });
+        if let Some(previous) = previous {
+            self.handle.log_appended(&self.chain, &previous);
+        }
+    }
+}
+
+/// Mine `ops` on the current tip off the async runtime, then append the block; if
+/// another block landed meanwhile, mine again on the new tip.
+async fn mine_and_append(chain: &ChainHandle, ops: Vec<Op>, signer: Arc<dyn Signer>, show_progress: bool) -> Result<Block, String> {
+    loop {
+        let next = chain.read().await.next_block();
+        let job = (next.clone(), ops.clone(), signer.clone(), chain.mining.clone());
+        let blk = task::spawn_blocking(move || {
+            let (next, ops, signer, status) = job;
+            let started = Instant::now();
+            let report = |nonce: u64, cand: &str, hps: f64| {
+                *status.lock().unwrap() = Some(MiningStatus { index: next.index, nonce, hps, last: cand.to_string(), started });
+                if show_progress {
+                    print_mining_progress(nonce, cand, hps);
+                }
+            };
+            *status.lock().unwrap() = Some(MiningStatus { index: next.index, nonce: 0, hps: 0.0, last: String::new(), started });
+            let mined = next.mine(ops, signer.as_ref(), Some(&report));
+            let last = status.lock().unwrap().take();
+            mined.map(|blk| {
+                let stats = last.map(|m| MinedStats { hash: blk.hash.clone(), secs: started.elapsed().as_secs_f64(), hps: m.hps });
+                (blk, stats)
+            })
+        })
+        .await
+        .map_err(|e| format!("mining task failed: {e}"))??;
+        let (blk, stats) = blk;
+        *chain.mined.lock().unwrap() = stats;
+
+        let mut chain = chain.write().await;
+        if chain.next_block() == next {
+            chain.append_verified(blk.clone())?;
+            return Ok(blk);
+        }
+    }
+}
+
+/// [`Chain::preview_batch`], also flagging that no key is loaded to sign with
+fn batch_preview(chain: &Chain, signer_pubkey: Option<&str>) -> Result<BatchPreview, String> {
+    let mut preview = chain.preview_batch(signer_pubkey)?;
+    if signer_pubkey.is_none() {
+        preview.violations.push("no signing key loaded".into());
+    }
+    Ok(preview)
+}
+
+/// Mine the open batch as one block; the batch is reopened if that fails
+async fn commit_batch(chain: &ChainHandle, signer: Arc<dyn Signer>, show_progress: bool) -> Result<Block, String> {
+    let ops = chain.write().await.take_batch()?;
+    let mined = mine_and_append(chain, ops.clone(), signer, show_progress).await;
+    if mined.is_err() {
+        chain.write().await.restore_batch(ops);
+    }
+    mined
+}
+
+/* ---------------- Event Log ---------------- */
+
+/// Size at which the event log is rotated unless `events` is told otherwise
+const DEFAULT_EVENTS_MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// Rotated event logs kept (`<file>.1` is the newest) unless `events` is told otherwise
+const DEFAULT_EVENTS_KEEP: usize = 5;
+/// Keys listed per block record; the op counts still cover the whole block
+const EVENT_KEYS: usize = 20;
+
+/// Time and rate of a mine that finished here, matched to its block by hash
+struct MinedStats {
     hash: String,
-    signature: Option<String>,     // hex-encoded signature over `hash`
-    signer_pubkey: Option<String>, // hex-encoded 32-byte pubkey
-}
-
-impl Block {
-    fn compute_hash(index: u64, timestamp: i64, merkle_root: &str, prev_hash: &str, nonce: u64) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(index.to_le_bytes());
-        hasher.update(timestamp.to_le_bytes());
-        hasher.update(merkle_root.as_bytes());
-        hasher.update(prev_hash.as_bytes());
-        hasher.update(nonce.to_le_bytes());
-        hex::encode(hasher.finalize())
-    }
-
-    fn mine_with_progress<F: Fn(u64, &str, f64)>(
-        index: u64,
-        timestamp: i64,
-        merkle_root: &str,
-        prev_hash: &str,
-        difficulty: usize,
-        progress: Option<F>,
-    ) -> (u64, String) {
-        let target = "0".repeat(difficulty);
-        let start = Instant::now();
-        let mut last_report = Instant::now();
-        let mut nonce = 0u64;
-
-        loop {
-            let candidate = Self::compute_hash(index, timestamp, merkle_root, prev_hash, nonce);
-            if candidate.starts_with(&target) {
-                // final progress report
-                if let Some(ref cb) = progress {
-                    let elapsed = start.elapsed().as_secs_f64();
-                    let hps = (nonce as f64 + 1.0) / elapsed.max(1e-6);
-                    cb(nonce, &candidate, hps);
-                }
-                return (nonce, candidate);
-            }
-            nonce = nonce.wrapping_add(1);
-
-            if let Some(ref cb) = progress {
-                if last_report.elapsed() >= Duration::from_millis(500) {
-                    let elapsed = start.elapsed().as_secs_f64();
-                    let hps = (nonce as f64 + 1.0) / elapsed.max(1e-6);
-                    cb(nonce, &candidate, hps);
-                    last_report = Instant::now();
-                }
-            }
-        }
-    }
-
-    fn new(
-        index: u64,
-        ops: Vec<Op>,
-        prev_hash: String,
-        difficulty: usize,
-        keypair: &SigningKey,
-        with_progress: bool,
-    ) -> Self {
-        let timestamp = Utc::now().timestamp();
-        let merkle_root = merkle_root(&ops);
-
-        let (nonce, hash) = if with_progress {
-            Self::mine_with_progress(
-                index,
-                timestamp,
-                &merkle_root,
-                &prev_hash,
-                difficulty,
-                Some(|nonce, cand: &str, hps| {
-                    eprint!("\r⛏️  mining… nonce={:<12} rate={:.0} H/s last={}", nonce, hps, &cand[..8]);
-                }),
-            )
-        } else {
-            Self::mine_with_progress(index, timestamp, &merkle_root, &prev_hash, difficulty, Option::<fn(u64, &str, f64)>::None)
-        };
-        eprintln!();
+    secs: f64,
+    hps: f64,
+}
 
-        let sig = keypair.sign(hash.as_bytes());
-        let sig_hex = hex::encode(sig.to_bytes());
-        let pubkey_hex = hex::encode(keypair.verifying_key().to_bytes());
+/// One line of the event log, written for every block appended to the chain
+#[derive(Serialize)]
+struct BlockEvent<'a> {
+    height: u64,
+    hash: &'a str,
+    prev_hash: &'a str,
+    timestamp: i64,
+    signer: Option<&'a str>,
+    ops: OpCounts,
+    keys: Vec<&'a str>,
+    mining: MiningEvent,
+}
 
+#[derive(Default, Serialize)]
+struct OpCounts {
+    put: usize,
+    del: usize,
+    rotate: usize,
+}
+
+/// How the block was mined; the time and rate are only known for blocks mined by this process
+#[derive(Serialize)]
+struct MiningEvent {
+    nonce: u64,
+    difficulty: usize,
+    secs: Option<f64>,
+    hps: Option<f64>,
+}
+
+impl<'a> BlockEvent<'a> {
+    fn of(blk: &'a Block, difficulty: usize, mined: Option<&MinedStats>) -> Self {
+        let mut ops = OpCounts::default();
+        let mut keys = Vec::new();
+        for op in &blk.ops {
+            let key = match op {
+                Op::Put { key, .. } => {
+                    ops.put += 1;
+                    key
+                }
+                Op::Del { key, .. } => {
+                    ops.del += 1;
+                    key
+                }
+                Op::Rotate { .. } => {
+                    ops.rotate += 1;
+                    continue;
+                }
+            };
+            keys.push(key.as_str());
+        }
+        keys.truncate(EVENT_KEYS);
+        let mined = mined.filter(|m| m.hash == blk.hash);
         Self {
-            index,
-            timestamp,
+            height: blk.index,
+            hash: &blk.hash,
+            prev_hash: &blk.prev_hash,
+            timestamp: blk.timestamp,
+            signer: blk.signer_pubkey.as_deref(),
             ops,
-            prev_hash,
-            merkle_root,
-            nonce,
-            hash,
-            signature: Some(sig_hex),
-            signer_pubkey: Some(pubkey_hex),
+            keys,
+            mining: MiningEvent { nonce: blk.nonce, difficulty, secs: mined.map(|m| m.secs), hps: mined.map(|m| m.hps) },
         }
     }
+}
 
-    fn verify(&self, prev_hash: &str, difficulty: usize) -> Result<(), String> {
-        if self.prev_hash != prev_hash {
-            return Err("prev_hash mismatch".into());
-        }
-        let recomputed = Self::compute_hash(self.index, self.timestamp, &self.merkle_root, &self.prev_hash, self.nonce);
-        if recomputed != self.hash {
-            return Err("hash mismatch".into());
+/// Append-only JSONL file of [`BlockEvent`]s, rotated to `<path>.1` … `<path>.<keep>` when it
+/// would grow past `max_bytes`.
+struct EventLog {
+    path: String,
+    file: fs::File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl EventLog {
+    /// `events <file> [--max-mb <n>] [--keep <n>]`
+    fn parse(args: &[&str]) -> Result<Self, String> {
+        let (mut path, mut max_bytes, mut keep) = (None, DEFAULT_EVENTS_MAX_BYTES, DEFAULT_EVENTS_KEEP);
+        let mut it = args.iter();
+        while let Some(&arg) = it.next() {
+            match arg {
+                "--max-mb" => {
+                    let mb = it.next().ok_or("--max-mb needs a size in MiB")?;
+                    max_bytes = mb.parse::<u64>().ok().filter(|&mb| mb > 0).ok_or(format!("bad size: {mb}"))? * 1024 * 1024;
+                }
+                "--keep" => {
+                    let n = it.next().ok_or("--keep needs a number of files")?;
+                    keep = n.parse().map_err(|_| format!("bad file count: {n}"))?;
+                }
+                file if !file.starts_with("--") && path.is_none() => path = Some(file),
+                other => return Err(format!("unexpected events argument: {other}")),
+            }
         }
-        if !self.hash.starts_with(&"0".repeat(difficulty)) {
-            return Err("insufficient PoW".into());
+        let path = path.ok_or("usage: events <file> [--max-mb <n>] [--keep <n>] | events off")?;
+        Self::open(path, max_bytes, keep).map_err(|e| format!("cannot open {path}: {e}"))
+    }
+
+    fn open(path: &str, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path: path.to_string(), file, size, max_bytes, keep })
+    }
+
+    fn append(&mut self, event: &BlockEvent) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
         }
-        if let (Some(sig_hex), Some(pub_hex)) = (&self.signature, &self.signer_pubkey) {
-            let sig_bytes = hex::decode(sig_hex).map_err(|_| "bad signature hex")?;
-            if sig_bytes.len() != 64 {
-                return Err("signature must be 64 bytes".into());
-            }
-            let mut sig_array = [0u8; 64];
-            sig_array.copy_from_slice(&sig_bytes);
-            let sig = Signature::try_from(&sig_array[..]).map_err(|_| "bad signature bytes")?;
-            let pk_bytes = hex::decode(pub_hex).map_err(|_| "bad pubkey hex")?;
-            if pk_bytes.len() != 32 {
-                return Err("public key must be 32 bytes".into());
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for i in (1..self.keep).rev() {
+                let older = format!("{}.{i}", self.path);
+                if FsPath::new(&older).exists() {
+                    fs::rename(&older, format!("{}.{}", self.path, i + 1))?;
+                }
             }
-            let mut pk_array = [0u8; 32];
-            pk_array.copy_from_slice(&pk_bytes);
-            let pk = VerifyingKey::from_bytes(&pk_array).map_err(|_| "bad pubkey bytes")?;
-            pk.verify(self.hash.as_bytes(), &sig).map_err(|_| "signature verify failed")?;
+            fs::rename(&self.path, format!("{}.1", self.path))?;
+            self.file = fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
         }
+        self.size = 0;
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Chain {
-    blocks: Vec<Block>,
-    difficulty: usize,
-    // batching
-    batch_active: bool,
-    batch_ops: Vec<Op>,
-}
-
-impl Chain {
-    fn genesis(difficulty: usize) -> Self {
-        let genesis = Block {
-            index: 0,
-            timestamp: 0,
-            ops: vec![Op::Put { key: "__genesis__".into(), value: "ok".into() }],
-            prev_hash: "0".into(),
-            merkle_root: "GENESIS".into(),
-            nonce: 0,
-            hash: "GENESIS".into(),
-            signature: None,
-            signer_pubkey: None,
+impl ChainHandle {
+    /// Write a record for every block past `previous`, if the event log is on. A tip that does
+    /// not descend from `previous` (a loaded chain) is a replacement, not an append, and is skipped.
+    fn log_appended(&self, chain: &Chain, previous: &TipResp) {
+        let mut events = self.events.lock().unwrap();
+        let Some(log) = events.as_mut() else {
+            return;
         };
-        Self {
-            blocks: vec![genesis],
-            difficulty,
-            batch_active: false,
-            batch_ops: Vec::new(),
+        let start = previous.index as usize;
+        if chain.blocks.get(start).is_none_or(|b| b.hash != previous.hash) {
+            return;
+        }
+        let mined = self.mined.lock().unwrap();
+        for blk in &chain.blocks[start + 1..] {
+            if let Err(e) = log.append(&BlockEvent::of(blk, chain.difficulty, mined.as_ref())) {
+                eprintln!("⚠️ event log {}: {e}", log.path);
+            }
         }
     }
+}
 
-    fn last_hash(&self) -> String {
-        self.blocks.last().map(|b| b.hash.clone()).unwrap_or_else(|| "0".into())
-    }
+/* ---------------- Replica ---------------- */
 
-    fn next_index(&self) -> u64 {
-        self.blocks.last().map(|b| b.index + 1).unwrap_or(0)
-    }
+const BLOCKS_PAGE_LIMIT: usize = 500;
 
-    fn append_signed(&mut self, ops: Vec<Op>, keypair: &SigningKey, with_progress: bool) {
-        let blk = Block::new(self.next_index(), ops, self.last_hash(), self.difficulty, keypair, with_progress);
-        println!("✅ mined block {} (nonce {})", blk.index, blk.nonce);
-        self.blocks.push(blk);
-    }
+struct Follower {
+    url: String,
+    handle: task::JoinHandle<()>,
+}
 
-    fn materialize(&self) -> HashMap<String, String> {
-        let mut state = HashMap::new();
-        for b in &self.blocks {
-            for op in &b.ops {
-                match op {
-                    Op::Put { key, value } => {
-                        if key != "__genesis__" {
-                            state.insert(key.clone(), value.clone());
-                        }
-                    }
-                    Op::Del { key } => {
-                        state.remove(key);
-                    }
-                }
+fn replica_of(follower: &Mutex<Option<Follower>>) -> Option<String> {
+    follower.lock().unwrap().as_ref().map(|f| f.url.clone())
+}
+
+/// Pull every block the primary has beyond our tip, verifying each before appending.
+async fn sync_from_primary(client: &reqwest::Client, url: &str, chain: &ChainHandle) -> Result<usize, String> {
+    let mut appended = 0;
+    loop {
+        let from = chain.read().await.next_index();
+        let resp: BlocksResp = client
+            .get(format!("{url}/blocks"))
+            .query(&[("from", from as usize), ("limit", BLOCKS_PAGE_LIMIT)])
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("fetch error: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("bad /blocks response: {e}"))?;
+
+        let fetched = resp.blocks.len();
+        let mut chain = chain.write().await;
+        if (chain.version, chain.chain_id.as_str()) != (resp.version, resp.chain_id.as_str()) {
+            // a fresh replica takes on the primary's chain id; one with blocks never does
+            if chain.blocks.len() > 1 {
+                return Err(format!(
+                    "primary is chain {:?} (format v{}), this one is {:?} (format v{})",
+                    resp.chain_id, resp.version, chain.chain_id, chain.version
+                ));
             }
+            chain.version = resp.version;
+            chain.chain_id = resp.chain_id;
+        }
+        chain.difficulty = resp.difficulty;
+        for blk in resp.blocks {
+            chain.append_verified(blk)?;
+            appended += 1;
+        }
+        if fetched < BLOCKS_PAGE_LIMIT {
+            return Ok(appended);
         }
-        state
     }
+}
 
-    fn verify_all(&self) -> Result<(), String> {
-        if self.blocks.is_empty() {
-            return Err("empty chain".into());
+/// Build the HTTP client for a primary given as `http(s)://host:port` or `unix:/path/to.sock`.
+fn primary_client(target: &str) -> Result<(reqwest::Client, String), String> {
+    match target.strip_prefix("unix:") {
+        Some(path) => {
+            let client = reqwest::Client::builder()
+                .unix_socket(path)
+                .build()
+                .map_err(|e| format!("client error: {e}"))?;
+            // the host part is ignored when talking over a socket
+            Ok((client, "http://localhost".to_string()))
         }
-        for i in 1..self.blocks.len() {
-            let prev = &self.blocks[i - 1];
-            let curr = &self.blocks[i];
-            curr.verify(&prev.hash, self.difficulty)?;
+        None => Ok((reqwest::Client::new(), target.to_string())),
+    }
+}
+
+async fn follow_loop(target: String, interval: Duration, chain: SharedChain) {
+    let (client, url) = match primary_client(&target) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("❌ follow {target}: {e}");
+            return;
         }
-        Ok(())
+    };
+    loop {
+        match sync_from_primary(&client, &url, &chain).await {
+            Ok(0) => {}
+            Ok(n) => println!("📡 replicated {n} block(s) from {target}"),
+            Err(e) => println!("❌ follow {target}: {e}"),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/* ---------------- Idempotency ---------------- */
+
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on a write answered from the idempotency cache rather than mined
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+const BLOCK_INDEX: &str = "block-index";
+const BLOCK_HASH: &str = "block-hash";
+
+/// How long an `Idempotency-Key` is remembered unless `serve --idempotency-window` says otherwise
+const DEFAULT_IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(600);
+
+/// The block a write mined
+#[derive(Debug, Clone)]
+struct Written {
+    index: u64,
+    hash: String,
+}
+
+/// Writes by `Idempotency-Key`, so a retried `/set` or `/del` gets the block the first
+/// attempt mined instead of mining another.
+///
+/// Only successes are kept: a write that failed mined nothing, and its retry runs again.
+/// A retry arriving while the first attempt still mines waits for it.
+struct IdempotencyCache {
+    window: Duration,
+    entries: Mutex<HashMap<String, IdempotencyEntry>>,
+}
+
+struct IdempotencyEntry {
+    first_seen: Instant,
+    /// The ops requested, to refuse a key reused for a different write
+    ops: Vec<Op>,
+    written: Arc<OnceCell<Written>>,
+}
+
+impl IdempotencyCache {
+    fn new(window: Duration) -> Self {
+        Self { window, entries: Mutex::new(HashMap::new()) }
     }
 
-    fn save(&self, path: &str) -> io::Result<()> {
-        let s = serde_json::to_string_pretty(self).unwrap();
-        fs::write(path, s)
+    /// The slot holding (or about to hold) the result of the write `key` names
+    fn slot(&self, key: &str, ops: &[Op]) -> Result<Arc<OnceCell<Written>>, String> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, e| e.first_seen.elapsed() < self.window);
+        let entry = entries.entry(key.to_string()).or_insert_with(|| IdempotencyEntry {
+            first_seen: Instant::now(),
+            ops: ops.to_vec(),
+            written: Arc::new(OnceCell::new()),
+        });
+        if entry.ops != ops {
+            return Err(format!("Idempotency-Key {key:?} was already used for a different request"));
+        }
+        Ok(entry.written.clone())
     }
+}
+
+/// Mine `ops` for a write RPC, or replay the block mined for the same `Idempotency-Key`.
+///
+/// The body stays `"ok"` (or the error); the block lands in `Block-Index`/`Block-Hash`.
+async fn idempotent_write(state: &AppState, headers: &HeaderMap, ops: Vec<Op>) -> Response {
+    let Some(key) = headers.get(IDEMPOTENCY_KEY).and_then(|v| v.to_str().ok()) else {
+        return written_response(mine_write(state, ops).await, false);
+    };
+    let slot = match state.idempotency.slot(key, &ops) {
+        Ok(slot) => slot,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(e)).into_response(),
+    };
+    let mut replayed = true;
+    let result = slot
+        .get_or_try_init(|| {
+            replayed = false;
+            mine_write(state, ops)
+        })
+        .await
+        .cloned();
+    written_response(result, replayed)
+}
+
+async fn mine_write(state: &AppState, ops: Vec<Op>) -> Result<Written, String> {
+    let maybe_signer = state.signer.lock().unwrap().clone();
+    let Some(signer) = maybe_signer else {
+        return Err("no signing key loaded".into());
+    };
+    // mine without chatty progress in HTTP
+    let blk = mine_and_append(&state.chain, ops, signer, false).await.map_err(|e| format!("error: {e}"))?;
+    report_mined(&blk);
+    Ok(Written { index: blk.index, hash: blk.hash })
+}
 
-    fn load(path: &str) -> io::Result<Self> {
-        let s = fs::read_to_string(path)?;
-        let c: Chain = serde_json::from_str(&s)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("parse error: {e}")))?;
-        Ok(c)
+fn written_response(result: Result<Written, String>, replayed: bool) -> Response {
+    let written = match result {
+        Ok(written) => written,
+        Err(e) => return Json(e).into_response(),
+    };
+    let mut resp = Json("ok").into_response();
+    let headers = resp.headers_mut();
+    headers.insert(BLOCK_INDEX, HeaderValue::from(written.index));
+    if let Ok(hash) = HeaderValue::from_str(&written.hash) {
+        headers.insert(BLOCK_HASH, hash);
+    }
+    if replayed {
+        headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
     }
+    resp
+}
+
+/* ---------------- RPC Server ---------------- */
+
+/// Options for `serve`, parsed from its flags.
+#[derive(Debug, Clone)]
+struct ServeConfig {
+    /// TCP port; `None` when serving only on a Unix socket.
+    port: Option<u16>,
+    /// Unix domain socket path, served in addition to (or instead of) TCP.
+    unix: Option<String>,
+    /// Dev mode: any origin may call the RPC from a browser.
+    dev: bool,
+    /// Origins allowed outside dev mode; empty means no cross-origin access.
+    cors_origins: Vec<String>,
+    cors_methods: Vec<Method>,
+    tls: Option<TlsSource>,
+    /// How long `/set` and `/del` remember an `Idempotency-Key`
+    idempotency_window: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum TlsSource {
+    /// PEM certificate chain and private key on disk.
+    Files { cert: String, key: String },
+    /// Throwaway certificate for localhost, generated at startup.
+    SelfSigned,
+}
 
-    // batching
-    fn begin_batch(&mut self) -> Result<(), String> {
-        if self.batch_active {
-            return Err("batch already active".into());
+impl ServeConfig {
+    fn parse(args: &[&str]) -> Result<Self, String> {
+        let mut cfg = ServeConfig {
+            port: None,
+            unix: None,
+            dev: false,
+            cors_origins: Vec::new(),
+            cors_methods: vec![Method::GET, Method::POST],
+            tls: None,
+            idempotency_window: DEFAULT_IDEMPOTENCY_WINDOW,
+        };
+        let (mut cert, mut key) = (None, None);
+        let mut it = args.iter();
+        while let Some(&arg) = it.next() {
+            match arg {
+                "--dev" => cfg.dev = true,
+                "--cors-origins" => {
+                    let list = it.next().ok_or("--cors-origins needs a comma-separated list")?;
+                    cfg.cors_origins = list.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect();
+                }
+                "--cors-methods" => {
+                    let list = it.next().ok_or("--cors-methods needs a comma-separated list")?;
+                    cfg.cors_methods = list
+                        .split(',')
+                        .map(|m| m.trim().to_ascii_uppercase().parse::<Method>().map_err(|_| format!("bad method: {m}")))
+                        .collect::<Result<_, _>>()?;
+                }
+                "--tls-cert" => cert = Some(it.next().ok_or("--tls-cert needs a PEM file")?.to_string()),
+                "--tls-key" => key = Some(it.next().ok_or("--tls-key needs a PEM file")?.to_string()),
+                "--tls-self-signed" => cfg.tls = Some(TlsSource::SelfSigned),
+                "--unix" => cfg.unix = Some(it.next().ok_or("--unix needs a socket path")?.to_string()),
+                "--idempotency-window" => {
+                    let secs = it.next().ok_or("--idempotency-window needs a number of seconds")?;
+                    let secs: u64 = secs.parse().map_err(|_| format!("bad idempotency window: {secs}"))?;
+                    cfg.idempotency_window = Duration::from_secs(secs);
+                }
+                port if !port.starts_with("--") => cfg.port = Some(port.parse().map_err(|_| format!("bad port: {port}"))?),
+                other => return Err(format!("unknown serve flag: {other}")),
+            }
         }
-        self.batch_active = true;
-        self.batch_ops.clear();
-        Ok(())
+        match (cert, key) {
+            (Some(cert), Some(key)) => cfg.tls = Some(TlsSource::Files { cert, key }),
+            (None, None) => {}
+            _ => return Err("--tls-cert and --tls-key must be given together".into()),
+        }
+        if cfg.port.is_none() && cfg.unix.is_none() {
+            cfg.port = Some(3000);
+        }
+        if cfg.tls.is_some() && cfg.port.is_none() {
+            return Err("TLS needs a TCP port; the Unix socket is always plaintext".into());
+        }
+        Ok(cfg)
     }
-    fn add_put(&mut self, key: String, value: String) -> Result<(), String> {
-        if !self.batch_active {
-            return Err("no active batch".into());
+
+    async fn rustls_config(&self) -> Result<Option<RustlsConfig>, String> {
+        let config = match &self.tls {
+            None => return Ok(None),
+            Some(TlsSource::Files { cert, key }) => RustlsConfig::from_pem_file(cert, key)
+                .await
+                .map_err(|e| format!("tls config error: {e}"))?,
+            Some(TlsSource::SelfSigned) => {
+                let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])
+                    .map_err(|e| format!("self-signed cert error: {e}"))?;
+                RustlsConfig::from_pem(cert.cert.pem().into_bytes(), cert.key_pair.serialize_pem().into_bytes())
+                    .await
+                    .map_err(|e| format!("tls config error: {e}"))?
+            }
+        };
+        Ok(Some(config))
+    }
+
+    /// Permissive in dev mode; otherwise only the configured origins and methods are allowed.
+    fn cors_layer(&self) -> Result<CorsLayer, String> {
+        if self.dev {
+            return Ok(CorsLayer::permissive());
         }
-        self.batch_ops.push(Op::Put { key, value });
-        Ok(())
+        let origins = self
+            .cors_origins
+            .iter()
+            .map(|o| HeaderValue::from_str(o).map_err(|_| format!("bad origin: {o}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods(self.cors_methods.clone())
+            .allow_headers([header::CONTENT_TYPE, header::IF_NONE_MATCH, HeaderName::from_static(IDEMPOTENCY_KEY)])
+            .expose_headers([
+                header::ETAG,
+                HeaderName::from_static(BLOCK_INDEX),
+                HeaderName::from_static(BLOCK_HASH),
+                HeaderName::from_static(IDEMPOTENT_REPLAYED),
+            ]))
     }
-    fn add_del(&mut self, key: String) -> Result<(), String> {
-        if !self.batch_active {
-            return Err("no active batch".into());
+}
+
+/// Spawn the TCP and/or Unix socket listeners in the background.
+async fn start_server(cfg: ServeConfig, state: AppState) -> Result<(), String> {
+    let cors = cfg.cors_layer()?;
+    let tls = cfg.rustls_config().await?;
+    let app = router(state, cors).await;
+
+    if let Some(path) = &cfg.unix {
+        // a socket file left behind by a previous run would make bind fail
+        if FsPath::new(path).exists() {
+            fs::remove_file(path).map_err(|e| format!("cannot remove stale socket {path}: {e}"))?;
         }
-        self.batch_ops.push(Op::Del { key });
-        Ok(())
+        let listener = tokio::net::UnixListener::bind(path).map_err(|e| format!("cannot bind {path}: {e}"))?;
+        println!("🧦 serving on unix socket {path}");
+        let app = app.clone();
+        task::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
     }
-    fn abort_batch(&mut self) {
-        self.batch_active = false;
-        self.batch_ops.clear();
+
+    let Some(port) = cfg.port else {
+        return Ok(());
+    };
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    if cfg.dev {
+        println!("🌐 starting server on {scheme}://0.0.0.0:{port} (dev mode: CORS allows any origin)");
+    } else {
+        println!("🌐 starting server on {scheme}://0.0.0.0:{port} (CORS origins: {:?})", cfg.cors_origins);
     }
-    fn commit_batch(&mut self, keypair: &SigningKey, with_progress: bool) -> Result<usize, String> {
-        if !self.batch_active {
-            return Err("no active batch".into());
-        }
-        let count = self.batch_ops.len();
-        let ops = std::mem::take(&mut self.batch_ops);
-        self.batch_active = false;
-        self.append_signed(ops, keypair, with_progress);
-        Ok(count)
+    if matches!(cfg.tls, Some(TlsSource::SelfSigned)) {
+        println!("🔏 using a freshly generated self-signed certificate for localhost; clients will need to trust it");
     }
+    // run server in background task
+    task::spawn(async move {
+        let addr = std::net::SocketAddr::from((std::net::Ipv4Addr::new(0, 0, 0, 0), port));
+        match tls {
+            Some(tls) => {
+                axum_server::bind_rustls(addr, tls).serve(app.into_make_service()).await.ok();
+            }
+            None => {
+                let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+                axum::serve(listener, app).await.ok();
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn router(state: AppState, cors: CorsLayer) -> Router {
+    Router::new()
+        .route("/", get(explorer_index))
+        .route("/explorer.js", get(explorer_js))
+        .route("/explorer.css", get(explorer_css))
+        .route("/get/{key}", get(http_get))
+        .route("/state", get(http_state))
+        .route("/blocks", get(http_blocks))
+        .route("/tip", get(http_tip))
+        .route("/verify", get(http_verify))
+        .route("/signers", get(http_signers))
+        .route("/history/{key}", get(http_history))
+        .route("/set", post(http_set))
+        .route("/del", post(http_del))
+        .route("/begin", post(http_begin))
+        .route("/addput", post(http_addput))
+        .route("/adddel", post(http_adddel))
+        .route("/preview", post(http_preview))
+        .route("/commit", post(http_commit))
+        .route("/abort", post(http_abort))
+        .route("/difficulty", post(http_difficulty))
+        .route("/mining/work", post(http_mining_work))
+        .route("/mining/found", post(http_mining_found))
+        .layer(CompressionLayer::new())
+        .layer(cors)
+        .with_state(state)
 }
 
-/* ---------------- Key Management ---------------- */
+/* ---------------- Explorer ---------------- */
 
-#[derive(Serialize, Deserialize)]
-struct KeyFile {
-    keypair_hex: String, // 64-byte secret||public hex
-    public_hex: String,  // convenience copy
+// A static page driving the read endpoints from the browser; nothing to build, it ships in the binary.
+
+async fn explorer_index() -> Html<&'static str> {
+    Html(include_str!("explorer/index.html"))
 }
 
-fn keygen_to_file(path: &str) -> io::Result<()> {
-    let mut csprng = OsRng;
-    let kp = SigningKey::generate(&mut csprng);
-    let keypair_hex = hex::encode(kp.to_bytes());
-    let public_hex = hex::encode(kp.verifying_key().to_bytes());
-    let data = KeyFile { keypair_hex, public_hex };
-    let json = serde_json::to_string_pretty(&data).unwrap();
-    fs::write(path, json)
+async fn explorer_js() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/javascript; charset=utf-8")], include_str!("explorer/explorer.js"))
 }
 
-fn load_key_from_file(path: &str) -> io::Result<SigningKey> {
-    let s = fs::read_to_string(path)?;
-    let kf: KeyFile = serde_json::from_str(&s)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("key parse error: {e}")))?;
-    let bytes = hex::decode(kf.keypair_hex)
-        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad keypair hex"))?;
-    if bytes.len() != 32 {
-        return Err(io::Error::new(io::ErrorKind::InvalidData, "expected 32-byte signing key"));
-    }
-    let mut arr = [0u8; 32];
-    arr.copy_from_slice(&bytes);
-    Ok(SigningKey::from_bytes(&arr))
+async fn explorer_css() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], include_str!("explorer/explorer.css"))
 }
 
-/* ---------------- RPC Types ---------------- */
+/* ---------------- HTTP Caching ---------------- */
 
-#[derive(Deserialize)]
-struct SetReq { key: String, value: String }
+/// Read responses may be stored by proxies but must be revalidated against the ETag.
+const READ_CACHE_CONTROL: &str = "public, no-cache";
 
-#[derive(Deserialize)]
-struct DelReq { key: String }
+/// Every read endpoint is a pure function of the tip, the difficulty and how far the ops are
+/// pruned, so one tag covers them all. Pruning leaves the tip alone but drops history and
+/// turns `/blocks` ranges into `410 Gone`, so it must change the tag too.
+fn tip_etag(chain: &Chain) -> String {
+    let pruned = chain.pruned_height().map_or("none".to_string(), |h| h.to_string());
+    format!("\"{}-{}-{pruned}\"", chain.last_hash(), chain.difficulty)
+}
 
-#[derive(Deserialize)]
-struct DifficultyReq { n: usize }
+fn if_none_match(req: &HeaderMap, etag: &str) -> bool {
+    req.get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|t| t.trim().trim_start_matches("W/"))
+        .any(|t| t == "*" || t == etag)
+}
 
-#[derive(Serialize)]
-struct VerifyResp { ok: bool, error: Option<String> }
+/// Answer 304 when the client already holds the current tip, otherwise render the body.
+fn conditional_json<T: Serialize>(req: &HeaderMap, chain: &Chain, body: impl FnOnce(&Chain) -> T) -> Response {
+    let etag = tip_etag(chain);
+    let headers = [(header::ETAG, etag.clone()), (header::CACHE_CONTROL, READ_CACHE_CONTROL.to_string())];
+    if if_none_match(req, &etag) {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+    (headers, Json(body(chain))).into_response()
+}
 
-#[derive(Clone)]
-struct AppState {
-    chain: Arc<Mutex<Chain>>,
-    keypair: Arc<Mutex<Option<SigningKey>>>,
+async fn http_get(Path(key): Path<String>, headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let chain = state.chain.read().await;
+    conditional_json(&headers, &chain, |c| c.materialize().get(&key).cloned())
 }
 
-/* ---------------- RPC Server ---------------- */
+async fn http_state(headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let chain = state.chain.read().await;
+    conditional_json(&headers, &chain, Chain::materialize)
+}
 
-async fn router(state: AppState) -> Router {
-    Router::new()
-        .route("/get/:key", get(http_get))
-        .route("/state", get(http_state))
-        .route("/verify", get(http_verify))
-        .route("/set", post(http_set))
-        .route("/del", post(http_del))
-        .route("/begin", post(http_begin))
-        .route("/addput", post(http_addput))
-        .route("/adddel", post(http_adddel))
-        .route("/commit", post(http_commit))
-        .route("/abort", post(http_abort))
-        .route("/difficulty", post(http_difficulty))
-        .with_state(state)
+async fn http_blocks(Query(q): Query<BlocksQuery>, headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let chain = state.chain.read().await;
+    let from = q.from.unwrap_or(0) as usize;
+    // a replica cannot rebuild state from headers alone
+    if let Some(h) = chain.pruned_height()
+        && from as u64 <= h
+    {
+        return (StatusCode::GONE, format!("ops of blocks up to {h} are pruned; request from={}", h + 1)).into_response();
+    }
+    let limit = q.limit.unwrap_or(BLOCKS_PAGE_LIMIT).min(BLOCKS_PAGE_LIMIT);
+    conditional_json(&headers, &chain, |c| BlocksResp {
+        difficulty: c.difficulty,
+        version: c.version,
+        chain_id: c.chain_id.clone(),
+        blocks: c.blocks.iter().skip(from).take(limit).cloned().collect(),
+    })
+}
+
+/// Longest `/tip?wait=` honoured, so a poll cannot hold a connection indefinitely
+const MAX_TIP_WAIT: Duration = Duration::from_secs(60);
+
+/// The tip, at once or, with `wait`, once it differs from `since` (default: the tip when
+/// the request arrived) or `wait` milliseconds have passed, whichever comes first.
+async fn http_tip(Query(q): Query<TipQuery>, State(state): State<AppState>) -> Json<TipResp> {
+    let mut tips = state.chain.subscribe();
+    let since = q.since.unwrap_or_else(|| tips.borrow().hash.clone());
+    let wait = Duration::from_millis(q.wait.unwrap_or(0)).min(MAX_TIP_WAIT);
+    if !wait.is_zero() {
+        // on timeout the tip is unchanged, and is answered as is
+        let _ = tokio::time::timeout(wait, tips.wait_for(|tip| tip.hash != since)).await;
+    }
+    let tip = tips.borrow().clone();
+    Json(tip)
 }
 
-async fn http_get(Path(key): Path<String>, State(state): State<AppState>) -> Json<Option<String>> {
-    let chain = state.chain.lock().unwrap();
-    let s = chain.materialize();
-    Json(s.get(&key).cloned())
+async fn http_signers(headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let chain = state.chain.read().await;
+    conditional_json(&headers, &chain, Chain::signers)
 }
 
-async fn http_state(State(state): State<AppState>) -> Json<HashMap<String, String>> {
-    let chain = state.chain.lock().unwrap();
-    Json(chain.materialize())
+async fn http_history(Path(key): Path<String>, headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let chain = state.chain.read().await;
+    conditional_json(&headers, &chain, |c| history_entries(c, &key))
 }
 
 async fn http_verify(State(state): State<AppState>) -> Json<VerifyResp> {
-    let chain = state.chain.lock().unwrap();
+    let chain = state.chain.read().await;
     match chain.verify_all() {
         Ok(_) => Json(VerifyResp { ok: true, error: None }),
         Err(e) => Json(VerifyResp { ok: false, error: Some(e) }),
     }
 }
 
-async fn http_set(State(state): State<AppState>, Json(req): Json<SetReq>) -> Json<String> {
-    let maybe_kp = state.keypair.lock().unwrap().clone();
-    if let Some(kp) = maybe_kp {
-        // mine without chatty progress in HTTP
-        let mut chain = state.chain.lock().unwrap();
-        chain.append_signed(vec![Op::Put { key: req.key, value: req.value }], &kp, false);
-        Json("ok".into())
-    } else {
-        Json("no signing key loaded".into())
+async fn http_set(headers: HeaderMap, State(state): State<AppState>, Json(req): Json<SetReq>) -> Response {
+    if let Some(rejected) = state.read_only() {
+        return rejected.into_response();
     }
+    idempotent_write(&state, &headers, vec![Op::put(req.key, req.value).with_meta(req.meta)]).await
 }
 
-async fn http_del(State(state): State<AppState>, Json(req): Json<DelReq>) -> Json<String> {
-    let maybe_kp = state.keypair.lock().unwrap().clone();
-    if let Some(kp) = maybe_kp {
-        let mut chain = state.chain.lock().unwrap();
-        chain.append_signed(vec![Op::Del { key: req.key }], &kp, false);
-        Json("ok".into())
-    } else {
-        Json("no signing key loaded".into())
+async fn http_del(headers: HeaderMap, State(state): State<AppState>, Json(req): Json<DelReq>) -> Response {
+    if let Some(rejected) = state.read_only() {
+        return rejected.into_response();
     }
+    idempotent_write(&state, &headers, vec![Op::del(req.key).with_meta(req.meta)]).await
 }
 
 async fn http_begin(State(state): State<AppState>) -> Json<String> {
-    let mut chain = state.chain.lock().unwrap();
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let mut chain = state.chain.write().await;
     match chain.begin_batch() {
         Ok(_) => Json("batch begun".into()),
         Err(e) => Json(format!("error: {e}")),
@@ -450,33 +1506,50 @@ async fn http_begin(State(state): State<AppState>) -> Json<String> {
 }
 
 #[derive(Deserialize)]
-struct AddPutReq { key: String, value: String }
+struct AddPutReq { key: String, value: String, #[serde(default)] meta: OpMeta }
 
 async fn http_addput(State(state): State<AppState>, Json(req): Json<AddPutReq>) -> Json<String> {
-    let mut chain = state.chain.lock().unwrap();
-    match chain.add_put(req.key, req.value) {
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let mut chain = state.chain.write().await;
+    match chain.add_op(Op::put(req.key, req.value).with_meta(req.meta)) {
         Ok(_) => Json("added".into()),
         Err(e) => Json(format!("error: {e}")),
     }
 }
 
 #[derive(Deserialize)]
-struct AddDelReq { key: String }
+struct AddDelReq { key: String, #[serde(default)] meta: OpMeta }
 
 async fn http_adddel(State(state): State<AppState>, Json(req): Json<AddDelReq>) -> Json<String> {
-    let mut chain = state.chain.lock().unwrap();
-    match chain.add_del(req.key) {
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let mut chain = state.chain.write().await;
+    match chain.add_op(Op::del(req.key).with_meta(req.meta)) {
         Ok(_) => Json("added".into()),
         Err(e) => Json(format!("error: {e}")),
     }
 }
 
+/// The open batch's effect on state, checked against the loaded key, without mining
+async fn http_preview(State(state): State<AppState>) -> Result<Json<BatchPreview>, Json<String>> {
+    let pubkey = state.signer.lock().unwrap().as_deref().map(public_hex);
+    batch_preview(&*state.chain.read().await, pubkey.as_deref()).map(Json).map_err(|e| Json(format!("error: {e}")))
+}
+
 async fn http_commit(State(state): State<AppState>) -> Json<String> {
-    let maybe_kp = state.keypair.lock().unwrap().clone();
-    if let Some(kp) = maybe_kp {
-        let mut chain = state.chain.lock().unwrap();
-        match chain.commit_batch(&kp, false) {
-            Ok(n) => Json(format!("committed {n} ops")),
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let maybe_signer = state.signer.lock().unwrap().clone();
+    if let Some(signer) = maybe_signer {
+        match commit_batch(&state.chain, signer, false).await {
+            Ok(blk) => {
+                report_mined(&blk);
+                Json(format!("committed {} ops", blk.ops.len()))
+            }
             Err(e) => Json(format!("error: {e}")),
         }
     } else {
@@ -485,13 +1558,19 @@ async fn http_commit(State(state): State<AppState>) -> Json<String> {
 }
 
 async fn http_abort(State(state): State<AppState>) -> Json<String> {
-    let mut chain = state.chain.lock().unwrap();
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let mut chain = state.chain.write().await;
     chain.abort_batch();
     Json("aborted".into())
 }
 
 async fn http_difficulty(State(state): State<AppState>, Json(body): Json<DifficultyReq>) -> Json<String> {
-    let mut chain = state.chain.lock().unwrap();
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let mut chain = state.chain.write().await;
     if body.n == 0 || body.n > 9 {
         return Json("choose 1..9".into());
     }
@@ -499,8 +1578,316 @@ async fn http_difficulty(State(state): State<AppState>, Json(body): Json<Difficu
     Json(format!("difficulty set to {}", body.n))
 }
 
+/* ---------------- Cooperative Mining ---------------- */
+
+// The server fixes a candidate block and hands out disjoint nonce ranges for it, each stamped
+// with the time it was handed out; clients grind their range and report a hit with its
+// timestamp, and the first valid one is signed and appended.
+
+/// Nonces handed out per `/mining/work` call unless `?size=` asks for another amount
+const DEFAULT_WORK_RANGE: u64 = 100_000;
+const MAX_WORK_RANGE: u64 = 100_000_000;
+
+/// The candidate block handed out by `/mining/work`; the ops are those of the open batch,
+/// or none, and it lasts until the tip moves or the batch changes.
+#[derive(Clone)]
+struct MiningJob {
+    id: u64,
+    next: NextBlock,
+    ops: Vec<Op>,
+    merkle_root: String,
+    /// First nonce not handed out yet
+    cursor: u64,
+}
+
+#[derive(Default)]
+struct MiningJobs {
+    current: Option<MiningJob>,
+    issued: u64,
+}
+
+impl MiningJob {
+    fn fits(&self, chain: &Chain) -> bool {
+        chain.next_block() == self.next && chain.batch().unwrap_or_default() == self.ops
+    }
+}
+
+impl MiningJobs {
+    /// The job still matching `chain`, replacing a stale or exhausted one
+    fn for_chain(&mut self, chain: &Chain) -> &mut MiningJob {
+        let stale = self.current.as_ref().is_none_or(|job| !job.fits(chain) || job.cursor == u64::MAX);
+        if stale {
+            self.issued += 1;
+            let ops = chain.batch().unwrap_or_default().to_vec();
+            self.current = Some(MiningJob {
+                id: self.issued,
+                next: chain.next_block(),
+                merkle_root: chain_core::merkle_root(&ops, chain.hash_scheme()),
+                ops,
+                cursor: 0,
+            });
+        }
+        self.current.as_mut().unwrap()
+    }
+}
+
+async fn http_mining_work(State(state): State<AppState>, Query(q): Query<WorkQuery>) -> Result<Json<WorkResp>, Json<String>> {
+    if let Some(rejected) = state.read_only() {
+        return Err(rejected);
+    }
+    if state.signer.lock().unwrap().is_none() {
+        return Err(Json("no signing key loaded".into()));
+    }
+    let size = q.size.unwrap_or(DEFAULT_WORK_RANGE);
+    if size == 0 || size > MAX_WORK_RANGE {
+        return Err(Json(format!("choose a size of 1..{MAX_WORK_RANGE}")));
+    }
+    let chain = state.chain.read().await;
+    let mut jobs = state.work.lock().unwrap();
+    let job = jobs.for_chain(&chain);
+    let start = job.cursor;
+    job.cursor = start.saturating_add(size);
+    let timestamp = SystemClock.now();
+    Ok(Json(WorkResp {
+        job: job.id,
+        index: job.next.index,
+        timestamp,
+        prev_hash: job.next.prev_hash.clone(),
+        merkle_root: job.merkle_root.clone(),
+        header: hex::encode(job.next.scheme.header_prefix(job.next.index, timestamp, &job.merkle_root, &job.next.prev_hash)),
+        difficulty: job.next.difficulty,
+        nonce_start: start,
+        nonce_end: job.cursor,
+    }))
+}
+
+async fn http_mining_found(State(state): State<AppState>, Json(body): Json<FoundReq>) -> Json<String> {
+    if let Some(rejected) = state.read_only() {
+        return rejected;
+    }
+    let maybe_signer = state.signer.lock().unwrap().clone();
+    let Some(signer) = maybe_signer else {
+        return Json("no signing key loaded".into());
+    };
+    let job = {
+        let chain = state.chain.read().await;
+        let jobs = state.work.lock().unwrap();
+        match &jobs.current {
+            Some(job) if job.id == body.job && job.fits(&chain) => job.clone(),
+            _ => return Json(format!("error: job {} is no longer current; fetch new work", body.job)),
+        }
+    };
+    let (next, ops) = (job.next.clone(), job.ops.clone());
+    // an external signer blocks, so sign off the async runtime like a local mine; the
+    // timestamp is the miner's, held to the chain's time rules when appended
+    let sealed = task::spawn_blocking(move || next.seal(body.timestamp, ops, body.nonce, signer.as_ref())).await;
+    let blk = match sealed {
+        Ok(Ok(blk)) => blk,
+        Ok(Err(e)) => return Json(format!("error: {e}")),
+        Err(e) => return Json(format!("error: signing task failed: {e}")),
+    };
+
+    // another solution may have landed while signing; only the first one is appended
+    let mut chain = state.chain.write().await;
+    if !job.fits(&chain) {
+        return Json(format!("error: block {} was already mined", blk.index));
+    }
+    if chain.batch_active() {
+        chain.take_batch().ok();
+    }
+    if let Err(e) = chain.append_verified(blk.clone()) {
+        return Json(format!("error: {e}"));
+    }
+    drop(chain);
+    state.work.lock().unwrap().current = None;
+    let worker = body.worker.as_deref().unwrap_or("anonymous");
+    println!("🎓 block {} mined by {worker} (nonce {}, {} ops)", blk.index, blk.nonce, blk.ops.len());
+    Json(format!("accepted block {} ({})", blk.index, blk.hash))
+}
+
+/* ---------------- Dashboard ---------------- */
+
+/// Longest the dashboard sleeps before redrawing when no block lands (mining progress, keys)
+const DASHBOARD_TICK: Duration = Duration::from_millis(250);
+
+/// Lines kept by the dashboard's log pane
+const DASHBOARD_LOG: usize = 200;
+
+/// One-line summary of an op, e.g. `PUT user = "Alice" author="bob"`
+fn describe_op(op: &Op) -> String {
+    let text = match op {
+        Op::Put { key, value, .. } => format!("PUT {key} = {value:?}"),
+        Op::Del { key, .. } => format!("DEL {key}"),
+        Op::Rotate { new_pubkey } => format!("ROTATE to {}", short(new_pubkey)),
+    };
+    match describe_meta(op.meta()) {
+        meta if meta.is_empty() => text,
+        meta => format!("{text} {meta}"),
+    }
+}
+
+/// What the dashboard has noticed so far, to log only what changed since the last frame
+struct Dashboard {
+    log: VecDeque<String>,
+    /// First block index not logged yet
+    seen: u64,
+    /// Block being mined at the last frame
+    mining: Option<u64>,
+    /// Ops in the open batch at the last frame
+    batch: Option<usize>,
+}
+
+impl Dashboard {
+    fn new(next_index: u64) -> Self {
+        Self { log: VecDeque::new(), seen: next_index, mining: None, batch: None }
+    }
+
+    fn log(&mut self, line: String) {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let (h, m, s) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        if self.log.len() == DASHBOARD_LOG {
+            self.log.pop_front();
+        }
+        self.log.push_back(format!("{h:02}:{m:02}:{s:02} {line}"));
+    }
+
+    fn observe(&mut self, chain: &Chain, mining: Option<&MiningStatus>) {
+        let seen = self.seen;
+        for b in chain.blocks.iter().filter(|b| b.index >= seen) {
+            let signer = b.signer_pubkey.as_deref().map_or("unsigned", short);
+            self.log(format!("block #{} {} appended: {} op(s), signed by {signer}", b.index, short(&b.hash), b.ops.len()));
+        }
+        self.seen = chain.next_index();
+
+        let mining = mining.map(|m| m.index);
+        if let Some(index) = mining
+            && self.mining != mining
+        {
+            self.log(format!("mining block #{index}"));
+        }
+        self.mining = mining;
+
+        let batch = chain.batch().map(<[Op]>::len);
+        if batch != self.batch {
+            match batch {
+                Some(n) => self.log(format!("batch open with {n} op(s)")),
+                None => self.log("batch closed".into()),
+            }
+        }
+        self.batch = batch;
+    }
+}
+
+/// Take over the terminal with a live view of the chain until `q` or `Esc`
+async fn run_dashboard(chain: &ChainHandle, whoami: &str, role: &str) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = dashboard_loop(&mut terminal, chain, whoami, role).await;
+    ratatui::restore();
+    result
+}
+
+async fn dashboard_loop(terminal: &mut DefaultTerminal, chain: &ChainHandle, whoami: &str, role: &str) -> io::Result<()> {
+    let mut tips = chain.subscribe();
+    let mut view = Dashboard::new(chain.read().await.next_index());
+    view.log("dashboard started".into());
+    loop {
+        {
+            let current = chain.read().await;
+            let mining = chain.mining.lock().unwrap().clone();
+            view.observe(&current, mining.as_ref());
+            terminal.draw(|frame| draw_dashboard(frame, &current, mining.as_ref(), &view, whoami, role))?;
+        }
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+            {
+                return Ok(());
+            }
+        }
+        if let Ok(Ok(())) = tokio::time::timeout(DASHBOARD_TICK, tips.changed()).await {
+            // the follower reports replicated blocks on stdout; repaint over whatever it wrote
+            terminal.clear()?;
+        }
+    }
+}
+
+fn draw_dashboard(frame: &mut Frame, chain: &Chain, mining: Option<&MiningStatus>, view: &Dashboard, whoami: &str, role: &str) {
+    let [top, middle, bottom, footer] =
+        Layout::vertical([Constraint::Length(4), Constraint::Min(8), Constraint::Length(10), Constraint::Length(1)]).areas(frame.area());
+    let [blocks, side] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(middle);
+    let [batch, miner] = Layout::vertical([Constraint::Min(3), Constraint::Length(7)]).areas(side);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let tip = TipResp::of(chain);
+    let pruned = chain.pruned_height().map(|h| format!("   ops pruned up to #{h}")).unwrap_or_default();
+    let header = vec![
+        Line::styled(format!("#{}  {}", tip.index, tip.hash), bold),
+        Line::from(format!("difficulty {}   signing as {whoami}   {role}{pruned}", chain.difficulty)),
+    ];
+    frame.render_widget(Paragraph::new(header).block(Pane::bordered().title(" Chain tip ")), top);
+
+    // as many of the newest blocks as fit between the borders and the header row
+    let rows = chain.blocks.iter().rev().take(blocks.height.saturating_sub(3) as usize).map(|b| {
+        Row::new([
+            b.index.to_string(),
+            short(&b.hash).to_string(),
+            b.ops.len().to_string(),
+            b.signer_pubkey.as_deref().map_or("unsigned", short).to_string(),
+            ago(b.timestamp),
+        ])
+    });
+    let widths = [Constraint::Length(7), Constraint::Length(17), Constraint::Length(4), Constraint::Length(17), Constraint::Min(8)];
+    let table = TuiTable::new(rows, widths)
+        .header(Row::new(["#", "hash", "ops", "signer", "mined"]).style(bold))
+        .block(Pane::bordered().title(" Recent blocks "));
+    frame.render_widget(table, blocks);
+
+    let pending: Vec<Line> = match chain.batch() {
+        None => vec![Line::from("no batch open")],
+        Some([]) => vec![Line::from("batch open, no ops yet")],
+        Some(ops) => ops.iter().map(|op| Line::from(describe_op(op))).collect(),
+    };
+    let title = format!(" Pending batch ({}) ", chain.batch().map_or(0, <[Op]>::len));
+    frame.render_widget(List::new(pending).block(Pane::bordered().title(title)), batch);
+
+    let status = match mining {
+        Some(m) => vec![
+            Line::styled(format!("mining block #{}", m.index), bold),
+            Line::from(format!("nonce {}", m.nonce)),
+            Line::from(format!("{:.0} H/s for {:.1}s", m.hps, m.started.elapsed().as_secs_f64())),
+            Line::from(format!("last {}", short(&m.last))),
+        ],
+        None => vec![Line::from("idle")],
+    };
+    frame.render_widget(Paragraph::new(status).block(Pane::bordered().title(" Mining ")), miner);
+
+    frame.render_widget(log_pane(view, bottom), bottom);
+    frame.render_widget(Line::styled(" q / Esc: back to the prompt", Style::default().add_modifier(Modifier::DIM)), footer);
+}
+
+/// The newest log lines that fit in `area`
+fn log_pane(view: &Dashboard, area: Rect) -> List<'_> {
+    let fit = area.height.saturating_sub(2) as usize;
+    let lines = view.log.iter().skip(view.log.len().saturating_sub(fit)).map(String::as_str);
+    List::new(lines).block(Pane::bordered().title(" Log "))
+}
+
 /* ---------------- CLI ---------------- */
 
+/// Commands that change the chain; refused while following a primary.
+const MUTATING_CMDS: &[&str] = &["set", "del", "begin", "addput", "adddel", "commit", "abort", "load", "difficulty", "rotatekey", "prune"];
+
+/// Commands that show a setting of the chain, or change it when given arguments; the
+/// change is refused while following a primary.
+const SETTING_CMDS: &[&str] = &["chainid", "timerules"];
+
+/// Whether the command in `parts` would change the chain
+fn is_mutating(parts: &[&str]) -> bool {
+    MUTATING_CMDS.contains(&parts[0]) || (SETTING_CMDS.contains(&parts[0]) && parts.len() > 1)
+}
+
 fn prompt() -> io::Result<String> {
     print!("chain-kv> ");
     io::stdout().flush()?;
@@ -516,131 +1903,281 @@ fn print_help() {
     println!("  begin                     - begin batch");
     println!("  addput <key> <value...>   - add op to batch");
     println!("  adddel <key>              - add op to batch");
+    println!("  preview                   - show what commit would change, without mining");
     println!("  commit                    - mine+sign a multi-op block");
     println!("  abort                     - drop current batch");
     println!("  get <key>                 - read value from materialized state");
     println!("  state                     - dump state");
+    println!("  history <key>             - every change to key: block, signer, author/reason/ticket");
+    println!("  meta [author|reason|ticket <text>] - metadata attached to your next writes; meta clear");
+    println!("  churn [prefix] [n]        - how often keys under prefix change; top n keys (default 10)");
     println!("  verify                    - verify PoW, signatures, and links");
-    println!("  save <file>               - save chain JSON");
-    println!("  load <file>               - load chain JSON");
+    println!("  dashboard                 - full-screen live view: tip, blocks, batch, mining, log (q to leave)");
+    println!("  prune <n>                 - drop the ops of all but the newest n blocks (headers stay; needs the key)");
+    println!("  events <file> [--max-mb <n>] [--keep <n>] - log every new block to a rotating JSONL file; events off");
+    println!("  save <file>               - save chain JSON (zstd-compressed if <file> ends in .zst)");
+    println!("  load <file>               - load chain JSON (plain or zstd)");
+    println!("  diff <a> <b>              - compare two chain files: fork point, diverging blocks, state");
     println!("  keygen <file>             - generate Ed25519 keypair JSON");
+    println!("  keygen --mnemonic [file]  - generate a key from a new 12-word recovery phrase and load it");
     println!("  loadkey <file>            - load signing key");
+    println!("  signer cmd <program> [args] - sign with an external program (key stays outside)");
+    println!("  signer http <url>         - sign with an HTTP signing service");
+    println!("  loadkey --mnemonic \"<words>\" - restore signing key from its recovery phrase");
+    println!("  rotatekey <file>          - hand signing over to the key in <file> (generated if missing)");
     println!("  whoami                    - show loaded public key");
+    println!("  signers                   - block and op counts per signing key");
+    println!("  signer blocks <pubkey>    - list the blocks a key signed");
     println!("  difficulty <n>            - set PoW difficulty (1..9)");
-    println!("  serve <port>              - start Axum server on port");
+    println!("  calibrate [secs] [--set]  - measure hash rate, suggest (or set) the difficulty for ~secs blocks (default 5)");
+    println!("  chainid [id]              - show the chain id, or set it before the first block");
+    println!("  timerules [<ahead> <behind>] - show or set how many seconds a block may be dated ahead of the clock");
+    println!("                              and behind its predecessor; \"off\" drops a limit");
+    println!("  serve [port] [flags]      - start Axum server on port (default 3000); browse / for the explorer");
+    println!("        --unix <path>           also serve on a Unix socket (TCP only if a port is given)");
+    println!("        --dev                   allow CORS from any origin");
+    println!("        --cors-origins <a,b>    allowed browser origins (default: none)");
+    println!("        --cors-methods <M,N>    allowed CORS methods (default: GET,POST)");
+    println!("        --tls-cert <pem> --tls-key <pem>   serve HTTPS with this certificate");
+    println!("        --tls-self-signed       serve HTTPS with a generated localhost certificate");
+    println!("        --idempotency-window <s>  remember Idempotency-Key writes for s seconds (default 600)");
+    println!("  follow <url> [secs]       - replicate a primary's blocks (read-only; default every 5s)");
+    println!("                              <url> may be http(s)://host:port or unix:/path/to.sock");
+    println!("  unfollow                  - stop following and accept local writes again");
     println!("  help                      - show this help");
     println!("  exit                      - quit");
+    println!("Read commands (get, state, history, churn, verify, signers, signer blocks, whoami, diff, preview, meta)");
+    println!("  take --json for machine-readable output and --full to stop cutting long values and lists.");
+    println!("  Set NO_COLOR to turn off colors.");
 }
 
 #[tokio::main]
 async fn main() {
-    let chain = Arc::new(Mutex::new(Chain::genesis(3)));
-    let keypair: Arc<Mutex<Option<SigningKey>>> = Arc::new(Mutex::new(None));
+    rustls::crypto::ring::default_provider()
+        .install_default()
+        .expect("install rustls crypto provider");
+    let chain: SharedChain = Arc::new(ChainHandle::new(Chain::genesis(3)));
+    let signer: SharedSigner = Arc::new(Mutex::new(None));
+    let follower: Arc<Mutex<Option<Follower>>> = Arc::new(Mutex::new(None));
+    // attached to every put and delete typed at this prompt
+    let mut meta = OpMeta::default();
+    // measured by `calibrate`, to show what a difficulty change costs
+    let mut hash_rate: Option<f64> = None;
 
     println!("🔗 ChainKV — PoW + Signatures + Merkle + Batching + RPC");
     print_help();
     println!();
 
-    loop {
-        let line = match prompt() {
-            Ok(s) => s,
-            Err(_) => break,
-        };
+    while let Ok(line) = prompt() {
         if line.is_empty() {
             continue;
         }
-        let parts: Vec<&str> = line.split_whitespace().collect();
+        let mut parts: Vec<&str> = line.split_whitespace().collect();
+        let out = Output::take(&mut parts);
+        if is_mutating(&parts)
+            && let Some(url) = replica_of(&follower)
+        {
+            println!("❌ read-only replica (following {url}). Use: unfollow");
+            continue;
+        }
         match parts[0] {
             "set" if parts.len() >= 3 => {
-                let kp = { keypair.lock().unwrap().clone() };
-                if let Some(kp) = kp {
+                let current = { signer.lock().unwrap().clone() };
+                if let Some(current) = current {
                     let key = parts[1].to_string();
                     let value = parts[2..].join(" ");
-                    chain.lock().unwrap().append_signed(vec![Op::Put { key, value }], &kp, true);
+                    match mine_and_append(&chain, vec![Op::put(key, value).with_meta(meta.clone())], current, true).await {
+                        Ok(blk) => report_mined(&blk),
+                        Err(e) => report_mining_failed(&e),
+                    }
                 } else {
                     println!("❌ no signing key loaded. Use: loadkey <file>");
                 }
             }
             "del" if parts.len() == 2 => {
-                let kp = { keypair.lock().unwrap().clone() };
-                if let Some(kp) = kp {
+                let current = { signer.lock().unwrap().clone() };
+                if let Some(current) = current {
                     let key = parts[1].to_string();
-                    chain.lock().unwrap().append_signed(vec![Op::Del { key }], &kp, true);
+                    match mine_and_append(&chain, vec![Op::del(key).with_meta(meta.clone())], current, true).await {
+                        Ok(blk) => report_mined(&blk),
+                        Err(e) => report_mining_failed(&e),
+                    }
                 } else {
                     println!("❌ no signing key loaded. Use: loadkey <file>");
                 }
             }
-            "begin" => match chain.lock().unwrap().begin_batch() {
+            "meta" if parts.len() == 1 => print_meta(&meta, out),
+            "meta" if parts.len() == 2 && parts[1] == "clear" => {
+                meta = OpMeta::default();
+                println!("🏷️ metadata cleared");
+            }
+            "meta" if parts.len() >= 3 && ["author", "reason", "ticket"].contains(&parts[1]) => {
+                let value = Some(parts[2..].join(" "));
+                match parts[1] {
+                    "author" => meta.author = value,
+                    "reason" => meta.reason = value,
+                    _ => meta.ticket = value,
+                }
+                print_meta(&meta, out);
+            }
+            "history" if parts.len() == 2 => print_history(&*chain.read().await, parts[1], out),
+            "begin" => match chain.write().await.begin_batch() {
                 Ok(_) => println!("🧺 batch started"),
                 Err(e) => println!("❌ {e}"),
             },
             "addput" if parts.len() >= 3 => {
                 let key = parts[1].to_string();
                 let value = parts[2..].join(" ");
-                match chain.lock().unwrap().add_put(key, value) {
+                match chain.write().await.add_op(Op::put(key, value).with_meta(meta.clone())) {
                     Ok(_) => println!("➕ added put"),
                     Err(e) => println!("❌ {e}"),
                 }
             }
             "adddel" if parts.len() == 2 => {
                 let key = parts[1].to_string();
-                match chain.lock().unwrap().add_del(key) {
+                match chain.write().await.add_op(Op::del(key).with_meta(meta.clone())) {
                     Ok(_) => println!("➖ added del"),
                     Err(e) => println!("❌ {e}"),
                 }
             }
             "commit" => {
-                let kp = { keypair.lock().unwrap().clone() };
-                if let Some(kp) = kp {
-                    match chain.lock().unwrap().commit_batch(&kp, true) {
-                        Ok(n) => println!("✅ committed {n} ops"),
-                        Err(e) => println!("❌ {e}"),
+                let current = { signer.lock().unwrap().clone() };
+                if let Some(current) = current {
+                    match commit_batch(&chain, current, true).await {
+                        Ok(blk) => {
+                            report_mined(&blk);
+                            println!("✅ committed {} ops", blk.ops.len());
+                        }
+                        Err(e) => report_mining_failed(&e),
                     }
                 } else {
                     println!("❌ no signing key loaded. Use: loadkey <file>");
                 }
             }
+            "preview" => {
+                let pubkey = signer.lock().unwrap().as_deref().map(public_hex);
+                match batch_preview(&*chain.read().await, pubkey.as_deref()) {
+                    Ok(preview) => print_preview(&preview, out),
+                    Err(e) => println!("❌ {e}"),
+                }
+            }
             "abort" => {
-                chain.lock().unwrap().abort_batch();
+                chain.write().await.abort_batch();
                 println!("🧹 batch aborted");
             }
             "get" if parts.len() == 2 => {
-                let state = chain.lock().unwrap().materialize();
+                let state = chain.read().await.materialize();
                 match state.get(parts[1]) {
+                    v if out.json => out.print_json(&v),
                     Some(v) => println!("🔎 {}", v),
                     None => println!("❌ Not found"),
                 }
             }
             "state" => {
-                let state = chain.lock().unwrap().materialize();
-                if state.is_empty() {
+                let state: BTreeMap<String, String> = chain.read().await.materialize().into_iter().collect();
+                if out.json {
+                    out.print_json(&state);
+                } else if state.is_empty() {
                     println!("(empty)");
                 } else {
+                    let mut table = Table::new(&["key", "value"]);
                     for (k, v) in state {
-                        println!("{k} = {v}");
+                        table.row(vec![k, v]);
                     }
+                    table.print(out);
                 }
             }
-            "verify" => match chain.lock().unwrap().verify_all() {
-                Ok(_) => println!("✅ chain ok ({} blocks, difficulty {})", chain.lock().unwrap().blocks.len(), chain.lock().unwrap().difficulty),
-                Err(e) => println!("❌ verify failed: {e}"),
+            "churn" if parts.len() <= 3 => {
+                let prefix = parts.get(1).copied().unwrap_or("");
+                match parts.get(2).map_or(Ok(DEFAULT_CHURN_TOP), |n| n.parse::<usize>()) {
+                    Ok(top) => print_churn(prefix, &chain.read().await.churn(prefix), top, out),
+                    Err(_) => println!("⚠️ usage: churn <key-prefix> [top-n]"),
+                }
+            }
+            "verify" => {
+                let chain = chain.read().await;
+                match chain.verify_all() {
+                    verified if out.json => out.print_json(&VerifyResp { ok: verified.is_ok(), error: verified.err() }),
+                    Ok(_) => match chain.pruned_height() {
+                        Some(h) => println!(
+                            "✅ chain ok ({} blocks, difficulty {}, headers only up to block {h})",
+                            chain.blocks.len(),
+                            chain.difficulty
+                        ),
+                        None => println!("✅ chain ok ({} blocks, difficulty {})", chain.blocks.len(), chain.difficulty),
+                    },
+                    Err(e) => println!("❌ verify failed: {e}"),
+                }
+            }
+            "dashboard" => {
+                let whoami = signer.lock().unwrap().as_deref().map_or("no key".to_string(), |s| short(&public_hex(s)).to_string());
+                let role = replica_of(&follower).map_or("primary".to_string(), |url| format!("replica of {url}"));
+                if let Err(e) = run_dashboard(&chain, &whoami, &role).await {
+                    println!("❌ dashboard: {e}");
+                }
+            }
+            "prune" if parts.len() == 2 => match parts[1].parse::<usize>() {
+                Ok(keep) => {
+                    let current = { signer.lock().unwrap().clone() };
+                    if let Some(current) = current {
+                        let mut chain = chain.write().await;
+                        match chain.prune(keep, current.as_ref()) {
+                            Ok(0) => println!("(nothing to prune)"),
+                            Ok(n) => println!("✂️ pruned ops of {n} block(s); state snapshot at block {}", chain.pruned_height().unwrap()),
+                            Err(e) => println!("❌ prune failed: {e}"),
+                        }
+                    } else {
+                        println!("❌ no signing key loaded. Use: loadkey <file>");
+                    }
+                }
+                Err(_) => println!("⚠️ usage: prune <blocks-to-keep>"),
             },
-            "save" if parts.len() == 2 => match chain.lock().unwrap().save(parts[1]) {
+            "save" if parts.len() == 2 => match chain.read().await.save(parts[1]) {
                 Ok(_) => println!("💾 saved {}", parts[1]),
                 Err(e) => println!("❌ save error: {e}"),
             },
             "load" if parts.len() == 2 => match Chain::load(parts[1]) {
-                Ok(loaded) => {
+                Ok(mut loaded) => {
+                    // the limits set with `timerules`, never ones from the file
+                    loaded.time_rules = chain.read().await.time_rules;
                     match loaded.verify_all() {
                         Ok(_) => {
-                            *chain.lock().unwrap() = loaded;
-                            println!("📥 loaded chain ({} blocks) | difficulty={}", chain.lock().unwrap().blocks.len(), chain.lock().unwrap().difficulty);
+                            println!("📥 loaded chain ({} blocks) | difficulty={}", loaded.blocks.len(), loaded.difficulty);
+                            *chain.write().await = loaded;
                         }
                         Err(e) => println!("❌ load verify failed: {e}"),
                     }
                 }
                 Err(e) => println!("❌ load error: {e}"),
             },
+            "diff" if parts.len() == 3 => match (Chain::load(parts[1]), Chain::load(parts[2])) {
+                (Ok(a), Ok(b)) => print_diff(parts[1], parts[2], &a.diff(&b), out),
+                (Err(e), _) => println!("❌ load error ({}): {e}", parts[1]),
+                (_, Err(e)) => println!("❌ load error ({}): {e}", parts[2]),
+            },
+            "keygen" if parts.len() <= 3 && parts.get(1) == Some(&"--mnemonic") => {
+                let (phrase, kp) = generate_mnemonic();
+                println!("📝 recovery phrase (write it down; anyone with it can sign as you):");
+                println!("   {phrase}");
+                if let Some(path) = parts.get(2) {
+                    match save_key_to_file(path, &kp) {
+                        Ok(_) => println!("🔐 keypair saved to {}", path),
+                        Err(e) => println!("❌ keygen error: {e}"),
+                    }
+                }
+                println!("🔓 loaded key. pubkey={}", public_hex(&kp));
+                *signer.lock().unwrap() = Some(Arc::new(kp));
+            }
+            "loadkey" if parts.len() > 2 && parts[1] == "--mnemonic" => {
+                let phrase = parts[2..].join(" ");
+                match key_from_mnemonic(phrase.trim_matches('"')) {
+                    Ok(kp) => {
+                        println!("🔓 restored key from mnemonic. pubkey={}", public_hex(&kp));
+                        *signer.lock().unwrap() = Some(Arc::new(kp));
+                    }
+                    Err(e) => println!("❌ loadkey error: {e}"),
+                }
+            }
             "keygen" if parts.len() == 2 => {
                 let path = parts[1];
                 if FsPath::new(path).exists() {
@@ -653,15 +2190,57 @@ async fn main() {
             }
             "loadkey" if parts.len() == 2 => match load_key_from_file(parts[1]) {
                 Ok(kp) => {
-                    let pub_hex = hex::encode(kp.verifying_key().to_bytes());
-                    *keypair.lock().unwrap() = Some(kp);
+                    let pub_hex = public_hex(&kp);
+                    *signer.lock().unwrap() = Some(Arc::new(kp));
                     println!("🔓 loaded key. pubkey={}", pub_hex);
                 }
                 Err(e) => println!("❌ loadkey error: {e}"),
             },
+            "signers" => print_signers(&chain.read().await.signers(), out),
+            "signer" if parts.len() == 3 && parts[1] == "blocks" => print_signed_blocks(&*chain.read().await, parts[2], out),
+            "signer" if parts.len() >= 3 && parts[1] == "cmd" => match CommandSigner::new(parts[2], &parts[3..]) {
+                Ok(external) => {
+                    println!("🔌 signing via `{}`. pubkey={}", parts[2..].join(" "), public_hex(&external));
+                    *signer.lock().unwrap() = Some(Arc::new(external));
+                }
+                Err(e) => println!("❌ signer error: {e}"),
+            },
+            "signer" if parts.len() == 3 && parts[1] == "http" => match HttpSigner::connect(parts[2]).await {
+                Ok(external) => {
+                    println!("🔌 signing via {}. pubkey={}", external.url, public_hex(&external));
+                    *signer.lock().unwrap() = Some(Arc::new(external));
+                }
+                Err(e) => println!("❌ signer error: {e}"),
+            },
+            "rotatekey" if parts.len() == 2 => {
+                let Some(current) = ({ signer.lock().unwrap().clone() }) else {
+                    println!("❌ no signing key loaded. Use: loadkey <file>");
+                    continue;
+                };
+                let path = parts[1];
+                let next = if FsPath::new(path).exists() {
+                    load_key_from_file(path)
+                } else {
+                    keygen_to_file(path).and_then(|_| load_key_from_file(path))
+                };
+                match next {
+                    Ok(next) => match mine_and_append(&chain, vec![Op::Rotate { new_pubkey: public_hex(&next) }], current, true).await {
+                        Ok(blk) => {
+                            report_mined(&blk);
+                            println!("🔁 blocks must now be signed by pubkey={}", public_hex(&next));
+                            *signer.lock().unwrap() = Some(Arc::new(next));
+                        }
+                        Err(e) => report_mining_failed(&e),
+                    },
+                    Err(e) => println!("❌ rotatekey error: {e}"),
+                }
+            }
             "whoami" => {
-                if let Some(kp) = &*keypair.lock().unwrap() {
-                    println!("🪪 pubkey={}", hex::encode(kp.verifying_key().to_bytes()));
+                if out.json {
+                    let pubkey = signer.lock().unwrap().as_deref().map(public_hex);
+                    out.print_json(&serde_json::json!({ "pubkey": pubkey }));
+                } else if let Some(current) = &*signer.lock().unwrap() {
+                    println!("🪪 pubkey={}", public_hex(current.as_ref()));
                 } else {
                     println!("(no key loaded)");
                 }
@@ -669,29 +2248,401 @@ async fn main() {
             "difficulty" if parts.len() == 2 => {
                 match parts[1].parse::<usize>() {
                     Ok(n) if (1..=9).contains(&n) => {
-                        chain.lock().unwrap().difficulty = n;
-                        println!("⛏️ difficulty set to {}", n);
+                        chain.write().await.difficulty = n;
+                        match hash_rate {
+                            Some(hps) => println!("⛏️ difficulty set to {n} (~{} per block at {hps:.0} H/s)", expected_time(n, hps)),
+                            None => println!("⛏️ difficulty set to {}", n),
+                        }
                     }
                     _ => println!("⚠️ choose 1..9"),
                 }
             }
-            "serve" if parts.len() == 2 => {
-                let port = parts[1].parse::<u16>().unwrap_or(3000);
-                let state = AppState {
-                    chain: chain.clone(),
-                    keypair: keypair.clone(),
+            "chainid" if parts.len() == 1 => {
+                let chain = chain.read().await;
+                match chain.signature_domain().chain_id() {
+                    Some(id) => println!("🔗 chain id {id} (format v{})", chain.version),
+                    None => println!("🔗 format v1 chain: signatures are not bound to a chain id"),
+                }
+            }
+            "chainid" if parts.len() == 2 => {
+                let mut chain = chain.write().await;
+                if chain.blocks.len() > 1 {
+                    println!("⚠️ the chain id can only be set before the first block");
+                } else {
+                    chain.version = CHAIN_FORMAT_VERSION;
+                    chain.chain_id = parts[1].to_string();
+                    println!("🔗 chain id set to {}", parts[1]);
+                }
+            }
+            "timerules" if parts.len() == 1 => print_time_rules(&chain.read().await.time_rules),
+            "timerules" if parts.len() == 3 => match (parse_limit(parts[1]), parse_limit(parts[2])) {
+                (Some(max_future_secs), Some(max_backward_secs)) => {
+                    let rules = TimeRules { max_future_secs, max_backward_secs };
+                    chain.write().await.time_rules = rules;
+                    print_time_rules(&rules);
+                }
+                _ => println!("⚠️ usage: timerules <secs-ahead|off> <secs-behind|off>"),
+            },
+            "events" if parts.len() == 1 => match chain.events.lock().unwrap().as_ref() {
+                Some(log) => println!(
+                    "📜 logging blocks to {} (rotated at {} MiB, {} old files kept)",
+                    log.path,
+                    log.max_bytes / 1024 / 1024,
+                    log.keep
+                ),
+                None => println!("📜 event log off"),
+            },
+            "events" if parts.len() == 2 && parts[1] == "off" => {
+                *chain.events.lock().unwrap() = None;
+                println!("📜 event log off");
+            }
+            "events" => match EventLog::parse(&parts[1..]) {
+                Ok(log) => {
+                    println!("📜 logging blocks to {}", log.path);
+                    *chain.events.lock().unwrap() = Some(log);
+                }
+                Err(e) => println!("❌ {e}"),
+            },
+            "calibrate" if parts.len() <= 3 => {
+                let set = parts.contains(&"--set");
+                let target = match parts[1..].iter().find(|&&p| p != "--set") {
+                    None => Some(DEFAULT_BLOCK_TIME),
+                    Some(secs) => secs.parse::<f64>().ok().filter(|s| *s > 0.0),
+                };
+                let Some(target) = target else {
+                    println!("⚠️ usage: calibrate [seconds per block] [--set]");
+                    continue;
+                };
+                if set && let Some(url) = replica_of(&follower) {
+                    println!("❌ read-only replica (following {url}). Use: unfollow");
+                    continue;
+                }
+                println!("⏱️ measuring hash rate for {}s…", CALIBRATE_SAMPLE.as_secs());
+                let Ok(hps) = task::spawn_blocking(|| Block::hash_rate(CALIBRATE_SAMPLE)).await else {
+                    println!("❌ benchmark failed");
+                    continue;
                 };
-                println!("🌐 starting server on 0.0.0.0:{port}");
-                // run server in background task
-                task::spawn(async move {
-                    let app = router(state).await;
-                    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::new(0, 0, 0, 0), port)).await.unwrap();
-                    axum::serve(listener, app).await.ok();
-                });
+                hash_rate = Some(hps);
+                let suggested = Block::difficulty_for(hps, target).min(9);
+                let current = chain.read().await.difficulty;
+                println!("🚀 {hps:.0} H/s");
+                print_calibration(hps, current, suggested, out);
+                if set {
+                    chain.write().await.difficulty = suggested;
+                    println!("⛏️ difficulty set to {suggested} (~{} per block)", expected_time(suggested, hps));
+                } else {
+                    println!("💡 difficulty {suggested} gives ~{} blocks; use: difficulty {suggested}", expected_time(suggested, hps));
+                }
+            }
+            "serve" => match ServeConfig::parse(&parts[1..]) {
+                Ok(cfg) => {
+                    let state = AppState {
+                        chain: chain.clone(),
+                        signer: signer.clone(),
+                        follower: follower.clone(),
+                        idempotency: Arc::new(IdempotencyCache::new(cfg.idempotency_window)),
+                        work: Arc::default(),
+                    };
+                    if let Err(e) = start_server(cfg, state).await {
+                        println!("❌ {e}");
+                    }
+                }
+                Err(e) => println!("❌ {e}"),
+            },
+            "follow" if parts.len() == 2 || parts.len() == 3 => {
+                let url = parts[1].trim_end_matches('/').to_string();
+                let secs = parts.get(2).and_then(|s| s.parse::<u64>().ok()).unwrap_or(5).max(1);
+                let mut slot = follower.lock().unwrap();
+                if let Some(old) = slot.take() {
+                    old.handle.abort();
+                }
+                let handle = task::spawn(follow_loop(url.clone(), Duration::from_secs(secs), chain.clone()));
+                println!("📡 following {url} every {secs}s (read-only)");
+                *slot = Some(Follower { url, handle });
             }
+            "unfollow" => match follower.lock().unwrap().take() {
+                Some(f) => {
+                    f.handle.abort();
+                    println!("🛑 stopped following {}", f.url);
+                }
+                None => println!("(not following)"),
+            },
             "help" => print_help(),
             "exit" => break,
             _ => println!("⚠️ unknown command. type: help"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    fn test_state(idempotency_window: Duration) -> AppState {
+        let key: Arc<dyn Signer> = Arc::new(SigningKey::from_bytes(&[7; 32]));
+        AppState {
+            chain: Arc::new(ChainHandle::new(Chain::genesis(1))),
+            signer: Arc::new(Mutex::new(Some(key))),
+            follower: Arc::default(),
+            idempotency: Arc::new(IdempotencyCache::new(idempotency_window)),
+            work: Arc::default(),
+        }
+    }
+
+    fn set(key: &str, value: &str) -> Json<SetReq> {
+        Json(SetReq { key: key.into(), value: value.into(), meta: OpMeta::default() })
+    }
+
+    fn with_header(name: impl axum::http::header::IntoHeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn signers_are_listed_with_their_blocks() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        http_set(HeaderMap::new(), State(state.clone()), set("a", "1")).await;
+        http_set(HeaderMap::new(), State(state.clone()), set("b", "2")).await;
+
+        let resp = http_signers(HeaderMap::new(), State(state.clone())).await;
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let signers: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(signers.len(), 1);
+        assert_eq!(signers[0]["pubkey"], public_hex(&SigningKey::from_bytes(&[7; 32])));
+        assert_eq!((&signers[0]["blocks"], &signers[0]["first_block"], &signers[0]["last_block"]), (&2.into(), &1.into(), &2.into()));
+    }
+
+    #[test]
+    fn diff_compares_saved_chain_files() {
+        let dir = std::env::temp_dir().join(format!("chain_kv_diff_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (left, right) = (dir.join("a.json"), dir.join("b.json"));
+        let (left, right) = (left.to_str().unwrap(), right.to_str().unwrap());
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut a = Chain::genesis(1);
+        a.append_signed(vec![Op::put("k", "1")], &key, None).unwrap();
+        let mut b = a.clone();
+        a.append_signed(vec![Op::put("k", "2")], &key, None).unwrap();
+        b.append_signed(vec![Op::put("j", "3")], &key, None).unwrap();
+        a.save(left).unwrap();
+        b.save(right).unwrap();
+
+        let (a, b) = (Chain::load(left).unwrap(), Chain::load(right).unwrap());
+        let diff = a.diff(&b);
+        assert_eq!(diff.fork_point, Some(1));
+        assert_eq!((diff.left.len(), diff.right.len()), (1, 1));
+        assert_eq!(diff.state.added.get("j").map(String::as_str), Some("3"));
+        assert_eq!(diff.state.changed.get("k"), Some(&("2".to_string(), "1".to_string())));
+        assert!(a.diff(&a).is_identical());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn preview_shows_the_batch_without_mining() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        assert_eq!(http_begin(State(state.clone())).await.0, "batch begun");
+        let put = AddPutReq { key: "a".into(), value: "1".into(), meta: OpMeta::default() };
+        assert_eq!(http_addput(State(state.clone()), Json(put)).await.0, "added");
+
+        let Json(preview) = http_preview(State(state.clone())).await.unwrap();
+        assert_eq!(preview.ops, 1);
+        assert_eq!(preview.changes.added.get("a").map(String::as_str), Some("1"));
+        assert!(preview.violations.is_empty());
+
+        *state.signer.lock().unwrap() = None;
+        let Json(preview) = http_preview(State(state.clone())).await.unwrap();
+        assert_eq!(preview.violations, ["no signing key loaded"]);
+        assert_eq!(state.chain.read().await.next_index(), 1);
+    }
+
+    #[tokio::test]
+    async fn idempotent_writes_are_replayed() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        let retry = with_header(IDEMPOTENCY_KEY, "k1");
+
+        let first = http_set(retry.clone(), State(state.clone()), set("grade", "A")).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()[BLOCK_INDEX], "1");
+        assert!(first.headers().get(IDEMPOTENT_REPLAYED).is_none());
+
+        // the retry gets the same block instead of mining another
+        let again = http_set(retry.clone(), State(state.clone()), set("grade", "A")).await;
+        assert_eq!(again.headers()[BLOCK_HASH], first.headers()[BLOCK_HASH]);
+        assert_eq!(again.headers()[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(state.chain.read().await.next_index(), 2);
+
+        // the key may not name a different write
+        let reused = http_set(retry, State(state.clone()), set("grade", "B")).await;
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // without a key every write mines
+        let fresh = http_set(HeaderMap::new(), State(state.clone()), set("grade", "A")).await;
+        assert_eq!(fresh.headers()[BLOCK_INDEX], "2");
+    }
+
+    #[test]
+    fn idempotency_keys_are_forgotten_after_the_window() {
+        let cache = IdempotencyCache::new(Duration::from_millis(20));
+        let ops = [Op::del("k")];
+        let first = cache.slot("k1", &ops).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.slot("k1", &ops).unwrap()));
+        assert!(cache.slot("k1", &[Op::del("other")]).is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        let later = cache.slot("k1", &[Op::del("other")]).unwrap();
+        assert!(!Arc::ptr_eq(&first, &later));
+    }
+
+    #[tokio::test]
+    async fn reads_are_not_modified_until_the_tag_changes() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        let etag = tip_etag(&*state.chain.read().await);
+        let read = |headers: HeaderMap| http_state(headers, State(state.clone()));
+
+        let resp = read(HeaderMap::new()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::ETAG], etag.as_str());
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], READ_CACHE_CONTROL);
+        let cached = with_header(header::IF_NONE_MATCH, &format!("\"other\", W/{etag}"));
+        assert_eq!(read(cached.clone()).await.status(), StatusCode::NOT_MODIFIED);
+
+        // a new tip, a new difficulty and pruning each change the tag
+        let mut tags = vec![etag];
+        http_set(HeaderMap::new(), State(state.clone()), set("a", "1")).await;
+        http_set(HeaderMap::new(), State(state.clone()), set("b", "2")).await;
+        tags.push(tip_etag(&*state.chain.read().await));
+        state.chain.write().await.difficulty = 2;
+        tags.push(tip_etag(&*state.chain.read().await));
+        let key = SigningKey::from_bytes(&[7; 32]);
+        assert_eq!(state.chain.write().await.prune(1, &key), Ok(2));
+        tags.push(tip_etag(&*state.chain.read().await));
+        assert_eq!(tags.iter().collect::<std::collections::HashSet<_>>().len(), 4);
+        assert_eq!(read(cached).await.status(), StatusCode::OK);
+        assert_eq!(read(with_header(header::IF_NONE_MATCH, &tags[3])).await.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn tip_long_polls_wait_for_a_new_block() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        let genesis = TipResp::of(&*state.chain.read().await);
+        let poll = |wait: u64, since: Option<&str>| {
+            http_tip(Query(TipQuery { wait: Some(wait), since: since.map(String::from) }), State(state.clone()))
+        };
+
+        // nothing lands: the same tip once the wait runs out
+        let started = Instant::now();
+        let Json(tip) = poll(50, None).await;
+        assert_eq!(tip, genesis);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // a waiter wakes as soon as a block lands
+        let started = Instant::now();
+        let (Json(tip), _) = tokio::join!(poll(10_000, Some(&genesis.hash)), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            http_set(HeaderMap::new(), State(state.clone()), set("a", "1")).await
+        });
+        assert_eq!(tip.index, 1);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // a tip that already moved past `since` is answered at once
+        let started = Instant::now();
+        let Json(now) = poll(10_000, Some(&genesis.hash)).await;
+        assert_eq!(now, tip);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn mining_work_hands_out_disjoint_ranges() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        let work = |size: Option<u64>| http_mining_work(State(state.clone()), Query(WorkQuery { size }));
+
+        let Json(a) = work(Some(10)).await.unwrap();
+        let Json(b) = work(None).await.unwrap();
+        assert_eq!((a.job, a.index, a.nonce_start, a.nonce_end), (1, 1, 0, 10));
+        assert_eq!((b.job, b.nonce_start, b.nonce_end), (1, 10, 10 + DEFAULT_WORK_RANGE));
+        assert!(b.timestamp >= a.timestamp);
+        assert!(work(Some(0)).await.is_err());
+        assert!(work(Some(MAX_WORK_RANGE + 1)).await.is_err());
+
+        // a new tip starts a new job from the first nonce
+        http_set(HeaderMap::new(), State(state.clone()), set("a", "1")).await;
+        let Json(c) = work(Some(10)).await.unwrap();
+        assert_eq!((c.job, c.index, c.nonce_start), (2, 2, 0));
+
+        *state.signer.lock().unwrap() = None;
+        assert_eq!(work(None).await.err().unwrap().0, "no signing key loaded");
+    }
+
+    #[tokio::test]
+    async fn mining_solutions_are_checked() {
+        let state = test_state(DEFAULT_IDEMPOTENCY_WINDOW);
+        let Json(w) = http_mining_work(State(state.clone()), Query(WorkQuery { size: None })).await.unwrap();
+        let scheme = state.chain.read().await.hash_scheme();
+        let wins = |timestamp: i64, nonce: u64| {
+            scheme.block_hash(w.index, timestamp, &w.merkle_root, &w.prev_hash, nonce).starts_with(&"0".repeat(w.difficulty))
+        };
+        let hit = |timestamp: i64| (w.nonce_start..w.nonce_end).find(|&nonce| wins(timestamp, nonce)).unwrap();
+        let found = |job: u64, nonce: u64, timestamp: i64| {
+            let state = state.clone();
+            async move {
+                let Json(msg) = http_mining_found(State(state), Json(FoundReq { job, nonce, timestamp, worker: None })).await;
+                msg
+            }
+        };
+
+        let miss = (w.nonce_start..).find(|&nonce| !wins(w.timestamp, nonce)).unwrap();
+        assert!(found(w.job, miss, w.timestamp).await.contains("insufficient PoW"));
+        let late = w.timestamp + 3600;
+        let msg = found(w.job, hit(late), late).await;
+        assert!(msg.contains("in the future"), "{msg}");
+        assert!(found(w.job + 1, 0, w.timestamp).await.contains("no longer current"));
+
+        let nonce = hit(w.timestamp);
+        assert!(found(w.job, nonce, w.timestamp).await.starts_with("accepted block 1"));
+        let chain = state.chain.read().await;
+        assert_eq!((chain.blocks[1].nonce, chain.blocks[1].timestamp), (nonce, w.timestamp));
+        drop(chain);
+        assert!(found(w.job, nonce, w.timestamp).await.contains("no longer current"));
+    }
+
+    #[test]
+    fn event_log_rotates() {
+        let dir = std::env::temp_dir().join(format!("chain_kv_events_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.jsonl");
+        let path = path.to_str().unwrap();
+        let lines = |file: &str| fs::read_to_string(file).map_or(0, |text| text.lines().count());
+
+        let chain = Chain::genesis(1);
+        let event = BlockEvent::of(&chain.blocks[0], 1, None);
+        let line = serde_json::to_vec(&event).unwrap().len() as u64 + 1;
+        let mut log = EventLog::open(path, 2 * line, 2).unwrap();
+        for _ in 0..7 {
+            log.append(&event).unwrap();
+        }
+        // two lines a file; the oldest file is gone
+        assert_eq!(lines(path), 1);
+        assert_eq!(lines(&format!("{path}.1")), 2);
+        assert_eq!(lines(&format!("{path}.2")), 2);
+        assert!(!FsPath::new(&format!("{path}.3")).exists());
+
+        // reopening carries on from the file's size
+        let mut log = EventLog::open(path, 2 * line, 2).unwrap();
+        log.append(&event).unwrap();
+        assert_eq!(lines(path), 2);
+        log.append(&event).unwrap();
+        assert_eq!((lines(path), lines(&format!("{path}.1"))), (1, 2));
+
+        // keeping no old files truncates in place
+        let mut log = EventLog::open(path, line, 0).unwrap();
+        log.append(&event).unwrap();
+        assert_eq!(lines(path), 1);
+
+        assert!(EventLog::parse(&[path, "--max-mb", "0"]).is_err());
+        assert!(EventLog::parse(&["--keep", "2"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
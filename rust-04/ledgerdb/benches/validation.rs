@@ -0,0 +1,51 @@
+//! `Block::validate` on blocks of a few hundred transactions, on a one-thread rayon
+//! pool (the serial baseline) and on the default pool.
+
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ledgerdb::core::{Block, Transaction, TransactionInput, TransactionOutput};
+use ledgerdb::crypto::{Address, Hash256, PublicKey, Signature, SignatureAlgorithm};
+
+fn address() -> Address {
+    Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5]))
+}
+
+/// A mined block extending `genesis` whose transactions each spend one output of
+/// the returned UTXO set
+fn block_with(genesis: &Block, transactions: u32) -> (Block, HashMap<String, TransactionOutput>) {
+    let mut utxo_set = HashMap::new();
+    let mut txs = vec![Transaction::coinbase(address(), 50, 1)];
+    for i in 0..transactions {
+        utxo_set.insert(format!("{}:{}", Hash256::zero(), i), TransactionOutput::new(1000, address()));
+        let input = TransactionInput::new(
+            Hash256::zero(),
+            i,
+            Some(Signature::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1; 64])),
+            Some(PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![2; 33])),
+        );
+        txs.push(Transaction::new(vec![input], vec![TransactionOutput::new(900, address())]));
+    }
+    let mut block = Block::new(1, genesis.hash(), txs, 1);
+    block.mine().expect("difficulty 1 mines");
+    (block, utxo_set)
+}
+
+fn validate(c: &mut Criterion) {
+    let genesis = Block::genesis(address(), 1_000_000);
+    let serial = rayon::ThreadPoolBuilder::new().num_threads(1).build().expect("one-thread pool");
+    let mut group = c.benchmark_group("block_validate");
+    for transactions in [100, 500, 1000] {
+        let (block, utxo_set) = block_with(&genesis, transactions);
+        group.bench_with_input(BenchmarkId::new("serial", transactions), &block, |b, block| {
+            b.iter(|| serial.install(|| block.validate(Some(&genesis), &utxo_set)).expect("valid block"))
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", transactions), &block, |b, block| {
+            b.iter(|| block.validate(Some(&genesis), &utxo_set).expect("valid block"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, validate);
+criterion_main!(benches);
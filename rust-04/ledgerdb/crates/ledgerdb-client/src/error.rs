@@ -0,0 +1,67 @@
+use ledgerdb_types::{ApiErrorBody, ErrorCode, Hash256};
+
+/// Result type for client calls
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// Errors returned by [`crate::Client`] and [`crate::Subscription`]
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// The node answered with a non-2xx status
+    #[error("API error {status} {}: {}", .body.code, .body.message)]
+    Api { status: u16, body: ApiErrorBody, request_id: Option<String> },
+    /// The request could not be sent or its body could not be read
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The WebSocket connection failed
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    /// A message did not decode into the expected type
+    #[error("Decode error: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// The response body was over the limit set with
+    /// [`crate::Client::with_max_response_size`]
+    #[error("Response exceeds {0} bytes")]
+    TooLarge(usize),
+    /// A snapshot chunk did not match its hash in the manifest
+    #[error("Snapshot chunk {0} does not match the manifest")]
+    InvalidChunk(Hash256),
+    /// A downloaded file could not be written or read back
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A successful response's envelope carried no data
+    #[error("Response envelope has no data")]
+    MissingData,
+    /// The base URL cannot be turned into a WebSocket URL
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        ClientError::WebSocket(Box::new(error))
+    }
+}
+
+impl ClientError {
+    /// The node's error code, for API errors
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            ClientError::Api { body, .. } => Some(body.code),
+            _ => None,
+        }
+    }
+
+    /// The id the node assigned to the failed request, for matching it with the
+    /// node's logs
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            ClientError::Api { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the node reported that the resource does not exist
+    pub fn is_not_found(&self) -> bool {
+        self.code() == Some(ErrorCode::NotFound)
+    }
+}
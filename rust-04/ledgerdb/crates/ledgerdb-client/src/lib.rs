@@ -0,0 +1,665 @@
+//! Async client for the LedgerDB REST and WebSocket API.
+//!
+//! [`Client`] has one typed method per REST endpoint, unwraps the node's response
+//! envelope and decodes the payload into the shared types from `ledgerdb-types`,
+//! which are re-exported as [`types`].
+//! [`Client::subscribe`] opens a [`Subscription`] to the `/ws` feed.
+//!
+//! ```no_run
+//! # async fn run() -> ledgerdb_client::Result<()> {
+//! let client = ledgerdb_client::Client::new("http://127.0.0.1:8080");
+//! let tip = client.latest_block().await?;
+//! println!("height {} with work {}", tip.block.index, tip.chain_work);
+//!
+//! let mut feed = client.subscribe(&["new_blocks"]).await?;
+//! while let Some(message) = feed.next().await {
+//!     println!("{:?}", message?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+mod snapshot;
+mod subscription;
+
+pub use error::{ClientError, Result};
+pub use ledgerdb_types as types;
+pub use snapshot::download_snapshot;
+pub use subscription::Subscription;
+
+use ledgerdb_types::{
+    AddressHistoryParams, AddressTransactionResponse, ApiErrorBody, ApiResponse, BalanceHistoryParams, BanRequest, BalanceHistoryResponse, BatchRequest, BatchResponse, BlockFilterResponse, BlockParams, BlockSubmitResponse,
+    BlockVerbosity, BlockView, BlockchainInfoResponse, BlockchainStatsResponse, ChainBlockResponse, CheckpointsResponse, CompactBlock,
+    CreatePsbtRequest, CreateTenantRequest, CreateUserRequest, DifficultyResponse, ErrorCode, Hash256, HeadersParams, HeadersResponse, HealthResponse, IntegrityReport, LogLevel, LoginRequest, LoginResponse, MemoSearchParams, MemoTransactionResponse, MerkleProof, MiningStatusResponse, NodeAddresses,
+    OrphanPoolResponse, PaginatedResponse, PaginationParams, PeerRecord, PendingTransactionResponse, PsbtFinalizeResponse, PsbtRequest, PsbtResponse, RewardResponse, RichListParams, Role, RichListResponse, SnapshotParams,
+    SimulationParams, SimulationStarted, SnapshotManifest, SupplyAudit, SupplyDistributionResponse, SyncStatus, TaskHealth, TenantInfo, Transaction, TransactionProofResponse, TransactionValidation, UserAccount, UtxoResponse, UtxoSnapshotResponse, VerifyProofRequest, VerifyProofResponse, VersionResponse, WebSocketStats,
+    WatchRequest, WatchedAddress, WatchedAddressResponse, REQUEST_ID_HEADER,
+};
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Client for one node's HTTP API
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    max_response_size: Option<usize>,
+}
+
+impl Client {
+    /// Client for the node at `base_url`, e.g. `http://127.0.0.1:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Client using a preconfigured `reqwest` client, for timeouts, proxies or
+    /// default headers
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self { http, base_url, max_response_size: None }
+    }
+
+    /// Refuse response bodies over `bytes` with [`ClientError::TooLarge`] instead
+    /// of buffering them, for talking to nodes that are not trusted
+    pub fn with_max_response_size(mut self, bytes: usize) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// `GET /health`
+    pub async fn health(&self) -> Result<HealthResponse> {
+        self.get("/health").await
+    }
+
+    /// `GET /version`
+    pub async fn version(&self) -> Result<VersionResponse> {
+        self.get("/version").await
+    }
+
+    /// `GET /api/blockchain/info`
+    pub async fn blockchain_info(&self) -> Result<BlockchainInfoResponse> {
+        self.get("/api/blockchain/info").await
+    }
+
+    /// `GET /api/blockchain/stats`
+    pub async fn blockchain_stats(&self) -> Result<BlockchainStatsResponse> {
+        self.get("/api/blockchain/stats").await
+    }
+
+    /// `GET /api/mining/difficulty`
+    pub async fn mining_difficulty(&self) -> Result<DifficultyResponse> {
+        self.get("/api/mining/difficulty").await
+    }
+
+    /// `GET /api/mining/status`
+    pub async fn mining_status(&self) -> Result<MiningStatusResponse> {
+        self.get("/api/mining/status").await
+    }
+
+    /// `GET /api/reward`
+    pub async fn reward(&self) -> Result<RewardResponse> {
+        self.get("/api/reward").await
+    }
+
+    /// `GET /api/checkpoints`
+    pub async fn checkpoints(&self) -> Result<CheckpointsResponse> {
+        self.get("/api/checkpoints").await
+    }
+
+    /// `GET /api/sync/status`
+    pub async fn sync_status(&self) -> Result<SyncStatus> {
+        self.get("/api/sync/status").await
+    }
+
+    /// `GET /api/p2p/addresses`
+    pub async fn node_addresses(&self) -> Result<NodeAddresses> {
+        self.get("/api/p2p/addresses").await
+    }
+
+    /// `GET /api/ws/stats`
+    pub async fn websocket_stats(&self) -> Result<WebSocketStats> {
+        self.get("/api/ws/stats").await
+    }
+
+    /// `GET /api/snapshot/utxo`; `height` defaults to the tip
+    pub async fn utxo_snapshot(&self, height: Option<u64>) -> Result<UtxoSnapshotResponse> {
+        let request = self.http.get(self.url("/api/snapshot/utxo")).query(&SnapshotParams { height });
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/snapshot/utxo/manifest`: the snapshot's chunks, for
+    /// [`download_snapshot`]; `height` defaults to the tip
+    pub async fn snapshot_manifest(&self, height: Option<u64>) -> Result<SnapshotManifest> {
+        let request = self.http.get(self.url("/api/snapshot/utxo/manifest")).query(&SnapshotParams { height });
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/snapshot/utxo/chunks/:hash`; a node that has not built the
+    /// snapshot yet builds it at `height`
+    pub async fn snapshot_chunk(&self, hash: &Hash256, height: Option<u64>) -> Result<Vec<u8>> {
+        let request = self
+            .http
+            .get(self.url(&format!("/api/snapshot/utxo/chunks/{}", hash)))
+            .query(&SnapshotParams { height });
+        let response = self.send(request).await?;
+        self.read_body(response).await
+    }
+
+    /// `GET /api/utxos`, ordered by the height that created them
+    pub async fn utxos(&self, params: &PaginationParams) -> Result<PaginatedResponse<UtxoResponse>> {
+        let request = self.http.get(self.url("/api/utxos")).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/snapshot/utxo/download`: the serialized snapshot, ready for
+    /// `ledgerdb --snapshot`
+    pub async fn download_utxo_snapshot(&self, height: Option<u64>) -> Result<Vec<u8>> {
+        let request = self
+            .http
+            .get(self.url("/api/snapshot/utxo/download"))
+            .query(&SnapshotParams { height });
+        let response = self.send(request).await?;
+        self.read_body(response).await
+    }
+
+    /// `GET /api/blocks`: newest first unless `params.order` is `asc`
+    pub async fn blocks(&self, params: &PaginationParams) -> Result<PaginatedResponse<ChainBlockResponse>> {
+        let request = self.http.get(self.url("/api/blocks")).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/blocks?verbosity=`: [`Client::blocks`] with raw blocks or only
+    /// transaction hashes, for paging through many blocks cheaply
+    pub async fn block_views(
+        &self,
+        params: &PaginationParams,
+        verbosity: BlockVerbosity,
+    ) -> Result<PaginatedResponse<BlockView>> {
+        let request = self
+            .http
+            .get(self.url("/api/blocks"))
+            .query(params)
+            .query(&BlockParams::from(verbosity));
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/blocks/latest`
+    pub async fn latest_block(&self) -> Result<ChainBlockResponse> {
+        self.get("/api/blocks/latest").await
+    }
+
+    /// `GET /api/blocks/height/:height`
+    pub async fn block_by_height(&self, height: u64) -> Result<ChainBlockResponse> {
+        self.get(&format!("/api/blocks/height/{}", height)).await
+    }
+
+    /// `GET /api/blocks/:hash`, with the hash in hex
+    pub async fn block_by_hash(&self, hash: &str) -> Result<ChainBlockResponse> {
+        self.get(&format!("/api/blocks/{}", hash)).await
+    }
+
+    /// `GET /api/blocks/height/:height?verbosity=`
+    pub async fn block_view_by_height(&self, height: u64, verbosity: BlockVerbosity) -> Result<BlockView> {
+        self.get_block_view(&format!("/api/blocks/height/{}", height), verbosity).await
+    }
+
+    /// `GET /api/blocks/:hash?verbosity=`, with the hash in hex
+    pub async fn block_view_by_hash(&self, hash: &str, verbosity: BlockVerbosity) -> Result<BlockView> {
+        self.get_block_view(&format!("/api/blocks/{}", hash), verbosity).await
+    }
+
+    /// `GET /api/blocks/:id/transactions`, by height or hex hash
+    pub async fn block_transactions(&self, block_id: &str) -> Result<Vec<Transaction>> {
+        self.get(&format!("/api/blocks/{}/transactions", block_id)).await
+    }
+
+    /// `GET /api/blocks/:id/filter`, by height or hex hash
+    pub async fn block_filter(&self, block_id: &str) -> Result<BlockFilterResponse> {
+        self.get(&format!("/api/blocks/{}/filter", block_id)).await
+    }
+
+    /// `GET /api/blocks/orphans`
+    pub async fn orphan_blocks(&self) -> Result<OrphanPoolResponse> {
+        self.get("/api/blocks/orphans").await
+    }
+
+    /// `POST /api/blocks/submit`: a rejected block is an API error with code
+    /// `BLOCK_REJECTED`. `block` is a [`Block`](ledgerdb_types::Block), or a node's own block type, which
+    /// serializes the same.
+    pub async fn submit_block<B: Serialize>(&self, block: &B) -> Result<BlockSubmitResponse> {
+        let request = self.http.post(self.url("/api/blocks/submit")).json(block);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `POST /api/blocks/compact`: status `incomplete` lists the transactions the
+    /// node lacks, and the full block should be submitted instead
+    pub async fn submit_compact_block<M: Serialize, T: Serialize>(&self, block: &CompactBlock<M, T>) -> Result<BlockSubmitResponse> {
+        let request = self.http.post(self.url("/api/blocks/compact")).json(block);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/headers`
+    pub async fn headers(&self, from: Option<u64>, count: Option<u64>) -> Result<HeadersResponse> {
+        let request = self.http.get(self.url("/api/headers")).query(&HeadersParams { from, count });
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `POST /api/batch`; a query that fails has an `error` instead of `data`
+    pub async fn batch(&self, request: &BatchRequest) -> Result<BatchResponse> {
+        let request = self.http.post(self.url("/api/batch")).json(request);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/transactions/pending`
+    pub async fn pending_transactions(&self, params: &PaginationParams) -> Result<PaginatedResponse<PendingTransactionResponse>> {
+        let request = self.http.get(self.url("/api/transactions/pending")).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `POST /api/transactions/validate`: every check the transaction passes or
+    /// fails, without submitting it
+    pub async fn validate_transaction(&self, transaction: &Transaction) -> Result<TransactionValidation> {
+        let request = self.http.post(self.url("/api/transactions/validate")).json(transaction);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `POST /api/psbt`: wrap an unsigned transaction for its keys to sign
+    pub async fn create_psbt(&self, transaction: &Transaction) -> Result<PsbtResponse> {
+        let body = CreatePsbtRequest { transaction: transaction.clone() };
+        let request = self.http.post(self.url("/api/psbt")).json(&body);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `POST /api/psbt/combine`: merge hex-encoded copies signed by different keys
+    pub async fn combine_psbts(&self, psbts: Vec<String>) -> Result<PsbtResponse> {
+        let request = self.http.post(self.url("/api/psbt/combine")).json(&PsbtRequest { psbts });
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `POST /api/psbt/finalize`: combine, sign off and add the transaction to the pool
+    pub async fn finalize_psbt(&self, psbts: Vec<String>) -> Result<PsbtFinalizeResponse> {
+        let request = self.http.post(self.url("/api/psbt/finalize")).json(&PsbtRequest { psbts });
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/transactions/:hash`, with the hash in hex
+    pub async fn transaction(&self, hash: &str) -> Result<Transaction> {
+        self.get(&format!("/api/transactions/{}", hash)).await
+    }
+
+    /// `GET /api/transactions/:hash/proof`
+    pub async fn transaction_proof(&self, hash: &str) -> Result<TransactionProofResponse> {
+        self.get(&format!("/api/transactions/{}/proof", hash)).await
+    }
+
+    /// `POST /api/proofs/verify`: whether `proof` leads from `leaf_hash` to `merkle_root`
+    pub async fn verify_proof(&self, merkle_root: Hash256, leaf_hash: Hash256, proof: &MerkleProof) -> Result<VerifyProofResponse> {
+        let body = VerifyProofRequest { merkle_root, leaf_hash, proof: proof.clone() };
+        let request = self.http.post(self.url("/api/proofs/verify")).json(&body);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/transactions/search`, newest first; needs a node keeping the
+    /// SQLite index
+    pub async fn search_transactions(&self, params: &MemoSearchParams) -> Result<PaginatedResponse<MemoTransactionResponse>> {
+        let request = self.http.get(self.url("/api/transactions/search")).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/addresses/{address}/transactions`, newest first; needs a node
+    /// keeping the SQLite index
+    pub async fn address_transactions(
+        &self,
+        address: &str,
+        params: &AddressHistoryParams,
+    ) -> Result<PaginatedResponse<AddressTransactionResponse>> {
+        let request = self.http.get(self.url(&format!("/api/addresses/{}/transactions", address))).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/addresses/{address}/balance/history`, oldest first; needs a node
+    /// keeping the SQLite index
+    pub async fn balance_history(
+        &self,
+        address: &str,
+        params: &BalanceHistoryParams,
+    ) -> Result<BalanceHistoryResponse> {
+        let request = self.http.get(self.url(&format!("/api/addresses/{}/balance/history", address))).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/stats/richlist`
+    pub async fn rich_list(&self, params: &RichListParams) -> Result<RichListResponse> {
+        let request = self.http.get(self.url("/api/stats/richlist")).query(params);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/stats/distribution`
+    pub async fn supply_distribution(&self) -> Result<SupplyDistributionResponse> {
+        self.get("/api/stats/distribution").await
+    }
+
+    /// `GET /api/audit/supply`
+    pub async fn audit_supply(&self) -> Result<SupplyAudit> {
+        self.get("/api/audit/supply").await
+    }
+
+    /// `GET /api/watch`
+    pub async fn watched_addresses(&self) -> Result<Vec<WatchedAddress>> {
+        self.get("/api/watch").await
+    }
+
+    /// `POST /api/watch`
+    pub async fn watch_address(&self, address: &str, label: Option<String>) -> Result<WatchedAddress> {
+        let body = WatchRequest { address: address.to_string(), label };
+        let request = self.http.post(self.url("/api/watch")).json(&body);
+        self.decode(self.send(request).await?).await
+    }
+
+    /// `GET /api/watch/:address`
+    pub async fn watched_address(&self, address: &str) -> Result<WatchedAddressResponse> {
+        self.get(&format!("/api/watch/{}", address)).await
+    }
+
+    /// `DELETE /api/watch/:address`
+    pub async fn unwatch_address(&self, address: &str) -> Result<()> {
+        self.send(self.http.delete(self.url(&format!("/api/watch/{}", address)))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/auth/login`; send the token back with a client built by
+    /// [`Client::with_http_client`] carrying an `Authorization: Bearer` header
+    pub async fn login(&self, username: &str, password: &str) -> Result<LoginResponse> {
+        let body = LoginRequest { username: username.to_string(), password: password.to_string() };
+        self.decode(self.send(self.http.post(self.url("/api/auth/login")).json(&body)).await?).await
+    }
+
+    /// `GET /api/users`
+    pub async fn users(&self) -> Result<Vec<UserAccount>> {
+        self.get("/api/users").await
+    }
+
+    /// `POST /api/users`
+    pub async fn create_user(&self, username: &str, password: &str, role: Role) -> Result<UserAccount> {
+        let body = CreateUserRequest { username: username.to_string(), password: password.to_string(), role };
+        self.decode(self.send(self.http.post(self.url("/api/users")).json(&body)).await?).await
+    }
+
+    /// `DELETE /api/users/:username`
+    pub async fn delete_user(&self, username: &str) -> Result<()> {
+        self.send(self.http.delete(self.url(&format!("/api/users/{}", username)))).await?;
+        Ok(())
+    }
+
+    /// `GET /admin/tasks`
+    pub async fn task_health(&self) -> Result<Vec<TaskHealth>> {
+        self.get("/admin/tasks").await
+    }
+
+    /// `GET /admin/verify`, or `POST` to also drop dangling index entries
+    pub async fn verify_storage(&self, repair: bool) -> Result<IntegrityReport> {
+        if repair {
+            self.decode(self.send(self.http.post(self.url("/admin/verify"))).await?).await
+        } else {
+            self.get("/admin/verify").await
+        }
+    }
+
+    /// `GET /admin/tenants`; reach a tenant's API with a client for `{base_url}/t/{name}`
+    pub async fn tenants(&self) -> Result<Vec<TenantInfo>> {
+        self.get("/admin/tenants").await
+    }
+
+    /// `POST /admin/tenants`
+    pub async fn create_tenant(&self, request: &CreateTenantRequest) -> Result<TenantInfo> {
+        self.decode(self.send(self.http.post(self.url("/admin/tenants")).json(request)).await?).await
+    }
+
+    /// `DELETE /admin/tenants/:name`
+    pub async fn archive_tenant(&self, name: &str) -> Result<()> {
+        self.send(self.http.delete(self.url(&format!("/admin/tenants/{}", name)))).await?;
+        Ok(())
+    }
+
+    /// `POST /admin/simulate`; the simulation keeps running on the node
+    pub async fn start_simulation(&self, params: &SimulationParams) -> Result<SimulationStarted> {
+        self.decode(self.send(self.http.post(self.url("/admin/simulate")).json(params)).await?).await
+    }
+
+    /// `GET /admin/peers`
+    pub async fn peers(&self) -> Result<Vec<PeerRecord>> {
+        self.get("/admin/peers").await
+    }
+
+    /// `POST /admin/peers/:address/ban`; `address` is `ip:port`
+    pub async fn ban_peer(&self, address: &str, request: &BanRequest) -> Result<PeerRecord> {
+        self.decode(self.send(self.http.post(self.url(&format!("/admin/peers/{}/ban", address))).json(request)).await?).await
+    }
+
+    /// `DELETE /admin/peers/:address/ban`
+    pub async fn unban_peer(&self, address: &str) -> Result<()> {
+        self.send(self.http.delete(self.url(&format!("/admin/peers/{}/ban", address)))).await?;
+        Ok(())
+    }
+
+    /// `GET /admin/logs/level`
+    pub async fn log_level(&self) -> Result<LogLevel> {
+        self.get("/admin/logs/level").await
+    }
+
+    /// `PUT /admin/logs/level`; lasts until the node restarts or reloads a changed `logging.level`
+    pub async fn set_log_level(&self, level: &str) -> Result<LogLevel> {
+        let request = self.http.put(self.url("/admin/logs/level")).json(&LogLevel { level: level.to_string() });
+        self.decode(self.send(request).await?).await
+    }
+
+    /// Connect to `/ws` and subscribe to `topics` (see
+    /// [`types::SUBSCRIPTION_TOPICS`])
+    pub async fn subscribe(&self, topics: &[&str]) -> Result<Subscription> {
+        let url = subscription::websocket_url(&self.base_url)?;
+        Subscription::connect(&url, topics).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn get_block_view(&self, path: &str, verbosity: BlockVerbosity) -> Result<BlockView> {
+        let request = self.http.get(self.url(path)).query(&BlockParams::from(verbosity));
+        self.decode(self.send(request).await?).await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.decode(self.send(self.http.get(self.url(path))).await?).await
+    }
+
+    /// Send `request`, turning non-2xx responses into [`ClientError::Api`]
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let header_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let text = String::from_utf8_lossy(&self.read_body(response).await?).into_owned();
+        let (body, request_id) = match serde_json::from_str::<ApiResponse<serde::de::IgnoredAny>>(&text) {
+            Ok(ApiResponse { error: Some(body), request_id, .. }) => (body, request_id.or(header_id)),
+            // Something other than the node answered, e.g. a proxy
+            _ => {
+                let code = ErrorCode::from_http_status(status.as_u16());
+                (ApiErrorBody { code, message: text, details: None }, header_id)
+            }
+        };
+        Err(ClientError::Api { status: status.as_u16(), body, request_id })
+    }
+
+    /// Unwrap the payload of a successful response's envelope
+    async fn decode<T: DeserializeOwned>(&self, response: Response) -> Result<T> {
+        let bytes = self.read_body(response).await?;
+        let envelope: ApiResponse<T> = serde_json::from_slice(&bytes)?;
+        envelope.data.ok_or(ClientError::MissingData)
+    }
+
+    /// Read `response`'s body, giving up as soon as it passes `max_response_size`
+    async fn read_body(&self, mut response: Response) -> Result<Vec<u8>> {
+        let Some(limit) = self.max_response_size else {
+            return Ok(response.bytes().await?.to_vec());
+        };
+        if response.content_length().is_some_and(|length| length > limit as u64) {
+            return Err(ClientError::TooLarge(limit));
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                return Err(ClientError::TooLarge(limit));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::{ws::{Message, WebSocketUpgrade}, Path},
+        http::StatusCode,
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::json;
+
+    async fn spawn_server() -> Client {
+        let app = Router::new()
+            .route(
+                "/api/blocks/height/:height",
+                get(|Path(height): Path<u64>| async move {
+                    if height > 0 {
+                        let body = json!({
+                            "data": null,
+                            "error": {"code": "NOT_FOUND", "message": "Block not found", "details": null},
+                            "request_id": "req-1", "timestamp": "2024-01-01T00:00:00Z"
+                        });
+                        return (StatusCode::NOT_FOUND, Json(body)).into_response();
+                    }
+                    let mut block = json!({
+                        "header": {
+                            "version": 1, "network": "mainnet", "previous_hash": ledgerdb_types::Hash256::zero(),
+                            "merkle_root": ledgerdb_types::Hash256([1; 32]),
+                            "timestamp": "2024-01-01T00:00:00Z", "difficulty": 1, "nonce": 7,
+                            "transaction_count": 0, "size": 0, "metadata_hash": null
+                        },
+                        "transactions": [],
+                        "metadata": {
+                            "proposer": null, "gas_limit": null, "gas_used": null,
+                            "block_reward": 50, "total_fees": 0, "average_fee": 0,
+                            "processing_time_ms": null, "extra_data": null
+                        },
+                        "index": 0
+                    });
+                    block["chain_work"] = json!(ledgerdb_types::format_chain_work(2));
+                    Json(ApiResponse::success(block, None)).into_response()
+                }),
+            )
+            .route("/api/watch/:address", axum::routing::delete(|| async { StatusCode::NO_CONTENT }))
+            .route("/teapot", get(|| async { (StatusCode::IM_A_TEAPOT, "short and stout") }))
+            .route(
+                "/ws",
+                get(|ws: WebSocketUpgrade| async move {
+                    ws.on_upgrade(|mut socket| async move {
+                        // Echo the subscription back as a confirmation
+                        if let Some(Ok(Message::Text(text))) = socket.recv().await {
+                            let request: ledgerdb_types::SubscriptionRequest =
+                                serde_json::from_str(&text).unwrap();
+                            let reply = json!({"type": "Subscribed", "data": {
+                                "topic": request.topic, "subscription_id": "s1",
+                                "success": true, "message": null}});
+                            let _ = socket.send(Message::Text(reply.to_string())).await;
+                        }
+                        let _ = socket.close().await;
+                    })
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Client::new(format!("http://{}/", addr))
+    }
+
+    #[tokio::test]
+    async fn test_decodes_chain_block() {
+        let client = spawn_server().await;
+        let block = client.block_by_height(0).await.unwrap();
+        assert_eq!(block.block.index, 0);
+        assert_eq!(block.block.header.nonce, 7);
+        assert_eq!(ledgerdb_types::parse_chain_work(&block.chain_work), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_api_errors_carry_code() {
+        let client = spawn_server().await;
+        let error = client.block_by_height(5).await.unwrap_err();
+        assert!(error.is_not_found());
+        assert_eq!(error.code(), Some(ErrorCode::NotFound));
+        assert_eq!(error.request_id(), Some("req-1"));
+
+        // A body that is not an envelope is kept as the message
+        let error = client.get::<serde_json::Value>("/teapot").await.unwrap_err();
+        match error {
+            ClientError::Api { status, body, .. } => {
+                assert_eq!(status, 418);
+                assert_eq!(body.code, ErrorCode::InvalidRequest);
+                assert_eq!(body.message, "short and stout");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let client = spawn_server().await;
+        let error = client.clone().with_max_response_size(64).block_by_height(0).await.unwrap_err();
+        assert!(matches!(error, ClientError::TooLarge(64)), "unexpected error: {error}");
+        let error = client.clone().with_max_response_size(8).get::<serde_json::Value>("/teapot").await.unwrap_err();
+        assert!(matches!(error, ClientError::TooLarge(8)), "unexpected error: {error}");
+        client.with_max_response_size(4096).block_by_height(0).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_empty_success_body() {
+        let client = spawn_server().await;
+        client.unwatch_address("ab").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscription_receives_messages() {
+        let client = spawn_server().await;
+        let mut feed = client.subscribe(&["new_blocks"]).await.unwrap();
+        match feed.next().await.unwrap().unwrap() {
+            ledgerdb_types::WsMessage::Subscribed(data) => assert_eq!(data.topic, "new_blocks"),
+            other => panic!("unexpected message: {other:?}"),
+        }
+        assert!(feed.next().await.is_none());
+    }
+
+    #[test]
+    fn test_websocket_url() {
+        assert_eq!(subscription::websocket_url("http://node:8080").unwrap(), "ws://node:8080/ws");
+        assert_eq!(subscription::websocket_url("https://node").unwrap(), "wss://node/ws");
+        assert!(subscription::websocket_url("node:8080").is_err());
+    }
+}
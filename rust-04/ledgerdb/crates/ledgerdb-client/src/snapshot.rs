@@ -0,0 +1,152 @@
+//! Chunked UTXO snapshot downloads.
+
+use crate::error::{ClientError, Result};
+use crate::Client;
+use futures_util::{stream, StreamExt};
+use ledgerdb_types::{Hash256, SnapshotManifest};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Download the snapshot file described by `manifest` from `nodes` into `output`;
+/// returns how many chunks had to be fetched.
+///
+/// The chunks are spread over the nodes, at most `max_in_flight` at a time, and a
+/// chunk that fails or does not match its hash is asked for from the next node.
+/// Each chunk is kept in `chunk_dir` under its hash as it arrives, so running the
+/// download again after an interruption only fetches the ones still missing. Once
+/// all are in they are joined into `output` and `chunk_dir` is removed.
+///
+/// # Panics
+///
+/// If `nodes` is empty.
+pub async fn download_snapshot(
+    nodes: &[Client],
+    manifest: &SnapshotManifest,
+    chunk_dir: &Path,
+    output: &Path,
+    max_in_flight: usize,
+) -> Result<usize> {
+    assert!(!nodes.is_empty(), "no nodes to download the snapshot from");
+    tokio::fs::create_dir_all(chunk_dir).await?;
+    let mut missing = Vec::new();
+    for (index, hash) in manifest.chunks.iter().enumerate() {
+        match tokio::fs::read(chunk_path(chunk_dir, hash)).await {
+            Ok(chunk) if manifest.verify_chunk(index, &chunk) => {}
+            _ => missing.push(index),
+        }
+    }
+
+    let fetched = missing.len();
+    let mut downloads = stream::iter(missing)
+        .map(|index| fetch_chunk(nodes, manifest, index, chunk_dir))
+        .buffer_unordered(max_in_flight.max(1));
+    while let Some(result) = downloads.next().await {
+        result?;
+    }
+
+    let mut file = tokio::fs::File::create(output).await?;
+    for hash in &manifest.chunks {
+        file.write_all(&tokio::fs::read(chunk_path(chunk_dir, hash)).await?).await?;
+    }
+    file.flush().await?;
+    tokio::fs::remove_dir_all(chunk_dir).await?;
+    Ok(fetched)
+}
+
+/// Fetch the `index`th chunk into `chunk_dir`, asking each node in turn from the
+/// `index`th until one sends the right bytes
+async fn fetch_chunk(nodes: &[Client], manifest: &SnapshotManifest, index: usize, chunk_dir: &Path) -> Result<()> {
+    let hash = &manifest.chunks[index];
+    let mut last_error = None;
+    for node in nodes.iter().cycle().skip(index % nodes.len()).take(nodes.len()) {
+        match node.snapshot_chunk(hash, Some(manifest.height)).await {
+            Ok(chunk) if manifest.verify_chunk(index, &chunk) => {
+                tokio::fs::write(chunk_path(chunk_dir, hash), chunk).await?;
+                return Ok(());
+            }
+            Ok(_) => last_error = Some(ClientError::InvalidChunk(*hash)),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.expect("nodes is not empty"))
+}
+
+fn chunk_path(chunk_dir: &Path, hash: &Hash256) -> PathBuf {
+    chunk_dir.join(hash.to_hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path as UrlPath, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+    use ledgerdb_types::ApiResponse;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const FILE: &[u8] = b"a snapshot file of a few chunks";
+
+    fn manifest() -> SnapshotManifest {
+        SnapshotManifest::new(4, Hash256([1; 32]), Hash256([2; 32]), FILE, 8)
+    }
+
+    /// A node serving `FILE`, or garbage for every chunk if `corrupt`; counts the
+    /// chunk requests it answers
+    async fn spawn_node(corrupt: bool) -> (Client, Arc<AtomicUsize>) {
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        let app = Router::new()
+            .route("/api/snapshot/utxo/manifest", get(|| async { Json(ApiResponse::success(manifest(), None)) }))
+            .route(
+                "/api/snapshot/utxo/chunks/:hash",
+                get(move |UrlPath(hash): UrlPath<String>| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let manifest = manifest();
+                    let Some(index) = manifest.chunks.iter().position(|chunk| chunk.to_hex() == hash) else {
+                        return StatusCode::NOT_FOUND.into_response();
+                    };
+                    if corrupt {
+                        return b"garbage!".to_vec().into_response();
+                    }
+                    FILE.chunks(8).nth(index).unwrap().to_vec().into_response()
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (Client::new(format!("http://{}", addr)), served)
+    }
+
+    #[tokio::test]
+    async fn test_download_from_several_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chunks, output) = (dir.path().join("chunks"), dir.path().join("utxo.snapshot"));
+        let (good, _) = spawn_node(false).await;
+        let (bad, _) = spawn_node(true).await;
+        let manifest = good.snapshot_manifest(None).await.unwrap();
+        assert_eq!(manifest, self::manifest());
+
+        // Chunks the bad node sends are refused and fetched from the good one
+        let fetched = download_snapshot(&[bad.clone(), good], &manifest, &chunks, &output, 2).await.unwrap();
+        assert_eq!(fetched, 4);
+        assert_eq!(std::fs::read(&output).unwrap(), FILE);
+        assert!(!chunks.exists());
+
+        let error = download_snapshot(&[bad], &manifest, &chunks, &output, 2).await.unwrap_err();
+        assert!(matches!(error, ClientError::InvalidChunk(_)), "unexpected error: {error}");
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chunks, output) = (dir.path().join("chunks"), dir.path().join("utxo.snapshot"));
+        let manifest = manifest();
+        std::fs::create_dir_all(&chunks).unwrap();
+        std::fs::write(chunk_path(&chunks, &manifest.chunks[0]), &FILE[..8]).unwrap();
+        std::fs::write(chunk_path(&chunks, &manifest.chunks[1]), b"torn").unwrap();
+
+        let (node, served) = spawn_node(false).await;
+        let fetched = download_snapshot(&[node], &manifest, &chunks, &output, 1).await.unwrap();
+        assert_eq!((fetched, served.load(Ordering::SeqCst)), (3, 3));
+        assert_eq!(std::fs::read(&output).unwrap(), FILE);
+    }
+}
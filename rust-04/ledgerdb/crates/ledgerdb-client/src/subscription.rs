@@ -0,0 +1,79 @@
+//! WebSocket subscriptions to `/ws`.
+
+use crate::error::{ClientError, Result};
+use futures_util::{SinkExt, StreamExt};
+use ledgerdb_types::{SubscriptionRequest, WsMessage};
+use tokio_tungstenite::{
+    connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream,
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// A live WebSocket connection delivering the messages of its subscribed topics.
+///
+/// Only `ws://` is supported; put a TLS-terminating proxy in front of the node to
+/// reach it from outside.
+pub struct Subscription {
+    socket: Socket,
+}
+
+impl Subscription {
+    pub(crate) async fn connect(url: &str, topics: &[&str]) -> Result<Self> {
+        let (socket, _) = connect_async(url).await?;
+        let mut subscription = Self { socket };
+        for topic in topics {
+            subscription.subscribe(topic).await?;
+        }
+        Ok(subscription)
+    }
+
+    /// Start receiving messages for `topic`
+    pub async fn subscribe(&mut self, topic: &str) -> Result<()> {
+        self.send(&SubscriptionRequest::subscribe(topic)).await
+    }
+
+    /// Stop receiving messages for `topic`
+    pub async fn unsubscribe(&mut self, topic: &str) -> Result<()> {
+        self.send(&SubscriptionRequest::unsubscribe(topic)).await
+    }
+
+    /// The next message from the node, or `None` once the connection closed.
+    ///
+    /// Subscription confirmations and keepalives are returned like any other
+    /// message; WebSocket-level pings are answered automatically.
+    pub async fn next(&mut self) -> Option<Result<WsMessage>> {
+        loop {
+            match self.socket.next().await? {
+                Ok(Message::Text(text)) => {
+                    return Some(serde_json::from_str(&text).map_err(ClientError::from))
+                }
+                Ok(Message::Close(_)) => return None,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+
+    /// Close the connection
+    pub async fn close(mut self) -> Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+
+    async fn send(&mut self, request: &SubscriptionRequest) -> Result<()> {
+        let text = serde_json::to_string(request)?;
+        self.socket.send(Message::Text(text)).await?;
+        Ok(())
+    }
+}
+
+/// The `/ws` URL of the node at `base_url`
+pub(crate) fn websocket_url(base_url: &str) -> Result<String> {
+    if let Some(rest) = base_url.strip_prefix("http://") {
+        Ok(format!("ws://{}/ws", rest))
+    } else if let Some(rest) = base_url.strip_prefix("https://") {
+        Ok(format!("wss://{}/ws", rest))
+    } else {
+        Err(ClientError::InvalidUrl(base_url.to_string()))
+    }
+}
@@ -0,0 +1,511 @@
+//! Chain data as it appears on the wire.
+//!
+//! These mirror the node's own block, transaction and hash types field for field and
+//! serialize identically, but carry no validation or hashing logic.
+
+use chrono::{DateTime, Utc};
+use serde::de::{self, value::SeqAccessDeserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A 32-byte hash, serialized as an array of 32 numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Hash256(pub [u8; 32]);
+
+impl Hash256 {
+    pub fn zero() -> Self {
+        Self([0u8; 32])
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    pub fn from_hex(hex_str: &str) -> Result<Self, hex::FromHexError> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(hex_str, &mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for Hash256 {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+/// Network a node belongs to.
+///
+/// Block headers carry it and addresses encode it in their version byte, so blocks
+/// and addresses from one network are never accepted on another.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    /// Production network
+    #[default]
+    Mainnet,
+    /// Public test network
+    Testnet,
+    /// Local development network
+    Regtest,
+}
+
+impl Network {
+    pub const ALL: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Regtest];
+
+    /// Version byte leading the network's encoded addresses
+    pub fn address_version(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet => 0x6f,
+            Network::Regtest => 0x3c,
+        }
+    }
+
+    fn from_address_version(version: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|network| network.address_version() == version)
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest => "regtest",
+        })
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            "regtest" => Ok(Network::Regtest),
+            _ => Err(format!("unknown network '{}'", s)),
+        }
+    }
+}
+
+/// Network whose addresses `Display`, `FromStr` and serde use, as an index into [`Network::ALL`]
+static ADDRESS_NETWORK: AtomicU8 = AtomicU8::new(0);
+
+/// Encode and accept addresses of `network` from now on, process-wide. A node sets
+/// its own network at startup; a client talking to a test node sets that one.
+pub fn set_address_network(network: Network) {
+    let index = Network::ALL.iter().position(|n| *n == network).unwrap_or(0);
+    ADDRESS_NETWORK.store(index as u8, Ordering::Relaxed);
+}
+
+/// Network set by [`set_address_network`]; mainnet until then
+pub fn address_network() -> Network {
+    Network::ALL[ADDRESS_NETWORK.load(Ordering::Relaxed) as usize]
+}
+
+/// An address: the hash of a public key.
+///
+/// In JSON it is a Base58Check string for the [`address_network`] (see
+/// [`Address::encode_for`]); binary formats keep the bare hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema), schema(value_type = String))]
+pub struct Address(pub Hash256);
+
+impl Address {
+    pub fn as_hash(&self) -> &Hash256 {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+
+    pub fn from_hex(hex_str: &str) -> Result<Self, hex::FromHexError> {
+        Hash256::from_hex(hex_str).map(Self)
+    }
+
+    /// Encode for the [`address_network`]
+    pub fn encode(&self) -> String {
+        self.encode_for(address_network())
+    }
+
+    /// Base58Check: the network's version byte, the hash, then the first four bytes
+    /// of the double SHA-256 of both
+    pub fn encode_for(&self, network: Network) -> String {
+        let mut payload = Vec::with_capacity(37);
+        payload.push(network.address_version());
+        payload.extend_from_slice(&self.0 .0);
+        let checksum = address_checksum(&payload);
+        payload.extend_from_slice(&checksum);
+        bs58::encode(payload).into_string()
+    }
+
+    /// Parse an address of the [`address_network`]
+    pub fn decode(encoded: &str) -> Result<Self, AddressError> {
+        Self::decode_for(encoded, address_network())
+    }
+
+    /// Parse what [`Self::encode_for`] produced for `network`, rejecting a mistyped
+    /// character by its checksum
+    pub fn decode_for(encoded: &str, network: Network) -> Result<Self, AddressError> {
+        let bytes = bs58::decode(encoded.trim()).into_vec().map_err(|_| AddressError::Base58)?;
+        if bytes.len() != 37 {
+            return Err(AddressError::Length(bytes.len()));
+        }
+        let (payload, checksum) = bytes.split_at(33);
+        if address_checksum(payload) != checksum {
+            return Err(AddressError::Checksum);
+        }
+        if payload[0] != network.address_version() {
+            return Err(match Network::from_address_version(payload[0]) {
+                Some(other) => AddressError::Network(other),
+                None => AddressError::Version(payload[0]),
+            });
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&payload[1..]);
+        Ok(Self(Hash256(hash)))
+    }
+}
+
+fn address_checksum(payload: &[u8]) -> [u8; 4] {
+    let digest = Sha256::digest(Sha256::digest(payload));
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s)
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_newtype_struct("Address", &self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(AddressVisitor)
+        } else {
+            Hash256::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+/// Reads the encoded string, or the byte array JSON held before addresses were encoded
+struct AddressVisitor;
+
+impl<'de> Visitor<'de> for AddressVisitor {
+    type Value = Address;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a Base58Check address")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Address, E> {
+        Address::decode(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Address, A::Error> {
+        <[u8; 32]>::deserialize(SeqAccessDeserializer::new(seq)).map(|bytes| Address(Hash256(bytes)))
+    }
+}
+
+/// Why a string is not an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// A character outside the Base58 alphabet
+    Base58,
+    /// Decoded to this many bytes instead of 37
+    Length(usize),
+    /// The checksum does not match: most likely a typo
+    Checksum,
+    /// An address of this other network
+    Network(Network),
+    /// A version byte no network uses
+    Version(u8),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Base58 => f.write_str("not Base58"),
+            AddressError::Length(len) => write!(f, "decodes to {} bytes, expected 37", len),
+            AddressError::Checksum => f.write_str("checksum mismatch"),
+            AddressError::Network(network) => write!(f, "address is for {}", network),
+            AddressError::Version(version) => write!(f, "unknown version byte {:#04x}", version),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Signature scheme of a key or signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum SignatureAlgorithm {
+    EcdsaSecp256k1,
+    Ed25519,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Signature {
+    pub algorithm: SignatureAlgorithm,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PublicKey {
+    pub algorithm: SignatureAlgorithm,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TransactionInput {
+    pub previous_tx_hash: Hash256,
+    pub output_index: u32,
+    pub signature: Option<Signature>,
+    pub public_key: Option<PublicKey>,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TransactionOutput {
+    pub amount: u64,
+    pub recipient: Address,
+    pub script: Option<Vec<u8>>,
+    pub spent: bool,
+    pub created_at_height: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TransactionFee {
+    pub base_fee: u64,
+    pub per_byte_fee: u64,
+    pub priority_multiplier: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Transaction {
+    pub id: String,
+    pub version: u32,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+    pub fee: TransactionFee,
+    /// Block height (below 500,000,000) or Unix time before which the transaction cannot be mined
+    pub lock_time: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Optional memo of at most 256 bytes, covered by the transaction hash
+    #[serde(alias = "data")]
+    pub memo: Option<Vec<u8>>,
+    pub size: Option<usize>,
+}
+
+impl Transaction {
+    /// Whether this is a block's coinbase
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1
+            && self.inputs[0].previous_tx_hash == Hash256::zero()
+            && self.inputs[0].output_index == u32::MAX
+    }
+
+    pub fn total_output(&self) -> u64 {
+        self.outputs.iter().map(|output| output.amount).sum()
+    }
+
+    /// The memo as text, if it is valid UTF-8
+    pub fn memo_text(&self) -> Option<&str> {
+        self.memo.as_deref().and_then(|memo| std::str::from_utf8(memo).ok())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockHeader {
+    pub version: u32,
+    pub network: Network,
+    pub previous_hash: Hash256,
+    pub merkle_root: Hash256,
+    pub timestamp: DateTime<Utc>,
+    pub difficulty: u32,
+    pub nonce: u64,
+    pub transaction_count: u32,
+    pub size: u64,
+    pub metadata_hash: Option<Hash256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockMetadata {
+    pub proposer: Option<String>,
+    pub gas_limit: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub block_reward: u64,
+    pub total_fees: u64,
+    pub average_fee: u64,
+    pub processing_time_ms: Option<u64>,
+    pub extra_data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<Transaction>,
+    pub metadata: BlockMetadata,
+    pub index: u64,
+}
+
+/// Sibling hashes from a transaction up to its block's merkle root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MerkleProof {
+    pub leaf_hash: Hash256,
+    pub leaf_index: usize,
+    pub proof_hashes: Vec<Hash256>,
+    /// `true` where the sibling is on the right
+    pub proof_directions: Vec<bool>,
+    pub root_hash: Hash256,
+}
+
+/// How the block subsidy evolves with height
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RewardSchedule {
+    Fixed {
+        reward: u64,
+    },
+    Halving {
+        initial: u64,
+        interval: u64,
+    },
+    LinearDecay {
+        initial: u64,
+        decrement: u64,
+        interval: u64,
+        floor: u64,
+    },
+}
+
+/// Where a node started from a snapshot is in checking the history below it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum SnapshotValidation {
+    Pending { height: u64, commitment: Hash256 },
+    Valid { height: u64 },
+    Invalid { height: u64, reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_hex_round_trip() {
+        let hash = Hash256([0xab; 32]);
+        assert_eq!(hash.to_hex(), "ab".repeat(32));
+        assert_eq!(hash.to_hex().parse::<Hash256>().unwrap(), hash);
+        assert!(Hash256::from_hex("abcd").is_err());
+    }
+
+    #[test]
+    fn test_hash_serializes_as_byte_array() {
+        let json = serde_json::to_value(Hash256([1; 32])).unwrap();
+        assert_eq!(json, serde_json::json!(vec![1; 32]));
+    }
+
+    #[test]
+    fn test_address_encoding() {
+        let address = Address(Hash256([1; 32]));
+        let encoded = address.encode();
+        assert_eq!(encoded.parse::<Address>().unwrap(), address);
+        assert_eq!(serde_json::to_value(address).unwrap(), serde_json::json!(encoded));
+        assert_eq!(serde_json::from_value::<Address>(serde_json::json!(encoded)).unwrap(), address);
+        // JSON from before encoded addresses still reads
+        assert_eq!(serde_json::from_value::<Address>(serde_json::json!(vec![1; 32])).unwrap(), address);
+
+        // Any single mistyped character is caught
+        for (i, c) in encoded.char_indices() {
+            let typo = if c == '2' { '3' } else { '2' };
+            let mistyped = format!("{}{}{}", &encoded[..i], typo, &encoded[i + 1..]);
+            assert!(Address::decode(&mistyped).is_err(), "{}", mistyped);
+        }
+        assert_eq!(Address::decode(&address.to_hex()), Err(AddressError::Base58));
+        assert_eq!(Address::decode("1111"), Err(AddressError::Length(4)));
+    }
+
+    #[test]
+    fn test_address_networks() {
+        let address = Address(Hash256([7; 32]));
+        for network in Network::ALL {
+            let encoded = address.encode_for(network);
+            assert_eq!(Address::decode_for(&encoded, network), Ok(address));
+            for other in Network::ALL.into_iter().filter(|other| *other != network) {
+                assert_eq!(Address::decode_for(&encoded, other), Err(AddressError::Network(network)));
+            }
+        }
+        assert_eq!("Regtest".parse::<Network>(), Ok(Network::Regtest));
+        assert_eq!(serde_json::to_value(Network::Testnet).unwrap(), "testnet");
+    }
+
+    #[test]
+    fn test_transaction_memo_reads_legacy_data_field() {
+        let json = serde_json::json!({
+            "id": "t", "version": 1, "inputs": [], "outputs": [],
+            "fee": { "base_fee": 0, "per_byte_fee": 0, "priority_multiplier": 1.0 },
+            "lock_time": 0, "timestamp": "2024-01-01T00:00:00Z", "data": b"INV-7".to_vec(), "size": null,
+        });
+        let transaction: Transaction = serde_json::from_value(json).unwrap();
+        assert_eq!(transaction.memo_text(), Some("INV-7"));
+        assert_eq!(serde_json::to_value(&transaction).unwrap()["memo"], serde_json::json!(b"INV-7".to_vec()));
+    }
+
+    #[test]
+    fn test_reward_schedule_tagging() {
+        let schedule: RewardSchedule =
+            serde_json::from_str(r#"{"type":"halving","initial":50,"interval":210000}"#).unwrap();
+        assert_eq!(schedule, RewardSchedule::Halving { initial: 50, interval: 210000 });
+    }
+}
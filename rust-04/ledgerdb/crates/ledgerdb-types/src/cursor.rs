@@ -0,0 +1,226 @@
+//! Cursor pagination.
+//!
+//! Page numbers shift when blocks or transactions arrive between two requests, so a
+//! client walking a listing page by page sees items twice or skips them. A
+//! [`Cursor`] instead names the last item a page returned and the direction of the
+//! walk; the next page starts right after that item, however the listing changed.
+//!
+//! Listings are ordered oldest first by a [`Cursor::position`] (a block height, or
+//! a timestamp for the mempool) with [`Cursor::key`] breaking ties. Clients treat
+//! cursors as opaque tokens and hand back the `next_cursor` or `prev_cursor` of
+//! the previous page's [`PaginationMeta`](crate::PaginationMeta).
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Version byte leading every encoded cursor
+const CURSOR_VERSION: u8 = 1;
+
+/// Which way a cursor walks through a listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorDirection {
+    /// Towards older items; pages list items newest first
+    Backward,
+    /// Towards newer items; pages list items oldest first
+    Forward,
+}
+
+/// The last item of a page and the direction to continue in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub direction: CursorDirection,
+    /// Block height of the item, or the timestamp in milliseconds for pending transactions
+    pub position: u64,
+    /// Identifier of the item among those at `position`; empty for blocks
+    pub key: String,
+}
+
+impl Cursor {
+    pub fn new(direction: CursorDirection, position: u64, key: impl Into<String>) -> Self {
+        Self { direction, position, key: key.into() }
+    }
+
+    /// The opaque token sent to clients
+    pub fn encode(&self) -> String {
+        let mut bytes = Vec::with_capacity(10 + self.key.len());
+        bytes.push(CURSOR_VERSION);
+        bytes.push(match self.direction {
+            CursorDirection::Backward => 0,
+            CursorDirection::Forward => 1,
+        });
+        bytes.extend_from_slice(&self.position.to_be_bytes());
+        bytes.extend_from_slice(self.key.as_bytes());
+        hex::encode(bytes)
+    }
+
+    /// Parse a token produced by [`Cursor::encode`]
+    pub fn decode(token: &str) -> Option<Self> {
+        let bytes = hex::decode(token).ok()?;
+        if bytes.len() < 10 || bytes[0] != CURSOR_VERSION {
+            return None;
+        }
+        let direction = match bytes[1] {
+            0 => CursorDirection::Backward,
+            1 => CursorDirection::Forward,
+            _ => return None,
+        };
+        let position = u64::from_be_bytes(bytes[2..10].try_into().ok()?);
+        let key = String::from_utf8(bytes[10..].to_vec()).ok()?;
+        Some(Self { direction, position, key })
+    }
+
+    /// Order of this cursor's item relative to an item at `position` with `key`
+    fn cmp_item(&self, (position, key): (u64, &str)) -> Ordering {
+        (self.position, self.key.as_str()).cmp(&(position, key))
+    }
+}
+
+/// Where one page lies in a listing sorted oldest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageWindow {
+    /// Indices of the page's items in the listing
+    pub range: Range<usize>,
+    /// Whether the page lists its items newest first
+    pub newest_first: bool,
+    /// Continues past the page's last item in the direction of the walk
+    pub next_cursor: Option<Cursor>,
+    /// Turns back from the page's first item
+    pub prev_cursor: Option<Cursor>,
+}
+
+impl PageWindow {
+    /// The page of `limit` items that `cursor` points at or, without one, page
+    /// number `page` counting back from the newest item
+    ///
+    /// `key_at(i)` is the position and key of the `i`th of the `len` items. Page
+    /// numbers keep their old meaning: `ascending` only flips the order within the
+    /// page. With a cursor the walk direction decides the order instead.
+    pub fn new<'a>(
+        len: usize,
+        limit: u64,
+        page: u64,
+        cursor: Option<&Cursor>,
+        ascending: bool,
+        key_at: impl Fn(usize) -> (u64, &'a str),
+    ) -> Self {
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        let (range, newest_first) = match cursor {
+            None => {
+                let skipped = usize::try_from(page).unwrap_or(usize::MAX).saturating_mul(limit);
+                let end = len.saturating_sub(skipped);
+                (end.saturating_sub(limit)..end, !ascending)
+            }
+            // The cursor's own item is excluded, and need not exist any more
+            Some(cursor) => match cursor.direction {
+                CursorDirection::Backward => {
+                    let end = partition_point(len, |i| cursor.cmp_item(key_at(i)) == Ordering::Greater);
+                    (end.saturating_sub(limit)..end, true)
+                }
+                CursorDirection::Forward => {
+                    let start = partition_point(len, |i| cursor.cmp_item(key_at(i)) != Ordering::Less);
+                    (start..start.saturating_add(limit).min(len), false)
+                }
+            },
+        };
+
+        let cursor_at = |direction, index| {
+            let (position, key) = key_at(index);
+            Cursor::new(direction, position, key)
+        };
+        let (older, newer) = if range.is_empty() {
+            (None, None)
+        } else {
+            (
+                (range.start > 0).then(|| cursor_at(CursorDirection::Backward, range.start)),
+                (range.end < len).then(|| cursor_at(CursorDirection::Forward, range.end - 1)),
+            )
+        };
+        let (next_cursor, prev_cursor) = match cursor.map(|cursor| cursor.direction) {
+            Some(CursorDirection::Forward) => (newer, older),
+            _ => (older, newer),
+        };
+        Self { range, newest_first, next_cursor, prev_cursor }
+    }
+}
+
+/// Number of leading indices in `0..len` for which `pred` holds, `pred` being true
+/// up to some index and false after it
+fn partition_point(len: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heights(len: usize) -> impl Fn(usize) -> (u64, &'static str) {
+        move |i| {
+            assert!(i < len);
+            (i as u64, "")
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::new(CursorDirection::Forward, 1_700_000_000_000, "ab:1");
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode("not hex"), None);
+        assert_eq!(Cursor::decode("0200"), None);
+    }
+
+    #[test]
+    fn test_page_numbers_count_back_from_newest() {
+        let window = PageWindow::new(5, 2, 0, None, false, heights(5));
+        assert_eq!(window.range, 3..5);
+        assert!(window.newest_first);
+        assert_eq!(window.next_cursor, Some(Cursor::new(CursorDirection::Backward, 3, "")));
+        assert_eq!(window.prev_cursor, None);
+
+        let last = PageWindow::new(5, 2, 2, None, true, heights(5));
+        assert_eq!(last.range, 0..1);
+        assert!(!last.newest_first);
+        assert_eq!(last.next_cursor, None);
+
+        assert!(PageWindow::new(5, 2, u64::MAX, None, false, heights(5)).range.is_empty());
+    }
+
+    #[test]
+    fn test_cursor_walks_are_stable() {
+        let first = PageWindow::new(5, 2, 0, None, false, heights(5));
+        // Two blocks arrive between the requests; the walk continues where it left off
+        let second = PageWindow::new(7, 2, 0, first.next_cursor.as_ref(), false, heights(7));
+        assert_eq!(second.range, 1..3);
+        assert_eq!(second.prev_cursor, Some(Cursor::new(CursorDirection::Forward, 2, "")));
+
+        let back = PageWindow::new(7, 2, 0, second.prev_cursor.as_ref(), false, heights(7));
+        assert_eq!(back.range, 3..5);
+        assert!(!back.newest_first);
+        assert_eq!(back.next_cursor, Some(Cursor::new(CursorDirection::Forward, 4, "")));
+        assert_eq!(back.prev_cursor, Some(Cursor::new(CursorDirection::Backward, 3, "")));
+
+        let end = PageWindow::new(7, 10, 0, back.next_cursor.as_ref(), false, heights(7));
+        assert_eq!(end.range, 5..7);
+        assert_eq!(end.next_cursor, None);
+    }
+
+    #[test]
+    fn test_cursor_keys_break_ties() {
+        let items = [(1, "a"), (1, "b"), (1, "c"), (2, "a")];
+        let key_at = |i: usize| items[i];
+        let cursor = Cursor::new(CursorDirection::Forward, 1, "a");
+        assert_eq!(PageWindow::new(4, 2, 0, Some(&cursor), false, key_at).range, 1..3);
+
+        // The cursor's own item may have gone, e.g. a spent UTXO
+        let cursor = Cursor::new(CursorDirection::Backward, 1, "bb");
+        assert_eq!(PageWindow::new(4, 2, 0, Some(&cursor), false, key_at).range, 0..2);
+    }
+}
@@ -0,0 +1,290 @@
+//! The envelope around every JSON response.
+//!
+//! Successful responses carry their payload in `data`, failed ones an
+//! [`ApiErrorBody`] in `error`; both carry the request id (also sent as the
+//! `x-request-id` header) and the time the response was produced:
+//!
+//! ```json
+//! {"data": {...}, "error": null, "request_id": "6f0c...", "timestamp": "2024-01-01T00:00:00Z"}
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Header carrying the request id, echoed from the request when the client sets it
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Machine-readable reason a request failed
+///
+/// Serialized in `SCREAMING_SNAKE_CASE`. Codes the client does not know decode as
+/// [`ErrorCode::Unknown`], so new codes can be added without breaking it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request could not be parsed: bad JSON, query string or path segment (400)
+    InvalidRequest,
+    /// A path or body field is not a valid hex hash (400)
+    InvalidHash,
+    /// A path or body field is not a valid address (400)
+    InvalidAddress,
+    /// A block id is neither a height nor a hash (400)
+    InvalidBlockId,
+    /// A UTXO id is not `<tx hash>:<output index>` (400)
+    InvalidUtxoId,
+    /// An index is out of range for the object it refers to (400)
+    InvalidIndex,
+    /// The request is well-formed but violates a consensus or API rule (400)
+    ValidationError,
+    /// A submitted block failed validation; `details` names the block (400)
+    BlockRejected,
+    /// The chain rejected the operation (400)
+    BlockchainError,
+    /// A signature or key could not be processed (400)
+    CryptoError,
+    /// A merkle proof could not be built for the transaction (400)
+    ProofGenerationFailed,
+    /// Authentication is missing or invalid (401)
+    Unauthorized,
+    /// The caller may not perform this operation (403)
+    Forbidden,
+    /// The resource does not exist (404)
+    NotFound,
+    /// The route exists but not for this HTTP method (405)
+    MethodNotAllowed,
+    /// The request took longer than its route allows (408)
+    RequestTimeout,
+    /// The request conflicts with the node's current state (409)
+    Conflict,
+    /// The request body exceeds the configured limit (413)
+    PayloadTooLarge,
+    /// The client exceeded its request rate (429)
+    RateLimited,
+    /// The miner failed (500)
+    MiningError,
+    /// The node's storage failed (500)
+    StorageError,
+    /// The node is misconfigured (500)
+    ConfigError,
+    /// An I/O operation on the node failed (500)
+    IoError,
+    /// Data stored on the node could not be encoded or decoded (500)
+    SerializationError,
+    /// Any other failure on the node (500)
+    InternalError,
+    /// The endpoint exists but is not implemented yet (501)
+    NotImplemented,
+    /// The node cannot serve the request right now, e.g. while shutting down (503)
+    ServiceUnavailable,
+    /// A code this version of the types does not know
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Every code the node may send, in documentation order
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::InvalidRequest,
+        ErrorCode::InvalidHash,
+        ErrorCode::InvalidAddress,
+        ErrorCode::InvalidBlockId,
+        ErrorCode::InvalidUtxoId,
+        ErrorCode::InvalidIndex,
+        ErrorCode::ValidationError,
+        ErrorCode::BlockRejected,
+        ErrorCode::BlockchainError,
+        ErrorCode::CryptoError,
+        ErrorCode::ProofGenerationFailed,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::MethodNotAllowed,
+        ErrorCode::RequestTimeout,
+        ErrorCode::Conflict,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::RateLimited,
+        ErrorCode::MiningError,
+        ErrorCode::StorageError,
+        ErrorCode::ConfigError,
+        ErrorCode::IoError,
+        ErrorCode::SerializationError,
+        ErrorCode::InternalError,
+        ErrorCode::NotImplemented,
+        ErrorCode::ServiceUnavailable,
+    ];
+
+    /// HTTP status the node answers with for this code
+    pub fn http_status(self) -> u16 {
+        match self {
+            ErrorCode::InvalidRequest
+            | ErrorCode::InvalidHash
+            | ErrorCode::InvalidAddress
+            | ErrorCode::InvalidBlockId
+            | ErrorCode::InvalidUtxoId
+            | ErrorCode::InvalidIndex
+            | ErrorCode::ValidationError
+            | ErrorCode::BlockRejected
+            | ErrorCode::BlockchainError
+            | ErrorCode::CryptoError
+            | ErrorCode::ProofGenerationFailed => 400,
+            ErrorCode::Unauthorized => 401,
+            ErrorCode::Forbidden => 403,
+            ErrorCode::NotFound => 404,
+            ErrorCode::MethodNotAllowed => 405,
+            ErrorCode::RequestTimeout => 408,
+            ErrorCode::Conflict => 409,
+            ErrorCode::PayloadTooLarge => 413,
+            ErrorCode::RateLimited => 429,
+            ErrorCode::MiningError
+            | ErrorCode::StorageError
+            | ErrorCode::ConfigError
+            | ErrorCode::IoError
+            | ErrorCode::SerializationError
+            | ErrorCode::InternalError
+            | ErrorCode::Unknown => 500,
+            ErrorCode::NotImplemented => 501,
+            ErrorCode::ServiceUnavailable => 503,
+        }
+    }
+
+    /// The code for an error status that no handler produced, e.g. an unknown route
+    /// or a body the extractor rejected
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            401 => ErrorCode::Unauthorized,
+            403 => ErrorCode::Forbidden,
+            404 => ErrorCode::NotFound,
+            405 => ErrorCode::MethodNotAllowed,
+            408 => ErrorCode::RequestTimeout,
+            409 => ErrorCode::Conflict,
+            413 => ErrorCode::PayloadTooLarge,
+            429 => ErrorCode::RateLimited,
+            501 => ErrorCode::NotImplemented,
+            503 => ErrorCode::ServiceUnavailable,
+            400..=499 => ErrorCode::InvalidRequest,
+            _ => ErrorCode::InternalError,
+        }
+    }
+
+    /// The code as it appears on the wire
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "INVALID_REQUEST",
+            ErrorCode::InvalidHash => "INVALID_HASH",
+            ErrorCode::InvalidAddress => "INVALID_ADDRESS",
+            ErrorCode::InvalidBlockId => "INVALID_BLOCK_ID",
+            ErrorCode::InvalidUtxoId => "INVALID_UTXO_ID",
+            ErrorCode::InvalidIndex => "INVALID_INDEX",
+            ErrorCode::ValidationError => "VALIDATION_ERROR",
+            ErrorCode::BlockRejected => "BLOCK_REJECTED",
+            ErrorCode::BlockchainError => "BLOCKCHAIN_ERROR",
+            ErrorCode::CryptoError => "CRYPTO_ERROR",
+            ErrorCode::ProofGenerationFailed => "PROOF_GENERATION_FAILED",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::MethodNotAllowed => "METHOD_NOT_ALLOWED",
+            ErrorCode::RequestTimeout => "REQUEST_TIMEOUT",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::MiningError => "MINING_ERROR",
+            ErrorCode::StorageError => "STORAGE_ERROR",
+            ErrorCode::ConfigError => "CONFIG_ERROR",
+            ErrorCode::IoError => "IO_ERROR",
+            ErrorCode::SerializationError => "SERIALIZATION_ERROR",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::NotImplemented => "NOT_IMPLEMENTED",
+            ErrorCode::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+            ErrorCode::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a request failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiErrorBody {
+    pub code: ErrorCode,
+    /// Human-readable description; not meant to be matched on
+    pub message: String,
+    /// Structured context, e.g. the rejected block for `BLOCK_REJECTED`
+    pub details: Option<serde_json::Value>,
+}
+
+/// Envelope around every JSON response body
+///
+/// Exactly one of `data` and `error` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiResponse<T> {
+    /// Payload of a successful response
+    pub data: Option<T>,
+    /// Why the request failed
+    pub error: Option<ApiErrorBody>,
+    /// Identifier of the request, also sent as the `x-request-id` header
+    pub request_id: Option<String>,
+    /// When the node produced the response
+    pub timestamp: DateTime<Utc>,
+}
+
+/// The envelope of a failed response, as documented in the OpenAPI description
+pub type ErrorResponse = ApiResponse<()>;
+
+impl<T> ApiResponse<T> {
+    /// Envelope for a successful response
+    pub fn success(data: T, request_id: Option<String>) -> Self {
+        Self { data: Some(data), error: None, request_id, timestamp: Utc::now() }
+    }
+
+    /// Envelope for a failed response
+    pub fn failure(error: ApiErrorBody, request_id: Option<String>) -> Self {
+        Self { data: None, error: Some(error), request_id, timestamp: Utc::now() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_wire_names() {
+        for code in ErrorCode::ALL {
+            let json = serde_json::to_value(code).unwrap();
+            assert_eq!(json, code.as_str());
+            assert_eq!(serde_json::from_value::<ErrorCode>(json).unwrap(), *code);
+        }
+        assert_eq!(serde_json::from_str::<ErrorCode>("\"SOMETHING_NEW\"").unwrap(), ErrorCode::Unknown);
+    }
+
+    #[test]
+    fn test_error_code_status_round_trip() {
+        for code in [ErrorCode::NotFound, ErrorCode::RateLimited, ErrorCode::PayloadTooLarge] {
+            assert_eq!(ErrorCode::from_http_status(code.http_status()), code);
+        }
+        assert_eq!(ErrorCode::from_http_status(422), ErrorCode::InvalidRequest);
+        assert_eq!(ErrorCode::from_http_status(502), ErrorCode::InternalError);
+    }
+
+    #[test]
+    fn test_envelope_encoding() {
+        let success = serde_json::to_value(ApiResponse::success(7, Some("abc".to_string()))).unwrap();
+        assert_eq!(success["data"], 7);
+        assert!(success["error"].is_null());
+        assert_eq!(success["request_id"], "abc");
+
+        let error = ApiErrorBody { code: ErrorCode::NotFound, message: "gone".to_string(), details: None };
+        let failure = serde_json::to_value(ErrorResponse::failure(error, None)).unwrap();
+        assert!(failure["data"].is_null());
+        assert_eq!(failure["error"]["code"], "NOT_FOUND");
+        let decoded: ApiResponse<u32> = serde_json::from_value(failure).unwrap();
+        assert_eq!(decoded.error.unwrap().code, ErrorCode::NotFound);
+    }
+}
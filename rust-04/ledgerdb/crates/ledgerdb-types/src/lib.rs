@@ -0,0 +1,18 @@
+//! Wire types for the LedgerDB REST and WebSocket API.
+//!
+//! The node serializes its responses with these types (or, for chain data, with
+//! types that encode identically), so a client that decodes into them sees exactly
+//! what the server sent. The crate has no dependency on the node itself and is what
+//! `ledgerdb-client` builds on.
+
+pub mod chain;
+pub mod cursor;
+pub mod envelope;
+pub mod responses;
+pub mod websocket;
+
+pub use chain::*;
+pub use cursor::*;
+pub use envelope::*;
+pub use responses::*;
+pub use websocket::*;
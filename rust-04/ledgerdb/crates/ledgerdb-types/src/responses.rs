@@ -0,0 +1,1575 @@
+//! REST request and response bodies.
+//!
+//! Types here that do not mention chain data are the node's own: its handlers
+//! serialize exactly these. The rest mirror the node's responses over the wire
+//! types in [`crate::chain`].
+
+use crate::chain::{
+    Address, Block, BlockHeader, BlockMetadata, Hash256, MerkleProof, Network, RewardSchedule,
+    SnapshotValidation, Transaction,
+};
+use crate::cursor::{Cursor, PageWindow};
+use crate::envelope::ApiErrorBody;
+use crate::websocket::MiningProgressData;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Health check response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HealthResponse {
+    pub status: String,
+    pub timestamp: DateTime<Utc>,
+    pub version: String,
+    pub uptime: u64,
+}
+
+/// `/version` response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VersionResponse {
+    pub version: String,
+    pub api_version: String,
+    pub build_time: String,
+    pub git_commit: String,
+}
+
+/// Blockchain info response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockchainInfoResponse {
+    pub network: Network,
+    pub height: u64,
+    pub latest_block_hash: Hash256,
+    pub total_transactions: u64,
+    pub total_supply: u64,
+    pub difficulty: u32,
+    pub network_hash_rate: f64,
+}
+
+/// `/api/blockchain/stats` response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockchainStatsResponse {
+    pub blockchain: ChainStats,
+    pub storage: StorageStats,
+    pub network: NetworkStats,
+}
+
+/// Chain section of [`BlockchainStatsResponse`]
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChainStats {
+    pub height: u64,
+    pub total_blocks: u64,
+    pub total_transactions: u64,
+    pub total_supply: u64,
+    pub average_block_time: f64,
+    pub difficulty: u32,
+    /// Hex total work of the main chain
+    pub chain_work: String,
+}
+
+/// `GET /api/mining/difficulty` response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DifficultyResponse {
+    /// Leading zero bits the next block's hash needs
+    pub difficulty: u32,
+    /// Hex of the largest hash that meets `difficulty`
+    pub target: String,
+    /// Hashes a miner expects to try per block, `2^difficulty`
+    pub expected_hashes: f64,
+    /// Hash rate of the network, estimated from recent block times
+    pub network_hash_rate: f64,
+    /// Expected seconds per block at `network_hash_rate`; absent until blocks
+    /// have been timed
+    pub estimated_block_seconds: Option<f64>,
+    /// Seconds per block the difficulty retargets towards
+    pub target_block_time: u64,
+    /// Blocks until the next retarget
+    pub next_adjustment: u64,
+}
+
+/// `GET /api/mining/status` response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MiningStatusResponse {
+    /// Whether the node's miner is searching for a nonce right now
+    pub is_mining: bool,
+    pub current_block_height: u64,
+    pub difficulty: u32,
+    /// Hash rate of the most recent search, 0 before the miner has reported
+    pub hash_rate: f64,
+    /// The miner's most recent event, as sent on the `mining_progress` topic
+    pub last_event: Option<MiningProgressData>,
+}
+
+/// Storage section of [`BlockchainStatsResponse`]
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StorageStats {
+    pub total_size: u64,
+    pub block_count: u64,
+    pub transaction_count: u64,
+    pub utxo_count: u64,
+    /// Entries in each tree
+    #[serde(default)]
+    pub trees: BTreeMap<String, u64>,
+    /// Bytes of cache the backend was opened with
+    #[serde(default)]
+    pub cache_capacity: u64,
+    #[serde(default)]
+    pub io: StorageIoStats,
+}
+
+/// Flushes and record (de)serialization since the storage was opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StorageIoStats {
+    pub flushes: u64,
+    /// Time spent flushing, in microseconds
+    pub flush_micros: u64,
+    /// Records encoded or decoded
+    pub serializations: u64,
+    /// Time spent encoding and decoding, in microseconds
+    pub serialization_micros: u64,
+}
+
+/// Network section of [`BlockchainStatsResponse`]
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NetworkStats {
+    pub hash_rate: f64,
+    pub connected_peers: u32,
+}
+
+/// A main-chain block with the cumulative work behind it
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChainBlockResponse<B = Block> {
+    #[serde(flatten)]
+    pub block: B,
+    /// Hex total work of the chain up to and including this block
+    pub chain_work: String,
+}
+
+/// Chain work as fixed-width hex, since JSON numbers cannot hold a `u128`
+pub fn format_chain_work(work: u128) -> String {
+    format!("{:032x}", work)
+}
+
+/// Parse chain work as formatted by [`format_chain_work`]
+pub fn parse_chain_work(hex: &str) -> Option<u128> {
+    u128::from_str_radix(hex, 16).ok()
+}
+
+/// How much of a block the block endpoints return, after bitcoind's `getblock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockVerbosity {
+    /// `0`: the block's binary encoding in hex
+    Raw,
+    /// `1`: header, metadata and transaction hashes
+    Summary,
+    /// `2`: every transaction in full
+    #[default]
+    Full,
+}
+
+impl BlockVerbosity {
+    /// The verbosity for a `?verbosity=` level, if it is one
+    pub fn from_level(level: u8) -> Option<Self> {
+        match level {
+            0 => Some(BlockVerbosity::Raw),
+            1 => Some(BlockVerbosity::Summary),
+            2 => Some(BlockVerbosity::Full),
+            _ => None,
+        }
+    }
+
+    /// The `?verbosity=` level
+    pub fn level(self) -> u8 {
+        match self {
+            BlockVerbosity::Raw => 0,
+            BlockVerbosity::Summary => 1,
+            BlockVerbosity::Full => 2,
+        }
+    }
+}
+
+/// Query parameters of the endpoints returning blocks
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct BlockParams {
+    /// 0 = raw hex, 1 = header and transaction hashes, 2 = full transactions (default)
+    pub verbosity: Option<u8>,
+}
+
+impl BlockParams {
+    /// The requested verbosity, or `None` if the level is not 0, 1 or 2
+    pub fn verbosity(&self) -> Option<BlockVerbosity> {
+        match self.verbosity {
+            Some(level) => BlockVerbosity::from_level(level),
+            None => Some(BlockVerbosity::default()),
+        }
+    }
+}
+
+impl From<BlockVerbosity> for BlockParams {
+    fn from(verbosity: BlockVerbosity) -> Self {
+        Self { verbosity: Some(verbosity.level()) }
+    }
+}
+
+/// A block at verbosity 0
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RawBlockResponse {
+    /// Hex block hash
+    pub hash: String,
+    pub index: u64,
+    /// Hex of the block's binary encoding, as the node stores it
+    pub hex: String,
+    /// Hex total work of the chain up to and including this block
+    pub chain_work: String,
+}
+
+/// A block at verbosity 1: everything but the transaction bodies
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockSummaryResponse<H = BlockHeader, M = BlockMetadata> {
+    /// Hex block hash
+    pub hash: String,
+    pub index: u64,
+    pub header: H,
+    pub metadata: M,
+    /// Hex hashes of the block's transactions, coinbase first
+    pub transaction_hashes: Vec<String>,
+    /// Hex total work of the chain up to and including this block
+    pub chain_work: String,
+}
+
+/// A block at any verbosity; the shapes are told apart by their fields
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(untagged)]
+pub enum BlockView<B = Block, H = BlockHeader, M = BlockMetadata> {
+    Raw(RawBlockResponse),
+    Summary(BlockSummaryResponse<H, M>),
+    Full(ChainBlockResponse<B>),
+}
+
+/// Outcome of `POST /api/blocks/submit` or `POST /api/blocks/compact` for a block
+/// that was not rejected
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockSubmitResponse {
+    /// `connected`, `reorganized`, `side_branch`, `orphaned` or `duplicate`, or
+    /// `incomplete` for a compact block whose transactions are not all known
+    pub status: String,
+    /// Hex hash of the submitted block
+    pub hash: String,
+    /// Height of the tip once the block connected, or of a `side_branch` block
+    pub height: Option<u64>,
+    /// Height of the block a `reorganized` chain now forks from the old one at
+    #[serde(default)]
+    pub fork_height: Option<u64>,
+    /// Orphans connected after the block
+    pub orphans_connected: usize,
+    /// Hex hash of the parent an orphaned block waits for
+    pub missing_parent: Option<String>,
+    /// Positions in an `incomplete` compact block of the transactions the node
+    /// lacks; send the full block instead
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_transactions: Vec<u32>,
+}
+
+/// Request body for `POST /api/blocks/compact`: a new block announced by its header
+/// and a short ID per transaction, for a node that already holds the transactions
+/// in its pool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CompactBlock<M = BlockMetadata, T = Transaction> {
+    /// Hex of the serialized header, as in `/api/headers`
+    pub header: String,
+    pub index: u64,
+    pub metadata: M,
+    /// Salt of the short IDs, picked per announcement
+    pub nonce: u64,
+    /// Hex short IDs of the transactions not in `prefilled`, in block order: as in
+    /// BIP152, the low 6 bytes (little-endian) of SipHash-2-4 over the transaction
+    /// hash, keyed by the SHA-256 of the header bytes and little-endian `nonce`
+    pub short_ids: Vec<String>,
+    /// Transactions sent in full, such as the coinbase, which no pool holds
+    pub prefilled: Vec<PrefilledTransaction<T>>,
+}
+
+/// A transaction sent in full in a [`CompactBlock`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PrefilledTransaction<T = Transaction> {
+    /// Position in the block
+    pub index: u32,
+    pub transaction: T,
+}
+
+/// A block in the orphan pool
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrphanBlockResponse {
+    /// Hex block hash
+    pub hash: String,
+    pub index: u64,
+    /// Hex hash of the parent it is waiting for
+    pub previous_hash: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// The orphan pool, as listed by `/api/blocks/orphans`
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrphanPoolResponse {
+    /// Orphans, oldest first
+    pub orphans: Vec<OrphanBlockResponse>,
+    /// Hex hashes of the parents neither the chain nor the pool holds
+    pub missing_parents: Vec<String>,
+}
+
+/// Block reward response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RewardResponse {
+    pub height: u64,
+    pub current_reward: u64,
+    pub next_reward: Option<u64>,
+    pub next_change_height: Option<u64>,
+    pub schedule: RewardSchedule,
+}
+
+/// A pinned block hash
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CheckpointInfo {
+    pub height: u64,
+    pub hash: Hash256,
+    pub reached: bool,
+}
+
+/// Checkpoints response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CheckpointsResponse {
+    pub checkpoints: Vec<CheckpointInfo>,
+    pub last_checkpoint: Option<u64>,
+}
+
+/// UTXO snapshot summary response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UtxoSnapshotResponse {
+    pub height: u64,
+    pub block_hash: Hash256,
+    pub commitment: Hash256,
+    pub utxo_count: usize,
+    pub total_amount: u64,
+    pub import_validation: Option<SnapshotValidation>,
+}
+
+/// Query parameters for UTXO snapshot endpoints
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct SnapshotParams {
+    /// Block height to snapshot at (default: tip)
+    pub height: Option<u64>,
+}
+
+/// A UTXO snapshot file cut into fixed-size chunks, each named by its SHA-256, so
+/// the chunks can be fetched from any node holding the same snapshot and checked
+/// on arrival
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SnapshotManifest {
+    pub height: u64,
+    pub block_hash: Hash256,
+    /// The snapshot's commitment over its outputs
+    pub commitment: Hash256,
+    /// Length of the snapshot file in bytes
+    pub size: u64,
+    /// Bytes per chunk; only the last one may be shorter
+    pub chunk_size: u64,
+    /// SHA-256 of each chunk, in file order
+    pub chunks: Vec<Hash256>,
+}
+
+impl SnapshotManifest {
+    /// Manifest of the snapshot file `bytes`, cut every `chunk_size` bytes
+    pub fn new(height: u64, block_hash: Hash256, commitment: Hash256, bytes: &[u8], chunk_size: usize) -> Self {
+        Self {
+            height,
+            block_hash,
+            commitment,
+            size: bytes.len() as u64,
+            chunk_size: chunk_size as u64,
+            chunks: bytes.chunks(chunk_size).map(Self::chunk_hash).collect(),
+        }
+    }
+
+    /// The name of `chunk`
+    pub fn chunk_hash(chunk: &[u8]) -> Hash256 {
+        Hash256(Sha256::digest(chunk).into())
+    }
+
+    /// Whether `chunk` is the manifest's `index`th chunk
+    pub fn verify_chunk(&self, index: usize, chunk: &[u8]) -> bool {
+        let offset = index as u64 * self.chunk_size;
+        let expected_len = self.size.saturating_sub(offset).min(self.chunk_size);
+        self.chunks.get(index) == Some(&Self::chunk_hash(chunk)) && chunk.len() as u64 == expected_len
+    }
+}
+
+/// Query parameters for `/api/headers`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct HeadersParams {
+    /// Height of the first header (default: 0)
+    pub from: Option<u64>,
+    /// Number of headers (default and maximum: 2000)
+    pub count: Option<u64>,
+}
+
+/// Block headers for light clients
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HeadersResponse {
+    /// Height of the first header
+    pub from: u64,
+    /// Height of the chain tip
+    pub tip_height: u64,
+    /// Hex of each header's compact encoding (`BlockHeader::to_bytes`), in height order
+    pub headers: Vec<String>,
+}
+
+/// Stage of the initial block download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    /// No sync peers are configured, or the download has not started
+    Idle,
+    /// Downloading and checking the peer's header chain
+    Headers,
+    /// Downloading and connecting the blocks under the checked headers
+    Blocks,
+    /// Caught up with the peer's tip
+    Synced,
+    /// The last attempt failed; `error` says why
+    Failed,
+}
+
+/// `/api/sync/status` response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SyncStatus {
+    pub phase: SyncPhase,
+    /// Base URL of the node the header chain came from
+    pub peer: Option<String>,
+    /// Height of the local tip when the download started
+    pub start_height: u64,
+    /// Height of the peer's tip
+    pub target_height: u64,
+    /// Height of the last header checked
+    pub header_height: u64,
+    /// Height of the last block connected
+    pub block_height: u64,
+    /// Batches of blocks requested but not yet connected
+    pub in_flight: usize,
+    pub started_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+impl Default for SyncStatus {
+    fn default() -> Self {
+        Self {
+            phase: SyncPhase::Idle,
+            peer: None,
+            start_height: 0,
+            target_height: 0,
+            header_height: 0,
+            block_height: 0,
+            in_flight: 0,
+            started_at: None,
+            error: None,
+        }
+    }
+}
+
+/// `/api/p2p/addresses` response: where other nodes can reach this one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NodeAddresses {
+    /// `ip:port` of every TCP listener serving the API
+    pub listen: Vec<String>,
+    /// Base URL for other nodes' `sync.peers`: `p2p.external_address`, or the
+    /// router's external address once a UPnP mapping is up
+    pub external: Option<String>,
+    pub upnp: Option<UpnpMapping>,
+}
+
+/// Ports forwarded to this node by the local router over UPnP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpnpMapping {
+    /// `ip:port` of the router's UPnP service
+    pub gateway: String,
+    /// The router's address on the internet
+    pub external_ip: String,
+    /// TCP ports forwarded, each to the same port on this node
+    pub ports: Vec<u16>,
+    /// When the lease runs out unless renewed
+    pub expires_at: DateTime<Utc>,
+}
+
+/// `/api/ws/stats` response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebSocketStats {
+    /// Open `/ws` connections
+    pub connections: usize,
+    /// Connections accepted at most; further upgrades get 503
+    pub max_connections: usize,
+    /// Connections subscribed to each topic, e.g. `mining_progress`
+    pub topics: BTreeMap<String, usize>,
+}
+
+/// Compact block filter response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockFilterResponse {
+    pub block_hash: Hash256,
+    pub height: u64,
+    pub n: u32,
+    pub p: u8,
+    pub m: u64,
+    /// Hex of the Golomb-coded set
+    pub filter: String,
+}
+
+/// `/api/transactions/:hash/proof` response
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TransactionProofResponse {
+    pub transaction_hash: Hash256,
+    pub block_hash: Hash256,
+    pub block_height: u64,
+    pub transaction_index: usize,
+    pub merkle_proof: MerkleProof,
+    pub merkle_root: Hash256,
+}
+
+/// Request body for `POST /api/proofs/verify`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VerifyProofRequest<H = Hash256, P = MerkleProof> {
+    /// Root the proof should lead to, e.g. a block header's `merkle_root`
+    pub merkle_root: H,
+    /// Leaf being proven, e.g. a transaction hash
+    pub leaf_hash: H,
+    pub proof: P,
+}
+
+/// `POST /api/proofs/verify` response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VerifyProofResponse {
+    /// The proof leads from `leaf_hash` to `merkle_root`
+    pub valid: bool,
+}
+
+/// One entry of `/api/utxos`
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UtxoResponse {
+    /// `<tx hash>:<output index>`
+    pub utxo_id: String,
+    pub tx_hash: Hash256,
+    pub output_index: u32,
+    pub amount: u64,
+    pub address: Address,
+    pub recipient: Address,
+    pub block_height: u64,
+    pub is_spent: bool,
+}
+
+/// One entry of `/api/transactions/pending` and of mempool updates on the WebSocket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PendingTransactionResponse<T = Transaction> {
+    pub transaction: T,
+    /// Value of the outputs it spends minus its own outputs: what the miner collects
+    pub fee: u64,
+    /// `fee` per byte of `size`
+    pub fee_rate: f64,
+    /// Serialized size in bytes
+    pub size: usize,
+    /// When this node's pool accepted it
+    pub added_at: DateTime<Utc>,
+    /// Seconds since `added_at`
+    pub age_secs: u64,
+}
+
+/// Request body for `POST /api/psbt`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatePsbtRequest<T = Transaction> {
+    /// Transaction to sign; each input names the public key that will sign it
+    pub transaction: T,
+}
+
+/// Request body for `POST /api/psbt/combine` and `POST /api/psbt/finalize`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PsbtRequest {
+    /// Hex-encoded copies of one partially signed transaction, each carrying the
+    /// signatures of some of its keys
+    pub psbts: Vec<String>,
+}
+
+/// A partially signed transaction and what it still needs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PsbtResponse {
+    /// Hex-encoded container to hand to the next signer
+    pub psbt: String,
+    /// Hex hash that every input signs
+    pub signing_hash: String,
+    /// Value of the spent outputs minus the transaction's own outputs
+    pub fee: u64,
+    /// Inputs still waiting for a signature
+    pub missing_signatures: Vec<usize>,
+}
+
+/// `POST /api/psbt/finalize` response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PsbtFinalizeResponse {
+    /// Hex hash of the signed transaction, now in the pool
+    pub hash: String,
+}
+
+/// `POST /api/transactions/validate` response: every check a transaction goes
+/// through, not just the first one it fails
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TransactionValidation {
+    /// Whether `errors` is empty
+    pub valid: bool,
+    /// Hex hash of the transaction
+    pub hash: String,
+    pub inputs: Vec<InputValidation>,
+    /// Value of the outputs spent that were found
+    pub total_in: u64,
+    pub total_out: u64,
+    /// `total_in` minus `total_out`, or 0 when the outputs exceed the inputs
+    pub fee: u64,
+    /// Fee the node requires of a non-coinbase transaction
+    pub min_fee: u64,
+    /// Whether the lock time lets the next block include it
+    pub lock_time_final: bool,
+    /// Whether the node's pool already holds it
+    pub in_mempool: bool,
+    /// Every check that failed, in the order they were made
+    pub errors: Vec<String>,
+}
+
+/// What [`TransactionValidation`] found about one input
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InputValidation {
+    /// Output spent, as `tx_hash:output_index`
+    pub outpoint: String,
+    /// Whether the output is unspent on the main chain
+    pub utxo_found: bool,
+    /// Value of the output, when found
+    pub amount: Option<u64>,
+    pub signature_valid: bool,
+    /// Whether the output was created by a coinbase transaction
+    pub coinbase: bool,
+    /// Blocks on top of the one holding the output, itself included
+    pub confirmations: Option<u64>,
+    /// False only for a coinbase output younger than the maturity period
+    pub mature: bool,
+    /// Hex hashes of pool transactions already spending the output
+    pub conflicts: Vec<String>,
+}
+
+/// Most queries in one `POST /api/batch` unless the node is configured otherwise
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+
+/// One lookup in a `POST /api/batch` request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchQuery {
+    /// A main-chain block by height or hex hash
+    Block { id: String },
+    /// A confirmed transaction by hex hash
+    Transaction { hash: String },
+    /// Balance of a hex address
+    Address { address: String },
+}
+
+/// Request body for `POST /api/batch`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BatchRequest {
+    pub queries: Vec<BatchQuery>,
+    /// Verbosity of the blocks returned, as for the block endpoints (default: 2)
+    #[serde(default)]
+    pub verbosity: Option<u8>,
+}
+
+/// Balance of an address, as answered to a batch query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AddressBalance {
+    /// Hex address
+    pub address: String,
+    pub balance: u64,
+    pub utxo_count: usize,
+}
+
+/// What a batch query found, named after the query's type
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItem<B = Block, H = BlockHeader, M = BlockMetadata, T = Transaction> {
+    Block(BlockView<B, H, M>),
+    Transaction(T),
+    Address(AddressBalance),
+}
+
+/// Answer to one batch query; exactly one of `data` and `error` is set
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BatchResult<B = Block, H = BlockHeader, M = BlockMetadata, T = Transaction> {
+    pub data: Option<BatchItem<B, H, M, T>>,
+    pub error: Option<ApiErrorBody>,
+}
+
+/// `POST /api/batch` response: one result per query, in the order asked
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BatchResponse<B = Block, H = BlockHeader, M = BlockMetadata, T = Transaction> {
+    pub results: Vec<BatchResult<B, H, M, T>>,
+}
+
+impl<B, H, M, T> BatchResult<B, H, M, T> {
+    pub fn found(item: BatchItem<B, H, M, T>) -> Self {
+        Self { data: Some(item), error: None }
+    }
+
+    pub fn failed(error: ApiErrorBody) -> Self {
+        Self { data: None, error: Some(error) }
+    }
+}
+
+/// Request body for `POST /api/watch`
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WatchRequest {
+    /// Hex address to watch
+    pub address: String,
+    pub label: Option<String>,
+}
+
+/// One watched address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WatchedAddress {
+    /// Hex address
+    pub address: String,
+    pub label: Option<String>,
+    pub added_at: DateTime<Utc>,
+    /// Taken from a webhook endpoint's `addresses` rather than registered; not persisted
+    pub configured: bool,
+}
+
+/// A watched address with its current balance
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WatchedAddressResponse {
+    #[serde(flatten)]
+    pub entry: WatchedAddress,
+    pub balance: u64,
+    pub utxo_count: usize,
+}
+
+/// Whether funds moved to or from the watched address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityDirection {
+    Received,
+    Spent,
+}
+
+/// A transaction paying or spending from a watched address, at some confirmation count
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AddressActivity {
+    pub address: String,
+    pub tx_hash: String,
+    pub direction: ActivityDirection,
+    /// Total the transaction paid to, or spent from, the address
+    pub amount: u64,
+    /// Block holding the transaction; `None` in the mempool, or once a reorg dropped it
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    /// 0 in the mempool, 1 once mined, plus one for each block on top
+    pub confirmations: u64,
+}
+
+/// Query parameters for `/api/addresses/{address}/transactions`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct AddressHistoryParams {
+    /// Only transactions in blocks timestamped at or after this
+    pub from: Option<DateTime<Utc>>,
+    /// Only transactions in blocks timestamped before this
+    pub to: Option<DateTime<Utc>>,
+    /// Page number, counting from the newest transaction (default: 0)
+    pub page: Option<u64>,
+    /// Transactions per page (default 20, at most 100)
+    pub limit: Option<u64>,
+}
+
+/// A confirmed transaction paying or spending from an address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AddressTransactionResponse {
+    pub tx_hash: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    /// Timestamp of the block holding the transaction
+    pub timestamp: DateTime<Utc>,
+    /// Total the transaction paid to the address
+    pub received: u64,
+    /// Total the transaction spent from the address
+    pub sent: u64,
+    /// The transaction's memo, if it is text
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+/// Query parameters for `/api/transactions/search`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct MemoSearchParams {
+    /// Memo to look for, e.g. an invoice reference
+    pub memo: String,
+    /// Match memos starting with `memo` rather than equal to it (default: false)
+    pub prefix: Option<bool>,
+    /// Page number, counting from the newest transaction (default: 0)
+    pub page: Option<u64>,
+    /// Transactions per page (default 20, at most 100)
+    pub limit: Option<u64>,
+}
+
+/// A confirmed transaction whose memo matched a search
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MemoTransactionResponse {
+    pub tx_hash: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    /// Timestamp of the block holding the transaction
+    pub timestamp: DateTime<Utc>,
+    pub memo: String,
+}
+
+/// How finely `/api/addresses/{address}/balance/history` samples a balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceGranularity {
+    /// A point for every block that changed the balance
+    #[default]
+    Block,
+    /// A point for every UTC day on which the balance changed
+    Day,
+}
+
+/// Query parameters for `/api/addresses/{address}/balance/history`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct BalanceHistoryParams {
+    /// `block` (the default) or `day`
+    pub granularity: Option<BalanceGranularity>,
+}
+
+/// An address's balance after a block, or at the end of a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BalancePoint {
+    /// The block's timestamp, or midnight UTC starting the day
+    pub timestamp: DateTime<Utc>,
+    /// Last block in the period that changed the balance
+    pub block_height: u64,
+    pub block_hash: String,
+    pub balance: u64,
+}
+
+/// Balance of an address over time, oldest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BalanceHistoryResponse {
+    pub address: String,
+    pub granularity: BalanceGranularity,
+    pub points: Vec<BalancePoint>,
+}
+
+/// Query parameters for `/api/stats/richlist`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct RichListParams {
+    /// Holders to list (default 100, at most 1000)
+    pub limit: Option<usize>,
+}
+
+/// One address of the rich list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RichListEntry {
+    /// 1 for the largest balance
+    pub rank: usize,
+    pub address: String,
+    pub balance: u64,
+    /// Percentage of the total supply
+    pub share: f64,
+}
+
+/// The largest balances in the UTXO set at a block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RichListResponse {
+    pub height: u64,
+    pub block_hash: String,
+    /// Sum of every unspent output
+    pub total_supply: u64,
+    /// Addresses holding any unspent output
+    pub holders: usize,
+    pub entries: Vec<RichListEntry>,
+}
+
+/// Addresses whose balance lies in `[min_balance, max_balance)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DistributionBucket {
+    pub min_balance: u64,
+    /// `None` for the last bucket, which is unbounded
+    pub max_balance: Option<u64>,
+    pub addresses: usize,
+    /// Sum of their balances
+    pub balance: u64,
+    /// Percentage of the total supply
+    pub share: f64,
+}
+
+/// How the supply is spread across addresses at a block, in power-of-ten balance
+/// buckets from `[0, 10)` up to the one holding the largest balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SupplyDistributionResponse {
+    pub height: u64,
+    pub block_hash: String,
+    pub total_supply: u64,
+    pub holders: usize,
+    pub buckets: Vec<DistributionBucket>,
+}
+
+/// Something the supply audit found wrong
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SupplyProblem {
+    /// The block at fault, when the audit can tell
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    pub message: String,
+}
+
+/// The chain's supply recomputed from its blocks and checked against the reward
+/// schedule and the UTXO set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SupplyAudit {
+    pub height: u64,
+    pub block_hash: String,
+    /// Sum of the subsidies of every block up to `height`
+    pub expected_supply: u64,
+    /// Subsidy, plus fees, that coinbases did not claim; no output holds it
+    pub unclaimed: u64,
+    /// Supply left by replaying every block from genesis
+    pub replayed_supply: u64,
+    /// Sum of the node's UTXO set
+    pub utxo_supply: u64,
+    /// `replayed_supply` is `expected_supply` less `unclaimed`, it matches
+    /// `utxo_supply`, and no block issues more than its subsidy
+    pub consistent: bool,
+    /// The first problems found, oldest block first
+    pub problems: Vec<SupplyProblem>,
+}
+
+/// What a signed-in user may do; each role may also do everything the roles
+/// before it may
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Read the chain, mempool and stats
+    Viewer,
+    /// Also submit transactions and blocks and mine
+    Operator,
+    /// Also manage users and use the `/admin` endpoints
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("unknown role '{}': expected viewer, operator or admin", other)),
+        }
+    }
+}
+
+/// Request body for `POST /api/auth/login`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// A session token, sent back as `Authorization: Bearer <token>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LoginResponse {
+    pub token: String,
+    pub username: String,
+    pub role: Role,
+    /// After this the token is refused and the user has to log in again
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request body for `POST /api/users`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+}
+
+/// A user account, without its password
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UserAccount {
+    pub username: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for `POST /admin/tenants`
+///
+/// The chain settings left out are the host node's. Tenants share the host's
+/// network, since addresses are encoded for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateTenantRequest {
+    /// Lowercase letters, digits and '-'; the tenant is served under `/t/{name}/`
+    pub name: String,
+    /// Paid by the genesis block, so a different address gives a different chain
+    #[serde(default)]
+    pub genesis_address: Option<Address>,
+    #[serde(default)]
+    pub reward_schedule: Option<RewardSchedule>,
+    #[serde(default)]
+    pub initial_difficulty: Option<u32>,
+    /// Seconds; also how often the tenant's miner tries for a block
+    #[serde(default)]
+    pub target_block_time: Option<u64>,
+    /// Run a miner paying the genesis address
+    #[serde(default)]
+    pub mining: bool,
+}
+
+/// A hosted chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TenantInfo {
+    pub name: String,
+    pub genesis_hash: Hash256,
+    pub height: u64,
+    pub mining: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A peer in the node's address book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PeerRecord {
+    /// `ip:port`
+    pub address: String,
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Round trip of the last ping
+    pub latency_ms: Option<u64>,
+    /// Penalty points since the last ban; reaching the ban threshold bans the peer
+    pub misbehavior_score: u32,
+    pub banned_until: Option<DateTime<Utc>>,
+    pub ban_reason: Option<String>,
+}
+
+impl PeerRecord {
+    /// Whether the peer is banned at `now`
+    pub fn is_banned_at(&self, now: DateTime<Utc>) -> bool {
+        self.banned_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Request body for `POST /admin/peers/:address/ban`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BanRequest {
+    /// Seconds; capped at, and defaulting to, the node's maximum ban time
+    #[serde(default)]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Synthetic load for `POST /admin/simulate` and `ledgerdb simulate`
+///
+/// Transactions arrive as a Poisson process and their amounts are log-normal
+/// around `median_amount`; every field left out takes its default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(default)]
+pub struct SimulationParams {
+    /// Random wallets to create; the first collects the block rewards that fund the rest
+    pub wallets: usize,
+    /// Mean transactions per second
+    pub tx_rate: f64,
+    /// Simulated seconds to run for, after the funding blocks
+    pub duration: u64,
+    /// Seconds between mined blocks; defaults to the chain's target block time
+    pub block_interval: Option<u64>,
+    /// Median amount sent
+    pub median_amount: u64,
+    /// Standard deviation of the amount's natural logarithm
+    pub amount_spread: f64,
+    /// Fees are drawn evenly between the chain's minimum fee and this
+    pub max_fee: u64,
+    /// Seeds the wallets and every draw, so a run can be repeated
+    pub seed: Option<u64>,
+    /// Pace events against the wall clock instead of running as fast as possible
+    pub realtime: bool,
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        Self {
+            wallets: 20,
+            tx_rate: 1.0,
+            duration: 600,
+            block_interval: None,
+            median_amount: 50_000,
+            amount_spread: 1.0,
+            max_fee: 5_000,
+            seed: None,
+            realtime: true,
+        }
+    }
+}
+
+/// `POST /admin/simulate` response; the run continues as a background task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SimulationStarted {
+    /// Name of the task in `/admin/tasks`
+    pub task: String,
+    /// Seed to pass back to repeat the run
+    pub seed: u64,
+}
+
+/// What a finished simulation did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub wallets: usize,
+    /// Transactions accepted into the mempool
+    pub submitted: u64,
+    /// Transactions built but refused by the mempool
+    pub rejected: u64,
+    /// Arrivals dropped because no wallet had confirmed funds to spend
+    pub unfunded: u64,
+    /// Sum of the amounts submitted, excluding change and fees
+    pub volume: u64,
+    pub fees: u64,
+    /// Blocks mined, including the ones funding the first wallet
+    pub blocks: u64,
+    pub start_height: u64,
+    pub end_height: u64,
+    /// Submitted transactions still waiting for a block at the end
+    pub pending: usize,
+}
+
+/// What is wrong with a storage entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StorageIssueKind {
+    /// The entry cannot be decoded
+    Unreadable,
+    /// A block or transaction stored under a key other than its hash
+    HashMismatch,
+    /// A height index entry naming a missing block, or a block at another height
+    DanglingBlockIndex,
+    /// A transaction index entry naming a missing transaction or block
+    DanglingTxIndex,
+    /// An address index entry listing a UTXO that is not stored
+    DanglingAddressIndex,
+    /// A UTXO created by a transaction that is not stored
+    OrphanUtxo,
+}
+
+impl StorageIssueKind {
+    /// Whether repairing drops the entry; other kinds need a `reindex`
+    pub fn is_dangling_index(self) -> bool {
+        matches!(
+            self,
+            Self::DanglingBlockIndex | Self::DanglingTxIndex | Self::DanglingAddressIndex
+        )
+    }
+}
+
+/// One corrupt storage entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StorageIssue {
+    /// Tree (column family) holding the entry
+    pub tree: String,
+    /// The entry's key, as text where it is text and hex otherwise
+    pub key: String,
+    pub kind: StorageIssueKind,
+    pub detail: String,
+    /// Whether the entry was removed or rewritten by this check
+    pub repaired: bool,
+}
+
+/// Result of checking the storage trees against each other
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IntegrityReport {
+    /// Entries read across all trees
+    pub entries_checked: u64,
+    pub issues: Vec<StorageIssue>,
+}
+
+impl IntegrityReport {
+    /// Whether no issue is left unrepaired
+    pub fn is_clean(&self) -> bool {
+        self.issues.iter().all(|issue| issue.repaired)
+    }
+}
+
+/// What to do when a supervised task returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum RestartPolicy {
+    /// Run once; record the outcome and stop
+    Never,
+    /// Restart after an error or panic, up to `max_restarts` times
+    OnFailure {
+        max_restarts: u32,
+        #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+        backoff: Duration,
+    },
+    /// Restart whenever the task returns, until shutdown
+    Always {
+        #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+        backoff: Duration,
+    },
+}
+
+/// Lifecycle state of a supervised task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    Restarting,
+    Completed,
+    Failed,
+    Stopped,
+}
+
+/// Health snapshot of one task, as reported by `/admin/tasks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TaskHealth {
+    pub name: String,
+    pub state: TaskState,
+    pub policy: RestartPolicy,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub last_change: DateTime<Utc>,
+}
+
+/// Tracing filter of a running node: body and response of `/admin/logs/level`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LogLevel {
+    /// A level (`debug`) or any `EnvFilter` directive list (`info,ledgerdb::api=trace`)
+    pub level: String,
+}
+
+/// Pagination parameters
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct PaginationParams {
+    /// Page number (0-based)
+    pub page: Option<u64>,
+    /// Number of items per page
+    pub limit: Option<u64>,
+    /// Sort order (asc/desc)
+    pub order: Option<String>,
+    /// `next_cursor` or `prev_cursor` of an earlier page; replaces `page` and `order`
+    pub cursor: Option<String>,
+}
+
+impl Default for PaginationParams {
+    fn default() -> Self {
+        Self {
+            page: Some(0),
+            limit: Some(20),
+            order: Some("desc".to_string()),
+            cursor: None,
+        }
+    }
+}
+
+impl PaginationParams {
+    /// Continue a walk from `cursor`
+    pub fn after(cursor: impl Into<String>, limit: u64) -> Self {
+        Self { page: None, limit: Some(limit), order: None, cursor: Some(cursor.into()) }
+    }
+}
+
+/// Paginated response wrapper
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PaginatedResponse<T> {
+    /// Response data
+    pub data: Vec<T>,
+    /// Pagination metadata
+    pub pagination: PaginationMeta,
+}
+
+/// Pagination metadata
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PaginationMeta {
+    /// Current page (0 when paging by cursor)
+    pub page: u64,
+    /// Items per page
+    pub limit: u64,
+    /// Total number of items
+    pub total: u64,
+    /// Total number of pages
+    pub total_pages: u64,
+    /// Whether there's a next page
+    pub has_next: bool,
+    /// Whether there's a previous page
+    pub has_prev: bool,
+    /// Token for the page after this one, walking the same way
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// Token for the page before this one
+    #[serde(default)]
+    pub prev_cursor: Option<String>,
+}
+
+impl PaginationMeta {
+    /// Create pagination metadata
+    pub fn new(page: u64, limit: u64, total: u64) -> Self {
+        let total_pages = total.div_ceil(limit);
+
+        Self {
+            page,
+            limit,
+            total,
+            total_pages,
+            has_next: page.saturating_add(1) < total_pages,
+            has_prev: page > 0,
+            next_cursor: None,
+            prev_cursor: None,
+        }
+    }
+
+    /// Metadata of the page `window` selected; `cursor_mode` when the request
+    /// named a cursor rather than a page number
+    pub fn for_window(page: u64, limit: u64, total: u64, window: &PageWindow, cursor_mode: bool) -> Self {
+        let mut meta = Self::new(if cursor_mode { 0 } else { page }, limit, total);
+        if cursor_mode {
+            meta.has_next = window.next_cursor.is_some();
+            meta.has_prev = window.prev_cursor.is_some();
+        }
+        meta.next_cursor = window.next_cursor.as_ref().map(Cursor::encode);
+        meta.prev_cursor = window.prev_cursor.as_ref().map(Cursor::encode);
+        meta
+    }
+}
+
+/// Create a paginated response
+pub fn paginate<T>(data: Vec<T>, page: u64, limit: u64, total: u64) -> PaginatedResponse<T> {
+    PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(page, limit, total),
+    }
+}
+
+/// Create a paginated response for the page `window` selected
+pub fn paginate_window<T>(
+    data: Vec<T>,
+    params: &PaginationParams,
+    limit: u64,
+    total: u64,
+    window: &PageWindow,
+) -> PaginatedResponse<T> {
+    PaginatedResponse {
+        data,
+        pagination: PaginationMeta::for_window(params.page.unwrap_or(0), limit, total, window, params.cursor.is_some()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_work_round_trip() {
+        let hex = format_chain_work(6);
+        assert_eq!(hex.len(), 32);
+        assert_eq!(parse_chain_work(&hex), Some(6));
+    }
+
+    #[test]
+    fn test_chain_block_flattens_block() {
+        let json = serde_json::json!({"index": 3, "chain_work": "00"});
+        let response: ChainBlockResponse<serde_json::Value> = serde_json::from_value(json).unwrap();
+        assert_eq!(response.block["index"], 3);
+        assert_eq!(response.chain_work, "00");
+    }
+
+    #[test]
+    fn test_block_verbosity_levels() {
+        assert_eq!(BlockParams::default().verbosity(), Some(BlockVerbosity::Full));
+        assert_eq!(BlockParams { verbosity: Some(3) }.verbosity(), None);
+        for level in 0..=2 {
+            assert_eq!(BlockVerbosity::from_level(level).unwrap().level(), level);
+        }
+    }
+
+    #[test]
+    fn test_block_view_shapes() {
+        let raw = serde_json::json!({"hash": "ab", "index": 1, "hex": "00", "chain_work": "02"});
+        let view: BlockView<serde_json::Value, serde_json::Value, serde_json::Value> =
+            serde_json::from_value(raw).unwrap();
+        assert!(matches!(view, BlockView::Raw(RawBlockResponse { index: 1, .. })));
+
+        let summary = serde_json::json!({"hash": "ab", "index": 1, "header": {}, "metadata": {},
+                                         "transaction_hashes": ["cd"], "chain_work": "02"});
+        let view: BlockView<serde_json::Value, serde_json::Value, serde_json::Value> =
+            serde_json::from_value(summary).unwrap();
+        assert!(matches!(view, BlockView::Summary(s) if s.transaction_hashes == ["cd"]));
+
+        let full = serde_json::json!({"index": 1, "transactions": [], "chain_work": "02"});
+        let view: BlockView<serde_json::Value, serde_json::Value, serde_json::Value> =
+            serde_json::from_value(full).unwrap();
+        assert!(matches!(view, BlockView::Full(_)));
+    }
+
+    #[test]
+    fn test_batch_encoding() {
+        let request: BatchRequest = serde_json::from_value(serde_json::json!({
+            "queries": [{"type": "block", "id": "7"}, {"type": "address", "address": "ab"}]
+        }))
+        .unwrap();
+        assert_eq!(request.queries[0], BatchQuery::Block { id: "7".to_string() });
+        assert_eq!(request.verbosity, None);
+
+        let balance = AddressBalance { address: "ab".to_string(), balance: 5, utxo_count: 1 };
+        let result: BatchResult = BatchResult::found(BatchItem::Address(balance));
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["data"]["address"]["balance"], 5);
+        assert!(json["error"].is_null());
+    }
+
+    #[test]
+    fn test_snapshot_manifest_chunks() {
+        let bytes: Vec<u8> = (0..10u8).collect();
+        let manifest = SnapshotManifest::new(3, Hash256([1; 32]), Hash256([2; 32]), &bytes, 4);
+        assert_eq!((manifest.size, manifest.chunks.len()), (10, 3));
+        assert!(manifest.verify_chunk(0, &bytes[..4]));
+        assert!(manifest.verify_chunk(2, &bytes[8..]));
+        assert!(!manifest.verify_chunk(1, &bytes[..4]));
+        assert!(!manifest.verify_chunk(3, &[]));
+        assert_eq!(SnapshotManifest::new(3, Hash256([1; 32]), Hash256([2; 32]), &[], 4).chunks, vec![]);
+    }
+
+    #[test]
+    fn test_roles_are_ordered_by_privilege() {
+        assert!(Role::Viewer < Role::Operator && Role::Operator < Role::Admin);
+        for role in [Role::Viewer, Role::Operator, Role::Admin] {
+            assert_eq!(serde_json::to_value(role).unwrap(), role.as_str());
+            assert_eq!(role.as_str().parse::<Role>().unwrap(), role);
+        }
+        assert!("root".parse::<Role>().is_err());
+    }
+
+    #[test]
+    fn test_restart_policy_encoding() {
+        let policy = RestartPolicy::Always { backoff: Duration::from_secs(5) };
+        let json = serde_json::to_value(policy).unwrap();
+        assert_eq!(json["kind"], "always");
+        assert_eq!(serde_json::from_value::<RestartPolicy>(json).unwrap(), policy);
+    }
+}
@@ -0,0 +1,276 @@
+//! Messages on the `/ws` WebSocket.
+//!
+//! Every frame is a JSON [`WsMessage`]: `{"type": "NewBlock", "data": {...}}`.
+//! Clients send a [`SubscriptionRequest`] naming one of the topics in
+//! [`SUBSCRIPTION_TOPICS`] and receive the matching messages from then on.
+
+use crate::chain::Hash256;
+use crate::responses::{AddressActivity, PendingTransactionResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Topics a client may subscribe to
+pub const SUBSCRIPTION_TOPICS: &[&str] = &[
+    "mining_progress",
+    "new_blocks",
+    "new_transactions",
+    "network_status",
+    "mempool_updates",
+    "difficulty_adjustments",
+    "watched_addresses",
+    "all",
+];
+
+/// WebSocket message types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", content = "data")]
+pub enum WsMessage {
+    MiningProgress(MiningProgressData),
+    NewBlock(NewBlockData),
+    NewTransaction(NewTransactionData),
+    NetworkStatus(NetworkStatusData),
+    MempoolUpdate(MempoolUpdateData),
+    DifficultyAdjustment(DifficultyAdjustmentData),
+    ConnectionStatus(ConnectionStatusData),
+    Error(ErrorData),
+    Ping(PingData),
+    Pong(PongData),
+    Subscribed(SubscriptionData),
+    Unsubscribed(UnsubscriptionData),
+    AddressActivity(AddressActivity),
+}
+
+/// Where a miner's search for a block's nonce stands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MiningState {
+    #[default]
+    Searching,
+    Found,
+    Cancelled,
+    Timeout,
+}
+
+/// Mining progress data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MiningProgressData {
+    /// Whether the search is still running or how it ended
+    #[serde(default)]
+    pub state: MiningState,
+    pub block_height: u64,
+    pub difficulty: u32,
+    pub nonce: u64,
+    pub hash_rate: f64,
+    /// Chance (0-100) that a miner at this rate would have found the block by now
+    pub progress: f64,
+    /// Estimated time remaining (seconds)
+    pub estimated_time: Option<u64>,
+    pub attempts: u64,
+    pub best_hash: Option<Hash256>,
+}
+
+/// New block data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NewBlockData {
+    pub hash: Hash256,
+    pub height: u64,
+    pub transaction_count: usize,
+    pub size: usize,
+    pub timestamp: u64,
+    pub miner: Option<String>,
+    pub reward: u64,
+    pub total_fees: u64,
+    pub difficulty: u32,
+}
+
+/// New transaction data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NewTransactionData {
+    pub hash: Hash256,
+    pub size: usize,
+    pub fee: Option<u64>,
+    pub fee_rate: Option<f64>,
+    pub input_count: usize,
+    pub output_count: usize,
+    pub total_input: u64,
+    pub total_output: u64,
+}
+
+/// Mempool update data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MempoolUpdateData {
+    pub transaction_count: u64,
+    pub total_size: u64,
+    pub total_fees: u64,
+    /// Total fees over total size
+    pub average_fee_rate: f64,
+    /// The newest transactions in the pool, newest first
+    pub recent_transactions: Vec<PendingTransactionResponse>,
+}
+
+/// Network status data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NetworkStatusData {
+    /// Current block height
+    pub block_height: u64,
+    /// Network hash rate
+    pub hash_rate: f64,
+    /// Connected peers
+    pub connected_peers: u32,
+    /// Sync status
+    pub sync_status: String,
+    /// Network difficulty
+    pub difficulty: u32,
+    /// Average block time
+    pub average_block_time: f64,
+}
+
+/// Difficulty adjustment data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DifficultyAdjustmentData {
+    /// Old difficulty
+    pub old_difficulty: u32,
+    /// New difficulty
+    pub new_difficulty: u32,
+    /// Change percentage
+    pub change_percentage: f64,
+    /// Block height of adjustment
+    pub block_height: u64,
+    /// Next adjustment in blocks
+    pub next_adjustment: u64,
+}
+
+/// Connection status data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConnectionStatusData {
+    /// Connection ID
+    pub connection_id: String,
+    /// Connection status
+    pub status: String,
+    /// Connected at
+    pub connected_at: u64,
+    /// Subscriptions
+    pub subscriptions: Vec<String>,
+}
+
+/// Error data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorData {
+    /// Error code
+    pub code: String,
+    /// Error message
+    pub message: String,
+    /// Additional details
+    pub details: Option<serde_json::Value>,
+}
+
+/// Ping data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PingData {
+    /// Timestamp
+    pub timestamp: u64,
+    /// Optional message
+    pub message: Option<String>,
+}
+
+/// Pong data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PongData {
+    /// Original timestamp from ping
+    pub timestamp: u64,
+    /// Response timestamp
+    pub response_timestamp: u64,
+    /// Optional message
+    pub message: Option<String>,
+}
+
+/// Subscription data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SubscriptionData {
+    /// Subscription topic
+    pub topic: String,
+    /// Subscription ID
+    pub subscription_id: String,
+    /// Success status
+    pub success: bool,
+    /// Optional message
+    pub message: Option<String>,
+}
+
+/// Unsubscription data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UnsubscriptionData {
+    /// Subscription topic
+    pub topic: String,
+    /// Subscription ID
+    pub subscription_id: String,
+    /// Success status
+    pub success: bool,
+}
+
+/// WebSocket client subscription request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SubscriptionRequest {
+    /// Action (subscribe/unsubscribe)
+    pub action: String,
+    /// Topic to subscribe to
+    pub topic: String,
+    /// Optional parameters
+    pub params: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl SubscriptionRequest {
+    pub fn subscribe(topic: impl Into<String>) -> Self {
+        Self { action: "subscribe".to_string(), topic: topic.into(), params: None }
+    }
+
+    pub fn unsubscribe(topic: impl Into<String>) -> Self {
+        Self { action: "unsubscribe".to_string(), topic: topic.into(), params: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_is_adjacently_tagged() {
+        let message = WsMessage::Ping(PingData { timestamp: 7, message: None });
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "Ping");
+        assert_eq!(json["data"]["timestamp"], 7);
+        assert!(matches!(serde_json::from_value(json).unwrap(), WsMessage::Ping(_)));
+    }
+
+    #[test]
+    fn test_mining_progress_defaults_to_searching() {
+        let json = serde_json::json!({
+            "block_height": 3,
+            "difficulty": 4,
+            "nonce": 100,
+            "hash_rate": 10.0,
+            "progress": 50.0,
+            "estimated_time": null,
+            "attempts": 100,
+            "best_hash": null,
+        });
+        let data: MiningProgressData = serde_json::from_value(json).unwrap();
+        assert_eq!(data.state, MiningState::Searching);
+        assert_eq!(serde_json::to_value(MiningState::Timeout).unwrap(), "timeout");
+    }
+}
@@ -0,0 +1,27 @@
+//! Blocks arrive as bincode from peers and from `?verbosity=0` clients; decoding and
+//! validating whatever they send must return an error, never panic.
+
+#![no_main]
+
+use std::collections::HashMap;
+
+use ledgerdb::core::{Block, BlockHeader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = BlockHeader::from_bytes(data) {
+        let _ = header.hash();
+        let _ = header.validate();
+        let _ = header.meets_difficulty_target();
+    }
+
+    if let Ok(block) = Block::from_bytes(data) {
+        let _ = block.hash();
+        let _ = block.stats();
+        let _ = block.validate(None, &HashMap::new());
+        let reencoded = block.to_bytes().expect("a decoded block encodes");
+        let again = Block::from_bytes(&reencoded).expect("a re-encoded block decodes");
+        // Compared as bytes: a NaN fee multiplier is never equal to itself
+        assert_eq!(again.to_bytes().expect("a decoded block encodes"), reencoded);
+    }
+});
@@ -0,0 +1,20 @@
+//! Transactions are stored and relayed as bincode; decoding and validating an
+//! arbitrary one must return an error, never panic.
+
+#![no_main]
+
+use std::collections::HashMap;
+
+use ledgerdb::core::Transaction;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = Transaction::from_bytes(data) {
+        let _ = tx.hash();
+        let _ = tx.is_coinbase();
+        let _ = tx.total_output_amount();
+        let _ = tx.calculate_fee(&HashMap::new());
+        let _ = tx.validate(&HashMap::new());
+        let _ = tx.verify_signatures();
+    }
+});
@@ -0,0 +1,14 @@
+//! UTXO ids come straight from API paths; parsing must reject, not panic, and what
+//! parses must print back to an id that parses the same.
+
+#![no_main]
+
+use ledgerdb::core::UtxoId;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(s) = std::str::from_utf8(data) else { return };
+    if let Ok(id) = UtxoId::from_string(s) {
+        assert_eq!(UtxoId::from_string(&id.to_string()).expect("printed id parses"), id);
+    }
+});
@@ -0,0 +1,204 @@
+//! Client addresses and who may reach the admin and mining endpoints.
+//!
+//! The client of a request is the peer that connected, unless that peer is a
+//! trusted reverse proxy: then it is read from `X-Forwarded-For`, walking back past
+//! every trusted proxy. [`access_middleware`] stores it as a [`ClientIp`] request
+//! extension for later layers and handlers, and refuses clients outside the
+//! configured allow and deny lists.
+
+use super::{middleware::path_under, ApiError};
+use crate::config::{AccessConfig, IpAccessList};
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnet::IpNet;
+use ledgerdb_types::ErrorCode;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Header listing the addresses a request was forwarded for, client first
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Routes under [`AccessConfig::admin`], with everything below them
+const ADMIN_ROUTES: &[&str] = &["/admin"];
+
+/// Routes under [`AccessConfig::mining`], with everything below them
+const MINING_ROUTES: &[&str] = &["/api/mine", "/api/blocks/submit", "/api/blocks/compact"];
+
+/// Address of the client that sent the request, behind any trusted proxies
+///
+/// Set as a request extension by [`access_middleware`]. Connections without a
+/// peer address, such as those on the Unix socket, have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// The client behind a connection from `peer`
+///
+/// Each proxy appends the address it received the request from to
+/// `X-Forwarded-For`, so the hops are read from the right, stopping at the first
+/// one that is not a trusted proxy. Anything a client wrote further left is
+/// ignored.
+pub fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let trusted = |ip: IpAddr| trusted_proxies.iter().any(|net| net.contains(&ip));
+    let mut client = peer.to_canonical();
+    if !trusted(client) {
+        return client;
+    }
+    let hops: Vec<&str> = headers
+        .get_all(FORWARDED_FOR_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for hop in hops.into_iter().rev() {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => {
+                client = ip.to_canonical();
+                if !trusted(client) {
+                    break;
+                }
+            }
+            // A garbled hop was not written by a trusted proxy; the last good one stands
+            Err(_) => break,
+        }
+    }
+    client
+}
+
+/// The rules for requests to `path`, if it is an admin or mining endpoint
+fn rules_for<'a>(access: &'a AccessConfig, path: &str) -> Option<&'a IpAccessList> {
+    if path_under(path, ADMIN_ROUTES) {
+        Some(&access.admin)
+    } else if path_under(path, MINING_ROUTES) {
+        Some(&access.mining)
+    } else {
+        None
+    }
+}
+
+/// Resolve the [`ClientIp`] and refuse clients the admin and mining rules keep out
+///
+/// Needs the router to be served with `ConnectInfo<SocketAddr>`. Requests without
+/// a peer address are let through: on the Unix socket the file permissions
+/// decide who may connect.
+pub async fn access_middleware(
+    State(access): State<Arc<AccessConfig>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Through the extractor, which also honours a `MockConnectInfo` in tests
+    let (mut parts, body) = request.into_parts();
+    let peer = ConnectInfo::<SocketAddr>::from_request_parts(&mut parts, &()).await.ok();
+    let mut request = Request::from_parts(parts, body);
+    let client = peer.map(|ConnectInfo(peer)| resolve_client_ip(peer.ip(), request.headers(), &access.trusted_proxies));
+
+    if let Some(client) = client {
+        if let Some(rules) = rules_for(&access, request.uri().path()) {
+            if !rules.permits(client) {
+                warn!("Refused {} {} from {}", request.method(), request.uri().path(), client);
+                return ApiError::new(ErrorCode::Forbidden, format!("{} may not use this endpoint", client))
+                    .into_response();
+            }
+        }
+        request.extensions_mut().insert(ClientIp(client));
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::connect_info::MockConnectInfo,
+        http::{HeaderValue, StatusCode},
+        middleware::from_fn_with_state,
+        routing::get,
+        Extension, Router,
+    };
+    use tower::ServiceExt;
+
+    fn nets(networks: &[&str]) -> Vec<IpNet> {
+        networks.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(FORWARDED_FOR_HEADER, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_client_ip_behind_trusted_proxies() {
+        let proxies = nets(&["10.0.0.0/8"]);
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        // A direct client's header is not believed
+        let spoofed = forwarded(&["127.0.0.1"]);
+        assert_eq!(resolve_client_ip(ip("203.0.113.9"), &spoofed, &proxies), ip("203.0.113.9"));
+
+        // Behind two proxies, the spoofed entry left of the real client is skipped
+        let chain = forwarded(&["127.0.0.1, 198.51.100.4", "10.0.0.7"]);
+        assert_eq!(resolve_client_ip(ip("10.0.0.2"), &chain, &proxies), ip("198.51.100.4"));
+
+        // A proxy without the header, or a garbled hop, leaves the last known address
+        assert_eq!(resolve_client_ip(ip("10.0.0.2"), &HeaderMap::new(), &proxies), ip("10.0.0.2"));
+        let garbled = forwarded(&["198.51.100.4, nonsense"]);
+        assert_eq!(resolve_client_ip(ip("10.0.0.2"), &garbled, &proxies), ip("10.0.0.2"));
+
+        assert_eq!(resolve_client_ip(ip("::ffff:10.0.0.2"), &forwarded(&["192.0.2.1"]), &proxies), ip("192.0.2.1"));
+    }
+
+    #[test]
+    fn test_rules_by_route() {
+        let access = AccessConfig {
+            admin: IpAccessList { allow: nets(&["127.0.0.1/32"]), deny: Vec::new() },
+            ..AccessConfig::default()
+        };
+        assert!(std::ptr::eq(rules_for(&access, "/admin/verify").unwrap(), &access.admin));
+        assert!(std::ptr::eq(rules_for(&access, "/api/blocks/submit").unwrap(), &access.mining));
+        assert!(std::ptr::eq(rules_for(&access, "/api/blocks/compact").unwrap(), &access.mining));
+        assert!(rules_for(&access, "/administrator").is_none());
+        assert!(rules_for(&access, "/api/blocks").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_refuse_outside_clients() {
+        let access = AccessConfig {
+            trusted_proxies: nets(&["10.0.0.0/8"]),
+            admin: IpAccessList { allow: nets(&["127.0.0.1/32"]), deny: Vec::new() },
+            ..AccessConfig::default()
+        };
+        let app = |peer: &str| {
+            Router::new()
+                .route("/admin/tasks", get(|Extension(ClientIp(ip)): Extension<ClientIp>| async move { ip.to_string() }))
+                .layer(from_fn_with_state(Arc::new(access.clone()), access_middleware))
+                .layer(MockConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+        };
+        let request = |forwarded_for: Option<&str>| {
+            let builder = Request::builder().uri("/admin/tasks");
+            let builder = match forwarded_for {
+                Some(value) => builder.header(FORWARDED_FOR_HEADER, value),
+                None => builder,
+            };
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let response = app("127.0.0.1:5000").oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app("192.0.2.1:5000").oneshot(request(Some("127.0.0.1"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app("10.0.0.2:5000").oneshot(request(Some("127.0.0.1"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], b"127.0.0.1");
+    }
+}
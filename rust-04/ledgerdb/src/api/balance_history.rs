@@ -0,0 +1,190 @@
+//! Address balances over time, for `/api/addresses/{address}/balance/history`.
+//!
+//! A history is folded from the address's activity in the SQLite index. Computed
+//! histories are cached along with the block they run up to: the next request only
+//! folds in the blocks mined since, and a history whose block a reorg replaced is
+//! rebuilt from scratch.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use ledgerdb_types::{BalanceGranularity, BalancePoint};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Histories kept at most; the one covering the fewest blocks is dropped first
+pub const MAX_CACHED_HISTORIES: usize = 1024;
+
+/// What one transaction did to an address's balance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub timestamp: DateTime<Utc>,
+    pub received: u64,
+    pub sent: u64,
+}
+
+/// A balance history up to and including the block at `tip`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BalanceHistory {
+    /// Last block covered, `None` before any
+    pub tip: Option<(u64, String)>,
+    pub balance: u64,
+    pub points: Vec<BalancePoint>,
+}
+
+impl BalanceHistory {
+    /// Fold in `changes`, oldest first, from the blocks after `tip` up to the new
+    /// `tip`
+    pub fn extend(
+        &mut self,
+        granularity: BalanceGranularity,
+        changes: impl IntoIterator<Item = BalanceChange>,
+        tip: (u64, String),
+    ) {
+        for change in changes {
+            self.balance = self.balance.saturating_add(change.received).saturating_sub(change.sent);
+            let timestamp = match granularity {
+                BalanceGranularity::Block => change.timestamp,
+                BalanceGranularity::Day => start_of_day(change.timestamp.date_naive()),
+            };
+            let point = BalancePoint {
+                timestamp,
+                block_height: change.block_height,
+                block_hash: change.block_hash,
+                balance: self.balance,
+            };
+            // Later changes in the same block or day replace the earlier point
+            match self.points.last_mut() {
+                Some(last) if same_period(granularity, last, &point) => *last = point,
+                _ => self.points.push(point),
+            }
+        }
+        self.tip = Some(tip);
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+fn same_period(granularity: BalanceGranularity, a: &BalancePoint, b: &BalancePoint) -> bool {
+    match granularity {
+        BalanceGranularity::Block => a.block_height == b.block_height,
+        BalanceGranularity::Day => a.timestamp == b.timestamp,
+    }
+}
+
+/// Computed balance histories by address and granularity
+#[derive(Debug, Default)]
+pub struct BalanceHistoryCache {
+    histories: Mutex<HashMap<(String, BalanceGranularity), BalanceHistory>>,
+}
+
+impl BalanceHistoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached history of `address`, if `on_chain` says the block it runs up to
+    /// is still on the main chain; otherwise it is dropped
+    pub fn get(
+        &self,
+        address: &str,
+        granularity: BalanceGranularity,
+        on_chain: impl FnOnce(u64, &str) -> bool,
+    ) -> Option<BalanceHistory> {
+        let mut histories = self.lock();
+        let key = (address.to_string(), granularity);
+        let history = histories.get(&key)?;
+        match &history.tip {
+            Some((height, hash)) if on_chain(*height, hash) => Some(history.clone()),
+            _ => {
+                histories.remove(&key);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, address: &str, granularity: BalanceGranularity, history: BalanceHistory) {
+        let mut histories = self.lock();
+        let key = (address.to_string(), granularity);
+        if !histories.contains_key(&key) && histories.len() >= MAX_CACHED_HISTORIES {
+            let stalest = histories
+                .iter()
+                .min_by_key(|(_, history)| history.tip.as_ref().map(|(height, _)| *height))
+                .map(|(key, _)| key.clone());
+            if let Some(stalest) = stalest {
+                histories.remove(&stalest);
+            }
+        }
+        histories.insert(key, history);
+    }
+
+    /// Histories currently cached
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, BalanceGranularity), BalanceHistory>> {
+        self.histories.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn change(height: u64, hour: u32, received: u64, sent: u64) -> BalanceChange {
+        BalanceChange {
+            block_height: height,
+            block_hash: format!("{:064x}", height),
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 1 + hour / 24, hour % 24, 0, 0).unwrap(),
+            received,
+            sent,
+        }
+    }
+
+    fn tip(height: u64) -> (u64, String) {
+        (height, format!("{:064x}", height))
+    }
+
+    #[test]
+    fn test_points_per_block_and_day() {
+        let changes = vec![change(1, 1, 100, 0), change(2, 5, 0, 30), change(2, 5, 10, 0), change(3, 30, 5, 0)];
+
+        let mut by_block = BalanceHistory::default();
+        by_block.extend(BalanceGranularity::Block, changes.clone(), tip(3));
+        let balances: Vec<(u64, u64)> = by_block.points.iter().map(|p| (p.block_height, p.balance)).collect();
+        assert_eq!(balances, vec![(1, 100), (2, 80), (3, 85)]);
+
+        let mut by_day = BalanceHistory::default();
+        by_day.extend(BalanceGranularity::Day, changes, tip(3));
+        let balances: Vec<(u64, u64)> = by_day.points.iter().map(|p| (p.block_height, p.balance)).collect();
+        assert_eq!(balances, vec![(2, 80), (3, 85)]);
+        assert_eq!(by_day.points[1].timestamp, Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap());
+
+        // Extending picks up where the history stopped, merging into the same day
+        let mut extended = BalanceHistory::default();
+        extended.extend(BalanceGranularity::Day, vec![change(1, 1, 100, 0)], tip(1));
+        extended.extend(BalanceGranularity::Day, vec![change(2, 5, 0, 30), change(2, 5, 10, 0), change(3, 30, 5, 0)], tip(3));
+        assert_eq!(extended, by_day);
+    }
+
+    #[test]
+    fn test_reorged_histories_are_dropped() {
+        let cache = BalanceHistoryCache::new();
+        let mut history = BalanceHistory::default();
+        history.extend(BalanceGranularity::Block, vec![change(1, 1, 100, 0)], tip(4));
+        cache.insert("alice", BalanceGranularity::Block, history.clone());
+
+        assert_eq!(cache.get("alice", BalanceGranularity::Block, |_, _| true), Some(history));
+        assert_eq!(cache.get("alice", BalanceGranularity::Day, |_, _| true), None);
+        assert_eq!(cache.get("alice", BalanceGranularity::Block, |height, _| height != 4), None);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! The built-in explorer page and its assets, served on `/` and `/static/...`.
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+};
+
+/// Serve the main index.html file
+pub async fn serve_index() -> impl IntoResponse {
+    // Serve embedded HTML since static file doesn't exist yet
+    Html(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>LedgerDB Blockchain Explorer</title>
+    <style>
+        body { font-family: Arial, sans-serif; margin: 40px; background: #f5f5f5; }
+        .container { max-width: 800px; margin: 0 auto; background: white; padding: 30px; border-radius: 8px; box-shadow: 0 2px 10px rgba(0,0,0,0.1); }
+        h1 { color: #333; text-align: center; }
+        .status { background: #e8f5e8; padding: 15px; border-radius: 5px; margin: 20px 0; }
+        .endpoint { background: #f8f9fa; padding: 10px; margin: 10px 0; border-left: 4px solid #007bff; }
+        code { background: #f1f1f1; padding: 2px 6px; border-radius: 3px; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>🚀 LedgerDB Blockchain Explorer</h1>
+        <div class="status">
+            <h3>✅ Blockchain is running!</h3>
+            <p>The LedgerDB blockchain node is operational and ready to process transactions.</p>
+        </div>
+        
+        <h3>📡 API Endpoints</h3>
+        <div class="endpoint"><strong>GET /api/blockchain/info</strong> - Chain height, tip and network</div>
+        <div class="endpoint"><strong>GET /api/blocks</strong> - Get blocks</div>
+        <div class="endpoint"><strong>GET /api/blocks/:hash</strong> - Get block by hash</div>
+        <div class="endpoint"><strong>GET /api/transactions/pending</strong> - Get pending transactions</div>
+        <div class="endpoint"><strong>GET /api/transactions/:hash</strong> - Get transaction by hash</div>
+        <div class="endpoint"><strong>POST /api/psbt/finalize</strong> - Submit a signed transaction</div>
+        <div class="endpoint"><strong>GET /api/addresses/:address/transactions</strong> - Get address history</div>
+        <div class="endpoint"><strong>GET /api/mining/status</strong> - Mining status</div>
+        <div class="endpoint"><strong>GET /api/health</strong> - Health check</div>
+        <div class="endpoint"><strong>GET /api/docs</strong> - Interactive API documentation</div>
+        
+        <h3>🔌 WebSocket</h3>
+        <div class="endpoint"><strong>WS /ws</strong> - Real-time blockchain updates</div>
+        
+        <p style="text-align: center; margin-top: 30px; color: #666;">
+            Built with ❤️ using Rust, Axum, and Tokio
+        </p>
+    </div>
+</body>
+</html>"#
+    )
+}
+
+/// Serve static files
+pub async fn serve_static(Path(file): Path<String>) -> impl IntoResponse {
+    // In a real application, you'd serve actual static files
+    // For now, return a simple response
+    match file.as_str() {
+        "style.css" => (
+            StatusCode::OK,
+            [("content-type", "text/css")],
+            "/* LedgerDB Styles */\nbody { font-family: 'Segoe UI', sans-serif; }"
+        ).into_response(),
+        _ => (
+            StatusCode::NOT_FOUND,
+            "File not found"
+        ).into_response()
+    }
+}
@@ -4,22 +4,33 @@
 //! including block operations, transaction management, mining, and administrative functions.
 
 use super::{
-    responses::*, ApiError, AppState, PaginatedResponse, PaginationParams,
+    responses::*, ApiError, ApiResponse, AppState, Cursor, ErrorCode, ErrorResponse, PageWindow,
+    PaginatedResponse, PaginationParams,
 };
-use crate::core::{Block, Transaction};
+use super::websocket::MiningProgressData;
+use crate::core::{compact_block_header, reconstruct_block, Block, BlockStatus, Blockchain, ChunkedSnapshot, CompactBlock, Reconstruction, PartiallySignedTransaction, SNAPSHOT_CHUNK_SIZE, PendingTransaction, Transaction, UtxoEntry, UtxoId};
 use crate::crypto::{Address, Hash256};
+use crate::tasks::TaskHealth;
+use crate::tenants::{split_tenant_path, TenantManager};
+use crate::watch::WatchedAddress;
 use axum::{
-    extract::{Path, Query, State, WebSocketUpgrade},
-    http::StatusCode,
-    response::{IntoResponse, Json},
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use chrono::Utc;
 use serde_json::json;
-use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tower::ServiceExt;
 
 /// Health check endpoint
-pub async fn health_check() -> Json<HealthResponse> {
+#[utoipa::path(
+    get, path = "/health", tag = "node",
+    responses((status = 200, body = ApiResponse<HealthResponse>))
+)]
+pub async fn health_check() -> Json<ApiResponse<HealthResponse>> {
     let uptime = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -32,27 +43,318 @@ pub async fn health_check() -> Json<HealthResponse> {
         uptime,
     };
 
-    Json(response)
+    success(response)
 }
 
-/// Get API version
-pub async fn get_api_version(State(state): State<AppState>) -> Json<serde_json::Value> {
-    Json(json!({
-        "version": state.config.version,
-        "api_version": "v1",
-        "build_time": option_env!("BUILD_TIME").unwrap_or("unknown"),
-        "git_commit": option_env!("GIT_COMMIT").unwrap_or("unknown")
+/// Report the health of supervised background tasks
+#[utoipa::path(
+    get, path = "/admin/tasks", tag = "admin",
+    responses((status = 200, body = ApiResponse<Vec<TaskHealth>>))
+)]
+pub async fn get_task_health(State(state): State<AppState>) -> Json<ApiResponse<Vec<TaskHealth>>> {
+    success(state.tasks.health())
+}
+
+/// Check the stored blocks, transactions, UTXOs and indexes against each other
+#[utoipa::path(
+    get, path = "/admin/verify", tag = "admin",
+    responses((status = 200, body = ApiResponse<IntegrityReport>))
+)]
+pub async fn verify_storage(State(state): State<AppState>) -> ApiResult<IntegrityReport> {
+    check_storage(&state, false).await
+}
+
+/// Check the storage like `GET /admin/verify`, dropping dangling index entries
+#[utoipa::path(
+    post, path = "/admin/verify", tag = "admin",
+    responses((status = 200, body = ApiResponse<IntegrityReport>))
+)]
+pub async fn repair_storage(State(state): State<AppState>) -> ApiResult<IntegrityReport> {
+    check_storage(&state, true).await
+}
+
+async fn check_storage(state: &AppState, repair: bool) -> ApiResult<IntegrityReport> {
+    // Hold off chain writes, which would otherwise show up half-seen as dangling
+    // entries and be dropped by a repair
+    let _blockchain = state.blockchain.write().await;
+    let report = state.storage.check_integrity(repair).await.map_err(ApiError::from)?;
+    Ok(success(report))
+}
+
+/// The filter tracing events currently pass
+#[utoipa::path(
+    get, path = "/admin/logs/level", tag = "admin",
+    responses((status = 200, body = ApiResponse<LogLevel>), (status = 503, description = "Logging is not reloadable", body = ErrorResponse))
+)]
+pub async fn get_log_level() -> ApiResult<LogLevel> {
+    let level = crate::utils::logging::current_log_level()
+        .ok_or_else(|| ApiError::new(ErrorCode::ServiceUnavailable, "Logging is not reloadable in this node"))?;
+    Ok(success(LogLevel { level }))
+}
+
+/// Change the log filter until the node restarts or reloads a changed `logging.level`
+#[utoipa::path(
+    put, path = "/admin/logs/level", tag = "admin", request_body = LogLevel,
+    responses((status = 200, body = ApiResponse<LogLevel>), (status = 400, description = "Invalid filter", body = ErrorResponse))
+)]
+pub async fn set_log_level(Json(request): Json<LogLevel>) -> ApiResult<LogLevel> {
+    crate::utils::logging::set_log_level(&request.level)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e.to_string()))?;
+    tracing::info!("log level set to {}", request.level);
+    get_log_level().await
+}
+
+fn tenant_manager(state: &AppState) -> std::result::Result<&Arc<TenantManager>, ApiError> {
+    state
+        .tenants
+        .as_ref()
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Multi-tenant hosting is not enabled"))
+}
+
+/// List the chains hosted under `/t/{name}/`
+#[utoipa::path(
+    get, path = "/admin/tenants", tag = "admin",
+    responses((status = 200, body = ApiResponse<Vec<TenantInfo>>), (status = 404, description = "Tenants are not enabled", body = ErrorResponse))
+)]
+pub async fn list_tenants(State(state): State<AppState>) -> ApiResult<Vec<TenantInfo>> {
+    Ok(success(tenant_manager(&state)?.list().await))
+}
+
+/// Create and start a hosted chain
+#[utoipa::path(
+    post, path = "/admin/tenants", tag = "admin", request_body = CreateTenantRequest,
+    responses((status = 201, body = ApiResponse<TenantInfo>), (status = 400, description = "Invalid name or settings", body = ErrorResponse), (status = 409, description = "Name taken", body = ErrorResponse))
+)]
+pub async fn create_tenant(
+    State(state): State<AppState>,
+    Json(request): Json<CreateTenantRequest>,
+) -> std::result::Result<(StatusCode, Json<ApiResponse<TenantInfo>>), ApiError> {
+    crate::tenants::check_request(&request).map_err(|e| ApiError::new(ErrorCode::ValidationError, e))?;
+    let tenant = tenant_manager(&state)?
+        .create(request)
+        .await?
+        .ok_or_else(|| ApiError::new(ErrorCode::Conflict, "Tenant name is taken"))?;
+    Ok((StatusCode::CREATED, success(tenant)))
+}
+
+/// Start generating synthetic wallets, transactions and blocks on this chain
+#[utoipa::path(
+    post, path = "/admin/simulate", tag = "admin", request_body = SimulationParams,
+    responses((status = 202, description = "Running as a background task", body = ApiResponse<SimulationStarted>), (status = 400, description = "Invalid parameters", body = ErrorResponse))
+)]
+pub async fn start_simulation(
+    State(state): State<AppState>,
+    Json(params): Json<SimulationParams>,
+) -> std::result::Result<(StatusCode, Json<ApiResponse<SimulationStarted>>), ApiError> {
+    crate::simulation::check_params(&params).map_err(|e| ApiError::new(ErrorCode::ValidationError, e))?;
+    let started = crate::simulation::spawn(&state, params).await?;
+    Ok((StatusCode::ACCEPTED, success(started)))
+}
+
+/// Stop a hosted chain and move its data under `archived/`
+#[utoipa::path(
+    delete, path = "/admin/tenants/{name}", tag = "admin",
+    params(("name" = String, Path, description = "Tenant to archive")),
+    responses((status = 204, description = "Archived"), (status = 404, description = "Not found", body = ErrorResponse))
+)]
+pub async fn archive_tenant(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> std::result::Result<StatusCode, ApiError> {
+    if tenant_manager(&state)?.archive(&name).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(ErrorCode::NotFound, "No such tenant"))
+    }
+}
+
+fn peer_address(address: &str) -> std::result::Result<SocketAddr, ApiError> {
+    address
+        .parse()
+        .map_err(|_| ApiError::new(ErrorCode::InvalidRequest, "Peer address must be ip:port"))
+}
+
+/// List the peers in the address book, with their scores and bans
+#[utoipa::path(
+    get, path = "/admin/peers", tag = "admin",
+    responses((status = 200, body = ApiResponse<Vec<PeerRecord>>))
+)]
+pub async fn list_peers(State(state): State<AppState>) -> Json<ApiResponse<Vec<PeerRecord>>> {
+    success(state.peers.list())
+}
+
+/// Ban a peer, for at most the node's maximum ban time
+#[utoipa::path(
+    post, path = "/admin/peers/{address}/ban", tag = "admin", request_body = BanRequest,
+    params(("address" = String, Path, description = "Peer as ip:port")),
+    responses((status = 200, body = ApiResponse<PeerRecord>), (status = 400, description = "Invalid address or duration", body = ErrorResponse))
+)]
+pub async fn ban_peer(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(request): Json<BanRequest>,
+) -> ApiResult<PeerRecord> {
+    let address = peer_address(&address)?;
+    if request.duration == Some(0) {
+        return Err(ApiError::new(ErrorCode::ValidationError, "Ban duration must be positive"));
+    }
+    let peers = state.peers.clone();
+    let peer = state.storage.spawn(move || peers.ban(address, request.duration, request.reason)).await?;
+    Ok(success(peer))
+}
+
+/// Lift a peer's ban and clear its misbehavior score
+#[utoipa::path(
+    delete, path = "/admin/peers/{address}/ban", tag = "admin",
+    params(("address" = String, Path, description = "Peer as ip:port")),
+    responses((status = 204, description = "Unbanned"), (status = 400, description = "Invalid address", body = ErrorResponse), (status = 404, description = "Not banned", body = ErrorResponse))
+)]
+pub async fn unban_peer(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> std::result::Result<StatusCode, ApiError> {
+    let address = peer_address(&address)?;
+    let peers = state.peers.clone();
+    if state.storage.spawn(move || peers.unban(address)).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(ErrorCode::NotFound, "Peer is not banned"))
+    }
+}
+
+/// Hand a request under `/t/{name}/` to that tenant's router, without the prefix
+pub async fn tenant_proxy(State(state): State<AppState>, mut request: Request) -> Response {
+    let Some((name, path)) = split_tenant_path(request.uri().path()) else {
+        return ApiError::new(ErrorCode::NotFound, "No such tenant").into_response();
+    };
+    let tenant = state.tenants.as_ref().and_then(|tenants| tenants.get(name));
+    let uri = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let Some(tenant) = tenant else {
+        return ApiError::new(ErrorCode::NotFound, "No such tenant").into_response();
+    };
+    match uri.parse() {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(e) => return ApiError::new(ErrorCode::InvalidRequest, format!("Invalid path: {}", e)).into_response(),
+    }
+    match tenant.router().oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// Log in; send the token as `Authorization: Bearer <token>` on later requests
+#[utoipa::path(
+    post, path = "/api/auth/login", tag = "auth", request_body = LoginRequest,
+    responses((status = 200, body = ApiResponse<LoginResponse>), (status = 401, description = "Wrong username or password", body = ErrorResponse))
+)]
+pub async fn login(State(state): State<AppState>, Json(request): Json<LoginRequest>) -> ApiResult<LoginResponse> {
+    // Checking the password hash is deliberately slow
+    let users = state.users.clone();
+    let username = request.username.clone();
+    let session = tokio::task::spawn_blocking(move || users.login(&request.username, &request.password))
+        .await
+        .map_err(|e| ApiError::from(crate::error::LedgerError::Internal(format!("Login task failed: {}", e))))?;
+    let Some((token, session)) = session else {
+        tracing::warn!("failed login for {}", username);
+        return Err(ApiError::new(ErrorCode::Unauthorized, "Wrong username or password"));
+    };
+    Ok(success(LoginResponse {
+        token,
+        username: session.username,
+        role: session.role,
+        expires_at: session.expires_at,
     }))
 }
 
+/// End the session of the token the request is sent with
+#[utoipa::path(
+    post, path = "/api/auth/logout", tag = "auth",
+    responses((status = 204, description = "Logged out"), (status = 401, description = "Not logged in", body = ErrorResponse))
+)]
+pub async fn logout(State(state): State<AppState>, headers: HeaderMap) -> std::result::Result<StatusCode, ApiError> {
+    match super::bearer_token(&headers) {
+        Some(token) if state.users.logout(token) => Ok(StatusCode::NO_CONTENT),
+        _ => Err(ApiError::new(ErrorCode::Unauthorized, "No session to end")),
+    }
+}
+
+/// List user accounts
+#[utoipa::path(
+    get, path = "/api/users", tag = "auth",
+    responses((status = 200, body = ApiResponse<Vec<UserAccount>>))
+)]
+pub async fn list_users(State(state): State<AppState>) -> Json<ApiResponse<Vec<UserAccount>>> {
+    success(state.users.list())
+}
+
+/// Create a user account
+#[utoipa::path(
+    post, path = "/api/users", tag = "auth", request_body = CreateUserRequest,
+    responses((status = 201, body = ApiResponse<UserAccount>), (status = 400, description = "Invalid username or password", body = ErrorResponse), (status = 409, description = "Username taken", body = ErrorResponse))
+)]
+pub async fn create_user(
+    State(state): State<AppState>,
+    Json(request): Json<CreateUserRequest>,
+) -> std::result::Result<(StatusCode, Json<ApiResponse<UserAccount>>), ApiError> {
+    crate::auth::check_credentials(&request.username, &request.password)
+        .map_err(|e| ApiError::new(ErrorCode::ValidationError, e))?;
+    let users = state.users.clone();
+    let account = state
+        .storage
+        .spawn(move || users.create(&request.username, &request.password, request.role))
+        .await?
+        .ok_or_else(|| ApiError::new(ErrorCode::Conflict, "Username is taken"))?;
+    Ok((StatusCode::CREATED, success(account)))
+}
+
+/// Delete a user account and end its sessions
+#[utoipa::path(
+    delete, path = "/api/users/{username}", tag = "auth",
+    params(("username" = String, Path, description = "Account to delete")),
+    responses((status = 204, description = "Deleted"), (status = 404, description = "Not found", body = ErrorResponse))
+)]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+) -> std::result::Result<StatusCode, ApiError> {
+    let users = state.users.clone();
+    if state.storage.spawn(move || users.remove(&username)).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(ErrorCode::NotFound, "No such user"))
+    }
+}
+
+/// Get API version
+#[utoipa::path(
+    get, path = "/version", tag = "node",
+    responses((status = 200, body = ApiResponse<VersionResponse>))
+)]
+pub async fn get_api_version(State(state): State<AppState>) -> Json<ApiResponse<VersionResponse>> {
+    success(VersionResponse {
+        version: state.config.version.clone(),
+        api_version: "v1".to_string(),
+        build_time: option_env!("BUILD_TIME").unwrap_or("unknown").to_string(),
+        git_commit: option_env!("GIT_COMMIT").unwrap_or("unknown").to_string(),
+    })
+}
+
 /// Get blockchain information
+#[utoipa::path(
+    get, path = "/api/blockchain/info", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<BlockchainInfoResponse>))
+)]
 pub async fn get_blockchain_info(
     State(state): State<AppState>,
-) -> std::result::Result<Json<BlockchainInfoResponse>, ApiError> {
+) -> ApiResult<BlockchainInfoResponse> {
     let blockchain = state.blockchain.read().await;
     let stats = blockchain.get_stats();
     
     let response = BlockchainInfoResponse {
+        network: blockchain.network(),
         height: stats.height,
         latest_block_hash: blockchain.get_latest_block().map(|b| b.hash()).unwrap_or_default(),
         total_transactions: stats.total_transactions,
@@ -61,305 +363,952 @@ pub async fn get_blockchain_info(
         network_hash_rate: calculate_network_hash_rate(&blockchain).await,
     };
 
-    Ok(Json(response))
+    Ok(success(response))
+}
+
+/// Get the current block reward and the next change in the reward schedule
+#[utoipa::path(
+    get, path = "/api/reward", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<RewardResponse>))
+)]
+pub async fn get_reward(State(state): State<AppState>) -> Json<ApiResponse<RewardResponse>> {
+    let blockchain = state.blockchain.read().await;
+    let schedule = blockchain.config.reward_schedule.clone();
+    let height = blockchain.height();
+    let next_change_height = schedule.next_change(height);
+
+    success(RewardResponse {
+        height,
+        current_reward: schedule.reward_at(height),
+        next_reward: next_change_height.map(|h| schedule.reward_at(h)),
+        next_change_height,
+        schedule,
+    })
+}
+
+/// Progress of the initial block download from the configured sync peers
+#[utoipa::path(
+    get, path = "/api/sync/status", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<SyncStatus>))
+)]
+pub async fn get_sync_status(State(state): State<AppState>) -> Json<ApiResponse<SyncStatus>> {
+    success(state.sync.status())
+}
+
+/// Where other nodes can reach this one: its listen addresses, the external URL
+/// to give them as a sync peer and any UPnP port mapping
+#[utoipa::path(
+    get, path = "/api/p2p/addresses", tag = "node",
+    responses((status = 200, body = ApiResponse<NodeAddresses>))
+)]
+pub async fn get_node_addresses(State(state): State<AppState>) -> Json<ApiResponse<NodeAddresses>> {
+    success(state.p2p.addresses())
+}
+
+/// Get the pinned checkpoints
+#[utoipa::path(
+    get, path = "/api/checkpoints", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<CheckpointsResponse>))
+)]
+pub async fn get_checkpoints(State(state): State<AppState>) -> Json<ApiResponse<CheckpointsResponse>> {
+    let blockchain = state.blockchain.read().await;
+    let height = blockchain.height();
+    let checkpoints = blockchain
+        .config
+        .checkpoints
+        .iter()
+        .map(|(&checkpoint_height, hash)| CheckpointInfo {
+            height: checkpoint_height,
+            hash: hash.clone(),
+            reached: checkpoint_height < height,
+        })
+        .collect();
+
+    success(CheckpointsResponse {
+        checkpoints,
+        last_checkpoint: blockchain.last_checkpoint().map(|(height, _)| height),
+    })
+}
+
+/// Summarize the UTXO set at a height, including its commitment hash
+#[utoipa::path(
+    get, path = "/api/snapshot/utxo", tag = "blockchain", params(SnapshotParams),
+    responses((status = 200, body = ApiResponse<UtxoSnapshotResponse>), (status = 400, description = "Invalid request", body = ErrorResponse))
+)]
+pub async fn get_utxo_snapshot_info(
+    State(state): State<AppState>,
+    Query(params): Query<SnapshotParams>,
+) -> ApiResult<UtxoSnapshotResponse> {
+    let blockchain = state.blockchain.read().await;
+    let height = params.height.unwrap_or_else(|| blockchain.height().saturating_sub(1));
+    let snapshot = blockchain.utxo_snapshot(height).map_err(ApiError::from)?;
+
+    Ok(success(UtxoSnapshotResponse {
+        height: snapshot.height,
+        block_hash: snapshot.block_hash.clone(),
+        commitment: snapshot.commitment.clone(),
+        utxo_count: snapshot.utxos.len(),
+        total_amount: snapshot.total_amount(),
+        import_validation: blockchain.snapshot_validation().cloned(),
+    }))
+}
+
+/// Download the UTXO set at a height as a snapshot file (`LEDGER_UTXO_SNAPSHOT`)
+#[utoipa::path(
+    get, path = "/api/snapshot/utxo/download", tag = "blockchain", params(SnapshotParams),
+    responses(
+        (status = 200, description = "Bincode snapshot file", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 400, description = "Invalid request", body = ErrorResponse)
+    )
+)]
+pub async fn download_utxo_snapshot(
+    State(state): State<AppState>,
+    Query(params): Query<SnapshotParams>,
+) -> std::result::Result<Response, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let height = params.height.unwrap_or_else(|| blockchain.height().saturating_sub(1));
+    let snapshot = blockchain.utxo_snapshot(height).map_err(ApiError::from)?;
+    drop(blockchain);
+
+    let bytes = bincode::serialize(&snapshot)
+        .map_err(|e| ApiError::new(ErrorCode::SerializationError, e.to_string()))?;
+    let disposition = format!("attachment; filename=\"utxo-{}.snapshot\"", snapshot.height);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    ).into_response())
+}
+
+/// Chunk manifest of the UTXO snapshot at a height, for downloading the snapshot
+/// file piece by piece from several nodes (`ledgerdb fetch-snapshot`)
+#[utoipa::path(
+    get, path = "/api/snapshot/utxo/manifest", tag = "blockchain", params(SnapshotParams),
+    responses((status = 200, body = ApiResponse<SnapshotManifest>), (status = 404, description = "No block at that height", body = ErrorResponse))
+)]
+pub async fn get_snapshot_manifest(
+    State(state): State<AppState>,
+    Query(params): Query<SnapshotParams>,
+) -> ApiResult<SnapshotManifest> {
+    let snapshot = chunked_snapshot(&state, params.height).await?;
+    Ok(success(snapshot.manifest.clone()))
+}
+
+/// One chunk of a snapshot file, by the SHA-256 listed in its manifest
+///
+/// A node that has not built the snapshot yet builds it at `height`, which a
+/// download passes from the manifest.
+#[utoipa::path(
+    get, path = "/api/snapshot/utxo/chunks/{hash}", tag = "blockchain",
+    params(("hash" = String, Path, description = "Chunk SHA-256 (hex)"), SnapshotParams),
+    responses(
+        (status = 200, description = "Chunk bytes", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 404, description = "No such chunk", body = ErrorResponse)
+    )
+)]
+pub async fn get_snapshot_chunk(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    Query(params): Query<SnapshotParams>,
+) -> std::result::Result<Response, ApiError> {
+    let hash: ledgerdb_types::Hash256 = hash
+        .parse()
+        .map_err(|_| ApiError::new(ErrorCode::InvalidHash, "Invalid chunk hash format"))?;
+    let snapshot = match state.snapshot_chunks.with_chunk(&hash) {
+        Some(snapshot) => snapshot,
+        None if params.height.is_some() => chunked_snapshot(&state, params.height).await?,
+        None => return Err(ApiError::new(ErrorCode::NotFound, "Snapshot chunk not found")),
+    };
+    let chunk = snapshot
+        .chunk(&hash)
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Snapshot chunk not found"))?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], chunk.to_vec()).into_response())
+}
+
+/// The snapshot at `height` (default: the tip) in chunks, built once per block
+async fn chunked_snapshot(state: &AppState, height: Option<u64>) -> std::result::Result<Arc<ChunkedSnapshot>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let height = height.unwrap_or_else(|| blockchain.height().saturating_sub(1));
+    let block = blockchain
+        .get_block_by_index(height)
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, format!("No block at height {}", height)))?;
+    let block_hash = ledgerdb_types::Hash256(*block.hash().as_bytes());
+    state
+        .snapshot_chunks
+        .get_or_compute(&block_hash, || ChunkedSnapshot::new(&blockchain.utxo_snapshot(height)?, SNAPSHOT_CHUNK_SIZE))
+        .map_err(ApiError::from)
+}
+
+/// Most headers returned by one `/api/headers` request
+pub const MAX_HEADERS_PER_REQUEST: u64 = 2000;
+
+/// Compact block headers from `from`, for light clients to check with
+/// `verify_header_chain` before trusting merkle proofs
+#[utoipa::path(
+    get, path = "/api/headers", tag = "blocks", params(HeadersParams),
+    responses((status = 200, body = ApiResponse<HeadersResponse>), (status = 404, description = "Not found", body = ErrorResponse))
+)]
+pub async fn get_headers(
+    State(state): State<AppState>,
+    Query(params): Query<HeadersParams>,
+) -> ApiResult<HeadersResponse> {
+    let from = params.from.unwrap_or(0);
+    let count = params.count.unwrap_or(MAX_HEADERS_PER_REQUEST).min(MAX_HEADERS_PER_REQUEST);
+    let blockchain = state.blockchain.read().await;
+    let tip_height = blockchain.height().saturating_sub(1);
+    if from > tip_height {
+        return Err(ApiError::new(ErrorCode::NotFound, format!("No block at height {}", from)));
+    }
+
+    let headers = (from..from.saturating_add(count).min(tip_height + 1))
+        .filter_map(|height| blockchain.get_block_by_index(height))
+        .map(|block| block.header.to_bytes().map(hex::encode))
+        .collect::<crate::Result<Vec<_>>>()
+        .map_err(ApiError::from)?;
+
+    Ok(success(HeadersResponse { from, tip_height, headers }))
 }
 
 /// Get blockchain statistics
+#[utoipa::path(
+    get, path = "/api/blockchain/stats", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<BlockchainStatsResponse>))
+)]
 pub async fn get_blockchain_stats(
     State(state): State<AppState>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
+) -> ApiResult<BlockchainStatsResponse> {
     let blockchain = state.blockchain.read().await;
     let stats = blockchain.get_stats();
     let storage_stats = state.storage.get_stats().await.map_err(ApiError::from)?;
 
-    let response = json!({
-        "blockchain": {
-            "height": stats.height,
-            "total_blocks": stats.height,
-            "total_transactions": stats.total_transactions,
-            "total_supply": stats.total_supply,
-            "average_block_time": stats.average_block_time,
-            "difficulty": blockchain.get_current_difficulty(),
+    let response = BlockchainStatsResponse {
+        blockchain: ChainStats {
+            height: stats.height,
+            total_blocks: stats.height,
+            total_transactions: stats.total_transactions,
+            total_supply: stats.total_supply,
+            average_block_time: stats.average_block_time,
+            difficulty: blockchain.get_current_difficulty(),
+            chain_work: format_chain_work(stats.chain_work),
         },
-        "storage": {
-            "total_size": storage_stats.total_size,
-            "block_count": storage_stats.block_count,
-            "transaction_count": storage_stats.transaction_count,
-            "utxo_count": storage_stats.utxo_count,
+        storage: StorageStats {
+            total_size: storage_stats.database_size,
+            block_count: storage_stats.blocks_count as u64,
+            transaction_count: storage_stats.transactions_count as u64,
+            utxo_count: storage_stats.utxos_count as u64,
+            trees: storage_stats.trees.into_iter().map(|(name, len)| (name, len as u64)).collect(),
+            cache_capacity: storage_stats.cache_capacity,
+            io: storage_stats.io,
         },
-        "network": {
-            "hash_rate": calculate_network_hash_rate(&blockchain).await,
-            "connected_peers": 0, // TODO: Implement peer management
-        }
-    });
+        network: NetworkStats {
+            hash_rate: calculate_network_hash_rate(&blockchain).await,
+            connected_peers: 0, // TODO: Implement peer management
+        },
+    };
 
-    Ok(Json(response))
+    Ok(success(response))
 }
 
 /// Get blocks with pagination
+#[utoipa::path(
+    get, path = "/api/blocks", tag = "blocks", params(PaginationParams, BlockParams),
+    responses((status = 200, body = ApiResponse<PaginatedResponse<BlockView>>), (status = 400, description = "Invalid request", body = ErrorResponse))
+)]
 pub async fn get_blocks(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> std::result::Result<Json<PaginatedResponse<Block>>, ApiError> {
+    Query(block_params): Query<BlockParams>,
+) -> ApiResult<PaginatedResponse<BlockView>> {
+    let verbosity = block_verbosity(&block_params)?;
     let blockchain = state.blockchain.read().await;
-    let page = params.page.unwrap_or(0);
-    let limit = params.limit.unwrap_or(20).min(100); // Cap at 100
+    let limit = params.limit.unwrap_or(20).clamp(1, 100); // Cap at 100
     
+    // Blocks are listed by height, so page 0 holds the newest
     let total_blocks = blockchain.height();
-    let start_height = if page * limit > total_blocks {
-        return Ok(Json(super::paginate(vec![], page, limit, total_blocks)));
-    } else {
-        total_blocks.saturating_sub((page + 1) * limit)
-    };
+    let window = page_window(&params, limit, total_blocks as usize, |index| (index as u64, ""))?;
     
-    let mut blocks = Vec::new();
-    for height in start_height..start_height + limit.min(total_blocks - start_height) {
-        if let Some(block) = blockchain.get_block_by_index(height) {
-            blocks.push(block);
+    let mut blocks = Vec::with_capacity(window.range.len());
+    for height in window.range.clone() {
+        if let Some(block) = blockchain.get_block_by_index(height as u64) {
+            blocks.push(block_view(block, &blockchain, verbosity)?);
         }
     }
     
-    // Reverse for descending order (newest first)
-    if params.order.as_deref() != Some("asc") {
+    if window.newest_first {
         blocks.reverse();
     }
     
-    Ok(Json(super::paginate(blocks, page, limit, total_blocks)))
+    Ok(success(super::paginate_window(blocks, &params, limit, total_blocks, &window)))
+}
+
+/// Where the page `params` asks for lies in a listing of `len` items sorted oldest
+/// first, `key_at` giving each item's cursor position and key
+fn page_window<'a>(
+    params: &PaginationParams,
+    limit: u64,
+    len: usize,
+    key_at: impl Fn(usize) -> (u64, &'a str),
+) -> std::result::Result<PageWindow, ApiError> {
+    let cursor = match params.cursor.as_deref() {
+        Some(token) => Some(
+            Cursor::decode(token)
+                .ok_or_else(|| ApiError::new(ErrorCode::InvalidRequest, "Invalid pagination cursor"))?,
+        ),
+        None => None,
+    };
+    let ascending = params.order.as_deref() == Some("asc");
+    Ok(PageWindow::new(len, limit, params.page.unwrap_or(0), cursor.as_ref(), ascending, key_at))
 }
 
 /// Get latest block
+#[utoipa::path(
+    get, path = "/api/blocks/latest", tag = "blocks", params(BlockParams),
+    responses((status = 200, body = ApiResponse<BlockView>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
 pub async fn get_latest_block(
     State(state): State<AppState>,
-) -> std::result::Result<Json<Block>, ApiError> {
+    Query(params): Query<BlockParams>,
+) -> ApiResult<BlockView> {
+    let verbosity = block_verbosity(&params)?;
     let blockchain = state.blockchain.read().await;
     
-    blockchain
+    let block = blockchain
         .get_latest_block()
-        .cloned()
-        .map(Json)
-        .ok_or_else(|| ApiError::new("NOT_FOUND", "No blocks found"))
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "No blocks found"))?;
+    Ok(success(block_view(block, &blockchain, verbosity)?))
+}
+
+/// Submit an externally mined block.
+///
+/// The block must carry valid proof-of-work. One extending the tip is validated in
+/// full and connected, along with any orphans waiting on it. One building on an
+/// older block starts or extends a side branch (202), which replaces the chain above
+/// the fork once it has more work. One with an unknown parent goes to the orphan
+/// pool (202). A rejected block gets `BLOCK_REJECTED` with the reason.
+#[utoipa::path(
+    post, path = "/api/blocks/submit", tag = "blocks", request_body = Block,
+    responses(
+        (status = 200, description = "Connected, possibly reorganizing the chain, or already known", body = ApiResponse<BlockSubmitResponse>),
+        (status = 202, description = "Kept on a side branch with less work than the chain, or parent unknown and held in the orphan pool", body = ApiResponse<BlockSubmitResponse>),
+        (status = 400, description = "Rejected (`BLOCK_REJECTED`)", body = ErrorResponse)
+    )
+)]
+pub async fn submit_block(
+    State(state): State<AppState>,
+    Json(block): Json<Block>,
+) -> std::result::Result<(StatusCode, Json<ApiResponse<BlockSubmitResponse>>), ApiError> {
+    let (code, response) = process_submitted_block(&state, block).await?;
+    Ok((code, success(response)))
+}
+
+/// Announce a block by its header and short transaction IDs.
+///
+/// The node rebuilds the block from the transactions in its pool and handles it as
+/// `/api/blocks/submit` would. If any are missing it answers `incomplete` with
+/// their positions, and the announcer should submit the full block instead.
+#[utoipa::path(
+    post, path = "/api/blocks/compact", tag = "blocks", request_body = CompactBlock,
+    responses(
+        (status = 200, description = "Connected, possibly reorganizing the chain, already known, or `incomplete`", body = ApiResponse<BlockSubmitResponse>),
+        (status = 202, description = "Kept on a side branch or held in the orphan pool", body = ApiResponse<BlockSubmitResponse>),
+        (status = 400, description = "Malformed announcement (`INVALID_REQUEST`) or rejected block (`BLOCK_REJECTED`)", body = ErrorResponse)
+    )
+)]
+pub async fn submit_compact_block(
+    State(state): State<AppState>,
+    Json(compact): Json<CompactBlock>,
+) -> std::result::Result<(StatusCode, Json<ApiResponse<BlockSubmitResponse>>), ApiError> {
+    let invalid = |e: crate::error::LedgerError| ApiError::new(ErrorCode::InvalidRequest, e.to_string());
+    let hash = compact_block_header(&compact).map_err(invalid)?.hash();
+    let reconstruction = {
+        let blockchain = state.blockchain.read().await;
+        if blockchain.get_block_by_hash(&hash).is_some() {
+            let response = BlockSubmitResponse {
+                status: "duplicate".to_string(),
+                hash: hash.to_hex(),
+                height: None,
+                fork_height: None,
+                orphans_connected: 0,
+                missing_parent: None,
+                missing_transactions: Vec::new(),
+            };
+            return Ok((StatusCode::OK, success(response)));
+        }
+        reconstruct_block(&compact, blockchain.get_pending_transactions()).map_err(invalid)?
+    };
+
+    let (code, response) = match reconstruction {
+        Reconstruction::Complete(block) => process_submitted_block(&state, *block).await?,
+        Reconstruction::Missing(missing_transactions) => (
+            StatusCode::OK,
+            BlockSubmitResponse {
+                status: "incomplete".to_string(),
+                hash: hash.to_hex(),
+                height: None,
+                fork_height: None,
+                orphans_connected: 0,
+                missing_parent: None,
+                missing_transactions,
+            },
+        ),
+    };
+    Ok((code, success(response)))
+}
+
+/// Validate and connect a submitted block, or keep it as a side branch or orphan
+async fn process_submitted_block(
+    state: &AppState,
+    block: Block,
+) -> std::result::Result<(StatusCode, BlockSubmitResponse), ApiError> {
+    let hash = block.hash();
+    let index = block.index;
+    // Connecting validates and persists the block; keep that off the async workers
+    let mut blockchain = state.blockchain.clone().write_owned().await;
+    let status = state.storage.spawn(move || blockchain.process_block(block)).await.map_err(|e| {
+        ApiError::new(ErrorCode::BlockRejected, e.to_string())
+            .with_details(json!({ "hash": hash.to_hex(), "index": index }))
+    })?;
+
+    let mut response = BlockSubmitResponse {
+        status: String::new(),
+        hash: hash.to_hex(),
+        height: None,
+        fork_height: None,
+        orphans_connected: 0,
+        missing_parent: None,
+        missing_transactions: Vec::new(),
+    };
+    let code = match status {
+        BlockStatus::Connected { height, orphans_connected } => {
+            response.status = "connected".to_string();
+            response.height = Some(height);
+            response.orphans_connected = orphans_connected;
+            StatusCode::OK
+        }
+        BlockStatus::Reorganized { fork_height, height, orphans_connected, .. } => {
+            response.status = "reorganized".to_string();
+            response.height = Some(height);
+            response.fork_height = Some(fork_height);
+            response.orphans_connected = orphans_connected;
+            StatusCode::OK
+        }
+        BlockStatus::SideBranch { height } => {
+            response.status = "side_branch".to_string();
+            response.height = Some(height);
+            StatusCode::ACCEPTED
+        }
+        BlockStatus::Orphaned { missing_parent } => {
+            response.status = "orphaned".to_string();
+            response.missing_parent = Some(missing_parent.to_hex());
+            StatusCode::ACCEPTED
+        }
+        BlockStatus::AlreadyKnown => {
+            response.status = "duplicate".to_string();
+            StatusCode::OK
+        }
+    };
+    Ok((code, response))
+}
+
+/// List blocks waiting for their parent, and the parents they are waiting for
+#[utoipa::path(
+    get, path = "/api/blocks/orphans", tag = "blocks",
+    responses((status = 200, body = ApiResponse<OrphanPoolResponse>))
+)]
+pub async fn get_orphan_blocks(State(state): State<AppState>) -> Json<ApiResponse<OrphanPoolResponse>> {
+    let mut blockchain = state.blockchain.write().await;
+    blockchain.expire_orphans();
+
+    let orphans = blockchain
+        .orphan_blocks()
+        .into_iter()
+        .map(|orphan| OrphanBlockResponse {
+            hash: orphan.block.hash().to_hex(),
+            index: orphan.block.index,
+            previous_hash: orphan.block.header.previous_hash.to_hex(),
+            received_at: orphan.received_at,
+        })
+        .collect();
+    let missing_parents = blockchain.missing_parents().iter().map(Hash256::to_hex).collect();
+    success(OrphanPoolResponse { orphans, missing_parents })
 }
 
 /// Get block by height
+#[utoipa::path(
+    get, path = "/api/blocks/height/{height}", tag = "blocks",
+    params(("height" = u64, Path, description = "Block height"), BlockParams),
+    responses((status = 200, body = ApiResponse<BlockView>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
 pub async fn get_block_by_height(
     State(state): State<AppState>,
     Path(height): Path<u64>,
-) -> std::result::Result<Json<Block>, ApiError> {
+    Query(params): Query<BlockParams>,
+) -> ApiResult<BlockView> {
+    let verbosity = block_verbosity(&params)?;
     let blockchain = state.blockchain.read().await;
     
-    blockchain
+    let block = blockchain
         .get_block_by_index(height)
-        .cloned()
-        .map(Json)
-        .ok_or_else(|| ApiError::new("NOT_FOUND", format!("Block at height {} not found", height)))
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, format!("Block at height {} not found", height)))?;
+    Ok(success(block_view(block, &blockchain, verbosity)?))
 }
 
 /// Get block by hash
+#[utoipa::path(
+    get, path = "/api/blocks/{hash}", tag = "blocks",
+    params(("hash" = String, Path, description = "Hex block hash"), BlockParams),
+    responses((status = 200, body = ApiResponse<BlockView>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
 pub async fn get_block_by_hash(
     State(state): State<AppState>,
     Path(hash): Path<String>,
-) -> std::result::Result<Json<Block>, ApiError> {
+    Query(params): Query<BlockParams>,
+) -> ApiResult<BlockView> {
     let hash = Hash256::from_hex(&hash)
-        .map_err(|_| ApiError::new("INVALID_HASH", "Invalid block hash format"))?;
+        .map_err(|_| ApiError::new(ErrorCode::InvalidHash, "Invalid block hash format"))?;
+    let verbosity = block_verbosity(&params)?;
     
     let blockchain = state.blockchain.read().await;
     
-    blockchain
+    let block = blockchain
         .get_block_by_hash(&hash)
-        .cloned()
-        .map(Json)
-        .ok_or_else(|| ApiError::new("NOT_FOUND", "Block not found"))
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Block not found"))?;
+    Ok(success(block_view(block, &blockchain, verbosity)?))
+}
+
+/// The verbosity asked for with `?verbosity=`
+fn block_verbosity(params: &BlockParams) -> std::result::Result<BlockVerbosity, ApiError> {
+    params
+        .verbosity()
+        .ok_or_else(|| ApiError::new(ErrorCode::InvalidRequest, "verbosity must be 0, 1 or 2"))
 }
 
 /// Get transactions in a block
+#[utoipa::path(
+    get, path = "/api/blocks/{hash}/transactions", tag = "blocks",
+    params(("hash" = String, Path, description = "Block height or hex hash")),
+    responses((status = 200, body = ApiResponse<Vec<Transaction>>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
 pub async fn get_block_transactions(
     State(state): State<AppState>,
     Path(block_id): Path<String>,
-) -> std::result::Result<Json<Vec<Transaction>>, ApiError> {
+) -> ApiResult<Vec<Transaction>> {
     let blockchain = state.blockchain.read().await;
+    let block = find_block(&blockchain, &block_id)?;
     
+    Ok(success(block.transactions().to_vec()))
+}
+
+/// The main-chain block with height or hex hash `block_id`
+fn find_block<'a>(blockchain: &'a Blockchain, block_id: &str) -> std::result::Result<&'a Block, ApiError> {
     // Try to parse as height first, then as hash
     let block = if let Ok(height) = block_id.parse::<u64>() {
         blockchain.get_block_by_index(height)
-    } else if let Ok(hash) = Hash256::from_hex(&block_id) {
+    } else if let Ok(hash) = Hash256::from_hex(block_id) {
         blockchain.get_block_by_hash(&hash)
     } else {
-        return Err(ApiError::new("INVALID_BLOCK_ID", "Invalid block ID format"));
+        return Err(ApiError::new(ErrorCode::InvalidBlockId, "Invalid block ID format"));
     };
     
-    let block = block.ok_or_else(|| ApiError::new("NOT_FOUND", "Block not found"))?;
-    
-    Ok(Json(block.transactions))
+    block.ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Block not found"))
 }
 
-/// Create a new transaction
-pub async fn create_transaction(
+/// Compact filter of the addresses a block pays and spends from, for light wallets
+/// to test their own addresses against with `BlockFilter::matches_any`
+#[utoipa::path(
+    get, path = "/api/blocks/{hash}/filter", tag = "blocks",
+    params(("hash" = String, Path, description = "Block height or hex hash")),
+    responses((status = 200, body = ApiResponse<BlockFilterResponse>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
+pub async fn get_block_filter(
     State(state): State<AppState>,
-    Json(request): Json<CreateTransactionRequest>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
-    // TODO: Implement transaction creation from request
-    // This would involve:
-    // 1. Validating inputs and outputs
-    // 2. Creating the transaction
-    // 3. Adding to transaction pool
-    // 4. Broadcasting to network
-    
-    Err(ApiError::new("NOT_IMPLEMENTED", "Transaction creation not yet implemented"))
+    Path(block_id): Path<String>,
+) -> ApiResult<BlockFilterResponse> {
+    let blockchain = state.blockchain.read().await;
+    let block = find_block(&blockchain, &block_id)?;
+
+    // Blocks stored before filters existed (and genesis) are filtered on demand
+    let filter = match state.storage.load_block_filter(block.hash()).await {
+        Ok(filter) => filter,
+        Err(_) => blockchain.block_filter(block),
+    };
+
+    Ok(success(BlockFilterResponse {
+        block_hash: filter.block_hash,
+        height: block.index,
+        n: filter.n,
+        p: crate::core::FILTER_P,
+        m: crate::core::FILTER_M,
+        filter: hex::encode(filter.data),
+    }))
 }
 
 /// Get pending transactions
+#[utoipa::path(
+    get, path = "/api/transactions/pending", tag = "transactions", params(PaginationParams),
+    responses((status = 200, body = ApiResponse<PaginatedResponse<PendingTransactionResponse>>), (status = 400, description = "Invalid cursor", body = ErrorResponse))
+)]
 pub async fn get_pending_transactions(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> std::result::Result<Json<PaginatedResponse<Transaction>>, ApiError> {
+) -> ApiResult<PaginatedResponse<PendingTransactionResponse>> {
     let blockchain = state.blockchain.read().await;
-    let pending_txs = blockchain.get_pending_transactions();
-    
-    let page = params.page.unwrap_or(0);
-    let limit = params.limit.unwrap_or(20).min(100);
-    let total = pending_txs.len() as u64;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
     
-    let start = (page * limit) as usize;
-    let end = ((page + 1) * limit).min(total) as usize;
+    // The pool is unordered; list it by timestamp so pages and cursors stay put
+    let mut pending: Vec<(u64, PendingTransaction)> = blockchain
+        .get_pending_details()
+        .into_iter()
+        .map(|p| (p.transaction.timestamp.timestamp_millis().max(0) as u64, p))
+        .collect();
+    pending.sort_by(|(a_time, a), (b_time, b)| (a_time, &a.transaction.id).cmp(&(b_time, &b.transaction.id)));
+    let total = pending.len() as u64;
+    let window = page_window(&params, limit, pending.len(), |i| (pending[i].0, pending[i].1.transaction.id.as_str()))?;
     
-    let transactions = if start < pending_txs.len() {
-        pending_txs[start..end].to_vec()
-    } else {
-        vec![]
-    };
+    let mut transactions: Vec<PendingTransactionResponse> = pending[window.range.clone()]
+        .iter()
+        .map(|(_, p)| pending_transaction_response(p))
+        .collect();
+    if window.newest_first {
+        transactions.reverse();
+    }
     
-    Ok(Json(super::paginate(transactions, page, limit, total)))
+    Ok(success(super::paginate_window(transactions, &params, limit, total, &window)))
 }
 
 /// Get transaction by hash
+#[utoipa::path(
+    get, path = "/api/transactions/{hash}", tag = "transactions",
+    params(("hash" = String, Path, description = "Hex transaction hash")),
+    responses((status = 200, body = ApiResponse<Transaction>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
 pub async fn get_transaction_by_hash(
     State(state): State<AppState>,
     Path(hash): Path<String>,
-) -> std::result::Result<Json<Transaction>, ApiError> {
-    let hash = Hash256::from_hex(&hash)
-        .map_err(|_| ApiError::new("INVALID_HASH", "Invalid transaction hash format"))?;
-    
+) -> ApiResult<Transaction> {
     let blockchain = state.blockchain.read().await;
+    find_transaction(&blockchain, &hash).map(|transaction| success(transaction.clone()))
+}
+
+/// Confirmed transactions carrying a memo, newest first
+#[utoipa::path(
+    get, path = "/api/transactions/search", tag = "transactions", params(MemoSearchParams),
+    responses(
+        (status = 200, body = ApiResponse<PaginatedResponse<MemoTransactionResponse>>),
+        (status = 400, description = "Empty or oversized memo", body = ErrorResponse),
+        (status = 501, description = "The node keeps no SQLite index", body = ErrorResponse),
+    )
+)]
+pub async fn search_transactions(
+    State(state): State<AppState>,
+    Query(params): Query<MemoSearchParams>,
+) -> ApiResult<PaginatedResponse<MemoTransactionResponse>> {
+    if params.memo.is_empty() {
+        return Err(ApiError::new(ErrorCode::InvalidRequest, "`memo` is empty"));
+    }
+    if params.memo.len() > crate::utils::constants::MAX_MEMO_SIZE {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("`memo` is longer than {} bytes", crate::utils::constants::MAX_MEMO_SIZE),
+        ));
+    }
+
+    let page = params.page.unwrap_or(0);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let (transactions, total) = memo_search(&state, &params, page, limit).await?;
+    Ok(success(super::paginate(transactions, page, limit, total)))
+}
+
+/// One page of memo search results from the SQLite index, and the total
+#[cfg(feature = "sqlite-index")]
+async fn memo_search(
+    state: &AppState,
+    params: &MemoSearchParams,
+    page: u64,
+    limit: u64,
+) -> Result<(Vec<MemoTransactionResponse>, u64), ApiError> {
+    let storage = state.storage.blocking().clone();
+    if storage.sqlite_index().is_none() {
+        return Err(ApiError::new(ErrorCode::NotImplemented, "Memo search needs `storage.sqlite_index` set"));
+    }
+    let query = crate::storage::MemoQuery {
+        memo: params.memo.clone(),
+        prefix: params.prefix.unwrap_or(false),
+        limit,
+        offset: page.saturating_mul(limit),
+    };
+    let found = state.storage
+        .spawn(move || storage.sqlite_index().expect("index checked above").search_memos(&query))
+        .await
+        .map_err(ApiError::from)?;
+    let transactions = found.transactions
+        .into_iter()
+        .map(|tx| MemoTransactionResponse {
+            tx_hash: tx.tx_hash,
+            block_height: tx.block_height,
+            block_hash: tx.block_hash,
+            timestamp: tx.timestamp,
+            memo: tx.memo,
+        })
+        .collect();
+    Ok((transactions, found.total))
+}
+
+#[cfg(not(feature = "sqlite-index"))]
+async fn memo_search(
+    _state: &AppState,
+    _params: &MemoSearchParams,
+    _page: u64,
+    _limit: u64,
+) -> Result<(Vec<MemoTransactionResponse>, u64), ApiError> {
+    Err(ApiError::new(
+        ErrorCode::NotImplemented,
+        "Memo search needs ledgerdb built with the `sqlite-index` feature",
+    ))
+}
+
+/// The confirmed transaction with hex hash `hash`
+fn find_transaction<'a>(blockchain: &'a Blockchain, hash: &str) -> std::result::Result<&'a Transaction, ApiError> {
+    let hash = Hash256::from_hex(hash)
+        .map_err(|_| ApiError::new(ErrorCode::InvalidHash, "Invalid transaction hash format"))?;
     
     blockchain
         .get_transaction(&hash)
-        .map(Json)
-        .ok_or_else(|| ApiError::new("NOT_FOUND", "Transaction not found"))
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Transaction not found"))
 }
 
 /// Get Merkle proof for a transaction
+#[utoipa::path(
+    get, path = "/api/transactions/{hash}/proof", tag = "transactions",
+    params(("hash" = String, Path, description = "Hex transaction hash")),
+    responses((status = 200, body = ApiResponse<TransactionProofResponse>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
 pub async fn get_transaction_merkle_proof(
     State(state): State<AppState>,
     Path(hash): Path<String>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
+) -> ApiResult<TransactionProofResponse> {
     let hash = Hash256::from_hex(&hash)
-        .map_err(|_| ApiError::new("INVALID_HASH", "Invalid transaction hash format"))?;
+        .map_err(|_| ApiError::new(ErrorCode::InvalidHash, "Invalid transaction hash format"))?;
     
     let blockchain = state.blockchain.read().await;
     
     // Find the block containing this transaction
     let (block, tx_index) = blockchain
         .find_transaction_in_block(&hash)
-        .ok_or_else(|| ApiError::new("NOT_FOUND", "Transaction not found in any block"))?;
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Transaction not found in any block"))?;
     
     // Generate Merkle proof
     let proof = block.generate_merkle_proof(tx_index)
-        .map_err(|e| ApiError::new("PROOF_GENERATION_FAILED", format!("Failed to generate proof: {}", e)))?;
+        .map_err(|e| ApiError::new(ErrorCode::ProofGenerationFailed, format!("Failed to generate proof: {}", e)))?;
     
-    Ok(Json(json!({
-        "transaction_hash": hash,
-        "block_hash": block.hash(),
-        "block_height": block.index,
-        "transaction_index": tx_index,
-        "merkle_proof": proof,
-        "merkle_root": block.header.merkle_root
-    })))
+    Ok(success(TransactionProofResponse {
+        transaction_hash: hash,
+        block_hash: block.hash(),
+        block_height: block.index,
+        transaction_index: tx_index,
+        merkle_proof: proof,
+        merkle_root: block.header.merkle_root.clone(),
+    }))
 }
 
-/// Validate a transaction
-pub async fn validate_transaction(
+/// Check a Merkle proof against a root without looking anything up.
+///
+/// Clients with the library can run `crypto::verify_merkle_proof` themselves; this is
+/// for those that cannot.
+#[utoipa::path(
+    post, path = "/api/proofs/verify", tag = "transactions", request_body = VerifyProofRequest,
+    responses((status = 200, body = ApiResponse<VerifyProofResponse>), (status = 400, description = "Invalid request", body = ErrorResponse))
+)]
+pub async fn verify_proof(Json(request): Json<VerifyProofRequest>) -> ApiResult<VerifyProofResponse> {
+    Ok(success(VerifyProofResponse {
+        valid: crate::crypto::verify_merkle_proof(&request.merkle_root, &request.leaf_hash, &request.proof),
+    }))
+}
+
+/// Look up many blocks, transactions and address balances in one request.
+///
+/// Each query gets its own result, in order; a query that fails has an `error`
+/// with the code its single-item endpoint would have returned, and does not fail
+/// the batch. Batches over `max_batch_size` queries are refused.
+#[utoipa::path(
+    post, path = "/api/batch", tag = "blocks", request_body = BatchRequest,
+    responses((status = 200, body = ApiResponse<BatchResponse>), (status = 400, description = "Too many queries or bad verbosity", body = ErrorResponse))
+)]
+pub async fn batch_query(
     State(state): State<AppState>,
-    Json(transaction): Json<Transaction>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
+    Json(request): Json<BatchRequest>,
+) -> ApiResult<BatchResponse> {
+    let max_batch_size = state.config.max_batch_size;
+    if request.queries.len() > max_batch_size {
+        return Err(ApiError::new(
+            ErrorCode::ValidationError,
+            format!("A batch may hold at most {} queries", max_batch_size),
+        )
+        .with_details(json!({ "queries": request.queries.len(), "max_batch_size": max_batch_size })));
+    }
+    let verbosity = block_verbosity(&BlockParams { verbosity: request.verbosity })?;
+
     let blockchain = state.blockchain.read().await;
-    
-    // Create UTXO map for validation
-    let utxo_map: std::collections::HashMap<String, crate::core::TransactionOutput> = blockchain
-        .get_all_utxos()
+    let results = request
+        .queries
         .iter()
-        .map(|utxo| {
-            let key = format!("{}:{}", utxo.tx_hash, utxo.output_index);
-            (key, utxo.output.clone())
+        .map(|query| match batch_item(&blockchain, query, verbosity) {
+            Ok(item) => BatchResult::found(item),
+            Err(error) => BatchResult::failed(error.into()),
         })
         .collect();
-    
-    match transaction.validate(&utxo_map) {
-        Ok(_) => Ok(Json(json!({
-            "valid": true,
-            "message": "Transaction is valid"
-        }))),
-        Err(e) => Ok(Json(json!({
-            "valid": false,
-            "error": e.to_string()
-        })))
-    }
+    Ok(success(BatchResponse { results }))
 }
 
-/// Start mining
-pub async fn start_mining(
+/// Answer one query of a batch
+fn batch_item(
+    blockchain: &Blockchain,
+    query: &BatchQuery,
+    verbosity: BlockVerbosity,
+) -> std::result::Result<BatchItem, ApiError> {
+    Ok(match query {
+        BatchQuery::Block { id } => {
+            BatchItem::Block(block_view(find_block(blockchain, id)?, blockchain, verbosity)?)
+        }
+        BatchQuery::Transaction { hash } => BatchItem::Transaction(find_transaction(blockchain, hash)?.clone()),
+        BatchQuery::Address { address } => {
+            let parsed = Address::from_string(address)
+                .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
+            let utxos = blockchain.get_utxos_for_address(&parsed);
+            BatchItem::Address(AddressBalance {
+                address: address.clone(),
+                balance: utxos.iter().map(|utxo| utxo.output.amount).sum(),
+                utxo_count: utxos.len(),
+            })
+        }
+    })
+}
+
+/// Check a transaction against the chain and pool without submitting it
+#[utoipa::path(
+    post, path = "/api/transactions/validate", tag = "transactions", request_body = Transaction,
+    responses((status = 200, body = ApiResponse<TransactionValidation>))
+)]
+pub async fn validate_transaction(
     State(state): State<AppState>,
-    Json(request): Json<StartMiningRequest>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
-    // TODO: Implement mining start
-    Err(ApiError::new("NOT_IMPLEMENTED", "Mining start not yet implemented"))
+    Json(transaction): Json<Transaction>,
+) -> ApiResult<TransactionValidation> {
+    let blockchain = state.blockchain.read().await;
+    Ok(success(blockchain.check_transaction(&transaction)))
 }
 
-/// Stop mining
-pub async fn stop_mining(
+/// Wrap an unsigned transaction for signing by its keys, recording the outputs it spends
+#[utoipa::path(
+    post, path = "/api/psbt", tag = "transactions", request_body = CreatePsbtRequest,
+    responses((status = 200, body = ApiResponse<PsbtResponse>), (status = 400, description = "An input is unknown or names the wrong key", body = ErrorResponse))
+)]
+pub async fn create_psbt(
     State(state): State<AppState>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
-    // TODO: Implement mining stop
-    Err(ApiError::new("NOT_IMPLEMENTED", "Mining stop not yet implemented"))
+    Json(request): Json<CreatePsbtRequest>,
+) -> ApiResult<PsbtResponse> {
+    let blockchain = state.blockchain.read().await;
+    let spent = request.transaction.inputs
+        .iter()
+        .map(|input| {
+            let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+            blockchain.get_utxo(&utxo_id)
+                .map(|utxo| utxo.output.clone())
+                .ok_or_else(|| ApiError::new(ErrorCode::ValidationError, format!("UTXO not found: {}", utxo_id)))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let psbt = PartiallySignedTransaction::new(request.transaction, spent)?;
+    Ok(success(psbt_response(&psbt)?))
+}
+
+/// Merge copies of one partially signed transaction signed by different keys
+#[utoipa::path(
+    post, path = "/api/psbt/combine", tag = "transactions", request_body = PsbtRequest,
+    responses((status = 200, body = ApiResponse<PsbtResponse>), (status = 400, description = "Malformed or mismatched copies", body = ErrorResponse))
+)]
+pub async fn combine_psbts(Json(request): Json<PsbtRequest>) -> ApiResult<PsbtResponse> {
+    let psbt = combine_all(&request.psbts)?;
+    Ok(success(psbt_response(&psbt)?))
 }
 
-/// Get mining status
+/// Combine the copies, build the signed transaction and add it to the pool
+#[utoipa::path(
+    post, path = "/api/psbt/finalize", tag = "transactions", request_body = PsbtRequest,
+    responses((status = 200, body = ApiResponse<PsbtFinalizeResponse>), (status = 400, description = "Unsigned inputs or rejected by the pool", body = ErrorResponse))
+)]
+pub async fn finalize_psbt(
+    State(state): State<AppState>,
+    Json(request): Json<PsbtRequest>,
+) -> ApiResult<PsbtFinalizeResponse> {
+    let transaction = combine_all(&request.psbts)?.finalize()?;
+    let hash = transaction.hash().to_hex();
+    state.blockchain.write().await.add_transaction_to_pool(transaction)?;
+    Ok(success(PsbtFinalizeResponse { hash }))
+}
+
+fn combine_all(encoded: &[String]) -> std::result::Result<PartiallySignedTransaction, ApiError> {
+    let (first, rest) = encoded.split_first()
+        .ok_or_else(|| ApiError::new(ErrorCode::InvalidRequest, "psbts is empty"))?;
+    let mut psbt = PartiallySignedTransaction::from_hex(first)?;
+    for other in rest {
+        psbt.combine(&PartiallySignedTransaction::from_hex(other)?)?;
+    }
+    Ok(psbt)
+}
+
+/// Whether the node's miner is searching, and its most recent event
+#[utoipa::path(
+    get, path = "/api/mining/status", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<MiningStatusResponse>))
+)]
 pub async fn get_mining_status(
     State(state): State<AppState>,
-) -> std::result::Result<Json<MiningStatusResponse>, ApiError> {
-    let miner = state.miner.read().await;
+) -> ApiResult<MiningStatusResponse> {
     let blockchain = state.blockchain.read().await;
+    let last_event = state.miner_events.latest();
     
     let response = MiningStatusResponse {
-        is_mining: miner.is_some(),
+        is_mining: last_event.as_ref().is_some_and(|event| !event.is_final()),
         current_block_height: blockchain.height(),
         difficulty: blockchain.get_current_difficulty(),
-        hash_rate: 0.0,
+        hash_rate: last_event.as_ref().map_or(0.0, |event| event.hash_rate()),
+        last_event: last_event.map(MiningProgressData::from),
     };
     
-    Ok(Json(response))
+    Ok(success(response))
 }
 
-/// Get mining difficulty
+/// Difficulty of the next block, its target and the work and time a block takes
+#[utoipa::path(
+    get, path = "/api/mining/difficulty", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<DifficultyResponse>))
+)]
 pub async fn get_mining_difficulty(
     State(state): State<AppState>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
+) -> ApiResult<DifficultyResponse> {
     let blockchain = state.blockchain.read().await;
     let difficulty = blockchain.get_current_difficulty();
+    let network_hash_rate = calculate_network_hash_rate(&blockchain).await;
     
-    Ok(Json(json!({
-        "difficulty": difficulty,
-        "target": format!("{:064x}", u64::MAX >> difficulty.min(63)),
-        "next_adjustment": blockchain.blocks_until_difficulty_adjustment(),
-    })))
+    Ok(success(DifficultyResponse {
+        difficulty,
+        target: crate::crypto::calculate_target(difficulty).to_hex(),
+        expected_hashes: crate::crypto::expected_hashes(difficulty),
+        network_hash_rate,
+        estimated_block_seconds: crate::crypto::estimated_block_seconds(difficulty, network_hash_rate),
+        target_block_time: blockchain.config.target_block_time,
+        next_adjustment: blockchain.blocks_until_difficulty_adjustment(),
+    }))
 }
 
 /// Get address balance
 pub async fn get_address_balance(
     State(state): State<AppState>,
     Path(address): Path<String>,
-) -> std::result::Result<Json<AddressBalanceResponse>, ApiError> {
+) -> ApiResult<AddressBalanceResponse> {
     let address = Address::from_string(&address)
-        .map_err(|_| ApiError::new("INVALID_ADDRESS", "Invalid address format"))?;
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
     
     let blockchain = state.blockchain.read().await;
     let utxos = blockchain.get_utxos_for_address(&address);
@@ -372,16 +1321,88 @@ pub async fn get_address_balance(
 
     };
     
-    Ok(Json(response))
+    Ok(success(response))
+}
+
+/// Watch an address; its payments, spends and their confirmations are pushed to
+/// the `watched_addresses` WebSocket topic and to webhooks
+#[utoipa::path(
+    post, path = "/api/watch", tag = "watch", request_body = WatchRequest,
+    responses((status = 201, body = ApiResponse<WatchedAddress>), (status = 400, description = "Invalid request", body = ErrorResponse))
+)]
+pub async fn add_watched_address(
+    State(state): State<AppState>,
+    Json(request): Json<WatchRequest>,
+) -> std::result::Result<(StatusCode, Json<ApiResponse<WatchedAddress>>), ApiError> {
+    let address = Address::from_string(&request.address)
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
+    let watch = state.watch.clone();
+    let entry = state.storage.spawn(move || watch.add(&address, request.label)).await?;
+    Ok((StatusCode::CREATED, success(entry)))
+}
+
+/// List watched addresses
+#[utoipa::path(
+    get, path = "/api/watch", tag = "watch",
+    responses((status = 200, body = ApiResponse<Vec<WatchedAddress>>))
+)]
+pub async fn list_watched_addresses(State(state): State<AppState>) -> Json<ApiResponse<Vec<WatchedAddress>>> {
+    success(state.watch.list())
+}
+
+/// Get a watched address and its balance
+#[utoipa::path(
+    get, path = "/api/watch/{address}", tag = "watch",
+    params(("address" = String, Path, description = "Hex address")),
+    responses((status = 200, body = ApiResponse<WatchedAddressResponse>), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
+pub async fn get_watched_address(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> ApiResult<WatchedAddressResponse> {
+    let address = Address::from_string(&address)
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
+    let entry = state
+        .watch
+        .get(&address)
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Address is not watched"))?;
+
+    let blockchain = state.blockchain.read().await;
+    let utxos = blockchain.get_utxos_for_address(&address);
+    Ok(success(WatchedAddressResponse {
+        entry,
+        balance: utxos.iter().map(|utxo| utxo.output.amount).sum(),
+        utxo_count: utxos.len(),
+    }))
+}
+
+/// Stop watching an address
+#[utoipa::path(
+    delete, path = "/api/watch/{address}", tag = "watch",
+    params(("address" = String, Path, description = "Hex address")),
+    responses((status = 204, description = "No longer watched"), (status = 400, description = "Invalid request", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse))
+)]
+pub async fn remove_watched_address(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> std::result::Result<StatusCode, ApiError> {
+    let address = Address::from_string(&address)
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
+    let watch = state.watch.clone();
+    if state.storage.spawn(move || watch.remove(&address)).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(ErrorCode::NotFound, "Address is not watched"))
+    }
 }
 
 /// Get UTXOs for an address
 pub async fn get_address_utxos(
     State(state): State<AppState>,
     Path(address): Path<String>,
-) -> std::result::Result<Json<Vec<UtxoResponse>>, ApiError> {
+) -> ApiResult<Vec<UtxoResponse>> {
     let address = Address::from_string(&address)
-        .map_err(|_| ApiError::new("INVALID_ADDRESS", "Invalid address format"))?;
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
     
     let blockchain = state.blockchain.read().await;
     let utxos = blockchain.get_utxos_for_address(&address);
@@ -392,86 +1413,305 @@ pub async fn get_address_utxos(
             utxo_id: format!("{}:{}", utxo.tx_hash, utxo.output_index),
             amount: utxo.output.amount,
             address: utxo.output.recipient.clone(),
-            recipient: utxo.output.recipient,
+            recipient: utxo.output.recipient.clone(),
             block_height: utxo.block_height,
-            tx_hash: utxo.tx_hash,
+            tx_hash: utxo.tx_hash.clone(),
             output_index: utxo.output_index,
             is_spent: false,
 
         })
         .collect();
     
-    Ok(Json(utxo_responses))
+    Ok(success(utxo_responses))
 }
 
-/// Get transactions for an address
+/// Confirmed transactions paying or spending from an address, newest first,
+/// optionally between two block timestamps
+#[utoipa::path(
+    get, path = "/api/addresses/{address}/transactions", tag = "addresses",
+    params(("address" = String, Path, description = "Address"), AddressHistoryParams),
+    responses(
+        (status = 200, body = ApiResponse<PaginatedResponse<AddressTransactionResponse>>),
+        (status = 400, description = "Invalid address or time range", body = ErrorResponse),
+        (status = 501, description = "The node keeps no SQLite index", body = ErrorResponse),
+    )
+)]
 pub async fn get_address_transactions(
     State(state): State<AppState>,
     Path(address): Path<String>,
-    Query(params): Query<PaginationParams>,
-) -> std::result::Result<Json<PaginatedResponse<Transaction>>, ApiError> {
+    Query(params): Query<AddressHistoryParams>,
+) -> ApiResult<PaginatedResponse<AddressTransactionResponse>> {
     let address = Address::from_string(&address)
-        .map_err(|_| ApiError::new("INVALID_ADDRESS", "Invalid address format"))?;
-    
-    // TODO: Implement address transaction history
-    // This would require indexing transactions by address
-    
-    Err(ApiError::new("NOT_IMPLEMENTED", "Address transaction history not yet implemented"))
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return Err(ApiError::new(ErrorCode::InvalidRequest, "`from` is after `to`"));
+        }
+    }
+
+    let page = params.page.unwrap_or(0);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let (transactions, total) = address_history(&state, &address, &params, page, limit).await?;
+    Ok(success(super::paginate(transactions, page, limit, total)))
+}
+
+/// One page of `address`'s history from the SQLite index, and the total
+#[cfg(feature = "sqlite-index")]
+async fn address_history(
+    state: &AppState,
+    address: &Address,
+    params: &AddressHistoryParams,
+    page: u64,
+    limit: u64,
+) -> Result<(Vec<AddressTransactionResponse>, u64), ApiError> {
+    let storage = state.storage.blocking().clone();
+    if storage.sqlite_index().is_none() {
+        return Err(ApiError::new(ErrorCode::NotImplemented, "Address history needs `storage.sqlite_index` set"));
+    }
+    let query = crate::storage::AddressHistoryQuery {
+        from: params.from,
+        to: params.to,
+        limit,
+        offset: page.saturating_mul(limit),
+    };
+    let address = address.to_hex();
+    let history = state.storage
+        .spawn(move || storage.sqlite_index().expect("index checked above").address_history(&address, &query))
+        .await
+        .map_err(ApiError::from)?;
+    let transactions = history.transactions
+        .into_iter()
+        .map(|tx| AddressTransactionResponse {
+            tx_hash: tx.tx_hash,
+            block_height: tx.block_height,
+            block_hash: tx.block_hash,
+            timestamp: tx.timestamp,
+            received: tx.received,
+            sent: tx.sent,
+            memo: tx.memo,
+        })
+        .collect();
+    Ok((transactions, history.total))
+}
+
+#[cfg(not(feature = "sqlite-index"))]
+async fn address_history(
+    _state: &AppState,
+    _address: &Address,
+    _params: &AddressHistoryParams,
+    _page: u64,
+    _limit: u64,
+) -> Result<(Vec<AddressTransactionResponse>, u64), ApiError> {
+    Err(ApiError::new(
+        ErrorCode::NotImplemented,
+        "Address history needs ledgerdb built with the `sqlite-index` feature",
+    ))
+}
+
+/// Balance of an address over time, oldest first, rebuilt from its history in the
+/// SQLite index
+#[utoipa::path(
+    get, path = "/api/addresses/{address}/balance/history", tag = "addresses",
+    params(("address" = String, Path, description = "Address"), BalanceHistoryParams),
+    responses(
+        (status = 200, body = ApiResponse<BalanceHistoryResponse>),
+        (status = 400, description = "Invalid address or granularity", body = ErrorResponse),
+        (status = 501, description = "The node keeps no SQLite index", body = ErrorResponse),
+    )
+)]
+pub async fn get_balance_history(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<BalanceHistoryParams>,
+) -> ApiResult<BalanceHistoryResponse> {
+    let address = Address::from_string(&address)
+        .map_err(|_| ApiError::new(ErrorCode::InvalidAddress, "Invalid address format"))?;
+    let granularity = params.granularity.unwrap_or_default();
+    let points = balance_history(&state, &address, granularity).await?;
+    Ok(success(BalanceHistoryResponse { address: address.to_string(), granularity, points }))
+}
+
+/// `address`'s balance history up to the tip, extending the cached one when no
+/// reorg replaced the block it ends at
+#[cfg(feature = "sqlite-index")]
+async fn balance_history(
+    state: &AppState,
+    address: &Address,
+    granularity: BalanceGranularity,
+) -> Result<Vec<BalancePoint>, ApiError> {
+    use super::BalanceChange;
+
+    let storage = state.storage.blocking().clone();
+    if storage.sqlite_index().is_none() {
+        return Err(ApiError::new(ErrorCode::NotImplemented, "Balance history needs `storage.sqlite_index` set"));
+    }
+    let key = address.to_hex();
+    let (mut history, tip) = {
+        let blockchain = state.blockchain.read().await;
+        let history = state.balance_histories
+            .get(&key, granularity, |height, hash| {
+                blockchain.get_block_by_index(height).is_some_and(|block| block.hash().to_hex() == hash)
+            })
+            .unwrap_or_default();
+        let tip = blockchain.get_latest_block().map(|block| (block.index, block.hash().to_hex()));
+        (history, tip)
+    };
+    let Some(tip) = tip else {
+        return Ok(Vec::new());
+    };
+    if history.tip.as_ref() == Some(&tip) {
+        return Ok(history.points);
+    }
+
+    let above = history.tip.as_ref().map(|(height, _)| *height);
+    let activity = {
+        let key = key.clone();
+        state.storage
+            .spawn(move || storage.sqlite_index().expect("index checked above").address_activity_since(&key, above))
+            .await
+            .map_err(ApiError::from)?
+    };
+    // Blocks mined since the tip was read wait for the next request
+    let tip_height = tip.0;
+    let changes = activity
+        .into_iter()
+        .filter(move |tx| tx.block_height <= tip_height)
+        .map(|tx| BalanceChange {
+            block_height: tx.block_height,
+            block_hash: tx.block_hash,
+            timestamp: tx.timestamp,
+            received: tx.received,
+            sent: tx.sent,
+        });
+    history.extend(granularity, changes, tip);
+    let points = history.points.clone();
+    state.balance_histories.insert(&key, granularity, history);
+    Ok(points)
+}
+
+#[cfg(not(feature = "sqlite-index"))]
+async fn balance_history(
+    _state: &AppState,
+    _address: &Address,
+    _granularity: BalanceGranularity,
+) -> Result<Vec<BalancePoint>, ApiError> {
+    Err(ApiError::new(
+        ErrorCode::NotImplemented,
+        "Balance history needs ledgerdb built with the `sqlite-index` feature",
+    ))
+}
+
+/// The addresses holding the most, from the UTXO set at the tip
+#[utoipa::path(
+    get, path = "/api/stats/richlist", tag = "blockchain", params(RichListParams),
+    responses((status = 200, body = ApiResponse<RichListResponse>))
+)]
+pub async fn get_rich_list(
+    State(state): State<AppState>,
+    Query(params): Query<RichListParams>,
+) -> ApiResult<RichListResponse> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    let stats = holder_stats(&state).await;
+    Ok(success(stats.rich_list(limit)))
+}
+
+/// How the supply at the tip is spread over power-of-ten balance buckets
+#[utoipa::path(
+    get, path = "/api/stats/distribution", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<SupplyDistributionResponse>))
+)]
+pub async fn get_supply_distribution(State(state): State<AppState>) -> ApiResult<SupplyDistributionResponse> {
+    let stats = holder_stats(&state).await;
+    Ok(success(stats.distribution()))
+}
+
+/// Holder balances at the tip, computed once per tip
+async fn holder_stats(state: &AppState) -> Arc<super::HolderStats> {
+    let blockchain = state.blockchain.read().await;
+    let (height, block_hash) = blockchain
+        .get_latest_block()
+        .map(|block| (block.index, block.hash().to_hex()))
+        .unwrap_or_default();
+    state.holder_stats.get_or_compute(&block_hash, || {
+        let balances = blockchain.address_balances().map(|(address, balance)| (address.to_string(), balance));
+        super::HolderStats::new(height, block_hash.clone(), balances)
+    })
+}
+
+/// Replay the chain and check its supply against the reward schedule and the UTXO set
+#[utoipa::path(
+    get, path = "/api/audit/supply", tag = "blockchain",
+    responses((status = 200, body = ApiResponse<SupplyAudit>))
+)]
+pub async fn audit_supply(State(state): State<AppState>) -> ApiResult<SupplyAudit> {
+    // Replaying every block is CPU-bound; keep it off the runtime's workers
+    let blockchain = state.blockchain.clone().read_owned().await;
+    let audit = tokio::task::spawn_blocking(move || blockchain.audit_supply())
+        .await
+        .map_err(|e| ApiError::from(crate::error::LedgerError::Internal(format!("Supply audit task failed: {}", e))))?;
+    Ok(success(audit))
 }
 
 /// Get all UTXOs
+#[utoipa::path(
+    get, path = "/api/utxos", tag = "blockchain", params(PaginationParams),
+    responses((status = 200, body = ApiResponse<PaginatedResponse<UtxoResponse>>), (status = 400, description = "Invalid cursor", body = ErrorResponse))
+)]
 pub async fn get_all_utxos(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> std::result::Result<Json<PaginatedResponse<UtxoResponse>>, ApiError> {
+) -> ApiResult<PaginatedResponse<UtxoResponse>> {
     let blockchain = state.blockchain.read().await;
-    let all_utxos = blockchain.get_all_utxos();
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
     
-    let page = params.page.unwrap_or(0);
-    let limit = params.limit.unwrap_or(20).min(100);
+    // Listed by the height that created them, ties broken by UTXO id
+    let mut all_utxos: Vec<(String, &UtxoEntry)> = blockchain
+        .get_all_utxos()
+        .into_iter()
+        .map(|utxo| (format!("{}:{}", utxo.tx_hash, utxo.output_index), utxo))
+        .collect();
+    all_utxos.sort_by(|(a_id, a), (b_id, b)| (a.block_height, a_id).cmp(&(b.block_height, b_id)));
     let total = all_utxos.len() as u64;
+    let window = page_window(&params, limit, all_utxos.len(), |i| {
+        (all_utxos[i].1.block_height, all_utxos[i].0.as_str())
+    })?;
     
-    let start = (page * limit) as usize;
-    let end = ((page + 1) * limit).min(total) as usize;
-    
-    let utxos = if start < all_utxos.len() {
-        all_utxos[start..end]
-            .iter()
-            .map(|utxo| UtxoResponse {
-                utxo_id: format!("{}:{}", utxo.tx_hash, utxo.output_index),
-                amount: utxo.output.amount,
-                address: utxo.output.recipient.clone(),
-                recipient: utxo.output.recipient.clone(),
-                block_height: utxo.block_height,
-                tx_hash: utxo.tx_hash,
-                output_index: utxo.output_index,
-                is_spent: false,
-    
-            })
-            .collect()
-    } else {
-        vec![]
-    };
+    let mut utxos: Vec<UtxoResponse> = all_utxos[window.range.clone()]
+        .iter()
+        .map(|(utxo_id, utxo)| UtxoResponse {
+            utxo_id: utxo_id.clone(),
+            amount: utxo.output.amount,
+            address: utxo.output.recipient.clone(),
+            recipient: utxo.output.recipient.clone(),
+            block_height: utxo.block_height,
+            tx_hash: utxo.tx_hash.clone(),
+            output_index: utxo.output_index,
+            is_spent: false,
+        })
+        .collect();
+    if window.newest_first {
+        utxos.reverse();
+    }
     
-    Ok(Json(super::paginate(utxos, page, limit, total)))
+    Ok(success(super::paginate_window(utxos, &params, limit, total, &window)))
 }
 
 /// Get UTXO by ID
 pub async fn get_utxo_by_id(
     State(state): State<AppState>,
     Path(utxo_id): Path<String>,
-) -> std::result::Result<Json<UtxoResponse>, ApiError> {
+) -> ApiResult<UtxoResponse> {
     // Parse UTXO ID (format: "tx_hash:output_index")
     let parts: Vec<&str> = utxo_id.split(':').collect();
     if parts.len() != 2 {
-        return Err(ApiError::new("INVALID_UTXO_ID", "UTXO ID must be in format 'tx_hash:output_index'"));
+        return Err(ApiError::new(ErrorCode::InvalidUtxoId, "UTXO ID must be in format 'tx_hash:output_index'"));
     }
     
     let tx_hash = Hash256::from_hex(parts[0])
-        .map_err(|_| ApiError::new("INVALID_HASH", "Invalid transaction hash in UTXO ID"))?;
+        .map_err(|_| ApiError::new(ErrorCode::InvalidHash, "Invalid transaction hash in UTXO ID"))?;
     let output_index: u32 = parts[1]
         .parse()
-        .map_err(|_| ApiError::new("INVALID_INDEX", "Invalid output index in UTXO ID"))?;
+        .map_err(|_| ApiError::new(ErrorCode::InvalidIndex, "Invalid output index in UTXO ID"))?;
     
     let blockchain = state.blockchain.read().await;
     
@@ -481,116 +1721,57 @@ pub async fn get_utxo_by_id(
             utxo_id: utxo_id.to_string(),
             amount: utxo.output.amount,
             address: utxo.output.recipient.clone(),
-            recipient: utxo.output.recipient,
+            recipient: utxo.output.recipient.clone(),
             block_height: utxo.block_height,
-            tx_hash: utxo.tx_hash,
+            tx_hash: utxo.tx_hash.clone(),
             output_index: utxo.output_index,
             is_spent: false,
         };
-        Ok(Json(response))
+        Ok(success(response))
     } else {
-        Err(ApiError::new("NOT_FOUND", "UTXO not found"))
+        Err(ApiError::new(ErrorCode::NotFound, "UTXO not found"))
     }
 }
 
 /// Get network peers (placeholder)
 pub async fn get_network_peers(
     State(_state): State<AppState>,
-) -> std::result::Result<Json<Vec<serde_json::Value>>, ApiError> {
+) -> ApiResult<Vec<serde_json::Value>> {
     // TODO: Implement peer management
-    Ok(Json(vec![]))
+    Ok(success(vec![]))
 }
 
 /// Get network status
 pub async fn get_network_status(
-    State(state): State<AppState>,
-) -> std::result::Result<Json<NetworkStatusResponse>, ApiError> {
-    let blockchain = state.blockchain.read().await;
-    
+    State(_state): State<AppState>,
+) -> ApiResult<NetworkStatusResponse> {
     let response = NetworkStatusResponse {
         peer_count: 0,
         is_synced: false,
         sync_progress: 0.0,
     };
     
-    Ok(Json(response))
-}
-
-/// Compact database (admin endpoint)
-pub async fn compact_database(
-    State(state): State<AppState>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
-    // TODO: Implement database compaction
-    Err(ApiError::new("NOT_IMPLEMENTED", "Database compaction not yet implemented"))
-}
-
-/// Create backup (admin endpoint)
-pub async fn create_backup(
-    State(state): State<AppState>,
-) -> std::result::Result<Json<serde_json::Value>, ApiError> {
-    // TODO: Implement backup creation
-    Err(ApiError::new("NOT_IMPLEMENTED", "Backup creation not yet implemented"))
-}
-
-/// Get system metrics (admin endpoint)
-#[axum::debug_handler]
-pub async fn get_system_metrics(
-    State(state): State<AppState>,
-) -> std::result::Result<Json<SystemMetricsResponse>, ApiError> {
-    // TODO: Implement system metrics collection
-    let response = SystemMetricsResponse {
-        memory_usage: 0,
-        cpu_usage: 0.0,
-        disk_usage: 0,
-        network_io: NetworkIoMetrics {
-            bytes_sent: 0,
-            bytes_received: 0,
-            packets_sent: 0,
-            packets_received: 0,
-        },
-    };
-    
-    Ok(Json(response))
+    Ok(success(response))
 }
 
-/// Helper function to calculate network hash rate
+/// Network hash rate implied by the current difficulty and recent block times
 async fn calculate_network_hash_rate(blockchain: &crate::core::Blockchain) -> f64 {
-    // TODO: Implement actual hash rate calculation based on recent blocks
-    // This would involve analyzing the time between blocks and difficulty
-    0.0
+    blockchain.get_stats().estimated_hash_rate
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
-    use crate::storage::PersistentStorage;
-    use tokio::sync::broadcast;
+    use crate::api::test_support::TestNode;
 
     async fn create_test_state() -> AppState {
-        let config = Config::default();
-        let storage = Arc::new(PersistentStorage::new(":memory:").unwrap());
-        let blockchain = Arc::new(RwLock::new(
-            Blockchain::load_or_create(storage.clone(), config.blockchain.clone())
-                .await
-                .unwrap()
-        ));
-        let (mining_progress_tx, _) = broadcast::channel(100);
-        
-        AppState {
-            blockchain,
-            storage,
-            mining_progress_tx,
-            miner: Arc::new(RwLock::new(None)),
-            config: super::ApiConfig::default(),
-        }
+        TestNode::start().await.state
     }
 
     #[tokio::test]
     async fn test_health_check() {
-        let state = create_test_state().await;
-        let result = health_check(State(state)).await;
-        assert!(result.is_ok());
+        let Json(response) = health_check().await;
+        assert!(response.error.is_none());
     }
 
     #[tokio::test]
@@ -601,10 +1782,9 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_latest_block_empty_chain() {
+    async fn test_get_latest_block_is_genesis() {
         let state = create_test_state().await;
-        let result = get_latest_block(State(state)).await;
-        // Should return error for empty blockchain
-        assert!(result.is_err());
+        let result = get_latest_block(State(state), Query(BlockParams::default())).await;
+        assert!(result.is_ok());
     }
-}
\ No newline at end of file
+}
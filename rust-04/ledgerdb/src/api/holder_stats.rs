@@ -0,0 +1,160 @@
+//! Rich list and supply distribution, for `/api/stats/richlist` and
+//! `/api/stats/distribution`.
+//!
+//! Both are read off every address's balance in the UTXO set, sorted. That is
+//! computed on the first request after the tip moves and shared by every request
+//! until it moves again.
+
+use ledgerdb_types::{DistributionBucket, RichListEntry, RichListResponse, SupplyDistributionResponse};
+use std::sync::{Arc, Mutex};
+
+/// Every holder's balance at one block, largest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HolderStats {
+    pub height: u64,
+    pub block_hash: String,
+    pub total_supply: u64,
+    /// (address, balance), largest balance first, ties by address
+    pub holders: Vec<(String, u64)>,
+}
+
+impl HolderStats {
+    /// Stats for the block at `height` from each address's balance
+    pub fn new(height: u64, block_hash: String, balances: impl IntoIterator<Item = (String, u64)>) -> Self {
+        let mut holders: Vec<(String, u64)> = balances.into_iter().collect();
+        holders.sort_by(|(a, a_balance), (b, b_balance)| b_balance.cmp(a_balance).then_with(|| a.cmp(b)));
+        let total_supply = holders.iter().fold(0u64, |total, (_, balance)| total.saturating_add(*balance));
+        Self { height, block_hash, total_supply, holders }
+    }
+
+    /// The `limit` largest balances
+    pub fn rich_list(&self, limit: usize) -> RichListResponse {
+        let entries = self.holders
+            .iter()
+            .take(limit)
+            .enumerate()
+            .map(|(i, (address, balance))| RichListEntry {
+                rank: i + 1,
+                address: address.clone(),
+                balance: *balance,
+                share: self.share(*balance),
+            })
+            .collect();
+        RichListResponse {
+            height: self.height,
+            block_hash: self.block_hash.clone(),
+            total_supply: self.total_supply,
+            holders: self.holders.len(),
+            entries,
+        }
+    }
+
+    /// Holders and supply per power-of-ten balance bucket
+    pub fn distribution(&self) -> SupplyDistributionResponse {
+        let mut buckets: Vec<DistributionBucket> = Vec::new();
+        // Smallest balances first, so the buckets fill in order
+        for (_, balance) in self.holders.iter().rev() {
+            while buckets.last().is_none_or(|bucket| bucket.max_balance.is_some_and(|max| *balance >= max)) {
+                let min_balance = buckets.last().and_then(|bucket| bucket.max_balance).unwrap_or(0);
+                let max_balance = if min_balance == 0 { Some(10) } else { min_balance.checked_mul(10) };
+                buckets.push(DistributionBucket { min_balance, max_balance, addresses: 0, balance: 0, share: 0.0 });
+            }
+            let bucket = buckets.last_mut().expect("a bucket was pushed above");
+            bucket.addresses += 1;
+            bucket.balance = bucket.balance.saturating_add(*balance);
+        }
+        for bucket in &mut buckets {
+            bucket.share = self.share(bucket.balance);
+        }
+        SupplyDistributionResponse {
+            height: self.height,
+            block_hash: self.block_hash.clone(),
+            total_supply: self.total_supply,
+            holders: self.holders.len(),
+            buckets,
+        }
+    }
+
+    /// `amount` as a percentage of the total supply
+    fn share(&self, amount: u64) -> f64 {
+        if self.total_supply == 0 {
+            0.0
+        } else {
+            amount as f64 * 100.0 / self.total_supply as f64
+        }
+    }
+}
+
+/// The [`HolderStats`] of the latest tip anyone asked about
+#[derive(Debug, Default)]
+pub struct HolderStatsCache {
+    latest: Mutex<Option<Arc<HolderStats>>>,
+}
+
+impl HolderStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stats at the block `block_hash`, computed with `compute` unless cached
+    pub fn get_or_compute(&self, block_hash: &str, compute: impl FnOnce() -> HolderStats) -> Arc<HolderStats> {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        match latest.as_ref() {
+            Some(stats) if stats.block_hash == block_hash => stats.clone(),
+            _ => latest.insert(Arc::new(compute())).clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> HolderStats {
+        let balances = [("carol", 5), ("alice", 600), ("bob", 300), ("dave", 95), ("erin", 0)];
+        HolderStats::new(7, "tip".to_string(), balances.map(|(address, balance)| (address.to_string(), balance)))
+    }
+
+    #[test]
+    fn test_rich_list() {
+        let rich = stats().rich_list(2);
+        assert_eq!((rich.total_supply, rich.holders), (1000, 5));
+        let top: Vec<(usize, &str, u64, f64)> =
+            rich.entries.iter().map(|e| (e.rank, e.address.as_str(), e.balance, e.share)).collect();
+        assert_eq!(top, vec![(1, "alice", 600, 60.0), (2, "bob", 300, 30.0)]);
+    }
+
+    #[test]
+    fn test_distribution_buckets() {
+        let balances = [("a", 5), ("b", 0), ("c", 500), ("d", 1500)];
+        let distribution = HolderStats::new(7, "tip".to_string(), balances.map(|(address, balance)| (address.to_string(), balance)))
+            .distribution();
+        let buckets: Vec<(u64, Option<u64>, usize, u64)> = distribution.buckets
+            .iter()
+            .map(|b| (b.min_balance, b.max_balance, b.addresses, b.balance))
+            .collect();
+        // The empty [10, 100) bucket is not skipped
+        assert_eq!(
+            buckets,
+            vec![(0, Some(10), 2, 5), (10, Some(100), 0, 0), (100, Some(1000), 1, 500), (1000, Some(10_000), 1, 1500)]
+        );
+        assert_eq!(distribution.buckets[2].share, 500.0 * 100.0 / 2005.0);
+
+        let whale = HolderStats::new(0, "tip".to_string(), [("whale".to_string(), u64::MAX)]).distribution();
+        let last = whale.buckets.last().unwrap();
+        assert_eq!((last.min_balance, last.max_balance, last.addresses), (10_000_000_000_000_000_000, None, 1));
+        assert_eq!(whale.buckets.len(), 20);
+    }
+
+    #[test]
+    fn test_cached_until_the_tip_moves() {
+        let cache = HolderStatsCache::new();
+        let first = cache.get_or_compute("tip", stats);
+        let again = cache.get_or_compute("tip", || unreachable!("cached"));
+        assert!(Arc::ptr_eq(&first, &again));
+
+        let moved = cache.get_or_compute("next", || HolderStats::new(8, "next".to_string(), []));
+        assert_eq!((moved.height, moved.total_supply), (8, 0));
+        assert!(moved.distribution().buckets.is_empty());
+    }
+}
@@ -0,0 +1,846 @@
+//! End-to-end tests of the HTTP API through a real listener.
+
+use super::responses::{format_chain_work, BanRequest, BlockFilterResponse, BlockVerbosity, HeadersResponse, SnapshotManifest};
+use super::test_support::{miner_address, EnvelopeExt, TestNode};
+use super::{create_router, AppState, ErrorCode, RouterBuilder, Shutdown, WebSocketManager};
+use crate::config::SyncConfig;
+use crate::core::{compact_block, verify_header_chain, Block, BlockFilter, BlockHeader, BlockStatus, Blockchain, BlockchainConfig, UtxoSnapshot};
+use crate::crypto::{Address, Hash256, MerkleProof, MinerEvent, MiningControl, MiningProgress, PublicKey, SignatureAlgorithm};
+use crate::sync::{sync_from_peers, SyncPhase};
+use crate::utils::constants::BAN_SCORE_THRESHOLD;
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_health_and_version() {
+    let node = TestNode::start().await;
+
+    let health = node.server.get("/health").await;
+    health.assert_status_ok();
+    assert_eq!(health.data::<Value>()["status"], "healthy");
+
+    let version = node.server.get("/version").await;
+    assert_eq!(version.data::<Value>()["api_version"], "v1");
+}
+
+#[tokio::test]
+async fn test_route_groups() {
+    let node = TestNode::start().await;
+    assert_eq!(node.server.get("/").await.status_code(), StatusCode::NOT_FOUND);
+    let health = node.server.get("/api/health").await;
+    health.assert_status_ok();
+    assert_eq!(health.headers()["x-content-type-options"], "nosniff");
+
+    let app = RouterBuilder::new(node.state.clone()).admin(false).static_files(true).build();
+    let server = axum_test::TestServerConfig::builder().http_transport().build_server(app).unwrap();
+    server.get("/").await.assert_status_ok();
+    server.get("/static/style.css").await.assert_status_ok();
+    server.get("/health").await.assert_status_ok();
+    let admin = server.get("/admin/tasks").await;
+    assert_eq!(admin.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(admin.error().code, ErrorCode::NotFound);
+}
+
+#[tokio::test]
+async fn test_websocket_stats_and_limit() {
+    let node = TestNode::start().await;
+    let state = AppState { websockets: Arc::new(WebSocketManager::new(1)), ..node.state.clone() };
+    let server = axum_test::TestServerConfig::builder().http_transport().build_server(create_router(state)).unwrap();
+    let client = ledgerdb_client::Client::new(server.server_address().expect("http transport").as_str());
+
+    let stats_when = |done: fn(&ledgerdb_types::WebSocketStats) -> bool| {
+        let client = client.clone();
+        async move {
+            for _ in 0..100 {
+                let stats = client.websocket_stats().await.unwrap();
+                if done(&stats) {
+                    return stats;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            panic!("WebSocket stats never settled");
+        }
+    };
+
+    let subscription = client.subscribe(&["new_blocks"]).await.unwrap();
+    let stats = stats_when(|stats| stats.topics.contains_key("new_blocks")).await;
+    assert_eq!((stats.connections, stats.max_connections), (1, 1));
+    assert_eq!((stats.topics["mining_progress"], stats.topics["new_blocks"]), (1, 1));
+
+    // The only slot is taken until the subscription closes
+    assert!(client.subscribe(&[]).await.is_err());
+    subscription.close().await.unwrap();
+    let stats = stats_when(|stats| stats.connections == 0).await;
+    assert!(stats.topics.is_empty());
+    client.subscribe(&[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_blockchain_info_tracks_height() {
+    let node = TestNode::start().await;
+    node.mine_blocks(2).await;
+
+    let info = node.server.get("/api/blockchain/info").await;
+    info.assert_status_ok();
+    assert_eq!(info.data::<Value>()["height"], 3);
+}
+
+#[tokio::test]
+async fn test_reward_schedule() {
+    let node = TestNode::start().await;
+    node.mine_blocks(2).await;
+
+    let reward = node.server.get("/api/reward").await;
+    reward.assert_status_ok();
+    let reward = reward.data::<Value>();
+    assert_eq!(reward["height"], 3);
+    assert_eq!(reward["current_reward"], 50_000_000);
+    assert_eq!(reward["next_change_height"], 210_000);
+    assert_eq!(reward["next_reward"], 25_000_000);
+    assert_eq!(reward["schedule"]["type"], "halving");
+}
+
+#[tokio::test]
+async fn test_checkpoints() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(2).await;
+    {
+        let mut blockchain = node.state.blockchain.write().await;
+        blockchain.config.checkpoints.insert(1, mined[0].hash());
+        blockchain.config.checkpoints.insert(100, mined[1].hash());
+    }
+
+    let checkpoints = node.server.get("/api/checkpoints").await;
+    checkpoints.assert_status_ok();
+    let checkpoints = checkpoints.data::<Value>();
+    assert_eq!(checkpoints["last_checkpoint"], 1);
+    assert_eq!(checkpoints["checkpoints"][0]["height"], 1);
+    assert_eq!(checkpoints["checkpoints"][0]["reached"], true);
+    assert_eq!(checkpoints["checkpoints"][1]["height"], 100);
+    assert_eq!(checkpoints["checkpoints"][1]["reached"], false);
+}
+
+#[tokio::test]
+async fn test_light_client_sync() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(3).await;
+    let genesis = node.state.blockchain.read().await.get_block_by_index(0).unwrap().hash();
+
+    let response = node.server.get("/api/headers?from=1&count=10").await;
+    response.assert_status_ok();
+    let response = response.data::<HeadersResponse>();
+    assert_eq!((response.from, response.tip_height), (1, 3));
+    let headers: Vec<BlockHeader> = response
+        .headers
+        .iter()
+        .map(|h| BlockHeader::from_bytes(&hex::decode(h).unwrap()).unwrap())
+        .collect();
+    assert_eq!(verify_header_chain(&genesis, &headers).unwrap(), mined[2].hash());
+
+    let coinbase = mined[1].transactions()[0].hash();
+    let proof = node.server.get(&format!("/api/transactions/{}/proof", coinbase.to_hex())).await;
+    proof.assert_status_ok();
+    let proof: MerkleProof = serde_json::from_value(proof.data::<Value>()["merkle_proof"].clone()).unwrap();
+    assert!(headers[1].proves_transaction(&coinbase, &proof));
+
+    let verify = |leaf: &Hash256| json!({ "merkle_root": headers[1].merkle_root, "leaf_hash": leaf, "proof": proof });
+    let response = node.server.post("/api/proofs/verify").json(&verify(&coinbase)).await;
+    response.assert_status_ok();
+    assert_eq!(response.data::<Value>()["valid"], true);
+    let other = mined[0].transactions()[0].hash();
+    assert_eq!(node.server.post("/api/proofs/verify").json(&verify(&other)).await.data::<Value>()["valid"], false);
+
+    node.server.get("/api/headers?from=4").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_initial_block_download() {
+    let source = TestNode::start().await;
+    let mined = source.mine_blocks(5).await;
+    let fresh = TestNode::start().await;
+    assert_eq!(fresh.server.get("/api/sync/status").await.data::<Value>()["phase"], "idle");
+
+    let address = source.server.server_address().expect("http transport");
+    let config = SyncConfig { peers: vec![address.to_string()], batch_size: 2, max_in_flight: 2, ..SyncConfig::default() };
+    sync_from_peers(&fresh.state, &config, &Shutdown::new()).await.unwrap();
+
+    let tip = fresh.state.blockchain.read().await.get_latest_block().cloned().unwrap();
+    assert_eq!(tip.hash(), mined[4].hash());
+    let status = fresh.server.get("/api/sync/status").await.data::<Value>();
+    assert_eq!(status["phase"], "synced");
+    assert_eq!((status["start_height"].as_u64(), status["block_height"].as_u64()), (Some(0), Some(5)));
+    assert_eq!(status["in_flight"], 0);
+
+    // Nothing is left to fetch from a peer at the same height
+    sync_from_peers(&fresh.state, &config, &Shutdown::new()).await.unwrap();
+    assert_eq!(fresh.state.sync.status().block_height, 5);
+
+    // A banned peer is not asked
+    let peer = address.socket_addrs(|| None).unwrap()[0];
+    fresh.state.peers.ban(peer, None, None).unwrap();
+    assert!(sync_from_peers(&fresh.state, &config, &Shutdown::new()).await.is_err());
+    assert_eq!(fresh.state.sync.status().error.as_deref(), Some("Network error: no sync peer answered"));
+    fresh.state.peers.unban(peer).unwrap();
+
+    let unreachable = SyncConfig { peers: vec!["http://127.0.0.1:1".to_string()], ..SyncConfig::default() };
+    assert!(sync_from_peers(&fresh.state, &unreachable, &Shutdown::new()).await.is_err());
+    assert_eq!(fresh.state.sync.status().phase, SyncPhase::Failed);
+}
+
+#[tokio::test]
+async fn test_compact_block_announcements() {
+    let source = TestNode::start().await;
+    let peer = TestNode::start().await;
+    let mined = source.mine_blocks(2).await;
+
+    // A block of only a coinbase needs nothing from the pool
+    let response = peer.server.post("/api/blocks/compact").json(&compact_block(&mined[0], 1).unwrap()).await;
+    response.assert_status_ok();
+    assert_eq!(response.data::<Value>()["status"], "connected");
+    let response = peer.server.post("/api/blocks/compact").json(&compact_block(&mined[0], 2).unwrap()).await;
+    assert_eq!(response.data::<Value>()["status"], "duplicate");
+
+    // A payment the peer has not seen is asked for by position
+    let payment = crate::core::Transaction::new(
+        vec![crate::core::TransactionInput::new(Hash256::new([7; 32]), 0, None, None)],
+        vec![crate::core::TransactionOutput::new(10, miner_address())],
+    );
+    let mut block = mined[1].clone();
+    block.transactions_mut().push(payment);
+    block.header.transaction_count = 2;
+    let response = peer.server.post("/api/blocks/compact").json(&compact_block(&block, 3).unwrap()).await;
+    let response = response.data::<Value>();
+    assert_eq!(response["status"], "incomplete");
+    assert_eq!(response["missing_transactions"], json!([1]));
+
+    let mut malformed = compact_block(&mined[1], 4).unwrap();
+    malformed.prefilled.clear();
+    let response = peer.server.post("/api/blocks/compact").json(&malformed).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.error().code, ErrorCode::InvalidRequest);
+
+    // The relay announces blocks mined after it starts
+    peer.server.post("/api/blocks/submit").json(&mined[1]).await.assert_status_ok();
+    let address = peer.server.server_address().expect("http transport");
+    let config = SyncConfig { peers: vec![address.to_string()], ..SyncConfig::default() };
+    crate::relay::spawn(&source.state, &config).unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let tip = source.mine_blocks(1).await.remove(0);
+    for _ in 0..50 {
+        if peer.state.blockchain.read().await.height() == 4 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let peer_tip = peer.state.blockchain.read().await.get_latest_block().map(Block::hash);
+    assert_eq!(peer_tip, Some(tip.hash()));
+    source.state.shutdown.trigger();
+}
+
+#[tokio::test]
+async fn test_chunked_snapshot_download() {
+    let node = TestNode::start().await;
+    node.mine_blocks(3).await;
+    let expected = node.state.blockchain.read().await.utxo_snapshot(2).unwrap();
+
+    let manifest = node.server.get("/api/snapshot/utxo/manifest?height=2").await.data::<SnapshotManifest>();
+    assert_eq!((manifest.height, manifest.commitment.0), (2, *expected.commitment.as_bytes()));
+    let chunk = node.server.get(&format!("/api/snapshot/utxo/chunks/{}", manifest.chunks[0])).await;
+    chunk.assert_status_ok();
+    assert!(manifest.verify_chunk(0, chunk.as_bytes()));
+    node.server.get(&format!("/api/snapshot/utxo/chunks/{}", Hash256::zero().to_hex())).await.assert_status(StatusCode::NOT_FOUND);
+    node.server.get("/api/snapshot/utxo/chunks/zz").await.assert_status(StatusCode::BAD_REQUEST);
+    node.server.get("/api/snapshot/utxo/manifest?height=9").await.assert_status(StatusCode::NOT_FOUND);
+
+    // A second node on the same chain builds the chunks from the manifest's height
+    let other = TestNode::start().await;
+    for height in 1..=3 {
+        let block = node.state.blockchain.read().await.get_block_by_index(height).cloned().unwrap();
+        other.state.blockchain.write().await.add_block(block).unwrap();
+    }
+    let clients: Vec<ledgerdb_client::Client> = [&node, &other]
+        .iter()
+        .map(|node| ledgerdb_client::Client::new(node.server.server_address().expect("http transport").as_str()))
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("utxo.snapshot");
+    ledgerdb_client::download_snapshot(&clients[1..], &manifest, &dir.path().join("chunks"), &output, 2).await.unwrap();
+    assert_eq!(UtxoSnapshot::read_from(&output).unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_block_filter() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(1).await;
+
+    let response = node.server.get(&format!("/api/blocks/{}/filter", mined[0].hash().to_hex())).await;
+    response.assert_status_ok();
+    let response = response.data::<BlockFilterResponse>();
+    assert_eq!(response.height, 1);
+    let filter = BlockFilter {
+        block_hash: response.block_hash,
+        n: response.n,
+        data: hex::decode(&response.filter).unwrap(),
+    };
+    assert!(filter.matches(&miner_address()).unwrap());
+    let stranger = Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![9; 33]));
+    assert!(!filter.matches(&stranger).unwrap());
+
+    node.server.get("/api/blocks/7/filter").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_block_queries() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(2).await;
+    let tip_hash = mined[1].hash().to_hex();
+
+    let latest = node.server.get("/api/blocks/latest").await.data::<Value>();
+    assert_eq!(latest["index"], 2);
+
+    let by_height = node.server.get("/api/blocks/height/1").await;
+    by_height.assert_status_ok();
+    assert_eq!(by_height.data::<Value>()["index"], 1);
+
+    let by_hash = node.server.get(&format!("/api/blocks/{}", tip_hash)).await;
+    by_hash.assert_status_ok();
+    assert_eq!(by_hash.data::<Value>()["index"], 2);
+
+    let transactions = node
+        .server
+        .get(&format!("/api/blocks/{}/transactions", tip_hash))
+        .await
+        .data::<Value>();
+    assert_eq!(transactions.as_array().unwrap().len(), 1, "only the coinbase");
+}
+
+#[tokio::test]
+async fn test_block_verbosity() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(2).await;
+    let tip_hash = mined[1].hash().to_hex();
+
+    let raw = node.server.get(&format!("/api/blocks/{}?verbosity=0", tip_hash)).await.data::<Value>();
+    assert_eq!(raw["hash"], tip_hash);
+    let decoded = Block::from_bytes(&hex::decode(raw["hex"].as_str().unwrap()).unwrap()).unwrap();
+    assert_eq!(decoded.hash(), mined[1].hash());
+    assert!(raw.get("transactions").is_none());
+
+    let summary = node.server.get("/api/blocks/height/2?verbosity=1").await.data::<Value>();
+    assert_eq!(summary["index"], 2);
+    assert_eq!(summary["transaction_hashes"], json!([mined[1].transactions()[0].hash().to_hex()]));
+    assert_eq!(summary["header"]["nonce"], mined[1].header.nonce);
+    assert!(summary.get("transactions").is_none());
+
+    // The default stays the full block
+    let full = node.server.get("/api/blocks/latest").await.data::<Value>();
+    assert_eq!(full, node.server.get("/api/blocks/latest?verbosity=2").await.data::<Value>());
+    assert!(full["transactions"][0]["outputs"].is_array());
+
+    let page = node.server.get("/api/blocks?limit=2&verbosity=1").await.data::<Value>();
+    assert_eq!(page["data"][0]["transaction_hashes"].as_array().unwrap().len(), 1);
+
+    let invalid = node.server.get("/api/blocks/latest?verbosity=3").await;
+    invalid.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(invalid.error().code, ErrorCode::InvalidRequest);
+
+    let address = node.server.server_address().expect("http transport");
+    let client = ledgerdb_client::Client::new(address.as_str());
+    let view = client.block_view_by_height(1, BlockVerbosity::Summary).await.unwrap();
+    assert!(matches!(view, ledgerdb_types::BlockView::Summary(s) if s.hash == mined[0].hash().to_hex()));
+}
+
+#[tokio::test]
+async fn test_batch_query() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(2).await;
+    let tx_hash = mined[0].transactions()[0].hash().to_hex();
+    let miner = miner_address().to_string();
+
+    let request = json!({
+        "queries": [
+            { "type": "block", "id": "2" },
+            { "type": "block", "id": mined[0].hash().to_hex() },
+            { "type": "transaction", "hash": tx_hash },
+            { "type": "address", "address": miner },
+            { "type": "block", "id": "99" },
+            { "type": "transaction", "hash": "zz" },
+            { "type": "address", "address": "not-an-address" },
+        ],
+        "verbosity": 1,
+    });
+    let response = node.server.post("/api/batch").json(&request).await;
+    response.assert_status_ok();
+    let results = response.data::<Value>()["results"].as_array().unwrap().clone();
+    assert_eq!(results.len(), 7);
+
+    assert_eq!(results[0]["data"]["block"]["index"], 2);
+    assert!(results[0]["data"]["block"]["transaction_hashes"].is_array());
+    assert_eq!(results[1]["data"]["block"]["hash"], mined[0].hash().to_hex());
+    assert_eq!(results[2]["data"]["transaction"]["id"], mined[0].transactions()[0].id);
+    let utxo_count = {
+        let blockchain = node.state.blockchain.read().await;
+        blockchain.get_utxos_for_address(&miner_address()).len()
+    };
+    assert_eq!(results[3]["data"]["address"]["address"], miner);
+    assert_eq!(results[3]["data"]["address"]["utxo_count"], utxo_count);
+    assert!(results[3]["data"]["address"]["balance"].as_u64().unwrap() > 0);
+
+    let codes: Vec<_> = results[4..].iter().map(|result| result["error"]["code"].clone()).collect();
+    assert_eq!(codes, [json!("NOT_FOUND"), json!("INVALID_HASH"), json!("INVALID_ADDRESS")]);
+    assert!(results[4..].iter().all(|result| result["data"].is_null()));
+
+    let queries: Vec<_> = (0..=node.state.config.max_batch_size)
+        .map(|height| json!({ "type": "block", "id": height.to_string() }))
+        .collect();
+    let oversized = node.server.post("/api/batch").json(&json!({ "queries": queries })).await;
+    oversized.assert_status(StatusCode::BAD_REQUEST);
+    let error = oversized.error();
+    assert_eq!(error.code, ErrorCode::ValidationError);
+    assert_eq!(error.details.unwrap()["max_batch_size"], node.state.config.max_batch_size);
+
+    let address = node.server.server_address().expect("http transport");
+    let client = ledgerdb_client::Client::new(address.as_str());
+    let batch = ledgerdb_types::BatchRequest {
+        queries: vec![ledgerdb_types::BatchQuery::Transaction { hash: tx_hash }],
+        verbosity: None,
+    };
+    let response = client.batch(&batch).await.unwrap();
+    assert!(matches!(
+        &response.results[0].data,
+        Some(ledgerdb_types::BatchItem::Transaction(tx)) if tx.id == mined[0].transactions()[0].id
+    ));
+}
+
+#[tokio::test]
+async fn test_chain_work_reported() {
+    let node = TestNode::start().await;
+    node.mine_blocks(2).await;
+    let (tip_work, block_1_work) = {
+        let blockchain = node.state.blockchain.read().await;
+        (blockchain.chain_work(), blockchain.chain_work_at(1).unwrap())
+    };
+    assert!(tip_work > block_1_work);
+
+    let latest = node.server.get("/api/blocks/latest").await.data::<Value>();
+    assert_eq!(latest["chain_work"], format_chain_work(tip_work));
+    let by_height = node.server.get("/api/blocks/height/1").await.data::<Value>();
+    assert_eq!(by_height["chain_work"], format_chain_work(block_1_work));
+
+    let stats = node.server.get("/api/blockchain/stats").await.data::<Value>();
+    assert_eq!(stats["blockchain"]["chain_work"], format_chain_work(tip_work));
+}
+
+#[tokio::test]
+async fn test_mining_status_follows_miner_events() {
+    let node = TestNode::start().await;
+    let status = node.server.get("/api/mining/status").await.data::<Value>();
+    assert_eq!(status["is_mining"], false);
+    assert!(status["last_event"].is_null());
+
+    let control = MiningControl::new().with_events(node.state.miner_events.clone());
+    crate::node::mine_one(&node.state.blockchain, &node.state.storage, &miner_address(), &control)
+        .await
+        .unwrap()
+        .expect("block added on the tip");
+    let status = node.server.get("/api/mining/status").await.data::<Value>();
+    assert_eq!(status["is_mining"], false);
+    assert_eq!(status["current_block_height"], 2);
+    assert_eq!(status["last_event"]["state"], "found");
+    assert_eq!(status["last_event"]["block_height"], 1);
+    assert_eq!(status["last_event"]["progress"], 100.0);
+
+    node.state.miner_events.send(MinerEvent::Progress {
+        height: 2,
+        progress: MiningProgress { attempts: 500, hash_rate: 250.0, target_difficulty: 4, ..Default::default() },
+    });
+    let status = node.server.get("/api/mining/status").await.data::<Value>();
+    assert_eq!(status["is_mining"], true);
+    assert_eq!(status["hash_rate"], 250.0);
+    assert_eq!(status["last_event"]["state"], "searching");
+    assert_eq!(status["last_event"]["attempts"], 500);
+}
+
+#[tokio::test]
+async fn test_orphan_pool_listing() {
+    let node = TestNode::start().await;
+    let mut source = Blockchain::new(BlockchainConfig::default(), miner_address()).unwrap();
+    for _ in 0..2 {
+        let block = source.create_block(miner_address()).unwrap();
+        source.add_block(block).unwrap();
+    }
+    let (parent, orphan) = (source.get_block_by_index(1).unwrap(), source.get_block_by_index(2).unwrap());
+
+    let status = node.state.blockchain.write().await.process_block(orphan.clone()).unwrap();
+    assert_eq!(status, BlockStatus::Orphaned { missing_parent: parent.hash() });
+    let pool = node.server.get("/api/blocks/orphans").await.data::<Value>();
+    assert_eq!(pool["orphans"][0]["hash"], orphan.hash().to_hex());
+    assert_eq!(pool["orphans"][0]["index"], 2);
+    assert_eq!(pool["missing_parents"], json!([parent.hash().to_hex()]));
+
+    let status = node.state.blockchain.write().await.process_block(parent.clone()).unwrap();
+    assert_eq!(status, BlockStatus::Connected { height: 2, orphans_connected: 1 });
+    let pool = node.server.get("/api/blocks/orphans").await.data::<Value>();
+    assert_eq!(pool, json!({ "orphans": [], "missing_parents": [] }));
+}
+
+#[tokio::test]
+async fn test_submit_block() {
+    let node = TestNode::start().await;
+    let mut source = Blockchain::new(BlockchainConfig::default(), miner_address()).unwrap();
+    for _ in 0..3 {
+        let block = source.create_block(miner_address()).unwrap();
+        source.add_block(block).unwrap();
+    }
+    let blocks: Vec<_> = (1..=3).map(|h| source.get_block_by_index(h).unwrap().clone()).collect();
+
+    let orphaned = node.server.post("/api/blocks/submit").json(&blocks[1]).await;
+    orphaned.assert_status(StatusCode::ACCEPTED);
+    let orphaned = orphaned.data::<Value>();
+    assert_eq!(orphaned["status"], "orphaned");
+    assert_eq!(orphaned["missing_parent"], blocks[0].hash().to_hex());
+
+    let connected = node.server.post("/api/blocks/submit").json(&blocks[0]).await;
+    connected.assert_status_ok();
+    let connected = connected.data::<Value>();
+    assert_eq!(connected["status"], "connected");
+    assert_eq!(connected["height"], 2);
+    assert_eq!(connected["orphans_connected"], 1);
+
+    let duplicate = node.server.post("/api/blocks/submit").json(&blocks[0]).await.data::<Value>();
+    assert_eq!(duplicate["status"], "duplicate");
+
+    // Tampering with a transaction breaks the merkle commitment
+    let mut tampered = blocks[2].clone();
+    tampered.transactions_mut()[0].outputs[0].amount += 1;
+    let rejected = node.server.post("/api/blocks/submit").json(&tampered).await;
+    rejected.assert_status(StatusCode::BAD_REQUEST);
+    let rejected = rejected.error();
+    assert_eq!(rejected.code, ErrorCode::BlockRejected);
+    assert_eq!(rejected.details.unwrap()["index"], 3);
+    assert_eq!(node.state.blockchain.read().await.height(), 3);
+
+    let accepted = node.server.post("/api/blocks/submit").json(&blocks[2]).await.data::<Value>();
+    assert_eq!(accepted["height"], 3);
+}
+
+#[tokio::test]
+async fn test_submitted_branch_cannot_reorganize_below_a_checkpoint() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(3).await;
+    node.state.blockchain.write().await.config.checkpoints.insert(2, mined[1].hash());
+    let rival_miner = Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1u8; 33]));
+
+    // A heavier branch forking from genesis would undo the checkpointed block
+    let mut early = Blockchain::new(BlockchainConfig::default(), miner_address()).unwrap();
+    for _ in 0..4 {
+        let block = early.create_block(rival_miner.clone()).unwrap();
+        early.add_block(block).unwrap();
+    }
+    for height in 1..=3 {
+        let side = node.server.post("/api/blocks/submit").json(early.get_block_by_index(height).unwrap()).await;
+        side.assert_status(StatusCode::ACCEPTED);
+        assert_eq!(side.data::<Value>()["status"], "side_branch");
+    }
+    let refused = node.server.post("/api/blocks/submit").json(early.get_block_by_index(4).unwrap()).await;
+    refused.assert_status(StatusCode::BAD_REQUEST);
+    let refused = refused.error();
+    assert_eq!(refused.code, ErrorCode::BlockRejected);
+    assert!(refused.message.contains("checkpoint"), "{}", refused.message);
+    assert_eq!(node.state.blockchain.read().await.get_latest_block().unwrap().hash(), mined[2].hash());
+
+    // One forking at the checkpoint may replace the blocks above it
+    let mut late = Blockchain::new(BlockchainConfig::default(), miner_address()).unwrap();
+    for block in &mined[..2] {
+        late.add_block(block.clone()).unwrap();
+    }
+    for _ in 0..2 {
+        let block = late.create_block(rival_miner.clone()).unwrap();
+        late.add_block(block).unwrap();
+    }
+    let side = node.server.post("/api/blocks/submit").json(late.get_block_by_index(3).unwrap()).await.data::<Value>();
+    assert_eq!(side["status"], "side_branch");
+    let reorganized = node.server.post("/api/blocks/submit").json(late.get_block_by_index(4).unwrap()).await;
+    reorganized.assert_status_ok();
+    let reorganized = reorganized.data::<Value>();
+    assert_eq!(reorganized["status"], "reorganized");
+    assert_eq!(reorganized["fork_height"], 2);
+    assert_eq!(reorganized["height"], 4);
+    let tip = node.state.blockchain.read().await.get_latest_block().unwrap().hash();
+    assert_eq!(tip, late.get_block_by_index(4).unwrap().hash());
+}
+
+#[tokio::test]
+async fn test_blocks_pagination() {
+    let node = TestNode::start().await;
+    node.mine_blocks(4).await; // five blocks including genesis
+
+    let first = node.server.get("/api/blocks?page=0&limit=2").await.data::<Value>();
+    let heights = first["data"].as_array().unwrap().iter().map(|b| b["index"].as_u64().unwrap()).collect::<Vec<_>>();
+    assert_eq!(heights, vec![4, 3]);
+    assert_eq!(first["pagination"]["total_pages"], 3);
+    assert_eq!(first["pagination"]["has_next"], true);
+
+    // The partial last page must not repeat blocks from earlier pages
+    let last = node.server.get("/api/blocks?page=2&limit=2").await.data::<Value>();
+    let heights = last["data"].as_array().unwrap().iter().map(|b| b["index"].as_u64().unwrap()).collect::<Vec<_>>();
+    assert_eq!(heights, vec![0]);
+    assert_eq!(last["pagination"]["has_next"], false);
+
+    let ascending = node.server.get("/api/blocks?page=0&limit=2&order=asc").await.data::<Value>();
+    assert_eq!(ascending["data"][0]["index"], 3);
+}
+
+#[tokio::test]
+async fn test_cursor_pagination() {
+    let node = TestNode::start().await;
+    node.mine_blocks(4).await; // five blocks including genesis
+    let heights = |page: &Value| page["data"].as_array().unwrap().iter().map(|b| b["index"].as_u64().unwrap()).collect::<Vec<_>>();
+
+    let first = node.server.get("/api/blocks?limit=2").await.data::<Value>();
+    assert_eq!(heights(&first), vec![4, 3]);
+    let next = first["pagination"]["next_cursor"].as_str().unwrap().to_string();
+
+    // New blocks neither shift the walk nor repeat blocks already seen
+    node.mine_blocks(2).await;
+    let second = node.server.get(&format!("/api/blocks?limit=2&cursor={}", next)).await.data::<Value>();
+    assert_eq!(heights(&second), vec![2, 1]);
+    assert_eq!(second["pagination"]["has_next"], true);
+    assert_eq!(second["pagination"]["total"], 7);
+
+    let last = node.server
+        .get(&format!("/api/blocks?limit=2&cursor={}", second["pagination"]["next_cursor"].as_str().unwrap()))
+        .await
+        .data::<Value>();
+    assert_eq!(heights(&last), vec![0]);
+    assert!(last["pagination"]["next_cursor"].is_null());
+    assert_eq!(last["pagination"]["has_next"], false);
+
+    // Turning back walks towards the tip, oldest first
+    let newer = node.server
+        .get(&format!("/api/blocks?limit=3&cursor={}", second["pagination"]["prev_cursor"].as_str().unwrap()))
+        .await
+        .data::<Value>();
+    assert_eq!(heights(&newer), vec![3, 4, 5]);
+
+    let invalid = node.server.get("/api/blocks?cursor=zz").await;
+    invalid.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(invalid.error().code, ErrorCode::InvalidRequest);
+
+    // Walking the UTXO set visits every entry once
+    let total = node.server.get("/api/utxos").await.data::<Value>()["pagination"]["total"].as_u64().unwrap();
+    let mut seen = Vec::new();
+    let mut url = "/api/utxos?limit=2".to_string();
+    loop {
+        let page = node.server.get(&url).await.data::<Value>();
+        seen.extend(page["data"].as_array().unwrap().iter().map(|utxo| utxo["utxo_id"].as_str().unwrap().to_string()));
+        match page["pagination"]["next_cursor"].as_str() {
+            Some(cursor) => url = format!("/api/utxos?limit=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+    assert_eq!(seen.len() as u64, total);
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len() as u64, total);
+
+    let address = node.server.server_address().expect("http transport");
+    let client = ledgerdb_client::Client::new(address.as_str());
+    let page = client.blocks(&ledgerdb_types::PaginationParams::after(next, 10)).await.unwrap();
+    assert_eq!(page.data.iter().map(|block| block.block.index).collect::<Vec<_>>(), vec![2, 1, 0]);
+}
+
+#[tokio::test]
+async fn test_pagination_edge_cases() {
+    let node = TestNode::start().await;
+    node.mine_blocks(2).await;
+
+    let past_end = node.server.get("/api/blocks?page=50&limit=10").await;
+    past_end.assert_status_ok();
+    assert!(past_end.data::<Value>()["data"].as_array().unwrap().is_empty());
+
+    // A zero limit is treated as one rather than dividing by zero
+    let zero = node.server.get("/api/blocks?limit=0").await.data::<Value>();
+    assert_eq!(zero["data"].as_array().unwrap().len(), 1);
+
+    let capped = node.server.get("/api/blocks?limit=1000").await.data::<Value>();
+    assert_eq!(capped["pagination"]["limit"], 100);
+
+    let overflow = node.server.get(&format!("/api/blocks?page={}&limit=100", u64::MAX)).await;
+    overflow.assert_status_ok();
+
+    let bad_query = node.server.get("/api/blocks?page=minus-one").await;
+    bad_query.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_pending_transactions_empty() {
+    let node = TestNode::start().await;
+
+    let pending = node.server.get("/api/transactions/pending?page=3").await;
+    pending.assert_status_ok();
+    let body = pending.data::<Value>();
+    assert!(body["data"].as_array().unwrap().is_empty());
+    assert_eq!(body["pagination"]["total"], 0);
+}
+
+#[tokio::test]
+async fn test_error_mapping() {
+    let node = TestNode::start().await;
+
+    let missing_height = node.server.get("/api/blocks/height/999").await;
+    missing_height.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(missing_height.error().code, ErrorCode::NotFound);
+
+    let bad_hash = node.server.get("/api/blocks/zzzz").await;
+    bad_hash.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(bad_hash.error().code, ErrorCode::InvalidHash);
+
+    let unknown_tx = node.server.get(&format!("/api/transactions/{}", "00".repeat(32))).await;
+    unknown_tx.assert_status(StatusCode::NOT_FOUND);
+
+    let unknown_route = node.server.get("/api/does-not-exist").await;
+    unknown_route.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_response_envelope() {
+    let node = TestNode::start().await;
+
+    let info = node.server.get("/api/blockchain/info").add_header("x-request-id", "trace-42").await;
+    assert_eq!(info.header("x-request-id"), "trace-42");
+    let body = info.json::<Value>();
+    assert_eq!(body["request_id"], "trace-42");
+    assert!(body["error"].is_null());
+    assert_eq!(body["data"]["height"], 1);
+    assert!(body["timestamp"].is_string());
+
+    // Without a usable id from the client the node assigns one
+    let generated = node.server.get("/health").add_header("x-request-id", "has spaces").await;
+    let id = generated.header("x-request-id").to_str().unwrap().to_string();
+    assert_ne!(id, "has spaces");
+    assert_eq!(generated.json::<Value>()["request_id"], id);
+
+    // Errors that no handler produced are enveloped too
+    let unknown_route = node.server.get("/api/does-not-exist").await;
+    unknown_route.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(unknown_route.error().code, ErrorCode::NotFound);
+    assert!(unknown_route.json::<Value>()["request_id"].is_string());
+
+    let wrong_method = node.server.delete("/api/blocks").await;
+    wrong_method.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(wrong_method.error().code, ErrorCode::MethodNotAllowed);
+
+    let bad_query = node.server.get("/api/blocks?page=minus-one").await;
+    bad_query.assert_status(StatusCode::BAD_REQUEST);
+    let error = bad_query.error();
+    assert_eq!(error.code, ErrorCode::InvalidRequest);
+    assert!(error.message.contains("query string"), "rejection text kept: {}", error.message);
+
+    let malformed = node.server.post("/api/blocks/submit").text("not json").await;
+    assert!(malformed.status_code().is_client_error());
+    assert_eq!(malformed.error().code, ErrorCode::InvalidRequest);
+}
+
+#[tokio::test]
+async fn test_watch_list() {
+    let node = TestNode::start().await;
+    let address = miner_address().to_string();
+
+    let added = node.server.post("/api/watch").json(&json!({ "address": address, "label": "miner" })).await;
+    added.assert_status(StatusCode::CREATED);
+    assert_eq!(added.data::<Value>()["label"], "miner");
+
+    node.mine_blocks(1).await;
+    let watched = node.server.get(&format!("/api/watch/{}", address)).await;
+    watched.assert_status_ok();
+    let watched = watched.data::<Value>();
+    assert_eq!(watched["address"], address);
+    assert_eq!(watched["utxo_count"], 2);
+
+    let list = node.server.get("/api/watch").await.data::<Value>();
+    assert_eq!(list.as_array().unwrap().len(), 1);
+
+    node.server.delete(&format!("/api/watch/{}", address)).await.assert_status(StatusCode::NO_CONTENT);
+    node.server.get(&format!("/api/watch/{}", address)).await.assert_status(StatusCode::NOT_FOUND);
+    node.server.delete(&format!("/api/watch/{}", address)).await.assert_status(StatusCode::NOT_FOUND);
+
+    let invalid = node.server.post("/api/watch").json(&json!({ "address": "nope" })).await;
+    assert_eq!(invalid.error().code, ErrorCode::InvalidAddress);
+}
+
+#[tokio::test]
+async fn test_peer_bans() {
+    let node = TestNode::start().await;
+    let peer = "10.0.0.1:8333";
+    node.state.peers.misbehaved(peer.parse().unwrap(), BAN_SCORE_THRESHOLD, "invalid block").unwrap();
+
+    let peers = node.server.get("/admin/peers").await.data::<Value>();
+    assert_eq!(peers[0]["address"], peer);
+    assert_eq!(peers[0]["ban_reason"], "invalid block");
+
+    node.server.delete(&format!("/admin/peers/{}/ban", peer)).await.assert_status(StatusCode::NO_CONTENT);
+    node.server.delete(&format!("/admin/peers/{}/ban", peer)).await.assert_status(StatusCode::NOT_FOUND);
+    assert!(!node.state.peers.is_banned(&peer.parse().unwrap()));
+
+    let address = node.server.server_address().expect("http transport");
+    let client = ledgerdb_client::Client::new(address.as_str());
+    let request = BanRequest { duration: Some(60), reason: Some("spam".to_string()) };
+    let banned = client.ban_peer("10.0.0.2:8333", &request).await.unwrap();
+    assert_eq!(banned.ban_reason.as_deref(), Some("spam"));
+    assert_eq!(client.peers().await.unwrap().len(), 2);
+    client.unban_peer("10.0.0.2:8333").await.unwrap();
+
+    let invalid = node.server.post("/admin/peers/nope/ban").json(&json!({})).await;
+    assert_eq!(invalid.error().code, ErrorCode::InvalidRequest);
+    let zero = node.server.post(&format!("/admin/peers/{}/ban", peer)).json(&json!({ "duration": 0 })).await;
+    assert_eq!(zero.error().code, ErrorCode::ValidationError);
+}
+
+#[tokio::test]
+async fn test_client_decodes_responses() {
+    let node = TestNode::start().await;
+    let mined = node.mine_blocks(2).await;
+    let address = node.server.server_address().expect("http transport");
+    let client = ledgerdb_client::Client::new(address.as_str());
+
+    // Shared wire types must encode blocks exactly as the node does
+    let block = client.block_by_height(2).await.unwrap();
+    assert_eq!(serde_json::to_value(&block.block).unwrap(), serde_json::to_value(&mined[1]).unwrap());
+    assert_eq!(block.chain_work, format_chain_work(6));
+
+    let page = client.blocks(&Default::default()).await.unwrap();
+    assert_eq!(page.pagination.total, 3);
+    assert_eq!(page.data[0].block.index, 2);
+
+    let stats = client.blockchain_stats().await.unwrap();
+    assert_eq!(stats.blockchain.height, 3);
+    assert_eq!(client.blockchain_info().await.unwrap().height, 3);
+    assert_eq!(client.version().await.unwrap().api_version, "v1");
+    assert_eq!(client.headers(Some(1), None).await.unwrap().headers.len(), 2);
+    assert!(client.orphan_blocks().await.unwrap().orphans.is_empty());
+
+    let error = client.block_by_height(10).await.unwrap_err();
+    assert!(error.is_not_found());
+}
+
+#[tokio::test]
+async fn test_openapi_document() {
+    let node = TestNode::start().await;
+
+    let spec = node.server.get("/api/openapi.json").await;
+    spec.assert_status_ok();
+    let spec = spec.json::<Value>();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    for path in ["/health", "/api/blocks/height/{height}", "/api/blocks/submit", "/api/watch/{address}"] {
+        assert!(spec["paths"][path].is_object(), "missing {}", path);
+    }
+    assert!(spec["paths"]["/api/blocks/submit"]["post"]["responses"]["202"].is_object());
+    assert!(spec["components"]["schemas"]["BlockSubmitResponse"].is_object());
+    assert!(spec["components"]["schemas"]["ErrorCode"].is_object());
+
+    let docs = node.server.get("/api/docs/").await;
+    docs.assert_status_ok();
+    assert!(docs.text().contains("swagger"));
+}
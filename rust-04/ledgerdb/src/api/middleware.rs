@@ -3,28 +3,71 @@
 //! This module provides middleware for request logging, rate limiting, authentication,
 //! CORS handling, and other cross-cutting concerns.
 
+use super::{ApiConfig, ApiError, AppState, ClientIp};
+use crate::auth::{constant_time_eq, Role, Session};
+use crate::peers::PeerStore;
+use crate::tenants::split_tenant_path;
 use axum::{
-    extract::Request,
-    http::{HeaderMap, Method, StatusCode},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use chrono::{DateTime, Utc};
+use ledgerdb_types::{ErrorCode, REQUEST_ID_HEADER};
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     time::{Duration, Instant},
 };
-use tokio::time::sleep;
+use tower::{timeout::Timeout, ServiceExt};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+tokio::task_local! {
+    /// Id of the request being handled, set by [`request_logging_middleware`]
+    static REQUEST_ID: String;
+}
+
+/// Longest client-supplied request id that is accepted rather than replaced
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Largest plain-text error body that [`error_envelope_middleware`] keeps as the message
+const MAX_ERROR_BODY: usize = 16 * 1024;
+
+/// Routes that may stream a large download or build one, with everything below
+/// them, and get [`RequestTimeouts::export`]
+const EXPORT_ROUTES: &[&str] = &["/api/snapshot/utxo/download", "/api/snapshot/utxo/manifest", "/api/snapshot/utxo/chunks"];
+
+/// Id of the request being handled, if called inside [`request_logging_middleware`]
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// The client's `x-request-id`, if it is short and printable enough to log
+fn client_request_id(headers: &HeaderMap) -> Option<String> {
+    let id = headers.get(REQUEST_ID_HEADER)?.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+    valid.then(|| id.to_string())
+}
+
 /// Request logging middleware
+///
+/// Assigns the request its id (the client's `x-request-id` when usable), makes it
+/// available to handlers through [`current_request_id`] and echoes it in the
+/// response's `x-request-id` header.
 pub async fn request_logging_middleware(
     request: Request,
     next: Next,
 ) -> Response {
     let start_time = Instant::now();
-    let request_id = Uuid::new_v4().to_string();
+    let request_id = client_request_id(request.headers()).unwrap_or_else(|| Uuid::new_v4().to_string());
     let method = request.method().clone();
     let uri = request.uri().clone();
     let headers = request.headers().clone();
@@ -47,7 +90,10 @@ pub async fn request_logging_middleware(
     );
     
     // Process the request
-    let response = next.run(request).await;
+    let mut response = REQUEST_ID.scope(request_id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
     
     let duration = start_time.elapsed();
     let status = response.status();
@@ -80,100 +126,235 @@ pub async fn request_logging_middleware(
     response
 }
 
-/// Rate limiting middleware
-pub async fn rate_limiting_middleware(
+/// Wrap error responses that no handler produced in the response envelope
+///
+/// Unknown routes, wrong methods, extractor rejections and errors returned by other
+/// middleware come back with plain-text (or empty) bodies; this replaces them with
+/// an `ApiError` whose code follows the status, keeping the status and headers.
+pub async fn error_envelope_middleware(
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    // Simple in-memory rate limiter
-    // In production, you'd want to use Redis or a more sophisticated solution
-    static RATE_LIMITER: Mutex<Option<Arc<RateLimiter>>> = Mutex::new(None);
-    
-    let rate_limiter = {
-        let mut guard = RATE_LIMITER.lock().unwrap();
-        if guard.is_none() {
-            *guard = Some(Arc::new(RateLimiter::new(100, Duration::from_secs(60))));
-        }
-        guard.as_ref().unwrap().clone()
+) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let text = axum::body::to_bytes(body, MAX_ERROR_BODY)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+        .unwrap_or_default();
+    let message = if text.is_empty() {
+        status.canonical_reason().unwrap_or("Request failed").to_string()
+    } else {
+        text
     };
-    
-    // For now, skip rate limiting since we can't easily extract IP from request
-    // In production, you'd want to implement proper IP extraction
-    // if !rate_limiter.check_rate_limit(addr.ip().to_string()).await {
-    //     warn!("Rate limit exceeded for {}", addr.ip());
-    //     return Err(StatusCode::TOO_MANY_REQUESTS);
-    // }
-    
-    Ok(next.run(request).await)
+
+    let envelope = ApiError::new(ErrorCode::from_http_status(status.as_u16()), message).into_response();
+    let mut response = Response::from_parts(parts, envelope.into_body());
+    response.headers_mut().remove(header::CONTENT_LENGTH);
+    response.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
 }
 
-/// Authentication middleware (placeholder)
-pub async fn auth_middleware(
+/// How long each kind of request may take to produce its response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    /// Writes and anything else without a timeout of its own
+    pub default: Duration,
+    /// `GET` and `HEAD` requests
+    pub read: Duration,
+    /// Export downloads
+    pub export: Duration,
+}
+
+impl RequestTimeouts {
+    /// The timeouts set in `config`
+    pub fn new(config: &ApiConfig) -> Self {
+        Self {
+            default: Duration::from_secs(config.request_timeout),
+            read: Duration::from_secs(config.read_timeout),
+            export: Duration::from_secs(config.export_timeout),
+        }
+    }
+
+    /// Timeout for a `method` request to `path`
+    pub fn for_request(&self, method: &Method, path: &str) -> Duration {
+        if path_under(path, EXPORT_ROUTES) {
+            self.export
+        } else if method == Method::GET || method == Method::HEAD {
+            self.read
+        } else {
+            self.default
+        }
+    }
+}
+
+/// Answer with `408 Request Timeout` when the handler is not done within the
+/// route's timeout
+///
+/// The timeout covers producing the response, not streaming its body.
+pub async fn timeout_middleware(
+    State(timeouts): State<RequestTimeouts>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    // Check for API key or JWT token
-    let _auth_header = request.headers().get("authorization");
-    
-    // For now, we'll allow all requests
-    // TODO: Implement proper authentication
-    
-    Ok(next.run(request).await)
+) -> Response {
+    let limit = timeouts.for_request(request.method(), request.uri().path());
+    match Timeout::new(next, limit).oneshot(request).await {
+        Ok(response) => response,
+        // `next` itself never fails, so the only error is the timeout
+        Err(_) => ApiError::new(
+            ErrorCode::RequestTimeout,
+            format!("Request did not complete within {}s", limit.as_secs()),
+        )
+        .into_response(),
+    }
 }
 
-/// CORS middleware (handled by tower-http, but this is a custom implementation)
-pub async fn cors_middleware(
+/// Misbehavior score for going over the rate limit, charged once per window; five
+/// windows over the limit get a client banned
+const RATE_LIMIT_SCORE: u32 = 20;
+
+/// Refuse clients over the process-wide rate limit with `429 RATE_LIMITED`, and
+/// banned clients with `403 FORBIDDEN`, closing their connections.
+///
+/// Clients are told apart by their [`ClientIp`], so this must run inside
+/// [`access_middleware`](super::access_middleware); requests without one (on the
+/// Unix socket) are not limited. Each window in which a client goes over the limit
+/// is charged to it in `peers`, under its IP with port `0`.
+pub async fn rate_limiting_middleware(
+    State(peers): State<Arc<PeerStore>>,
     request: Request,
     next: Next,
 ) -> Response {
-    let method = request.method().clone();
-    let mut response = next.run(request).await;
-    
-    // Add CORS headers
-    let response_headers = response.headers_mut();
-    
-    response_headers.insert(
-        "access-control-allow-origin",
-        "*".parse().unwrap(),
-    );
-    
-    response_headers.insert(
-        "access-control-allow-methods",
-        "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap(),
-    );
-    
-    response_headers.insert(
-        "access-control-allow-headers",
-        "content-type, authorization, x-request-id".parse().unwrap(),
-    );
-    
-    response_headers.insert(
-        "access-control-max-age",
-        "86400".parse().unwrap(),
-    );
-    
-    // Handle preflight requests
-    if method == Method::OPTIONS {
-        *response.status_mut() = StatusCode::NO_CONTENT;
+    limit_rate(global_rate_limiter(), &peers, request, next).await
+}
+
+async fn limit_rate(limiter: &RateLimiter, peers: &PeerStore, request: Request, next: Next) -> Response {
+    let Some(&ClientIp(ip)) = request.extensions().get::<ClientIp>() else {
+        return next.run(request).await;
+    };
+    let client = SocketAddr::new(ip, 0);
+    if peers.is_banned(&client) {
+        return disconnect(ApiError::new(ErrorCode::Forbidden, "Client is banned").into_response());
     }
-    
+
+    match limiter.admit(&ip.to_string()) {
+        Admission::Allowed => next.run(request).await,
+        Admission::Refused { first } => {
+            if first {
+                warn!("Rate limit exceeded for {}", ip);
+                if let Err(e) = peers.misbehaved(client, RATE_LIMIT_SCORE, "exceeded the API rate limit") {
+                    warn!("Failed to record misbehavior of {}: {}", ip, e);
+                }
+            }
+            disconnect(
+                ApiError::new(
+                    ErrorCode::RateLimited,
+                    format!("More than {} requests per minute", limiter.max_requests()),
+                )
+                .into_response(),
+            )
+        }
+    }
+}
+
+/// `response` with `Connection: close`, so the connection is dropped once it is sent
+fn disconnect(mut response: Response) -> Response {
+    response.headers_mut().insert(header::CONNECTION, HeaderValue::from_static("close"));
     response
 }
 
-/// Request timeout middleware
-pub async fn timeout_middleware(
-    request: Request,
+/// Routes anyone may call without logging in, with everything below them
+const PUBLIC_ROUTES: &[&str] = &["/health", "/version", "/api/health", "/api/auth/login", "/api/docs", "/api/openapi.json", "/static"];
+
+/// Routes that need the admin role, with everything below them
+const ADMIN_ROLE_ROUTES: &[&str] = &["/admin", "/api/users"];
+
+/// `POST` routes that only read, or only end the caller's own session
+const VIEWER_WRITE_ROUTES: &[&str] = &["/api/auth/logout", "/api/batch", "/api/transactions/validate"];
+
+/// Whether `path` is one of `routes` or below one of them
+pub(super) fn path_under(path: &str, routes: &[&str]) -> bool {
+    routes.iter().any(|route| {
+        path.strip_prefix(route).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// The role a `method` request to `path` needs, or `None` for public routes
+///
+/// Reads need [`Role::Viewer`], anything that changes the node
+/// [`Role::Operator`], and user management and `/admin` [`Role::Admin`]. A
+/// request for a hosted chain needs what the same request to the host would.
+pub fn required_role(method: &Method, path: &str) -> Option<Role> {
+    let path = split_tenant_path(path).map_or(path, |(_, path)| path);
+    if path == "/" || path_under(path, PUBLIC_ROUTES) {
+        None
+    } else if path_under(path, ADMIN_ROLE_ROUTES) {
+        Some(Role::Admin)
+    } else if method == Method::GET || method == Method::HEAD || path_under(path, VIEWER_WRITE_ROUTES) {
+        Some(Role::Viewer)
+    } else {
+        Some(Role::Operator)
+    }
+}
+
+/// The token of an `Authorization: Bearer <token>` header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim()).filter(|token| !token.is_empty())
+}
+
+/// Require a login token whose role covers the route, when `api.enable_auth` is set
+///
+/// The configured `api_key` is accepted as an admin token. The caller's
+/// [`Session`] is added to the request extensions for the handlers.
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let timeout_duration = Duration::from_secs(30); // 30 second timeout
-    
-    match tokio::time::timeout(timeout_duration, next.run(request)).await {
-        Ok(response) => Ok(response),
-        Err(_) => {
-            error!("Request timed out after {:?}", timeout_duration);
-            Err(StatusCode::REQUEST_TIMEOUT)
+) -> Response {
+    if !state.config.enable_auth {
+        return next.run(request).await;
+    }
+    let Some(required) = required_role(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let Some(token) = bearer_token(request.headers()) else {
+        return ApiError::new(
+            ErrorCode::Unauthorized,
+            "Log in with POST /api/auth/login and send the token as `Authorization: Bearer <token>`",
+        )
+        .into_response();
+    };
+    let is_api_key = state.config.api_key.as_deref().is_some_and(|key| constant_time_eq(key.as_bytes(), token.as_bytes()));
+    let session = if is_api_key {
+        Session { username: "api-key".to_string(), role: Role::Admin, expires_at: DateTime::<Utc>::MAX_UTC }
+    } else {
+        match state.users.session(token) {
+            Some(session) => session,
+            None => return ApiError::new(ErrorCode::Unauthorized, "Token is invalid or has expired").into_response(),
         }
+    };
+
+    if session.role < required {
+        return ApiError::new(
+            ErrorCode::Forbidden,
+            format!("{} has the {} role; this needs {}", session.username, session.role.as_str(), required.as_str()),
+        )
+        .into_response();
     }
+    request.extensions_mut().insert(session);
+    next.run(request).await
 }
 
 /// Request size limiting middleware
@@ -235,11 +416,28 @@ pub async fn security_headers_middleware(
     response
 }
 
+/// Rate limiter shared by every router in the process
+fn global_rate_limiter() -> &'static RateLimiter {
+    static RATE_LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+    RATE_LIMITER.get_or_init(|| RateLimiter::new(100, Duration::from_secs(60)))
+}
+
+/// Change the API rate limit (requests per minute per client; `None` for unlimited)
+/// without restarting
+pub fn set_rate_limit(requests_per_minute: Option<u32>) {
+    global_rate_limiter().set_max_requests(requests_per_minute.unwrap_or(u32::MAX));
+}
+
+/// Current API rate limit in requests per minute per client
+pub fn rate_limit() -> u32 {
+    global_rate_limiter().max_requests()
+}
+
 /// Simple in-memory rate limiter
 #[derive(Debug)]
 pub struct RateLimiter {
     /// Maximum requests per window
-    max_requests: u32,
+    max_requests: AtomicU32,
     /// Time window duration
     window_duration: Duration,
     /// Request counts per client
@@ -252,13 +450,23 @@ struct ClientRateLimit {
     count: u32,
     /// Window start time
     window_start: Instant,
+    /// Whether a request was refused in the current window
+    refused: bool,
+}
+
+/// Whether [`RateLimiter::admit`] let a request through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Allowed,
+    /// Over the limit; `first` is set for the client's first refusal in the window
+    Refused { first: bool },
 }
 
 impl RateLimiter {
     /// Create a new rate limiter
     pub fn new(max_requests: u32, window_duration: Duration) -> Self {
         Self {
-            max_requests,
+            max_requests: AtomicU32::new(max_requests),
             window_duration,
             clients: Arc::new(Mutex::new(HashMap::new())),
         }
@@ -266,29 +474,48 @@ impl RateLimiter {
     
     /// Check if a client is within rate limits
     pub async fn check_rate_limit(&self, client_id: String) -> bool {
+        self.admit(&client_id) == Admission::Allowed
+    }
+
+    /// Count a request from `client_id` if it is within the limit
+    pub fn admit(&self, client_id: &str) -> Admission {
         let now = Instant::now();
         let mut clients = self.clients.lock().unwrap();
         
-        let client_limit = clients.entry(client_id.clone()).or_insert(ClientRateLimit {
+        let client_limit = clients.entry(client_id.to_string()).or_insert(ClientRateLimit {
             count: 0,
             window_start: now,
+            refused: false,
         });
         
         // Check if we need to reset the window
         if now.duration_since(client_limit.window_start) >= self.window_duration {
             client_limit.count = 0;
             client_limit.window_start = now;
+            client_limit.refused = false;
         }
         
         // Check if client is within limits
-        if client_limit.count >= self.max_requests {
-            false
+        if client_limit.count >= self.max_requests() {
+            let first = !client_limit.refused;
+            client_limit.refused = true;
+            Admission::Refused { first }
         } else {
             client_limit.count += 1;
-            true
+            Admission::Allowed
         }
     }
     
+    /// Maximum requests per client per window
+    pub fn max_requests(&self) -> u32 {
+        self.max_requests.load(Ordering::Relaxed)
+    }
+
+    /// Change the limit; counts already made in the current window still apply
+    pub fn set_max_requests(&self, max_requests: u32) {
+        self.max_requests.store(max_requests, Ordering::Relaxed);
+    }
+
     /// Clean up expired entries (should be called periodically)
     pub async fn cleanup_expired(&self) {
         let now = Instant::now();
@@ -321,6 +548,12 @@ pub struct ApiKeyInfo {
     pub last_used: Option<Instant>,
 }
 
+impl Default for ApiKeyValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ApiKeyValidator {
     /// Create a new API key validator
     pub fn new() -> Self {
@@ -465,9 +698,108 @@ mod tests {
         tokio::time::sleep(Duration::from_secs(1)).await;
         
         // Should pass again
+        assert!(limiter.check_rate_limit(client_id.clone()).await);
+
+        // Raising the limit lets the same window continue
+        limiter.set_max_requests(3);
+        assert!(limiter.check_rate_limit(client_id.clone()).await);
         assert!(limiter.check_rate_limit(client_id).await);
     }
     
+    /// Status of a `GET /api/blocks` from `peer` through [`limit_rate`]
+    async fn get_blocks(limiter: &Arc<RateLimiter>, peers: &Arc<PeerStore>, peer: &str) -> StatusCode {
+        let response = request_blocks(limiter, peers, peer).await;
+        let closes = response.headers().get(header::CONNECTION) == Some(&HeaderValue::from_static("close"));
+        // Refused clients are disconnected; served ones keep their connection
+        assert_eq!(closes, response.status() != StatusCode::OK);
+        response.status()
+    }
+
+    /// `GET /api/blocks` from `peer` through [`limit_rate`]
+    async fn request_blocks(limiter: &Arc<RateLimiter>, peers: &Arc<PeerStore>, peer: &str) -> Response {
+        use super::super::access_middleware;
+        use axum::{body::Body, extract::connect_info::MockConnectInfo, middleware::{from_fn, from_fn_with_state}, routing::get, Router};
+
+        let (limiter, peers) = (limiter.clone(), peers.clone());
+        let app = Router::new()
+            .route("/api/blocks", get(|| async { "ok" }))
+            .layer(from_fn(move |request: Request, next: Next| {
+                let (limiter, peers) = (limiter.clone(), peers.clone());
+                async move { limit_rate(&limiter, &peers, request, next).await }
+            }))
+            .layer(from_fn_with_state(Arc::new(crate::config::AccessConfig::default()), access_middleware))
+            .layer(MockConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        app.oneshot(Request::get("/api/blocks").body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_by_client_ip() {
+        let limiter = Arc::new(RateLimiter::new(2, Duration::from_secs(60)));
+        let peers = Arc::new(PeerStore::new(None).unwrap());
+
+        // The limit is per client, whatever port it connects from
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5000").await, StatusCode::OK);
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5001").await, StatusCode::OK);
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5002").await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.2:5000").await, StatusCode::OK);
+
+        // Going over the limit is charged once per window
+        let client = SocketAddr::new("192.0.2.1".parse().unwrap(), 0);
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5003").await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(peers.get(&client).unwrap().misbehavior_score, RATE_LIMIT_SCORE);
+        assert!(peers.get(&SocketAddr::new("192.0.2.2".parse().unwrap(), 0)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_rate_violations_ban_the_client() {
+        let window = Duration::from_millis(100);
+        let limiter = Arc::new(RateLimiter::new(1, window));
+        let peers = Arc::new(PeerStore::new(None).unwrap());
+        let client = SocketAddr::new("192.0.2.1".parse().unwrap(), 0);
+
+        let windows = crate::utils::constants::BAN_SCORE_THRESHOLD.div_ceil(RATE_LIMIT_SCORE);
+        for _ in 0..windows {
+            assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5000").await, StatusCode::OK);
+            assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5000").await, StatusCode::TOO_MANY_REQUESTS);
+            tokio::time::sleep(window).await;
+        }
+
+        // Banned: refused even in a fresh window, while other clients are served
+        assert!(peers.is_banned(&client));
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.1:5001").await, StatusCode::FORBIDDEN);
+        assert_eq!(get_blocks(&limiter, &peers, "192.0.2.2:5000").await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_request_timeouts_by_route() {
+        let timeouts = RequestTimeouts::new(&ApiConfig::default());
+        assert_eq!(timeouts.for_request(&Method::GET, "/api/blocks"), Duration::from_secs(10));
+        assert_eq!(timeouts.for_request(&Method::HEAD, "/api/blocks"), Duration::from_secs(10));
+        assert_eq!(timeouts.for_request(&Method::POST, "/api/transactions"), Duration::from_secs(30));
+        assert_eq!(timeouts.for_request(&Method::GET, "/api/snapshot/utxo/download"), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_required_roles() {
+        assert_eq!(required_role(&Method::GET, "/health"), None);
+        assert_eq!(required_role(&Method::POST, "/api/auth/login"), None);
+        assert_eq!(required_role(&Method::GET, "/api/blocks/latest"), Some(Role::Viewer));
+        assert_eq!(required_role(&Method::POST, "/api/batch"), Some(Role::Viewer));
+        assert_eq!(required_role(&Method::POST, "/api/transactions"), Some(Role::Operator));
+        assert_eq!(required_role(&Method::GET, "/api/users"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::GET, "/admin/tasks"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::POST, "/t/school/api/auth/login"), None);
+        assert_eq!(required_role(&Method::POST, "/t/school/api/users"), Some(Role::Admin));
+        // Only whole path segments match
+        assert_eq!(required_role(&Method::GET, "/healthz"), Some(Role::Viewer));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer abc123"));
+        assert_eq!(bearer_token(&headers), Some("abc123"));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic abc123"));
+        assert_eq!(bearer_token(&headers), None);
+    }
+
     #[test]
     fn test_api_key_validator() {
         let validator = ApiKeyValidator::new();
@@ -3,51 +3,138 @@
 //! This module provides REST API endpoints for interacting with the blockchain,
 //! including block retrieval, transaction management, mining operations, and WebSocket support.
 
+mod access;
+mod balance_history;
+mod frontend;
 mod handlers;
+mod holder_stats;
 mod middleware;
+mod openapi;
 mod responses;
+mod router;
+mod shutdown;
+mod snapshot_chunks;
+mod tls;
+mod unix_socket;
 mod websocket;
 
+pub use access::*;
+pub use balance_history::*;
+pub use frontend::*;
 pub use handlers::*;
+pub use holder_stats::*;
 pub use middleware::*;
+pub use openapi::*;
 pub use responses::*;
+pub use router::*;
+pub use shutdown::*;
+pub use snapshot_chunks::*;
+pub use tls::*;
+pub use unix_socket::*;
 pub use websocket::*;
 
+/// Pagination, shared with `ledgerdb-client`
+pub use ledgerdb_types::{
+    paginate, paginate_window, Cursor, CursorDirection, PageWindow, PaginatedResponse,
+    PaginationMeta, PaginationParams,
+};
+/// The response envelope and its error codes, also shared with `ledgerdb-client`
+pub use ledgerdb_types::{ApiErrorBody, ApiResponse, ErrorCode, ErrorResponse, REQUEST_ID_HEADER};
+
+#[cfg(test)]
+pub(crate) mod test_support;
+#[cfg(test)]
+mod integration_tests;
+
 
 use crate::core::Blockchain;
-use crate::crypto::pow::{MiningProgress, ProofOfWorkMiner};
-use crate::error::Result;
-use crate::storage::PersistentStorage;
-use axum::{
-    extract::DefaultBodyLimit,
-    http::{header, Method, StatusCode},
-    middleware::from_fn,
-    response::Json,
-    routing::{get, post},
-    Router,
-};
+use crate::crypto::pow::{MinerEvents, ProofOfWorkMiner};
+use crate::storage::{AsyncStorage, PersistentStorage};
+use crate::auth::UserStore;
+use crate::tasks::TaskManager;
+use crate::tenants::TenantManager;
+use crate::p2p::Reachability;
+use crate::peers::PeerStore;
+use crate::sync::SyncProgress;
+use crate::watch::WatchList;
+use axum::{http::StatusCode, response::Json};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tower::ServiceBuilder;
-use tower_http::{
-    cors::{Any, CorsLayer},
-    trace::TraceLayer,
-};
+use tokio::sync::RwLock;
 
 /// Shared application state
 #[derive(Debug, Clone)]
 pub struct AppState {
     /// Blockchain instance
     pub blockchain: Arc<RwLock<Blockchain>>,
-    /// Persistent storage
-    pub storage: Arc<PersistentStorage>,
-    /// Mining progress broadcaster
-    pub mining_progress_tx: broadcast::Sender<MiningProgress>,
+    /// Persistent storage, called from handlers on a bounded pool of blocking threads
+    pub storage: AsyncStorage,
+    /// Events from the node's miner, for the mining status endpoint and WebSocket
+    pub miner_events: MinerEvents,
     /// Proof-of-work miner
     pub miner: Arc<RwLock<Option<ProofOfWorkMiner>>>,
     /// API configuration
     pub config: ApiConfig,
+    /// Server shutdown signal, used to close WebSockets cleanly
+    pub shutdown: Shutdown,
+    /// Supervisor for background tasks
+    pub tasks: Arc<TaskManager>,
+    /// Addresses whose activity is reported to WebSocket subscribers and webhooks
+    pub watch: Arc<WatchList>,
+    /// Address balance histories computed so far
+    pub balance_histories: Arc<BalanceHistoryCache>,
+    /// Rich list and supply distribution of the latest tip asked about
+    pub holder_stats: Arc<HolderStatsCache>,
+    /// Chunks of the latest UTXO snapshot downloaded in pieces
+    pub snapshot_chunks: Arc<SnapshotChunkCache>,
+    /// Open `/ws` connections and their subscriptions
+    pub websockets: Arc<WebSocketManager>,
+    /// User accounts and login sessions
+    pub users: Arc<UserStore>,
+    /// Known peers and their bans
+    pub peers: Arc<PeerStore>,
+    /// Initial block download progress
+    pub sync: Arc<SyncProgress>,
+    /// Addresses other nodes can reach this one on
+    pub p2p: Arc<Reachability>,
+    /// Chains hosted under `/t/{name}/`; `None` unless `tenants.enabled`, and
+    /// always for the tenants' own states
+    pub tenants: Option<Arc<TenantManager>>,
+}
+
+impl AppState {
+    /// State serving `blockchain`, with empty caches, no miner and its own shutdown
+    /// signal and task supervisor; the watch-list and peers are kept in `storage`
+    pub fn new(
+        blockchain: Blockchain,
+        storage: Arc<PersistentStorage>,
+        io_workers: usize,
+        config: ApiConfig,
+        users: Arc<UserStore>,
+        tenants: Option<Arc<TenantManager>>,
+    ) -> crate::Result<Self> {
+        let shutdown = Shutdown::new();
+        Ok(Self {
+            blockchain: Arc::new(RwLock::new(blockchain)),
+            storage: AsyncStorage::new(storage.clone(), io_workers),
+            miner_events: MinerEvents::new(100),
+            miner: Arc::new(RwLock::new(None)),
+            shutdown: shutdown.clone(),
+            tasks: Arc::new(TaskManager::new(shutdown)),
+            watch: Arc::new(WatchList::new(Some(storage.clone()))?),
+            balance_histories: Arc::new(BalanceHistoryCache::new()),
+            holder_stats: Arc::new(HolderStatsCache::new()),
+            snapshot_chunks: Arc::new(SnapshotChunkCache::new()),
+            websockets: Arc::new(WebSocketManager::new(config.max_websocket_connections)),
+            users,
+            peers: Arc::new(PeerStore::new(Some(storage))?),
+            sync: Arc::new(SyncProgress::new()),
+            p2p: Arc::new(Reachability::new()),
+            tenants,
+            config,
+        })
+    }
 }
 
 /// API configuration
@@ -55,16 +142,31 @@ pub struct AppState {
 pub struct ApiConfig {
     /// Maximum request body size
     pub max_body_size: usize,
-    /// Request timeout in seconds
+    /// Request timeout in seconds, for writes and anything without its own timeout
     pub request_timeout: u64,
+    /// Timeout in seconds for `GET` and `HEAD` requests
+    pub read_timeout: u64,
+    /// Timeout in seconds for export downloads
+    pub export_timeout: u64,
     /// Rate limiting: requests per minute
     pub rate_limit: u32,
-    /// Enable CORS
+    /// Answer cross-origin requests from the origins in `cors`
     pub enable_cors: bool,
+    /// Allowed origins, with the environment's default already filled in (see
+    /// [`crate::config::Config::cors`])
+    pub cors: crate::config::CorsConfig,
     /// Enable request logging
     pub enable_logging: bool,
     /// WebSocket connection limit
     pub max_websocket_connections: usize,
+    /// Most queries accepted in one `POST /api/batch`
+    pub max_batch_size: usize,
+    /// Trusted proxies and who may reach the admin and mining endpoints
+    pub access: crate::config::AccessConfig,
+    /// Require a login token (or `api_key`) with a sufficient role on every route
+    pub enable_auth: bool,
+    /// Static key accepted as an admin bearer token
+    pub api_key: Option<String>,
     /// API version
     pub version: String,
 }
@@ -74,59 +176,63 @@ impl Default for ApiConfig {
         Self {
             max_body_size: 1024 * 1024, // 1MB
             request_timeout: 30,
+            read_timeout: 10,
+            export_timeout: 300,
             rate_limit: 100,
             enable_cors: true,
+            cors: crate::config::CorsConfig::default(),
             enable_logging: true,
             max_websocket_connections: 100,
+            max_batch_size: responses::DEFAULT_MAX_BATCH_SIZE,
+            access: crate::config::AccessConfig::default(),
+            enable_auth: false,
+            api_key: None,
             version: "1.0.0".to_string(),
         }
     }
 }
 
-/// Create the main API router
-pub fn create_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
-        .allow_origin(Any);
-
-    let middleware_stack = ServiceBuilder::new()
-        .layer(TraceLayer::new_for_http())
-        .layer(cors)
-        .layer(DefaultBodyLimit::max(state.config.max_body_size))
-        .layer(from_fn(request_logging_middleware))
-        .layer(from_fn(rate_limiting_middleware));
-
-    Router::new()
-        // Health and info endpoints
-        .route("/health", get(health_check))
-        .route("/version", get(get_api_version))
-        
-        .layer(middleware_stack)
-        .with_state(state)
+impl ApiConfig {
+    /// The API settings from a node's configuration
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self {
+            max_body_size: config.api.max_request_size,
+            request_timeout: config.server.request_timeout,
+            read_timeout: config.server.read_timeout,
+            export_timeout: config.server.export_timeout,
+            max_batch_size: config.api.max_batch_size,
+            access: config.api.access.clone(),
+            enable_auth: config.api.enable_auth,
+            api_key: config.api.api_key.clone(),
+            max_websocket_connections: config.api.websocket.max_connections,
+            enable_cors: config.server.enable_cors,
+            cors: config.cors(),
+            ..Self::default()
+        }
+    }
 }
 
 /// API error response
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Sent as the `error` of the response envelope, with the HTTP status that the
+/// code documents.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ApiError {
     /// Error code
-    pub code: String,
+    pub code: ErrorCode,
     /// Error message
     pub message: String,
     /// Additional error details
     pub details: Option<serde_json::Value>,
-    /// Request ID for tracking
-    pub request_id: Option<String>,
 }
 
 impl ApiError {
     /// Create a new API error
-    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
         Self {
-            code: code.into(),
+            code,
             message: message.into(),
             details: None,
-            request_id: None,
         }
     }
 
@@ -135,12 +241,6 @@ impl ApiError {
         self.details = Some(details);
         self
     }
-
-    /// Add request ID to the error
-    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
-        self.request_id = Some(request_id.into());
-        self
-    }
 }
 
 /// Convert internal errors to API errors
@@ -148,136 +248,77 @@ impl From<crate::error::LedgerError> for ApiError {
     fn from(error: crate::error::LedgerError) -> Self {
         match error {
             crate::error::LedgerError::Validation(e) => {
-                ApiError::new("VALIDATION_ERROR", format!("Validation failed: {}", e))
-            }
-            crate::error::LedgerError::Blockchain(e) => {
-                ApiError::new("BLOCKCHAIN_ERROR", format!("Blockchain error: {}", e))
-            }
-            crate::error::LedgerError::Storage(e) => {
-                ApiError::new("STORAGE_ERROR", format!("Storage error: {}", e))
-            }
-            crate::error::LedgerError::Crypto(e) => {
-                ApiError::new("CRYPTO_ERROR", format!("Cryptographic error: {}", e))
-            }
-            crate::error::LedgerError::Mining(e) => {
-                ApiError::new("MINING_ERROR", format!("Mining error: {}", e))
-            }
-            crate::error::LedgerError::Api(e) => {
-                ApiError::new("API_ERROR", format!("API error: {}", e))
+                ApiError::new(ErrorCode::ValidationError, format!("Validation failed: {}", e))
             }
             crate::error::LedgerError::Config(e) => {
-                ApiError::new("CONFIG_ERROR", format!("Configuration error: {}", e))
+                ApiError::new(ErrorCode::ConfigError, format!("Configuration error: {}", e))
             }
             crate::error::LedgerError::Internal(e) => {
-                ApiError::new("INTERNAL_ERROR", format!("Internal error: {}", e))
+                ApiError::new(ErrorCode::InternalError, format!("Internal error: {}", e))
             }
             crate::error::LedgerError::Io(e) => {
-                ApiError::new("IO_ERROR", format!("I/O error: {}", e))
+                ApiError::new(ErrorCode::IoError, format!("I/O error: {}", e))
             }
             crate::error::LedgerError::Serialization(e) => {
-                ApiError::new("SERIALIZATION_ERROR", format!("Serialization error: {}", e))
+                ApiError::new(ErrorCode::SerializationError, format!("Serialization error: {}", e))
+            }
+            crate::error::LedgerError::Network(e) => {
+                ApiError::new(ErrorCode::ServiceUnavailable, format!("Network error: {}", e))
             }
+            crate::error::LedgerError::Database(e) => {
+                ApiError::new(ErrorCode::StorageError, format!("Storage error: {}", e))
+            }
+            crate::error::LedgerError::Parse(e) | crate::error::LedgerError::InvalidInput(e) => {
+                ApiError::new(ErrorCode::InvalidRequest, e)
+            }
+            crate::error::LedgerError::NotFound(e) => ApiError::new(ErrorCode::NotFound, e),
         }
     }
 }
 
-/// Convert API errors to HTTP responses
-impl axum::response::IntoResponse for ApiError {
-    fn into_response(self) -> axum::response::Response {
-        let status = match self.code.as_str() {
-            "VALIDATION_ERROR" => StatusCode::BAD_REQUEST,
-            "NOT_FOUND" => StatusCode::NOT_FOUND,
-            "UNAUTHORIZED" => StatusCode::UNAUTHORIZED,
-            "FORBIDDEN" => StatusCode::FORBIDDEN,
-            "RATE_LIMITED" => StatusCode::TOO_MANY_REQUESTS,
-            "INTERNAL_ERROR" => StatusCode::INTERNAL_SERVER_ERROR,
-            _ => StatusCode::BAD_REQUEST,
+/// Give the internal API error kinds their documented codes
+impl From<crate::error::ApiError> for ApiError {
+    fn from(error: crate::error::ApiError) -> Self {
+        use crate::error::ApiError as Kind;
+        let code = match &error {
+            Kind::InternalServerError(_) | Kind::NetworkError(_) => ErrorCode::InternalError,
+            Kind::BadRequest(_) => ErrorCode::InvalidRequest,
+            Kind::Unauthorized(_) => ErrorCode::Unauthorized,
+            Kind::NotFound(_) => ErrorCode::NotFound,
+            Kind::Conflict(_) => ErrorCode::Conflict,
+            Kind::ServiceUnavailable(_) => ErrorCode::ServiceUnavailable,
+            Kind::ValidationError(_) => ErrorCode::ValidationError,
+            Kind::BlockchainError(_) => ErrorCode::BlockchainError,
+            Kind::StorageError(_) => ErrorCode::StorageError,
+            Kind::MiningError(_) => ErrorCode::MiningError,
         };
-
-        (status, Json(self)).into_response()
+        ApiError::new(code, error.to_string())
     }
 }
 
-/// Pagination parameters
-#[derive(Debug, Deserialize)]
-pub struct PaginationParams {
-    /// Page number (0-based)
-    pub page: Option<u64>,
-    /// Number of items per page
-    pub limit: Option<u64>,
-    /// Sort order (asc/desc)
-    pub order: Option<String>,
-}
-
-impl Default for PaginationParams {
-    fn default() -> Self {
-        Self {
-            page: Some(0),
-            limit: Some(20),
-            order: Some("desc".to_string()),
+impl From<ApiError> for ApiErrorBody {
+    fn from(error: ApiError) -> Self {
+        ApiErrorBody {
+            code: error.code,
+            message: error.message,
+            details: error.details,
         }
     }
 }
 
-/// Paginated response wrapper
-#[derive(Debug, Serialize)]
-pub struct PaginatedResponse<T> {
-    /// Response data
-    pub data: Vec<T>,
-    /// Pagination metadata
-    pub pagination: PaginationMeta,
-}
-
-/// Pagination metadata
-#[derive(Debug, Serialize)]
-pub struct PaginationMeta {
-    /// Current page
-    pub page: u64,
-    /// Items per page
-    pub limit: u64,
-    /// Total number of items
-    pub total: u64,
-    /// Total number of pages
-    pub total_pages: u64,
-    /// Whether there's a next page
-    pub has_next: bool,
-    /// Whether there's a previous page
-    pub has_prev: bool,
-}
-
-impl PaginationMeta {
-    /// Create pagination metadata
-    pub fn new(page: u64, limit: u64, total: u64) -> Self {
-        let total_pages = (total + limit - 1) / limit; // Ceiling division
-        
-        Self {
-            page,
-            limit,
-            total,
-            total_pages,
-            has_next: page + 1 < total_pages,
-            has_prev: page > 0,
-        }
-    }
-}
+/// Convert API errors to HTTP responses
+impl axum::response::IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        let status = StatusCode::from_u16(self.code.http_status())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
-/// Create a paginated response
-pub fn paginate<T>(
-    data: Vec<T>,
-    page: u64,
-    limit: u64,
-    total: u64,
-) -> PaginatedResponse<T> {
-    PaginatedResponse {
-        data,
-        pagination: PaginationMeta::new(page, limit, total),
+        (status, Json(ErrorResponse::failure(self.into(), current_request_id()))).into_response()
     }
 }
 
 /// Request/Response types for API endpoints
 pub mod types {
     use super::*;
-    use crate::core::{Block, Transaction};
     use crate::crypto::{Address, Hash256};
     use chrono::{DateTime, Utc};
 
@@ -307,6 +348,9 @@ pub mod types {
         pub inputs: Vec<TransactionInputRequest>,
         pub outputs: Vec<TransactionOutputRequest>,
         pub fee: Option<u64>,
+        /// Text carried with the payment, such as an invoice reference
+        #[serde(default)]
+        pub memo: Option<String>,
     }
 
     /// Transaction input request
@@ -398,8 +442,8 @@ mod tests {
 
     #[test]
     fn test_api_error_creation() {
-        let error = ApiError::new("TEST_ERROR", "Test message");
-        assert_eq!(error.code, "TEST_ERROR");
+        let error = ApiError::new(ErrorCode::NotFound, "Test message");
+        assert_eq!(error.code, ErrorCode::NotFound);
         assert_eq!(error.message, "Test message");
         assert!(error.details.is_none());
     }
@@ -420,8 +464,11 @@ mod tests {
         let config = ApiConfig::default();
         assert_eq!(config.max_body_size, 1024 * 1024);
         assert_eq!(config.request_timeout, 30);
+        assert_eq!((config.read_timeout, config.export_timeout), (10, 300));
         assert_eq!(config.rate_limit, 100);
+        assert_eq!(config.max_batch_size, 100);
         assert!(config.enable_cors);
+        assert!(config.cors.allowed_origins.is_empty());
         assert!(config.enable_logging);
     }
 }
\ No newline at end of file
@@ -0,0 +1,106 @@
+//! OpenAPI description of the HTTP API.
+//!
+//! The document is generated from the `#[utoipa::path]` annotations on the
+//! handlers and the `ToSchema` derives on the request and response types, served
+//! as JSON at `/api/openapi.json` and browsable with Swagger UI at `/api/docs`.
+
+use super::{handlers, websocket, AppState};
+use axum::Router;
+use ledgerdb_types::{SubscriptionRequest, WsMessage};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Where the generated document is served
+pub const OPENAPI_JSON_PATH: &str = "/api/openapi.json";
+
+/// Where Swagger UI is served
+pub const SWAGGER_UI_PATH: &str = "/api/docs";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "LedgerDB API", description = "REST and WebSocket API of a LedgerDB node"),
+    paths(
+        handlers::health_check,
+        handlers::get_api_version,
+        handlers::get_blockchain_info,
+        handlers::get_blockchain_stats,
+        handlers::get_mining_difficulty,
+        handlers::get_mining_status,
+        handlers::get_reward,
+        handlers::get_checkpoints,
+        handlers::get_sync_status,
+        handlers::get_node_addresses,
+        handlers::get_rich_list,
+        handlers::get_supply_distribution,
+        handlers::audit_supply,
+        handlers::get_utxo_snapshot_info,
+        handlers::download_utxo_snapshot,
+        handlers::get_snapshot_manifest,
+        handlers::get_snapshot_chunk,
+        handlers::get_all_utxos,
+        handlers::get_blocks,
+        handlers::get_latest_block,
+        handlers::get_orphan_blocks,
+        handlers::submit_block,
+        handlers::submit_compact_block,
+        handlers::get_block_by_height,
+        handlers::get_block_by_hash,
+        handlers::get_block_transactions,
+        handlers::get_block_filter,
+        handlers::get_headers,
+        handlers::batch_query,
+        handlers::get_pending_transactions,
+        handlers::validate_transaction,
+        handlers::get_transaction_by_hash,
+        handlers::search_transactions,
+        handlers::get_transaction_merkle_proof,
+        handlers::verify_proof,
+        handlers::create_psbt,
+        handlers::combine_psbts,
+        handlers::finalize_psbt,
+        handlers::get_address_transactions,
+        handlers::get_balance_history,
+        handlers::list_watched_addresses,
+        handlers::add_watched_address,
+        handlers::get_watched_address,
+        handlers::remove_watched_address,
+        handlers::login,
+        handlers::logout,
+        handlers::list_users,
+        handlers::create_user,
+        handlers::delete_user,
+        handlers::get_task_health,
+        handlers::verify_storage,
+        handlers::repair_storage,
+        handlers::get_log_level,
+        handlers::set_log_level,
+        handlers::list_tenants,
+        handlers::create_tenant,
+        handlers::archive_tenant,
+        handlers::start_simulation,
+        handlers::list_peers,
+        handlers::ban_peer,
+        handlers::unban_peer,
+        websocket::mining_progress_websocket,
+        websocket::get_websocket_stats,
+    ),
+    components(schemas(SubscriptionRequest, WsMessage)),
+    tags(
+        (name = "node", description = "Health, version and the WebSocket feed"),
+        (name = "blockchain", description = "Chain state, rewards, checkpoints and UTXO snapshots"),
+        (name = "blocks", description = "Blocks, headers and block submission"),
+        (name = "transactions", description = "Transactions and merkle proofs"),
+        (name = "addresses", description = "Address history from the SQLite index"),
+        (name = "watch", description = "Address watch-list"),
+        (name = "auth", description = "Login sessions and user accounts"),
+        (name = "admin", description = "Node administration"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Routes serving the OpenAPI document and Swagger UI
+pub fn docs_router() -> Router<AppState> {
+    SwaggerUi::new(SWAGGER_UI_PATH)
+        .url(OPENAPI_JSON_PATH, ApiDoc::openapi())
+        .into()
+}
@@ -3,24 +3,107 @@
 //! This module defines common response formats, error handling, and serialization
 //! for the HTTP API endpoints.
 
-use crate::core::{Block, Transaction};
-use crate::crypto::{Address, Hash256};
+use super::{current_request_id, ApiError, ApiResponse};
+use crate::core::{Block, BlockHeader, BlockMetadata, Blockchain, PartiallySignedTransaction, PendingTransaction, RewardSchedule, SnapshotValidation, Transaction};
+use crate::crypto::{Address, Hash256, MerkleProof};
+use axum::Json;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::collections::HashMap;
 
-/// Health check response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct HealthResponse {
-    pub status: String,
-    pub timestamp: DateTime<Utc>,
-    pub version: String,
-    pub uptime: u64,
+pub use ledgerdb_types::{
+    format_chain_work, AddressBalance, BanRequest, CreateTenantRequest, CreateUserRequest, LoginRequest, LoginResponse, Role, SimulationParams, SimulationReport, SimulationStarted, TenantInfo, UserAccount, AddressHistoryParams, AddressTransactionResponse, BalanceGranularity, BalanceHistoryParams, BalanceHistoryResponse, BalancePoint, BatchQuery, BatchRequest, BlockParams, DEFAULT_MAX_BATCH_SIZE, BlockSubmitResponse, BlockVerbosity, RawBlockResponse, BlockchainStatsResponse, ChainStats,
+    DifficultyResponse, HeadersParams, HeadersResponse, HealthResponse, InputValidation, IntegrityReport, LogLevel, MemoSearchParams, MemoTransactionResponse, MiningStatusResponse, Network, NetworkStats, NodeAddresses, OrphanBlockResponse,
+    OrphanPoolResponse, PeerRecord, PsbtFinalizeResponse, PsbtRequest, PsbtResponse, RichListParams, RichListResponse, SnapshotManifest, SnapshotParams, StorageStats, SupplyAudit, SupplyDistributionResponse, SyncStatus, TransactionValidation, VerifyProofResponse, VersionResponse, WatchRequest, WebSocketStats,
+    WatchedAddressResponse,
+};
+
+/// A main-chain block with the cumulative work behind it
+pub type ChainBlockResponse = ledgerdb_types::ChainBlockResponse<Block>;
+
+/// Pair `block` with the chain work at its height
+pub fn chain_block_response(block: &Block, blockchain: &Blockchain) -> ChainBlockResponse {
+    ChainBlockResponse {
+        block: block.clone(),
+        chain_work: format_chain_work(blockchain.chain_work_at(block.index).unwrap_or(0)),
+    }
+}
+
+/// A main-chain block without its transaction bodies
+pub type BlockSummaryResponse = ledgerdb_types::BlockSummaryResponse<BlockHeader, BlockMetadata>;
+
+/// A main-chain block at the verbosity the client asked for
+pub type BlockView = ledgerdb_types::BlockView<Block, BlockHeader, BlockMetadata>;
+
+/// What a batch query found
+pub type BatchItem = ledgerdb_types::BatchItem<Block, BlockHeader, BlockMetadata, Transaction>;
+
+/// Answer to one batch query
+pub type BatchResult = ledgerdb_types::BatchResult<Block, BlockHeader, BlockMetadata, Transaction>;
+
+/// `POST /api/batch` response
+pub type BatchResponse = ledgerdb_types::BatchResponse<Block, BlockHeader, BlockMetadata, Transaction>;
+
+/// A mempool transaction with its fee, fee rate, size and time in the pool
+pub type PendingTransactionResponse = ledgerdb_types::PendingTransactionResponse<Transaction>;
+
+/// Request body for `POST /api/psbt`
+pub type CreatePsbtRequest = ledgerdb_types::CreatePsbtRequest<Transaction>;
+
+/// Request body for `POST /api/proofs/verify`
+pub type VerifyProofRequest = ledgerdb_types::VerifyProofRequest<Hash256, MerkleProof>;
+
+/// Describe `psbt` for the next signer
+pub fn psbt_response(psbt: &PartiallySignedTransaction) -> crate::error::Result<PsbtResponse> {
+    Ok(PsbtResponse {
+        psbt: psbt.to_hex()?,
+        signing_hash: psbt.signing_hash().to_hex(),
+        fee: psbt.fee(),
+        missing_signatures: psbt.missing_signatures(),
+    })
+}
+
+/// `pending` as `/api/transactions/pending` and mempool updates list it
+pub fn pending_transaction_response(pending: &PendingTransaction) -> PendingTransactionResponse {
+    let size = bincode::serialize(pending.transaction).map(|b| b.len()).unwrap_or(0);
+    let fee_rate = if size > 0 { pending.fee as f64 / size as f64 } else { 0.0 };
+    PendingTransactionResponse {
+        transaction: pending.transaction.clone(),
+        fee: pending.fee,
+        fee_rate,
+        size,
+        added_at: pending.added_at,
+        age_secs: pending.age.num_seconds().max(0) as u64,
+    }
+}
+
+/// `block` as the block endpoints return it at `verbosity`
+pub fn block_view(block: &Block, blockchain: &Blockchain, verbosity: BlockVerbosity) -> crate::error::Result<BlockView> {
+    let chain_work = format_chain_work(blockchain.chain_work_at(block.index).unwrap_or(0));
+    Ok(match verbosity {
+        BlockVerbosity::Raw => BlockView::Raw(RawBlockResponse {
+            hash: block.hash().to_hex(),
+            index: block.index,
+            hex: hex::encode(block.to_bytes()?),
+            chain_work,
+        }),
+        BlockVerbosity::Summary => BlockView::Summary(BlockSummaryResponse {
+            hash: block.hash().to_hex(),
+            index: block.index,
+            header: block.header.clone(),
+            metadata: block.metadata.clone(),
+            transaction_hashes: block.transactions().iter().map(|tx| tx.hash().to_hex()).collect(),
+            chain_work,
+        }),
+        BlockVerbosity::Full => BlockView::Full(chain_block_response(block, blockchain)),
+    })
 }
 
 /// Blockchain info response
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BlockchainInfoResponse {
+    pub network: Network,
     pub height: u64,
     pub latest_block_hash: Hash256,
     pub total_transactions: u64,
@@ -29,13 +112,78 @@ pub struct BlockchainInfoResponse {
     pub network_hash_rate: f64,
 }
 
-/// Mining status response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MiningStatusResponse {
-    pub is_mining: bool,
-    pub current_block_height: u64,
-    pub difficulty: u32,
-    pub hash_rate: f64,
+/// Block reward response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RewardResponse {
+    /// Height of the next block to be mined
+    pub height: u64,
+    /// Subsidy the next block may claim
+    pub current_reward: u64,
+    /// Subsidy after the next schedule change; `None` once the reward is final
+    pub next_reward: Option<u64>,
+    /// Height at which `next_reward` takes effect
+    pub next_change_height: Option<u64>,
+    /// The chain's reward schedule
+    pub schedule: RewardSchedule,
+}
+
+/// A pinned block hash
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CheckpointInfo {
+    pub height: u64,
+    pub hash: Hash256,
+    /// Whether the local chain has reached this height
+    pub reached: bool,
+}
+
+/// Checkpoints response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CheckpointsResponse {
+    /// Checkpoints in height order
+    pub checkpoints: Vec<CheckpointInfo>,
+    /// Height of the highest reached checkpoint; the chain never reorganizes below it
+    pub last_checkpoint: Option<u64>,
+}
+
+/// UTXO snapshot summary response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UtxoSnapshotResponse {
+    /// Height of the last block applied to the set
+    pub height: u64,
+    pub block_hash: Hash256,
+    /// Commitment hash over the set; equal on every node with the same chain
+    pub commitment: Hash256,
+    pub utxo_count: usize,
+    pub total_amount: u64,
+    /// Background check of the snapshot this node was started from, if any
+    pub import_validation: Option<SnapshotValidation>,
+}
+
+/// Compact block filter response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockFilterResponse {
+    pub block_hash: Hash256,
+    pub height: u64,
+    /// Number of addresses in the set
+    pub n: u32,
+    /// Golomb-Rice parameter
+    pub p: u8,
+    /// Inverse false-positive rate
+    pub m: u64,
+    /// Hex of the Golomb-coded set
+    pub filter: String,
+}
+
+/// Merkle proof that a confirmed transaction is in its block
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionProofResponse {
+    pub transaction_hash: Hash256,
+    pub block_hash: Hash256,
+    pub block_height: u64,
+    /// Position of the transaction in the block
+    pub transaction_index: usize,
+    pub merkle_proof: MerkleProof,
+    pub merkle_root: Hash256,
 }
 
 /// Address balance response
@@ -47,7 +195,7 @@ pub struct AddressBalanceResponse {
 }
 
 /// UTXO response
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UtxoResponse {
     pub utxo_id: String,
     pub tx_hash: Hash256,
@@ -67,132 +215,12 @@ pub struct NetworkStatusResponse {
     pub sync_progress: f64,
 }
 
-/// System metrics response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SystemMetricsResponse {
-    pub memory_usage: u64,
-    pub cpu_usage: f64,
-    pub disk_usage: u64,
-    pub network_io: NetworkIoMetrics,
-}
-
-/// Network IO metrics
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NetworkIoMetrics {
-    pub bytes_sent: u64,
-    pub bytes_received: u64,
-    pub packets_sent: u64,
-    pub packets_received: u64,
-}
-
-/// Create transaction request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateTransactionRequest {
-    pub from: Address,
-    pub to: Address,
-    pub amount: u64,
-    pub fee: Option<u64>,
-}
+/// Result of a JSON handler: its payload or the error, both sent in the envelope
+pub type ApiResult<T> = std::result::Result<Json<ApiResponse<T>>, ApiError>;
 
-/// Start mining request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StartMiningRequest {
-    pub address: Address,
-    pub threads: Option<u32>,
-}
-
-/// Paginated response wrapper
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PaginatedResponse<T> {
-    pub data: Vec<T>,
-    pub total: u64,
-    pub page: u32,
-    pub per_page: u32,
-    pub total_pages: u32,
-}
-
-/// Pagination parameters
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PaginationParams {
-    pub page: Option<u32>,
-    pub per_page: Option<u32>,
-}
-
-/// Standard API response wrapper
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    /// Response data
-    pub data: T,
-    /// Response metadata
-    pub meta: ResponseMeta,
-}
-
-/// Response metadata
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ResponseMeta {
-    /// Request ID for tracing
-    pub request_id: Option<String>,
-    /// Response timestamp
-    pub timestamp: DateTime<Utc>,
-    /// API version
-    pub version: String,
-    /// Processing time in milliseconds
-    pub processing_time_ms: Option<u64>,
-    /// Additional metadata
-    pub extra: Option<HashMap<String, serde_json::Value>>,
-}
-
-impl ResponseMeta {
-    /// Create new response metadata
-    pub fn new() -> Self {
-        Self {
-            request_id: None,
-            timestamp: Utc::now(),
-            version: "1.0.0".to_string(),
-            processing_time_ms: None,
-            extra: None,
-        }
-    }
-    
-    /// Set request ID
-    pub fn with_request_id(mut self, request_id: String) -> Self {
-        self.request_id = Some(request_id);
-        self
-    }
-    
-    /// Set processing time
-    pub fn with_processing_time(mut self, processing_time_ms: u64) -> Self {
-        self.processing_time_ms = Some(processing_time_ms);
-        self
-    }
-    
-    /// Add extra metadata
-    pub fn with_extra(mut self, key: String, value: serde_json::Value) -> Self {
-        if self.extra.is_none() {
-            self.extra = Some(HashMap::new());
-        }
-        self.extra.as_mut().unwrap().insert(key, value);
-        self
-    }
-}
-
-impl Default for ResponseMeta {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-/// Create a successful API response
-pub fn success<T>(data: T) -> ApiResponse<T> {
-    ApiResponse {
-        data,
-        meta: ResponseMeta::new(),
-    }
-}
-
-/// Create a successful API response with metadata
-pub fn success_with_meta<T>(data: T, meta: ResponseMeta) -> ApiResponse<T> {
-    ApiResponse { data, meta }
+/// Wrap `data` in the envelope of the request being handled
+pub fn success<T>(data: T) -> Json<ApiResponse<T>> {
+    Json(ApiResponse::success(data, current_request_id()))
 }
 
 /// Block response with additional metadata
@@ -220,7 +248,7 @@ impl BlockResponse {
     pub fn from_block(block: Block, current_height: u64) -> Self {
         let size = bincode::serialize(&block).map(|b| b.len()).unwrap_or(0);
         let confirmations = current_height.saturating_sub(block.index);
-        let total_fees = block.transactions.iter()
+        let total_fees = block.transactions().iter()
             .map(|tx| tx.fee.base_fee + tx.fee.per_byte_fee * tx.size.unwrap_or(0) as u64)
             .sum();
         
@@ -499,90 +527,6 @@ pub enum SearchResultType {
     Utxo,
 }
 
-/// Error response structure
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorResponse {
-    /// Error code
-    pub error: ErrorInfo,
-    /// Request metadata
-    pub meta: ResponseMeta,
-}
-
-/// Error information
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorInfo {
-    /// Error code
-    pub code: String,
-    /// Error message
-    pub message: String,
-    /// Error details
-    pub details: Option<serde_json::Value>,
-    /// Error context
-    pub context: Option<HashMap<String, serde_json::Value>>,
-    /// Suggested actions
-    pub suggestions: Option<Vec<String>>,
-}
-
-/// Create an error response
-pub fn error_response(
-    code: impl Into<String>,
-    message: impl Into<String>,
-) -> ErrorResponse {
-    ErrorResponse {
-        error: ErrorInfo {
-            code: code.into(),
-            message: message.into(),
-            details: None,
-            context: None,
-            suggestions: None,
-        },
-        meta: ResponseMeta::new(),
-    }
-}
-
-/// Create a detailed error response
-pub fn detailed_error_response(
-    code: impl Into<String>,
-    message: impl Into<String>,
-    details: Option<serde_json::Value>,
-    context: Option<HashMap<String, serde_json::Value>>,
-    suggestions: Option<Vec<String>>,
-) -> ErrorResponse {
-    ErrorResponse {
-        error: ErrorInfo {
-            code: code.into(),
-            message: message.into(),
-            details,
-            context,
-            suggestions,
-        },
-        meta: ResponseMeta::new(),
-    }
-}
-
-/// Validation error response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ValidationErrorResponse {
-    /// Field-specific errors
-    pub field_errors: HashMap<String, Vec<String>>,
-    /// General validation errors
-    pub general_errors: Vec<String>,
-    /// Request metadata
-    pub meta: ResponseMeta,
-}
-
-/// Create a validation error response
-pub fn validation_error_response(
-    field_errors: HashMap<String, Vec<String>>,
-    general_errors: Vec<String>,
-) -> ValidationErrorResponse {
-    ValidationErrorResponse {
-        field_errors,
-        general_errors,
-        meta: ResponseMeta::new(),
-    }
-}
-
 /// Health check response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HealthCheckResponse {
@@ -639,37 +583,14 @@ pub struct SystemInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{Block, BlockHeader, Transaction};
-    use crate::crypto::Hash256;
-    
-    #[test]
-    fn test_response_meta_creation() {
-        let meta = ResponseMeta::new()
-            .with_request_id("test-123".to_string())
-            .with_processing_time(100)
-            .with_extra("custom".to_string(), serde_json::json!("value"));
-        
-        assert_eq!(meta.request_id, Some("test-123".to_string()));
-        assert_eq!(meta.processing_time_ms, Some(100));
-        assert!(meta.extra.is_some());
-    }
     
     #[test]
     fn test_success_response() {
-        let data = "test data";
-        let response = success(data);
-        
-        assert_eq!(response.data, "test data");
-        assert_eq!(response.meta.version, "1.0.0");
-    }
-    
-    #[test]
-    fn test_error_response() {
-        let error = error_response("TEST_ERROR", "Test error message");
-        
-        assert_eq!(error.error.code, "TEST_ERROR");
-        assert_eq!(error.error.message, "Test error message");
-        assert!(error.error.details.is_none());
+        let Json(response) = success("test data");
+
+        assert_eq!(response.data, Some("test data"));
+        assert!(response.error.is_none());
+        assert!(response.request_id.is_none(), "no request is being handled");
     }
     
     #[test]
@@ -0,0 +1,215 @@
+//! The one place the HTTP routes and their middleware are put together.
+//!
+//! Routes come in groups that a [`RouterBuilder`] can leave out, and every group
+//! sits behind the same middleware stack, so the server binary, embedded nodes
+//! and hosted chains cannot drift apart.
+
+use super::*;
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{header, HeaderValue, Method},
+    middleware::{from_fn, from_fn_with_state},
+    routing::{any, delete, get, post},
+    Router,
+};
+use std::sync::Arc;
+use tower::ServiceBuilder;
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
+
+/// Builds the router from its route groups; all but the static files are on
+/// unless turned off
+#[derive(Debug, Clone)]
+pub struct RouterBuilder {
+    state: AppState,
+    api: bool,
+    websocket: bool,
+    static_files: bool,
+    admin: bool,
+}
+
+impl RouterBuilder {
+    /// A builder serving `state` with the API, WebSocket and admin routes
+    pub fn new(state: AppState) -> Self {
+        Self { state, api: true, websocket: true, static_files: false, admin: true }
+    }
+
+    /// Health, version, docs, `/api/...` and the hosted chains under `/t/...`
+    pub fn api(mut self, enabled: bool) -> Self {
+        self.api = enabled;
+        self
+    }
+
+    /// Real-time updates on `/ws` and their counts on `/api/ws/stats`
+    pub fn websocket(mut self, enabled: bool) -> Self {
+        self.websocket = enabled;
+        self
+    }
+
+    /// The explorer page on `/` and its assets under `/static/...`
+    pub fn static_files(mut self, enabled: bool) -> Self {
+        self.static_files = enabled;
+        self
+    }
+
+    /// Node administration under `/admin/...`
+    pub fn admin(mut self, enabled: bool) -> Self {
+        self.admin = enabled;
+        self
+    }
+
+    /// The router with the chosen groups behind the full middleware stack
+    pub fn build(self) -> Router {
+        let state = self.state;
+        let middleware_stack = ServiceBuilder::new()
+            .layer(TraceLayer::new_for_http())
+            .layer(CompressionLayer::new())
+            .layer(cors_layer(&state.config))
+            .layer(DefaultBodyLimit::max(state.config.max_body_size))
+            .layer(from_fn(request_logging_middleware))
+            .layer(from_fn(security_headers_middleware))
+            .layer(from_fn(error_envelope_middleware))
+            .layer(from_fn_with_state(Arc::new(state.config.access.clone()), access_middleware))
+            .layer(from_fn_with_state(state.clone(), auth_middleware))
+            .layer(from_fn_with_state(RequestTimeouts::new(&state.config), timeout_middleware))
+            .layer(from_fn_with_state(state.peers.clone(), rate_limiting_middleware));
+
+        let mut router = Router::new();
+        if self.api {
+            router = router.merge(api_routes());
+        }
+        if self.websocket {
+            router = router.merge(websocket_routes());
+        }
+        if self.static_files {
+            router = router.merge(static_routes());
+        }
+        if self.admin {
+            router = router.merge(admin_routes());
+        }
+        router.layer(middleware_stack).with_state(state)
+    }
+}
+
+/// Create the main API router: every group but the static files
+pub fn create_router(state: AppState) -> Router {
+    RouterBuilder::new(state).build()
+}
+
+/// The CORS layer for `config`: browsers may call the API from the listed
+/// origins only, and from none when CORS is disabled or no origins are listed
+pub fn cors_layer(config: &ApiConfig) -> CorsLayer {
+    let origins = if config.enable_cors { config.cors.allowed_origins.as_slice() } else { &[] };
+    if origins.is_empty() {
+        return CorsLayer::new();
+    }
+    let any = origins.iter().any(|origin| origin == "*");
+    let allow_origin = if any {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+    };
+    CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+        .allow_origin(allow_origin)
+        .allow_credentials(config.cors.allow_credentials && !any)
+}
+
+fn api_routes() -> Router<AppState> {
+    Router::new()
+        // Health and info endpoints
+        .route("/health", get(health_check))
+        .route("/api/health", get(health_check))
+        .route("/version", get(get_api_version))
+
+        // Blockchain endpoints
+        .route("/api/blockchain/info", get(get_blockchain_info))
+        .route("/api/blockchain/stats", get(get_blockchain_stats))
+        .route("/api/mining/difficulty", get(get_mining_difficulty))
+        .route("/api/mining/status", get(get_mining_status))
+        .route("/api/reward", get(get_reward))
+        .route("/api/checkpoints", get(get_checkpoints))
+        .route("/api/sync/status", get(get_sync_status))
+        .route("/api/p2p/addresses", get(get_node_addresses))
+        .route("/api/stats/richlist", get(get_rich_list))
+        .route("/api/stats/distribution", get(get_supply_distribution))
+        .route("/api/audit/supply", get(audit_supply))
+        .route("/api/snapshot/utxo", get(get_utxo_snapshot_info))
+        .route("/api/snapshot/utxo/download", get(download_utxo_snapshot))
+        .route("/api/snapshot/utxo/manifest", get(get_snapshot_manifest))
+        .route("/api/snapshot/utxo/chunks/:hash", get(get_snapshot_chunk))
+        .route("/api/blocks", get(get_blocks))
+        .route("/api/blocks/latest", get(get_latest_block))
+        .route("/api/blocks/orphans", get(get_orphan_blocks))
+        .route("/api/blocks/submit", post(submit_block))
+        .route("/api/blocks/compact", post(submit_compact_block))
+        .route("/api/blocks/height/:height", get(get_block_by_height))
+        .route("/api/blocks/:hash", get(get_block_by_hash))
+        .route("/api/blocks/:hash/transactions", get(get_block_transactions))
+        .route("/api/blocks/:hash/filter", get(get_block_filter))
+        .route("/api/headers", get(get_headers))
+        .route("/api/batch", post(batch_query))
+
+        // Transaction endpoints
+        .route("/api/transactions/pending", get(get_pending_transactions))
+        .route("/api/transactions/validate", post(validate_transaction))
+        .route("/api/transactions/search", get(search_transactions))
+        .route("/api/transactions/:hash", get(get_transaction_by_hash))
+        .route("/api/transactions/:hash/proof", get(get_transaction_merkle_proof))
+        .route("/api/proofs/verify", post(verify_proof))
+        .route("/api/psbt", post(create_psbt))
+        .route("/api/psbt/combine", post(combine_psbts))
+        .route("/api/psbt/finalize", post(finalize_psbt))
+
+        // UTXO endpoints
+        .route("/api/utxos", get(get_all_utxos))
+
+        // Address endpoints
+        .route("/api/addresses/:address/transactions", get(get_address_transactions))
+        .route("/api/addresses/:address/balance/history", get(get_balance_history))
+
+        // Accounts
+        .route("/api/auth/login", post(login))
+        .route("/api/auth/logout", post(logout))
+        .route("/api/users", get(list_users).post(create_user))
+        .route("/api/users/:username", delete(delete_user))
+
+        // Watch-list endpoints
+        .route("/api/watch", get(list_watched_addresses).post(add_watched_address))
+        .route("/api/watch/:address", get(get_watched_address).delete(remove_watched_address))
+
+        // Hosted chains
+        .route("/t/:tenant", any(tenant_proxy))
+        .route("/t/:tenant/*path", any(tenant_proxy))
+
+        // API documentation
+        .merge(docs_router())
+}
+
+fn websocket_routes() -> Router<AppState> {
+    Router::new()
+        .route("/ws", get(mining_progress_websocket))
+        .route("/api/ws/stats", get(get_websocket_stats))
+}
+
+fn static_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(serve_index))
+        .route("/static/*file", get(serve_static))
+}
+
+fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/admin/tasks", get(get_task_health))
+        .route("/admin/verify", get(verify_storage).post(repair_storage))
+        .route("/admin/logs/level", get(get_log_level).put(set_log_level))
+        .route("/admin/tenants", get(list_tenants).post(create_tenant))
+        .route("/admin/tenants/:name", delete(archive_tenant))
+        .route("/admin/simulate", post(start_simulation))
+        .route("/admin/peers", get(list_peers))
+        .route("/admin/peers/:address/ban", post(ban_peer).delete(unban_peer))
+}
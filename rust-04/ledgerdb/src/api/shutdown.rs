@@ -0,0 +1,144 @@
+//! Graceful shutdown coordination for the HTTP API.
+//!
+//! A single [`Shutdown`] handle is shared by the listeners and the WebSocket
+//! handlers. When SIGINT or SIGTERM arrives the listeners stop accepting, in-flight
+//! requests get a grace period to finish, and open WebSockets are sent a close frame.
+
+use std::{future::IntoFuture, time::Duration};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Cloneable handle used to trigger and observe server shutdown
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    tx: watch::Sender<bool>,
+}
+
+impl Shutdown {
+    /// Create a handle that has not been triggered yet
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(false);
+        Self { tx }
+    }
+
+    /// Signal every listener and connection to shut down
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    /// Whether shutdown has been triggered
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Resolve once shutdown has been triggered
+    pub async fn wait(&self) {
+        let mut rx = self.tx.subscribe();
+        // the sender lives in `self`, so the channel cannot close while we wait
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Trigger shutdown when the process receives SIGINT or SIGTERM
+    pub fn trigger_on_signal(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            info!("Shutdown signal received, draining connections");
+            shutdown.trigger();
+        });
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wait for Ctrl+C, or SIGTERM on Unix
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Run `server` until it exits, allowing it at most `grace` to drain once shutdown is triggered
+pub async fn drain_with_timeout<F, T>(server: F, shutdown: &Shutdown, grace: Duration) -> Option<T>
+where
+    F: IntoFuture<Output = T>,
+{
+    let server = server.into_future();
+    tokio::pin!(server);
+    tokio::select! {
+        result = &mut server => return Some(result),
+        _ = shutdown.wait() => {}
+    }
+
+    match tokio::time::timeout(grace, server).await {
+        Ok(result) => Some(result),
+        Err(_) => {
+            warn!("Connections still open after {:?}; forcing shutdown", grace);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_resolves_after_trigger() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_triggered());
+
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.wait().await }
+        });
+        shutdown.trigger();
+
+        waiter.await.unwrap();
+        assert!(shutdown.is_triggered());
+    }
+
+    #[tokio::test]
+    async fn test_drain_returns_finished_server() {
+        let shutdown = Shutdown::new();
+        let result = drain_with_timeout(async { 7 }, &shutdown, Duration::from_secs(1)).await;
+        assert_eq!(result, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_drain_times_out_stuck_server() {
+        let shutdown = Shutdown::new();
+        shutdown.trigger();
+        let stuck = std::future::pending::<()>();
+        let result = drain_with_timeout(stuck, &shutdown, Duration::from_millis(10)).await;
+        assert!(result.is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//! UTXO snapshots served in chunks, for `/api/snapshot/utxo/manifest` and
+//! `/api/snapshot/utxo/chunks/:hash`.
+//!
+//! Building a snapshot walks the whole UTXO set, and a download asks for every
+//! chunk in turn, so the latest snapshot asked for is kept in memory and its
+//! chunks are served from there until a snapshot of another block is asked for.
+
+use crate::core::ChunkedSnapshot;
+use crate::error::Result;
+use ledgerdb_types::Hash256;
+use std::sync::{Arc, Mutex};
+
+/// The [`ChunkedSnapshot`] of the latest block anyone asked about
+#[derive(Debug, Default)]
+pub struct SnapshotChunkCache {
+    latest: Mutex<Option<Arc<ChunkedSnapshot>>>,
+}
+
+impl SnapshotChunkCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The snapshot at the block `block_hash`, built with `compute` unless cached
+    pub fn get_or_compute(
+        &self,
+        block_hash: &Hash256,
+        compute: impl FnOnce() -> Result<ChunkedSnapshot>,
+    ) -> Result<Arc<ChunkedSnapshot>> {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        match latest.as_ref() {
+            Some(snapshot) if snapshot.manifest.block_hash == *block_hash => Ok(snapshot.clone()),
+            _ => Ok(latest.insert(Arc::new(compute()?)).clone()),
+        }
+    }
+
+    /// The cached snapshot, if it has a chunk named `hash`
+    pub fn with_chunk(&self, hash: &Hash256) -> Option<Arc<ChunkedSnapshot>> {
+        let latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        latest.as_ref().filter(|snapshot| snapshot.chunk(hash).is_some()).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::UtxoSnapshot;
+
+    fn chunked(height: u64) -> ChunkedSnapshot {
+        let snapshot = UtxoSnapshot::new(height, crate::crypto::Hash256::new([height as u8; 32]), []).unwrap();
+        ChunkedSnapshot::new(&snapshot, 16).unwrap()
+    }
+
+    #[test]
+    fn test_cached_until_another_block() {
+        let cache = SnapshotChunkCache::new();
+        let first = cache.get_or_compute(&Hash256([1; 32]), || Ok(chunked(1))).unwrap();
+        let again = cache.get_or_compute(&Hash256([1; 32]), || unreachable!("cached")).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(cache.with_chunk(&first.manifest.chunks[0]).is_some());
+
+        let other = cache.get_or_compute(&Hash256([2; 32]), || Ok(chunked(2))).unwrap();
+        assert_eq!(other.manifest.height, 2);
+        assert!(cache.with_chunk(&first.manifest.chunks[0]).is_none());
+    }
+}
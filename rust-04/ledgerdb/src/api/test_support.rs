@@ -0,0 +1,85 @@
+//! In-process API server for end-to-end tests.
+//!
+//! [`TestNode`] wires a fresh blockchain to in-memory storage, builds the full
+//! router from [`create_router`](super::create_router) and serves it on an
+//! ephemeral localhost port, so tests go through real HTTP and the whole
+//! middleware stack rather than calling handlers directly.
+
+use super::{create_router, ApiConfig, ApiErrorBody, ApiResponse, AppState};
+use crate::auth::UserStore;
+use crate::core::blockchain::{Blockchain, BlockchainConfig};
+use crate::core::Block;
+use crate::crypto::{Address, PublicKey, SignatureAlgorithm};
+use crate::storage::PersistentStorage;
+use axum_test::{TestResponse, TestServer, TestServerConfig};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A running API server backed by throwaway storage
+pub struct TestNode {
+    /// HTTP client bound to the server's ephemeral port
+    pub server: TestServer,
+    /// Shared state, for arranging chain contents directly
+    pub state: AppState,
+}
+
+impl TestNode {
+    /// Start a node holding only the genesis block
+    pub async fn start() -> Self {
+        let storage = Arc::new(PersistentStorage::in_memory());
+        let blockchain = Blockchain::with_storage(BlockchainConfig::default(), storage.clone(), miner_address())
+            .expect("create test blockchain");
+        let users = Arc::new(UserStore::new(None, Duration::from_secs(60)).expect("open user store"));
+        let state = AppState::new(blockchain, storage, 2, ApiConfig::default(), users, None).expect("create app state");
+
+        let server = TestServerConfig::builder()
+            .http_transport()
+            .build_server(create_router(state.clone()))
+            .expect("start test server");
+
+        Self {
+            server,
+            state,
+        }
+    }
+
+    /// Mine `count` blocks on top of the current tip and return them
+    pub async fn mine_blocks(&self, count: usize) -> Vec<Block> {
+        let mut blockchain = self.state.blockchain.write().await;
+        let mut mined = Vec::with_capacity(count);
+        for _ in 0..count {
+            let block = blockchain.create_block(miner_address()).expect("create block");
+            blockchain.add_block(block).expect("add block");
+            mined.push(blockchain.get_latest_block().cloned().expect("tip"));
+        }
+        mined
+    }
+}
+
+/// Address credited with the genesis and mined block rewards
+pub fn miner_address() -> Address {
+    Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![0u8; 33]))
+}
+
+/// Unwrapping the response envelope in assertions
+pub trait EnvelopeExt {
+    /// Payload of a successful response
+    fn data<T: DeserializeOwned>(&self) -> T;
+    /// Error of a failed response
+    fn error(&self) -> ApiErrorBody;
+}
+
+impl EnvelopeExt for TestResponse {
+    fn data<T: DeserializeOwned>(&self) -> T {
+        let envelope = self.json::<ApiResponse<T>>();
+        assert!(envelope.error.is_none(), "unexpected error: {:?}", envelope.error);
+        envelope.data.expect("envelope without data")
+    }
+
+    fn error(&self) -> ApiErrorBody {
+        let envelope = self.json::<ApiResponse<serde::de::IgnoredAny>>();
+        assert!(envelope.data.is_none(), "expected an error envelope");
+        envelope.error.expect("envelope without error")
+    }
+}
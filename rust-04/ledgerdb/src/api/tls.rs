@@ -0,0 +1,73 @@
+//! TLS support for the HTTP API.
+//!
+//! This module turns the TLS settings in [`ServerConfig`] into a rustls server
+//! configuration, either from PEM files on disk or from a self-signed certificate
+//! generated at startup for local development.
+
+use crate::config::ServerConfig;
+use crate::error::{LedgerError, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use tracing::warn;
+
+/// Hostnames covered by the generated development certificate
+const SELF_SIGNED_NAMES: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+/// Build the rustls configuration for the server, or `None` when TLS is disabled
+pub async fn load_tls_config(server: &ServerConfig) -> Result<Option<RustlsConfig>> {
+    match (&server.tls_cert_path, &server.tls_key_path) {
+        (Some(cert), Some(key)) => {
+            let config = RustlsConfig::from_pem_file(cert, key)
+                .await
+                .map_err(|e| LedgerError::Config(format!("Failed to load TLS certificate: {}", e)))?;
+            Ok(Some(config))
+        }
+        (None, None) if server.tls_self_signed => {
+            warn!("Serving HTTPS with a generated self-signed certificate; do not use this in production");
+            let (cert_pem, key_pem) = generate_self_signed()?;
+            let config = RustlsConfig::from_pem(cert_pem, key_pem)
+                .await
+                .map_err(|e| LedgerError::Config(format!("Invalid self-signed certificate: {}", e)))?;
+            Ok(Some(config))
+        }
+        (None, None) => Ok(None),
+        _ => Err(LedgerError::Config(
+            "server.tls_cert_path and server.tls_key_path must be set together".to_string(),
+        )),
+    }
+}
+
+/// Generate a PEM certificate and private key for local development
+pub fn generate_self_signed() -> Result<(Vec<u8>, Vec<u8>)> {
+    let names = SELF_SIGNED_NAMES.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    let certified = rcgen::generate_simple_self_signed(names)
+        .map_err(|e| LedgerError::Internal(format!("Self-signed certificate generation failed: {}", e)))?;
+
+    Ok((
+        certified.cert.pem().into_bytes(),
+        certified.key_pair.serialize_pem().into_bytes(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_self_signed() {
+        let (cert, key) = generate_self_signed().unwrap();
+        assert!(String::from_utf8(cert).unwrap().contains("BEGIN CERTIFICATE"));
+        assert!(String::from_utf8(key).unwrap().contains("PRIVATE KEY"));
+    }
+
+    #[tokio::test]
+    async fn test_tls_disabled_by_default() {
+        let server = ServerConfig::default();
+        assert!(load_tls_config(&server).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tls_requires_cert_and_key() {
+        let server = ServerConfig { tls_cert_path: Some("cert.pem".into()), ..ServerConfig::default() };
+        assert!(load_tls_config(&server).await.is_err());
+    }
+}
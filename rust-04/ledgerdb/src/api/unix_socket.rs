@@ -0,0 +1,122 @@
+//! Unix domain socket listener for the HTTP API.
+//!
+//! `axum::serve` only accepts a `TcpListener` in axum 0.7, so this module runs its
+//! own accept loop and hands each connection to hyper, the same way axum does
+//! internally. Local tooling and reverse proxies can then reach the API without
+//! opening a TCP port.
+
+use crate::api::Shutdown;
+use crate::error::{LedgerError, Result};
+use axum::{extract::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto, graceful::GracefulShutdown},
+};
+use std::{path::Path, time::Duration};
+use tokio::net::UnixListener;
+use tower::ServiceExt;
+use tracing::{debug, warn};
+
+/// Bind a Unix socket at `path`, replacing a stale socket file left by a previous run
+pub fn bind_unix_socket(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| {
+            LedgerError::Io(format!("Failed to remove stale socket {}: {}", path.display(), e))
+        })?;
+    }
+    UnixListener::bind(path)
+        .map_err(|e| LedgerError::Io(format!("Failed to bind {}: {}", path.display(), e)))
+}
+
+/// Serve `app` on the Unix socket at `path` until `shutdown` is triggered.
+///
+/// Open connections then get up to `grace` to finish before the socket file is removed.
+pub async fn serve_unix(path: &Path, app: Router, shutdown: Shutdown, grace: Duration) -> Result<()> {
+    let listener = bind_unix_socket(path)?;
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Unix socket accept failed: {}", e);
+                    continue;
+                }
+            },
+            _ = shutdown.wait() => break,
+        };
+        let app = app.clone();
+        let service = hyper::service::service_fn(move |request: Request<Incoming>| {
+            app.clone().oneshot(request)
+        });
+        let connection = builder
+            .serve_connection_with_upgrades(TokioIo::new(stream), service)
+            .into_owned();
+        let connection = graceful.watch(connection);
+
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("Unix socket connection closed with error: {}", e);
+            }
+        });
+    }
+
+    drop(listener);
+    if tokio::time::timeout(grace, graceful.shutdown()).await.is_err() {
+        warn!("Unix socket connections still open after {:?}; closing anyway", grace);
+    }
+    std::fs::remove_file(path)
+        .map_err(|e| LedgerError::Io(format!("Failed to remove socket {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    #[tokio::test]
+    async fn test_bind_replaces_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api.sock");
+        std::fs::write(&path, b"stale").unwrap();
+
+        assert!(bind_unix_socket(&path).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_serve_unix_roundtrip_and_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api.sock");
+        let app = Router::new().route("/health", get(|| async { "ok" }));
+
+        let server_path = path.clone();
+        let shutdown = Shutdown::new();
+        let server = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { serve_unix(&server_path, app, shutdown, Duration::from_secs(1)).await }
+        });
+        while !path.exists() {
+            tokio::task::yield_now().await;
+        }
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("ok"));
+
+        shutdown.trigger();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+}
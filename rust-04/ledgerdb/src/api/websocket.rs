@@ -3,28 +3,36 @@
 //! This module provides WebSocket endpoints for streaming real-time data
 //! including mining progress, new blocks, transactions, and network status.
 
-use crate::api::AppState;
-use crate::core::{Block, Transaction};
-use crate::crypto::pow::MiningProgress;
-use crate::crypto::Hash256;
+use crate::api::{pending_transaction_response, success, ApiError, ApiResponse, AppState, ErrorCode, ErrorResponse, PendingTransactionResponse};
+use crate::core::{Block, Blockchain, Transaction};
+use crate::crypto::pow::{expected_hashes, MinerEvent};
+use crate::crypto::{Address, Hash256};
+use crate::watch::{AddressActivity, WatchList};
 use axum::{
     extract::{ws::WebSocket, State, WebSocketUpgrade},
-    response::Response,
+    response::{IntoResponse, Json, Response},
 };
-use futures_util::{sink::SinkExt, stream::{StreamExt, SplitSink, SplitStream}};
+use futures_util::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use tokio::{
-    sync::broadcast,
-    time::{interval, timeout},
+    sync::{broadcast, RwLock},
+    task::JoinHandle,
+    time::interval,
 };
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+pub use ledgerdb_types::{
+    ConnectionStatusData, DifficultyAdjustmentData, ErrorData, MiningProgressData, MiningState,
+    NetworkStatusData, PingData, PongData, SubscriptionData, SubscriptionRequest, UnsubscriptionData,
+};
+use ledgerdb_types::WebSocketStats;
+
 /// WebSocket message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -53,27 +61,8 @@ pub enum WsMessage {
     Subscribed(SubscriptionData),
     /// Unsubscription confirmation
     Unsubscribed(UnsubscriptionData),
-}
-
-/// Mining progress data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MiningProgressData {
-    /// Current block height being mined
-    pub block_height: u64,
-    /// Current difficulty
-    pub difficulty: u32,
-    /// Current nonce
-    pub nonce: u64,
-    /// Hash rate (hashes per second)
-    pub hash_rate: f64,
-    /// Progress percentage (0-100)
-    pub progress: f64,
-    /// Estimated time remaining (seconds)
-    pub estimated_time: Option<u64>,
-    /// Number of attempts
-    pub attempts: u64,
-    /// Best hash found so far
-    pub best_hash: Option<Hash256>,
+    /// Payment to or from a watched address, or a new confirmation of one
+    AddressActivity(AddressActivity),
 }
 
 /// New block data
@@ -120,23 +109,6 @@ pub struct NewTransactionData {
     pub total_output: u64,
 }
 
-/// Network status data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct NetworkStatusData {
-    /// Current block height
-    pub block_height: u64,
-    /// Network hash rate
-    pub hash_rate: f64,
-    /// Connected peers
-    pub connected_peers: u32,
-    /// Sync status
-    pub sync_status: String,
-    /// Network difficulty
-    pub difficulty: u32,
-    /// Average block time
-    pub average_block_time: f64,
-}
-
 /// Mempool update data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MempoolUpdateData {
@@ -146,104 +118,34 @@ pub struct MempoolUpdateData {
     pub total_size: u64,
     /// Total fees
     pub total_fees: u64,
-    /// Average fee rate
+    /// Total fees over total size
     pub average_fee_rate: f64,
-    /// Recent transactions added
-    pub recent_transactions: Vec<Hash256>,
-}
-
-/// Difficulty adjustment data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DifficultyAdjustmentData {
-    /// Old difficulty
-    pub old_difficulty: u32,
-    /// New difficulty
-    pub new_difficulty: u32,
-    /// Change percentage
-    pub change_percentage: f64,
-    /// Block height of adjustment
-    pub block_height: u64,
-    /// Next adjustment in blocks
-    pub next_adjustment: u64,
-}
-
-/// Connection status data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConnectionStatusData {
-    /// Connection ID
-    pub connection_id: String,
-    /// Connection status
-    pub status: String,
-    /// Connected at
-    pub connected_at: u64,
-    /// Subscriptions
-    pub subscriptions: Vec<String>,
-}
-
-/// Error data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ErrorData {
-    /// Error code
-    pub code: String,
-    /// Error message
-    pub message: String,
-    /// Additional details
-    pub details: Option<serde_json::Value>,
-}
-
-/// Ping data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PingData {
-    /// Timestamp
-    pub timestamp: u64,
-    /// Optional message
-    pub message: Option<String>,
+    /// The newest transactions in the pool, newest first
+    pub recent_transactions: Vec<PendingTransactionResponse>,
 }
 
-/// Pong data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PongData {
-    /// Original timestamp from ping
-    pub timestamp: u64,
-    /// Response timestamp
-    pub response_timestamp: u64,
-    /// Optional message
-    pub message: Option<String>,
-}
+/// Transactions listed in a mempool update
+pub const MEMPOOL_UPDATE_RECENT: usize = 20;
 
-/// Subscription data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SubscriptionData {
-    /// Subscription topic
-    pub topic: String,
-    /// Subscription ID
-    pub subscription_id: String,
-    /// Success status
-    pub success: bool,
-    /// Optional message
-    pub message: Option<String>,
-}
+/// How often a `mempool_updates` subscription checks the pool for changes
+const MEMPOOL_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
 
-/// Unsubscription data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UnsubscriptionData {
-    /// Subscription topic
-    pub topic: String,
-    /// Subscription ID
-    pub subscription_id: String,
-    /// Success status
-    pub success: bool,
-}
-
-/// WebSocket client subscription request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SubscriptionRequest {
-    /// Action (subscribe/unsubscribe)
-    pub action: String,
-    /// Topic to subscribe to
-    pub topic: String,
-    /// Optional parameters
-    pub params: Option<HashMap<String, serde_json::Value>>,
+impl MempoolUpdateData {
+    /// Totals over the whole pool, plus its newest [`MEMPOOL_UPDATE_RECENT`] transactions
+    pub fn from_pool(blockchain: &Blockchain) -> Self {
+        let mut pending: Vec<PendingTransactionResponse> = blockchain
+            .get_pending_details()
+            .iter()
+            .map(pending_transaction_response)
+            .collect();
+        let total_size: u64 = pending.iter().map(|p| p.size as u64).sum();
+        let total_fees: u64 = pending.iter().map(|p| p.fee).sum();
+        let average_fee_rate = if total_size > 0 { total_fees as f64 / total_size as f64 } else { 0.0 };
+        let transaction_count = pending.len() as u64;
+        pending.sort_by_key(|p| std::cmp::Reverse(p.added_at));
+        pending.truncate(MEMPOOL_UPDATE_RECENT);
+        Self { transaction_count, total_size, total_fees, average_fee_rate, recent_transactions: pending }
+    }
 }
 
 /// Available subscription topics
@@ -261,13 +163,15 @@ pub enum SubscriptionTopic {
     MempoolUpdates,
     /// Difficulty adjustments
     DifficultyAdjustments,
+    /// Activity of watched addresses
+    WatchedAddresses,
     /// All updates
     All,
 }
 
 impl SubscriptionTopic {
     /// Parse topic from string
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn parse(s: &str) -> Option<Self> {
         match s {
             "mining_progress" => Some(Self::MiningProgress),
             "new_blocks" => Some(Self::NewBlocks),
@@ -275,6 +179,7 @@ impl SubscriptionTopic {
             "network_status" => Some(Self::NetworkStatus),
             "mempool_updates" => Some(Self::MempoolUpdates),
             "difficulty_adjustments" => Some(Self::DifficultyAdjustments),
+            "watched_addresses" => Some(Self::WatchedAddresses),
             "all" => Some(Self::All),
             _ => None,
         }
@@ -289,6 +194,7 @@ impl SubscriptionTopic {
             Self::NetworkStatus => "network_status",
             Self::MempoolUpdates => "mempool_updates",
             Self::DifficultyAdjustments => "difficulty_adjustments",
+            Self::WatchedAddresses => "watched_addresses",
             Self::All => "all",
         }
     }
@@ -301,6 +207,8 @@ pub struct WebSocketManager {
     connections: Arc<Mutex<HashMap<String, WebSocketConnection>>>,
     /// Broadcast channels for different topics
     channels: HashMap<SubscriptionTopic, broadcast::Sender<WsMessage>>,
+    /// Connections accepted at most
+    max_connections: usize,
 }
 
 /// WebSocket connection information
@@ -319,8 +227,8 @@ pub struct WebSocketConnection {
 }
 
 impl WebSocketManager {
-    /// Create a new WebSocket manager
-    pub fn new() -> Self {
+    /// Create a new WebSocket manager accepting up to `max_connections` connections
+    pub fn new(max_connections: usize) -> Self {
         let mut channels = HashMap::new();
         
         // Create broadcast channels for each topic
@@ -339,13 +247,20 @@ impl WebSocketManager {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
             channels,
+            max_connections,
         }
     }
     
-    /// Add a new connection
-    pub fn add_connection(&self, connection: WebSocketConnection) {
+    /// Add a new connection, unless `max_connections` are open already; it is
+    /// removed again when the returned slot is dropped
+    pub fn add_connection(self: &Arc<Self>, connection: WebSocketConnection) -> Option<ConnectionSlot> {
         let mut connections = self.connections.lock().unwrap();
-        connections.insert(connection.id.clone(), connection);
+        if connections.len() >= self.max_connections {
+            return None;
+        }
+        let id = connection.id.clone();
+        connections.insert(id.clone(), connection);
+        Some(ConnectionSlot { manager: self.clone(), id })
     }
     
     /// Remove a connection
@@ -354,6 +269,30 @@ impl WebSocketManager {
         connections.remove(connection_id);
     }
     
+    /// Record that a connection subscribed to `topic`
+    pub fn add_subscription(&self, connection_id: &str, topic: SubscriptionTopic, subscription_id: String) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(connection_id) {
+            connection.subscriptions.insert(topic, subscription_id);
+        }
+    }
+    
+    /// Record that a connection unsubscribed from `topic`
+    pub fn remove_subscription(&self, connection_id: &str, topic: &SubscriptionTopic) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(connection_id) {
+            connection.subscriptions.remove(topic);
+        }
+    }
+    
+    /// Open connections and how many are subscribed to each topic
+    pub fn stats(&self) -> WebSocketStats {
+        let connections = self.connections.lock().unwrap();
+        let mut topics = std::collections::BTreeMap::new();
+        for topic in connections.values().flat_map(|connection| connection.subscriptions.keys()) {
+            *topics.entry(topic.to_str().to_string()).or_insert(0) += 1;
+        }
+        WebSocketStats { connections: connections.len(), max_connections: self.max_connections, topics }
+    }
+    
     /// Broadcast message to topic subscribers
     pub fn broadcast_to_topic(&self, topic: SubscriptionTopic, message: WsMessage) {
         if let Some(sender) = self.channels.get(&topic) {
@@ -374,24 +313,81 @@ impl WebSocketManager {
     }
 }
 
-/// Mining progress WebSocket endpoint
+/// A connection's place in its [`WebSocketManager`], given up when dropped
+#[derive(Debug)]
+pub struct ConnectionSlot {
+    manager: Arc<WebSocketManager>,
+    id: String,
+}
+
+impl ConnectionSlot {
+    /// Connection ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.manager.remove_connection(&self.id);
+    }
+}
+
+/// Mining progress WebSocket endpoint.
+///
+/// Send a `SubscriptionRequest` to choose topics; messages arrive as `WsMessage`.
+/// Once `max_websocket_connections` are open, further upgrades are refused.
+#[utoipa::path(
+    get, path = "/ws", tag = "node",
+    responses((status = 101, description = "Switching to the WebSocket protocol"), (status = 503, description = "All WebSocket connections are in use", body = ErrorResponse))
+)]
 pub async fn mining_progress_websocket(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_mining_progress_websocket(socket, state))
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<WsMessage>();
+    let connection = WebSocketConnection {
+        id: Uuid::new_v4().to_string(),
+        connected_at: Instant::now(),
+        subscriptions: HashMap::from([(SubscriptionTopic::MiningProgress, Uuid::new_v4().to_string())]),
+        last_ping: None,
+        sender: tx.clone(),
+    };
+    // Taken before the upgrade so the limit holds; released if the upgrade fails
+    let Some(slot) = state.websockets.add_connection(connection) else {
+        return ApiError::new(
+            ErrorCode::ServiceUnavailable,
+            format!("All {} WebSocket connections are in use", state.websockets.stats().max_connections),
+        )
+        .into_response();
+    };
+    ws.on_upgrade(|socket| handle_mining_progress_websocket(socket, state, slot, tx, rx))
+}
+
+/// Open connections and subscribers per topic on `/ws`
+#[utoipa::path(
+    get, path = "/api/ws/stats", tag = "node",
+    responses((status = 200, body = ApiResponse<WebSocketStats>))
+)]
+pub async fn get_websocket_stats(State(state): State<AppState>) -> Json<ApiResponse<WebSocketStats>> {
+    success(state.websockets.stats())
 }
 
 /// Handle mining progress WebSocket connection
-async fn handle_mining_progress_websocket(socket: WebSocket, state: AppState) {
-    let connection_id = Uuid::new_v4().to_string();
+async fn handle_mining_progress_websocket(
+    socket: WebSocket,
+    state: AppState,
+    slot: ConnectionSlot,
+    tx: tokio::sync::mpsc::UnboundedSender<WsMessage>,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<WsMessage>,
+) {
+    let connection_id = slot.id().to_string();
     info!("New mining progress WebSocket connection: {}", connection_id);
     
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<WsMessage>();
     
-    // Subscribe to mining progress updates
-    let mut mining_progress_rx = state.mining_progress_tx.subscribe();
+    // Subscribe to the miner's events
+    let mut miner_events = state.miner_events.subscribe();
     
     // Send connection status
     let connection_status = WsMessage::ConnectionStatus(ConnectionStatusData {
@@ -409,9 +405,24 @@ async fn handle_mining_progress_websocket(socket: WebSocket, state: AppState) {
         return;
     }
     
-    // Spawn task to handle outgoing messages
+    // Spawn task to handle outgoing messages; on server shutdown it says goodbye with a close frame
+    let shutdown = state.shutdown.clone();
     let outgoing_task = tokio::spawn(async move {
-        while let Some(message) = rx.recv().await {
+        loop {
+            let message = tokio::select! {
+                message = rx.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                _ = shutdown.wait() => {
+                    let close = axum::extract::ws::CloseFrame {
+                        code: axum::extract::ws::close_code::AWAY,
+                        reason: "server shutting down".into(),
+                    };
+                    let _ = sender.send(axum::extract::ws::Message::Close(Some(close))).await;
+                    break;
+                }
+            };
             let json = match serde_json::to_string(&message) {
                 Ok(json) => json,
                 Err(e) => {
@@ -430,11 +441,17 @@ async fn handle_mining_progress_websocket(socket: WebSocket, state: AppState) {
     let mining_progress_task = {
         let tx = tx.clone();
         tokio::spawn(async move {
-            while let Ok(progress) = mining_progress_rx.recv().await {
-                let message = WsMessage::MiningProgress(MiningProgressData::from(progress));
-                
-                if tx.send(message).is_err() {
-                    break;
+            loop {
+                match miner_events.recv().await {
+                    Ok(event) => {
+                        if tx.send(WsMessage::MiningProgress(MiningProgressData::from(event))).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("WebSocket subscriber missed {} miner events", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
             }
         })
@@ -443,13 +460,18 @@ async fn handle_mining_progress_websocket(socket: WebSocket, state: AppState) {
     // Spawn task to handle incoming messages
     let incoming_task = {
         let tx = tx.clone();
+        let watch = state.watch.clone();
+        let blockchain = state.blockchain.clone();
+        let websockets = state.websockets.clone();
         tokio::spawn(async move {
+            // Streams started by subscription requests, stopped on unsubscribe or close
+            let mut forwards: HashMap<SubscriptionTopic, JoinHandle<()>> = HashMap::new();
             while let Some(msg) = receiver.next().await {
                 match msg {
                     Ok(axum::extract::ws::Message::Text(text)) => {
                         // Handle client messages (ping, subscription requests, etc.)
                         if let Ok(request) = serde_json::from_str::<SubscriptionRequest>(&text) {
-                            handle_subscription_request(request, &tx).await;
+                            handle_subscription_request(request, &tx, &watch, &blockchain, &websockets, &connection_id, &mut forwards).await;
                         }
                     }
                     Ok(axum::extract::ws::Message::Close(_)) => {
@@ -463,6 +485,9 @@ async fn handle_mining_progress_websocket(socket: WebSocket, state: AppState) {
                     _ => {}
                 }
             }
+            for forward in forwards.into_values() {
+                forward.abort();
+            }
         })
     };
     
@@ -497,19 +522,36 @@ async fn handle_mining_progress_websocket(socket: WebSocket, state: AppState) {
         _ = keepalive_task => {},
     }
     
-    info!("Mining progress WebSocket connection closed: {}", connection_id);
+    info!("Mining progress WebSocket connection closed: {}", slot.id());
 }
 
 /// Handle subscription request
 async fn handle_subscription_request(
     request: SubscriptionRequest,
     tx: &tokio::sync::mpsc::UnboundedSender<WsMessage>,
+    watch: &WatchList,
+    blockchain: &Arc<RwLock<Blockchain>>,
+    websockets: &WebSocketManager,
+    connection_id: &str,
+    forwards: &mut HashMap<SubscriptionTopic, JoinHandle<()>>,
 ) {
     let subscription_id = Uuid::new_v4().to_string();
     
     let response = match request.action.as_str() {
         "subscribe" => {
-            if let Some(_topic) = SubscriptionTopic::from_str(&request.topic) {
+            if let Some(topic) = SubscriptionTopic::parse(&request.topic) {
+                let forward = match topic {
+                    SubscriptionTopic::WatchedAddresses => {
+                        let addresses = request_addresses(request.params.as_ref());
+                        Some(forward_address_activity(watch, addresses, tx.clone()))
+                    }
+                    SubscriptionTopic::MempoolUpdates => Some(forward_mempool_updates(blockchain.clone(), tx.clone())),
+                    _ => None,
+                };
+                if let Some(previous) = forward.and_then(|forward| forwards.insert(topic.clone(), forward)) {
+                    previous.abort();
+                }
+                websockets.add_subscription(connection_id, topic, subscription_id.clone());
                 WsMessage::Subscribed(SubscriptionData {
                     topic: request.topic,
                     subscription_id,
@@ -525,6 +567,12 @@ async fn handle_subscription_request(
             }
         }
         "unsubscribe" => {
+            if let Some(topic) = SubscriptionTopic::parse(&request.topic) {
+                if let Some(forward) = forwards.remove(&topic) {
+                    forward.abort();
+                }
+                websockets.remove_subscription(connection_id, &topic);
+            }
             WsMessage::Unsubscribed(UnsubscriptionData {
                 topic: request.topic,
                 subscription_id,
@@ -545,18 +593,106 @@ async fn handle_subscription_request(
     }
 }
 
-/// Convert mining progress to WebSocket message
-impl From<MiningProgress> for MiningProgressData {
-    fn from(progress: MiningProgress) -> Self {
-        Self {
-            block_height: 0, // TODO: Get from blockchain state
-            difficulty: progress.target_difficulty,
-            nonce: progress.current_nonce,
-            hash_rate: progress.hash_rate,
-            progress: if progress.attempts > 0 { (progress.attempts as f64 / 1000000.0).min(100.0) } else { 0.0 },
-            estimated_time: progress.estimated_remaining_seconds.map(|s| s as u64),
-            attempts: progress.attempts,
-            best_hash: progress.best_hash,
+/// The `addresses` parameter of a subscription, skipping entries that are not
+/// valid addresses; `None` if absent
+fn request_addresses(params: Option<&HashMap<String, serde_json::Value>>) -> Option<HashSet<String>> {
+    let addresses = params?.get("addresses")?.as_array()?;
+    Some(addresses
+        .iter()
+        .filter_map(|a| a.as_str())
+        .filter_map(|a| Address::from_string(a).ok())
+        .map(|a| a.to_string())
+        .collect())
+}
+
+/// Send watched-address activity to `tx`, limited to `addresses` if given
+fn forward_address_activity(
+    watch: &WatchList,
+    addresses: Option<HashSet<String>>,
+    tx: tokio::sync::mpsc::UnboundedSender<WsMessage>,
+) -> JoinHandle<()> {
+    let mut activity = watch.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match activity.recv().await {
+                Ok(entry) => {
+                    if addresses.as_ref().is_some_and(|a| !a.contains(&entry.address)) {
+                        continue;
+                    }
+                    if tx.send(WsMessage::AddressActivity(entry)).is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("WebSocket subscriber missed {} watched-address updates", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Send the pool's state to `tx` on subscribing and whenever transactions enter or leave it
+fn forward_mempool_updates(
+    blockchain: Arc<RwLock<Blockchain>>,
+    tx: tokio::sync::mpsc::UnboundedSender<WsMessage>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = interval(MEMPOOL_UPDATE_INTERVAL);
+        let mut last: Option<HashSet<Hash256>> = None;
+        loop {
+            ticks.tick().await;
+            let update = {
+                let blockchain = blockchain.read().await;
+                let pool: HashSet<Hash256> = blockchain.get_pending_transactions().iter().map(|t| t.hash()).collect();
+                if last.as_ref() == Some(&pool) {
+                    continue;
+                }
+                last = Some(pool);
+                MempoolUpdateData::from_pool(&blockchain)
+            };
+            if tx.send(WsMessage::MempoolUpdate(update)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Convert a miner event to WebSocket message; `progress` is the chance that a
+/// search this long would have found the block
+impl From<MinerEvent> for MiningProgressData {
+    fn from(event: MinerEvent) -> Self {
+        let (block_height, difficulty, attempts, hash_rate) =
+            (event.height(), event.difficulty(), event.attempts(), event.hash_rate());
+        let chance = 100.0 * (1.0 - (-(attempts as f64) / expected_hashes(difficulty)).exp());
+        let base = Self {
+            state: MiningState::Searching,
+            block_height,
+            difficulty,
+            nonce: 0,
+            hash_rate,
+            progress: chance,
+            estimated_time: None,
+            attempts,
+            best_hash: None,
+        };
+        match event {
+            MinerEvent::Progress { progress, .. } => Self {
+                nonce: progress.current_nonce,
+                estimated_time: progress.estimated_remaining_seconds.map(|s| s as u64),
+                best_hash: progress.best_hash.map(|h| ledgerdb_types::Hash256(*h.as_bytes())),
+                ..base
+            },
+            MinerEvent::Found { nonce, hash, .. } => Self {
+                state: MiningState::Found,
+                nonce,
+                progress: 100.0,
+                estimated_time: Some(0),
+                best_hash: Some(ledgerdb_types::Hash256(*hash.as_bytes())),
+                ..base
+            },
+            MinerEvent::Cancelled { .. } => Self { state: MiningState::Cancelled, ..base },
+            MinerEvent::Timeout { .. } => Self { state: MiningState::Timeout, ..base },
         }
     }
 }
@@ -565,14 +701,14 @@ impl From<MiningProgress> for MiningProgressData {
 impl From<&Block> for NewBlockData {
     fn from(block: &Block) -> Self {
         let size = bincode::serialize(block).map(|b| b.len()).unwrap_or(0);
-        let total_fees = block.transactions.iter()
+        let total_fees = block.transactions().iter()
             .map(|tx| tx.fee.calculate_total_fee(size))
             .sum();
         
         Self {
             hash: block.hash(),
             height: block.index,
-            transaction_count: block.transactions.len(),
+            transaction_count: block.transactions().len(),
             size,
             timestamp: block.header.timestamp.timestamp() as u64,
             miner: None, // TODO: Extract miner from coinbase transaction
@@ -623,9 +759,10 @@ mod tests {
     
     #[test]
     fn test_subscription_topic_parsing() {
-        assert_eq!(SubscriptionTopic::from_str("mining_progress"), Some(SubscriptionTopic::MiningProgress));
-        assert_eq!(SubscriptionTopic::from_str("new_blocks"), Some(SubscriptionTopic::NewBlocks));
-        assert_eq!(SubscriptionTopic::from_str("invalid"), None);
+        assert_eq!(SubscriptionTopic::parse("mining_progress"), Some(SubscriptionTopic::MiningProgress));
+        assert_eq!(SubscriptionTopic::parse("new_blocks"), Some(SubscriptionTopic::NewBlocks));
+        assert_eq!(SubscriptionTopic::parse("watched_addresses"), Some(SubscriptionTopic::WatchedAddresses));
+        assert_eq!(SubscriptionTopic::parse("invalid"), None);
     }
     
     #[test]
@@ -655,9 +792,39 @@ mod tests {
     
     #[test]
     fn test_websocket_manager_creation() {
-        let manager = WebSocketManager::new();
+        let manager = WebSocketManager::new(100);
         assert_eq!(manager.connection_count(), 0);
         assert!(manager.channels.contains_key(&SubscriptionTopic::MiningProgress));
         assert!(manager.channels.contains_key(&SubscriptionTopic::NewBlocks));
     }
+
+    fn connection(id: &str) -> WebSocketConnection {
+        WebSocketConnection {
+            id: id.to_string(),
+            connected_at: Instant::now(),
+            subscriptions: HashMap::from([(SubscriptionTopic::MiningProgress, "s1".to_string())]),
+            last_ping: None,
+            sender: tokio::sync::mpsc::unbounded_channel().0,
+        }
+    }
+
+    #[test]
+    fn test_websocket_manager_limit_and_stats() {
+        let manager = Arc::new(WebSocketManager::new(2));
+        let first = manager.add_connection(connection("a")).unwrap();
+        let second = manager.add_connection(connection("b")).unwrap();
+        assert!(manager.add_connection(connection("c")).is_none());
+
+        manager.add_subscription("b", SubscriptionTopic::NewBlocks, "s2".to_string());
+        manager.remove_subscription("a", &SubscriptionTopic::MiningProgress);
+        let stats = manager.stats();
+        assert_eq!((stats.connections, stats.max_connections), (2, 2));
+        assert_eq!(stats.topics.get("mining_progress"), Some(&1));
+        assert_eq!(stats.topics.get("new_blocks"), Some(&1));
+
+        drop(first);
+        assert_eq!(manager.connection_count(), 1);
+        assert!(manager.add_connection(connection("c")).is_some());
+        drop(second);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,291 @@
+//! User accounts and login sessions for the HTTP API.
+//!
+//! Accounts are stored with a salted PBKDF2-HMAC-SHA256 hash of their password.
+//! Logging in issues a random token that is good for the configured session
+//! lifetime; sessions are held in memory, so a restart signs everyone out.
+
+use crate::error::Result;
+use crate::storage::PersistentStorage;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+pub use ledgerdb_types::{Role, UserAccount};
+
+/// PBKDF2 rounds for new password hashes; stored hashes keep their own count
+pub const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Shortest password accepted for a new account
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// Longest username accepted for a new account
+pub const MAX_USERNAME_LEN: usize = 64;
+
+const SALT_LEN: usize = 16;
+const TOKEN_LEN: usize = 32;
+
+/// A stored account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserRecord {
+    pub username: String,
+    pub role: Role,
+    /// Hex PBKDF2 salt
+    pub salt: String,
+    pub iterations: u32,
+    /// Hex PBKDF2-HMAC-SHA256 of the password
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserRecord {
+    /// An account for `username` with a freshly salted hash of `password`
+    pub fn new(username: &str, password: &str, role: Role) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        rand::rng().fill_bytes(&mut salt);
+        Self {
+            username: username.to_string(),
+            role,
+            salt: hex::encode(salt),
+            iterations: PBKDF2_ITERATIONS,
+            password_hash: hex::encode(pbkdf2_sha256(password.as_bytes(), &salt, PBKDF2_ITERATIONS)),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Whether `password` is this account's password
+    pub fn verify_password(&self, password: &str) -> bool {
+        let (Ok(salt), Ok(expected)) = (hex::decode(&self.salt), hex::decode(&self.password_hash)) else {
+            return false;
+        };
+        constant_time_eq(&expected, &pbkdf2_sha256(password.as_bytes(), &salt, self.iterations))
+    }
+
+    /// The account as the API shows it
+    pub fn account(&self) -> UserAccount {
+        UserAccount {
+            username: self.username.clone(),
+            role: self.role,
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// Whether `a` and `b` are equal, comparing every byte so the time taken does not
+/// tell how much of them matched
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Why a username or password is not accepted for a new account
+pub fn check_credentials(username: &str, password: &str) -> std::result::Result<(), String> {
+    if username.is_empty() || username.len() > MAX_USERNAME_LEN {
+        return Err(format!("Username must be 1 to {} characters", MAX_USERNAME_LEN));
+    }
+    if !username.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.')) {
+        return Err("Username may only contain letters, digits, '-', '_' and '.'".to_string());
+    }
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!("Password must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+    Ok(())
+}
+
+/// PBKDF2-HMAC-SHA256 (RFC 8018) with a 32-byte output, a single block
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mac = Hmac::<Sha256>::new_from_slice(password).expect("HMAC accepts keys of any length");
+    let mut first = mac.clone();
+    first.update(salt);
+    first.update(&1u32.to_be_bytes());
+    let mut block = first.finalize().into_bytes();
+    let mut output: [u8; 32] = block.into();
+    for _ in 1..iterations {
+        let mut round = mac.clone();
+        round.update(&block);
+        block = round.finalize().into_bytes();
+        output.iter_mut().zip(block.iter()).for_each(|(out, b)| *out ^= b);
+    }
+    output
+}
+
+/// A signed-in user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub username: String,
+    pub role: Role,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// The node's user accounts and their open sessions
+#[derive(Debug)]
+pub struct UserStore {
+    storage: Option<Arc<PersistentStorage>>,
+    users: RwLock<HashMap<String, UserRecord>>,
+    /// Token -> session
+    sessions: Mutex<HashMap<String, Session>>,
+    session_ttl: Duration,
+}
+
+impl UserStore {
+    /// Load the accounts from `storage`, or start empty without it; sessions last
+    /// `session_ttl`
+    pub fn new(storage: Option<Arc<PersistentStorage>>, session_ttl: Duration) -> Result<Self> {
+        let mut users = HashMap::new();
+        if let Some(storage) = &storage {
+            for record in storage.load_users()? {
+                users.insert(record.username.clone(), record);
+            }
+        }
+        Ok(Self {
+            storage,
+            users: RwLock::new(users),
+            sessions: Mutex::new(HashMap::new()),
+            session_ttl,
+        })
+    }
+
+    /// Create an account; `None` if the username is taken
+    ///
+    /// Hashes the password, so call it off the async workers.
+    pub fn create(&self, username: &str, password: &str, role: Role) -> Result<Option<UserAccount>> {
+        let record = UserRecord::new(username, password, role);
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        if users.contains_key(username) {
+            return Ok(None);
+        }
+        if let Some(storage) = &self.storage {
+            storage.store_user(&record)?;
+        }
+        let account = record.account();
+        users.insert(record.username.clone(), record);
+        Ok(Some(account))
+    }
+
+    /// Delete an account and end its sessions; returns whether it existed
+    pub fn remove(&self, username: &str) -> Result<bool> {
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        if users.remove(username).is_none() {
+            return Ok(false);
+        }
+        if let Some(storage) = &self.storage {
+            storage.remove_user(username)?;
+        }
+        self.lock_sessions().retain(|_, session| session.username != username);
+        Ok(true)
+    }
+
+    /// Every account, ordered by username
+    pub fn list(&self) -> Vec<UserAccount> {
+        let users = self.users.read().unwrap_or_else(|e| e.into_inner());
+        let mut accounts: Vec<UserAccount> = users.values().map(UserRecord::account).collect();
+        accounts.sort_by(|a, b| a.username.cmp(&b.username));
+        accounts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.read().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    /// Start a session if `password` is `username`'s, returning its token
+    ///
+    /// Hashes the password, so call it off the async workers.
+    pub fn login(&self, username: &str, password: &str) -> Option<(String, Session)> {
+        let record = self.users.read().unwrap_or_else(|e| e.into_inner()).get(username).cloned()?;
+        if !record.verify_password(password) {
+            return None;
+        }
+        let mut token = [0u8; TOKEN_LEN];
+        rand::rng().fill_bytes(&mut token);
+        let token = hex::encode(token);
+        let ttl = chrono::Duration::from_std(self.session_ttl).unwrap_or(chrono::Duration::MAX);
+        let session = Session {
+            username: record.username,
+            role: record.role,
+            expires_at: Utc::now().checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC),
+        };
+        let mut sessions = self.lock_sessions();
+        let now = Utc::now();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(token.clone(), session.clone());
+        Some((token, session))
+    }
+
+    /// End the session of `token`; returns whether there was one
+    pub fn logout(&self, token: &str) -> bool {
+        self.lock_sessions().remove(token).is_some()
+    }
+
+    /// The unexpired session of `token`
+    pub fn session(&self, token: &str) -> Option<Session> {
+        let mut sessions = self.lock_sessions();
+        match sessions.get(token) {
+            Some(session) if session.expires_at > Utc::now() => Some(session.clone()),
+            Some(_) => {
+                sessions.remove(token);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn lock_sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pbkdf2_vectors() {
+        // RFC 7914, section 11, and the matching single-iteration vector
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 2)),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+    }
+
+    #[test]
+    fn test_accounts_and_sessions() {
+        let storage = Arc::new(PersistentStorage::in_memory());
+        let users = UserStore::new(Some(storage.clone()), Duration::from_secs(60)).unwrap();
+        assert!(check_credentials("student 1", "long enough").is_err());
+        assert!(check_credentials("student1", "short").is_err());
+
+        let account = users.create("student1", "correct horse", Role::Viewer).unwrap().unwrap();
+        assert_eq!(account.role, Role::Viewer);
+        assert!(users.create("student1", "another password", Role::Admin).unwrap().is_none());
+
+        assert!(users.login("student1", "wrong password").is_none());
+        let (token, session) = users.login("student1", "correct horse").unwrap();
+        assert_eq!((session.username.as_str(), session.role), ("student1", Role::Viewer));
+        assert_eq!(users.session(&token), Some(session));
+
+        // Accounts survive a restart, sessions do not
+        let reloaded = UserStore::new(Some(storage), Duration::from_secs(60)).unwrap();
+        assert_eq!(reloaded.list(), vec![account]);
+        assert!(reloaded.session(&token).is_none());
+
+        assert!(users.remove("student1").unwrap());
+        assert!(users.session(&token).is_none());
+        assert!(users.is_empty());
+    }
+
+    #[test]
+    fn test_sessions_expire() {
+        let users = UserStore::new(None, Duration::ZERO).unwrap();
+        users.create("teacher", "correct horse", Role::Admin).unwrap();
+        let (token, _) = users.login("teacher", "correct horse").unwrap();
+        assert!(users.session(&token).is_none());
+        assert!(!users.logout(&token));
+    }
+}
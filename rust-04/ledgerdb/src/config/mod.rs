@@ -2,15 +2,33 @@
 //!
 //! This module handles all configuration aspects including environment variables,
 //! configuration files, and runtime settings with proper validation and defaults.
+//!
+//! A TOML file read by [`Config::from_file`] has one table per section (`[server]`,
+//! `[blockchain]`, ...); anything it leaves out keeps its default, and `LEDGER_*`
+//! environment variables override both. Part of the configuration can be reloaded
+//! while the node runs; see [`reload`].
 
+use crate::core::RewardSchedule;
+use crate::crypto::Hash256;
 use crate::error::{ConfigError, Result};
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use tracing::Level;
 
+pub mod reload;
+
+pub use reload::{ConfigReloader, RuntimeSettings};
+
 /// Main application configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     /// Server configuration
     pub server: ServerConfig,
@@ -24,10 +42,19 @@ pub struct Config {
     pub logging: LoggingConfig,
     /// API configuration
     pub api: ApiConfig,
+    /// Webhook notifications
+    pub webhooks: WebhookConfig,
+    /// Independent chains hosted next to the node's own
+    pub tenants: TenantsConfig,
+    /// Initial block download from other nodes
+    pub sync: SyncConfig,
+    /// How other nodes reach this one
+    pub p2p: P2pConfig,
 }
 
 /// Server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     /// Server host address
     pub host: String,
@@ -35,21 +62,56 @@ pub struct ServerConfig {
     pub port: u16,
     /// Maximum number of concurrent connections
     pub max_connections: usize,
-    /// Request timeout in seconds
+    /// Request timeout in seconds, for writes and anything without its own timeout
     pub request_timeout: u64,
-    /// Enable CORS
+    /// Timeout in seconds for `GET` and `HEAD` requests
+    pub read_timeout: u64,
+    /// Timeout in seconds for export downloads such as the UTXO snapshot
+    pub export_timeout: u64,
+    /// Seconds to let in-flight requests finish after a shutdown signal
+    pub shutdown_timeout: u64,
+    /// Answer cross-origin requests from browsers as `cors` allows
     pub enable_cors: bool,
+    /// Which web pages may call the API from a browser
+    pub cors: CorsConfig,
     /// Static files directory
     pub static_dir: Option<PathBuf>,
+    /// TLS certificate chain (PEM); HTTPS is served when both cert and key are set
+    pub tls_cert_path: Option<PathBuf>,
+    /// TLS private key (PEM)
+    pub tls_key_path: Option<PathBuf>,
+    /// Serve HTTPS with a generated self-signed certificate when no cert is configured
+    pub tls_self_signed: bool,
+    /// Unix domain socket to serve the API on, for local tooling and reverse proxies
+    pub unix_socket_path: Option<PathBuf>,
+    /// Listen on `host:port`; may be turned off when a Unix socket is configured
+    pub tcp_enabled: bool,
+}
+
+/// Cross-origin access to the API from browsers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API, e.g. `https://explorer.example.com`, or
+    /// `*` for any; when empty, any in development (`LEDGER_ENV=development`) and
+    /// none otherwise
+    pub allowed_origins: Vec<String>,
+    /// Let browsers send cookies and `Authorization` headers cross-origin; needs
+    /// explicit origins
+    pub allow_credentials: bool,
 }
 
 /// Blockchain configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BlockchainConfig {
+    /// Network the node runs on; selects the genesis block, address prefix and
+    /// default reward schedule
+    pub network: Network,
+    /// Block reward schedule, overriding the network's default
+    pub reward_schedule: Option<RewardSchedule>,
     /// Initial mining difficulty
     pub initial_difficulty: u32,
-    /// Mining reward in smallest units
-    pub mining_reward: u64,
     /// Maximum transactions per block
     pub max_transactions_per_block: usize,
     /// Difficulty adjustment interval in blocks
@@ -60,11 +122,131 @@ pub struct BlockchainConfig {
     pub max_block_size: usize,
     /// Transaction fee per byte
     pub transaction_fee_per_byte: u64,
+    /// Hex block hashes pinned at given heights
+    #[serde(with = "height_keys")]
+    pub checkpoints: BTreeMap<u64, String>,
+    /// Hours a transaction may wait in the pool before it is dropped
+    pub mempool_expiry_hours: u64,
+}
+
+pub use ledgerdb_types::Network;
+
+/// Reward schedule `network` uses when none is configured
+pub fn default_reward_schedule(network: Network) -> RewardSchedule {
+    match network {
+        Network::Mainnet => RewardSchedule::Halving { initial: 50_000_000, interval: 210_000 },
+        Network::Testnet => RewardSchedule::Halving { initial: 50_000_000, interval: 10_000 },
+        Network::Regtest => RewardSchedule::Fixed { reward: 50_000_000 },
+    }
+}
+
+impl BlockchainConfig {
+    /// The configured reward schedule, or the network's default
+    pub fn effective_reward_schedule(&self) -> RewardSchedule {
+        self.reward_schedule
+            .clone()
+            .unwrap_or_else(|| default_reward_schedule(self.network))
+    }
+
+    /// The configured checkpoints with their hashes decoded
+    pub fn checkpoint_hashes(&self) -> Result<BTreeMap<u64, Hash256>> {
+        self.checkpoints
+            .iter()
+            .map(|(height, hash)| {
+                let hash = Hash256::from_hex(hash).map_err(|e| {
+                    ConfigError::Invalid(format!("blockchain.checkpoints[{}]: {}", height, e))
+                })?;
+                Ok((*height, hash))
+            })
+            .collect()
+    }
+}
+
+/// Serializes height-keyed maps with string keys, which TOML requires
+mod height_keys {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(map: &BTreeMap<u64, String>, serializer: S) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<String, &String> = map.iter().map(|(height, v)| (height.to_string(), v)).collect();
+        map.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<u64, String>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(height, v)| {
+                let height = height
+                    .parse()
+                    .map_err(|_| D::Error::custom(format!("checkpoint height '{}' is not a number", height)))?;
+                Ok((height, v))
+            })
+            .collect()
+    }
+}
+
+/// Parse `LEDGER_CHECKPOINTS`: comma-separated `<height>:<hex hash>` pairs
+/// Comma-separated CIDR networks, as in `LEDGER_ADMIN_ALLOW=127.0.0.1/32,::1/128`
+fn parse_networks(var: &str, s: &str) -> Result<Vec<IpNet>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry.parse().map_err(|_| {
+                ConfigError::Invalid(format!("{}: '{}' is not a CIDR network", var, entry)).into()
+            })
+        })
+        .collect()
+}
+
+fn parse_checkpoints(s: &str) -> Result<BTreeMap<u64, String>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (height, hash) = entry.split_once(':').ok_or_else(|| {
+                ConfigError::Invalid(format!("checkpoint '{}' is not <height>:<hash>", entry))
+            })?;
+            let height = height.trim().parse().map_err(|_| {
+                ConfigError::Invalid(format!("checkpoint height '{}' is not a number", height))
+            })?;
+            Ok((height, hash.trim().to_lowercase()))
+        })
+        .collect()
+}
+
+/// Database behind [`PersistentStorage`](crate::storage::PersistentStorage)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// sled database at `db_path`
+    Sled,
+    /// Process memory; everything is lost on exit
+    Memory,
+    /// RocksDB database at `db_path`; needs the `rocksdb` feature
+    #[serde(rename = "rocksdb")]
+    RocksDb,
+}
+
+impl FromStr for StorageBackend {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sled" => Ok(StorageBackend::Sled),
+            "memory" => Ok(StorageBackend::Memory),
+            "rocksdb" => Ok(StorageBackend::RocksDb),
+            _ => Err(ConfigError::Invalid(format!("unknown storage backend '{}'", s))),
+        }
+    }
 }
 
 /// Storage configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StorageConfig {
+    /// Database backend
+    pub backend: StorageBackend,
     /// Database file path
     pub db_path: PathBuf,
     /// Enable database compression
@@ -77,10 +259,20 @@ pub struct StorageConfig {
     pub auto_backup_interval_hours: Option<u64>,
     /// Maximum number of backup files to keep
     pub max_backup_files: usize,
+    /// UTXO snapshot to start from instead of replaying the stored blocks
+    pub utxo_snapshot: Option<PathBuf>,
+    /// SQLite database indexing the chain for explorer queries (`sqlite-index` feature)
+    pub sqlite_index: Option<PathBuf>,
+    /// Append every accepted transaction and connected block to this file, for
+    /// `ledgerdb replay`
+    pub replay_log: Option<PathBuf>,
+    /// Storage calls from async code that may run at once, each on a blocking thread
+    pub io_workers: usize,
 }
 
 /// Mining configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MiningConfig {
     /// Enable mining
     pub enabled: bool,
@@ -96,6 +288,7 @@ pub struct MiningConfig {
 
 /// Logging configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LoggingConfig {
     /// Log level
     pub level: String,
@@ -103,6 +296,12 @@ pub struct LoggingConfig {
     pub format: String,
     /// Log file path (None for stdout)
     pub file: Option<PathBuf>,
+    /// Rotate the log file before it grows past this many megabytes (None for no size limit)
+    pub max_file_size_mb: Option<u64>,
+    /// Also rotate the log file when the hour or day changes
+    pub rotation: LogRotation,
+    /// Rotated log files kept next to `file` (`<file>.1` is the newest); older ones are deleted
+    pub max_files: usize,
     /// Enable colored output
     pub colored: bool,
     /// Include timestamps
@@ -111,25 +310,89 @@ pub struct LoggingConfig {
     pub thread_ids: bool,
 }
 
+/// When the log file is rotated regardless of its size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl FromStr for LogRotation {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" => Ok(LogRotation::Never),
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            _ => Err(ConfigError::Invalid(format!("unknown log rotation '{}'", s))),
+        }
+    }
+}
+
 /// API configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiConfig {
     /// API rate limiting (requests per minute)
     pub rate_limit: Option<u32>,
     /// Enable API authentication
     pub enable_auth: bool,
-    /// API key for authentication
+    /// API key for authentication; accepted as an admin bearer token
     pub api_key: Option<String>,
+    /// Minutes a login token stays valid
+    pub session_ttl_minutes: u64,
     /// Maximum request body size in bytes
     pub max_request_size: usize,
     /// Enable request/response logging
     pub enable_request_logging: bool,
+    /// Most queries accepted in one `POST /api/batch`
+    pub max_batch_size: usize,
     /// WebSocket configuration
     pub websocket: WebSocketConfig,
+    /// Who may reach the admin and mining endpoints
+    pub access: AccessConfig,
+}
+
+/// Client address rules for the admin and mining endpoints
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessConfig {
+    /// Reverse proxies whose `X-Forwarded-For` is believed
+    pub trusted_proxies: Vec<IpNet>,
+    /// `/admin/...`
+    pub admin: IpAccessList,
+    /// `/api/mine` and `/api/blocks/submit`
+    pub mining: IpAccessList,
+}
+
+/// Networks allowed to and refused from a group of endpoints
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpAccessList {
+    /// Networks let in; when empty, every address that is not denied is
+    pub allow: Vec<IpNet>,
+    /// Networks refused, even if they are also allowed
+    pub deny: Vec<IpNet>,
+}
+
+impl IpAccessList {
+    /// Whether a client at `ip` is let in
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        // IPv4 clients on a dual-stack socket show up as `::ffff:a.b.c.d`
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
 }
 
 /// WebSocket configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WebSocketConfig {
     /// Maximum number of WebSocket connections
     pub max_connections: usize,
@@ -141,16 +404,117 @@ pub struct WebSocketConfig {
     pub message_buffer_size: usize,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            server: ServerConfig::default(),
-            blockchain: BlockchainConfig::default(),
-            storage: StorageConfig::default(),
-            mining: MiningConfig::default(),
-            logging: LoggingConfig::default(),
-            api: ApiConfig::default(),
-        }
+/// Webhook configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// Delivery attempts after the first before an event is dropped
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds; doubles on each retry
+    pub initial_backoff_ms: u64,
+    /// Per-request timeout in seconds
+    pub timeout_seconds: u64,
+    /// Endpoints to notify
+    pub endpoints: Vec<WebhookEndpoint>,
+}
+
+/// One URL that receives webhook events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    /// URL the events are POSTed to
+    pub url: String,
+    /// Events to send; empty sends every kind
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+    /// Addresses whose incoming transactions produce `transaction` events
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Key for the HMAC-SHA256 `X-Ledger-Signature` header; unsigned if unset
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// Multi-tenant hosting
+///
+/// Each tenant is a separate chain served under `/t/{name}/`, kept in its own
+/// subdirectory of `dir`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantsConfig {
+    /// Load the tenants in `dir` and serve the tenant admin endpoints
+    pub enabled: bool,
+    /// Directory holding one subdirectory per tenant
+    pub dir: PathBuf,
+}
+
+/// Initial block download and block relay
+///
+/// With `peers` set, the node downloads and checks the header chain of the peer
+/// with the highest tip, then fetches the blocks under it in batches from all the
+/// peers and connects them, before reporting itself synced. After that it
+/// announces the blocks it gains to the same peers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Base URLs of the nodes to sync from, e.g. `http://10.0.0.5:8080`
+    pub peers: Vec<String>,
+    /// Blocks asked for in one `POST /api/batch`
+    pub batch_size: usize,
+    /// Batches requested at once
+    pub max_in_flight: usize,
+    /// Seconds to wait for a peer to answer
+    pub timeout_seconds: u64,
+    /// Announce new blocks to `peers` as compact blocks
+    pub announce_blocks: bool,
+}
+
+/// Reachability for other nodes
+///
+/// Nodes talk to each other over the HTTP API, so a peer connects to one of the
+/// API's TCP listeners. A node behind a home router can have the router forward
+/// those ports with `upnp`, and tells others where to find it with
+/// `external_address`; both show at `/api/p2p/addresses`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct P2pConfig {
+    /// Further `ip:port` addresses to serve the API on, next to `server.host:server.port`
+    pub listen_addresses: Vec<SocketAddr>,
+    /// URL other nodes should sync from, e.g. `http://203.0.113.7:3000`; without it
+    /// a UPnP mapping's external address is advertised
+    pub external_address: Option<String>,
+    /// Ask the local router over UPnP to forward the listening ports (needs the
+    /// `upnp` feature)
+    pub upnp: bool,
+    /// Seconds each UPnP mapping is leased for; it is renewed halfway through
+    pub upnp_lease_seconds: u32,
+}
+
+/// Kinds of chain event a webhook endpoint can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEventKind {
+    /// A block was added to the chain
+    Block,
+    /// The chain switched to a different branch
+    Reorg,
+    /// A block paid one of the endpoint's addresses
+    Transaction,
+}
+
+impl fmt::Display for WebhookEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WebhookEventKind::Block => "block",
+            WebhookEventKind::Reorg => "reorg",
+            WebhookEventKind::Transaction => "transaction",
+        })
+    }
+}
+
+impl ServerConfig {
+    /// Get the listen address as `host:port`
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
     }
 }
 
@@ -161,8 +525,17 @@ impl Default for ServerConfig {
             port: 3000,
             max_connections: 1000,
             request_timeout: 30,
+            read_timeout: 10,
+            export_timeout: 300,
+            shutdown_timeout: 10,
             enable_cors: true,
+            cors: CorsConfig::default(),
             static_dir: Some(PathBuf::from("frontend/dist")),
+            tls_cert_path: None,
+            tls_key_path: None,
+            tls_self_signed: false,
+            unix_socket_path: None,
+            tcp_enabled: true,
         }
     }
 }
@@ -170,13 +543,16 @@ impl Default for ServerConfig {
 impl Default for BlockchainConfig {
     fn default() -> Self {
         Self {
+            network: Network::Mainnet,
+            reward_schedule: None,
             initial_difficulty: 2,
-            mining_reward: 50_000_000, // 50 tokens with 6 decimal places
             max_transactions_per_block: 100,
             difficulty_adjustment_interval: 10,
             target_block_time: 60, // 1 minute
             max_block_size: 1_048_576, // 1 MB
             transaction_fee_per_byte: 1,
+            checkpoints: BTreeMap::new(),
+            mempool_expiry_hours: 336, // 2 weeks
         }
     }
 }
@@ -184,12 +560,17 @@ impl Default for BlockchainConfig {
 impl Default for StorageConfig {
     fn default() -> Self {
         Self {
+            backend: StorageBackend::Sled,
             db_path: PathBuf::from("ledgerdb.db"),
             enable_compression: true,
             cache_size_mb: 64,
             backup_dir: Some(PathBuf::from("backups")),
             auto_backup_interval_hours: Some(24),
             max_backup_files: 7,
+            utxo_snapshot: None,
+            sqlite_index: None,
+            replay_log: None,
+            io_workers: 4,
         }
     }
 }
@@ -212,6 +593,9 @@ impl Default for LoggingConfig {
             level: "info".to_string(),
             format: "pretty".to_string(),
             file: None,
+            max_file_size_mb: Some(100),
+            rotation: LogRotation::Daily,
+            max_files: 7,
             colored: true,
             timestamps: true,
             thread_ids: false,
@@ -219,15 +603,25 @@ impl Default for LoggingConfig {
     }
 }
 
+impl ApiConfig {
+    /// How long a login token stays valid
+    pub fn session_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.session_ttl_minutes.saturating_mul(60))
+    }
+}
+
 impl Default for ApiConfig {
     fn default() -> Self {
         Self {
             rate_limit: Some(100), // 100 requests per minute
             enable_auth: false,
             api_key: None,
+            session_ttl_minutes: 8 * 60,
             max_request_size: 1_048_576, // 1 MB
             enable_request_logging: true,
+            max_batch_size: 100,
             websocket: WebSocketConfig::default(),
+            access: AccessConfig::default(),
         }
     }
 }
@@ -243,91 +637,334 @@ impl Default for WebSocketConfig {
     }
 }
 
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff_ms: 500,
+            timeout_seconds: 10,
+            endpoints: Vec::new(),
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            peers: Vec::new(),
+            batch_size: 50,
+            max_in_flight: 4,
+            timeout_seconds: 30,
+            announce_blocks: true,
+        }
+    }
+}
+
+impl Default for P2pConfig {
+    fn default() -> Self {
+        Self {
+            listen_addresses: Vec::new(),
+            external_address: None,
+            upnp: false,
+            upnp_lease_seconds: 3600,
+        }
+    }
+}
+
+impl Default for TenantsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: PathBuf::from("tenants"),
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from environment variables and defaults
     pub fn from_env() -> Result<Self> {
         let mut config = Self::default();
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
 
+    /// Load a TOML configuration file, with environment variables layered on top
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        let mut config: Self = toml::from_str(&contents)
+            .map_err(|e| ConfigError::Parse(format!("{}: {}", path.display(), e)))?;
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The configuration as TOML, in the format read by [`Config::from_file`]
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| ConfigError::Parse(e.to_string()).into())
+    }
+
+    /// Override settings from `LEDGER_*` (and `PORT`) environment variables
+    fn apply_env(&mut self) -> Result<()> {
         // Server configuration
         if let Ok(host) = env::var("LEDGER_HOST") {
-            config.server.host = host;
+            self.server.host = host;
         }
         if let Ok(port) = env::var("PORT").or_else(|_| env::var("LEDGER_PORT")) {
-            config.server.port = port.parse().map_err(|_| ConfigError::InvalidConfig {
+            self.server.port = port.parse().map_err(|_| ConfigError::InvalidConfig {
                 field: "port".to_string(),
             })?;
         }
         if let Ok(max_conn) = env::var("LEDGER_MAX_CONNECTIONS") {
-            config.server.max_connections = max_conn.parse().map_err(|_| {
+            self.server.max_connections = max_conn.parse().map_err(|_| {
                 ConfigError::InvalidConfig {
                     field: "max_connections".to_string(),
                 }
             })?;
         }
 
+        if let Ok(timeout) = env::var("LEDGER_REQUEST_TIMEOUT") {
+            self.server.request_timeout = timeout.parse().map_err(|_| {
+                ConfigError::Invalid("request_timeout".to_string())
+            })?;
+        }
+        if let Ok(timeout) = env::var("LEDGER_READ_TIMEOUT") {
+            self.server.read_timeout = timeout.parse().map_err(|_| {
+                ConfigError::Invalid("read_timeout".to_string())
+            })?;
+        }
+        if let Ok(timeout) = env::var("LEDGER_EXPORT_TIMEOUT") {
+            self.server.export_timeout = timeout.parse().map_err(|_| {
+                ConfigError::Invalid("export_timeout".to_string())
+            })?;
+        }
+        if let Ok(grace) = env::var("LEDGER_SHUTDOWN_TIMEOUT") {
+            self.server.shutdown_timeout = grace.parse().map_err(|_| {
+                ConfigError::Invalid("shutdown_timeout".to_string())
+            })?;
+        }
+
+        if let Ok(cert) = env::var("LEDGER_TLS_CERT") {
+            self.server.tls_cert_path = Some(PathBuf::from(cert));
+        }
+        if let Ok(key) = env::var("LEDGER_TLS_KEY") {
+            self.server.tls_key_path = Some(PathBuf::from(key));
+        }
+        if let Ok(self_signed) = env::var("LEDGER_TLS_SELF_SIGNED") {
+            self.server.tls_self_signed = self_signed.parse().map_err(|_| {
+                ConfigError::Invalid("tls_self_signed".to_string())
+            })?;
+        }
+        if let Ok(origins) = env::var("LEDGER_CORS_ORIGINS") {
+            self.server.cors.allowed_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Ok(credentials) = env::var("LEDGER_CORS_CREDENTIALS") {
+            self.server.cors.allow_credentials = credentials.parse().map_err(|_| {
+                ConfigError::Invalid("cors.allow_credentials".to_string())
+            })?;
+        }
+        if let Ok(socket) = env::var("LEDGER_UNIX_SOCKET") {
+            self.server.unix_socket_path = Some(PathBuf::from(socket));
+        }
+        if let Ok(tcp) = env::var("LEDGER_TCP_ENABLED") {
+            self.server.tcp_enabled = tcp.parse().map_err(|_| {
+                ConfigError::Invalid("tcp_enabled".to_string())
+            })?;
+        }
+
         // Blockchain configuration
         if let Ok(difficulty) = env::var("LEDGER_INITIAL_DIFFICULTY") {
-            config.blockchain.initial_difficulty = difficulty.parse().map_err(|_| {
+            self.blockchain.initial_difficulty = difficulty.parse().map_err(|_| {
                 ConfigError::InvalidConfig {
                     field: "initial_difficulty".to_string(),
                 }
             })?;
         }
-        if let Ok(reward) = env::var("LEDGER_MINING_REWARD") {
-            config.blockchain.mining_reward = reward.parse().map_err(|_| {
-                ConfigError::InvalidConfig {
-                    field: "mining_reward".to_string(),
-                }
+        if let Ok(network) = env::var("LEDGER_NETWORK") {
+            self.blockchain.network = network.parse().map_err(ConfigError::Invalid)?;
+        }
+        if let Ok(schedule) = env::var("LEDGER_REWARD_SCHEDULE") {
+            self.blockchain.reward_schedule = Some(schedule.parse().map_err(|e| {
+                ConfigError::Invalid(format!("reward_schedule: {}", e))
+            })?);
+        }
+        if let Ok(checkpoints) = env::var("LEDGER_CHECKPOINTS") {
+            self.blockchain.checkpoints = parse_checkpoints(&checkpoints)?;
+        }
+        if let Ok(hours) = env::var("LEDGER_MEMPOOL_EXPIRY_HOURS") {
+            self.blockchain.mempool_expiry_hours = hours.parse().map_err(|_| {
+                ConfigError::Invalid("mempool_expiry_hours".to_string())
             })?;
         }
 
         // Storage configuration
+        if let Ok(backend) = env::var("LEDGER_STORAGE_BACKEND") {
+            self.storage.backend = backend.parse()?;
+        }
         if let Ok(db_path) = env::var("LEDGER_DB_PATH") {
-            config.storage.db_path = PathBuf::from(db_path);
+            self.storage.db_path = PathBuf::from(db_path);
+        }
+        if let Ok(snapshot) = env::var("LEDGER_UTXO_SNAPSHOT") {
+            self.storage.utxo_snapshot = Some(PathBuf::from(snapshot));
+        }
+        if let Ok(index) = env::var("LEDGER_SQLITE_INDEX") {
+            self.storage.sqlite_index = Some(PathBuf::from(index));
+        }
+        if let Ok(log) = env::var("LEDGER_REPLAY_LOG") {
+            self.storage.replay_log = Some(PathBuf::from(log));
+        }
+        if let Ok(compression) = env::var("LEDGER_ENABLE_COMPRESSION") {
+            self.storage.enable_compression = compression.parse().map_err(|_| {
+                ConfigError::Invalid("enable_compression".to_string())
+            })?;
         }
         if let Ok(cache_size) = env::var("LEDGER_CACHE_SIZE_MB") {
-            config.storage.cache_size_mb = cache_size.parse().map_err(|_| {
+            self.storage.cache_size_mb = cache_size.parse().map_err(|_| {
                 ConfigError::InvalidConfig {
                     field: "cache_size_mb".to_string(),
                 }
             })?;
         }
+        if let Ok(workers) = env::var("LEDGER_STORAGE_IO_WORKERS") {
+            self.storage.io_workers = workers.parse().map_err(|_| ConfigError::InvalidConfig {
+                field: "storage_io_workers".to_string(),
+            })?;
+        }
 
         // Mining configuration
         if let Ok(enabled) = env::var("LEDGER_MINING_ENABLED") {
-            config.mining.enabled = enabled.parse().map_err(|_| ConfigError::InvalidConfig {
+            self.mining.enabled = enabled.parse().map_err(|_| ConfigError::InvalidConfig {
                 field: "mining_enabled".to_string(),
             })?;
         }
         if let Ok(threads) = env::var("LEDGER_MINING_THREADS") {
-            config.mining.threads = threads.parse().map_err(|_| ConfigError::InvalidConfig {
+            self.mining.threads = threads.parse().map_err(|_| ConfigError::InvalidConfig {
                 field: "mining_threads".to_string(),
             })?;
         }
 
         // Logging configuration
         if let Ok(level) = env::var("LEDGER_LOG_LEVEL") {
-            config.logging.level = level;
+            self.logging.level = level;
         }
         if let Ok(format) = env::var("LEDGER_LOG_FORMAT") {
-            config.logging.format = format;
+            self.logging.format = format;
+        }
+        if let Ok(file) = env::var("LEDGER_LOG_FILE") {
+            self.logging.file = Some(PathBuf::from(file));
+        }
+        if let Ok(rotation) = env::var("LEDGER_LOG_ROTATION") {
+            self.logging.rotation = rotation.parse()?;
         }
 
         // API configuration
         if let Ok(api_key) = env::var("LEDGER_API_KEY") {
-            config.api.api_key = Some(api_key);
-            config.api.enable_auth = true;
+            self.api.api_key = Some(api_key);
+            self.api.enable_auth = true;
         }
         if let Ok(rate_limit) = env::var("LEDGER_RATE_LIMIT") {
-            config.api.rate_limit = Some(rate_limit.parse().map_err(|_| {
+            self.api.rate_limit = Some(rate_limit.parse().map_err(|_| {
                 ConfigError::InvalidConfig {
                     field: "rate_limit".to_string(),
                 }
             })?);
         }
+        if let Ok(enabled) = env::var("LEDGER_ENABLE_AUTH") {
+            self.api.enable_auth = enabled.parse().map_err(|_| {
+                ConfigError::Invalid("enable_auth".to_string())
+            })?;
+        }
+        if let Ok(minutes) = env::var("LEDGER_SESSION_TTL_MINUTES") {
+            self.api.session_ttl_minutes = minutes.parse().map_err(|_| {
+                ConfigError::Invalid("session_ttl_minutes".to_string())
+            })?;
+        }
+        if let Ok(size) = env::var("LEDGER_MAX_REQUEST_SIZE") {
+            self.api.max_request_size = size.parse().map_err(|_| {
+                ConfigError::Invalid("max_request_size".to_string())
+            })?;
+        }
+        if let Ok(max_batch_size) = env::var("LEDGER_MAX_BATCH_SIZE") {
+            self.api.max_batch_size = max_batch_size.parse().map_err(|_| {
+                ConfigError::Invalid("max_batch_size".to_string())
+            })?;
+        }
 
-        config.validate()?;
-        Ok(config)
+        let access = &mut self.api.access;
+        for (var, networks) in [
+            ("LEDGER_TRUSTED_PROXIES", &mut access.trusted_proxies),
+            ("LEDGER_ADMIN_ALLOW", &mut access.admin.allow),
+            ("LEDGER_ADMIN_DENY", &mut access.admin.deny),
+            ("LEDGER_MINING_ALLOW", &mut access.mining.allow),
+            ("LEDGER_MINING_DENY", &mut access.mining.deny),
+        ] {
+            if let Ok(value) = env::var(var) {
+                *networks = parse_networks(var, &value)?;
+            }
+        }
+
+        // Tenant configuration
+        if let Ok(enabled) = env::var("LEDGER_TENANTS_ENABLED") {
+            self.tenants.enabled = enabled.parse().map_err(|_| {
+                ConfigError::Invalid("tenants_enabled".to_string())
+            })?;
+        }
+        if let Ok(dir) = env::var("LEDGER_TENANTS_DIR") {
+            self.tenants.dir = PathBuf::from(dir);
+        }
+
+        // Sync configuration
+        if let Ok(peers) = env::var("LEDGER_SYNC_PEERS") {
+            self.sync.peers = peers
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(String::from)
+                .collect();
+        }
+
+        // P2P configuration
+        if let Ok(addresses) = env::var("LEDGER_P2P_LISTEN") {
+            self.p2p.listen_addresses = addresses
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(|address| address.parse().map_err(|_| ConfigError::Invalid(format!("p2p.listen_addresses: {}", address))))
+                .collect::<std::result::Result<_, _>>()?;
+        }
+        if let Ok(address) = env::var("LEDGER_P2P_EXTERNAL_ADDRESS") {
+            self.p2p.external_address = Some(address);
+        }
+        if let Ok(upnp) = env::var("LEDGER_P2P_UPNP") {
+            self.p2p.upnp = upnp.parse().map_err(|_| {
+                ConfigError::Invalid("p2p.upnp".to_string())
+            })?;
+        }
+
+        // Webhook configuration
+        if let Ok(urls) = env::var("LEDGER_WEBHOOKS") {
+            let secret = env::var("LEDGER_WEBHOOK_SECRET").ok();
+            self.webhooks.endpoints = urls
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(|url| WebhookEndpoint {
+                    url: url.to_string(),
+                    events: Vec::new(),
+                    addresses: Vec::new(),
+                    secret: secret.clone(),
+                })
+                .collect();
+        }
+
+        Ok(())
     }
 
     /// Validate the configuration
@@ -342,6 +979,50 @@ impl Config {
             .into());
         }
 
+        if self.server.tls_cert_path.is_some() != self.server.tls_key_path.is_some() {
+            return Err(ConfigError::Invalid(
+                "server.tls_cert_path and server.tls_key_path must be set together".to_string(),
+            )
+            .into());
+        }
+
+        if !self.server.tcp_enabled && self.server.unix_socket_path.is_none() {
+            return Err(ConfigError::Invalid(
+                "server.tcp_enabled is false but no server.unix_socket_path is set".to_string(),
+            )
+            .into());
+        }
+
+        for (i, origin) in self.server.cors.allowed_origins.iter().enumerate() {
+            if origin == "*" {
+                if self.server.cors.allow_credentials {
+                    return Err(ConfigError::Invalid(
+                        "server.cors.allow_credentials needs explicit origins, not '*'".to_string(),
+                    )
+                    .into());
+                }
+                continue;
+            }
+            let host = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://"));
+            if !host.is_some_and(|host| !host.is_empty() && !host.contains('/') && host.bytes().all(|b| b.is_ascii_graphic())) {
+                return Err(ConfigError::Invalid(format!(
+                    "server.cors.allowed_origins[{}] '{}' is not an origin like https://example.com",
+                    i, origin
+                ))
+                .into());
+            }
+        }
+
+        for (field, seconds) in [
+            ("server.request_timeout", self.server.request_timeout),
+            ("server.read_timeout", self.server.read_timeout),
+            ("server.export_timeout", self.server.export_timeout),
+        ] {
+            if seconds == 0 {
+                return Err(ConfigError::Invalid(format!("{} must be at least 1 second", field)).into());
+            }
+        }
+
         // Validate blockchain config
         if self.blockchain.initial_difficulty == 0 {
             return Err(ConfigError::ValueOutOfRange {
@@ -352,6 +1033,14 @@ impl Config {
             .into());
         }
 
+        if let Some(schedule) = &self.blockchain.reward_schedule {
+            schedule.validate().map_err(|e| {
+                ConfigError::Invalid(format!("blockchain.reward_schedule: {}", e))
+            })?;
+        }
+
+        self.blockchain.checkpoint_hashes()?;
+
         if self.blockchain.max_transactions_per_block == 0 {
             return Err(ConfigError::ValueOutOfRange {
                 field: "blockchain.max_transactions_per_block".to_string(),
@@ -361,6 +1050,29 @@ impl Config {
             .into());
         }
 
+        if self.blockchain.mempool_expiry_hours == 0 {
+            return Err(ConfigError::Invalid(
+                "blockchain.mempool_expiry_hours must be at least 1".to_string(),
+            ).into());
+        }
+
+        if self.storage.io_workers == 0 {
+            return Err(ConfigError::ValueOutOfRange {
+                field: "storage.io_workers".to_string(),
+                value: "0".to_string(),
+                range: "1+".to_string(),
+            }
+            .into());
+        }
+
+        if self.api.session_ttl_minutes == 0 {
+            return Err(ConfigError::Invalid("api.session_ttl_minutes must be at least 1".to_string()).into());
+        }
+
+        if self.api.max_request_size == 0 {
+            return Err(ConfigError::Invalid("api.max_request_size must be at least 1 byte".to_string()).into());
+        }
+
         // Validate mining config
         if self.mining.threads == 0 {
             return Err(ConfigError::ValueOutOfRange {
@@ -371,6 +1083,67 @@ impl Config {
             .into());
         }
 
+        // Validate webhook config
+        for (i, endpoint) in self.webhooks.endpoints.iter().enumerate() {
+            if !endpoint.url.starts_with("http://") && !endpoint.url.starts_with("https://") {
+                return Err(ConfigError::Invalid(format!(
+                    "webhooks.endpoints[{}].url '{}' is not an http(s) URL",
+                    i, endpoint.url
+                ))
+                .into());
+            }
+            for address in &endpoint.addresses {
+                ledgerdb_types::Address::decode_for(address, self.blockchain.network).map_err(|e| {
+                    ConfigError::Invalid(format!("webhooks.endpoints[{}].addresses: {} {}", i, address, e))
+                })?;
+            }
+        }
+
+        // Validate sync config
+        for (i, url) in self.sync.peers.iter().enumerate() {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(ConfigError::Invalid(format!(
+                    "sync.peers[{}] '{}' is not an http(s) URL",
+                    i, url
+                ))
+                .into());
+            }
+        }
+        if !(1..=ledgerdb_types::DEFAULT_MAX_BATCH_SIZE).contains(&self.sync.batch_size) {
+            return Err(ConfigError::Invalid(format!(
+                "sync.batch_size must be between 1 and {}",
+                ledgerdb_types::DEFAULT_MAX_BATCH_SIZE
+            ))
+            .into());
+        }
+        for (field, value) in [
+            ("sync.max_in_flight", self.sync.max_in_flight as u64),
+            ("sync.timeout_seconds", self.sync.timeout_seconds),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::Invalid(format!("{} must be at least 1", field)).into());
+            }
+        }
+
+        // Validate p2p config
+        if let Some(url) = &self.p2p.external_address {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(ConfigError::Invalid(format!("p2p.external_address '{}' is not an http(s) URL", url)).into());
+            }
+        }
+        if self.p2p.upnp && !cfg!(feature = "upnp") {
+            return Err(ConfigError::Invalid("p2p.upnp needs ledgerdb built with the `upnp` feature".to_string()).into());
+        }
+        if self.p2p.upnp_lease_seconds < 60 {
+            return Err(ConfigError::Invalid("p2p.upnp_lease_seconds must be at least 60".to_string()).into());
+        }
+
+        if self.logging.max_file_size_mb == Some(0) {
+            return Err(ConfigError::Invalid(
+                "logging.max_file_size_mb must be at least 1".to_string(),
+            ).into());
+        }
+
         // Validate logging level
         match self.logging.level.to_lowercase().as_str() {
             "trace" | "debug" | "info" | "warn" | "error" => {}
@@ -399,7 +1172,17 @@ impl Config {
 
     /// Get the server address as a string
     pub fn server_address(&self) -> String {
-        format!("{}:{}", self.server.host, self.server.port)
+        self.server.address()
+    }
+
+    /// `server.cors` with the environment's default origins filled in when none
+    /// are listed: any in development, none otherwise
+    pub fn cors(&self) -> CorsConfig {
+        let mut cors = self.server.cors.clone();
+        if cors.allowed_origins.is_empty() && self.is_development() {
+            cors.allowed_origins = vec!["*".to_string()];
+        }
+        cors
     }
 
     /// Check if development mode is enabled
@@ -435,6 +1218,142 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_tls_config_validation() {
+        let mut config = Config::default();
+        config.server.tls_cert_path = Some(PathBuf::from("cert.pem"));
+        assert!(config.validate().is_err());
+
+        config.server.tls_key_path = Some(PathBuf::from("key.pem"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_listener_config_validation() {
+        let mut config = Config::default();
+        config.server.tcp_enabled = false;
+        assert!(config.validate().is_err());
+
+        config.server.unix_socket_path = Some(PathBuf::from("/tmp/ledgerdb.sock"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_sync_config_validation() {
+        let mut config = Config::default();
+        config.sync.peers = vec!["10.0.0.5:8080".to_string()];
+        assert!(config.validate().is_err());
+
+        config.sync.peers = vec!["http://10.0.0.5:8080".to_string()];
+        assert!(config.validate().is_ok());
+        config.sync.batch_size = ledgerdb_types::DEFAULT_MAX_BATCH_SIZE + 1;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_p2p_config_validation() {
+        let mut config = Config::default();
+        config.p2p.listen_addresses = vec!["127.0.0.1:3001".parse().unwrap()];
+        config.p2p.external_address = Some("203.0.113.7:3000".to_string());
+        assert!(config.validate().is_err());
+
+        config.p2p.external_address = Some("http://203.0.113.7:3000".to_string());
+        assert!(config.validate().is_ok());
+        config.p2p.upnp_lease_seconds = 0;
+        assert!(config.validate().is_err());
+
+        config.p2p.upnp_lease_seconds = 3600;
+        config.p2p.upnp = true;
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "upnp"));
+    }
+
+    #[test]
+    fn test_cors_config_validation() {
+        let mut config = Config::default();
+        config.server.cors.allowed_origins = vec!["https://explorer.example.com".to_string(), "http://localhost:5173".to_string()];
+        config.server.cors.allow_credentials = true;
+        assert!(config.validate().is_ok());
+
+        for origin in ["explorer.example.com", "https://explorer.example.com/", "https://"] {
+            config.server.cors.allowed_origins = vec![origin.to_string()];
+            assert!(config.validate().is_err(), "{}", origin);
+        }
+        config.server.cors.allowed_origins = vec!["*".to_string()];
+        assert!(config.validate().is_err());
+        config.server.cors.allow_credentials = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_reward_schedule_selection() {
+        let mut config = Config::default();
+        assert_eq!(
+            config.blockchain.effective_reward_schedule(),
+            RewardSchedule::Halving { initial: 50_000_000, interval: 210_000 }
+        );
+
+        config.blockchain.network = "regtest".parse().unwrap();
+        assert_eq!(
+            config.blockchain.effective_reward_schedule(),
+            RewardSchedule::Fixed { reward: 50_000_000 }
+        );
+
+        let custom = RewardSchedule::LinearDecay { initial: 100, decrement: 10, interval: 5, floor: 1 };
+        config.blockchain.reward_schedule = Some(custom.clone());
+        assert_eq!(config.blockchain.effective_reward_schedule(), custom);
+
+        config.blockchain.reward_schedule = Some(RewardSchedule::Halving { initial: 1, interval: 0 });
+        assert!(config.validate().is_err());
+        assert!("devnet".parse::<Network>().is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_config() {
+        let hash = "ab".repeat(32);
+        let checkpoints = parse_checkpoints(&format!("100:{}, 2000:{}", hash, hash.to_uppercase())).unwrap();
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[&2000], hash);
+        assert!(parse_checkpoints("100").is_err());
+        assert!(parse_checkpoints(&format!("tip:{}", hash)).is_err());
+
+        let mut config = Config::default();
+        config.blockchain.checkpoints = checkpoints;
+        assert!(config.validate().is_ok());
+        assert_eq!(config.blockchain.checkpoint_hashes().unwrap()[&100].to_hex(), hash);
+
+        config.blockchain.checkpoints.insert(5, "abcd".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_webhook_config() {
+        let address = ledgerdb_types::Address(ledgerdb_types::Hash256([0xab; 32]));
+        let mut config = Config::default();
+        config.webhooks.endpoints.push(WebhookEndpoint {
+            url: "https://hooks.example.com/ledger".to_string(),
+            events: vec![WebhookEventKind::Block, WebhookEventKind::Transaction],
+            addresses: vec![address.encode_for(Network::Mainnet)],
+            secret: Some("s3cret".to_string()),
+        });
+        assert!(config.validate().is_ok());
+
+        let parsed: Config = toml::from_str(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed.webhooks.endpoints, config.webhooks.endpoints);
+
+        config.webhooks.endpoints[0].addresses.push("not-hex".to_string());
+        assert!(config.validate().is_err());
+        config.webhooks.endpoints[0].addresses.pop();
+
+        // Addresses are checked against the configured network
+        config.blockchain.network = Network::Testnet;
+        assert!(config.validate().is_err());
+        config.webhooks.endpoints[0].addresses = vec![address.encode_for(Network::Testnet)];
+        assert!(config.validate().is_ok());
+
+        config.webhooks.endpoints[0].url = "ftp://hooks.example.com".to_string();
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_tracing_level() {
         let mut config = Config::default();
@@ -448,6 +1367,84 @@ mod tests {
         assert_eq!(config.server_address(), "0.0.0.0:3000");
     }
 
+    #[test]
+    fn test_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledgerdb.toml");
+        fs::write(
+            &path,
+            r#"
+[server]
+port = 4100
+
+[blockchain]
+network = "testnet"
+checkpoints = { 10 = "abababababababababababababababababababababababababababababababab" }
+
+[blockchain.reward_schedule]
+type = "fixed"
+reward = 7
+
+[logging]
+format = "compact"
+file = "logs/ledgerdb.log"
+rotation = "hourly"
+"#,
+        )
+        .unwrap();
+
+        env::set_var("LEDGER_LOG_FORMAT", "json");
+        let config = Config::from_file(&path).unwrap();
+        env::remove_var("LEDGER_LOG_FORMAT");
+        assert_eq!(config.server.port, 4100);
+        assert_eq!(config.server.host, ServerConfig::default().host);
+        assert_eq!(config.blockchain.network, Network::Testnet);
+        assert_eq!(config.blockchain.effective_reward_schedule(), RewardSchedule::Fixed { reward: 7 });
+        assert_eq!(config.blockchain.checkpoints.len(), 1);
+        assert_eq!(config.logging.format, "json");
+        assert_eq!(config.logging.file, Some(PathBuf::from("logs/ledgerdb.log")));
+        assert_eq!(config.logging.rotation, LogRotation::Hourly);
+        assert_eq!(config.logging.max_files, LoggingConfig::default().max_files);
+
+        fs::write(&path, "[server]\nport = \"many\"\n").unwrap();
+        assert!(Config::from_file(&path).is_err());
+        assert!(Config::from_file(dir.path().join("missing.toml")).is_err());
+    }
+
+    #[test]
+    fn test_ip_access_list() {
+        let access: AccessConfig = toml::from_str(
+            r#"
+trusted_proxies = ["10.0.0.0/8"]
+admin = { allow = ["127.0.0.1/32", "192.168.0.0/16"], deny = ["192.168.7.0/24"] }
+"#,
+        )
+        .unwrap();
+        assert_eq!(access.trusted_proxies.len(), 1);
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(access.admin.permits(ip("127.0.0.1")));
+        assert!(access.admin.permits(ip("::ffff:192.168.1.20")));
+        assert!(!access.admin.permits(ip("192.168.7.1")));
+        assert!(!access.admin.permits(ip("8.8.8.8")));
+        // No rules lets everyone in
+        assert!(access.mining.permits(ip("8.8.8.8")));
+
+        assert!(toml::from_str::<AccessConfig>("trusted_proxies = [\"10.0.0.1\"]").is_err());
+        assert_eq!(parse_networks("LEDGER_ADMIN_ALLOW", "127.0.0.1/32, ::1/128").unwrap().len(), 2);
+        assert!(parse_networks("LEDGER_ADMIN_ALLOW", "localhost").is_err());
+    }
+
+    #[test]
+    fn test_toml_roundtrip() {
+        let mut config = Config::default();
+        config.blockchain.checkpoints.insert(5, "cd".repeat(32));
+        config.blockchain.reward_schedule = Some(RewardSchedule::Halving { initial: 100, interval: 10 });
+        let printed = config.to_toml().unwrap();
+        let parsed: Config = toml::from_str(&printed).unwrap();
+        assert_eq!(parsed.to_toml().unwrap(), printed);
+        assert_eq!(parsed.blockchain.checkpoints[&5], "cd".repeat(32));
+    }
+
     #[test]
     fn test_env_override() {
         env::set_var("PORT", "8080");
@@ -0,0 +1,193 @@
+//! Applying configuration changes to a running node.
+//!
+//! Only [`RuntimeSettings`] can change without a restart. [`ConfigReloader`]
+//! re-reads the configuration, refuses the new one if anything else changed, and
+//! otherwise applies the log level and rate limit and publishes the new settings
+//! to the tasks that watch them.
+
+use super::Config;
+use crate::error::{ConfigError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use tokio::sync::watch;
+
+/// Settings that take effect without a restart
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeSettings {
+    /// `logging.level`
+    pub log_level: String,
+    /// `api.rate_limit`, requests per minute
+    pub rate_limit: Option<u32>,
+    /// `mining.threads`
+    pub mining_threads: usize,
+    /// `storage.auto_backup_interval_hours`
+    pub backup_interval_hours: Option<u64>,
+    /// `storage.max_backup_files`
+    pub max_backup_files: usize,
+}
+
+impl RuntimeSettings {
+    /// The runtime-tunable part of `config`
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            log_level: config.logging.level.clone(),
+            rate_limit: config.api.rate_limit,
+            mining_threads: config.mining.threads,
+            backup_interval_hours: config.storage.auto_backup_interval_hours,
+            max_backup_files: config.storage.max_backup_files,
+        }
+    }
+
+    /// Copy these settings into `config`
+    fn apply_to(&self, config: &mut Config) {
+        config.logging.level = self.log_level.clone();
+        config.api.rate_limit = self.rate_limit;
+        config.mining.threads = self.mining_threads;
+        config.storage.auto_backup_interval_hours = self.backup_interval_hours;
+        config.storage.max_backup_files = self.max_backup_files;
+    }
+}
+
+impl Config {
+    /// Dotted names of the settings that differ between `self` and `new`, or an
+    /// error naming those that cannot change without a restart
+    pub fn reload_changes(&self, new: &Config) -> Result<Vec<String>> {
+        let mut fixed = new.clone();
+        RuntimeSettings::from_config(self).apply_to(&mut fixed);
+        let immutable = changed_paths(&to_value(self)?, &to_value(&fixed)?);
+        if !immutable.is_empty() {
+            return Err(ConfigError::Invalid(format!(
+                "{} cannot change without a restart",
+                immutable.join(", ")
+            ))
+            .into());
+        }
+        Ok(changed_paths(&to_value(self)?, &to_value(new)?))
+    }
+}
+
+/// Re-reads the configuration and applies what can change at runtime
+#[derive(Debug)]
+pub struct ConfigReloader {
+    /// File to re-read; `None` re-reads only the environment
+    path: Option<PathBuf>,
+    current: Config,
+    settings: watch::Sender<RuntimeSettings>,
+}
+
+impl ConfigReloader {
+    /// Track `current`, which was loaded from `path` (or the environment if `None`)
+    pub fn new(path: Option<PathBuf>, current: Config) -> Self {
+        let (settings, _) = watch::channel(RuntimeSettings::from_config(&current));
+        Self { path, current, settings }
+    }
+
+    /// The configuration in effect
+    pub fn current(&self) -> &Config {
+        &self.current
+    }
+
+    /// Receiver that sees every applied change to the runtime settings
+    pub fn subscribe(&self) -> watch::Receiver<RuntimeSettings> {
+        self.settings.subscribe()
+    }
+
+    /// Load the configuration again and apply it; returns the changed settings.
+    ///
+    /// Nothing is applied if the new configuration is invalid or changes a setting
+    /// that needs a restart.
+    pub fn reload(&mut self) -> Result<Vec<String>> {
+        let new = match &self.path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::from_env()?,
+        };
+        self.apply(new)
+    }
+
+    /// Apply `new` in place of the current configuration; see [`ConfigReloader::reload`]
+    pub fn apply(&mut self, new: Config) -> Result<Vec<String>> {
+        new.validate()?;
+        let changed = self.current.reload_changes(&new)?;
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+
+        let settings = RuntimeSettings::from_config(&new);
+        if settings.log_level != self.current.logging.level {
+            crate::utils::logging::set_log_level(&settings.log_level)?;
+        }
+        crate::api::set_rate_limit(settings.rate_limit);
+        self.settings.send_replace(settings);
+        self.current = new;
+        Ok(changed)
+    }
+}
+
+fn to_value(config: &Config) -> Result<Value> {
+    serde_json::to_value(config).map_err(|e| ConfigError::Parse(e.to_string()).into())
+}
+
+/// Dotted paths of the leaves that differ between two JSON values
+fn changed_paths(old: &Value, new: &Value) -> Vec<String> {
+    fn walk(old: &Value, new: &Value, path: &str, out: &mut Vec<String>) {
+        match (old, new) {
+            (Value::Object(a), Value::Object(b)) => {
+                let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    walk(a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null), &child, out);
+                }
+            }
+            _ if old != new => out.push(path.to_string()),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    walk(old, new, "", &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_changes() {
+        let current = Config::default();
+        let mut new = current.clone();
+        assert!(current.reload_changes(&new).unwrap().is_empty());
+
+        new.logging.level = "debug".to_string();
+        new.api.rate_limit = Some(30);
+        assert_eq!(current.reload_changes(&new).unwrap(), ["api.rate_limit", "logging.level"]);
+
+        new.storage.db_path = PathBuf::from("elsewhere.db");
+        new.server.port = 4000;
+        let err = current.reload_changes(&new).unwrap_err().to_string();
+        assert!(err.contains("server.port, storage.db_path"), "{}", err);
+    }
+
+    #[test]
+    fn test_reloader_applies_runtime_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledgerdb.toml");
+        std::fs::write(&path, "[mining]\nthreads = 2\n").unwrap();
+        let mut reloader = ConfigReloader::new(Some(path.clone()), Config::from_file(&path).unwrap());
+        let settings = reloader.subscribe();
+
+        std::fs::write(&path, "[mining]\nthreads = 6\n\n[api]\nrate_limit = 20\n").unwrap();
+        assert_eq!(reloader.reload().unwrap(), ["api.rate_limit", "mining.threads"]);
+        assert_eq!(settings.borrow().mining_threads, 6);
+        assert_eq!(crate::api::rate_limit(), 20);
+        assert_eq!(reloader.current().mining.threads, 6);
+
+        // A change that needs a restart leaves everything as it was
+        std::fs::write(&path, "[mining]\nthreads = 8\n\n[storage]\ndb_path = \"other.db\"\n").unwrap();
+        assert!(reloader.reload().is_err());
+        assert_eq!(settings.borrow().mining_threads, 6);
+        assert_eq!(reloader.current().mining.threads, 6);
+    }
+}
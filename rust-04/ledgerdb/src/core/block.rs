@@ -4,17 +4,24 @@
 //! including block headers, validation, and mining-related functionality.
 
 use crate::core::Transaction;
-use crate::crypto::{Hash256, MerkleTree};
-use crate::error::{Result, ValidationError};
+use crate::crypto::{estimated_block_seconds, Hash256, MerkleProof, MerkleTree, MinerEvent, MiningControl, MiningProgress};
+use crate::error::{LedgerError, Result, ValidationError};
+use crate::utils::{Clock, SystemClock};
 use chrono::{DateTime, Utc};
+use ledgerdb_types::Network;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 /// Block header containing metadata
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct BlockHeader {
     /// Block version for future upgrades
     pub version: u32,
+    /// Network the block belongs to; part of the hash, so a block is only valid on one network
+    pub network: Network,
     /// Hash of the previous block
     pub previous_hash: Hash256,
     /// Merkle root of all transactions in the block
@@ -34,19 +41,21 @@ pub struct BlockHeader {
 }
 
 impl BlockHeader {
-    /// Create a new block header
+    /// Create a new block header stamped `timestamp`
     pub fn new(
         version: u32,
         previous_hash: Hash256,
         merkle_root: Hash256,
+        timestamp: DateTime<Utc>,
         difficulty: u32,
         transaction_count: u32,
     ) -> Self {
         Self {
             version,
+            network: Network::default(),
             previous_hash,
             merkle_root,
-            timestamp: Utc::now(),
+            timestamp,
             difficulty,
             nonce: 0,
             transaction_count,
@@ -71,12 +80,8 @@ impl BlockHeader {
             return Err(ValidationError::InvalidDifficulty("Difficulty cannot be zero".to_string()).into());
         }
         
-        // Check timestamp is not too far in the future (within 2 hours)
-        let max_future_time = Utc::now() + chrono::Duration::hours(2);
-        if self.timestamp > max_future_time {
-            return Err(ValidationError::InvalidTimestamp("Block timestamp too far in future".to_string()).into());
-        }
-        
+        // Timestamps depend on the chain (median time past, network-adjusted
+        // time) and are checked by `Blockchain`
         Ok(())
     }
 
@@ -88,10 +93,54 @@ impl BlockHeader {
             self.difficulty,
         )
     }
+
+    /// Compact binary encoding, as served by `/api/headers`
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode block header: {}", e)))
+    }
+
+    /// Decode a header written by [`BlockHeader::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to decode block header: {}", e)))
+    }
+
+    /// Whether `proof` (from `/api/transactions/:hash/proof`) shows that this header
+    /// commits to the transaction `tx_hash`
+    pub fn proves_transaction(&self, tx_hash: &Hash256, proof: &MerkleProof) -> bool {
+        crate::crypto::verify_merkle_proof(&self.merkle_root, tx_hash, proof)
+    }
+}
+
+/// Check that `headers` extend the block whose hash is `previous_hash`: each one must
+/// link to the one before it, be well-formed and meet its own proof-of-work target.
+/// Returns the hash of the last header.
+///
+/// This is what a light client can check without full blocks. It does not check
+/// that each difficulty follows the retargeting rules or that timestamps respect the
+/// median time past; pick the best of several header chains by accumulated work.
+pub fn verify_header_chain(previous_hash: &Hash256, headers: &[BlockHeader]) -> Result<Hash256> {
+    let mut tip = previous_hash.clone();
+    for (i, header) in headers.iter().enumerate() {
+        if header.previous_hash != tip {
+            return Err(ValidationError::InvalidPreviousHash(format!(
+                "header {} does not link to {}", i, tip.to_hex()
+            )).into());
+        }
+        header.validate()?;
+        if !header.meets_difficulty_target() {
+            return Err(ValidationError::InvalidProofOfWork(format!(
+                "header {} does not meet difficulty {}", i, header.difficulty
+            )).into());
+        }
+        tip = header.hash();
+    }
+    Ok(tip)
 }
 
 /// Block metadata for additional information
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct BlockMetadata {
     /// Block proposer/miner identifier
     pub proposer: Option<String>,
@@ -126,13 +175,45 @@ impl Default for BlockMetadata {
     }
 }
 
+/// A block's Merkle tree, built on first use and kept until its transactions change.
+///
+/// Not part of the block's value: serde skips it and any two compare equal.
+#[derive(Debug, Clone, Default)]
+struct MerkleCache(OnceLock<MerkleTree>);
+
+impl MerkleCache {
+    /// A cache already holding `tree`
+    fn with(tree: MerkleTree) -> Self {
+        Self(OnceLock::from(tree))
+    }
+
+    fn get_or_build(&self, transactions: &[Transaction]) -> Result<&MerkleTree> {
+        if let Some(tree) = self.0.get() {
+            return Ok(tree);
+        }
+        let tree = MerkleTree::from_transactions(transactions)?;
+        Ok(self.0.get_or_init(|| tree))
+    }
+
+    fn clear(&mut self) {
+        self.0.take();
+    }
+}
+
+impl PartialEq for MerkleCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
 /// Complete block structure
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Block {
     /// Block header
     pub header: BlockHeader,
-    /// List of transactions in the block
-    pub transactions: Vec<Transaction>,
+    /// List of transactions in the block, behind [`Block::transactions`] and
+    /// [`Block::transactions_mut`] so the cached Merkle tree follows them
+    transactions: Vec<Transaction>,
     /// Block metadata
     pub metadata: BlockMetadata,
     /// Block index/height in the chain
@@ -140,57 +221,82 @@ pub struct Block {
     /// Cached block hash
     #[serde(skip)]
     pub cached_hash: Option<Hash256>,
+    /// Cached Merkle tree over `transactions`
+    #[serde(skip)]
+    merkle_cache: MerkleCache,
 }
 
 impl Block {
-    /// Create a new block
+    /// Create a new block stamped with the system clock
     pub fn new(
         index: u64,
         previous_hash: Hash256,
         transactions: Vec<Transaction>,
         difficulty: u32,
     ) -> Self {
-        let merkle_tree = MerkleTree::from_transactions(&transactions);
-        let merkle_root = merkle_tree.root();
+        Self::new_at(index, previous_hash, transactions, difficulty, SystemClock.now())
+    }
+
+    /// Create a new block stamped `timestamp`, e.g. the time of a [`Clock`](crate::utils::Clock)
+    pub fn new_at(
+        index: u64,
+        previous_hash: Hash256,
+        transactions: Vec<Transaction>,
+        difficulty: u32,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let merkle_cache = MerkleTree::from_transactions(&transactions)
+            .map(MerkleCache::with)
+            .unwrap_or_default();
+        let merkle_root = merkle_cache.0.get().map_or_else(Hash256::zero, |tree| tree.root().clone());
         
         let header = BlockHeader::new(
             1, // version
             previous_hash,
             merkle_root,
+            timestamp,
             difficulty,
             transactions.len() as u32,
         );
         
-        let mut metadata = BlockMetadata::default();
-        metadata.total_fees = transactions.iter()
-            .map(|tx| tx.fee.base_fee)
-            .sum();
-        
-        if !transactions.is_empty() {
-            metadata.average_fee = metadata.total_fees / transactions.len() as u64;
-        }
-        
         let mut block = Self {
             header,
             transactions,
-            metadata,
+            metadata: BlockMetadata::default(),
             index,
             cached_hash: None,
+            merkle_cache,
         };
         
+        block.calculate_fees();
         block.calculate_size();
         block
     }
 
+    /// Reassemble a block from its parts, e.g. a compact block's header and the
+    /// transactions found for it; nothing is checked until it is validated
+    pub fn from_parts(index: u64, header: BlockHeader, transactions: Vec<Transaction>, metadata: BlockMetadata) -> Self {
+        Self {
+            header,
+            transactions,
+            metadata,
+            index,
+            cached_hash: None,
+            merkle_cache: MerkleCache::default(),
+        }
+    }
+
     /// Create the genesis block
     pub fn genesis(genesis_address: crate::crypto::Address, initial_supply: u64) -> Self {
-        let genesis_tx = Transaction::coinbase(genesis_address, initial_supply, 0);
-        let mut block = Self::new(0, Hash256::zero(), vec![genesis_tx], 1);
-        
-        // Set genesis block timestamp to a fixed value
-        block.header.timestamp = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+        // Fixed timestamps, so every node builds the same genesis block
+        let timestamp = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
             .unwrap()
             .with_timezone(&Utc);
+        let mut genesis_tx = Transaction::coinbase(genesis_address, initial_supply, 0);
+        genesis_tx.timestamp = timestamp;
+        genesis_tx.calculate_size();
+        let mut block = Self::new_at(0, Hash256::zero(), vec![genesis_tx], 1, timestamp);
+        block.calculate_size();
         
         block.metadata.proposer = Some("genesis".to_string());
         block.metadata.extra_data = Some(b"LedgerDB Genesis Block".to_vec());
@@ -198,6 +304,13 @@ impl Block {
         block
     }
 
+    /// The same block for `network`, which changes its hash
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.header.network = network;
+        self.cached_hash = None;
+        self
+    }
+
     /// Get the hash of this block
     pub fn hash(&self) -> Hash256 {
         if let Some(cached) = &self.cached_hash {
@@ -216,22 +329,84 @@ impl Block {
         hash
     }
 
+    /// Binary encoding, as served by the block endpoints at `?verbosity=0`
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode block: {}", e)))
+    }
+
+    /// Decode a block written by [`Block::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to decode block: {}", e)))
+    }
+
     /// Calculate and set the block size
     pub fn calculate_size(&mut self) {
         let serialized = bincode::serialize(self).unwrap_or_default();
         self.header.size = serialized.len() as u64;
     }
 
-    /// Get the Merkle tree for this block's transactions
-    pub fn merkle_tree(&self) -> MerkleTree {
-        MerkleTree::from_transactions(&self.transactions)
+    /// Set the fee totals in the metadata from the transactions
+    fn calculate_fees(&mut self) {
+        self.metadata.total_fees = self.transactions.iter()
+            .map(|tx| tx.fee.base_fee)
+            .sum();
+        self.metadata.average_fee = match self.transactions.len() {
+            0 => 0,
+            count => self.metadata.total_fees / count as u64,
+        };
+    }
+
+    /// Get the Merkle tree for this block's transactions.
+    ///
+    /// The tree is built on first use and kept, so validation and proofs share it. It is
+    /// dropped when the transactions change through [`Block::transactions_mut`] and
+    /// updated in place by [`Block::replace_transaction`].
+    pub fn merkle_tree(&self) -> Result<&MerkleTree> {
+        self.merkle_cache.get_or_build(&self.transactions)
     }
 
     /// Verify the Merkle root matches the transactions
     pub fn verify_merkle_root(&self) -> bool {
-        let merkle_tree = self.merkle_tree();
-        let calculated_root = merkle_tree.root();
-        *calculated_root == self.header.merkle_root
+        self.merkle_tree()
+            .is_ok_and(|tree| *tree.root() == self.header.merkle_root)
+    }
+
+    /// The transactions in the block, coinbase first
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// The transactions, to change in place. Drops the cached Merkle tree; the header
+    /// keeps committing to the old transactions.
+    pub fn transactions_mut(&mut self) -> &mut Vec<Transaction> {
+        self.merkle_cache.clear();
+        &mut self.transactions
+    }
+
+    /// Put `transaction` at `index` in place of the one there, e.g. a miner adjusting the
+    /// coinbase, and commit the header to it. Only the changed path of the Merkle tree is
+    /// rehashed; the nonce has to be mined again.
+    pub fn replace_transaction(&mut self, index: usize, transaction: Transaction) -> Result<()> {
+        if index >= self.transactions.len() {
+            return Err(ValidationError::InvalidIndex(format!(
+                "block {} has no transaction {}", self.index, index
+            )).into());
+        }
+        let mut tree = match self.merkle_cache.0.take() {
+            Some(tree) => tree,
+            None => MerkleTree::from_transactions(&self.transactions)?,
+        };
+        tree.update_leaf(index, transaction.hash())?;
+        self.transactions[index] = transaction;
+
+        self.header.merkle_root = tree.root().clone();
+        self.merkle_cache = MerkleCache::with(tree);
+        self.cached_hash = None;
+        self.calculate_fees();
+        self.calculate_size();
+        Ok(())
     }
 
     /// Get a transaction by its hash
@@ -252,8 +427,7 @@ impl Block {
 
     /// Generate a Merkle proof for a transaction at a specific index
     pub fn generate_merkle_proof(&self, tx_index: usize) -> Result<crate::crypto::MerkleProof> {
-        let merkle_tree = self.merkle_tree();
-        merkle_tree.generate_proof_by_index(tx_index)
+        self.merkle_tree()?.generate_proof_by_index(tx_index)
     }
 
     /// Get all transaction hashes in this block
@@ -263,7 +437,12 @@ impl Block {
             .collect()
     }
 
-    /// Validate the entire block
+    /// Validate the entire block.
+    ///
+    /// Structural checks run first. The transactions, their signatures included, are
+    /// then checked on the rayon pool while the merkle root and proof of work are
+    /// checked beside them; the error returned is the one a serial pass would hit
+    /// first.
     pub fn validate(
         &self,
         previous_block: Option<&Block>,
@@ -282,14 +461,11 @@ impl Block {
             }
             
             if self.header.previous_hash != prev.hash() {
-                return Err(ValidationError::InvalidPreviousHash.into());
-            }
-            
-            // Check timestamp is after previous block
-            if self.header.timestamp <= prev.header.timestamp {
-                return Err(ValidationError::InvalidTimestamp(
-                    "Block timestamp must be after previous block".to_string()
-                ).into());
+                return Err(ValidationError::InvalidPreviousHash(format!(
+                    "expected {}, got {}",
+                    prev.hash(),
+                    self.header.previous_hash
+                )).into());
             }
         } else if self.index != 0 {
             return Err(ValidationError::InvalidBlockIndex {
@@ -303,51 +479,82 @@ impl Block {
             return Err(ValidationError::EmptyBlock.into());
         }
         
-        // First transaction should be coinbase for non-genesis blocks
-        if self.index > 0 && !self.transactions[0].is_coinbase() {
-            return Err(ValidationError::MissingCoinbase.into());
+        // Validate transaction count
+        if self.header.transaction_count != self.transactions.len() as u32 {
+            return Err(ValidationError::InvalidTransactionCount(
+                format!("Expected {} transactions, found {}", 
+                    self.transactions.len(), 
+                    self.header.transaction_count)
+            ).into());
         }
         
-        // Only first transaction should be coinbase
+        // Only the first transaction may be a coinbase, and every block but
+        // genesis starts with one
         for (i, tx) in self.transactions.iter().enumerate() {
-            if i == 0 && self.index > 0 {
-                if !tx.is_coinbase() {
+            if i == 0 {
+                if self.index > 0 && !tx.is_coinbase() {
                     return Err(ValidationError::MissingCoinbase.into());
                 }
             } else if tx.is_coinbase() {
                 return Err(ValidationError::MultipleCoinbase.into());
             }
-            
-            // Validate each transaction
-            tx.validate(utxo_set)?;
-        }
-        
-        // Verify Merkle root
-        if !self.verify_merkle_root() {
-            return Err(ValidationError::InvalidMerkleRoot.into());
         }
         
-        // Verify proof of work
-        if !self.header.meets_difficulty_target() {
-            return Err(ValidationError::InvalidProofOfWork.into());
+        let (transactions, (merkle_root, proof_of_work)) = rayon::join(
+            || self.validate_transactions(utxo_set),
+            || rayon::join(
+                || self.verify_merkle_root(),
+                // Genesis is fixed by the network rather than mined
+                || self.is_genesis() || self.header.meets_difficulty_target(),
+            ),
+        );
+        transactions?;
+        if !merkle_root {
+            return Err(ValidationError::InvalidMerkleRoot(format!(
+                "block {} does not commit to its transactions", self.hash()
+            )).into());
         }
-        
-        // Validate transaction count
-        if self.header.transaction_count != self.transactions.len() as u32 {
-            return Err(ValidationError::InvalidTransactionCount(
-                format!("Expected {} transactions, found {}", 
-                    self.transactions.len(), 
-                    self.header.transaction_count)
-            ).into());
+        if !proof_of_work {
+            return Err(ValidationError::InvalidProofOfWork(format!(
+                "block {} does not meet its difficulty target {}", self.hash(), self.header.difficulty
+            )).into());
         }
         
         Ok(())
     }
 
+    /// Validate every transaction and its signatures in parallel, reporting the
+    /// failure of the earliest transaction
+    fn validate_transactions(&self, utxo_set: &HashMap<String, crate::core::TransactionOutput>) -> Result<()> {
+        let failure = self.transactions.par_iter().find_map_first(|tx| {
+            let checked = tx.validate(utxo_set).map_err(|err| match err {
+                LedgerError::Validation(msg) => LedgerError::Validation(format!("transaction {}: {}", tx.hash(), msg)),
+                other => other,
+            });
+            let checked = checked.and_then(|()| match tx.verify_signatures()? {
+                true => Ok(()),
+                false => Err(ValidationError::InvalidSignature(format!(
+                    "transaction {} has a missing or invalid signature", tx.hash()
+                )).into()),
+            });
+            checked.err()
+        });
+        failure.map_or(Ok(()), Err)
+    }
+
     /// Mine this block by finding a valid nonce
-    pub fn mine(&mut self, progress_callback: Option<Box<dyn Fn(u64, f64) + Send>>) -> Result<()> {
+    pub fn mine(&mut self) -> Result<()> {
+        self.mine_with(&MiningControl::new())
+    }
+
+    /// Mine this block, publishing progress and the outcome through `control`.
+    ///
+    /// Fails with `MiningCancelled` once `control` is cancelled and with
+    /// `MiningTimeout` after its maximum number of attempts.
+    pub fn mine_with(&mut self, control: &MiningControl) -> Result<()> {
         use std::time::Instant;
         
+        let (height, difficulty) = (self.index, self.header.difficulty);
         let start_time = Instant::now();
         let mut attempts = 0u64;
         
@@ -357,23 +564,48 @@ impl Block {
             // Check if current nonce satisfies difficulty
             if self.header.meets_difficulty_target() {
                 self.calculate_and_cache_hash();
+                control.emit(|| MinerEvent::Found {
+                    height,
+                    difficulty,
+                    nonce: self.header.nonce,
+                    hash: self.hash(),
+                    attempts,
+                    elapsed_seconds: start_time.elapsed().as_secs_f64(),
+                });
                 return Ok(());
             }
             
             // Increment nonce
             self.header.nonce = self.header.nonce.wrapping_add(1);
             
-            // Report progress every 100,000 attempts
-            if attempts % 100_000 == 0 {
-                if let Some(ref callback) = progress_callback {
-                    let elapsed = start_time.elapsed().as_secs_f64();
-                    let hash_rate = attempts as f64 / elapsed;
-                    callback(attempts, hash_rate);
+            if attempts.is_multiple_of(control.progress_interval()) {
+                let elapsed_seconds = start_time.elapsed().as_secs_f64();
+                if control.is_cancelled() {
+                    control.emit(|| MinerEvent::Cancelled { height, difficulty, attempts, elapsed_seconds });
+                    return Err(ValidationError::MiningCancelled.into());
                 }
+                control.emit(|| {
+                    let hash_rate = if elapsed_seconds > 0.0 { attempts as f64 / elapsed_seconds } else { 0.0 };
+                    MinerEvent::Progress {
+                        height,
+                        progress: MiningProgress {
+                            current_nonce: self.header.nonce,
+                            attempts,
+                            hash_rate,
+                            elapsed_seconds,
+                            estimated_remaining_seconds: estimated_block_seconds(difficulty, hash_rate),
+                            is_complete: false,
+                            best_hash: None,
+                            target_difficulty: difficulty,
+                        },
+                    }
+                });
             }
             
             // Prevent infinite loops in tests
-            if attempts > 10_000_000 {
+            if attempts > control.max_attempts() {
+                let elapsed_seconds = start_time.elapsed().as_secs_f64();
+                control.emit(|| MinerEvent::Timeout { height, difficulty, attempts, elapsed_seconds });
                 return Err(ValidationError::MiningTimeout.into());
             }
         }
@@ -381,19 +613,18 @@ impl Block {
 
     /// Get block statistics
     pub fn stats(&self) -> BlockStats {
+        // Saturating, since a block decoded from the network may carry any amounts
         let total_tx_fees: u64 = self.transactions.iter()
             .filter(|tx| !tx.is_coinbase())
-            .map(|tx| tx.fee.base_fee)
-            .sum();
+            .fold(0, |total, tx| total.saturating_add(tx.fee.base_fee));
         
         let total_amount_transferred: u64 = self.transactions.iter()
-            .map(|tx| tx.total_output_amount())
-            .sum();
+            .fold(0, |total, tx| total.saturating_add(tx.total_output_amount()));
         
         let avg_tx_size = if !self.transactions.is_empty() {
             self.transactions.iter()
                 .filter_map(|tx| tx.size)
-                .sum::<usize>() / self.transactions.len()
+                .fold(0usize, |total, size| total.saturating_add(size)) / self.transactions.len()
         } else {
             0
         };
@@ -489,7 +720,7 @@ impl Default for BlockValidationContext {
 mod tests {
     use super::*;
     use crate::core::TransactionInput;
-    use crate::crypto::{Address, PublicKey, SignatureAlgorithm};
+    use crate::crypto::{Address, MinerEvents, PublicKey, SignatureAlgorithm};
 
     fn create_test_address() -> Address {
         let public_key = PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5]);
@@ -502,6 +733,64 @@ mod tests {
         Transaction::new(vec![input], vec![output])
     }
 
+    /// Mined blocks at difficulty 1 extending genesis
+    fn mined_blocks(count: u64) -> Vec<Block> {
+        let mut previous = Block::genesis(create_test_address(), 1_000_000).hash();
+        (1..=count)
+            .map(|index| {
+                let coinbase = Transaction::coinbase(create_test_address(), 50, index);
+                let mut block = Block::new(index, previous.clone(), vec![coinbase, create_test_transaction()], 1);
+                block.mine().unwrap();
+                previous = block.hash();
+                block
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_header_chain() {
+        let genesis = Block::genesis(create_test_address(), 1_000_000).hash();
+        let blocks = mined_blocks(3);
+        let headers: Vec<BlockHeader> = blocks.iter().map(|b| b.header.clone()).collect();
+        assert_eq!(verify_header_chain(&genesis, &headers).unwrap(), blocks[2].hash());
+        assert_eq!(verify_header_chain(&genesis, &[]).unwrap(), genesis);
+
+        // Headers survive the compact encoding
+        let bytes = headers[1].to_bytes().unwrap();
+        assert_eq!(BlockHeader::from_bytes(&bytes).unwrap(), headers[1]);
+
+        let mut gap = headers.clone();
+        gap.remove(1);
+        assert!(verify_header_chain(&genesis, &gap).is_err());
+        assert!(verify_header_chain(&Hash256::zero(), &headers).is_err());
+
+        let mut unmined = headers.clone();
+        unmined[2].difficulty = 250;
+        assert!(verify_header_chain(&genesis, &unmined).is_err());
+    }
+
+    #[test]
+    fn test_block_bytes_round_trip() {
+        let block = &mined_blocks(1)[0];
+        let decoded = Block::from_bytes(&block.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.hash(), block.hash());
+        assert_eq!(decoded.index, block.index);
+        assert_eq!(decoded.transactions.len(), 2);
+        assert!(Block::from_bytes(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_header_proves_transaction() {
+        let block = &mined_blocks(1)[0];
+        let tx_hash = block.transactions[1].hash();
+        let proof = block.generate_merkle_proof(1).unwrap();
+        assert!(block.header.proves_transaction(&tx_hash, &proof));
+        assert!(!block.header.proves_transaction(&block.transactions[0].hash(), &proof));
+
+        let other = &mined_blocks(2)[1];
+        assert!(!other.header.proves_transaction(&tx_hash, &proof));
+    }
+
     #[test]
     fn test_block_creation() {
         let transactions = vec![create_test_transaction()];
@@ -524,6 +813,14 @@ mod tests {
         assert!(genesis.transactions[0].is_coinbase());
     }
 
+    #[test]
+    fn test_genesis_block_is_deterministic() {
+        let first = Block::genesis(create_test_address(), 1_000_000);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = Block::genesis(create_test_address(), 1_000_000);
+        assert_eq!(first.hash(), second.hash());
+    }
+
     #[test]
     fn test_block_hash() {
         let transactions = vec![create_test_transaction()];
@@ -543,12 +840,115 @@ mod tests {
         assert!(block.verify_merkle_root());
     }
 
+    #[test]
+    fn test_merkle_tree_follows_transaction_changes() {
+        let coinbase = Transaction::coinbase(create_test_address(), 50, 1);
+        let transactions = vec![coinbase.clone(), create_test_transaction(), create_test_transaction()];
+        let mut block = Block::new(1, Hash256::zero(), transactions.clone(), 1);
+        assert!(std::ptr::eq(block.merkle_tree().unwrap(), block.merkle_tree().unwrap()));
+
+        // The miner swaps in a new coinbase: same root as building the block afresh
+        let adjusted = coinbase.with_lock_time(2);
+        block.replace_transaction(0, adjusted.clone()).unwrap();
+        let mut expected = transactions.clone();
+        expected[0] = adjusted;
+        let rebuilt = Block::new(1, Hash256::zero(), expected, 1);
+        assert_eq!(block.header.merkle_root, rebuilt.header.merkle_root);
+        assert_eq!(block.merkle_tree().unwrap().root(), &rebuilt.header.merkle_root);
+        assert_eq!(block.generate_merkle_proof(2).unwrap(), rebuilt.generate_merkle_proof(2).unwrap());
+        assert!(block.verify_merkle_root());
+        assert!(block.replace_transaction(3, create_test_transaction()).is_err());
+
+        // Changes made in place drop the tree, so it is rebuilt from what is there now
+        block.transactions_mut().truncate(1);
+        assert!(!block.verify_merkle_root());
+        assert_eq!(block.merkle_tree().unwrap().leaf_count(), 1);
+
+        // The cache is not part of the block's value
+        let fresh = Block::from_bytes(&rebuilt.to_bytes().unwrap()).unwrap();
+        assert_eq!(fresh, rebuilt);
+        assert!(fresh.verify_merkle_root());
+    }
+
+    #[test]
+    fn test_validation_reports_the_first_bad_transaction() {
+        let genesis = Block::genesis(create_test_address(), 1_000_000);
+        let mut utxo_set = HashMap::new();
+        let spends: Vec<Transaction> = (0..64u32)
+            .map(|i| {
+                utxo_set.insert(format!("{}:{}", Hash256::zero(), i), crate::core::TransactionOutput::new(1000, create_test_address()));
+                let input = TransactionInput::new(
+                    Hash256::zero(),
+                    i,
+                    Some(crate::crypto::Signature::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1])),
+                    Some(PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5])),
+                );
+                Transaction::new(vec![input], vec![crate::core::TransactionOutput::new(900, create_test_address())])
+            })
+            .collect();
+        let mine = |spends: &[Transaction]| {
+            let mut transactions = vec![Transaction::coinbase(create_test_address(), 50, 1)];
+            transactions.extend_from_slice(spends);
+            let mut block = Block::new(1, genesis.hash(), transactions, 1);
+            block.mine().unwrap();
+            block
+        };
+        mine(&spends).validate(Some(&genesis), &utxo_set).unwrap();
+
+        let mut unsigned = spends.clone();
+        for i in [40, 12] {
+            unsigned[i].inputs[0].signature = None;
+        }
+        let err = mine(&unsigned).validate(Some(&genesis), &utxo_set).unwrap_err();
+        assert!(err.to_string().contains(&unsigned[12].hash().to_string()), "{}", err);
+
+        let mut tampered = mine(&spends);
+        tampered.transactions_mut().pop();
+        tampered.header.transaction_count -= 1;
+        let err = tampered.validate(Some(&genesis), &utxo_set).unwrap_err();
+        assert!(err.to_string().contains("merkle root"), "{}", err);
+    }
+
+    #[test]
+    fn test_mining_reports_events() {
+        let transactions = vec![Transaction::coinbase(create_test_address(), 50, 1)];
+        let events = MinerEvents::new(64);
+        let mut received = events.subscribe();
+
+        let mut block = Block::new(1, Hash256::zero(), transactions.clone(), 1);
+        block.mine_with(&MiningControl::new().with_events(events.clone())).unwrap();
+        match received.try_recv().unwrap() {
+            MinerEvent::Found { height, nonce, hash, .. } => {
+                assert_eq!((height, nonce, hash), (1, block.header.nonce, block.hash()));
+            }
+            event => panic!("expected a found event, got {:?}", event),
+        }
+
+        // No nonce meets this target, so the search runs until it is stopped
+        let hopeless = || Block::new(2, Hash256::zero(), transactions.clone(), 255);
+        let control = MiningControl::new()
+            .with_events(events.clone())
+            .with_progress_interval(10)
+            .with_max_attempts(35);
+        let err = hopeless().mine_with(&control).unwrap_err();
+        assert!(err.to_string().contains("Mining timeout"), "{}", err);
+        let reported: Vec<MinerEvent> = std::iter::from_fn(|| received.try_recv().ok()).collect();
+        assert_eq!(reported.iter().filter(|e| matches!(e, MinerEvent::Progress { .. })).count(), 3);
+        assert!(matches!(reported.last(), Some(MinerEvent::Timeout { height: 2, attempts: 36, .. })));
+
+        control.cancel();
+        let err = hopeless().mine_with(&control).unwrap_err();
+        assert!(err.to_string().contains("Mining cancelled"), "{}", err);
+        assert!(matches!(received.try_recv().unwrap(), MinerEvent::Cancelled { attempts: 10, .. }));
+        assert!(matches!(events.latest(), Some(MinerEvent::Cancelled { .. })));
+    }
+
     #[test]
     fn test_block_header_validation() {
-        let header = BlockHeader::new(1, Hash256::zero(), Hash256::zero(), 4, 1);
+        let header = BlockHeader::new(1, Hash256::zero(), Hash256::zero(), Utc::now(), 4, 1);
         assert!(header.validate().is_ok());
         
-        let invalid_header = BlockHeader::new(0, Hash256::zero(), Hash256::zero(), 0, 1);
+        let invalid_header = BlockHeader::new(0, Hash256::zero(), Hash256::zero(), Utc::now(), 0, 1);
         assert!(invalid_header.validate().is_err());
     }
 
@@ -3,14 +3,33 @@
 //! This module implements the main blockchain structure, including block validation,
 //! chain management, UTXO tracking, and consensus rules.
 
-use crate::core::{Block, Transaction, TransactionInput, TransactionOutput};
-use crate::crypto::{Hash256, MerkleTree};
+use crate::core::{Block, BlockFilter, ReplayEvent, ReplayRecorder, RewardSchedule, SnapshotValidation, Transaction, TransactionOutput, UtxoSnapshot};
+use crate::crypto::{adjust_difficulty, block_work, estimate_hash_rate, Address, Hash256};
 use crate::error::{Result, BlockchainError, ValidationError};
-use crate::storage::PersistentStorage;
+use crate::storage::{PersistentStorage, StorageBatch};
+use crate::utils::constants::{COINBASE_MATURITY, MAX_ANTI_FEE_SNIPING_TIP_AGE, MAX_DIFFICULTY_ADJUSTMENT, MAX_MEMO_SIZE};
+use crate::utils::{Clock, NetworkTime, SystemClock};
 use chrono::{DateTime, Utc};
+use ledgerdb_types::{InputValidation, Network, SupplyAudit, SupplyProblem, TransactionValidation};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+/// Number of preceding blocks whose median timestamp a new block must exceed
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// How far past network-adjusted time a block timestamp may be, in seconds (2 hours)
+pub const MAX_FUTURE_BLOCK_TIME: i64 = 2 * 60 * 60;
+
+/// Orphan blocks held at most; the oldest is dropped to make room for another
+pub const MAX_ORPHAN_BLOCKS: usize = 100;
+
+/// Side-branch blocks held at most; the one with the least work is dropped to make
+/// room for another
+pub const MAX_SIDE_BLOCKS: usize = 1000;
+
+/// Problems a supply audit reports at most
+pub const MAX_SUPPLY_PROBLEMS: usize = 100;
 
 /// UTXO (Unspent Transaction Output) identifier
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -21,17 +40,18 @@ pub struct UtxoId {
     pub output_index: u32,
 }
 
+impl std::fmt::Display for UtxoId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.tx_hash.to_hex(), self.output_index)
+    }
+}
+
 impl UtxoId {
     /// Create a new UTXO identifier
     pub fn new(tx_hash: Hash256, output_index: u32) -> Self {
         Self { tx_hash, output_index }
     }
 
-    /// Convert to string representation
-    pub fn to_string(&self) -> String {
-        format!("{}:{}", self.tx_hash.to_hex(), self.output_index)
-    }
-
     /// Parse from string representation
     pub fn from_string(s: &str) -> Result<Self> {
         let parts: Vec<&str> = s.split(':').collect();
@@ -108,8 +128,10 @@ pub struct BlockchainStats {
     pub total_utxos: u64,
     /// Total supply of coins
     pub total_supply: u64,
-    /// Current difficulty
+    /// Difficulty the next block must be mined at
     pub current_difficulty: u32,
+    /// Total work of the chain up to its tip
+    pub chain_work: u128,
     /// Average block time in seconds
     pub average_block_time: f64,
     /// Total network hash rate (estimated)
@@ -129,6 +151,7 @@ impl Default for BlockchainStats {
             total_utxos: 0,
             total_supply: 0,
             current_difficulty: 1,
+            chain_work: 0,
             average_block_time: 600.0, // 10 minutes
             estimated_hash_rate: 0.0,
             blockchain_size: 0,
@@ -140,16 +163,16 @@ impl Default for BlockchainStats {
 /// Blockchain configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockchainConfig {
+    /// Network whose blocks the chain accepts
+    pub network: Network,
     /// Target block time in seconds
     pub target_block_time: u64,
     /// Difficulty adjustment interval (in blocks)
     pub difficulty_adjustment_interval: u64,
     /// Maximum block size in bytes
     pub max_block_size: u64,
-    /// Block reward amount
-    pub block_reward: u64,
-    /// Halving interval (in blocks)
-    pub halving_interval: u64,
+    /// Block subsidy by height
+    pub reward_schedule: RewardSchedule,
     /// Maximum transactions per block
     pub max_transactions_per_block: u32,
     /// Minimum transaction fee
@@ -158,26 +181,98 @@ pub struct BlockchainConfig {
     pub genesis_timestamp: DateTime<Utc>,
     /// Initial difficulty
     pub initial_difficulty: u32,
+    /// Block hashes pinned at given heights; the chain is never reorganized below them
+    pub checkpoints: BTreeMap<u64, Hash256>,
+    /// Seconds a transaction may wait in the pool before it is dropped
+    pub mempool_expiry: u64,
+    /// Seconds an orphan block may wait for its parent before it is dropped
+    pub orphan_expiry: u64,
 }
 
 impl Default for BlockchainConfig {
     fn default() -> Self {
         Self {
+            network: Network::Mainnet,
             target_block_time: 600, // 10 minutes
             difficulty_adjustment_interval: 2016, // ~2 weeks
             max_block_size: 1_000_000, // 1MB
-            block_reward: 5_000_000_000, // 50 units
-            halving_interval: 210_000, // ~4 years
+            reward_schedule: RewardSchedule::default(),
             max_transactions_per_block: 1000,
             min_transaction_fee: 1000, // 0.00001 units
             genesis_timestamp: DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
                 .unwrap()
                 .with_timezone(&Utc),
             initial_difficulty: 1,
+            checkpoints: BTreeMap::new(),
+            mempool_expiry: 14 * 24 * 60 * 60, // 2 weeks
+            orphan_expiry: 20 * 60, // 20 minutes
         }
     }
 }
 
+/// Position and cumulative work of a block on the main chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockIndexEntry {
+    /// Height of the block
+    pub height: u64,
+    /// Total work of the chain up to and including the block
+    pub chain_work: u128,
+}
+
+/// A block whose parent is unknown, waiting for the parent to arrive
+#[derive(Debug, Clone)]
+pub struct OrphanBlock {
+    pub block: Block,
+    pub received_at: DateTime<Utc>,
+}
+
+/// A block off the main chain, kept in case its branch overtakes the main chain
+#[derive(Debug, Clone)]
+struct SideBlock {
+    block: Block,
+    /// Total work of its branch up to and including it, by the difficulty each
+    /// header claims
+    chain_work: u128,
+}
+
+/// Outcome of [`Blockchain::process_block`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockStatus {
+    /// The block extended the chain, and so did `orphans_connected` orphans that
+    /// were waiting on it; `height` is that of the new tip
+    Connected { height: u64, orphans_connected: usize },
+    /// The block's branch now has more work than the main chain had, so the
+    /// `disconnected` blocks above `fork_height` were replaced by it; `height` is that
+    /// of the new tip
+    Reorganized { fork_height: u64, disconnected: usize, height: u64, orphans_connected: usize },
+    /// The block extends a branch with no more work than the main chain; it is kept
+    /// in case the branch overtakes it
+    SideBranch { height: u64 },
+    /// The parent is unknown; the block waits in the orphan pool until it arrives
+    Orphaned { missing_parent: Hash256 },
+    /// The block is already on the chain or in the orphan pool
+    AlreadyKnown,
+}
+
+/// A pending transaction and when the pool accepted it
+#[derive(Debug, Clone)]
+struct PoolEntry {
+    transaction: Transaction,
+    added_at: DateTime<Utc>,
+}
+
+/// A pool transaction with what the pool knows about it beyond its body
+#[derive(Debug, Clone, Copy)]
+pub struct PendingTransaction<'a> {
+    pub transaction: &'a Transaction,
+    /// When the pool accepted it
+    pub added_at: DateTime<Utc>,
+    /// Time since `added_at`, by the blockchain's clock
+    pub age: chrono::Duration,
+    /// Value of the outputs it spends minus its own outputs: what the miner collects
+    pub fee: u64,
+}
+
 /// Main blockchain structure
 #[derive(Debug)]
 pub struct Blockchain {
@@ -187,18 +282,30 @@ pub struct Blockchain {
     blocks: Vec<Block>,
     /// UTXO set for fast transaction validation
     utxo_set: HashMap<UtxoId, UtxoEntry>,
+    /// Address -> UTXOs paying it, kept in step with `utxo_set`
+    address_index: HashMap<Address, HashSet<UtxoId>>,
     /// Transaction pool for pending transactions
-    transaction_pool: HashMap<Hash256, Transaction>,
+    transaction_pool: HashMap<Hash256, PoolEntry>,
     /// Block index for fast lookup by hash
-    block_index: HashMap<Hash256, u64>,
+    block_index: HashMap<Hash256, BlockIndexEntry>,
     /// Persistent storage backend
     storage: Option<Arc<PersistentStorage>>,
     /// Blockchain statistics
     stats: BlockchainStats,
-    /// Orphaned blocks (blocks without valid parent)
-    orphaned_blocks: HashMap<Hash256, Block>,
+    /// Blocks whose parent is unknown, by hash
+    orphaned_blocks: HashMap<Hash256, OrphanBlock>,
+    /// Blocks on branches off the main chain, by hash
+    side_blocks: HashMap<Hash256, SideBlock>,
     /// Recent block times for difficulty adjustment
     recent_block_times: VecDeque<DateTime<Utc>>,
+    /// Source of the current time
+    clock: Arc<dyn Clock>,
+    /// Local clock corrected by peer clock samples
+    network_time: NetworkTime,
+    /// Progress checking the history below an imported UTXO snapshot
+    snapshot_validation: Option<SnapshotValidation>,
+    /// Where accepted transactions and connected blocks are recorded for replay
+    recorder: Option<ReplayRecorder>,
 }
 
 impl Blockchain {
@@ -208,16 +315,23 @@ impl Blockchain {
             config: config.clone(),
             blocks: Vec::new(),
             utxo_set: HashMap::new(),
+            address_index: HashMap::new(),
             transaction_pool: HashMap::new(),
             block_index: HashMap::new(),
             storage: None,
             stats: BlockchainStats::default(),
             orphaned_blocks: HashMap::new(),
+            side_blocks: HashMap::new(),
             recent_block_times: VecDeque::new(),
+            clock: Arc::new(SystemClock),
+            network_time: NetworkTime::new(),
+            snapshot_validation: None,
+            recorder: None,
         };
         
         // Create and add genesis block
-        let genesis_block = Block::genesis(genesis_address, config.block_reward);
+        let genesis_block = Block::genesis(genesis_address, config.reward_schedule.reward_at(0))
+            .with_network(config.network);
         blockchain.add_genesis_block(genesis_block)?;
         
         Ok(blockchain)
@@ -233,22 +347,57 @@ impl Blockchain {
         blockchain.storage = Some(storage);
         
         // Load existing blockchain from storage if available
-        if let Some(ref storage) = blockchain.storage {
-            blockchain.load_from_storage()?;
-        }
+        blockchain.load_from_storage()?;
         
         Ok(blockchain)
     }
 
+    /// Create blockchain with persistent storage, taking the UTXO set from `snapshot`
+    /// instead of replaying the stored blocks below it.
+    ///
+    /// The snapshot must be taken at a stored block. Its history stays unchecked until
+    /// [`Blockchain::finish_snapshot_validation`] is called with the result of replaying it.
+    pub fn with_snapshot(
+        config: BlockchainConfig,
+        storage: Arc<PersistentStorage>,
+        genesis_address: crate::crypto::Address,
+        snapshot: UtxoSnapshot,
+    ) -> Result<Self> {
+        snapshot.verify()?;
+        let mut blockchain = Self::new(config, genesis_address)?;
+        blockchain.storage = Some(storage);
+        blockchain.load_blocks_from_storage()?;
+
+        match blockchain.get_block_by_index(snapshot.height) {
+            Some(block) if block.hash() == snapshot.block_hash => {}
+            Some(_) => {
+                return Err(BlockchainError::InvalidChain(format!(
+                    "UTXO snapshot block {} does not match the stored chain", snapshot.height
+                )).into());
+            }
+            None => {
+                return Err(BlockchainError::InvalidChain(format!(
+                    "UTXO snapshot is at height {} but storage ends at {}",
+                    snapshot.height, blockchain.height()
+                )).into());
+            }
+        }
+
+        blockchain.replace_utxo_set(snapshot.utxo_set());
+        blockchain.apply_blocks_above(snapshot.height)?;
+        blockchain.snapshot_validation = Some(SnapshotValidation::Pending {
+            height: snapshot.height,
+            commitment: snapshot.commitment,
+        });
+        blockchain.update_stats();
+
+        Ok(blockchain)
+    }
+
     /// Load blockchain state from persistent storage
     fn load_from_storage(&mut self) -> Result<()> {
-        if let Some(ref storage) = self.storage {
-            // Load blocks from storage
-            let stored_blocks = storage.load_all_blocks()?;
-            
-            for block in stored_blocks {
-                self.add_block_internal(block, false)?;
-            }
+        if self.storage.is_some() {
+            self.load_blocks_from_storage()?;
             
             // Rebuild UTXO set
             self.rebuild_utxo_set()?;
@@ -260,6 +409,18 @@ impl Blockchain {
         Ok(())
     }
 
+    /// Append the stored blocks without touching the UTXO set
+    fn load_blocks_from_storage(&mut self) -> Result<()> {
+        if let Some(storage) = self.storage.clone() {
+            for block in storage.load_all_blocks()? {
+                self.validate_block_network(&block)?;
+                self.validate_block_checkpoint(&block)?;
+                self.add_block_internal(block, false)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Add the genesis block
     fn add_genesis_block(&mut self, genesis_block: Block) -> Result<()> {
         if !genesis_block.is_genesis() {
@@ -274,16 +435,21 @@ impl Blockchain {
 
     /// Add a new block to the blockchain
     pub fn add_block(&mut self, mut block: Block) -> Result<()> {
-        // Validate the block
-        self.validate_block(&block)?;
-        
-        // Mine the block if not already mined
+        // Mine the block if not already mined, but never at the wrong difficulty
+        self.validate_block_difficulty(&block)?;
         if !block.header.meets_difficulty_target() {
-            block.mine(None)?;
+            block.mine()?;
         }
         
+        // Validate the block
+        self.validate_block(&block)?;
+        
         // Add to blockchain
         self.add_block_internal(block, true)?;
+        self.record(|at, chain| ReplayEvent::Block {
+            at,
+            block: Box::new(chain.blocks.last().expect("block was just added").clone()),
+        });
         
         Ok(())
     }
@@ -292,6 +458,9 @@ impl Blockchain {
     fn add_block_internal(&mut self, block: Block, update_utxo: bool) -> Result<()> {
         let block_hash = block.hash();
         let block_height = block.index;
+
+        // Filter new blocks while the outputs they spend are still in the UTXO set
+        let filter = (update_utxo && self.storage.is_some()).then(|| self.block_filter(&block));
         
         // Update UTXO set if requested
         if update_utxo {
@@ -299,12 +468,13 @@ impl Blockchain {
         }
         
         // Remove transactions from pool
-        for tx in &block.transactions {
+        for tx in block.transactions() {
             self.transaction_pool.remove(&tx.hash());
         }
         
-        // Add to block index
-        self.block_index.insert(block_hash.clone(), block_height);
+        // Add to block index, on top of the work of the block it extends
+        let chain_work = self.chain_work().saturating_add(block_work(block.header.difficulty));
+        self.block_index.insert(block_hash.clone(), BlockIndexEntry { height: block_height, chain_work });
         
         // Add to blocks
         self.blocks.push(block);
@@ -317,11 +487,16 @@ impl Blockchain {
             }
         }
         
-        // Persist to storage
+        // Persist the block and its filter together
         if let Some(ref storage) = self.storage {
+            let mut batch = StorageBatch::new();
             if let Some(latest_block) = self.blocks.last() {
-                storage.store_block(latest_block)?;
+                batch.store_block(latest_block);
+            }
+            if let Some(filter) = &filter {
+                batch.store_block_filter(filter);
             }
+            storage.write_batch(batch)?;
         }
         
         // Update statistics
@@ -330,6 +505,297 @@ impl Blockchain {
         Ok(())
     }
 
+    /// Accept a block from outside the node: connect it if it extends the tip, keep
+    /// it on a side branch if its parent is known but not the tip, or park it in the
+    /// orphan pool if its parent is unknown.
+    ///
+    /// Unlike [`Blockchain::add_block`] the block must already carry valid
+    /// proof-of-work. A side branch that ends up with more work than the main chain
+    /// becomes the main chain. Orphans whose parent is then known are connected after
+    /// the block.
+    pub fn process_block(&mut self, block: Block) -> Result<BlockStatus> {
+        let hash = block.hash();
+        if self.block_index.contains_key(&hash)
+            || self.side_blocks.contains_key(&hash)
+            || self.orphaned_blocks.contains_key(&hash)
+        {
+            return Ok(BlockStatus::AlreadyKnown);
+        }
+        self.validate_block_network(&block)?;
+        if !block.header.meets_difficulty_target() {
+            return Err(ValidationError::InvalidProofOfWork(format!(
+                "block {} does not meet its difficulty target {}", hash, block.header.difficulty
+            )).into());
+        }
+
+        let parent = block.header.previous_hash.clone();
+        if !self.block_index.contains_key(&parent) && !self.side_blocks.contains_key(&parent) {
+            if !block.verify_merkle_root() {
+                return Err(ValidationError::InvalidMerkleRoot(format!(
+                    "block {} does not commit to its transactions", hash
+                )).into());
+            }
+            self.park_orphan(hash, block);
+            return Ok(BlockStatus::Orphaned { missing_parent: parent });
+        }
+
+        let old_tip = self.get_latest_block().map(|b| b.hash());
+        let index = block.index;
+        self.connect_block(block)?;
+        let orphans_connected = self.connect_orphans();
+
+        let height = self.height() - 1;
+        let tip = self.get_latest_block().map(|b| b.hash());
+        Ok(match old_tip {
+            _ if tip == old_tip => BlockStatus::SideBranch { height: index },
+            Some(old_tip) if !self.block_index.contains_key(&old_tip) => {
+                let fork_height = self.fork_height(&old_tip);
+                let old_height = self.side_blocks.get(&old_tip).map_or(fork_height, |side| side.block.index);
+                BlockStatus::Reorganized {
+                    fork_height,
+                    disconnected: (old_height - fork_height) as usize,
+                    height,
+                    orphans_connected,
+                }
+            }
+            _ => BlockStatus::Connected { height, orphans_connected },
+        })
+    }
+
+    /// Connect a block whose parent is on the main chain or a side branch: append it
+    /// if it extends the tip, otherwise keep it on its branch and switch to that branch
+    /// if it now has more work than the main chain
+    fn connect_block(&mut self, block: Block) -> Result<()> {
+        let hash = block.hash();
+        let parent = &block.header.previous_hash;
+        if self.get_latest_block().map(|b| b.hash()).as_ref() == Some(parent) {
+            return self.add_block(block);
+        }
+
+        let (parent_height, parent_work) = match self.block_index.get(parent) {
+            Some(entry) => (entry.height, entry.chain_work),
+            None => {
+                let side = &self.side_blocks[parent];
+                (side.block.index, side.chain_work)
+            }
+        };
+        if block.index != parent_height + 1 {
+            return Err(ValidationError::InvalidBlockIndex { expected: parent_height + 1, actual: block.index }.into());
+        }
+        if !block.verify_merkle_root() {
+            return Err(ValidationError::InvalidMerkleRoot(format!(
+                "block {} does not commit to its transactions", hash
+            )).into());
+        }
+
+        let chain_work = parent_work.saturating_add(block_work(block.header.difficulty));
+        self.keep_side_block(hash.clone(), SideBlock { block, chain_work });
+        if chain_work > self.chain_work() {
+            self.reorganize(&hash)?;
+        }
+        Ok(())
+    }
+
+    fn keep_side_block(&mut self, hash: Hash256, side: SideBlock) {
+        if self.side_blocks.len() >= MAX_SIDE_BLOCKS {
+            let weakest = self.side_blocks
+                .iter()
+                .min_by_key(|(_, side)| side.chain_work)
+                .map(|(hash, _)| hash.clone());
+            if let Some(weakest) = weakest {
+                self.side_blocks.remove(&weakest);
+            }
+        }
+        self.side_blocks.insert(hash, side);
+    }
+
+    /// Height of the main-chain block a side branch ending at `hash` forks from
+    fn fork_height(&self, hash: &Hash256) -> u64 {
+        let mut hash = hash;
+        while let Some(side) = self.side_blocks.get(hash) {
+            hash = &side.block.header.previous_hash;
+        }
+        self.block_index.get(hash).map_or(0, |entry| entry.height)
+    }
+
+    /// Make the side branch ending at `hash` the main chain, unless it forks below the
+    /// last checkpoint.
+    ///
+    /// The main-chain blocks above the fork become a side branch and their
+    /// transactions go back to the pool unless the new branch confirms or conflicts
+    /// with them. The branch's blocks are validated as they are connected; if one is
+    /// invalid, it and its descendants are dropped and the old chain is restored.
+    fn reorganize(&mut self, hash: &Hash256) -> Result<()> {
+        let mut branch = Vec::new();
+        let mut next = hash.clone();
+        while let Some(side) = self.side_blocks.remove(&next) {
+            next = side.block.header.previous_hash.clone();
+            branch.push(side);
+        }
+        branch.reverse();
+        let fork_height = self.block_index.get(&next).map_or(0, |entry| entry.height);
+        // A branch forking below a checkpoint can never become the chain, so it is dropped
+        if let Err(e) = self.check_reorg(fork_height) {
+            self.drop_side_descendants(branch.iter().map(|side| side.block.hash()).collect());
+            return Err(e);
+        }
+
+        let disconnected = self.disconnect_above(fork_height)?;
+        let mut branch = branch.into_iter();
+        let mut failure = None;
+        for side in branch.by_ref() {
+            let block_hash = side.block.hash();
+            if let Err(e) = self.add_block(side.block) {
+                failure = Some((block_hash, e));
+                break;
+            }
+        }
+
+        if let Some((invalid, error)) = failure {
+            // The valid part of the branch stays a side branch; what builds on the
+            // invalid block can never connect
+            for side in self.disconnect_above(fork_height)? {
+                self.keep_side_block(side.block.hash(), side);
+            }
+            let dropped = std::iter::once(invalid).chain(branch.map(|side| side.block.hash())).collect();
+            self.drop_side_descendants(dropped);
+            for side in disconnected {
+                self.add_block_internal(side.block, true)?;
+            }
+            return Err(error);
+        }
+
+        let returned: Vec<Transaction> = disconnected
+            .iter()
+            .flat_map(|side| side.block.transactions())
+            .filter(|tx| !tx.is_coinbase())
+            .cloned()
+            .collect();
+        for side in disconnected {
+            self.keep_side_block(side.block.hash(), side);
+        }
+        for transaction in returned {
+            // Confirmed or conflicting on the new branch: rejected, as it should be
+            let _ = self.add_transaction_to_pool(transaction);
+        }
+        Ok(())
+    }
+
+    /// Drop the side blocks building on any of `dropped`, however deep, since they
+    /// can never connect
+    fn drop_side_descendants(&mut self, mut dropped: HashSet<Hash256>) {
+        loop {
+            let children: Vec<Hash256> = self.side_blocks
+                .iter()
+                .filter(|(_, side)| dropped.contains(&side.block.header.previous_hash))
+                .map(|(hash, _)| hash.clone())
+                .collect();
+            if children.is_empty() {
+                return;
+            }
+            for hash in children {
+                self.side_blocks.remove(&hash);
+                dropped.insert(hash);
+            }
+        }
+    }
+
+    /// Remove the main-chain blocks above `height`, returning them with their work
+    fn disconnect_above(&mut self, height: u64) -> Result<Vec<SideBlock>> {
+        let removed = self.blocks.split_off((height as usize + 1).min(self.blocks.len()));
+        let mut batch = StorageBatch::new();
+        let disconnected: Vec<SideBlock> = removed
+            .into_iter()
+            .map(|block| {
+                batch.unindex_block(&block);
+                let chain_work = self.block_index.remove(&block.hash()).map_or(0, |entry| entry.chain_work);
+                SideBlock { block, chain_work }
+            })
+            .collect();
+        if let Some(storage) = &self.storage {
+            storage.write_batch(batch)?;
+        }
+
+        self.rebuild_utxo_set()?;
+        self.recent_block_times = self.blocks
+            .iter()
+            .rev()
+            .take(10)
+            .rev()
+            .map(|block| block.header.timestamp)
+            .collect();
+        self.update_stats();
+        Ok(disconnected)
+    }
+
+    fn park_orphan(&mut self, hash: Hash256, block: Block) {
+        self.expire_orphans();
+        if self.orphaned_blocks.len() >= MAX_ORPHAN_BLOCKS {
+            let oldest = self.orphaned_blocks
+                .iter()
+                .min_by_key(|(_, orphan)| orphan.received_at)
+                .map(|(hash, _)| hash.clone());
+            if let Some(oldest) = oldest {
+                self.orphaned_blocks.remove(&oldest);
+            }
+        }
+        self.orphaned_blocks.insert(hash, OrphanBlock { block, received_at: self.clock.now() });
+    }
+
+    /// Connect orphans whose parent is on the main chain or a side branch until none
+    /// is; an orphan that fails validation is dropped
+    fn connect_orphans(&mut self) -> usize {
+        let mut connected = 0;
+        loop {
+            let next = self.orphaned_blocks
+                .iter()
+                .find(|(_, orphan)| {
+                    let parent = &orphan.block.header.previous_hash;
+                    self.block_index.contains_key(parent) || self.side_blocks.contains_key(parent)
+                })
+                .map(|(hash, _)| hash.clone());
+            let Some(orphan) = next.and_then(|hash| self.orphaned_blocks.remove(&hash)) else {
+                break;
+            };
+            if self.connect_block(orphan.block).is_ok() {
+                connected += 1;
+            }
+        }
+        connected
+    }
+
+    /// Drop orphans older than `config.orphan_expiry`; returns how many were dropped
+    pub fn expire_orphans(&mut self) -> usize {
+        let cutoff = self.clock.now() - chrono::Duration::seconds(self.config.orphan_expiry as i64);
+        let before = self.orphaned_blocks.len();
+        self.orphaned_blocks.retain(|_, orphan| orphan.received_at > cutoff);
+        before - self.orphaned_blocks.len()
+    }
+
+    /// Blocks waiting in the orphan pool, oldest first
+    pub fn orphan_blocks(&self) -> Vec<&OrphanBlock> {
+        let mut orphans: Vec<_> = self.orphaned_blocks.values().collect();
+        orphans.sort_by_key(|orphan| orphan.received_at);
+        orphans
+    }
+
+    /// Parents the orphan pool is waiting for: blocks neither the chain nor the pool
+    /// holds, which have to be fetched from elsewhere
+    pub fn missing_parents(&self) -> Vec<Hash256> {
+        let mut parents: Vec<Hash256> = self.orphaned_blocks
+            .values()
+            .map(|orphan| orphan.block.header.previous_hash.clone())
+            .filter(|parent| {
+                !self.orphaned_blocks.contains_key(parent)
+                    && !self.block_index.contains_key(parent)
+                    && !self.side_blocks.contains_key(parent)
+            })
+            .collect();
+        parents.sort_by_key(|hash| hash.to_hex());
+        parents.dedup();
+        parents
+    }
+
     /// Validate a block before adding it to the chain
     pub fn validate_block(&self, block: &Block) -> Result<()> {
         // Get previous block for validation
@@ -349,15 +815,65 @@ impl Blockchain {
         block.validate(previous_block, &utxo_map)?;
         
         // Additional blockchain-specific validations
+        self.validate_block_network(block)?;
+        self.validate_block_checkpoint(block)?;
         self.validate_block_difficulty(block)?;
         self.validate_block_timestamp(block)?;
+        self.validate_block_lock_times(block)?;
         
         Ok(())
     }
 
+    /// Reject a block from another network
+    fn validate_block_network(&self, block: &Block) -> Result<()> {
+        if block.header.network != self.config.network {
+            return Err(ValidationError::WrongNetwork(format!(
+                "block {} is a {} block, this chain is {}",
+                block.hash(), block.header.network, self.config.network
+            )).into());
+        }
+        Ok(())
+    }
+
+    /// Reject a block at a checkpointed height whose hash differs from the checkpoint
+    fn validate_block_checkpoint(&self, block: &Block) -> Result<()> {
+        if let Some(expected) = self.config.checkpoints.get(&block.index) {
+            let hash = block.hash();
+            if &hash != expected {
+                return Err(ValidationError::CheckpointMismatch(format!(
+                    "block {} has hash {}, checkpoint is {}",
+                    block.index, hash, expected
+                )).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Highest checkpoint the chain has already passed
+    pub fn last_checkpoint(&self) -> Option<(u64, &Hash256)> {
+        let tip = self.get_latest_block()?.index;
+        self.config
+            .checkpoints
+            .range(..=tip)
+            .next_back()
+            .map(|(height, hash)| (*height, hash))
+    }
+
+    /// Check that a reorganization keeping blocks up to `fork_height` and replacing
+    /// everything above it would not undo a checkpointed block
+    pub fn check_reorg(&self, fork_height: u64) -> Result<()> {
+        match self.last_checkpoint() {
+            Some((height, _)) if fork_height < height => Err(BlockchainError::ConsensusError(format!(
+                "refusing to reorganize below checkpoint at height {} (fork at {})",
+                height, fork_height
+            )).into()),
+            _ => Ok(()),
+        }
+    }
+
     /// Validate block difficulty
     fn validate_block_difficulty(&self, block: &Block) -> Result<()> {
-        let expected_difficulty = self.calculate_next_difficulty();
+        let expected_difficulty = self.difficulty_at(block.index);
         
         if block.header.difficulty != expected_difficulty {
             return Err(ValidationError::InvalidDifficulty(
@@ -370,62 +886,235 @@ impl Blockchain {
 
     /// Validate block timestamp
     fn validate_block_timestamp(&self, block: &Block) -> Result<()> {
-        let now = Utc::now();
-        
         // Block timestamp cannot be too far in the future
-        if block.header.timestamp > now + chrono::Duration::hours(2) {
+        let latest_allowed = self.adjusted_time() + chrono::Duration::seconds(MAX_FUTURE_BLOCK_TIME);
+        if block.header.timestamp > latest_allowed {
             return Err(ValidationError::InvalidTimestamp(
                 "Block timestamp too far in future".to_string()
             ).into());
         }
         
-        // Block timestamp must be after previous block
-        if let Some(previous_block) = self.get_latest_block() {
-            if block.header.timestamp <= previous_block.header.timestamp {
-                return Err(ValidationError::InvalidTimestamp(
-                    "Block timestamp must be after previous block".to_string()
-                ).into());
+        // Block timestamp must be after the median of the preceding blocks, so one
+        // block with a fast clock cannot hold back the blocks after it
+        if let Some(median) = self.median_time_past(block.index) {
+            if block.header.timestamp <= median {
+                return Err(ValidationError::InvalidTimestamp(format!(
+                    "Block timestamp must be after the median time of the last {} blocks ({})",
+                    MEDIAN_TIME_SPAN, median
+                )).into());
             }
         }
         
         Ok(())
     }
 
+    /// Reject a block containing a transaction whose lock time has not yet passed
+    fn validate_block_lock_times(&self, block: &Block) -> Result<()> {
+        let time = self.lock_time_cutoff(block.index);
+        if let Some(tx) = block.transactions().iter().find(|tx| !tx.is_final(block.index, time)) {
+            return Err(ValidationError::NonFinalTransaction(format!(
+                "transaction {} is locked until {}, block {} is at median time {}",
+                tx.hash(), tx.lock_time, block.index, time
+            )).into());
+        }
+        Ok(())
+    }
+
+    /// Time that timestamp lock times are measured against for a block at `height`.
+    ///
+    /// This is the median time past rather than the block's own timestamp, so a
+    /// miner cannot release locked transactions early by stamping a block ahead.
+    pub fn lock_time_cutoff(&self, height: u64) -> DateTime<Utc> {
+        self.median_time_past(height).unwrap_or(self.config.genesis_timestamp)
+    }
+
+    /// Lock time for a transaction a wallet builds now: the height of the next
+    /// block, as Bitcoin wallets do against fee sniping.
+    ///
+    /// A miner re-mining the tip to collect its fees plus those of newer
+    /// transactions cannot include ones locked like this, which takes away some
+    /// of the reward for rewriting history. While the tip is older than
+    /// [`MAX_ANTI_FEE_SNIPING_TIP_AGE`] the node is probably catching up and the
+    /// height would be stale, so the transaction is left unlocked.
+    pub fn fee_sniping_lock_time(&self) -> u64 {
+        let Some(tip) = self.get_latest_block() else { return 0 };
+        let tip_age = self.adjusted_time() - tip.header.timestamp;
+        if tip_age > chrono::Duration::seconds(MAX_ANTI_FEE_SNIPING_TIP_AGE as i64) {
+            return 0;
+        }
+        self.height()
+    }
+
+    /// Median timestamp of the (up to) `MEDIAN_TIME_SPAN` blocks before `height`;
+    /// `None` for the genesis block
+    pub fn median_time_past(&self, height: u64) -> Option<DateTime<Utc>> {
+        let end = (height as usize).min(self.blocks.len());
+        let start = end.saturating_sub(MEDIAN_TIME_SPAN);
+        let mut times: Vec<DateTime<Utc>> = self.blocks[start..end]
+            .iter()
+            .map(|b| b.header.timestamp)
+            .collect();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort_unstable();
+        Some(times[times.len() / 2])
+    }
+
+    /// Current network-adjusted time
+    pub fn adjusted_time(&self) -> DateTime<Utc> {
+        self.clock.now() + chrono::Duration::seconds(self.network_time.offset())
+    }
+
+    /// Read the time from `clock` instead of the system clock, e.g. a
+    /// [`MockClock`](crate::utils::MockClock) in tests
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Record every transaction the pool accepts and every block connected from
+    /// now on to `recorder`, starting with a [`ReplayEvent::Start`]
+    pub fn set_recorder(&mut self, recorder: ReplayRecorder) -> Result<()> {
+        let genesis = self.get_block_by_index(0).map(|b| b.hash()).unwrap_or_else(Hash256::zero);
+        recorder.record(&ReplayEvent::Start { at: self.clock.now(), genesis, height: self.height() })?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Append the event built by `event` to the recorder, if there is one; a
+    /// failed write is logged, since the chain has already changed
+    fn record(&self, event: impl FnOnce(DateTime<Utc>, &Self) -> ReplayEvent) {
+        if let Some(recorder) = &self.recorder {
+            if let Err(e) = recorder.record(&event(self.clock.now(), self)) {
+                tracing::warn!("Replay recording to {} is incomplete: {}", recorder.path().display(), e);
+            }
+        }
+    }
+
+    /// Record a peer's clock offset from ours, in seconds, for network-adjusted time
+    pub fn add_time_sample(&mut self, offset_secs: i64) {
+        self.network_time.add_sample(offset_secs);
+    }
+
     /// Apply block transactions to UTXO set
     fn apply_block_to_utxo_set(&mut self, block: &Block) -> Result<()> {
-        for tx in &block.transactions {
-            // Remove spent UTXOs
-            for input in &tx.inputs {
-                if !input.is_coinbase() {
-                    let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
-                    if let Some(mut utxo_entry) = self.utxo_set.remove(&utxo_id) {
-                        utxo_entry.mark_spent(block.index);
-                        // Optionally keep spent UTXOs for historical tracking
-                    } else {
-                        return Err(ValidationError::UtxoNotFound(utxo_id.to_string()).into());
-                    }
+        // Recipients of the outputs spent, looked up before they leave the set
+        let spent: Vec<(Address, UtxoId)> = block.transactions()
+            .iter()
+            .flat_map(|tx| &tx.inputs)
+            .filter(|input| !input.is_coinbase())
+            .map(|input| UtxoId::new(input.previous_tx_hash.clone(), input.output_index))
+            .filter_map(|utxo_id| self.utxo_set.get(&utxo_id).map(|entry| (entry.output.recipient.clone(), utxo_id)))
+            .collect();
+        if let Err(e) = apply_block_to_utxos(&mut self.utxo_set, block) {
+            // The set may be half updated; index whatever it now holds
+            self.reindex_addresses();
+            return Err(e);
+        }
+
+        for (address, utxo_id) in spent {
+            if let Some(ids) = self.address_index.get_mut(&address) {
+                ids.remove(&utxo_id);
+                if ids.is_empty() {
+                    self.address_index.remove(&address);
                 }
             }
-            
-            // Add new UTXOs
+        }
+        for tx in block.transactions() {
+            let tx_hash = tx.hash();
             for (output_index, output) in tx.outputs.iter().enumerate() {
-                let utxo_id = UtxoId::new(tx.hash(), output_index as u32);
-                let utxo_entry = UtxoEntry::new(
-                    output.clone(),
-                    block.index,
-                    tx.hash(),
-                    output_index as u32,
-                );
-                self.utxo_set.insert(utxo_id, utxo_entry);
+                // Outputs spent later in the same block never reach the set
+                let utxo_id = UtxoId::new(tx_hash.clone(), output_index as u32);
+                if self.utxo_set.contains_key(&utxo_id) {
+                    self.address_index.entry(output.recipient.clone()).or_default().insert(utxo_id);
+                }
             }
         }
-        
         Ok(())
     }
 
+    /// Swap in a whole UTXO set, such as one from a snapshot
+    fn replace_utxo_set(&mut self, utxos: HashMap<UtxoId, UtxoEntry>) {
+        self.utxo_set = utxos;
+        self.reindex_addresses();
+    }
+
+    /// Rebuild the address index from the UTXO set
+    fn reindex_addresses(&mut self) {
+        self.address_index.clear();
+        for (utxo_id, entry) in &self.utxo_set {
+            self.address_index.entry(entry.output.recipient.clone()).or_default().insert(utxo_id.clone());
+        }
+    }
+
+    /// Apply every block above `height` to the UTXO set
+    fn apply_blocks_above(&mut self, height: u64) -> Result<()> {
+        let blocks = self.blocks[(height as usize + 1).min(self.blocks.len())..].to_vec();
+        for block in &blocks {
+            self.apply_block_to_utxo_set(block)?;
+        }
+        Ok(())
+    }
+
+    /// Snapshot of the UTXO set as it was right after block `height`
+    pub fn utxo_snapshot(&self, height: u64) -> Result<UtxoSnapshot> {
+        let block = self.get_block_by_index(height)
+            .ok_or_else(|| BlockchainError::BlockNotFound(format!("height {}", height)))?;
+        if height + 1 == self.height() {
+            return UtxoSnapshot::new(height, block.hash(), self.utxo_set.values().cloned());
+        }
+
+        let mut utxos = HashMap::new();
+        for block in &self.blocks[..=height as usize] {
+            apply_block_to_utxos(&mut utxos, block)?;
+        }
+        UtxoSnapshot::new(height, block.hash(), utxos.into_values())
+    }
+
+    /// Blocks from genesis through `height`, for replaying an imported snapshot's history
+    pub fn blocks_through(&self, height: u64) -> Vec<Block> {
+        self.blocks.iter().take(height as usize + 1).cloned().collect()
+    }
+
+    /// State of the background check of an imported UTXO snapshot, if the chain was
+    /// started from one
+    pub fn snapshot_validation(&self) -> Option<&SnapshotValidation> {
+        self.snapshot_validation.as_ref()
+    }
+
+    /// Record the outcome of replaying the history below an imported snapshot.
+    ///
+    /// If the replay disagrees with the snapshot, the replayed set replaces it.
+    pub fn finish_snapshot_validation(&mut self, replayed: Result<UtxoSnapshot>) -> Result<&SnapshotValidation> {
+        let Some(SnapshotValidation::Pending { height, commitment }) = self.snapshot_validation.clone() else {
+            return Err(BlockchainError::InvalidChain("no UTXO snapshot is awaiting validation".to_string()).into());
+        };
+
+        let outcome = match replayed {
+            Ok(replayed) if replayed.height == height && replayed.commitment == commitment => {
+                SnapshotValidation::Valid { height }
+            }
+            Ok(replayed) => {
+                self.replace_utxo_set(replayed.utxo_set());
+                self.apply_blocks_above(height)?;
+                self.update_stats();
+                SnapshotValidation::Invalid {
+                    height,
+                    reason: format!(
+                        "replayed UTXO set commits to {}, snapshot to {}",
+                        replayed.commitment, commitment
+                    ),
+                }
+            }
+            Err(e) => SnapshotValidation::Invalid { height, reason: e.to_string() },
+        };
+        Ok(self.snapshot_validation.insert(outcome))
+    }
+
     /// Rebuild UTXO set from scratch
     fn rebuild_utxo_set(&mut self) -> Result<()> {
         self.utxo_set.clear();
+        self.address_index.clear();
         
         // Clone the blocks to avoid borrowing conflicts
         let blocks = self.blocks.clone();
@@ -436,52 +1125,76 @@ impl Blockchain {
         Ok(())
     }
 
-    /// Calculate the next difficulty based on recent block times
+    /// Difficulty the next block must be mined at
     pub fn calculate_next_difficulty(&self) -> u32 {
-        if self.blocks.len() < self.config.difficulty_adjustment_interval as usize {
-            return self.config.initial_difficulty;
+        self.difficulty_at(self.height())
+    }
+
+    /// Difficulty the block at `height` must carry.
+    ///
+    /// Blocks before the first adjustment use `initial_difficulty`. Every
+    /// `difficulty_adjustment_interval` blocks the difficulty is retargeted from how long
+    /// the previous interval took; since difficulty counts leading zero bits, a retarget
+    /// moves it by at most `log2(MAX_DIFFICULTY_ADJUSTMENT)` either way. In between, each
+    /// block carries its parent's difficulty.
+    pub fn difficulty_at(&self, height: u64) -> u32 {
+        let interval = self.config.difficulty_adjustment_interval.max(1);
+        let initial = self.config.initial_difficulty;
+        let Some(parent) = height.checked_sub(1).and_then(|h| self.get_block_by_index(h)) else {
+            return initial;
+        };
+        if height < interval {
+            return initial;
         }
-        
-        let adjustment_interval = self.config.difficulty_adjustment_interval as usize;
-        let current_height = self.blocks.len();
-        
-        // Only adjust at specific intervals
-        if current_height % adjustment_interval != 0 {
-            return self.get_latest_block()
-                .map(|b| b.header.difficulty)
-                .unwrap_or(self.config.initial_difficulty);
+        // The genesis block is fixed, so it does not carry the configured difficulty
+        let parent_difficulty = if parent.index == 0 { initial } else { parent.header.difficulty };
+        if !height.is_multiple_of(interval) {
+            return parent_difficulty;
         }
-        
-        // Calculate time taken for the last interval
-        let start_block = &self.blocks[current_height - adjustment_interval];
-        let end_block = &self.blocks[current_height - 1];
-        
-        let time_taken = end_block.header.timestamp
-            .signed_duration_since(start_block.header.timestamp)
-            .num_seconds() as f64;
-        
-        let expected_time = (adjustment_interval as f64) * (self.config.target_block_time as f64);
-        let ratio = time_taken / expected_time;
-        
-        // Limit adjustment to prevent extreme changes
-        let adjustment_factor = ratio.max(0.25).min(4.0);
-        
-        let current_difficulty = end_block.header.difficulty as f64;
-        let new_difficulty = (current_difficulty / adjustment_factor).round() as u32;
-        
-        // Ensure minimum difficulty
-        new_difficulty.max(1)
+
+        // Time the last `interval` blocks took, measured from the block before them
+        // (the first window can only be measured from genesis)
+        let start = &self.blocks[height.saturating_sub(interval + 1) as usize];
+        let block_times = parent.index - start.index;
+        if block_times == 0 {
+            return parent_difficulty;
+        }
+        let actual_time = parent.header.timestamp
+            .signed_duration_since(start.header.timestamp)
+            .num_seconds()
+            .max(1) as u64;
+        let expected_time = block_times * self.config.target_block_time;
+
+        adjust_difficulty(parent_difficulty, expected_time, actual_time, MAX_DIFFICULTY_ADJUSTMENT.log2())
     }
 
     /// Get block by hash
     pub fn get_block_by_hash(&self, hash: &Hash256) -> Option<&Block> {
-        if let Some(&index) = self.block_index.get(hash) {
-            self.blocks.get(index as usize)
+        if let Some(entry) = self.block_index.get(hash) {
+            self.blocks.get(entry.height as usize)
         } else {
             None
         }
     }
 
+    /// Index entry of a block on the main chain
+    pub fn block_index_entry(&self, hash: &Hash256) -> Option<BlockIndexEntry> {
+        self.block_index.get(hash).copied()
+    }
+
+    /// Total work of the chain up to and including the block at `height`
+    pub fn chain_work_at(&self, height: u64) -> Option<u128> {
+        let block = self.get_block_by_index(height)?;
+        self.block_index.get(&block.hash()).map(|entry| entry.chain_work)
+    }
+
+    /// Total work of the main chain: the expected number of hashes behind its tip
+    pub fn chain_work(&self) -> u128 {
+        self.blocks.len().checked_sub(1)
+            .and_then(|tip| self.chain_work_at(tip as u64))
+            .unwrap_or(0)
+    }
+
     /// Get block by index
     pub fn get_block_by_index(&self, index: u64) -> Option<&Block> {
         self.blocks.get(index as usize)
@@ -508,13 +1221,13 @@ impl Blockchain {
         
         if let Some(latest_block) = self.get_latest_block() {
             let latest_hash = latest_block.hash();
-            let latest_difficulty = latest_block.header.difficulty;
             self.stats.latest_block_hash = latest_hash;
-            self.stats.current_difficulty = latest_difficulty;
         }
+        self.stats.current_difficulty = self.calculate_next_difficulty();
+        self.stats.chain_work = self.chain_work();
         
         self.stats.total_transactions = self.blocks.iter()
-            .map(|b| b.transactions.len() as u64)
+            .map(|b| b.transactions().len() as u64)
             .sum();
         
         self.stats.total_utxos = self.utxo_set.len() as u64;
@@ -535,17 +1248,26 @@ impl Blockchain {
             self.stats.average_block_time = total_time as f64 / (self.recent_block_times.len() - 1) as f64;
         }
         
-        // Estimate hash rate (simplified)
-        if self.stats.average_block_time > 0.0 {
-            let difficulty = self.stats.current_difficulty as f64;
-            self.stats.estimated_hash_rate = difficulty * 2_f64.powi(32) / self.stats.average_block_time;
-        }
+        // Hashes the network spends per block, over the time it takes
+        self.stats.estimated_hash_rate = estimate_hash_rate(self.stats.current_difficulty, self.stats.average_block_time);
         
-        self.stats.last_updated = Utc::now();
+        self.stats.last_updated = self.clock.now();
     }
 
     /// Add transaction to the pool
     pub fn add_transaction_to_pool(&mut self, transaction: Transaction) -> Result<()> {
+        self.expire_pool_transactions();
+
+        // Only accept transactions the next block could include
+        let next_index = self.height();
+        let cutoff = self.lock_time_cutoff(next_index);
+        if !transaction.is_final(next_index, cutoff) {
+            return Err(ValidationError::NonFinalTransaction(format!(
+                "transaction {} is locked until {}, next block is {} at median time {}",
+                transaction.hash(), transaction.lock_time, next_index, cutoff
+            )).into());
+        }
+
         // Validate transaction
         let utxo_map: HashMap<String, TransactionOutput> = self.utxo_set
             .iter()
@@ -566,21 +1288,172 @@ impl Blockchain {
         
         // Add to pool
         let tx_hash = transaction.hash();
-        self.transaction_pool.insert(tx_hash, transaction);
+        self.record(|at, _| ReplayEvent::Transaction { at, transaction: transaction.clone() });
+        self.transaction_pool.insert(tx_hash, PoolEntry { transaction, added_at: self.clock.now() });
         
         Ok(())
     }
 
+    /// Drop pool transactions older than `config.mempool_expiry`; returns how many
+    /// were dropped
+    pub fn expire_pool_transactions(&mut self) -> usize {
+        let cutoff = self.clock.now() - chrono::Duration::seconds(self.config.mempool_expiry as i64);
+        let before = self.transaction_pool.len();
+        self.transaction_pool.retain(|_, entry| entry.added_at > cutoff);
+        before - self.transaction_pool.len()
+    }
+
     /// Get pending transactions from pool
     pub fn get_pending_transactions(&self) -> Vec<&Transaction> {
-        self.transaction_pool.values().collect()
+        self.transaction_pool.values().map(|entry| &entry.transaction).collect()
+    }
+
+    /// Pending transactions with their arrival time and fee
+    pub fn get_pending_details(&self) -> Vec<PendingTransaction<'_>> {
+        let now = self.clock.now();
+        self.transaction_pool
+            .values()
+            .map(|entry| PendingTransaction {
+                transaction: &entry.transaction,
+                added_at: entry.added_at,
+                age: now - entry.added_at,
+                fee: self.pool_fee(&entry.transaction),
+            })
+            .collect()
+    }
+
+    /// Fee of a pool transaction, from the UTXOs it spends
+    fn pool_fee(&self, transaction: &Transaction) -> u64 {
+        let spent: u64 = transaction.inputs
+            .iter()
+            .filter(|input| !input.is_coinbase())
+            .filter_map(|input| self.utxo_set.get(&UtxoId::new(input.previous_tx_hash.clone(), input.output_index)))
+            .map(|utxo| utxo.output.amount)
+            .sum();
+        spent.saturating_sub(transaction.total_output_amount())
+    }
+
+    /// Run every check the pool and the next block would make on `transaction`,
+    /// plus fee and coinbase maturity, and report each outcome rather than
+    /// stopping at the first failure
+    pub fn check_transaction(&self, transaction: &Transaction) -> TransactionValidation {
+        let tx_hash = transaction.hash();
+        let mut errors = Vec::new();
+
+        if transaction.inputs.is_empty() {
+            errors.push("Empty inputs".to_string());
+        }
+        if transaction.outputs.is_empty() {
+            errors.push(ValidationError::EmptyOutputs.to_string());
+        }
+        for output in &transaction.outputs {
+            if let Err(e) = output.validate() {
+                errors.push(e.to_string());
+            }
+        }
+        if let Some(memo) = transaction.memo.as_ref().filter(|memo| memo.len() > MAX_MEMO_SIZE) {
+            errors.push(ValidationError::MemoTooLarge(format!(
+                "{} bytes, at most {} allowed", memo.len(), MAX_MEMO_SIZE
+            )).to_string());
+        }
+        if transaction.is_coinbase() {
+            errors.push(ValidationError::InvalidCoinbase("coinbase transactions are only valid inside a block".to_string()).to_string());
+        }
+
+        let next_index = self.height();
+        let lock_time_final = transaction.is_final(next_index, self.lock_time_cutoff(next_index));
+        if !lock_time_final {
+            errors.push(ValidationError::NonFinalTransaction(format!(
+                "locked until {}, next block is {}", transaction.lock_time, next_index
+            )).to_string());
+        }
+
+        let mut seen = HashSet::new();
+        let mut inputs = Vec::with_capacity(transaction.inputs.len());
+        let mut total_in = 0u64;
+        for input in transaction.inputs.iter().filter(|input| !input.is_coinbase()) {
+            let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+            let outpoint = utxo_id.to_string();
+            if !seen.insert(utxo_id.clone()) {
+                errors.push(format!("{}: spent twice by this transaction", outpoint));
+            }
+
+            let signature_valid = match (&input.signature, &input.public_key) {
+                (Some(signature), Some(public_key)) => {
+                    crate::crypto::verify_signature(tx_hash.as_slice(), signature, public_key).unwrap_or(false)
+                }
+                _ => false,
+            };
+            if !signature_valid {
+                errors.push(format!("{}: missing or invalid signature", outpoint));
+            }
+
+            let conflicts: Vec<String> = self.transaction_pool
+                .iter()
+                .filter(|(hash, _)| **hash != tx_hash)
+                .filter(|(_, entry)| entry.transaction.inputs.iter().any(|other| {
+                    other.previous_tx_hash == input.previous_tx_hash && other.output_index == input.output_index
+                }))
+                .map(|(hash, _)| hash.to_hex())
+                .collect();
+            if !conflicts.is_empty() {
+                errors.push(format!("{}: already spent by pending transaction {}", outpoint, conflicts.join(", ")));
+            }
+
+            let mut check = InputValidation { outpoint, signature_valid, mature: true, conflicts, ..Default::default() };
+            match self.utxo_set.get(&utxo_id) {
+                Some(utxo) => {
+                    let confirmations = next_index.saturating_sub(utxo.block_height);
+                    check.utxo_found = true;
+                    check.amount = Some(utxo.output.amount);
+                    check.confirmations = Some(confirmations);
+                    check.coinbase = self.get_block_by_index(utxo.block_height)
+                        .and_then(|block| block.get_transaction(&utxo.tx_hash))
+                        .is_some_and(|tx| tx.is_coinbase());
+                    check.mature = !check.coinbase || confirmations >= COINBASE_MATURITY;
+                    if !check.mature {
+                        errors.push(format!(
+                            "{}: coinbase output has {} of {} confirmations", check.outpoint, confirmations, COINBASE_MATURITY
+                        ));
+                    }
+                    total_in = total_in.saturating_add(utxo.output.amount);
+                }
+                None => errors.push(ValidationError::UtxoNotFound(check.outpoint.clone()).to_string()),
+            }
+            inputs.push(check);
+        }
+
+        let total_out = transaction.total_output_amount();
+        if total_in < total_out {
+            errors.push(ValidationError::InsufficientFunds(format!(
+                "required {}, available {}", total_out, total_in
+            )).to_string());
+        }
+        let fee = total_in.saturating_sub(total_out);
+        let min_fee = self.config.min_transaction_fee;
+        if total_in >= total_out && fee < min_fee {
+            errors.push(format!("fee {} is below the minimum of {}", fee, min_fee));
+        }
+
+        TransactionValidation {
+            valid: errors.is_empty(),
+            hash: tx_hash.to_hex(),
+            inputs,
+            total_in,
+            total_out,
+            fee,
+            min_fee,
+            lock_time_final,
+            in_mempool: self.transaction_pool.contains_key(&tx_hash),
+            errors,
+        }
     }
 
     /// Get transaction by hash (from blockchain or pool)
     pub fn get_transaction(&self, tx_hash: &Hash256) -> Option<&Transaction> {
         // First check transaction pool
-        if let Some(tx) = self.transaction_pool.get(tx_hash) {
-            return Some(tx);
+        if let Some(entry) = self.transaction_pool.get(tx_hash) {
+            return Some(&entry.transaction);
         }
         
         // Then check blockchain
@@ -599,34 +1472,45 @@ impl Blockchain {
             .map(|b| b.hash())
             .unwrap_or_else(Hash256::zero);
         
+        self.expire_pool_transactions();
+
         let next_index = self.height();
         let difficulty = self.calculate_next_difficulty();
+        let lock_time_cutoff = self.lock_time_cutoff(next_index);
         
         // Select transactions from pool
         let mut transactions = Vec::new();
         
         // Add coinbase transaction
-        let block_reward = self.calculate_block_reward(next_index);
+        let block_reward = self.block_reward(next_index);
         let coinbase_tx = Transaction::coinbase(miner_address, block_reward, next_index);
         transactions.push(coinbase_tx);
         
-        // Add pending transactions (up to limit)
+        // Add pending transactions whose lock time has passed (up to limit)
         let max_tx = (self.config.max_transactions_per_block - 1) as usize; // -1 for coinbase
-        for tx in self.transaction_pool.values().take(max_tx) {
+        let ready = self.transaction_pool
+            .values()
+            .map(|entry| &entry.transaction)
+            .filter(|tx| tx.is_final(next_index, lock_time_cutoff));
+        for tx in ready.take(max_tx) {
             transactions.push(tx.clone());
         }
         
-        // Create block
-        let block = Block::new(next_index, previous_hash, transactions, difficulty);
+        // Create block, stamped with network-adjusted time but always after the median time past
+        let mut block = Block::new_at(next_index, previous_hash, transactions, difficulty, self.adjusted_time())
+            .with_network(self.config.network);
+        if let Some(median) = self.median_time_past(next_index) {
+            if block.header.timestamp <= median {
+                block.header.timestamp = median + chrono::Duration::seconds(1);
+            }
+        }
         
         Ok(block)
     }
 
-    /// Calculate block reward for given height
-    fn calculate_block_reward(&self, height: u64) -> u64 {
-        let halvings = height / self.config.halving_interval;
-        let reward = self.config.block_reward >> halvings; // Halve for each halving period
-        reward.max(1) // Minimum reward of 1 unit
+    /// Block subsidy at `height` under the configured reward schedule
+    pub fn block_reward(&self, height: u64) -> u64 {
+        self.config.reward_schedule.reward_at(height)
     }
 
     /// Get UTXO by ID
@@ -636,11 +1520,25 @@ impl Blockchain {
 
     /// Get all UTXOs for an address
     pub fn get_utxos_for_address(&self, address: &crate::crypto::Address) -> Vec<&UtxoEntry> {
-        self.utxo_set.values()
-            .filter(|utxo| utxo.output.recipient == *address)
+        self.address_index
+            .get(address)
+            .into_iter()
+            .flatten()
+            .filter_map(|utxo_id| self.utxo_set.get(utxo_id))
             .collect()
     }
 
+    /// Balance of every address holding an unspent output, in no particular order
+    pub fn address_balances(&self) -> impl Iterator<Item = (&Address, u64)> + '_ {
+        self.address_index.iter().map(|(address, utxo_ids)| {
+            let balance = utxo_ids
+                .iter()
+                .filter_map(|utxo_id| self.utxo_set.get(utxo_id))
+                .fold(0u64, |total, utxo| total.saturating_add(utxo.output.amount));
+            (address, balance)
+        })
+    }
+
     /// Get balance for an address
     pub fn get_balance(&self, address: &crate::crypto::Address) -> u64 {
         self.get_utxos_for_address(address)
@@ -649,6 +1547,11 @@ impl Blockchain {
             .sum()
     }
 
+    /// Network the chain belongs to
+    pub fn network(&self) -> Network {
+        self.config.network
+    }
+
     /// Get the current difficulty
     pub fn get_current_difficulty(&self) -> u32 {
         self.stats.current_difficulty
@@ -669,7 +1572,7 @@ impl Blockchain {
     /// Find transaction in blockchain and return block with transaction index
     pub fn find_transaction_in_block(&self, tx_hash: &Hash256) -> Option<(&Block, usize)> {
         for block in &self.blocks {
-            for (index, tx) in block.transactions.iter().enumerate() {
+            for (index, tx) in block.transactions().iter().enumerate() {
                 if &tx.hash() == tx_hash {
                     return Some((block, index));
                 }
@@ -678,27 +1581,179 @@ impl Blockchain {
         None
     }
 
+    /// Compact filter of the addresses `block` pays and spends from
+    pub fn block_filter(&self, block: &Block) -> BlockFilter {
+        let spent: Vec<crate::crypto::Address> = block
+            .transactions()
+            .iter()
+            .flat_map(|tx| &tx.inputs)
+            .filter(|input| !input.is_coinbase())
+            .filter_map(|input| {
+                let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+                match self.utxo_set.get(&utxo_id) {
+                    Some(entry) => Some(entry.output.recipient.clone()),
+                    // Already spent, by this block or a later one
+                    None => self
+                        .get_transaction(&input.previous_tx_hash)
+                        .and_then(|tx| tx.outputs.get(input.output_index as usize))
+                        .map(|output| output.recipient.clone()),
+                }
+            })
+            .collect();
+        BlockFilter::build(block, &spent)
+    }
+
     /// Verify the entire blockchain
     pub fn verify_chain(&self) -> Result<()> {
+        // Each block is checked against the outputs unspent just before it
+        let mut utxo_map: HashMap<String, TransactionOutput> = HashMap::new();
         for (i, block) in self.blocks.iter().enumerate() {
             let previous_block = if i == 0 { None } else { Some(&self.blocks[i - 1]) };
             
-            let utxo_map: HashMap<String, TransactionOutput> = self.utxo_set
-                .iter()
-                .map(|(id, entry)| (id.to_string(), entry.output.clone()))
-                .collect();
-            
             block.validate(previous_block, &utxo_map)?;
+            self.validate_block_checkpoint(block)?;
+
+            for tx in block.transactions() {
+                for input in tx.inputs.iter().filter(|input| !input.is_coinbase()) {
+                    utxo_map.remove(&format!("{}:{}", input.previous_tx_hash, input.output_index));
+                }
+                let tx_hash = tx.hash();
+                for (index, output) in tx.outputs.iter().enumerate() {
+                    utxo_map.insert(format!("{}:{}", tx_hash, index), output.clone());
+                }
+            }
         }
         
         Ok(())
     }
+
+    /// Replay every block from genesis, checking what each issues against the
+    /// reward schedule and what the replay leaves against the UTXO set.
+    ///
+    /// A block issues the outputs it creates less the outputs it spends; a coinbase
+    /// may claim its subsidy plus fees, so no block may issue more than its subsidy.
+    /// Whatever a block leaves unclaimed is gone for good.
+    pub fn audit_supply(&self) -> SupplyAudit {
+        let mut problems = Vec::new();
+        let problem = |block: Option<&Block>, message: String| SupplyProblem {
+            block_height: block.map(|b| b.index),
+            block_hash: block.map(|b| b.hash().to_hex()),
+            message,
+        };
+
+        let mut replayed: HashMap<UtxoId, UtxoEntry> = HashMap::new();
+        let (mut expected_supply, mut unclaimed) = (0u64, 0u64);
+        let mut replay_complete = true;
+        'blocks: for block in &self.blocks {
+            let subsidy = self.block_reward(block.index);
+            expected_supply = expected_supply.saturating_add(subsidy);
+
+            // Created minus spent; fees burned by a coinbase claiming less make it negative
+            let mut issued = 0i128;
+            for tx in block.transactions() {
+                for input in tx.inputs.iter().filter(|input| !input.is_coinbase()) {
+                    let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+                    match replayed.remove(&utxo_id) {
+                        Some(spent) => issued -= i128::from(spent.output.amount),
+                        None => {
+                            problems.push(problem(Some(block), format!("spends {}, which is not unspent", utxo_id)));
+                            replay_complete = false;
+                            break 'blocks;
+                        }
+                    }
+                }
+                let tx_hash = tx.hash();
+                for (output_index, output) in tx.outputs.iter().enumerate() {
+                    issued += i128::from(output.amount);
+                    let utxo_id = UtxoId::new(tx_hash.clone(), output_index as u32);
+                    replayed.insert(utxo_id, UtxoEntry::new(output.clone(), block.index, tx_hash.clone(), output_index as u32));
+                }
+            }
+
+            if issued > i128::from(subsidy) {
+                problems.push(problem(Some(block), format!("issues {}, but its subsidy is {}", issued, subsidy)));
+            } else {
+                let left = u64::try_from(i128::from(subsidy) - issued).unwrap_or(u64::MAX);
+                unclaimed = unclaimed.saturating_add(left);
+            }
+        }
+
+        let sum = |utxos: &mut dyn Iterator<Item = &UtxoEntry>| {
+            utxos.fold(0u64, |total, utxo| total.saturating_add(utxo.output.amount))
+        };
+        let replayed_supply = sum(&mut replayed.values());
+        let utxo_supply = sum(&mut self.utxo_set.values());
+        if problems.is_empty() && replayed_supply != expected_supply.saturating_sub(unclaimed) {
+            problems.push(problem(None, format!(
+                "replay leaves {}, but the subsidies less unclaimed amounts come to {}",
+                replayed_supply, expected_supply.saturating_sub(unclaimed)
+            )));
+        }
+        if replay_complete && replayed_supply != utxo_supply {
+            // The oldest entry the two sets disagree on points at the block to look at
+            let differing = replayed.iter()
+                .filter(|(id, entry)| self.utxo_set.get(*id) != Some(*entry))
+                .chain(self.utxo_set.iter().filter(|(id, entry)| replayed.get(*id) != Some(*entry)))
+                .map(|(_, entry)| entry.block_height)
+                .min();
+            problems.push(problem(
+                differing.and_then(|height| self.get_block_by_index(height)),
+                format!("the UTXO set holds {}, but replaying the blocks leaves {}", utxo_supply, replayed_supply),
+            ));
+        }
+        let consistent = problems.is_empty();
+        problems.truncate(MAX_SUPPLY_PROBLEMS);
+
+        let tip = self.get_latest_block();
+        SupplyAudit {
+            height: tip.map_or(0, |block| block.index),
+            block_hash: tip.map(|block| block.hash().to_hex()).unwrap_or_default(),
+            expected_supply,
+            unclaimed,
+            replayed_supply,
+            utxo_supply,
+            consistent,
+            problems,
+        }
+    }
+}
+
+/// Spend a block's inputs from `utxos` and add its outputs
+pub(crate) fn apply_block_to_utxos(utxos: &mut HashMap<UtxoId, UtxoEntry>, block: &Block) -> Result<()> {
+    for tx in block.transactions() {
+        // Remove spent UTXOs
+        for input in &tx.inputs {
+            if !input.is_coinbase() {
+                let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+                if utxos.remove(&utxo_id).is_none() {
+                    return Err(ValidationError::UtxoNotFound(utxo_id.to_string()).into());
+                }
+            }
+        }
+        
+        // Add new UTXOs
+        let tx_hash = tx.hash();
+        for (output_index, output) in tx.outputs.iter().enumerate() {
+            let utxo_id = UtxoId::new(tx_hash.clone(), output_index as u32);
+            let utxo_entry = UtxoEntry::new(
+                output.clone(),
+                block.index,
+                tx_hash.clone(),
+                output_index as u32,
+            );
+            utxos.insert(utxo_id, utxo_entry);
+        }
+    }
+    
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::TransactionInput;
     use crate::crypto::{Address, PublicKey, SignatureAlgorithm};
+    use crate::utils::MockClock;
 
     fn create_test_address() -> Address {
         let public_key = PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5]);
@@ -717,7 +1772,7 @@ mod tests {
 
     #[test]
     fn test_utxo_id_string_conversion() {
-        let tx_hash = Hash256::from_hex("1234567890abcdef").unwrap();
+        let tx_hash = crate::crypto::hash_data(b"1234567890abcdef");
         let utxo_id = UtxoId::new(tx_hash.clone(), 0);
         
         let string_repr = utxo_id.to_string();
@@ -736,6 +1791,215 @@ mod tests {
         assert_eq!(difficulty, 1); // Should return initial difficulty
     }
 
+    fn retarget_test_chain() -> Blockchain {
+        let config = BlockchainConfig {
+            difficulty_adjustment_interval: 4,
+            target_block_time: 60,
+            initial_difficulty: 6,
+            ..BlockchainConfig::default()
+        };
+        Blockchain::new(config, create_test_address()).unwrap()
+    }
+
+    /// A chain whose clock stands a day after genesis until the returned clock is moved
+    fn mock_clock_chain(config: BlockchainConfig) -> (Blockchain, MockClock) {
+        let mut blockchain = Blockchain::new(config, create_test_address()).unwrap();
+        let genesis_time = blockchain.get_block_by_index(0).unwrap().header.timestamp;
+        let clock = MockClock::new(genesis_time + chrono::Duration::days(1));
+        blockchain.set_clock(Arc::new(clock.clone()));
+        (blockchain, clock)
+    }
+
+    /// Mine and add a block stamped `timestamp`
+    fn mine_at(blockchain: &mut Blockchain, timestamp: DateTime<Utc>) -> Result<()> {
+        let mut block = blockchain.create_block(create_test_address()).unwrap();
+        block.header.timestamp = timestamp;
+        block.mine().unwrap();
+        blockchain.add_block(block)
+    }
+
+    /// Mine `count` blocks `spacing` seconds apart, returning the difficulty of each
+    fn mine_stream(blockchain: &mut Blockchain, count: usize, spacing: i64) -> Vec<u32> {
+        (0..count)
+            .map(|_| {
+                let parent_time = blockchain.get_latest_block().unwrap().header.timestamp;
+                mine_at(blockchain, parent_time + chrono::Duration::seconds(spacing)).unwrap();
+                blockchain.get_latest_block().unwrap().header.difficulty
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_difficulty_rises_for_fast_blocks() {
+        // Twice as fast as targeted: one more leading zero bit per retarget
+        let mut blockchain = retarget_test_chain();
+        assert_eq!(mine_stream(&mut blockchain, 8, 30), vec![6, 6, 6, 7, 7, 7, 7, 8]);
+        assert_eq!(blockchain.get_current_difficulty(), 8);
+
+        // Far too fast: clamped to a 4x (two bit) step
+        let mut blockchain = retarget_test_chain();
+        assert_eq!(mine_stream(&mut blockchain, 4, 1), vec![6, 6, 6, 8]);
+    }
+
+    #[test]
+    fn test_difficulty_falls_for_slow_blocks() {
+        let mut blockchain = retarget_test_chain();
+        assert_eq!(mine_stream(&mut blockchain, 8, 240), vec![6, 6, 6, 4, 4, 4, 4, 2]);
+
+        // Far too slow: clamped to two bits, and never below 1
+        assert_eq!(mine_stream(&mut blockchain, 4, 6000), vec![2, 2, 2, 1]);
+        assert_eq!(blockchain.calculate_next_difficulty(), 1);
+    }
+
+    #[test]
+    fn test_blocks_must_carry_expected_difficulty() {
+        let mut blockchain = retarget_test_chain();
+        mine_stream(&mut blockchain, 3, 30);
+        assert_eq!(blockchain.calculate_next_difficulty(), 7);
+
+        let parent_time = blockchain.get_latest_block().unwrap().header.timestamp;
+        let mut block = blockchain.create_block(create_test_address()).unwrap();
+        assert_eq!(block.header.difficulty, 7);
+        block.header.timestamp = parent_time + chrono::Duration::seconds(30);
+        block.header.difficulty = 6; // the parent's, but this height retargets
+        block.mine().unwrap();
+        assert!(blockchain.add_block(block).is_err());
+        assert_eq!(blockchain.height(), 4);
+    }
+
+    #[test]
+    fn test_median_time_past_rule() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 11, 60);
+        let genesis_time = blockchain.get_block_by_index(0).unwrap().header.timestamp;
+        let median = blockchain.median_time_past(12).unwrap();
+        assert_eq!(median, genesis_time + chrono::Duration::seconds(360));
+
+        // A miner with a fast clock stamps a block far ahead of the others...
+        let parent_time = blockchain.get_latest_block().unwrap().header.timestamp;
+        mine_at(&mut blockchain, parent_time + chrono::Duration::minutes(90)).unwrap();
+        // ...and honest miners can still build on it with earlier timestamps
+        mine_at(&mut blockchain, parent_time + chrono::Duration::seconds(60)).unwrap();
+
+        // but not at or before the median of the last 11 blocks
+        let median = blockchain.median_time_past(blockchain.height()).unwrap();
+        assert!(mine_at(&mut blockchain, median).is_err());
+        mine_at(&mut blockchain, median + chrono::Duration::seconds(1)).unwrap();
+    }
+
+    #[test]
+    fn test_future_timestamps_use_network_adjusted_time() {
+        let (mut blockchain, clock) = mock_clock_chain(BlockchainConfig::default());
+        let ahead = clock.now() + chrono::Duration::minutes(150);
+        assert!(mine_at(&mut blockchain, ahead).is_err());
+
+        // Peers agree our clock is an hour slow
+        for _ in 0..crate::utils::MIN_TIME_SAMPLES {
+            blockchain.add_time_sample(3600);
+        }
+        mine_at(&mut blockchain, ahead).unwrap();
+    }
+
+    #[test]
+    fn test_created_blocks_follow_median_time_past() {
+        // Recent blocks stamped ahead of our clock (within the allowed drift)
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        for minutes in [30, 31, 32] {
+            mine_at(&mut blockchain, Utc::now() + chrono::Duration::minutes(minutes)).unwrap();
+        }
+
+        let block = blockchain.create_block(create_test_address()).unwrap();
+        assert!(block.header.timestamp > blockchain.median_time_past(4).unwrap());
+        blockchain.add_block(block).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 3, 60);
+        let pinned = blockchain.get_block_by_index(2).unwrap().hash();
+        blockchain.config.checkpoints.insert(2, pinned.clone());
+        blockchain.config.checkpoints.insert(10, Hash256::zero());
+
+        assert_eq!(blockchain.last_checkpoint(), Some((2, &pinned)));
+        assert!(blockchain.check_reorg(1).is_err());
+        assert!(blockchain.check_reorg(2).is_ok());
+        assert!(blockchain.verify_chain().is_ok());
+
+        // A block at a checkpointed height must carry the pinned hash
+        mine_stream(&mut blockchain, 6, 60);
+        let parent_time = blockchain.get_latest_block().unwrap().header.timestamp;
+        let err = mine_at(&mut blockchain, parent_time + chrono::Duration::seconds(60)).unwrap_err();
+        assert!(err.to_string().contains("Checkpoint mismatch"));
+        assert_eq!(blockchain.height(), 10);
+    }
+
+    #[test]
+    fn test_branches_forking_below_a_checkpoint_are_dropped() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 3, 60);
+        let tip = blockchain.get_latest_block().unwrap().hash();
+        let pinned = blockchain.get_block_by_index(2).unwrap().hash();
+        blockchain.config.checkpoints.insert(2, pinned);
+
+        let mut rival = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut rival, 4, 61);
+        let blocks: Vec<Block> = (1..=4).map(|h| rival.get_block_by_index(h).unwrap().clone()).collect();
+        for block in &blocks[..3] {
+            assert!(matches!(blockchain.process_block(block.clone()).unwrap(), BlockStatus::SideBranch { .. }));
+        }
+
+        // The heavier branch would undo the checkpointed block
+        let err = blockchain.process_block(blocks[3].clone()).unwrap_err();
+        assert!(err.to_string().contains("checkpoint"), "{}", err);
+        assert_eq!(blockchain.get_latest_block().unwrap().hash(), tip);
+        assert!(blockchain.side_blocks.is_empty());
+        assert_eq!(
+            blockchain.process_block(blocks[3].clone()).unwrap(),
+            BlockStatus::Orphaned { missing_parent: blocks[2].hash() }
+        );
+        blockchain.verify_chain().unwrap();
+    }
+
+    #[test]
+    fn test_start_from_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(PersistentStorage::new(dir.path()).unwrap());
+        let mut source = Blockchain::with_storage(BlockchainConfig::default(), storage.clone(), create_test_address()).unwrap();
+        mine_stream(&mut source, 4, 60);
+        let snapshot = source.utxo_snapshot(3).unwrap();
+        let expected_balance = source.get_balance(&create_test_address());
+        let history = source.blocks_through(3);
+        drop(source);
+
+        let mut blockchain = Blockchain::with_snapshot(
+            BlockchainConfig::default(), storage.clone(), create_test_address(), snapshot.clone(),
+        ).unwrap();
+        assert_eq!(blockchain.height(), 5);
+        assert_eq!(blockchain.get_balance(&create_test_address()), expected_balance);
+        assert!(matches!(blockchain.snapshot_validation(), Some(SnapshotValidation::Pending { height: 3, .. })));
+
+        let replayed = crate::core::replay_snapshot(&history);
+        assert_eq!(blockchain.finish_snapshot_validation(replayed).unwrap(), &SnapshotValidation::Valid { height: 3 });
+        assert!(blockchain.finish_snapshot_validation(Ok(snapshot.clone())).is_err());
+
+        // A snapshot that disagrees with the history is replaced by the replayed set
+        let mut forged = snapshot.clone();
+        forged.utxos[0].output.amount += 1_000;
+        let forged = UtxoSnapshot::new(forged.height, forged.block_hash, forged.utxos).unwrap();
+        let mut blockchain = Blockchain::with_snapshot(
+            BlockchainConfig::default(), storage.clone(), create_test_address(), forged,
+        ).unwrap();
+        assert_ne!(blockchain.get_balance(&create_test_address()), expected_balance);
+        let outcome = blockchain.finish_snapshot_validation(Ok(snapshot)).unwrap();
+        assert!(matches!(outcome, SnapshotValidation::Invalid { height: 3, .. }));
+        assert_eq!(blockchain.get_balance(&create_test_address()), expected_balance);
+
+        let early = blockchain.utxo_snapshot(1).unwrap();
+        let wrong_height = UtxoSnapshot::new(40, early.block_hash, early.utxos).unwrap();
+        assert!(Blockchain::with_snapshot(BlockchainConfig::default(), storage, create_test_address(), wrong_height).is_err());
+    }
+
     #[test]
     fn test_balance_calculation() {
         let config = BlockchainConfig::default();
@@ -743,21 +2007,275 @@ mod tests {
         let blockchain = Blockchain::new(config.clone(), genesis_address.clone()).unwrap();
         
         let balance = blockchain.get_balance(&genesis_address);
-        assert_eq!(balance, config.block_reward); // Genesis block reward
+        assert_eq!(balance, config.reward_schedule.reward_at(0)); // Genesis block reward
+    }
+
+    #[test]
+    fn test_address_index_follows_the_utxo_set() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        let address = |byte| Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![byte; 5]));
+        let (alice, bob, carol) = (create_test_address(), address(2), address(3));
+        let genesis = blockchain.get_utxos_for_address(&alice)[0].clone();
+
+        // Alice pays Carol and herself; Carol passes her output on to Bob in the same block
+        let pay = Transaction::new(
+            vec![TransactionInput::new(genesis.tx_hash.clone(), genesis.output_index, None, None)],
+            vec![TransactionOutput::new(400, carol.clone()), TransactionOutput::new(500, alice.clone())],
+        );
+        let pass_on = Transaction::new(
+            vec![TransactionInput::new(pay.hash(), 0, None, None)],
+            vec![TransactionOutput::new(400, bob.clone())],
+        );
+        let coinbase = Transaction::coinbase(bob.clone(), 50, 1);
+        let block = Block::new(1, blockchain.get_latest_block().unwrap().hash(), vec![coinbase, pay, pass_on], 1);
+        blockchain.apply_block_to_utxo_set(&block).unwrap();
+
+        for address in [&alice, &bob, &carol] {
+            let mut indexed: Vec<UtxoId> = blockchain.get_utxos_for_address(address).iter().map(|u| u.id()).collect();
+            let mut scanned: Vec<UtxoId> = blockchain.utxo_set.values()
+                .filter(|utxo| utxo.output.recipient == *address)
+                .map(|utxo| utxo.id())
+                .collect();
+            indexed.sort_by_key(|id| id.to_string());
+            scanned.sort_by_key(|id| id.to_string());
+            assert_eq!(indexed, scanned);
+        }
+        assert_eq!(blockchain.get_balance(&alice), 500);
+        assert_eq!(blockchain.get_balance(&bob), 450);
+        assert!(!blockchain.address_index.contains_key(&carol));
+
+        // Spending an output that is not there leaves the index matching the set
+        assert!(blockchain.apply_block_to_utxo_set(&block).is_err());
+        let indexed: usize = blockchain.address_index.values().map(HashSet::len).sum();
+        assert_eq!(indexed, blockchain.utxo_set.len());
     }
 
     #[test]
     fn test_block_reward_halving() {
-        let mut config = BlockchainConfig::default();
-        config.halving_interval = 10; // Small interval for testing
+        let config = BlockchainConfig {
+            reward_schedule: RewardSchedule::Halving { initial: 5_000_000_000, interval: 10 }, // Small interval for testing
+            ..BlockchainConfig::default()
+        };
         
         let genesis_address = create_test_address();
         let blockchain = Blockchain::new(config.clone(), genesis_address).unwrap();
         
         // Test rewards at different heights
-        assert_eq!(blockchain.calculate_block_reward(0), config.block_reward);
-        assert_eq!(blockchain.calculate_block_reward(10), config.block_reward / 2);
-        assert_eq!(blockchain.calculate_block_reward(20), config.block_reward / 4);
+        assert_eq!(blockchain.block_reward(0), 5_000_000_000);
+        assert_eq!(blockchain.block_reward(10), 5_000_000_000 / 2);
+        assert_eq!(blockchain.block_reward(20), 5_000_000_000 / 4);
+    }
+
+    #[test]
+    fn test_coinbase_uses_reward_schedule() {
+        let config = BlockchainConfig { reward_schedule: RewardSchedule::Fixed { reward: 1234 }, ..BlockchainConfig::default() };
+        let miner = create_test_address();
+        let mut blockchain = Blockchain::new(config, miner.clone()).unwrap();
+
+        assert_eq!(blockchain.get_balance(&miner), 1234);
+        let block = blockchain.create_block(miner).unwrap();
+        assert_eq!(block.transactions()[0].total_output_amount(), 1234);
+    }
+
+    #[test]
+    fn test_supply_audit() {
+        let config = BlockchainConfig {
+            reward_schedule: RewardSchedule::Halving { initial: 5000, interval: 2 },
+            ..BlockchainConfig::default()
+        };
+        let mut blockchain = Blockchain::new(config, create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 4, 60);
+        let audit = blockchain.audit_supply();
+        assert!(audit.consistent, "{:?}", audit.problems);
+        assert_eq!(audit.height, 4);
+        assert_eq!(audit.expected_supply, 5000 + 5000 + 2500 + 2500 + 1250);
+        assert_eq!((audit.unclaimed, audit.replayed_supply, audit.utxo_supply), (0, 16250, 16250));
+
+        // A UTXO set that drifted from the blocks is pinned on the block of the entry
+        let utxo_id = UtxoId::new(blockchain.blocks[3].transactions()[0].hash(), 0);
+        blockchain.utxo_set.get_mut(&utxo_id).unwrap().output.amount -= 100;
+        let audit = blockchain.audit_supply();
+        assert_eq!(audit.utxo_supply, 16150);
+        assert_eq!(audit.problems.len(), 1);
+        assert_eq!(audit.problems[0].block_height, Some(3));
+        assert_eq!(audit.problems[0].block_hash, Some(blockchain.blocks[3].hash().to_hex()));
+        blockchain.utxo_set.get_mut(&utxo_id).unwrap().output.amount += 100;
+
+        // So is a coinbase claiming more than the schedule allows
+        blockchain.blocks[2].transactions_mut()[0].outputs[0].amount += 1;
+        let audit = blockchain.audit_supply();
+        assert!(!audit.consistent);
+        assert_eq!(audit.problems[0].block_height, Some(2));
+        assert!(audit.problems[0].message.contains("issues 2501"), "{}", audit.problems[0].message);
+    }
+
+    #[test]
+    fn test_chain_work_accumulates() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        assert_eq!(blockchain.chain_work(), 2);
+
+        mine_stream(&mut blockchain, 2, 60);
+        assert_eq!(blockchain.chain_work(), 6);
+        assert_eq!(blockchain.get_stats().chain_work, 6);
+        assert_eq!(blockchain.chain_work_at(1), Some(4));
+        assert_eq!(blockchain.chain_work_at(3), None);
+
+        let tip = blockchain.get_latest_block().unwrap().hash();
+        assert_eq!(blockchain.block_index_entry(&tip), Some(BlockIndexEntry { height: 2, chain_work: 6 }));
+    }
+
+    #[test]
+    fn test_orphans_connect_when_parent_arrives() {
+        let mut source = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut source, 3, 60);
+        let blocks: Vec<Block> = (1..=3).map(|h| source.get_block_by_index(h).unwrap().clone()).collect();
+
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        assert_eq!(
+            blockchain.process_block(blocks[2].clone()).unwrap(),
+            BlockStatus::Orphaned { missing_parent: blocks[1].hash() }
+        );
+        assert_eq!(
+            blockchain.process_block(blocks[1].clone()).unwrap(),
+            BlockStatus::Orphaned { missing_parent: blocks[0].hash() }
+        );
+        assert_eq!(blockchain.process_block(blocks[2].clone()).unwrap(), BlockStatus::AlreadyKnown);
+        assert_eq!(blockchain.missing_parents(), vec![blocks[0].hash()]);
+        assert_eq!(blockchain.orphan_blocks().len(), 2);
+
+        assert_eq!(
+            blockchain.process_block(blocks[0].clone()).unwrap(),
+            BlockStatus::Connected { height: 3, orphans_connected: 2 }
+        );
+        assert!(blockchain.orphan_blocks().is_empty());
+        assert_eq!(blockchain.get_latest_block().unwrap().hash(), blocks[2].hash());
+        assert_eq!(blockchain.process_block(blocks[0].clone()).unwrap(), BlockStatus::AlreadyKnown);
+    }
+
+    #[test]
+    fn test_reorganizes_to_the_branch_with_more_work() {
+        let storage = Arc::new(PersistentStorage::in_memory());
+        let mut blockchain = Blockchain::with_storage(BlockchainConfig::default(), storage.clone(), create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 2, 60);
+        let old_tip = blockchain.get_latest_block().unwrap().clone();
+
+        let mut rival = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut rival, 3, 61);
+        let blocks: Vec<Block> = (1..=3).map(|h| rival.get_block_by_index(h).unwrap().clone()).collect();
+
+        // On equal work the chain seen first is kept
+        assert_eq!(blockchain.process_block(blocks[0].clone()).unwrap(), BlockStatus::SideBranch { height: 1 });
+        assert_eq!(blockchain.process_block(blocks[1].clone()).unwrap(), BlockStatus::SideBranch { height: 2 });
+        assert_eq!(blockchain.get_latest_block().unwrap().hash(), old_tip.hash());
+
+        assert_eq!(
+            blockchain.process_block(blocks[2].clone()).unwrap(),
+            BlockStatus::Reorganized { fork_height: 0, disconnected: 2, height: 3, orphans_connected: 0 }
+        );
+        assert_eq!(blockchain.get_latest_block().unwrap().hash(), blocks[2].hash());
+        assert_eq!(blockchain.chain_work(), rival.chain_work());
+        assert_eq!(blockchain.get_balance(&create_test_address()), rival.get_balance(&create_test_address()));
+        assert!(blockchain.block_index_entry(&old_tip.hash()).is_none());
+        assert_eq!(blockchain.process_block(old_tip).unwrap(), BlockStatus::AlreadyKnown);
+
+        let reloaded = Blockchain::with_storage(BlockchainConfig::default(), storage, create_test_address()).unwrap();
+        assert_eq!(reloaded.get_latest_block().unwrap().hash(), blocks[2].hash());
+    }
+
+    #[test]
+    fn test_invalid_branch_leaves_the_chain_in_place() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 2, 60);
+        let tip = blockchain.get_latest_block().unwrap().hash();
+        let balance = blockchain.get_balance(&create_test_address());
+
+        // Blocks claiming more work than this chain's difficulty rules allow
+        let harder = BlockchainConfig { initial_difficulty: 2, ..BlockchainConfig::default() };
+        let mut rival = Blockchain::new(harder, create_test_address()).unwrap();
+        mine_stream(&mut rival, 2, 61);
+        let blocks: Vec<Block> = (1..=2).map(|h| rival.get_block_by_index(h).unwrap().clone()).collect();
+
+        assert_eq!(blockchain.process_block(blocks[0].clone()).unwrap(), BlockStatus::SideBranch { height: 1 });
+        let err = blockchain.process_block(blocks[1].clone()).unwrap_err();
+        assert!(err.to_string().contains("difficulty"), "{}", err);
+
+        assert_eq!(blockchain.get_latest_block().unwrap().hash(), tip);
+        assert_eq!(blockchain.height(), 3);
+        assert_eq!(blockchain.get_balance(&create_test_address()), balance);
+        blockchain.verify_chain().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_block_drops_every_descendant() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut blockchain, 4, 60);
+        let tip = blockchain.get_latest_block().unwrap().hash();
+        let genesis = blockchain.get_block_by_index(0).unwrap().clone();
+        let reward = blockchain.block_reward(1);
+        let child = |parent: &Block, spacing: i64| {
+            let index = parent.index + 1;
+            let coinbase = Transaction::coinbase(create_test_address(), reward, index);
+            let timestamp = parent.header.timestamp + chrono::Duration::seconds(spacing);
+            let mut block = Block::new_at(index, parent.hash(), vec![coinbase], genesis.header.difficulty, timestamp);
+            block.mine().unwrap();
+            block
+        };
+
+        // Dated no later than the genesis block, so it fails once connected
+        let invalid = child(&genesis, 0);
+        let (first, sibling) = (child(&invalid, 61), child(&invalid, 62));
+        let (second, nephew) = (child(&first, 61), child(&sibling, 61));
+        let third = child(&second, 61);
+        for block in [&invalid, &first, &sibling, &second, &nephew, &third] {
+            assert!(matches!(blockchain.process_block(block.clone()).unwrap(), BlockStatus::SideBranch { .. }));
+        }
+
+        // One more block gives the branch more work, and connecting it fails
+        let err = blockchain.process_block(child(&third, 61)).unwrap_err();
+        assert!(err.to_string().contains("median time"), "{}", err);
+        assert_eq!(blockchain.get_latest_block().unwrap().hash(), tip);
+        assert!(blockchain.side_blocks.is_empty());
+        assert_eq!(
+            blockchain.process_block(child(&nephew, 61)).unwrap(),
+            BlockStatus::Orphaned { missing_parent: nephew.hash() }
+        );
+    }
+
+    #[test]
+    fn test_networks_do_not_mix() {
+        let testnet = BlockchainConfig { network: Network::Testnet, ..BlockchainConfig::default() };
+        let mut source = Blockchain::new(testnet, create_test_address()).unwrap();
+        let mainnet = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        assert_ne!(source.get_block_by_index(0).unwrap().hash(), mainnet.get_block_by_index(0).unwrap().hash());
+
+        mine_stream(&mut source, 2, 60);
+        assert_eq!(source.get_latest_block().unwrap().header.network, Network::Testnet);
+        let blocks: Vec<Block> = (1..=2).map(|h| source.get_block_by_index(h).unwrap().clone()).collect();
+
+        // Neither a block extending the tip nor an orphan gets in
+        let mut blockchain = mainnet;
+        for block in blocks {
+            let err = blockchain.process_block(block).unwrap_err();
+            assert!(err.to_string().contains("Wrong network"));
+        }
+        assert!(blockchain.orphan_blocks().is_empty());
+        assert_eq!(blockchain.height(), 1);
+    }
+
+    #[test]
+    fn test_stale_orphans_expire() {
+        let mut source = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        mine_stream(&mut source, 2, 60);
+        let orphan = source.get_latest_block().unwrap().clone();
+
+        let config = BlockchainConfig { orphan_expiry: 60, ..BlockchainConfig::default() };
+        let mut blockchain = Blockchain::new(config, create_test_address()).unwrap();
+        blockchain.process_block(orphan.clone()).unwrap();
+        assert_eq!(blockchain.expire_orphans(), 0);
+
+        blockchain.orphaned_blocks.get_mut(&orphan.hash()).unwrap().received_at -= chrono::Duration::seconds(120);
+        assert_eq!(blockchain.expire_orphans(), 1);
+        assert!(blockchain.missing_parents().is_empty());
     }
 
     #[test]
@@ -774,4 +2292,177 @@ mod tests {
         // Note: This will fail validation due to missing UTXO, but tests the pool mechanism
         assert!(blockchain.add_transaction_to_pool(tx).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_check_transaction() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        let genesis = blockchain.get_utxos_for_address(&create_test_address())[0].clone();
+        let signed_input = || {
+            TransactionInput::new(
+                genesis.tx_hash.clone(),
+                genesis.output_index,
+                Some(crate::crypto::Signature::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1])),
+                Some(PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5])),
+            )
+        };
+        let spend = |amount| Transaction::new(vec![signed_input()], vec![TransactionOutput::new(amount, create_test_address())]);
+
+        // The genesis reward is a coinbase one block deep: every input check but maturity passes
+        let report = blockchain.check_transaction(&spend(genesis.output.amount - 2000));
+        let input = &report.inputs[0];
+        assert!(input.utxo_found && input.signature_valid && input.coinbase);
+        assert_eq!(input.confirmations, Some(1));
+        assert!(!input.mature);
+        assert_eq!((report.total_in, report.fee, report.min_fee), (genesis.output.amount, 2000, 1000));
+        assert_eq!(report.errors.len(), 1);
+        assert!(!report.valid);
+
+        // Each failed check is reported, not just the first
+        let mut unsigned = spend(genesis.output.amount);
+        unsigned.inputs[0].signature = None;
+        let report = blockchain.check_transaction(&unsigned);
+        assert!(!report.inputs[0].signature_valid);
+        assert_eq!(report.fee, 0);
+        assert_eq!(report.errors.len(), 3);
+
+        // A pending spend of the same output is a conflict
+        let pending = spend(genesis.output.amount - 5000);
+        let pending_hash = pending.hash();
+        blockchain.transaction_pool.insert(pending_hash.clone(), PoolEntry { transaction: pending.clone(), added_at: Utc::now() });
+        let report = blockchain.check_transaction(&spend(genesis.output.amount - 2000));
+        assert_eq!(report.inputs[0].conflicts, vec![pending_hash.to_hex()]);
+        assert!(!report.in_mempool);
+        assert!(blockchain.check_transaction(&pending).in_mempool);
+    }
+
+    /// `template` with its coinbase locked until `lock_time`
+    fn with_locked_coinbase(template: &Block, lock_time: u64) -> Block {
+        let mut block = template.clone();
+        let coinbase = block.transactions()[0].clone().with_lock_time(lock_time);
+        block.replace_transaction(0, coinbase).unwrap();
+        block.mine().unwrap();
+        block
+    }
+
+    #[test]
+    fn test_lock_time_enforced() {
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), create_test_address()).unwrap();
+        let output = TransactionOutput::new(1000, create_test_address());
+        let locked = Transaction::new(vec![], vec![output]).with_lock_time(3);
+
+        // The pool turns away what the next block could not include
+        let err = blockchain.add_transaction_to_pool(locked.clone()).unwrap_err();
+        assert!(err.to_string().contains("Non-final transaction"));
+
+        // Block templates skip locked pool entries
+        blockchain.transaction_pool.insert(
+            locked.hash(),
+            PoolEntry { transaction: locked, added_at: Utc::now() },
+        );
+        let template = blockchain.create_block(create_test_address()).unwrap();
+        assert_eq!(template.transactions().len(), 1);
+
+        // A block carrying a transaction locked past its height is rejected
+        let err = blockchain.add_block(with_locked_coinbase(&template, 2)).unwrap_err();
+        assert!(err.to_string().contains("Non-final transaction"));
+        blockchain.add_block(with_locked_coinbase(&template, 1)).unwrap();
+
+        // Timestamp locks are measured against the median time past
+        let template = blockchain.create_block(create_test_address()).unwrap();
+        let cutoff = blockchain.lock_time_cutoff(template.index).timestamp() as u64;
+        assert!(blockchain.add_block(with_locked_coinbase(&template, cutoff + 1)).is_err());
+        blockchain.add_block(with_locked_coinbase(&template, cutoff)).unwrap();
+        assert_eq!(blockchain.height(), 3);
+    }
+
+    #[test]
+    fn test_fee_sniping_lock_time_survives_reorgs() {
+        let (mut blockchain, clock) = mock_clock_chain(BlockchainConfig::default());
+        // A day after genesis the tip looks stale, so nothing is locked
+        assert_eq!(blockchain.fee_sniping_lock_time(), 0);
+
+        for _ in 0..3 {
+            clock.advance(chrono::Duration::minutes(10));
+            mine_at(&mut blockchain, clock.now()).unwrap();
+        }
+        let lock_time = blockchain.fee_sniping_lock_time();
+        assert_eq!(lock_time, 4);
+        let output = TransactionOutput::new(1000, create_test_address());
+        let payment = Transaction::new(vec![], vec![output]).with_lock_time(lock_time);
+        assert!(blockchain.check_transaction(&payment).lock_time_final);
+
+        // A miner forking off block 2 to re-mine block 3 cannot take the payment
+        let (mut fork, fork_clock) = mock_clock_chain(BlockchainConfig::default());
+        fork_clock.set(clock.now());
+        for index in 1..3 {
+            fork.add_block(blockchain.get_block_by_index(index).unwrap().clone()).unwrap();
+        }
+        assert!(!fork.check_transaction(&payment).lock_time_final);
+        let err = fork.add_transaction_to_pool(payment).unwrap_err();
+        assert!(err.to_string().contains("Non-final transaction"));
+        let template = fork.create_block(create_test_address()).unwrap();
+        let err = fork.add_block(with_locked_coinbase(&template, lock_time)).unwrap_err();
+        assert!(err.to_string().contains("Non-final transaction"));
+
+        // It can go into the block after the old tip, on either branch
+        fork.add_block(template).unwrap();
+        let template = fork.create_block(create_test_address()).unwrap();
+        fork.add_block(with_locked_coinbase(&template, lock_time)).unwrap();
+        let template = blockchain.create_block(create_test_address()).unwrap();
+        blockchain.add_block(with_locked_coinbase(&template, lock_time)).unwrap();
+
+        // Without a block for eight hours new transactions go unlocked again
+        clock.advance(chrono::Duration::hours(8) + chrono::Duration::seconds(1));
+        assert_eq!(blockchain.fee_sniping_lock_time(), 0);
+    }
+
+    #[test]
+    fn test_pool_expiry() {
+        let config = BlockchainConfig { mempool_expiry: 3600, ..BlockchainConfig::default() };
+        let (mut blockchain, clock) = mock_clock_chain(config);
+        for (amount, age) in [(1001, 7200), (1002, 60)] {
+            let tx = Transaction::new(vec![], vec![TransactionOutput::new(amount, create_test_address())]);
+            let added_at = clock.now() - chrono::Duration::seconds(age);
+            blockchain.transaction_pool.insert(tx.hash(), PoolEntry { transaction: tx, added_at });
+        }
+
+        assert_eq!(blockchain.expire_pool_transactions(), 1);
+        let pending = blockchain.get_pending_transactions();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].total_output_amount(), 1002);
+        assert_eq!(blockchain.expire_pool_transactions(), 0);
+    }
+
+    #[test]
+    fn test_pending_details() {
+        let (mut blockchain, clock) = mock_clock_chain(BlockchainConfig::default());
+        let coinbase = blockchain.get_block_by_index(0).unwrap().transactions()[0].clone();
+        let reward = coinbase.total_output_amount();
+        let input = TransactionInput::new(coinbase.hash(), 0, None, None);
+        let tx = Transaction::new(vec![input], vec![TransactionOutput::new(reward - 500, create_test_address())]);
+        let added_at = clock.now() - chrono::Duration::seconds(90);
+        blockchain.transaction_pool.insert(tx.hash(), PoolEntry { transaction: tx, added_at });
+
+        let pending = blockchain.get_pending_details();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].fee, 500);
+        assert_eq!(pending[0].added_at, added_at);
+        assert_eq!(pending[0].age, chrono::Duration::seconds(90));
+    }
+
+    #[test]
+    fn test_created_blocks_use_the_clock() {
+        let (mut blockchain, clock) = mock_clock_chain(BlockchainConfig::default());
+        let block = blockchain.create_block(create_test_address()).unwrap();
+        assert_eq!(block.header.timestamp, clock.now());
+
+        // Blocks stamped in the clock's future are rejected until it catches up
+        clock.advance(chrono::Duration::hours(3));
+        let mut block = blockchain.create_block(create_test_address()).unwrap();
+        clock.set(block.header.timestamp - chrono::Duration::hours(3));
+        block.mine().unwrap();
+        assert!(blockchain.add_block(block.clone()).is_err());
+        clock.advance(chrono::Duration::hours(1));
+        blockchain.add_block(block).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+//! Compact blocks, after BIP152.
+//!
+//! A node announcing a new block to a peer that shares its mempool need not send
+//! the transactions again. A [`CompactBlock`] carries the header, the metadata and
+//! a 6-byte short ID per transaction; only the coinbase, which no pool holds, goes
+//! in full. The receiver matches the short IDs against its own pool with
+//! [`reconstruct_block`], and asks for the full block when some are missing.
+//!
+//! Short IDs are keyed by the header and a nonce the sender picks for each
+//! announcement, so nobody can craft transactions whose IDs collide in every block.
+//! A collision inside one pool only makes the transaction count as missing.
+
+use crate::core::filter::siphash24;
+use crate::core::{Block, BlockHeader, BlockMetadata, Transaction};
+use crate::crypto::{hash_multiple, Hash256};
+use crate::error::{LedgerError, Result};
+use std::collections::HashMap;
+
+/// A block as a header and short transaction IDs, for `POST /api/blocks/compact`
+pub type CompactBlock = ledgerdb_types::CompactBlock<BlockMetadata, Transaction>;
+
+/// A transaction sent in full in a [`CompactBlock`]
+pub type PrefilledTransaction = ledgerdb_types::PrefilledTransaction<Transaction>;
+
+/// Bytes of a short transaction ID
+pub const SHORT_ID_LENGTH: usize = 6;
+
+/// A [`CompactBlock`] matched against a transaction pool
+#[derive(Debug)]
+pub enum Reconstruction {
+    /// Every transaction was found; the block still has to be validated
+    Complete(Box<Block>),
+    /// Positions of the transactions the pool lacks
+    Missing(Vec<u32>),
+}
+
+/// `block` as a compact block whose short IDs are salted with `nonce`; the
+/// coinbase is prefilled
+pub fn compact_block(block: &Block, nonce: u64) -> Result<CompactBlock> {
+    let header = block.header.to_bytes()?;
+    let keys = short_id_keys(&header, nonce);
+    let mut short_ids = Vec::new();
+    let mut prefilled = Vec::new();
+    for (index, transaction) in block.transactions().iter().enumerate() {
+        if transaction.is_coinbase() {
+            prefilled.push(PrefilledTransaction { index: index as u32, transaction: transaction.clone() });
+        } else {
+            short_ids.push(hex::encode(short_id(keys, &transaction.hash())));
+        }
+    }
+    Ok(CompactBlock {
+        header: hex::encode(header),
+        index: block.index,
+        metadata: block.metadata.clone(),
+        nonce,
+        short_ids,
+        prefilled,
+    })
+}
+
+/// The header `compact` announces
+pub fn compact_block_header(compact: &CompactBlock) -> Result<BlockHeader> {
+    let bytes = hex::decode(&compact.header).map_err(|e| LedgerError::Parse(format!("compact block header: {}", e)))?;
+    BlockHeader::from_bytes(&bytes)
+}
+
+/// Rebuild the block `compact` announces from its prefilled transactions and
+/// `pool`. Fails if the announcement is malformed, e.g. has more or fewer
+/// transactions than its header counts.
+pub fn reconstruct_block<'a>(compact: &CompactBlock, pool: impl IntoIterator<Item = &'a Transaction>) -> Result<Reconstruction> {
+    let header = compact_block_header(compact)?;
+    let count = header.transaction_count as usize;
+    if compact.short_ids.len() + compact.prefilled.len() != count {
+        return Err(LedgerError::InvalidInput(format!(
+            "compact block has {} short IDs and {} prefilled transactions for {} transactions",
+            compact.short_ids.len(),
+            compact.prefilled.len(),
+            count
+        )));
+    }
+
+    let mut slots: Vec<Option<Transaction>> = vec![None; count];
+    for prefilled in &compact.prefilled {
+        match slots.get_mut(prefilled.index as usize) {
+            Some(slot @ None) => *slot = Some(prefilled.transaction.clone()),
+            _ => {
+                return Err(LedgerError::InvalidInput(format!(
+                    "compact block prefills position {} twice or out of range",
+                    prefilled.index
+                )))
+            }
+        }
+    }
+
+    let keys = short_id_keys(&hex::decode(&compact.header).expect("header decoded above"), compact.nonce);
+    // A short ID that two pool transactions share matches neither
+    let mut by_short_id: HashMap<[u8; SHORT_ID_LENGTH], Option<&Transaction>> = HashMap::new();
+    for transaction in pool {
+        by_short_id
+            .entry(short_id(keys, &transaction.hash()))
+            .and_modify(|found| *found = None)
+            .or_insert(Some(transaction));
+    }
+
+    let mut missing = Vec::new();
+    let mut short_ids = compact.short_ids.iter();
+    for (index, slot) in slots.iter_mut().enumerate() {
+        if slot.is_some() {
+            continue;
+        }
+        let id = short_ids.next().expect("one short ID per position not prefilled");
+        let id: [u8; SHORT_ID_LENGTH] = hex::decode(id)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| LedgerError::Parse(format!("short ID '{}' is not {} hex bytes", id, SHORT_ID_LENGTH)))?;
+        match by_short_id.get(&id) {
+            Some(Some(transaction)) => *slot = Some((*transaction).clone()),
+            _ => missing.push(index as u32),
+        }
+    }
+    if !missing.is_empty() {
+        return Ok(Reconstruction::Missing(missing));
+    }
+
+    let transactions = slots.into_iter().map(|slot| slot.expect("every position filled")).collect();
+    Ok(Reconstruction::Complete(Box::new(Block::from_parts(
+        compact.index,
+        header,
+        transactions,
+        compact.metadata.clone(),
+    ))))
+}
+
+/// SipHash keys for the short IDs of the block with serialized `header`
+fn short_id_keys(header: &[u8], nonce: u64) -> (u64, u64) {
+    let key = hash_multiple(&[header, &nonce.to_le_bytes()]);
+    let key = key.as_slice();
+    let k0 = u64::from_le_bytes(key[0..8].try_into().expect("hash has 32 bytes"));
+    let k1 = u64::from_le_bytes(key[8..16].try_into().expect("hash has 32 bytes"));
+    (k0, k1)
+}
+
+fn short_id((k0, k1): (u64, u64), tx_hash: &Hash256) -> [u8; SHORT_ID_LENGTH] {
+    let id = siphash24(k0, k1, tx_hash.as_slice()).to_le_bytes();
+    id[..SHORT_ID_LENGTH].try_into().expect("u64 has 8 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{TransactionInput, TransactionOutput};
+    use crate::crypto::{Address, PublicKey, SignatureAlgorithm};
+
+    fn address(seed: u8) -> Address {
+        Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![seed; 33]))
+    }
+
+    /// A block of a coinbase and `count` payments, and the payments
+    fn block_with_payments(count: u8) -> (Block, Vec<Transaction>) {
+        let payments: Vec<Transaction> = (1..=count)
+            .map(|seed| {
+                let input = TransactionInput::new(Hash256::new([seed; 32]), 0, None, None);
+                Transaction::new(vec![input], vec![TransactionOutput::new(100, address(seed))])
+            })
+            .collect();
+        let transactions = std::iter::once(Transaction::coinbase(address(0), 50, 0)).chain(payments.clone()).collect();
+        (Block::new(1, Hash256::zero(), transactions, 1), payments)
+    }
+
+    #[test]
+    fn test_reconstruct_from_pool() {
+        let (block, payments) = block_with_payments(5);
+        let compact = compact_block(&block, 7).unwrap();
+        assert_eq!(compact.prefilled.len(), 1);
+        assert_eq!(compact.short_ids.len(), 5);
+
+        // The pool's order and unrelated transactions do not matter
+        let stranger = Transaction::coinbase(address(99), 1, 99);
+        let pool: Vec<&Transaction> = payments.iter().rev().chain([&stranger]).collect();
+        let Reconstruction::Complete(rebuilt) = reconstruct_block(&compact, pool).unwrap() else {
+            panic!("every transaction is in the pool");
+        };
+        assert_eq!(rebuilt.hash(), block.hash());
+        assert_eq!(rebuilt.transactions(), block.transactions());
+        assert!(rebuilt.verify_merkle_root());
+    }
+
+    #[test]
+    fn test_missing_transactions_are_reported() {
+        let (block, payments) = block_with_payments(4);
+        let compact = compact_block(&block, 7).unwrap();
+        let pool = [&payments[0], &payments[3]];
+        match reconstruct_block(&compact, pool).unwrap() {
+            Reconstruction::Missing(missing) => assert_eq!(missing, vec![2, 3]),
+            Reconstruction::Complete(_) => panic!("two payments are not in the pool"),
+        }
+    }
+
+    #[test]
+    fn test_short_ids_depend_on_nonce() {
+        let (block, _) = block_with_payments(3);
+        assert_ne!(compact_block(&block, 1).unwrap().short_ids, compact_block(&block, 2).unwrap().short_ids);
+    }
+
+    #[test]
+    fn test_malformed_announcement_is_rejected() {
+        let (block, payments) = block_with_payments(2);
+        let mut compact = compact_block(&block, 7).unwrap();
+        compact.short_ids.pop();
+        assert!(reconstruct_block(&compact, &payments).is_err());
+
+        let mut compact = compact_block(&block, 7).unwrap();
+        compact.prefilled[0].index = 9;
+        assert!(reconstruct_block(&compact, &payments).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Compact block filters.
+//!
+//! A [`BlockFilter`] is a BIP158-style Golomb-coded set of the addresses a block
+//! touches: the recipients of its outputs and of the outputs its inputs spend. A
+//! light wallet downloads the filter for each block, tests its own addresses
+//! against it, and fetches only the blocks that match. False positives happen at a
+//! rate of about 1 in 784931 per address; false negatives never do.
+
+use crate::core::Block;
+use crate::crypto::{Address, Hash256};
+use crate::error::{LedgerError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Golomb-Rice parameter: bits in the remainder of each coded value
+pub const FILTER_P: u8 = 19;
+/// Inverse false-positive rate
+pub const FILTER_M: u64 = 784_931;
+
+/// Golomb-coded set of the addresses a block pays or spends from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockFilter {
+    /// Block the filter was built for; its hash keys the item hashing
+    pub block_hash: Hash256,
+    /// Number of distinct addresses in the set
+    pub n: u32,
+    /// Golomb-Rice coded differences of the sorted item hashes
+    pub data: Vec<u8>,
+}
+
+impl BlockFilter {
+    /// Build the filter for `block`; `spent` holds the recipients of the outputs its
+    /// inputs spend
+    pub fn build<'a>(block: &'a Block, spent: impl IntoIterator<Item = &'a Address>) -> Self {
+        let block_hash = block.hash();
+        let outputs = block.transactions().iter().flat_map(|tx| &tx.outputs).map(|o| &o.recipient);
+        let mut items: Vec<&[u8]> = outputs.chain(spent).map(|a| a.as_hash().as_slice()).collect();
+        items.sort_unstable();
+        items.dedup();
+
+        let n = items.len() as u64;
+        let mut values = hashed_set(&block_hash, n, items);
+        values.sort_unstable();
+
+        let mut writer = BitWriter::default();
+        let mut last = 0;
+        for value in values {
+            golomb_encode(&mut writer, value - last);
+            last = value;
+        }
+        Self { block_hash, n: n as u32, data: writer.finish() }
+    }
+
+    /// Whether `address` may be in the set
+    pub fn matches(&self, address: &Address) -> Result<bool> {
+        self.matches_any([address])
+    }
+
+    /// Whether any of `addresses` may be in the set
+    pub fn matches_any<'a>(&self, addresses: impl IntoIterator<Item = &'a Address>) -> Result<bool> {
+        if self.n == 0 {
+            return Ok(false);
+        }
+        let mut targets = hashed_set(
+            &self.block_hash,
+            self.n as u64,
+            addresses.into_iter().map(|a| a.as_hash().as_slice()),
+        );
+        targets.sort_unstable();
+
+        let mut reader = BitReader::new(&self.data);
+        let mut targets = targets.into_iter().peekable();
+        let mut value = 0;
+        for _ in 0..self.n {
+            value += golomb_decode(&mut reader)?;
+            while let Some(&target) = targets.peek() {
+                if target == value {
+                    return Ok(true);
+                }
+                if target > value {
+                    break;
+                }
+                targets.next();
+            }
+            if targets.peek().is_none() {
+                break;
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Map each item into `[0, n * M)` with SipHash keyed by the block hash
+fn hashed_set<'a>(block_hash: &Hash256, n: u64, items: impl IntoIterator<Item = &'a [u8]>) -> Vec<u64> {
+    let key = block_hash.as_slice();
+    let k0 = u64::from_le_bytes(key[0..8].try_into().expect("hash has 32 bytes"));
+    let k1 = u64::from_le_bytes(key[8..16].try_into().expect("hash has 32 bytes"));
+    let range = n * FILTER_M;
+    items
+        .into_iter()
+        .map(|item| ((siphash24(k0, k1, item) as u128 * range as u128) >> 64) as u64)
+        .collect()
+}
+
+fn golomb_encode(writer: &mut BitWriter, value: u64) {
+    for _ in 0..(value >> FILTER_P) {
+        writer.push(true);
+    }
+    writer.push(false);
+    for bit in (0..FILTER_P).rev() {
+        writer.push(value >> bit & 1 == 1);
+    }
+}
+
+fn golomb_decode(reader: &mut BitReader<'_>) -> Result<u64> {
+    let mut quotient = 0u64;
+    while reader.next()? {
+        quotient += 1;
+    }
+    let mut remainder = 0u64;
+    for _ in 0..FILTER_P {
+        remainder = remainder << 1 | reader.next()? as u64;
+    }
+    Ok(quotient << FILTER_P | remainder)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    used: u8,
+}
+
+impl BitWriter {
+    fn push(&mut self, bit: bool) {
+        if self.used == 0 {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().expect("byte pushed above") |= 0x80 >> self.used;
+        }
+        self.used = (self.used + 1) % 8;
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn next(&mut self) -> Result<bool> {
+        let byte = self
+            .bytes
+            .get(self.position / 8)
+            .ok_or_else(|| LedgerError::InvalidInput("block filter is truncated".to_string()))?;
+        let bit = byte & (0x80 >> (self.position % 8)) != 0;
+        self.position += 1;
+        Ok(bit)
+    }
+}
+
+/// SipHash-2-4, as BIP158 uses to hash filter items and BIP152 for short
+/// transaction IDs
+pub(crate) fn siphash24(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(u64::from_le_bytes(chunk.try_into().expect("chunk has 8 bytes")));
+    }
+    let mut last = (data.len() as u64 & 0xff) << 56;
+    for (i, byte) in chunks.remainder().iter().enumerate() {
+        last |= (*byte as u64) << (8 * i);
+    }
+    compress(last);
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Transaction;
+    use crate::crypto::{PublicKey, SignatureAlgorithm};
+
+    fn address(seed: u8) -> Address {
+        Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![seed; 33]))
+    }
+
+    #[test]
+    fn test_siphash_reference_vectors() {
+        // From the SipHash paper: key 00..0f, messages 00..(len-1)
+        let (k0, k1) = (0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+        assert_eq!(siphash24(k0, k1, &[]), 0x726f_db47_dd0e_0e31);
+        let message: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash24(k0, k1, &message), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn test_filter_matches_block_addresses() {
+        let transactions = (1..=20)
+            .map(|seed| Transaction::coinbase(address(seed), 100, seed as u64))
+            .collect();
+        let block = Block::new(1, Hash256::zero(), transactions, 1);
+        let spent = [address(200)];
+        let filter = BlockFilter::build(&block, &spent);
+        assert_eq!(filter.n, 21);
+
+        for seed in (1..=20).chain([200]) {
+            assert!(filter.matches(&address(seed)).unwrap(), "address {} is in the block", seed);
+        }
+        let strangers: Vec<Address> = (100..150).map(address).collect();
+        assert!(!filter.matches_any(&strangers).unwrap());
+        assert!(filter.matches_any(strangers.iter().chain([&address(7)])).unwrap());
+    }
+
+    #[test]
+    fn test_empty_and_truncated_filters() {
+        let block = Block::new(1, Hash256::zero(), vec![Transaction::coinbase(address(1), 1, 1)], 1);
+        let mut filter = BlockFilter::build(&block, &[]);
+        assert!(filter.matches(&address(1)).unwrap());
+
+        filter.n = 0;
+        assert!(!filter.matches(&address(1)).unwrap());
+
+        filter.n = 50;
+        filter.data.clear();
+        assert!(filter.matches(&address(2)).is_err());
+    }
+}
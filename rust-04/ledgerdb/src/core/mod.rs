@@ -5,9 +5,21 @@
 
 pub mod block;
 pub mod blockchain;
+pub mod compact;
+pub mod filter;
+pub mod psbt;
+pub mod replay;
+pub mod reward;
+pub mod snapshot;
 pub mod transaction;
 
 // Re-export commonly used types
 pub use block::*;
 pub use blockchain::*;
+pub use compact::*;
+pub use filter::*;
+pub use psbt::*;
+pub use replay::*;
+pub use reward::*;
+pub use snapshot::*;
 pub use transaction::*;
\ No newline at end of file
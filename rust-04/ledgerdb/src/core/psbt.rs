@@ -0,0 +1,243 @@
+//! Partially signed transactions.
+//!
+//! A [`PartiallySignedTransaction`] carries an unsigned transaction from the wallet
+//! that builds it to the keys that sign it, which may live on other machines or
+//! never touch the network at all. Each input names the public key expected to sign
+//! it and keeps a copy of the output it spends, so a signer can check the owner and
+//! the amounts without a node. Signers fill in the signatures they can, copies
+//! signed by different keys are combined, and once every input is signed the
+//! container is finalized into an ordinary [`Transaction`].
+//!
+//! The container travels as hex-encoded bincode, see [`PartiallySignedTransaction::to_hex`].
+
+use crate::core::{Transaction, TransactionOutput};
+use crate::crypto::{verify_signature, Address, Hash256, PrivateKey, Signature};
+use crate::error::{Result, ValidationError};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Format version written by [`PartiallySignedTransaction::to_hex`]
+pub const PSBT_VERSION: u32 = 1;
+
+/// A transaction on its way to being signed by one or more keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PartiallySignedTransaction {
+    pub version: u32,
+    /// The transaction without signatures; each input carries the public key that
+    /// must sign it
+    pub transaction: Transaction,
+    /// One entry per transaction input
+    pub inputs: Vec<PsbtInput>,
+}
+
+/// What a [`PartiallySignedTransaction`] holds for one input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PsbtInput {
+    /// Output the input spends
+    pub spent_output: TransactionOutput,
+    /// Signature over [`PartiallySignedTransaction::signing_hash`], once collected
+    pub signature: Option<Signature>,
+}
+
+impl PartiallySignedTransaction {
+    /// Wrap `transaction`, whose inputs spend `spent_outputs` in order.
+    ///
+    /// Every input must name the public key of the address it spends from; any
+    /// signatures already on the transaction are dropped.
+    pub fn new(mut transaction: Transaction, spent_outputs: Vec<TransactionOutput>) -> Result<Self> {
+        if transaction.is_coinbase() {
+            return Err(invalid("coinbase transactions are not signed"));
+        }
+        if transaction.inputs.len() != spent_outputs.len() {
+            return Err(invalid(format!(
+                "{} inputs but {} spent outputs", transaction.inputs.len(), spent_outputs.len()
+            )));
+        }
+        for (index, (input, spent)) in transaction.inputs.iter_mut().zip(&spent_outputs).enumerate() {
+            let public_key = input.public_key.as_ref()
+                .ok_or_else(|| invalid(format!("input {} names no public key", index)))?;
+            if Address::from_public_key(public_key) != spent.recipient {
+                return Err(invalid(format!(
+                    "input {} spends from {}, which its public key does not own", index, spent.recipient
+                )));
+            }
+            input.signature = None;
+        }
+
+        let inputs = spent_outputs
+            .into_iter()
+            .map(|spent_output| PsbtInput { spent_output, signature: None })
+            .collect();
+        Ok(Self { version: PSBT_VERSION, transaction, inputs })
+    }
+
+    /// Message every input signs: the transaction hash, which covers the public
+    /// keys but not the signatures
+    pub fn signing_hash(&self) -> Hash256 {
+        self.transaction.hash()
+    }
+
+    /// Sign every unsigned input that `key` owns; returns how many it signed
+    pub fn sign(&mut self, key: &PrivateKey) -> Result<usize> {
+        let public_key = key.public_key()?;
+        let hash = self.signing_hash();
+        let mut signed = 0;
+        for (input, partial) in self.transaction.inputs.iter().zip(&mut self.inputs) {
+            if partial.signature.is_none() && input.public_key.as_ref() == Some(&public_key) {
+                partial.signature = Some(key.sign(hash.as_slice())?);
+                signed += 1;
+            }
+        }
+        Ok(signed)
+    }
+
+    /// Attach a signature made elsewhere to input `index`
+    pub fn add_signature(&mut self, index: usize, signature: Signature) -> Result<()> {
+        let public_key = self.transaction.inputs.get(index)
+            .and_then(|input| input.public_key.as_ref())
+            .ok_or_else(|| invalid(format!("no input {}", index)))?;
+        if !verify_signature(self.signing_hash().as_slice(), &signature, public_key)? {
+            return Err(ValidationError::InvalidSignature(format!("input {}", index)).into());
+        }
+        self.inputs[index].signature = Some(signature);
+        Ok(())
+    }
+
+    /// Take the signatures `other` has and this one lacks; both must carry the
+    /// same transaction
+    pub fn combine(&mut self, other: &PartiallySignedTransaction) -> Result<()> {
+        if other.signing_hash() != self.signing_hash() || other.inputs.len() != self.inputs.len() {
+            return Err(invalid("cannot combine different transactions"));
+        }
+        for (mine, theirs) in self.inputs.iter_mut().zip(&other.inputs) {
+            if mine.signature.is_none() {
+                mine.signature = theirs.signature.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexes of the inputs still waiting for a signature
+    pub fn missing_signatures(&self) -> Vec<usize> {
+        self.inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| input.signature.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Whether every input is signed
+    pub fn is_complete(&self) -> bool {
+        self.inputs.iter().all(|input| input.signature.is_some())
+    }
+
+    /// Value of the spent outputs minus the transaction's own outputs
+    pub fn fee(&self) -> u64 {
+        let spent: u64 = self.inputs.iter().map(|input| input.spent_output.amount).sum();
+        spent.saturating_sub(self.transaction.total_output_amount())
+    }
+
+    /// Move the collected signatures into the transaction
+    pub fn finalize(self) -> Result<Transaction> {
+        let missing = self.missing_signatures();
+        if !missing.is_empty() {
+            return Err(invalid(format!("inputs {:?} are unsigned", missing)));
+        }
+        let mut transaction = self.transaction;
+        for (input, partial) in transaction.inputs.iter_mut().zip(self.inputs) {
+            input.signature = partial.signature;
+        }
+        Ok(transaction)
+    }
+
+    /// Encode for export
+    pub fn to_hex(&self) -> Result<String> {
+        let bytes = bincode::serialize(self).map_err(|e| invalid(e.to_string()))?;
+        Ok(hex::encode(bytes))
+    }
+
+    /// Decode what [`Self::to_hex`] produced
+    pub fn from_hex(encoded: &str) -> Result<Self> {
+        let bytes = hex::decode(encoded.trim()).map_err(|e| invalid(format!("bad hex: {}", e)))?;
+        let psbt: Self = bincode::deserialize(&bytes).map_err(|e| invalid(e.to_string()))?;
+        if psbt.version != PSBT_VERSION {
+            return Err(invalid(format!("unsupported version {}", psbt.version)));
+        }
+        if psbt.inputs.len() != psbt.transaction.inputs.len() {
+            return Err(invalid("input count does not match the transaction"));
+        }
+        Ok(psbt)
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> crate::error::LedgerError {
+    ValidationError::InvalidPsbt(msg.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TransactionInput;
+    use crate::crypto::SignatureAlgorithm;
+
+    fn key(seed: u8) -> PrivateKey {
+        PrivateKey::new(vec![seed; 32], SignatureAlgorithm::EcdsaSecp256k1)
+    }
+
+    /// A transaction spending one 1000-unit output of each of `owners`
+    fn spend_from(owners: &[&PrivateKey]) -> (Transaction, Vec<TransactionOutput>) {
+        let mut inputs = Vec::new();
+        let mut spent = Vec::new();
+        for (index, owner) in owners.iter().enumerate() {
+            let public_key = owner.public_key().unwrap();
+            inputs.push(TransactionInput::new(Hash256::new([index as u8 + 1; 32]), 0, None, Some(public_key.clone())));
+            spent.push(TransactionOutput::new(1000, public_key.to_address()));
+        }
+        let payee = key(99).public_key().unwrap().to_address();
+        let outputs = vec![TransactionOutput::new(1000 * owners.len() as u64 - 300, payee)];
+        (Transaction::new(inputs, outputs), spent)
+    }
+
+    #[test]
+    fn test_sign_combine_and_finalize() {
+        let (alice, bob) = (key(1), key(2));
+        let (transaction, spent) = spend_from(&[&alice, &bob]);
+        let created = PartiallySignedTransaction::new(transaction, spent).unwrap();
+        assert_eq!(created.fee(), 300);
+        assert_eq!(created.missing_signatures(), vec![0, 1]);
+
+        // Each signer works on its own exported copy
+        let mut for_alice = PartiallySignedTransaction::from_hex(&created.to_hex().unwrap()).unwrap();
+        let mut for_bob = created.clone();
+        assert_eq!(for_alice.sign(&alice).unwrap(), 1);
+        assert_eq!(for_alice.sign(&alice).unwrap(), 0);
+        assert_eq!(for_bob.sign(&bob).unwrap(), 1);
+        assert!(for_alice.clone().finalize().is_err());
+
+        for_alice.combine(&for_bob).unwrap();
+        assert!(for_alice.is_complete());
+        let hash = for_alice.signing_hash();
+        let transaction = for_alice.finalize().unwrap();
+        assert!(transaction.inputs.iter().all(|input| input.signature.is_some()));
+        assert_eq!(transaction.hash(), hash);
+        assert!(transaction.verify_signatures().unwrap());
+    }
+
+    #[test]
+    fn test_rejects_mismatched_inputs() {
+        let (alice, mallory) = (key(1), key(3));
+        let (transaction, _) = spend_from(&[&alice]);
+        let (_, not_alices) = spend_from(&[&mallory]);
+        assert!(PartiallySignedTransaction::new(transaction.clone(), not_alices).is_err());
+        assert!(PartiallySignedTransaction::new(transaction, vec![]).is_err());
+
+        let (first, spent) = spend_from(&[&alice]);
+        let (second, spent_again) = spend_from(&[&alice]);
+        let mut first = PartiallySignedTransaction::new(first, spent).unwrap();
+        let second = PartiallySignedTransaction::new(second, spent_again).unwrap();
+        assert!(first.combine(&second).is_err());
+        assert!(first.add_signature(5, alice.sign(b"x").unwrap()).is_err());
+        assert!(PartiallySignedTransaction::from_hex("zz").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! Recorded transaction and block workloads.
+//!
+//! A [`ReplayRecorder`] attached with [`Blockchain::set_recorder`] appends every
+//! transaction the pool accepts and every block the chain connects to a
+//! JSON-lines file, stamped with the chain clock's time. [`replay_recording`]
+//! feeds such a file to another chain in the same order while its clock follows
+//! the recorded times, so pool expiry, lock times and block timestamps behave as
+//! they did. A fresh chain with the same settings ends in the same state, and a
+//! bug seen under load happens again.
+
+use crate::core::{Block, Blockchain, Transaction};
+use crate::crypto::Hash256;
+use crate::error::{BlockchainError, LedgerError, Result};
+use crate::utils::{MockClock, SystemClock};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// One line of a replay file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ReplayEvent {
+    /// Recording started, or resumed after a restart, on the chain with this
+    /// genesis block when it had `height` blocks
+    Start { at: DateTime<Utc>, genesis: Hash256, height: u64 },
+    /// The pool accepted a transaction
+    Transaction { at: DateTime<Utc>, transaction: Transaction },
+    /// A block was connected to the tip
+    Block { at: DateTime<Utc>, block: Box<Block> },
+}
+
+impl ReplayEvent {
+    /// Chain clock time the event was recorded at
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            Self::Start { at, .. } | Self::Transaction { at, .. } | Self::Block { at, .. } => *at,
+        }
+    }
+}
+
+/// Appends [`ReplayEvent`]s to a file, one JSON object per line
+#[derive(Debug)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    out: Mutex<BufWriter<File>>,
+}
+
+impl ReplayRecorder {
+    /// Record to `path`, appending if it exists
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| LedgerError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+        Ok(Self { path, out: Mutex::new(BufWriter::new(file)) })
+    }
+
+    /// File being recorded to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `event` and flush it, so a crash loses nothing recorded
+    pub fn record(&self, event: &ReplayEvent) -> Result<()> {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_writer(&mut *out, event)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode replay event: {}", e)))?;
+        writeln!(out)
+            .and_then(|()| out.flush())
+            .map_err(|e| LedgerError::Io(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
+
+/// Outcome of [`replay_recording`]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ReplayReport {
+    /// Transactions the pool accepted again
+    pub transactions: u64,
+    /// Blocks connected again
+    pub blocks: u64,
+    /// Height of the tip afterwards
+    pub height: u64,
+    /// The first recorded event the chain refused, with its line number; the
+    /// replay stops there
+    pub divergence: Option<String>,
+}
+
+/// Feed the events recorded in `input` to `chain`, setting its clock to each
+/// event's time and back to the system clock afterwards.
+///
+/// A recording that started on another genesis block, or at another height
+/// than the chain has, is an error. A transaction or block the chain refuses
+/// ends the replay and is reported as its `divergence`.
+pub fn replay_recording(chain: &mut Blockchain, input: impl BufRead) -> Result<ReplayReport> {
+    let clock = MockClock::new(Utc::now());
+    chain.set_clock(Arc::new(clock.clone()));
+    let report = replay_events(chain, &clock, input);
+    chain.set_clock(Arc::new(SystemClock));
+    report
+}
+
+fn replay_events(chain: &mut Blockchain, clock: &MockClock, input: impl BufRead) -> Result<ReplayReport> {
+    let genesis = chain.get_block_by_index(0).map(|b| b.hash());
+    let mut report = ReplayReport::default();
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| LedgerError::Io(format!("Failed to read replay file: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: ReplayEvent = serde_json::from_str(&line)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid replay event on line {}: {}", n + 1, e)))?;
+
+        clock.set(event.at());
+        let outcome = match event {
+            ReplayEvent::Start { genesis: recorded, height, .. } => {
+                if genesis.as_ref() != Some(&recorded) {
+                    return Err(BlockchainError::InvalidChain(format!(
+                        "recording on line {} was made on a chain with genesis block {}", n + 1, recorded
+                    )).into());
+                }
+                if height != chain.height() {
+                    return Err(BlockchainError::InvalidChain(format!(
+                        "recording on line {} starts with {} blocks but the chain has {}", n + 1, height, chain.height()
+                    )).into());
+                }
+                Ok(())
+            }
+            ReplayEvent::Transaction { transaction, .. } => {
+                chain.add_transaction_to_pool(transaction).map(|()| report.transactions += 1)
+            }
+            ReplayEvent::Block { block, .. } => chain.add_block(*block).map(|()| report.blocks += 1),
+        };
+        if let Err(e) = outcome {
+            report.divergence = Some(format!("line {}: {}", n + 1, e));
+            break;
+        }
+    }
+
+    report.height = chain.get_latest_block().map_or(0, |b| b.index);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::BlockchainConfig;
+    use crate::crypto::{PrivateKey, SignatureAlgorithm};
+    use crate::simulation::{self, SimulationParams};
+    use std::io::BufReader;
+
+    fn chain(seed: u8) -> Blockchain {
+        let genesis = PrivateKey::new(vec![seed; 32], SignatureAlgorithm::EcdsaSecp256k1);
+        Blockchain::new(BlockchainConfig::default(), genesis.public_key().unwrap().to_address()).unwrap()
+    }
+
+    /// Record a short simulation, returning the recorded chain
+    fn record(path: &Path) -> Blockchain {
+        let mut recorded = chain(1);
+        recorded.set_recorder(ReplayRecorder::create(path).unwrap()).unwrap();
+        let params = SimulationParams {
+            wallets: 4,
+            tx_rate: 1.0,
+            duration: 60,
+            block_interval: Some(20),
+            seed: Some(3),
+            realtime: false,
+            ..Default::default()
+        };
+        simulation::run(&mut recorded, params).unwrap();
+        recorded
+    }
+
+    fn pending(chain: &Blockchain) -> Vec<Hash256> {
+        let mut hashes: Vec<Hash256> = chain.get_pending_transactions().iter().map(|tx| tx.hash()).collect();
+        hashes.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        hashes
+    }
+
+    #[test]
+    fn test_replay_reproduces_recorded_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workload.jsonl");
+        let recorded = record(&path);
+
+        let mut replayed = chain(1);
+        let report = replay_recording(&mut replayed, BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert_eq!(report.divergence, None);
+        assert_eq!(report.height, recorded.get_latest_block().unwrap().index);
+        assert_eq!(report.blocks, recorded.height() - 1);
+        assert!(report.transactions > 0);
+        assert_eq!(replayed.get_latest_block().unwrap().hash(), recorded.get_latest_block().unwrap().hash());
+        assert_eq!(pending(&replayed), pending(&recorded));
+    }
+
+    #[test]
+    fn test_replay_reports_divergence_and_foreign_recordings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workload.jsonl");
+        record(&path);
+        let lines: Vec<String> = std::fs::read_to_string(&path).unwrap().lines().map(String::from).collect();
+
+        // Dropping a block leaves the next one without its parent
+        let first_block = lines.iter().position(|line| line.contains(r#""event":"block""#)).unwrap();
+        let mut edited = lines.clone();
+        edited.remove(first_block);
+        let report = replay_recording(&mut chain(1), edited.join("\n").as_bytes()).unwrap();
+        assert!(report.divergence.unwrap().starts_with(&format!("line {}:", first_block + 1)));
+        assert_eq!(report.blocks, 0);
+
+        assert!(replay_recording(&mut chain(2), lines.join("\n").as_bytes()).is_err());
+    }
+}
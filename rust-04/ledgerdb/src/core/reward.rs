@@ -0,0 +1,184 @@
+//! Block reward schedules.
+//!
+//! The coinbase of every block may claim the subsidy given by the chain's
+//! [`RewardSchedule`] at that height, plus fees.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the block subsidy changes with height
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RewardSchedule {
+    /// Every block pays `reward`
+    Fixed {
+        /// Subsidy in smallest units
+        reward: u64,
+    },
+    /// `initial` halves every `interval` blocks, never dropping below one unit
+    Halving {
+        /// Subsidy of the genesis era in smallest units
+        initial: u64,
+        /// Blocks between halvings
+        interval: u64,
+    },
+    /// `initial` drops by `decrement` every `interval` blocks until it reaches `floor`
+    LinearDecay {
+        /// Subsidy of the genesis era in smallest units
+        initial: u64,
+        /// Amount removed from the subsidy at each step
+        decrement: u64,
+        /// Blocks between steps
+        interval: u64,
+        /// Lowest subsidy the schedule decays to
+        floor: u64,
+    },
+}
+
+impl RewardSchedule {
+    /// Subsidy of the block at `height`
+    pub fn reward_at(&self, height: u64) -> u64 {
+        match *self {
+            Self::Fixed { reward } => reward,
+            Self::Halving { initial, interval } => {
+                let halvings = height / interval.max(1);
+                initial.checked_shr(halvings.min(u32::MAX as u64) as u32).unwrap_or(0).max(1)
+            }
+            Self::LinearDecay { initial, decrement, interval, floor } => {
+                let steps = height / interval.max(1);
+                initial.saturating_sub(decrement.saturating_mul(steps)).max(floor)
+            }
+        }
+    }
+
+    /// First height above `height` whose subsidy differs from the one at `height`,
+    /// or `None` once the schedule has stopped changing
+    pub fn next_change(&self, height: u64) -> Option<u64> {
+        let interval = match *self {
+            Self::Fixed { .. } => return None,
+            Self::Halving { interval, .. } | Self::LinearDecay { interval, .. } => interval.max(1),
+        };
+        let next = (height / interval).checked_add(1)?.checked_mul(interval)?;
+        (self.reward_at(next) != self.reward_at(height)).then_some(next)
+    }
+
+    /// Check the schedule's parameters
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match *self {
+            Self::Fixed { .. } => Ok(()),
+            Self::Halving { interval: 0, .. } | Self::LinearDecay { interval: 0, .. } => {
+                Err("reward interval must be at least 1 block".to_string())
+            }
+            Self::LinearDecay { initial, floor, .. } if floor > initial => {
+                Err("reward floor is above the initial reward".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for RewardSchedule {
+    fn default() -> Self {
+        Self::Halving { initial: 50_000_000, interval: 210_000 }
+    }
+}
+
+impl fmt::Display for RewardSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed { reward } => write!(f, "fixed:{}", reward),
+            Self::Halving { initial, interval } => write!(f, "halving:{}:{}", initial, interval),
+            Self::LinearDecay { initial, decrement, interval, floor } => {
+                write!(f, "linear:{}:{}:{}:{}", initial, decrement, interval, floor)
+            }
+        }
+    }
+}
+
+impl From<ledgerdb_types::RewardSchedule> for RewardSchedule {
+    fn from(schedule: ledgerdb_types::RewardSchedule) -> Self {
+        use ledgerdb_types::RewardSchedule as Wire;
+        match schedule {
+            Wire::Fixed { reward } => Self::Fixed { reward },
+            Wire::Halving { initial, interval } => Self::Halving { initial, interval },
+            Wire::LinearDecay { initial, decrement, interval, floor } => {
+                Self::LinearDecay { initial, decrement, interval, floor }
+            }
+        }
+    }
+}
+
+/// Parses `fixed:<reward>`, `halving:<initial>:<interval>` or
+/// `linear:<initial>:<decrement>:<interval>:<floor>`, the format of `LEDGER_REWARD_SCHEDULE`
+impl FromStr for RewardSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let numbers = parts
+            .map(|p| p.parse::<u64>().map_err(|_| format!("invalid number '{}' in reward schedule", p)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let schedule = match (kind, numbers.as_slice()) {
+            ("fixed", &[reward]) => Self::Fixed { reward },
+            ("halving", &[initial, interval]) => Self::Halving { initial, interval },
+            ("linear", &[initial, decrement, interval, floor]) => {
+                Self::LinearDecay { initial, decrement, interval, floor }
+            }
+            _ => return Err(format!("unknown reward schedule '{}'", s)),
+        };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_schedule() {
+        let schedule = RewardSchedule::Fixed { reward: 7 };
+        assert_eq!(schedule.reward_at(0), 7);
+        assert_eq!(schedule.reward_at(1_000_000), 7);
+        assert_eq!(schedule.next_change(5), None);
+    }
+
+    #[test]
+    fn test_halving_schedule() {
+        let schedule = RewardSchedule::Halving { initial: 100, interval: 10 };
+        assert_eq!(schedule.reward_at(9), 100);
+        assert_eq!(schedule.reward_at(10), 50);
+        assert_eq!(schedule.reward_at(25), 25);
+        assert_eq!(schedule.reward_at(10_000), 1);
+        assert_eq!(schedule.reward_at(u64::MAX), 1);
+        assert_eq!(schedule.next_change(0), Some(10));
+        assert_eq!(schedule.next_change(10), Some(20));
+        assert_eq!(schedule.next_change(10_000), None);
+    }
+
+    #[test]
+    fn test_linear_decay_schedule() {
+        let schedule = RewardSchedule::LinearDecay { initial: 100, decrement: 30, interval: 5, floor: 20 };
+        assert_eq!(schedule.reward_at(4), 100);
+        assert_eq!(schedule.reward_at(5), 70);
+        assert_eq!(schedule.reward_at(10), 40);
+        assert_eq!(schedule.reward_at(15), 20);
+        assert_eq!(schedule.reward_at(500), 20);
+        assert_eq!(schedule.next_change(11), Some(15));
+        assert_eq!(schedule.next_change(15), None);
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        for s in ["fixed:5", "halving:50000000:210000", "linear:100:30:5:20"] {
+            assert_eq!(s.parse::<RewardSchedule>().unwrap().to_string(), s);
+        }
+        assert!("halving:100:0".parse::<RewardSchedule>().is_err());
+        assert!("linear:10:1:1:20".parse::<RewardSchedule>().is_err());
+        assert!("fixed".parse::<RewardSchedule>().is_err());
+        assert!("cliff:1".parse::<RewardSchedule>().is_err());
+    }
+}
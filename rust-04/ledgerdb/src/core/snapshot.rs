@@ -0,0 +1,262 @@
+//! UTXO set snapshots.
+//!
+//! A [`UtxoSnapshot`] holds every unspent output after one block, together with a
+//! commitment hash over them. A new node can load a snapshot instead of replaying
+//! the blocks below it, start serving at once, and confirm the snapshot later by
+//! replaying that history in the background ([`replay_snapshot`]).
+//!
+//! A [`ChunkedSnapshot`] serves a snapshot file in content-addressed chunks, so a
+//! new node can fetch the pieces of a large one from several nodes at once and
+//! resume an interrupted download.
+
+use crate::core::{apply_block_to_utxos, Block, TransactionOutput, UtxoEntry, UtxoId};
+use crate::crypto::hash::HashBuilder;
+use crate::crypto::Hash256;
+use crate::error::{BlockchainError, LedgerError, Result};
+use ledgerdb_types::SnapshotManifest;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Format version written to snapshot files
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Bytes per chunk when serving a snapshot in chunks
+pub const SNAPSHOT_CHUNK_SIZE: usize = 1024 * 1024; // 1MB
+
+/// The UTXO set as of one block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoSnapshot {
+    /// Snapshot format version
+    pub version: u32,
+    /// Height of the last block applied to the set
+    pub height: u64,
+    /// Hash of that block
+    pub block_hash: Hash256,
+    /// Hash over `height`, `block_hash` and every entry, in `utxos` order
+    pub commitment: Hash256,
+    /// Unspent outputs, ordered by transaction hash then output index
+    pub utxos: Vec<UtxoEntry>,
+}
+
+impl UtxoSnapshot {
+    /// Snapshot `utxos` as of block `height`, computing the commitment
+    pub fn new(height: u64, block_hash: Hash256, utxos: impl IntoIterator<Item = UtxoEntry>) -> Result<Self> {
+        let mut utxos: Vec<UtxoEntry> = utxos.into_iter().collect();
+        utxos.sort_by(|a, b| {
+            a.tx_hash
+                .as_slice()
+                .cmp(b.tx_hash.as_slice())
+                .then(a.output_index.cmp(&b.output_index))
+        });
+        let commitment = commitment(height, &block_hash, &utxos)?;
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            height,
+            block_hash,
+            commitment,
+            utxos,
+        })
+    }
+
+    /// Check the version and that the commitment matches the contents
+    pub fn verify(&self) -> Result<()> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(BlockchainError::InvalidChain(format!(
+                "unsupported UTXO snapshot version {}", self.version
+            )).into());
+        }
+        if commitment(self.height, &self.block_hash, &self.utxos)? != self.commitment {
+            return Err(BlockchainError::InvalidChain(
+                "UTXO snapshot does not match its commitment".to_string()
+            ).into());
+        }
+        Ok(())
+    }
+
+    /// The entries keyed by outpoint, as held by the blockchain
+    pub fn utxo_set(&self) -> HashMap<UtxoId, UtxoEntry> {
+        self.utxos.iter().map(|entry| (entry.id(), entry.clone())).collect()
+    }
+
+    /// Total amount held by the snapshot's outputs
+    pub fn total_amount(&self) -> u64 {
+        self.utxos.iter().map(|entry| entry.output.amount).sum()
+    }
+
+    /// Write the snapshot to `path` (bincode)
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| LedgerError::Io(format!("Failed to create {}: {}", path.as_ref().display(), e)))?;
+        bincode::serialize_into(BufWriter::new(file), self)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to write UTXO snapshot: {}", e)))
+    }
+
+    /// Read a snapshot written by [`UtxoSnapshot::write_to`] and verify it
+    pub fn read_from(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| LedgerError::Io(format!("Failed to open {}: {}", path.as_ref().display(), e)))?;
+        let snapshot: Self = bincode::deserialize_from(BufReader::new(file))
+            .map_err(|e| LedgerError::Serialization(format!("Failed to read UTXO snapshot: {}", e)))?;
+        snapshot.verify()?;
+        Ok(snapshot)
+    }
+}
+
+/// A snapshot file together with its [`SnapshotManifest`]
+#[derive(Debug)]
+pub struct ChunkedSnapshot {
+    pub manifest: SnapshotManifest,
+    bytes: Vec<u8>,
+}
+
+impl ChunkedSnapshot {
+    /// Serialize `snapshot` as [`UtxoSnapshot::write_to`] would and cut the file
+    /// every `chunk_size` bytes
+    pub fn new(snapshot: &UtxoSnapshot, chunk_size: usize) -> Result<Self> {
+        let bytes = bincode::serialize(snapshot)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode UTXO snapshot: {}", e)))?;
+        let manifest = SnapshotManifest::new(
+            snapshot.height,
+            ledgerdb_types::Hash256(*snapshot.block_hash.as_bytes()),
+            ledgerdb_types::Hash256(*snapshot.commitment.as_bytes()),
+            &bytes,
+            chunk_size,
+        );
+        Ok(Self { manifest, bytes })
+    }
+
+    /// The chunk named `hash`, if the file has one
+    pub fn chunk(&self, hash: &ledgerdb_types::Hash256) -> Option<&[u8]> {
+        let index = self.manifest.chunks.iter().position(|chunk| chunk == hash)?;
+        self.bytes.chunks(self.manifest.chunk_size as usize).nth(index)
+    }
+}
+
+/// Where a node started from a snapshot is in checking the history below it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum SnapshotValidation {
+    /// The blocks up to `height` have not been replayed yet
+    Pending { height: u64, commitment: Hash256 },
+    /// Replaying the blocks reproduced the snapshot
+    Valid { height: u64 },
+    /// Replaying failed or produced a different set; the replayed set is in use
+    Invalid { height: u64, reason: String },
+}
+
+/// Replay `blocks` (genesis first) into a UTXO set, checking that each block links to
+/// its parent, is internally valid and only spends outputs that exist
+pub fn replay_snapshot(blocks: &[Block]) -> Result<UtxoSnapshot> {
+    let last = blocks
+        .last()
+        .ok_or_else(|| BlockchainError::InvalidChain("no blocks to replay".to_string()))?;
+    let mut utxos = HashMap::new();
+    for (i, block) in blocks.iter().enumerate() {
+        // The genesis block is fixed by the chain configuration
+        if i > 0 {
+            let spent: HashMap<String, TransactionOutput> = block
+                .transactions()
+                .iter()
+                .flat_map(|tx| &tx.inputs)
+                .filter(|input| !input.is_coinbase())
+                .filter_map(|input| {
+                    let id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+                    utxos.get(&id).map(|entry: &UtxoEntry| (id.to_string(), entry.output.clone()))
+                })
+                .collect();
+            block.validate(Some(&blocks[i - 1]), &spent)?;
+        }
+        apply_block_to_utxos(&mut utxos, block)?;
+    }
+    UtxoSnapshot::new(last.index, last.hash(), utxos.into_values())
+}
+
+fn commitment(height: u64, block_hash: &Hash256, utxos: &[UtxoEntry]) -> Result<Hash256> {
+    let mut builder = HashBuilder::new();
+    builder
+        .update_u64(height)
+        .update_hash(block_hash)
+        .update_u64(utxos.len() as u64);
+    for entry in utxos {
+        let bytes = bincode::serialize(entry)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode UTXO: {}", e)))?;
+        builder.update(&bytes);
+    }
+    Ok(builder.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::{Blockchain, BlockchainConfig};
+    use crate::crypto::{Address, PublicKey, SignatureAlgorithm};
+
+    fn test_chain(blocks: usize) -> Blockchain {
+        let address = Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![7; 33]));
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), address.clone()).unwrap();
+        for _ in 0..blocks {
+            let block = blockchain.create_block(address.clone()).unwrap();
+            blockchain.add_block(block).unwrap();
+        }
+        blockchain
+    }
+
+    #[test]
+    fn test_snapshot_commitment() {
+        let blockchain = test_chain(3);
+        let snapshot = blockchain.utxo_snapshot(2).unwrap();
+        assert_eq!(snapshot.height, 2);
+        assert_eq!(snapshot.utxos.len(), 3);
+        assert!(snapshot.verify().is_ok());
+
+        // Same set in a different order commits to the same hash
+        let reversed = UtxoSnapshot::new(2, snapshot.block_hash.clone(), snapshot.utxos.iter().rev().cloned()).unwrap();
+        assert_eq!(reversed.commitment, snapshot.commitment);
+
+        let mut tampered = snapshot.clone();
+        tampered.utxos[0].output.amount += 1;
+        assert!(tampered.verify().is_err());
+        assert_ne!(blockchain.utxo_snapshot(3).unwrap().commitment, snapshot.commitment);
+    }
+
+    #[test]
+    fn test_snapshot_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("utxo.snapshot");
+        let snapshot = test_chain(2).utxo_snapshot(2).unwrap();
+        snapshot.write_to(&path).unwrap();
+        assert_eq!(UtxoSnapshot::read_from(&path).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_chunked_snapshot_reassembles() {
+        let snapshot = test_chain(3).utxo_snapshot(3).unwrap();
+        let chunked = ChunkedSnapshot::new(&snapshot, 64).unwrap();
+        assert!(chunked.manifest.chunks.len() > 1);
+        assert_eq!(chunked.manifest.commitment.0, *snapshot.commitment.as_bytes());
+
+        let mut bytes = Vec::new();
+        for (i, hash) in chunked.manifest.chunks.iter().enumerate() {
+            let chunk = chunked.chunk(hash).unwrap();
+            assert!(chunked.manifest.verify_chunk(i, chunk));
+            bytes.extend_from_slice(chunk);
+        }
+        assert_eq!(bincode::deserialize::<UtxoSnapshot>(&bytes).unwrap(), snapshot);
+        assert!(chunked.chunk(&ledgerdb_types::Hash256::zero()).is_none());
+    }
+
+    #[test]
+    fn test_replay_matches_snapshot() {
+        let blockchain = test_chain(4);
+        let blocks: Vec<Block> = (0..=2).filter_map(|i| blockchain.get_block_by_index(i).cloned()).collect();
+        assert_eq!(replay_snapshot(&blocks).unwrap(), blockchain.utxo_snapshot(2).unwrap());
+
+        let mut broken = blocks.clone();
+        broken.swap(1, 2);
+        assert!(replay_snapshot(&broken).is_err());
+    }
+}
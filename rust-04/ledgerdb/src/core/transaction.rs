@@ -4,14 +4,16 @@
 //! including input/output structures, validation, and serialization.
 
 use crate::crypto::{Address, Hash256, PublicKey, Signature};
-use crate::error::{Result, ValidationError};
+use crate::error::{LedgerError, Result, ValidationError};
+use crate::utils::constants::MAX_MEMO_SIZE;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Transaction input referencing a previous output
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct TransactionInput {
     /// Hash of the transaction containing the output being spent
     pub previous_tx_hash: Hash256,
@@ -73,7 +75,7 @@ impl TransactionInput {
 }
 
 /// Transaction output defining where funds are sent
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct TransactionOutput {
     /// Amount being transferred (in smallest unit)
     pub amount: u64,
@@ -130,7 +132,7 @@ impl TransactionOutput {
 }
 
 /// Transaction fee calculation
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct TransactionFee {
     /// Base fee per transaction
     pub base_fee: u64,
@@ -142,7 +144,9 @@ pub struct TransactionFee {
 
 impl TransactionFee {
     pub fn calculate_total_fee(&self, transaction_size: usize) -> u64 {
-        self.base_fee + (self.per_byte_fee * transaction_size as u64) + (self.base_fee as f64 * (self.priority_multiplier - 1.0)) as u64
+        self.base_fee
+            .saturating_add(self.per_byte_fee.saturating_mul(transaction_size as u64))
+            .saturating_add((self.base_fee as f64 * (self.priority_multiplier - 1.0)) as u64)
     }
 }
 
@@ -157,7 +161,10 @@ impl Default for TransactionFee {
 }
 
 /// Main transaction structure
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// `lock_time` values below this are block heights, values from it upwards Unix timestamps
+pub const LOCK_TIME_THRESHOLD: u64 = 500_000_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Transaction {
     /// Unique transaction identifier
     pub id: String,
@@ -169,12 +176,15 @@ pub struct Transaction {
     pub outputs: Vec<TransactionOutput>,
     /// Transaction fee information
     pub fee: TransactionFee,
-    /// Lock time (block height or timestamp)
+    /// Lock time: 0 for none, a block height below [`LOCK_TIME_THRESHOLD`], a Unix
+    /// timestamp from it upwards
     pub lock_time: u64,
     /// Transaction timestamp
     pub timestamp: DateTime<Utc>,
-    /// Optional transaction memo/data
-    pub data: Option<Vec<u8>>,
+    /// Optional memo, such as an invoice reference; at most [`MAX_MEMO_SIZE`]
+    /// bytes and covered by the transaction hash
+    #[serde(alias = "data")]
+    pub memo: Option<Vec<u8>>,
     /// Transaction size in bytes (calculated)
     pub size: Option<usize>,
 }
@@ -194,7 +204,7 @@ impl Transaction {
             fee: TransactionFee::default(),
             lock_time: 0,
             timestamp: Utc::now(),
-            data: None,
+            memo: None,
             size: None,
         };
         tx.calculate_size();
@@ -218,19 +228,60 @@ impl Transaction {
             },
             lock_time: 0,
             timestamp: Utc::now(),
-            data: Some(format!("Block {} mining reward", block_height).into_bytes()),
+            memo: Some(format!("Block {} mining reward", block_height).into_bytes()),
             size: None,
         };
         tx.calculate_size();
         tx
     }
 
+    /// Set the height or timestamp before which the transaction cannot be mined
+    pub fn with_lock_time(mut self, lock_time: u64) -> Self {
+        self.lock_time = lock_time;
+        self.calculate_size();
+        self
+    }
+
+    /// Attach a memo, replacing any existing one
+    pub fn with_memo(mut self, memo: impl Into<Vec<u8>>) -> Self {
+        self.memo = Some(memo.into());
+        self.calculate_size();
+        self
+    }
+
+    /// The memo as text, if it is valid UTF-8
+    pub fn memo_text(&self) -> Option<&str> {
+        self.memo.as_deref().and_then(|memo| std::str::from_utf8(memo).ok())
+    }
+
+    /// Whether the transaction may be included in a block at `height` whose
+    /// lock-time clock (the median time past) reads `time`
+    pub fn is_final(&self, height: u64, time: DateTime<Utc>) -> bool {
+        match self.lock_time {
+            0 => true,
+            lock if lock < LOCK_TIME_THRESHOLD => lock <= height,
+            lock => i64::try_from(lock).is_ok_and(|lock| lock <= time.timestamp()),
+        }
+    }
+
     /// Calculate and set the transaction size
     pub fn calculate_size(&mut self) {
         let serialized = bincode::serialize(self).unwrap_or_default();
         self.size = Some(serialized.len());
     }
 
+    /// Binary encoding, as stored and relayed between nodes
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode transaction: {}", e)))
+    }
+
+    /// Decode a transaction written by [`Transaction::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to decode transaction: {}", e)))
+    }
+
     /// Get the transaction hash
     pub fn hash(&self) -> Hash256 {
         let mut tx_for_hash = self.clone();
@@ -257,9 +308,10 @@ impl Transaction {
             .sum()
     }
 
-    /// Get total output amount
+    /// Get total output amount, saturating at `u64::MAX`; [`Transaction::validate`]
+    /// rejects transactions whose outputs overflow
     pub fn total_output_amount(&self) -> u64 {
-        self.outputs.iter().map(|output| output.amount).sum()
+        self.outputs.iter().fold(0u64, |total, output| total.saturating_add(output.amount))
     }
 
     /// Calculate transaction fee
@@ -271,11 +323,7 @@ impl Transaction {
         let input_amount = self.total_input_amount(utxo_set);
         let output_amount = self.total_output_amount();
         
-        if input_amount > output_amount {
-            input_amount - output_amount
-        } else {
-            0
-        }
+        input_amount.saturating_sub(output_amount)
     }
 
     /// Check if this is a coinbase transaction
@@ -292,6 +340,13 @@ impl Transaction {
         if self.outputs.is_empty() {
             return Err(ValidationError::EmptyOutputs.into());
         }
+        if let Some(memo) = &self.memo {
+            if memo.len() > MAX_MEMO_SIZE {
+                return Err(ValidationError::MemoTooLarge(format!(
+                    "{} bytes, at most {} allowed", memo.len(), MAX_MEMO_SIZE
+                )).into());
+            }
+        }
 
         // Validate inputs and outputs
         for input in &self.inputs {
@@ -319,7 +374,7 @@ impl Transaction {
                         return Err(ValidationError::OutputAlreadySpent(key).into());
                     }
                     total_input = total_input.checked_add(output.amount)
-                        .ok_or_else(|| ValidationError::ArithmeticOverflow)?;
+                        .ok_or_else(|| ValidationError::ArithmeticOverflow("total input".to_string()))?;
                 }
                 None => {
                     return Err(ValidationError::OutputNotFound(key).into());
@@ -327,7 +382,9 @@ impl Transaction {
             }
         }
 
-        let total_output = self.total_output_amount();
+        let total_output = self.outputs.iter()
+            .try_fold(0u64, |total, output| total.checked_add(output.amount))
+            .ok_or_else(|| ValidationError::ArithmeticOverflow("transaction outputs".to_string()))?;
         if total_input < total_output {
             return Err(ValidationError::InsufficientFunds(
                 format!("Insufficient funds: required {}, available {}", total_output, total_input)
@@ -551,6 +608,61 @@ mod tests {
         assert_eq!(tx.total_output_amount(), 800);
     }
 
+    #[test]
+    fn test_untrusted_transactions_do_not_panic() {
+        let input = TransactionInput::new(Hash256::zero(), 0, None, None);
+        let outputs = vec![
+            TransactionOutput::new(u64::MAX, create_test_address()),
+            TransactionOutput::new(u64::MAX, create_test_address()),
+        ];
+        let tx = Transaction::new(vec![input], outputs);
+        assert_eq!(tx.total_output_amount(), u64::MAX);
+
+        let decoded = Transaction::from_bytes(&tx.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.hash(), tx.hash());
+        assert!(Transaction::from_bytes(&[0xff; 7]).is_err());
+    }
+
+    #[test]
+    fn test_lock_time_finality() {
+        let output = TransactionOutput::new(1000, create_test_address());
+        let tx = Transaction::new(vec![], vec![output]);
+        let now = Utc::now();
+        assert!(tx.is_final(0, now));
+
+        let by_height = tx.clone().with_lock_time(10);
+        assert!(!by_height.is_final(9, now));
+        assert!(by_height.is_final(10, now));
+
+        let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let by_time = tx.with_lock_time(at.timestamp() as u64);
+        assert!(!by_time.is_final(u64::MAX, at - chrono::Duration::seconds(1)));
+        assert!(by_time.is_final(0, at));
+    }
+
+    #[test]
+    fn test_memo() {
+        let input = TransactionInput::new(Hash256::zero(), 0, None, None);
+        let output = TransactionOutput::new(1000, create_test_address());
+        let tx = Transaction::new(vec![input], vec![output]);
+        let invoiced = tx.clone().with_memo("INV-2024-0042");
+        assert_eq!(invoiced.memo_text(), Some("INV-2024-0042"));
+        assert_ne!(invoiced.hash(), tx.hash());
+        assert!(invoiced.size > tx.size);
+
+        // Exports from before the field was renamed still read
+        let mut json = serde_json::to_value(&invoiced).unwrap();
+        let memo = json.as_object_mut().unwrap().remove("memo").unwrap();
+        json["data"] = memo;
+        assert_eq!(serde_json::from_value::<Transaction>(json).unwrap(), invoiced);
+
+        let utxo_set = HashMap::new();
+        let at_cap = tx.clone().with_memo(vec![b'x'; MAX_MEMO_SIZE]);
+        assert!(!at_cap.validate(&utxo_set).unwrap_err().to_string().contains("Memo too large"));
+        let oversized = tx.with_memo(vec![b'x'; MAX_MEMO_SIZE + 1]);
+        assert!(oversized.validate(&utxo_set).unwrap_err().to_string().contains("Memo too large"));
+    }
+
     #[test]
     fn test_transaction_pool() {
         let mut pool = TransactionPool::new(10);
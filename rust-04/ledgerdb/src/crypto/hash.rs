@@ -128,14 +128,14 @@ pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Hash256 {
     
     // Inner hash
     let mut inner_hasher = Sha256::new();
-    inner_hasher.update(&ipad);
+    inner_hasher.update(ipad);
     inner_hasher.update(message);
     let inner_hash = inner_hasher.finalize();
     
     // Outer hash
     let mut outer_hasher = Sha256::new();
-    outer_hasher.update(&opad);
-    outer_hasher.update(&inner_hash);
+    outer_hasher.update(opad);
+    outer_hasher.update(inner_hash);
     
     Hash256::new(outer_hasher.finalize().into())
 }
@@ -241,10 +241,8 @@ mod tests {
     #[test]
     fn test_hash_builder() {
         let mut builder = HashBuilder::new();
-        let hash1 = builder
-            .update(b"hello")
-            .update(b"world")
-            .finalize();
+        builder.update(b"hello").update(b"world");
+        let hash1 = builder.finalize();
 
         let hash2 = crate::crypto::sha256_hash(b"helloworld");
         assert_eq!(hash1, hash2);
@@ -253,10 +251,8 @@ mod tests {
     #[test]
     fn test_hash_builder_numbers() {
         let mut builder = HashBuilder::new();
-        let hash = builder
-            .update_u64(12345)
-            .update_u32(678)
-            .finalize();
+        builder.update_u64(12345).update_u32(678);
+        let hash = builder.finalize();
         
         assert!(!hash.is_zero());
     }
@@ -315,11 +311,8 @@ mod tests {
         let hash3 = crate::crypto::sha256_hash(b"test3");
         
         let mut combiner = HashCombiner::new();
-        let combined = combiner
-            .add_hash(hash1)
-            .add_hash(hash2)
-            .add_hash(hash3)
-            .combine();
+        combiner.add_hash(hash1).add_hash(hash2).add_hash(hash3);
+        let combined = combiner.combine();
         
         assert!(!combined.is_zero());
     }
@@ -327,7 +320,7 @@ mod tests {
     #[test]
     fn test_hash_combiner_tree() {
         let hashes: Vec<Hash256> = (0..7)
-            .map(|i| crate::crypto::sha256_hash(&i.to_le_bytes()))
+            .map(|i: u32| crate::crypto::sha256_hash(&i.to_le_bytes()))
             .collect();
         
         let mut combiner = HashCombiner::new();
@@ -3,7 +3,7 @@
 //! This module provides key generation, management, and digital signature
 //! functionality for securing blockchain transactions and operations.
 
-use crate::crypto::{Address, Hash256, PublicKey, Signature, SignatureAlgorithm};
+use crate::crypto::{Address, PublicKey, Signature, SignatureAlgorithm};
 use crate::error::{CryptoError, Result};
 use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
@@ -282,7 +282,7 @@ impl Default for KeyManager {
 }
 
 /// Utility functions for key operations
-pub mod utils {
+pub mod key_utils {
     use super::*;
 
     /// Generate a deterministic key pair from a seed
@@ -332,11 +332,11 @@ pub mod utils {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::thread_rng;
+    use rand::rng;
 
     #[test]
     fn test_private_key_generation() {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         let private_key = PrivateKey::generate(&mut rng, SignatureAlgorithm::Ed25519).unwrap();
         assert_eq!(private_key.algorithm(), SignatureAlgorithm::Ed25519);
         assert_eq!(private_key.as_bytes().len(), 32);
@@ -344,7 +344,7 @@ mod tests {
 
     #[test]
     fn test_key_pair_generation() {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         let key_pair = KeyPair::generate(&mut rng, SignatureAlgorithm::Ed25519).unwrap();
         assert_eq!(
             key_pair.private_key().algorithm(),
@@ -358,7 +358,7 @@ mod tests {
 
     #[test]
     fn test_signing_and_verification() {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         let key_pair = KeyPair::generate(&mut rng, SignatureAlgorithm::Ed25519).unwrap();
         let message = b"test message";
         
@@ -369,7 +369,7 @@ mod tests {
 
     #[test]
     fn test_key_manager() {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         let mut manager = KeyManager::new(SignatureAlgorithm::Ed25519);
         
         let key_pair = manager.generate_key_pair(&mut rng).unwrap();
@@ -385,8 +385,8 @@ mod tests {
     #[test]
     fn test_key_pair_from_seed() {
         let seed = b"test seed";
-        let key_pair1 = utils::key_pair_from_seed(seed, SignatureAlgorithm::Ed25519).unwrap();
-        let key_pair2 = utils::key_pair_from_seed(seed, SignatureAlgorithm::Ed25519).unwrap();
+        let key_pair1 = key_utils::key_pair_from_seed(seed, SignatureAlgorithm::Ed25519).unwrap();
+        let key_pair2 = key_utils::key_pair_from_seed(seed, SignatureAlgorithm::Ed25519).unwrap();
         
         // Should be deterministic
         assert_eq!(key_pair1.address(), key_pair2.address());
@@ -394,11 +394,11 @@ mod tests {
 
     #[test]
     fn test_child_key_derivation() {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         let parent_key = PrivateKey::generate(&mut rng, SignatureAlgorithm::Ed25519).unwrap();
         
-        let child_key1 = utils::derive_child_key(&parent_key, 0).unwrap();
-        let child_key2 = utils::derive_child_key(&parent_key, 1).unwrap();
+        let child_key1 = key_utils::derive_child_key(&parent_key, 0).unwrap();
+        let child_key2 = key_utils::derive_child_key(&parent_key, 1).unwrap();
         
         assert_ne!(child_key1.as_bytes(), child_key2.as_bytes());
         assert_ne!(child_key1.as_bytes(), parent_key.as_bytes());
@@ -406,7 +406,7 @@ mod tests {
 
     #[test]
     fn test_private_key_hex() {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         let private_key = PrivateKey::generate(&mut rng, SignatureAlgorithm::Ed25519).unwrap();
         
         let hex = private_key.to_hex();
@@ -417,8 +417,8 @@ mod tests {
 
     #[test]
     fn test_multiple_key_generation() {
-        let mut rng = thread_rng();
-        let key_pairs = utils::generate_multiple_key_pairs(
+        let mut rng = rng();
+        let key_pairs = key_utils::generate_multiple_key_pairs(
             &mut rng,
             5,
             SignatureAlgorithm::Ed25519,
@@ -6,6 +6,7 @@
 use crate::crypto::Hash256;
 use crate::error::{CryptoError, Result};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::collections::HashMap;
 
 /// A node in the Merkle tree
@@ -55,14 +56,21 @@ impl MerkleNode {
 /// A Merkle tree for efficient data verification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerkleTree {
-    /// All nodes in the tree, indexed by their hash
-    nodes: HashMap<Hash256, MerkleNode>,
-    /// Root hash of the tree
-    root: Hash256,
-    /// Leaf hashes in order
-    leaves: Vec<Hash256>,
-    /// Height of the tree
-    height: usize,
+    /// Hashes level by level: the leaves in order first, the root alone last
+    levels: Vec<Vec<Hash256>>,
+}
+
+/// Hash of the internal node over `left` and `right`
+fn node_hash(left: &Hash256, right: &Hash256) -> Hash256 {
+    crate::crypto::hash_multiple(&[left.as_slice(), right.as_slice()])
+}
+
+/// The level above `level`, duplicating the last hash of an odd level
+fn parent_level(level: &[Hash256]) -> Vec<Hash256> {
+    level
+        .chunks(2)
+        .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
 }
 
 impl MerkleTree {
@@ -87,77 +95,66 @@ impl MerkleTree {
             return Err(CryptoError::EmptyMerkleTree.into());
         }
 
-        let mut nodes = HashMap::new();
-        let leaves = leaf_hashes.to_vec();
-        
-        // Add leaf nodes
-        for hash in &leaves {
-            nodes.insert(hash.clone(), MerkleNode::leaf(hash.clone()));
-        }
-
         // Build tree bottom-up
-        let mut current_level = leaves.clone();
-        let mut height = 0;
-
-        while current_level.len() > 1 {
-            let mut next_level = Vec::new();
-            
-            for chunk in current_level.chunks(2) {
-                let left_hash = chunk[0].clone();
-                let right_hash = if chunk.len() == 2 {
-                    chunk[1].clone()
-                } else {
-                    // Duplicate the last hash if odd number of nodes
-                    chunk[0].clone()
-                };
-
-                let internal_node = MerkleNode::internal(left_hash, right_hash);
-                let node_hash = internal_node.hash.clone();
-                nodes.insert(node_hash.clone(), internal_node);
-                next_level.push(node_hash);
-            }
-            
-            current_level = next_level;
-            height += 1;
+        let mut levels = vec![leaf_hashes.to_vec()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next_level = parent_level(&levels[levels.len() - 1]);
+            levels.push(next_level);
         }
 
-        let root = current_level.into_iter().next().unwrap();
-
-        Ok(Self {
-            nodes,
-            root,
-            leaves,
-            height,
-        })
+        Ok(Self { levels })
     }
 
     /// Get the root hash of the tree
     pub fn root(&self) -> &Hash256 {
-        &self.root
+        &self.levels[self.levels.len() - 1][0]
     }
 
     /// Get the height of the tree
     pub fn height(&self) -> usize {
-        self.height
+        self.levels.len() - 1
     }
 
     /// Get the number of leaves
     pub fn leaf_count(&self) -> usize {
-        self.leaves.len()
+        self.levels[0].len()
     }
 
     /// Get all leaf hashes
     pub fn leaves(&self) -> &[Hash256] {
-        &self.leaves
+        &self.levels[0]
+    }
+
+    /// Replace the leaf at `leaf_index` with `leaf_hash`, rehashing only the nodes on
+    /// its path to the root
+    pub fn update_leaf(&mut self, leaf_index: usize, leaf_hash: Hash256) -> Result<()> {
+        if leaf_index >= self.leaf_count() {
+            return Err(CryptoError::InvalidLeafIndex {
+                index: leaf_index,
+            }
+            .into());
+        }
+
+        self.levels[0][leaf_index] = leaf_hash;
+        let mut index = leaf_index;
+        for level in 1..self.levels.len() {
+            let left = index & !1;
+            let below = &self.levels[level - 1];
+            let hash = node_hash(&below[left], below.get(left + 1).unwrap_or(&below[left]));
+            index /= 2;
+            self.levels[level][index] = hash;
+        }
+
+        Ok(())
     }
 
     /// Generate a Merkle proof for a specific leaf
     pub fn generate_proof(&self, leaf_hash: &Hash256) -> Result<MerkleProof> {
         let leaf_index = self
-            .leaves
+            .leaves()
             .iter()
             .position(|h| h == leaf_hash)
-            .ok_or_else(|| CryptoError::LeafNotFound {
+            .ok_or(CryptoError::LeafNotFound {
                 index: 0, // Will be updated with actual index if needed
             })?;
 
@@ -166,7 +163,7 @@ impl MerkleTree {
 
     /// Generate a Merkle proof for a leaf at a specific index
     pub fn generate_proof_by_index(&self, leaf_index: usize) -> Result<MerkleProof> {
-        if leaf_index >= self.leaves.len() {
+        if leaf_index >= self.leaf_count() {
             return Err(CryptoError::InvalidLeafIndex {
                 index: leaf_index,
             }
@@ -176,13 +173,12 @@ impl MerkleTree {
         let mut proof_hashes = Vec::new();
         let mut proof_directions = Vec::new();
         let mut current_index = leaf_index;
-        let mut current_level = self.leaves.clone();
 
         // Traverse up the tree
-        while current_level.len() > 1 {
-            let sibling_index = if current_index % 2 == 0 {
+        for level in &self.levels[..self.height()] {
+            let sibling_index = if current_index.is_multiple_of(2) {
                 // Current node is left child, sibling is right
-                if current_index + 1 < current_level.len() {
+                if current_index + 1 < level.len() {
                     current_index + 1
                 } else {
                     // No right sibling, use self (odd number of nodes)
@@ -193,52 +189,42 @@ impl MerkleTree {
                 current_index - 1
             };
 
-            let sibling_hash = current_level[sibling_index].clone();
-            proof_hashes.push(sibling_hash);
-            proof_directions.push(current_index % 2 == 0); // true if current is left
-
-            // Move to next level
-            let mut next_level = Vec::new();
-            for chunk in current_level.chunks(2) {
-                let left_hash = chunk[0].clone();
-                let right_hash = if chunk.len() == 2 {
-                    chunk[1].clone()
-                } else {
-                    chunk[0].clone()
-                };
-                let combined = crate::crypto::hash_multiple(&[
-                    left_hash.as_slice(),
-                    right_hash.as_slice(),
-                ]);
-                next_level.push(combined);
-            }
-            
-            current_level = next_level;
+            proof_hashes.push(level[sibling_index].clone());
+            proof_directions.push(current_index.is_multiple_of(2)); // true if current is left
             current_index /= 2;
         }
 
         Ok(MerkleProof {
-            leaf_hash: self.leaves[leaf_index].clone(),
+            leaf_hash: self.leaves()[leaf_index].clone(),
             leaf_index,
             proof_hashes,
             proof_directions,
-            root_hash: self.root.clone(),
+            root_hash: self.root().clone(),
         })
     }
 
     /// Verify a Merkle proof against this tree
     pub fn verify_proof(&self, proof: &MerkleProof) -> bool {
-        proof.verify(&self.root)
+        proof.verify(self.root())
     }
 
     /// Get a node by its hash
-    pub fn get_node(&self, hash: &Hash256) -> Option<&MerkleNode> {
-        self.nodes.get(hash)
+    pub fn get_node(&self, hash: &Hash256) -> Option<MerkleNode> {
+        let (level, index) = self.levels.iter().enumerate().find_map(|(level, hashes)| {
+            hashes.iter().position(|h| h == hash).map(|index| (level, index))
+        })?;
+        if level == 0 {
+            return Some(MerkleNode::leaf(hash.clone()));
+        }
+        let below = &self.levels[level - 1];
+        let left = below[2 * index].clone();
+        let right = below.get(2 * index + 1).unwrap_or(&left).clone();
+        Some(MerkleNode::internal(left, right))
     }
 
     /// Check if the tree contains a specific leaf
     pub fn contains_leaf(&self, leaf_hash: &Hash256) -> bool {
-        self.leaves.contains(leaf_hash)
+        self.leaves().contains(leaf_hash)
     }
 
     /// Create a Merkle tree from transactions
@@ -259,46 +245,24 @@ impl MerkleTree {
     /// Get the path from root to a specific leaf
     pub fn get_path_to_leaf(&self, leaf_hash: &Hash256) -> Result<Vec<Hash256>> {
         let leaf_index = self
-            .leaves
+            .leaves()
             .iter()
             .position(|h| h == leaf_hash)
-            .ok_or_else(|| CryptoError::LeafNotFound {
+            .ok_or(CryptoError::LeafNotFound {
                 index: 0, // Will be updated with actual index if needed
             })?;
 
-        let mut path = vec![leaf_hash.clone()];
-        let mut current_index = leaf_index;
-        let mut current_level = self.leaves.clone();
-
-        while current_level.len() > 1 {
-            let mut next_level = Vec::new();
-            for chunk in current_level.chunks(2) {
-                let left_hash = chunk[0].clone();
-                let right_hash = if chunk.len() == 2 {
-                    chunk[1].clone()
-                } else {
-                    chunk[0].clone()
-                };
-                let combined = crate::crypto::hash_multiple(&[
-                    left_hash.as_slice(),
-                    right_hash.as_slice(),
-                ]);
-                next_level.push(combined);
-            }
-            
-            current_level = next_level;
-            current_index /= 2;
-            if current_index < current_level.len() {
-                path.push(current_level[current_index].clone());
-            }
-        }
-
-        Ok(path)
+        Ok(self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, hashes)| hashes[leaf_index >> level].clone())
+            .collect())
     }
 }
 
 /// A proof of inclusion for a leaf in a Merkle tree
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct MerkleProof {
     /// The leaf hash being proven
     pub leaf_hash: Hash256,
@@ -360,8 +324,26 @@ impl MerkleProof {
     }
 }
 
+/// Whether `proof` shows that `leaf_hash` is committed to by `merkle_root`.
+///
+/// Needs nothing but the three values, so a client holding only block headers can check
+/// a proof from `/api/transactions/:hash/proof` against the header's `merkle_root`; the
+/// node does the same for `POST /api/proofs/verify`. Besides hashing the path up to the
+/// root, the proof must name the same leaf and root, carry one direction per sibling and
+/// have directions that match its `leaf_index`.
+pub fn verify_merkle_proof(merkle_root: &Hash256, leaf_hash: &Hash256, proof: &MerkleProof) -> bool {
+    if proof.leaf_hash != *leaf_hash || proof.proof_hashes.len() != proof.proof_directions.len() {
+        return false;
+    }
+    let index_matches = proof.proof_directions
+        .iter()
+        .enumerate()
+        .all(|(level, &is_left)| is_left == (proof.leaf_index >> level).is_multiple_of(2));
+    index_matches && proof.leaf_index >> proof.proof_directions.len() == 0 && proof.verify(merkle_root)
+}
+
 /// Utility functions for Merkle tree operations
-pub mod utils {
+pub mod merkle_utils {
     use super::*;
 
     /// Compute Merkle root from a list of hashes
@@ -495,7 +477,7 @@ mod tests {
             crate::crypto::sha256_hash(b"tx3"),
         ];
         
-        let root = utils::compute_merkle_root(&hashes).unwrap();
+        let root = merkle_utils::compute_merkle_root(&hashes).unwrap();
         let tree = MerkleTree::from_hashes(&hashes).unwrap();
         
         assert_eq!(root, *tree.root());
@@ -503,19 +485,83 @@ mod tests {
 
     #[test]
     fn test_tree_height_calculation() {
-        assert_eq!(utils::calculate_tree_height(1), 0);
-        assert_eq!(utils::calculate_tree_height(2), 1);
-        assert_eq!(utils::calculate_tree_height(4), 2);
-        assert_eq!(utils::calculate_tree_height(8), 3);
-        assert_eq!(utils::calculate_tree_height(7), 3);
+        assert_eq!(merkle_utils::calculate_tree_height(1), 0);
+        assert_eq!(merkle_utils::calculate_tree_height(2), 1);
+        assert_eq!(merkle_utils::calculate_tree_height(4), 2);
+        assert_eq!(merkle_utils::calculate_tree_height(8), 3);
+        assert_eq!(merkle_utils::calculate_tree_height(7), 3);
     }
 
     #[test]
     fn test_max_leaves_for_height() {
-        assert_eq!(utils::max_leaves_for_height(0), 1);
-        assert_eq!(utils::max_leaves_for_height(1), 2);
-        assert_eq!(utils::max_leaves_for_height(2), 4);
-        assert_eq!(utils::max_leaves_for_height(3), 8);
+        assert_eq!(merkle_utils::max_leaves_for_height(0), 1);
+        assert_eq!(merkle_utils::max_leaves_for_height(1), 2);
+        assert_eq!(merkle_utils::max_leaves_for_height(2), 4);
+        assert_eq!(merkle_utils::max_leaves_for_height(3), 8);
+    }
+
+    #[test]
+    fn test_update_leaf_matches_rebuilt_tree() {
+        for count in 1..=7usize {
+            let mut leaves: Vec<Hash256> = (0..count)
+                .map(|i| crate::crypto::hash_data(format!("tx{}", i).as_bytes()))
+                .collect();
+            let mut tree = MerkleTree::from_hashes(&leaves).unwrap();
+
+            for i in 0..count {
+                leaves[i] = crate::crypto::hash_data(format!("replaced{}", i).as_bytes());
+                tree.update_leaf(i, leaves[i].clone()).unwrap();
+
+                let rebuilt = MerkleTree::from_hashes(&leaves).unwrap();
+                assert_eq!(tree.root(), rebuilt.root(), "{} leaves, leaf {}", count, i);
+                for j in 0..count {
+                    assert_eq!(tree.generate_proof_by_index(j).unwrap(), rebuilt.generate_proof_by_index(j).unwrap());
+                }
+            }
+        }
+
+        let mut tree = MerkleTree::new(&["tx1", "tx2"]).unwrap();
+        assert!(tree.update_leaf(2, Hash256::zero()).is_err());
+    }
+
+    #[test]
+    fn test_get_node() {
+        let data = vec!["tx1", "tx2", "tx3"];
+        let tree = MerkleTree::new(&data).unwrap();
+
+        let leaf = crate::crypto::hash_data(b"tx3");
+        assert_eq!(tree.get_node(&leaf), Some(MerkleNode::leaf(leaf.clone())));
+        let root = tree.get_node(tree.root()).unwrap();
+        assert!(!root.is_leaf);
+        assert_eq!(root.hash(), tree.root());
+        assert_eq!(tree.get_node(&Hash256::zero()), None);
+    }
+
+    #[test]
+    fn test_verify_merkle_proof() {
+        let data = vec!["tx1", "tx2", "tx3", "tx4", "tx5"];
+        let tree = MerkleTree::new(&data).unwrap();
+        for i in 0..data.len() {
+            let proof = tree.generate_proof_by_index(i).unwrap();
+            assert!(verify_merkle_proof(tree.root(), &tree.leaves()[i], &proof));
+        }
+
+        let proof = tree.generate_proof_by_index(2).unwrap();
+        let leaf = tree.leaves()[2].clone();
+        assert!(!verify_merkle_proof(&Hash256::zero(), &leaf, &proof));
+        assert!(!verify_merkle_proof(tree.root(), &tree.leaves()[3], &proof));
+
+        let mut tampered = proof.clone();
+        tampered.proof_hashes[0] = Hash256::zero();
+        assert!(!verify_merkle_proof(tree.root(), &leaf, &tampered));
+
+        // The directions must be the ones the leaf index spells out
+        let mut moved = proof.clone();
+        moved.leaf_index = 3;
+        assert!(!verify_merkle_proof(tree.root(), &leaf, &moved));
+        let mut short = proof;
+        short.proof_directions.pop();
+        assert!(!verify_merkle_proof(tree.root(), &leaf, &short));
     }
 
     #[test]
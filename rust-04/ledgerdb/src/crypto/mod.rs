@@ -14,13 +14,14 @@ pub use keys::*;
 pub use merkle::*;
 pub use pow::*;
 
-use crate::error::{CryptoError, Result};
+use crate::error::{CryptoError, LedgerError, Result};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use sha2::{Digest, Sha256};
 use std::fmt;
 
 /// A 256-bit hash value
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 pub struct Hash256([u8; 32]);
 
 impl Hash256 {
@@ -108,7 +109,7 @@ impl AsRef<[u8]> for Hash256 {
 }
 
 /// Digital signature representation
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct Signature {
     /// The signature algorithm used
     pub algorithm: SignatureAlgorithm,
@@ -124,7 +125,7 @@ impl Signature {
 }
 
 /// Supported signature algorithms
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 pub enum SignatureAlgorithm {
     /// ECDSA with secp256k1 curve
     EcdsaSecp256k1,
@@ -135,7 +136,7 @@ pub enum SignatureAlgorithm {
 
 
 /// Public key representation
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 pub struct PublicKey {
     /// The signature algorithm
     pub algorithm: SignatureAlgorithm,
@@ -166,7 +167,11 @@ impl PublicKey {
 }
 
 /// Blockchain address derived from public key
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// JSON carries it Base58Check-encoded, the same as [`ledgerdb_types::Address`];
+/// storage and hashing keep the bare hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, ToSchema)]
+#[schema(value_type = String)]
 pub struct Address(Hash256);
 
 impl Address {
@@ -181,7 +186,7 @@ impl Address {
         &self.0
     }
 
-    /// Convert to hex string
+    /// Convert to hex string: the bare hash, as storage keys use it
     pub fn to_hex(&self) -> String {
         self.0.to_hex()
     }
@@ -192,20 +197,42 @@ impl Address {
         Ok(Self(hash))
     }
 
-    /// Create from string (alias for from_hex)
-    pub fn from_string(hex_str: &str) -> Result<Self> {
-        Self::from_hex(hex_str)
-    }
-
-    /// Convert to string (alias for to_hex)
-    pub fn to_string(&self) -> String {
-        self.to_hex()
+    /// Parse the checksummed form that users see
+    pub fn from_string(encoded: &str) -> Result<Self> {
+        ledgerdb_types::Address::decode(encoded)
+            .map(Self::from)
+            .map_err(|e| CryptoError::InvalidFormat(format!("Invalid address {}: {}", encoded, e)).into())
     }
 }
 
+/// The checksummed form that users see
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_hex())
+        f.write_str(&ledgerdb_types::Address::from(self).encode())
+    }
+}
+
+impl From<&Address> for ledgerdb_types::Address {
+    fn from(address: &Address) -> Self {
+        ledgerdb_types::Address(ledgerdb_types::Hash256(address.0 .0))
+    }
+}
+
+impl From<ledgerdb_types::Address> for Address {
+    fn from(address: ledgerdb_types::Address) -> Self {
+        Self(Hash256(address.0 .0))
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        ledgerdb_types::Address::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        ledgerdb_types::Address::deserialize(deserializer).map(Self::from)
     }
 }
 
@@ -218,6 +245,11 @@ pub fn hash_data(data: &[u8]) -> Hash256 {
     Hash256(hasher.finalize().into())
 }
 
+/// Single SHA-256 hash; the same as [`hash_data`]
+pub fn sha256_hash(data: &[u8]) -> Hash256 {
+    hash_data(data)
+}
+
 /// Double SHA-256 hash (Bitcoin-style)
 pub fn double_hash(data: &[u8]) -> Hash256 {
     let first_hash = hash_data(data);
@@ -328,6 +360,24 @@ mod tests {
         assert_eq!(address, parsed);
     }
 
+    #[test]
+    fn test_address_encoding() {
+        let public_key = PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5]);
+        let address = Address::from_public_key(&public_key);
+
+        let encoded = address.to_string();
+        assert_eq!(Address::from_string(&encoded).unwrap(), address);
+        assert!(Address::from_string(&address.to_hex()).is_err());
+        let last = if encoded.ends_with('2') { "3" } else { "2" };
+        assert!(Address::from_string(&format!("{}{}", &encoded[..encoded.len() - 1], last)).is_err());
+
+        // JSON shows the encoded form; bincode keeps the bare hash it always had
+        assert_eq!(serde_json::to_value(&address).unwrap(), serde_json::json!(encoded));
+        assert_eq!(bincode::serialize(&address).unwrap(), address.as_hash().as_slice());
+        let decoded: Address = bincode::deserialize(&bincode::serialize(&address).unwrap()).unwrap();
+        assert_eq!(decoded, address);
+    }
+
     #[test]
     fn test_hash_multiple() {
         let data1 = b"hello";
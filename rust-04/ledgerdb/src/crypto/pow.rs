@@ -4,11 +4,12 @@
 //! proof-of-work validation for blockchain consensus.
 
 use crate::crypto::Hash256;
-use crate::error::{CryptoError, Result};
+use crate::error::Result;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::broadcast;
 
 /// Proof of Work configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +93,198 @@ pub struct MiningResult {
     pub stop_reason: Option<String>,
 }
 
+/// What a miner reports while searching for a block's nonce, and how the search ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MinerEvent {
+    /// Still searching, sent every progress interval
+    Progress {
+        /// Height of the block being mined
+        height: u64,
+        progress: MiningProgress,
+    },
+    /// A nonce meeting the difficulty target was found
+    Found {
+        height: u64,
+        difficulty: u32,
+        nonce: u64,
+        hash: Hash256,
+        attempts: u64,
+        elapsed_seconds: f64,
+    },
+    /// The search was cancelled before a nonce was found
+    Cancelled {
+        height: u64,
+        difficulty: u32,
+        attempts: u64,
+        elapsed_seconds: f64,
+    },
+    /// The search gave up after its maximum number of attempts
+    Timeout {
+        height: u64,
+        difficulty: u32,
+        attempts: u64,
+        elapsed_seconds: f64,
+    },
+}
+
+impl MinerEvent {
+    /// Height of the block the event is about
+    pub fn height(&self) -> u64 {
+        match self {
+            MinerEvent::Progress { height, .. }
+            | MinerEvent::Found { height, .. }
+            | MinerEvent::Cancelled { height, .. }
+            | MinerEvent::Timeout { height, .. } => *height,
+        }
+    }
+
+    /// Difficulty of the block the event is about
+    pub fn difficulty(&self) -> u32 {
+        match self {
+            MinerEvent::Progress { progress, .. } => progress.target_difficulty,
+            MinerEvent::Found { difficulty, .. }
+            | MinerEvent::Cancelled { difficulty, .. }
+            | MinerEvent::Timeout { difficulty, .. } => *difficulty,
+        }
+    }
+
+    /// Nonces tried so far
+    pub fn attempts(&self) -> u64 {
+        match self {
+            MinerEvent::Progress { progress, .. } => progress.attempts,
+            MinerEvent::Found { attempts, .. }
+            | MinerEvent::Cancelled { attempts, .. }
+            | MinerEvent::Timeout { attempts, .. } => *attempts,
+        }
+    }
+
+    /// Hashes per second over the search so far
+    pub fn hash_rate(&self) -> f64 {
+        let (attempts, elapsed) = match self {
+            MinerEvent::Progress { progress, .. } => return progress.hash_rate,
+            MinerEvent::Found { attempts, elapsed_seconds, .. }
+            | MinerEvent::Cancelled { attempts, elapsed_seconds, .. }
+            | MinerEvent::Timeout { attempts, elapsed_seconds, .. } => (*attempts, *elapsed_seconds),
+        };
+        if elapsed > 0.0 { attempts as f64 / elapsed } else { 0.0 }
+    }
+
+    /// Whether the search this event belongs to has ended
+    pub fn is_final(&self) -> bool {
+        !matches!(self, MinerEvent::Progress { .. })
+    }
+}
+
+/// Broadcasts miner events to any number of subscribers, remembering the latest
+/// one for callers that poll instead of subscribing
+#[derive(Debug, Clone)]
+pub struct MinerEvents {
+    sender: broadcast::Sender<MinerEvent>,
+    latest: Arc<Mutex<Option<MinerEvent>>>,
+}
+
+impl MinerEvents {
+    /// Create a hub whose subscribers may fall up to `capacity` events behind
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            latest: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Publish `event`, whether or not anyone is subscribed
+    pub fn send(&self, event: MinerEvent) {
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(event.clone());
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive every event sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<MinerEvent> {
+        self.sender.subscribe()
+    }
+
+    /// The most recent event, if any miner has reported one
+    pub fn latest(&self) -> Option<MinerEvent> {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// How a nonce search reports its events and when it stops early
+#[derive(Debug, Clone)]
+pub struct MiningControl {
+    events: Option<MinerEvents>,
+    cancelled: Arc<AtomicBool>,
+    max_attempts: u64,
+    progress_interval: u64,
+}
+
+impl MiningControl {
+    /// Attempts between progress events and cancellation checks
+    pub const DEFAULT_PROGRESS_INTERVAL: u64 = 100_000;
+    /// Attempts before a search times out
+    pub const DEFAULT_MAX_ATTEMPTS: u64 = 10_000_000;
+
+    /// A search that reports nothing and stops only on timeout
+    pub fn new() -> Self {
+        Self {
+            events: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            max_attempts: Self::DEFAULT_MAX_ATTEMPTS,
+            progress_interval: Self::DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Publish the search's events on `events`
+    pub fn with_events(mut self, events: MinerEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Give up after `max_attempts` nonces
+    pub fn with_max_attempts(mut self, max_attempts: u64) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Report progress and check for cancellation every `interval` nonces
+    pub fn with_progress_interval(mut self, interval: u64) -> Self {
+        self.progress_interval = interval.max(1);
+        self
+    }
+
+    /// Stop the search at its next progress interval; clones share the flag
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn max_attempts(&self) -> u64 {
+        self.max_attempts
+    }
+
+    pub fn progress_interval(&self) -> u64 {
+        self.progress_interval
+    }
+
+    /// Publish `event` if the search has somewhere to send it
+    pub fn emit(&self, event: impl FnOnce() -> MinerEvent) {
+        if let Some(events) = &self.events {
+            events.send(event());
+        }
+    }
+}
+
+impl Default for MiningControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Proof of Work miner
 #[derive(Debug)]
 pub struct ProofOfWorkMiner {
@@ -215,21 +408,13 @@ impl ProofOfWorkMiner {
             if last_progress_update.elapsed().as_millis() >= self.config.progress_interval_ms as u128 {
                 let elapsed = start_time.elapsed().as_secs_f64();
                 let hash_rate = attempts as f64 / elapsed;
-                
-                let estimated_remaining = if hash_rate > 0.0 {
-                    let target_attempts = calculate_expected_attempts(self.config.difficulty);
-                    let remaining_attempts = target_attempts.saturating_sub(attempts);
-                    Some(remaining_attempts as f64 / hash_rate)
-                } else {
-                    None
-                };
 
                 let progress = MiningProgress {
                     current_nonce: nonce,
                     attempts,
                     hash_rate,
                     elapsed_seconds: elapsed,
-                    estimated_remaining_seconds: estimated_remaining,
+                    estimated_remaining_seconds: estimated_block_seconds(self.config.difficulty, hash_rate),
                     is_complete: false,
                     best_hash: best_hash.clone(),
                     target_difficulty: self.config.difficulty,
@@ -269,7 +454,7 @@ impl ProofOfWorkMiner {
             attempts,
             hash_rate,
             elapsed_seconds: elapsed,
-            estimated_remaining_seconds: None,
+            estimated_remaining_seconds: estimated_block_seconds(self.config.difficulty, hash_rate),
             is_complete: !self.is_mining(),
             best_hash: None,
             target_difficulty: self.config.difficulty,
@@ -277,7 +462,9 @@ impl ProofOfWorkMiner {
     }
 }
 
-/// Calculate the target value for a given difficulty
+/// Calculate the target value for a given difficulty: the largest hash with
+/// `difficulty` leading zero bits, which [`validate_proof_of_work`] accepts hashes
+/// up to
 pub fn calculate_target(difficulty: u32) -> Hash256 {
     let mut target_bytes = [0xFFu8; 32];
     
@@ -285,10 +472,9 @@ pub fn calculate_target(difficulty: u32) -> Hash256 {
     let zero_bytes = difficulty / 8;
     let remaining_bits = difficulty % 8;
     
-    for i in 0..zero_bytes as usize {
-        if i < 32 {
-            target_bytes[i] = 0;
-        }
+    // Difficulty comes from untrusted headers; anything past 256 bits is all zeros
+    for byte in target_bytes.iter_mut().take(zero_bytes as usize) {
+        *byte = 0;
     }
     
     if zero_bytes < 32 && remaining_bits > 0 {
@@ -330,15 +516,44 @@ fn hash_to_score(hash: &Hash256) -> u64 {
     ])
 }
 
-/// Calculate expected number of attempts for a given difficulty
+/// Calculate expected number of attempts for a given difficulty, saturating at
+/// `u64::MAX`
 pub fn calculate_expected_attempts(difficulty: u32) -> u64 {
-    if difficulty == 0 {
-        1
+    1u64.checked_shl(difficulty).unwrap_or(u64::MAX)
+}
+
+/// Hashes a miner expects to try before one meets [`calculate_target`] at
+/// `difficulty`: `2^difficulty`, without saturating
+pub fn expected_hashes(difficulty: u32) -> f64 {
+    // A target of 256 or more leading zero bits only admits the zero hash
+    2f64.powi(difficulty.min(256) as i32)
+}
+
+/// Expected seconds to find a block at `difficulty` hashing `hash_rate` times a
+/// second; `None` without a hash rate.
+///
+/// Each hash is an independent try, so this is also the expected time left at
+/// any point of a search, however long it has run.
+pub fn estimated_block_seconds(difficulty: u32, hash_rate: f64) -> Option<f64> {
+    (hash_rate > 0.0).then(|| expected_hashes(difficulty) / hash_rate)
+}
+
+/// Hash rate that finds blocks at `difficulty` every `block_seconds` on average;
+/// 0 without a block time
+pub fn estimate_hash_rate(difficulty: u32, block_seconds: f64) -> f64 {
+    if block_seconds > 0.0 {
+        expected_hashes(difficulty) / block_seconds
     } else {
-        2u64.pow(difficulty)
+        0.0
     }
 }
 
+/// Work a block at `difficulty` proves: the expected number of hashes to find it,
+/// saturating at `u128::MAX`
+pub fn block_work(difficulty: u32) -> u128 {
+    1u128.checked_shl(difficulty).unwrap_or(u128::MAX)
+}
+
 /// Adjust difficulty based on block time
 pub fn adjust_difficulty(
     current_difficulty: u32,
@@ -484,6 +699,35 @@ mod tests {
         assert_eq!(calculate_expected_attempts(3), 8);
     }
 
+    #[test]
+    fn test_work_estimates_match_the_target() {
+        // The target admits hashes with `difficulty` leading zero bits: one in 2^difficulty
+        for difficulty in [0, 1, 8, 12, 40] {
+            let target = calculate_target(difficulty);
+            let bytes = target.as_slice();
+            let zero_bits = bytes.iter().position(|b| *b != 0).map_or(256, |i| i as u32 * 8 + bytes[i].leading_zeros());
+            assert_eq!(zero_bits, difficulty);
+            assert_eq!(expected_hashes(difficulty), block_work(difficulty) as f64);
+        }
+        assert_eq!(calculate_target(12).to_hex(), format!("000f{}", "f".repeat(60)));
+        assert_eq!(calculate_expected_attempts(64), u64::MAX);
+        assert_eq!(expected_hashes(300), expected_hashes(256));
+
+        assert_eq!(estimated_block_seconds(10, 512.0), Some(2.0));
+        assert_eq!(estimated_block_seconds(10, 0.0), None);
+        assert_eq!(estimate_hash_rate(10, 2.0), 512.0);
+        assert_eq!(estimate_hash_rate(10, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_block_work() {
+        assert_eq!(block_work(0), 1);
+        assert_eq!(block_work(20), 1 << 20);
+        assert_eq!(block_work(127), 1 << 127);
+        assert_eq!(block_work(128), u128::MAX);
+        assert_eq!(block_work(256), u128::MAX);
+    }
+
     #[test]
     fn test_mining_stats() {
         let mut stats = MiningStats::new();
@@ -524,6 +768,7 @@ mod tests {
         assert!(hash_meets_target(&zero_hash, &hard_target));
         
         let max_hash = Hash256::new([0xFF; 32]);
-        assert!(hash_meets_target(&max_hash, &easy_target));
+        assert!(hash_meets_target(&max_hash, &calculate_target(0)));
+        assert!(!hash_meets_target(&max_hash, &easy_target));
     }
 }
\ No newline at end of file
@@ -1,7 +1,4 @@
-use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use axum::Json;
-use serde_json::json;
 use serde::{Serialize, Deserialize};
 use std::fmt;
 
@@ -40,6 +37,7 @@ pub enum ValidationError {
     InvalidProofOfWork(String),
     InvalidTransactionCount(String),
     MiningTimeout,
+    MiningCancelled,
     InvalidNonce(String),
     InvalidPreviousHash(String),
     InvalidIndex(String),
@@ -51,6 +49,20 @@ pub enum ValidationError {
     EmptyOutputs,
     InvalidCoinbase(String),
     OutputAlreadySpent(String),
+    CheckpointMismatch(String),
+    NonFinalTransaction(String),
+    InvalidPsbt(String),
+    WrongNetwork(String),
+    MemoTooLarge(String),
+    InvalidVersion(String),
+    InvalidBlockIndex { expected: u64, actual: u64 },
+    EmptyBlock,
+    MissingCoinbase,
+    MultipleCoinbase,
+    InvalidAmount(String),
+    EmptyInputs,
+    MissingSignature,
+    MissingPublicKey,
 }
 
 impl fmt::Display for ValidationError {
@@ -64,6 +76,7 @@ impl fmt::Display for ValidationError {
             ValidationError::InvalidProofOfWork(msg) => write!(f, "Invalid proof of work: {}", msg),
             ValidationError::InvalidTransactionCount(msg) => write!(f, "Invalid transaction count: {}", msg),
             ValidationError::MiningTimeout => write!(f, "Mining timeout"),
+            ValidationError::MiningCancelled => write!(f, "Mining cancelled"),
             ValidationError::InvalidNonce(msg) => write!(f, "Invalid nonce: {}", msg),
             ValidationError::InvalidPreviousHash(msg) => write!(f, "Invalid previous hash: {}", msg),
             ValidationError::InvalidIndex(msg) => write!(f, "Invalid index: {}", msg),
@@ -75,6 +88,22 @@ impl fmt::Display for ValidationError {
             ValidationError::EmptyOutputs => write!(f, "Empty outputs"),
             ValidationError::InvalidCoinbase(msg) => write!(f, "Invalid coinbase: {}", msg),
             ValidationError::OutputAlreadySpent(msg) => write!(f, "Output already spent: {}", msg),
+            ValidationError::CheckpointMismatch(msg) => write!(f, "Checkpoint mismatch: {}", msg),
+            ValidationError::NonFinalTransaction(msg) => write!(f, "Non-final transaction: {}", msg),
+            ValidationError::InvalidPsbt(msg) => write!(f, "Invalid partially signed transaction: {}", msg),
+            ValidationError::WrongNetwork(msg) => write!(f, "Wrong network: {}", msg),
+            ValidationError::MemoTooLarge(msg) => write!(f, "Memo too large: {}", msg),
+            ValidationError::InvalidVersion(msg) => write!(f, "Invalid version: {}", msg),
+            ValidationError::InvalidBlockIndex { expected, actual } => {
+                write!(f, "Invalid block index: expected {}, got {}", expected, actual)
+            }
+            ValidationError::EmptyBlock => write!(f, "Empty block"),
+            ValidationError::MissingCoinbase => write!(f, "Missing coinbase"),
+            ValidationError::MultipleCoinbase => write!(f, "Multiple coinbase transactions"),
+            ValidationError::InvalidAmount(msg) => write!(f, "Invalid amount: {}", msg),
+            ValidationError::EmptyInputs => write!(f, "Empty inputs"),
+            ValidationError::MissingSignature => write!(f, "Missing signature"),
+            ValidationError::MissingPublicKey => write!(f, "Missing public key"),
         }
     }
 }
@@ -133,6 +162,20 @@ pub enum ConfigError {
     Invalid(String),
     #[error("Parse error: {0}")]
     Parse(String),
+    #[error("Invalid configuration value: {field}")]
+    InvalidConfig { field: String },
+    #[error("{field} = {value} is out of range {range}")]
+    ValueOutOfRange { field: String, value: String, range: String },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
 }
 
 // Convert between error types
@@ -154,6 +197,22 @@ impl From<CryptoError> for LedgerError {
     }
 }
 
+impl From<ConfigError> for LedgerError {
+    fn from(err: ConfigError) -> Self {
+        LedgerError::Config(err.to_string())
+    }
+}
+
+impl From<StorageError> for LedgerError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::DatabaseError(msg) => LedgerError::Database(msg),
+            StorageError::NotFound(msg) => LedgerError::NotFound(msg),
+            StorageError::SerializationError(msg) => LedgerError::Serialization(msg),
+        }
+    }
+}
+
 impl From<ValidationError> for BlockchainError {
     fn from(err: ValidationError) -> Self {
         BlockchainError::InvalidChain(err.to_string())
@@ -186,24 +245,10 @@ pub enum ApiError {
     NetworkError(String),
 }
 
+/// Sent in the API's response envelope, as the `crate::api::ApiError` it maps to
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            ApiError::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
-            ApiError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
-            ApiError::ValidationError(msg) => (StatusCode::BAD_REQUEST, msg),
-            ApiError::BlockchainError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            ApiError::StorageError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            ApiError::MiningError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            ApiError::NetworkError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-        };
-
-        let body = json!({ "error": error_message });
-        (status, Json(body)).into_response()
+        crate::api::ApiError::from(self).into_response()
     }
 }
 
@@ -0,0 +1,29 @@
+//! LedgerDB - A high-performance blockchain implementation in Rust
+//!
+//! The `ledgerdb` binary is a thin wrapper around this library. Applications that
+//! want to run a node in-process should start from [`NodeBuilder`], which wires
+//! storage, the blockchain, background tasks and optionally the HTTP API and a
+//! miner into a [`Node`] with start/stop control and a [`NodeEvent`] stream.
+
+pub mod api;
+pub mod auth;
+pub mod config;
+pub mod core;
+pub mod crypto;
+pub mod error;
+pub mod maintenance;
+pub mod node;
+pub mod p2p;
+pub mod peers;
+pub mod relay;
+pub mod simulation;
+pub mod storage;
+pub mod sync;
+pub mod tasks;
+pub mod tenants;
+pub mod utils;
+pub mod watch;
+pub mod webhooks;
+
+pub use error::{LedgerError, Result};
+pub use node::{Node, NodeBuilder, NodeEvent};
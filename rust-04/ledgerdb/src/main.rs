@@ -4,239 +4,310 @@
 //! It initializes the blockchain, starts the HTTP API server, and handles
 //! WebSocket connections for real-time updates.
 
-use axum::{
-    extract::ws::{WebSocket, WebSocketUpgrade},
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
-};
-use std::{
-    collections::HashMap,
-    net::SocketAddr,
-    sync::Arc,
-    time::Duration,
-};
-use tokio::time::sleep;
-use tower::ServiceBuilder;
-use tower_http::{
-    cors::CorsLayer,
-    trace::TraceLayer,
-};
-
-// Import our modules
-mod api;
-mod config;
-mod core;
-mod crypto;
-mod error;
-mod storage;
-mod utils;
-
-use api::{
-    handlers::*,
-    middleware::*,
-    responses::*,
-    websocket::*,
-};
-use crate::core::{
-    blockchain::Blockchain,
-    block::Block,
-    transaction::Transaction,
-};
-use crate::crypto::{
-    pow::{ProofOfWorkMiner, MiningProgress},
-    Hash256,
-    Address,
-    PublicKey,
-    SignatureAlgorithm,
-};
-use crate::storage::PersistentStorage;
-use crate::utils::{
-    time::current_timestamp,
-    format::format_hash,
-};
-
-// Use the AppState from the api module
-use api::AppState;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use ledgerdb::{api, config, maintenance, utils, NodeBuilder};
+use ledgerdb::core::snapshot::UtxoSnapshot;
+use ledgerdb::simulation::SimulationParams;
+
+/// LedgerDB blockchain node
+#[derive(Debug, Parser)]
+#[command(name = "ledgerdb", version, about)]
+struct Cli {
+    /// TOML configuration file; `LEDGER_*` environment variables override its values
+    #[arg(long, short, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Storage directory, overriding `storage.db_path`
+    #[arg(long, global = true, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Every command except `run` works on the storage directory directly; stop the
+/// node first, as the database can only be opened by one process
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the node and its HTTP API (the default)
+    Run,
+    /// Replay the stored chain from genesis and report the first invalid block
+    VerifyChain,
+    /// Check the stored blocks, transactions, UTXOs and indexes against each other
+    /// and print the issues found as JSON
+    VerifyStorage {
+        /// Drop index entries that point at missing records
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Write blocks to a JSON-lines file
+    Export {
+        /// First block to export
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        /// Last block to export (default: the tip)
+        #[arg(long)]
+        to: Option<u64>,
+        /// Output file (default: stdout)
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Append the blocks in a JSON-lines export to the stored chain
+    Import {
+        /// File written by `export`
+        file: PathBuf,
+    },
+    /// Rebuild the block, transaction, UTXO and address indexes from the stored blocks
+    Reindex,
+    /// Print chain and database statistics as JSON
+    Stats,
+    /// Mine blocks and submit random transactions between generated wallets,
+    /// printing what was done as JSON
+    Simulate {
+        /// Wallets to create
+        #[arg(long, default_value_t = 20)]
+        wallets: usize,
+        /// Mean transactions per second
+        #[arg(long, default_value_t = 1.0)]
+        tx_rate: f64,
+        /// Seconds of simulated time to run for
+        #[arg(long, default_value_t = 600)]
+        duration: u64,
+        /// Seconds between blocks (default: the target block time)
+        #[arg(long)]
+        block_interval: Option<u64>,
+        /// Median amount sent
+        #[arg(long, default_value_t = 50_000)]
+        median_amount: u64,
+        /// Repeat an earlier run
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Run against the wall clock instead of as fast as possible
+        #[arg(long)]
+        realtime: bool,
+    },
+    /// Feed a recording made with `storage.replay_log` to the stored chain and
+    /// print the outcome as JSON
+    Replay {
+        /// Recording to replay
+        file: PathBuf,
+    },
+    /// Download a UTXO snapshot in chunks from running nodes, for
+    /// `storage.utxo_snapshot`; run it again to resume an interrupted download
+    FetchSnapshot {
+        /// Node to download from, e.g. `http://10.0.0.5:8080`; repeat it to spread
+        /// the chunks over several nodes
+        #[arg(long = "node", required = true, value_name = "URL")]
+        nodes: Vec<String>,
+        /// Height to snapshot at (default: the first node's tip)
+        #[arg(long)]
+        height: Option<u64>,
+        /// Chunks to download at once
+        #[arg(long, default_value_t = 4)]
+        max_in_flight: usize,
+        /// Output file; chunks are kept next to it in `<FILE>.chunks` until all are in
+        #[arg(long, short, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration (file plus environment overrides) as TOML
+    Print,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    utils::init_logging();
+    let cli = Cli::parse();
+    let loaded_config = match &cli.config {
+        Some(path) => config::Config::from_file(path)?,
+        None => config::Config::from_env()?,
+    };
+    let mut app_config = loaded_config.clone();
+    if let Some(dir) = &cli.data_dir {
+        app_config.storage.db_path = dir.clone();
+    }
+    // Addresses are printed and parsed for the configured network by every command
+    ledgerdb_types::set_address_network(app_config.blockchain.network);
+    match cli.command {
+        None | Some(Command::Run) => {}
+        Some(Command::FetchSnapshot { nodes, height, max_in_flight, output }) => {
+            return fetch_snapshot(&nodes, height, max_in_flight, &output).await;
+        }
+        Some(command) => return run_command(command, &app_config),
+    }
+
+    // Initialize logging; the level and rate limit follow config reloads
+    utils::logging::init_reloadable_logging(&app_config.logging)?;
+    api::set_rate_limit(app_config.api.rate_limit);
     
     println!("🚀 Starting LedgerDB blockchain...");
-    
-    // Initialize storage
-    let storage = Arc::new(PersistentStorage::new("./data".to_string()).expect("Failed to initialize storage"));
 
-    // Create a genesis address
-    let genesis_public_key = PublicKey::new(
-        SignatureAlgorithm::EcdsaSecp256k1,
-        vec![0u8; 33] // Placeholder public key
-    );
-    let genesis_address = Address::from_public_key(&genesis_public_key);
-    
-    // Create blockchain config
-    let config = crate::core::blockchain::BlockchainConfig::default();
-    
-    // Initialize blockchain
-    let blockchain = Arc::new(tokio::sync::RwLock::new(
-        Blockchain::new(config, genesis_address).expect("Failed to create blockchain")
-    ));
-
-    // Initialize mining progress broadcaster
-    let (mining_progress_tx, _) = tokio::sync::broadcast::channel::<MiningProgress>(100);
-
-    // Initialize miner
-    let miner = Arc::new(tokio::sync::RwLock::new(None::<ProofOfWorkMiner>));
-
-    // Create API config
-    let config = api::ApiConfig::default();
-
-    // Create application state
-    let app_state = api::AppState {
-        blockchain: blockchain.clone(),
-        storage: storage.clone(),
-        mining_progress_tx,
-        miner,
-        config,
+    // The binary runs the same node an application embeds, plus the explorer page
+    let mut builder = match &cli.config {
+        // Reloaded on SIGHUP, compared with the file as loaded, before `--data-dir`
+        Some(path) => NodeBuilder::new().config_file(path),
+        None => NodeBuilder::new().config(loaded_config),
     };
-    
-    // The blockchain is already initialized with genesis block in Blockchain::new()
-    println!("📦 Genesis block created successfully!");
-    
-    // Build the router with all endpoints
-    let app = Router::new()
-        // API routes
-        .route("/api/blocks", get(get_blocks))
-        .route("/api/blocks/:hash", get(get_block_by_hash))
-        .route("/api/transactions", get(get_transactions))
-        .route("/api/transactions/:hash", get(get_transaction_by_hash))
-        .route("/api/mine", post(mine_block))
-        .route("/api/submit_transaction", post(submit_transaction))
-        .route("/api/balance/:address", get(get_balance))
-        .route("/api/stats", get(get_network_stats))
-        .route("/api/health", get(health_check))
-        
-        // WebSocket endpoint
-        .route("/ws", get(websocket_handler))
-        
-        // Static file serving (for frontend)
-        .route("/", get(serve_index))
-        .route("/static/*file", get(serve_static))
-        
-        // Add middleware
-        .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(CorsLayer::permissive())
-                .layer(axum::middleware::from_fn(request_logging_middleware))
-                .layer(axum::middleware::from_fn(security_headers_middleware))
-        )
-        .with_state(app_state);
-    
-    // Start the server
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    println!("🌐 LedgerDB API server starting on http://{}", addr);
-    println!("📊 WebSocket endpoint available at ws://{}/ws", addr);
-    println!("🔗 Blockchain explorer UI at http://{}", addr);
-    
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-    
-    Ok(())
-}
+    if let Some(dir) = &cli.data_dir {
+        builder = builder.storage_path(dir);
+    }
+    let mut node = builder.enable_api(true).static_files(true).build()?;
+    println!("📦 Loaded chain from {}", app_config.storage.db_path.display());
 
-/// Serve the main index.html file
-async fn serve_index() -> impl IntoResponse {
-    // Serve embedded HTML since static file doesn't exist yet
-    Html(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>LedgerDB Blockchain Explorer</title>
-    <style>
-        body { font-family: Arial, sans-serif; margin: 40px; background: #f5f5f5; }
-        .container { max-width: 800px; margin: 0 auto; background: white; padding: 30px; border-radius: 8px; box-shadow: 0 2px 10px rgba(0,0,0,0.1); }
-        h1 { color: #333; text-align: center; }
-        .status { background: #e8f5e8; padding: 15px; border-radius: 5px; margin: 20px 0; }
-        .endpoint { background: #f8f9fa; padding: 10px; margin: 10px 0; border-left: 4px solid #007bff; }
-        code { background: #f1f1f1; padding: 2px 6px; border-radius: 3px; }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>🚀 LedgerDB Blockchain Explorer</h1>
-        <div class="status">
-            <h3>✅ Blockchain is running!</h3>
-            <p>The LedgerDB blockchain node is operational and ready to process transactions.</p>
-        </div>
-        
-        <h3>📡 API Endpoints</h3>
-        <div class="endpoint"><strong>GET /api/blocks</strong> - Get all blocks</div>
-        <div class="endpoint"><strong>GET /api/blocks/:hash</strong> - Get block by hash</div>
-        <div class="endpoint"><strong>GET /api/transactions</strong> - Get all transactions</div>
-        <div class="endpoint"><strong>GET /api/transactions/:hash</strong> - Get transaction by hash</div>
-        <div class="endpoint"><strong>POST /api/mine</strong> - Mine a new block</div>
-        <div class="endpoint"><strong>POST /api/submit_transaction</strong> - Submit a transaction</div>
-        <div class="endpoint"><strong>GET /api/balance/:address</strong> - Get address balance</div>
-        <div class="endpoint"><strong>GET /api/stats</strong> - Get network statistics</div>
-        <div class="endpoint"><strong>GET /api/health</strong> - Health check</div>
-        
-        <h3>🔌 WebSocket</h3>
-        <div class="endpoint"><strong>WS /ws</strong> - Real-time blockchain updates</div>
-        
-        <p style="text-align: center; margin-top: 30px; color: #666;">
-            Built with ❤️ using Rust, Axum, and Tokio
-        </p>
-    </div>
-</body>
-</html>"#
-    )
-}
+    // Stop accepting and drain connections on SIGINT/SIGTERM
+    node.shutdown_handle().trigger_on_signal();
+    node.start().await?;
 
-/// Serve static files
-async fn serve_static(Path(file): Path<String>) -> impl IntoResponse {
-    // In a real application, you'd serve actual static files
-    // For now, return a simple response
-    match file.as_str() {
-        "style.css" => (
-            StatusCode::OK,
-            [("content-type", "text/css")],
-            "/* LedgerDB Styles */\nbody { font-family: 'Segoe UI', sans-serif; }"
-        ).into_response(),
-        _ => (
-            StatusCode::NOT_FOUND,
-            "File not found"
-        ).into_response()
+    let server_config = &app_config.server;
+    if let Some(path) = &server_config.unix_socket_path {
+        println!("🧦 LedgerDB API listening on unix socket {}", path.display());
+    }
+    let tls = server_config.tls_self_signed || server_config.tls_cert_path.is_some();
+    let (http, ws) = if tls { ("https", "wss") } else { ("http", "ws") };
+    for addr in node.local_addrs() {
+        println!("🌐 LedgerDB API server listening on {}://{}", http, addr);
+    }
+    if let Some(addr) = node.local_addr() {
+        println!("📊 WebSocket endpoint available at {}://{}/ws", ws, addr);
+        println!("🔗 Blockchain explorer UI at {}://{}", http, addr);
     }
-}
 
-/// WebSocket handler
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    State(state): State<AppState>,
-) -> Response {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
+    // Connections are drained, background work stopped and everything flushed to disk
+    node.stopped().await;
+    node.stop().await?;
+    println!("👋 LedgerDB shut down cleanly");
+
+    Ok(())
 }
 
-/// Handle WebSocket connections
-async fn handle_websocket(socket: WebSocket, state: AppState) {
+/// Run one of the offline maintenance commands and exit
+fn run_command(command: Command, app_config: &config::Config) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Run => unreachable!("`run` starts the server"),
+        Command::FetchSnapshot { .. } => unreachable!("`fetch-snapshot` runs on the async runtime"),
+        Command::VerifyChain => {
+            let report = maintenance::verify_chain(app_config)?;
+            println!(
+                "Chain is valid up to block {} ({}): {} unspent outputs holding {}",
+                report.height,
+                report.tip.to_hex(),
+                report.utxos,
+                report.supply
+            );
+        }
+        Command::VerifyStorage { repair } => {
+            let report = maintenance::verify_storage(app_config, repair)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            let unrepaired = report.issues.iter().filter(|issue| !issue.repaired).count();
+            if unrepaired > 0 {
+                return Err(format!("{} storage issues left; `ledgerdb reindex` rebuilds the indexes", unrepaired).into());
+            }
+        }
+        Command::Export { from, to, output } => {
+            let written = match &output {
+                Some(path) => {
+                    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                    maintenance::export_blocks(app_config, from, to, &mut file)?
+                }
+                None => maintenance::export_blocks(app_config, from, to, &mut std::io::stdout().lock())?,
+            };
+            eprintln!("Exported {} blocks", written);
+        }
+        Command::Import { file } => {
+            let input = std::io::BufReader::new(std::fs::File::open(&file)?);
+            let report = maintenance::import_blocks(app_config, input)?;
+            println!("Imported {} blocks ({} already stored)", report.imported, report.skipped);
+        }
+        Command::Reindex => {
+            let report = maintenance::reindex(app_config)?;
+            println!(
+                "Reindexed {} blocks and {} unspent outputs ({} orphaned blocks left unindexed)",
+                report.height, report.utxos, report.orphaned
+            );
+        }
+        Command::Stats => {
+            println!("{}", serde_json::to_string_pretty(&maintenance::stats(app_config)?)?);
+        }
+        Command::Simulate { wallets, tx_rate, duration, block_interval, median_amount, seed, realtime } => {
+            let params = SimulationParams {
+                wallets,
+                tx_rate,
+                duration,
+                block_interval,
+                median_amount,
+                seed,
+                realtime,
+                ..SimulationParams::default()
+            };
+            println!("{}", serde_json::to_string_pretty(&maintenance::simulate(app_config, params)?)?);
+        }
+        Command::Replay { file } => {
+            let input = std::io::BufReader::new(std::fs::File::open(&file)?);
+            let report = maintenance::replay(app_config, input)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if let Some(divergence) = report.divergence {
+                return Err(format!("Replay diverged at {}", divergence).into());
+            }
+        }
+        Command::Config { action: ConfigCommand::Print } => print!("{}", app_config.to_toml()?),
+    }
+    Ok(())
+}
 
-    let connection_id = manager.add_connection();
-    drop(manager);
-    
-    println!("🔌 New WebSocket connection: {}", connection_id);
-    
-    // Handle the WebSocket connection
-    if let Err(e) = handle_mining_progress_websocket(socket, state.clone()).await {
-        eprintln!("❌ WebSocket error: {}", e);
+/// Download the snapshot at `height` in chunks from `nodes` into `output`, then
+/// check it against the commitment in its manifest
+async fn fetch_snapshot(
+    nodes: &[String],
+    height: Option<u64>,
+    max_in_flight: usize,
+    output: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clients: Vec<ledgerdb_client::Client> = nodes
+        .iter()
+        .map(|url| ledgerdb_client::Client::new(url.as_str()).with_max_response_size(utils::constants::MAX_P2P_MESSAGE_SIZE))
+        .collect();
+    let mut manifest = None;
+    for client in &clients {
+        match client.snapshot_manifest(height).await {
+            Ok(found) => {
+                manifest = Some(found);
+                break;
+            }
+            Err(e) => eprintln!("No manifest from {}: {}", client.base_url(), e),
+        }
     }
-    
-    // Clean up connection
+    let manifest = manifest.ok_or("no node answered with a snapshot manifest")?;
+    eprintln!(
+        "Downloading the UTXO snapshot at height {}: {} bytes in {} chunks",
+        manifest.height,
+        manifest.size,
+        manifest.chunks.len()
+    );
 
-    manager.remove_connection(&connection_id);
-    println!("🔌 WebSocket connection closed: {}", connection_id);
+    let mut chunk_dir = output.as_os_str().to_owned();
+    chunk_dir.push(".chunks");
+    let chunk_dir = PathBuf::from(chunk_dir);
+    let fetched = ledgerdb_client::download_snapshot(&clients, &manifest, &chunk_dir, output, max_in_flight).await?;
+    let snapshot = UtxoSnapshot::read_from(output)?;
+    if *snapshot.commitment.as_bytes() != manifest.commitment.0 {
+        return Err("the downloaded snapshot does not match its manifest's commitment".into());
+    }
+    println!(
+        "Wrote the UTXO snapshot at height {} ({} outputs, {} of {} chunks fetched) to {}",
+        snapshot.height,
+        snapshot.utxos.len(),
+        fetched,
+        manifest.chunks.len(),
+        output.display()
+    );
+    Ok(())
 }
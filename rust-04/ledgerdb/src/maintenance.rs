@@ -0,0 +1,420 @@
+//! Offline maintenance of a node's storage directory.
+//!
+//! These operate on the sled database at `storage.db_path` directly, without the
+//! HTTP server, and back the `ledgerdb verify-chain`, `verify-storage`, `export`,
+//! `import`, `reindex`, `stats`, `simulate` and `replay` commands. sled allows
+//! one process per database, so stop a running node before using them.
+//!
+//! Exports are JSON lines, one block per line in height order; [`import_blocks`]
+//! reads the same format.
+
+use crate::config::Config;
+use crate::core::blockchain::{Blockchain, BlockchainStats};
+use crate::core::{replay_recording, replay_snapshot, Block, ReplayRecorder, ReplayReport};
+use crate::crypto::{block_work, Hash256};
+use crate::error::{BlockchainError, LedgerError, Result};
+use crate::node::{chain_config, default_address};
+use crate::simulation::{self, SimulationParams, SimulationReport};
+use crate::storage::{BlockchainMetadata, IntegrityReport, PersistentStorage, StorageStats};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+/// Outcome of [`verify_chain`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// Height of the last verified block
+    pub height: u64,
+    /// Hash of that block
+    pub tip: Hash256,
+    /// Unspent outputs after replaying every block
+    pub utxos: usize,
+    /// Total amount held by those outputs
+    pub supply: u64,
+}
+
+/// Outcome of [`import_blocks`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Blocks appended to the chain
+    pub imported: u64,
+    /// Blocks already stored with the same hash
+    pub skipped: u64,
+}
+
+/// Outcome of [`reindex`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReindexReport {
+    /// Height of the rebuilt chain's tip
+    pub height: u64,
+    /// Stored blocks that did not extend the rebuilt chain
+    pub orphaned: usize,
+    /// Unspent outputs written to the UTXO index
+    pub utxos: usize,
+}
+
+/// Chain and database statistics, as printed by `ledgerdb stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeStats {
+    pub chain: BlockchainStats,
+    pub storage: StorageStats,
+}
+
+/// Open the configured storage and load the chain stored in it, encoding
+/// addresses for its network from then on
+pub fn open_chain(config: &Config) -> Result<(Arc<PersistentStorage>, Blockchain)> {
+    ledgerdb_types::set_address_network(config.blockchain.network);
+    let storage = Arc::new(PersistentStorage::open(&config.storage)?);
+    let blockchain = Blockchain::with_storage(chain_config(&config.blockchain)?, storage.clone(), default_address())?;
+    Ok((storage, blockchain))
+}
+
+/// Record `blockchain`'s accepted transactions and blocks to `storage.replay_log`, if set
+pub fn record_replay_log(config: &Config, blockchain: &mut Blockchain) -> Result<()> {
+    if let Some(path) = &config.storage.replay_log {
+        blockchain.set_recorder(ReplayRecorder::create(path)?)?;
+    }
+    Ok(())
+}
+
+/// Replay every stored block from genesis, checking links, proof-of-work,
+/// checkpoints, signatures and that each spent output exists
+pub fn verify_chain(config: &Config) -> Result<VerifyReport> {
+    let (storage, blockchain) = open_chain(config)?;
+    let tip = tip_height(&blockchain);
+    let snapshot = replay_snapshot(&blockchain.blocks_through(tip))?;
+    storage.close()?;
+    Ok(VerifyReport {
+        height: snapshot.height,
+        tip: snapshot.block_hash.clone(),
+        utxos: snapshot.utxos.len(),
+        supply: snapshot.total_amount(),
+    })
+}
+
+/// Cross-check the stored blocks, transactions, UTXOs and indexes against each
+/// other without loading the chain; with `repair`, dangling index entries are
+/// dropped and anything else is left for [`reindex`]
+pub fn verify_storage(config: &Config, repair: bool) -> Result<IntegrityReport> {
+    let storage = PersistentStorage::open(&config.storage)?;
+    let report = storage.check_integrity(repair)?;
+    storage.close()?;
+    Ok(report)
+}
+
+/// Write blocks `from..=to` (`to` defaults to the tip) to `out` as JSON lines;
+/// returns the number written
+pub fn export_blocks(config: &Config, from: u64, to: Option<u64>, out: &mut impl Write) -> Result<u64> {
+    let (storage, blockchain) = open_chain(config)?;
+    let tip = tip_height(&blockchain);
+    let to = to.unwrap_or(tip);
+    if from > to || to > tip {
+        return Err(LedgerError::InvalidInput(format!(
+            "cannot export blocks {}..={} from a chain ending at {}", from, to, tip
+        )));
+    }
+
+    for height in from..=to {
+        let block = blockchain
+            .get_block_by_index(height)
+            .ok_or_else(|| BlockchainError::BlockNotFound(format!("height {}", height)))?;
+        serde_json::to_writer(&mut *out, block)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode block {}: {}", height, e)))?;
+        writeln!(out).map_err(|e| LedgerError::Io(format!("Failed to write export: {}", e)))?;
+    }
+    out.flush().map_err(|e| LedgerError::Io(format!("Failed to write export: {}", e)))?;
+    storage.close()?;
+    Ok(to - from + 1)
+}
+
+/// Append the blocks in a JSON-lines export to the stored chain.
+///
+/// Blocks the chain already has are skipped; each new block must extend the tip and
+/// passes the same validation as a mined or submitted block.
+pub fn import_blocks(config: &Config, input: impl BufRead) -> Result<ImportReport> {
+    let (storage, mut blockchain) = open_chain(config)?;
+    let mut report = ImportReport::default();
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| LedgerError::Io(format!("Failed to read import: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let block: Block = serde_json::from_str(&line)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid block on line {}: {}", n + 1, e)))?;
+
+        if let Some(existing) = blockchain.get_block_by_index(block.index) {
+            if existing.hash() != block.hash() {
+                return Err(BlockchainError::InvalidChain(format!(
+                    "block {} on line {} conflicts with the stored chain", block.index, n + 1
+                )).into());
+            }
+            report.skipped += 1;
+        } else if block.index == blockchain.height() {
+            blockchain.add_block(block)?;
+            report.imported += 1;
+        } else {
+            return Err(BlockchainError::InvalidChain(format!(
+                "block {} on line {} does not extend the chain ending at {}",
+                block.index, n + 1, tip_height(&blockchain)
+            )).into());
+        }
+    }
+
+    storage.close()?;
+    Ok(report)
+}
+
+/// Rebuild the height, transaction, UTXO and address indexes from the stored blocks.
+///
+/// The branch linking back to genesis with the most cumulative work becomes the
+/// chain, up to its first invalid block; any other stored block is left in place and
+/// counted as orphaned.
+pub fn reindex(config: &Config) -> Result<ReindexReport> {
+    ledgerdb_types::set_address_network(config.blockchain.network);
+    let storage = Arc::new(PersistentStorage::open(&config.storage)?);
+    let stored = storage.load_stored_blocks()?;
+    storage.clear_indexes()?;
+
+    // With the height index empty this loads only genesis; each block added below is
+    // stored again, which rewrites its index entries
+    let mut blockchain = Blockchain::with_storage(chain_config(&config.blockchain)?, storage.clone(), default_address())?;
+    let genesis = blockchain.get_block_by_index(0).map(|b| b.hash());
+    let candidates = stored
+        .iter()
+        .filter(|block| !(block.index == 0 && Some(block.hash()) == genesis))
+        .count();
+    let mut connected = 0;
+    for block in most_work_branch(&blockchain, stored) {
+        if blockchain.add_block(block).is_err() {
+            break;
+        }
+        connected += 1;
+    }
+    let orphaned = candidates - connected;
+
+    let utxos = blockchain.get_all_utxos();
+    for entry in &utxos {
+        storage.store_utxo(&entry.id(), entry)?;
+    }
+    let stats = blockchain.get_stats();
+    storage.store_metadata(&BlockchainMetadata {
+        height: tip_height(&blockchain),
+        latest_block_hash: stats.latest_block_hash.clone(),
+        total_transactions: stats.total_transactions,
+        genesis_hash: genesis.unwrap_or_else(Hash256::zero),
+        total_supply: stats.total_supply,
+        ..BlockchainMetadata::default()
+    })?;
+    storage.close()?;
+
+    Ok(ReindexReport {
+        height: tip_height(&blockchain),
+        orphaned,
+        utxos: utxos.len(),
+    })
+}
+
+/// Statistics about the stored chain and the database holding it
+pub fn stats(config: &Config) -> Result<NodeStats> {
+    let (storage, blockchain) = open_chain(config)?;
+    let stats = NodeStats {
+        chain: blockchain.get_stats().clone(),
+        storage: storage.get_stats()?,
+    };
+    storage.close()?;
+    Ok(stats)
+}
+
+/// Run a simulation against the stored chain, storing the blocks it mines
+pub fn simulate(config: &Config, params: SimulationParams) -> Result<SimulationReport> {
+    let (storage, mut blockchain) = open_chain(config)?;
+    record_replay_log(config, &mut blockchain)?;
+    let report = simulation::run(&mut blockchain, params);
+    storage.close()?;
+    report
+}
+
+/// Feed a recording made with `storage.replay_log` to the stored chain, which
+/// must be at the height the recording starts from
+pub fn replay(config: &Config, input: impl BufRead) -> Result<ReplayReport> {
+    let (storage, mut blockchain) = open_chain(config)?;
+    let report = replay_recording(&mut blockchain, input);
+    storage.close()?;
+    report
+}
+
+/// The stored blocks from above `blockchain`'s tip to the tip with the most
+/// cumulative work, by the difficulty each header claims; `stored` is in height order
+fn most_work_branch(blockchain: &Blockchain, stored: Vec<Block>) -> Vec<Block> {
+    let Some(tip) = blockchain.get_latest_block().map(|b| b.hash()) else {
+        return Vec::new();
+    };
+    let mut work = HashMap::from([(tip.clone(), blockchain.chain_work())]);
+    let mut best = (blockchain.chain_work(), tip);
+    let mut linked = HashMap::new();
+    for block in stored {
+        let hash = block.hash();
+        let Some(&parent_work) = work.get(&block.header.previous_hash) else {
+            continue;
+        };
+        if work.contains_key(&hash) {
+            continue;
+        }
+        let chain_work = parent_work.saturating_add(block_work(block.header.difficulty));
+        // On equal work the branch stored first is kept
+        if chain_work > best.0 {
+            best = (chain_work, hash.clone());
+        }
+        work.insert(hash.clone(), chain_work);
+        linked.insert(hash, block);
+    }
+
+    let mut branch = Vec::new();
+    let mut hash = best.1;
+    while let Some(block) = linked.remove(&hash) {
+        hash = block.header.previous_hash.clone();
+        branch.push(block);
+    }
+    branch.reverse();
+    branch
+}
+
+fn tip_height(blockchain: &Blockchain) -> u64 {
+    blockchain.get_latest_block().map(|b| b.index).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Address, PublicKey, SignatureAlgorithm};
+    use crate::storage::StorageIssueKind;
+    use std::io::Cursor;
+
+    fn test_config(dir: &std::path::Path) -> Config {
+        let mut config = Config::default();
+        config.storage.db_path = dir.join("db");
+        config.blockchain.initial_difficulty = 1;
+        config
+    }
+
+    /// Store a chain of `blocks` blocks above genesis at `config.storage.db_path`
+    fn mine_chain(config: &Config, blocks: usize) {
+        let (storage, mut blockchain) = open_chain(config).unwrap();
+        for _ in 0..blocks {
+            let block = blockchain.create_block(default_address()).unwrap();
+            blockchain.add_block(block).unwrap();
+        }
+        storage.close().unwrap();
+    }
+
+    #[test]
+    fn test_verify_and_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        mine_chain(&config, 3);
+
+        let report = verify_chain(&config).unwrap();
+        assert_eq!(report.height, 3);
+        assert_eq!(report.utxos, 4);
+
+        let stats = stats(&config).unwrap();
+        assert_eq!(stats.chain.latest_block_hash, report.tip);
+        assert_eq!(stats.chain.total_supply, report.supply);
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = test_config(&dir.path().join("source"));
+        mine_chain(&source, 4);
+
+        let mut export = Vec::new();
+        assert_eq!(export_blocks(&source, 0, None, &mut export).unwrap(), 5);
+        assert!(export_blocks(&source, 2, Some(9), &mut Vec::new()).is_err());
+
+        let target = test_config(&dir.path().join("target"));
+        let report = import_blocks(&target, Cursor::new(&export)).unwrap();
+        assert_eq!(report, ImportReport { imported: 4, skipped: 1 });
+        assert_eq!(verify_chain(&target).unwrap(), verify_chain(&source).unwrap());
+
+        // Importing again changes nothing
+        let report = import_blocks(&target, Cursor::new(&export)).unwrap();
+        assert_eq!(report, ImportReport { imported: 0, skipped: 5 });
+
+        // A partial export past the tip does not link
+        let mut tail = Vec::new();
+        export_blocks(&source, 3, None, &mut tail).unwrap();
+        let fresh = test_config(&dir.path().join("fresh"));
+        assert!(import_blocks(&fresh, Cursor::new(&tail)).is_err());
+    }
+
+    #[test]
+    fn test_verify_storage_repairs_dangling_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        mine_chain(&config, 2);
+        assert!(verify_storage(&config, false).unwrap().is_clean());
+
+        // Index a mined transaction under a block that was never stored
+        {
+            let (storage, blockchain) = open_chain(&config).unwrap();
+            let transaction = blockchain.get_block_by_index(1).unwrap().transactions()[0].clone();
+            storage.store_transaction(&transaction, &Hash256::zero()).unwrap();
+            storage.close().unwrap();
+        }
+
+        let report = verify_storage(&config, false).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, StorageIssueKind::DanglingTxIndex);
+        assert!(!report.issues[0].repaired);
+
+        let report = verify_storage(&config, true).unwrap();
+        assert!(report.issues[0].repaired);
+        assert!(verify_storage(&config, false).unwrap().is_clean());
+        assert_eq!(verify_chain(&config).unwrap().height, 2);
+    }
+
+    #[test]
+    fn test_reindex_rebuilds_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        mine_chain(&config, 3);
+        let before = verify_chain(&config).unwrap();
+
+        PersistentStorage::new(&config.storage.db_path).unwrap().clear_indexes().unwrap();
+        assert_eq!(verify_chain(&config).unwrap().height, 0);
+
+        let report = reindex(&config).unwrap();
+        assert_eq!(report, ReindexReport { height: 3, orphaned: 0, utxos: 4 });
+        assert_eq!(verify_chain(&config).unwrap(), before);
+    }
+
+    #[test]
+    fn test_reindex_follows_most_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        // A stale block 1 stored beside a three-block branch
+        let (storage, mut blockchain) = open_chain(&config).unwrap();
+        let other = Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![7; 33]));
+        let mut stale = blockchain.create_block(other).unwrap();
+        stale.mine().unwrap();
+        storage.store_block(&stale).unwrap();
+        for _ in 0..3 {
+            let block = blockchain.create_block(default_address()).unwrap();
+            blockchain.add_block(block).unwrap();
+        }
+        let work = blockchain.chain_work();
+        drop(blockchain);
+        storage.close().unwrap();
+        drop(storage);
+        let before = verify_chain(&config).unwrap();
+
+        let report = reindex(&config).unwrap();
+        assert_eq!(report, ReindexReport { height: 3, orphaned: 1, utxos: 4 });
+        assert_eq!(verify_chain(&config).unwrap(), before);
+        assert_eq!(open_chain(&config).unwrap().1.chain_work(), work);
+    }
+}
@@ -0,0 +1,754 @@
+//! Embeddable LedgerDB node.
+//!
+//! [`NodeBuilder`] assembles the same pieces the `ledgerdb` binary runs (storage,
+//! blockchain, background tasks, and optionally the HTTP API and a miner) into a
+//! [`Node`] that an application can start and stop itself. Chain activity is
+//! published as [`NodeEvent`]s on a broadcast channel.
+//!
+//! ```no_run
+//! # async fn run() -> ledgerdb::Result<()> {
+//! let mut node = ledgerdb::NodeBuilder::new()
+//!     .storage_path("./my-app-chain")
+//!     .enable_api(false)
+//!     .enable_mining(true)
+//!     .build()?;
+//! let mut events = node.subscribe();
+//! node.start().await?;
+//!
+//! while let Ok(event) = events.recv().await {
+//!     if let ledgerdb::NodeEvent::BlockMined { height, .. } = event {
+//!         println!("mined block {}", height);
+//!         break;
+//!     }
+//! }
+//! node.stop().await
+//! # }
+//! ```
+
+use crate::api::{self, ApiConfig, AppState, Shutdown};
+use crate::auth::UserStore;
+use crate::{p2p, relay, sync};
+use crate::config::{self, Config, ConfigReloader, RuntimeSettings};
+use crate::core::blockchain::{Blockchain, BlockchainConfig};
+use crate::core::{replay_snapshot, SnapshotValidation, UtxoSnapshot};
+use crate::crypto::{pow::MiningControl, Address, Hash256, PublicKey, SignatureAlgorithm};
+use crate::error::{ConfigError, LedgerError, Result};
+use crate::maintenance;
+use crate::storage::{AsyncStorage, PersistentStorage};
+use crate::tasks::{self, RestartPolicy, TaskManager};
+use crate::tenants::TenantManager;
+use crate::watch::WatchList;
+use crate::webhooks;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, watch, RwLock},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+use tracing::{info, warn};
+
+/// Capacity of the node event channel; slow subscribers see `RecvError::Lagged`
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Something that happened on a running node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum NodeEvent {
+    /// Background tasks are running and the API (if enabled) is accepting connections
+    Started,
+    /// The built-in miner appended a block to the chain
+    BlockMined { height: u64, hash: Hash256 },
+    /// The blocks below an imported UTXO snapshot were replayed; `valid` is whether
+    /// they reproduced it
+    SnapshotValidated { height: u64, valid: bool },
+    /// Servers are drained, tasks stopped and storage flushed
+    Stopped,
+}
+
+/// Configures and creates a [`Node`]
+#[derive(Debug, Clone, Default)]
+pub struct NodeBuilder {
+    config: Config,
+    config_file: Option<PathBuf>,
+    storage_path: Option<PathBuf>,
+    enable_api: Option<bool>,
+    enable_mining: Option<bool>,
+    miner_address: Option<Address>,
+    static_files: bool,
+}
+
+impl NodeBuilder {
+    /// A builder using [`Config::default`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `config` for the server, chain and mining settings
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Load the configuration from the TOML file at `path` (with `LEDGER_*` overrides)
+    /// instead of using [`NodeBuilder::config`], and reload it on SIGHUP once started
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// Store the chain at `path` instead of `config.storage.db_path`
+    pub fn storage_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.storage_path = Some(path.into());
+        self
+    }
+
+    /// Serve the HTTP API on the configured listeners (default: on)
+    pub fn enable_api(mut self, enable: bool) -> Self {
+        self.enable_api = Some(enable);
+        self
+    }
+
+    /// Run the built-in miner (default: `config.mining.enabled`)
+    pub fn enable_mining(mut self, enable: bool) -> Self {
+        self.enable_mining = Some(enable);
+        self
+    }
+
+    /// Address credited with block rewards from the built-in miner
+    pub fn miner_address(mut self, address: Address) -> Self {
+        self.miner_address = Some(address);
+        self
+    }
+
+    /// Serve the explorer page at `/` next to the API (default: off)
+    pub fn static_files(mut self, enable: bool) -> Self {
+        self.static_files = enable;
+        self
+    }
+
+    /// Open storage and load the chain; nothing runs until [`Node::start`]
+    pub fn build(mut self) -> Result<Node> {
+        if let Some(path) = &self.config_file {
+            self.config = Config::from_file(path)?;
+        }
+        let enable_api = self.enable_api.unwrap_or(true);
+        let enable_mining = self.enable_mining.unwrap_or(self.config.mining.enabled);
+        if enable_api && !self.config.server.tcp_enabled && self.config.server.unix_socket_path.is_none() {
+            return Err(ConfigError::Invalid("API is enabled but has no TCP or Unix socket listener".to_string()).into());
+        }
+
+        let mut storage_config = self.config.storage.clone();
+        if let Some(path) = self.storage_path {
+            storage_config.db_path = path;
+        }
+        let storage = Arc::new(PersistentStorage::open(&storage_config)?);
+        ledgerdb_types::set_address_network(self.config.blockchain.network);
+        let chain_config = chain_config(&self.config.blockchain)?;
+        let mut blockchain = match &self.config.storage.utxo_snapshot {
+            Some(path) => {
+                let snapshot = UtxoSnapshot::read_from(path)?;
+                info!("Starting from UTXO snapshot at height {} ({} outputs)", snapshot.height, snapshot.utxos.len());
+                Blockchain::with_snapshot(chain_config, storage.clone(), default_address(), snapshot)?
+            }
+            None => Blockchain::with_storage(chain_config, storage.clone(), default_address())?,
+        };
+        maintenance::record_replay_log(&self.config, &mut blockchain)?;
+
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let users = Arc::new(UserStore::new(Some(storage.clone()), self.config.api.session_ttl())?);
+        let api_config = ApiConfig::from_config(&self.config);
+        let tenants = self
+            .config
+            .tenants
+            .enabled
+            .then(|| Arc::new(TenantManager::new(&self.config, api_config.clone(), users.clone())));
+        let state = AppState::new(blockchain, storage, self.config.storage.io_workers, api_config, users, tenants)?;
+
+        let reloader = ConfigReloader::new(self.config_file.clone(), self.config.clone());
+        Ok(Node {
+            config: self.config,
+            reloader: Arc::new(Mutex::new(reloader)),
+            watch_config_file: self.config_file.is_some(),
+            state,
+            events,
+            enable_api,
+            enable_mining,
+            miner_address: self.miner_address.unwrap_or_else(default_address),
+            static_files: self.static_files,
+            servers: Vec::new(),
+            local_addrs: Vec::new(),
+            lifecycle: Lifecycle::Built,
+        })
+    }
+}
+
+/// A LedgerDB node embedded in the current process.
+///
+/// A node is started and stopped once; build a new one to restart. Dropping a
+/// started node without calling [`Node::stop`] leaves storage unflushed.
+#[derive(Debug)]
+pub struct Node {
+    config: Config,
+    reloader: Arc<Mutex<ConfigReloader>>,
+    watch_config_file: bool,
+    state: AppState,
+    events: broadcast::Sender<NodeEvent>,
+    enable_api: bool,
+    enable_mining: bool,
+    miner_address: Address,
+    static_files: bool,
+    servers: Vec<JoinHandle<Result<()>>>,
+    local_addrs: Vec<SocketAddr>,
+    lifecycle: Lifecycle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lifecycle {
+    Built,
+    Running,
+    Stopped,
+}
+
+impl Node {
+    /// Start background tasks, the miner and the API listeners
+    pub async fn start(&mut self) -> Result<()> {
+        if self.lifecycle != Lifecycle::Built {
+            return Err(LedgerError::InvalidInput("Node has already been started".to_string()));
+        }
+        self.lifecycle = Lifecycle::Running;
+
+        tasks::spawn_storage_compaction(&self.state.tasks, self.state.storage.clone())?;
+        if self.watch_config_file {
+            tasks::spawn_config_reload(&self.state.tasks, self.reloader.clone())?;
+        }
+        if let Some(SnapshotValidation::Pending { height, .. }) = self.state.blockchain.read().await.snapshot_validation() {
+            self.spawn_snapshot_validation(*height)?;
+        }
+        webhooks::spawn_chain_events(
+            &self.state.tasks,
+            self.state.blockchain.clone(),
+            self.state.watch.clone(),
+            &self.config.webhooks,
+        )?;
+        sync::spawn(&self.state, &self.config.sync)?;
+        relay::spawn(&self.state, &self.config.sync)?;
+        if self.enable_mining {
+            self.spawn_miner()?;
+        }
+        if let Some(tenants) = &self.state.tenants {
+            tenants.load().await?;
+        }
+        if self.enable_api {
+            self.start_api().await?;
+        }
+
+        info!("LedgerDB node started at height {}", self.state.blockchain.read().await.height());
+        let _ = self.events.send(NodeEvent::Started);
+        Ok(())
+    }
+
+    /// Drain API connections, stop background tasks and flush storage
+    pub async fn stop(&mut self) -> Result<()> {
+        if self.lifecycle != Lifecycle::Running {
+            return Err(LedgerError::InvalidInput("Node is not running".to_string()));
+        }
+        self.lifecycle = Lifecycle::Stopped;
+        let grace = Duration::from_secs(self.config.server.shutdown_timeout);
+        self.state.shutdown.trigger();
+
+        for server in self.servers.drain(..) {
+            match tokio::time::timeout(grace, server).await {
+                Ok(Ok(Ok(()))) => {}
+                Ok(Ok(Err(e))) => warn!("API server failed: {}", e),
+                Ok(Err(e)) => warn!("API server task panicked: {}", e),
+                Err(_) => warn!("API server still draining after {:?}; leaving it behind", grace),
+            }
+        }
+        if let Some(tenants) = &self.state.tenants {
+            tenants.shutdown().await;
+        }
+        self.state.tasks.shutdown(grace).await;
+        self.state.storage.close().await?;
+
+        info!("LedgerDB node stopped");
+        let _ = self.events.send(NodeEvent::Stopped);
+        Ok(())
+    }
+
+    /// Resolves once the node has been asked to stop, e.g. through [`Node::shutdown_handle`].
+    ///
+    /// [`Node::stop`] still has to be called afterwards to drain and flush.
+    pub async fn stopped(&self) {
+        self.state.shutdown.wait().await
+    }
+
+    /// The chain this node serves and mines on
+    pub fn blockchain(&self) -> Arc<RwLock<Blockchain>> {
+        self.state.blockchain.clone()
+    }
+
+    /// The node's persistent storage
+    pub fn storage(&self) -> Arc<PersistentStorage> {
+        self.state.storage.blocking().clone()
+    }
+
+    /// The node's storage for async code, sharing the node's `storage.io_workers`
+    pub fn async_storage(&self) -> AsyncStorage {
+        self.state.storage.clone()
+    }
+
+    /// Supervisor for the node's background tasks
+    pub fn tasks(&self) -> Arc<TaskManager> {
+        self.state.tasks.clone()
+    }
+
+    /// Chains hosted next to this one, when `tenants.enabled`
+    pub fn tenants(&self) -> Option<Arc<TenantManager>> {
+        self.state.tenants.clone()
+    }
+
+    /// Addresses whose activity the node reports over WebSocket and webhooks
+    pub fn watch_list(&self) -> Arc<WatchList> {
+        self.state.watch.clone()
+    }
+
+    /// Handle that stops the node's servers and tasks when triggered
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.state.shutdown.clone()
+    }
+
+    /// Subscribe to node events; events sent before subscribing are not replayed
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Re-read the configuration and apply the settings that can change while running.
+    ///
+    /// Returns the dotted names of the changed settings; fails, changing nothing, if
+    /// the new configuration is invalid or changes a setting that needs a restart.
+    pub fn reload_config(&self) -> Result<Vec<String>> {
+        self.reloader.lock().unwrap().reload()
+    }
+
+    /// Runtime-tunable settings, updated by every successful reload
+    pub fn runtime_settings(&self) -> watch::Receiver<RuntimeSettings> {
+        self.reloader.lock().unwrap().subscribe()
+    }
+
+    /// Address the TCP API is listening on for `server.host:server.port`, once started.
+    ///
+    /// Useful with port `0`, which binds an ephemeral port.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addrs.first().copied()
+    }
+
+    /// Addresses of every TCP API listener, [`Node::local_addr`] first and then
+    /// `p2p.listen_addresses`
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    /// Router serving this node's API, for mounting into an existing axum app
+    pub fn router(&self) -> axum::Router {
+        self.router_builder().build()
+    }
+
+    /// Builder for a router over this node with a chosen set of route groups,
+    /// e.g. without `/admin/...` or with the explorer page
+    pub fn router_builder(&self) -> api::RouterBuilder {
+        api::RouterBuilder::new(self.state.clone())
+    }
+
+    async fn start_api(&mut self) -> Result<()> {
+        let server = &self.config.server;
+        let grace = Duration::from_secs(server.shutdown_timeout);
+        let app = self.router_builder().static_files(self.static_files).build();
+
+        if let Some(path) = server.unix_socket_path.clone() {
+            let (app, shutdown) = (app.clone(), self.state.shutdown.clone());
+            self.servers.push(tokio::spawn(async move { api::serve_unix(&path, app, shutdown, grace).await }));
+        }
+        if !server.tcp_enabled {
+            return Ok(());
+        }
+
+        let addr: SocketAddr = server
+            .address()
+            .parse()
+            .map_err(|e| ConfigError::Invalid(format!("Invalid listen address {}: {}", server.address(), e)))?;
+        // Bind everything before serving anything, so a taken port fails the start cleanly
+        let listeners = std::iter::once(addr)
+            .chain(self.config.p2p.listen_addresses.iter().copied())
+            .map(bind)
+            .collect::<Result<Vec<_>>>()?;
+        self.local_addrs = listeners
+            .iter()
+            .map(|listener| {
+                listener
+                    .local_addr()
+                    .map_err(|e| LedgerError::Io(format!("Failed to read listen address: {}", e)))
+            })
+            .collect::<Result<_>>()?;
+
+        let tls = api::load_tls_config(server).await?;
+        self.state.p2p.set_listeners(&self.local_addrs, self.config.p2p.external_address.clone());
+        p2p::spawn_upnp(&self.state, &self.config.p2p, &self.local_addrs, tls.is_some())?;
+        for listener in listeners {
+            let (tls, app, shutdown) = (tls.clone(), app.clone(), self.state.shutdown.clone());
+            self.servers.push(tokio::spawn(serve_tcp(listener, tls, app, shutdown, grace)));
+        }
+        Ok(())
+    }
+
+    /// Replay the blocks below an imported UTXO snapshot without holding the chain lock
+    fn spawn_snapshot_validation(&self, height: u64) -> Result<()> {
+        let (blockchain, events) = (self.state.blockchain.clone(), self.events.clone());
+        self.state.tasks.spawn("snapshot-validation", RestartPolicy::Never, move |_| {
+            let (blockchain, events) = (blockchain.clone(), events.clone());
+            async move {
+                let history = blockchain.read().await.blocks_through(height);
+                let replayed = tokio::task::spawn_blocking(move || replay_snapshot(&history))
+                    .await
+                    .map_err(|e| LedgerError::Internal(format!("Snapshot validation task failed: {}", e)))?;
+
+                let mut chain = blockchain.write().await;
+                let valid = match chain.finish_snapshot_validation(replayed)? {
+                    SnapshotValidation::Invalid { reason, .. } => {
+                        warn!("UTXO snapshot at height {} is invalid: {}", height, reason);
+                        false
+                    }
+                    _ => {
+                        info!("UTXO snapshot at height {} matches the chain history", height);
+                        true
+                    }
+                };
+                let _ = events.send(NodeEvent::SnapshotValidated { height, valid });
+                Ok(())
+            }
+        })
+    }
+
+    fn spawn_miner(&self) -> Result<()> {
+        spawn_miner(
+            &self.state,
+            self.events.clone(),
+            self.miner_address.clone(),
+            Duration::from_secs(self.config.blockchain.target_block_time.max(1)),
+        )
+    }
+}
+
+/// Run a `miner` task on `state`'s chain that tries for a block every
+/// `block_interval`, publishing each one it adds on `events`
+pub(crate) fn spawn_miner(
+    state: &AppState,
+    events: broadcast::Sender<NodeEvent>,
+    miner_address: Address,
+    block_interval: Duration,
+) -> Result<()> {
+    let (blockchain, storage, miner_events) =
+        (state.blockchain.clone(), state.storage.clone(), state.miner_events.clone());
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(5),
+    };
+    state.tasks.spawn("miner", policy, move |shutdown| {
+        let (blockchain, storage, events, miner_events, miner_address) =
+            (blockchain.clone(), storage.clone(), events.clone(), miner_events.clone(), miner_address.clone());
+        async move {
+            let mut ticker = tokio::time::interval(block_interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown.wait() => return Ok(()),
+                }
+                // Shutdown cancels a search in progress rather than waiting for its nonce
+                let control = MiningControl::new().with_events(miner_events.clone());
+                let canceller = tokio::spawn({
+                    let (control, shutdown) = (control.clone(), shutdown.clone());
+                    async move {
+                        shutdown.wait().await;
+                        control.cancel();
+                    }
+                });
+                let mined = mine_one(&blockchain, &storage, &miner_address, &control).await;
+                canceller.abort();
+                if control.is_cancelled() {
+                    return Ok(());
+                }
+                if let Some(event) = mined? {
+                    let _ = events.send(event);
+                }
+            }
+        }
+    })
+}
+
+/// Mine one block on the current tip without holding the chain lock during proof-of-work.
+///
+/// The search reports through, and can be cancelled by, `control`. Returns `None`
+/// when the tip moved while mining and the block was discarded.
+pub(crate) async fn mine_one(
+    blockchain: &Arc<RwLock<Blockchain>>,
+    storage: &AsyncStorage,
+    miner_address: &Address,
+    control: &MiningControl,
+) -> Result<Option<NodeEvent>> {
+    let mut block = blockchain.write().await.create_block(miner_address.clone())?;
+    let control = control.clone();
+    block = tokio::task::spawn_blocking(move || block.mine_with(&control).map(|_| block))
+        .await
+        .map_err(|e| LedgerError::Internal(format!("Mining task failed: {}", e)))??;
+
+    // Connecting the block persists it, so it runs on a storage worker
+    let mut chain = blockchain.clone().write_owned().await;
+    storage.spawn(move || {
+        let tip = chain.get_latest_block().map(|b| b.hash()).unwrap_or_else(Hash256::zero);
+        if tip != block.header.previous_hash {
+            return Ok(None);
+        }
+        chain.add_block(block)?;
+        let mined = chain.get_latest_block().expect("chain has a tip after add_block");
+        Ok(Some(NodeEvent::BlockMined {
+            height: mined.index,
+            hash: mined.hash(),
+        }))
+    })
+    .await
+}
+
+/// A non-blocking TCP listener on `addr`, for [`serve_tcp`]
+fn bind(addr: SocketAddr) -> Result<std::net::TcpListener> {
+    let listener = std::net::TcpListener::bind(addr)
+        .map_err(|e| LedgerError::Io(format!("Failed to bind {}: {}", addr, e)))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| LedgerError::Io(format!("Failed to configure listener: {}", e)))?;
+    Ok(listener)
+}
+
+/// Serve `app` over plain HTTP or HTTPS on an already-bound listener until `shutdown`
+async fn serve_tcp(
+    listener: std::net::TcpListener,
+    tls: Option<axum_server::tls_rustls::RustlsConfig>,
+    app: axum::Router,
+    shutdown: Shutdown,
+    grace: Duration,
+) -> Result<()> {
+    match tls {
+        Some(tls) => {
+            let handle = axum_server::Handle::new();
+            tokio::spawn({
+                let (handle, shutdown) = (handle.clone(), shutdown.clone());
+                async move {
+                    shutdown.wait().await;
+                    handle.graceful_shutdown(Some(grace));
+                }
+            });
+            axum_server::from_tcp_rustls(listener, tls)
+                .handle(handle)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .map_err(|e| LedgerError::Network(format!("HTTPS server failed: {}", e)))
+        }
+        None => {
+            let listener = tokio::net::TcpListener::from_std(listener)
+                .map_err(|e| LedgerError::Io(format!("Failed to register listener: {}", e)))?;
+            let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).with_graceful_shutdown({
+                let shutdown = shutdown.clone();
+                async move { shutdown.wait().await }
+            });
+            match api::drain_with_timeout(server, &shutdown, grace).await {
+                Some(result) => result.map_err(|e| LedgerError::Network(format!("HTTP server failed: {}", e))),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// Chain parameters for the node, taken from the application config
+pub(crate) fn chain_config(config: &config::BlockchainConfig) -> Result<BlockchainConfig> {
+    Ok(BlockchainConfig {
+        network: config.network,
+        target_block_time: config.target_block_time,
+        difficulty_adjustment_interval: config.difficulty_adjustment_interval,
+        max_block_size: config.max_block_size as u64,
+        reward_schedule: config.effective_reward_schedule(),
+        max_transactions_per_block: config.max_transactions_per_block as u32,
+        initial_difficulty: config.initial_difficulty,
+        checkpoints: config.checkpoint_hashes()?,
+        mempool_expiry: config.mempool_expiry_hours.saturating_mul(60 * 60),
+        ..BlockchainConfig::default()
+    })
+}
+
+/// Genesis and default miner address, matching the `ledgerdb` binary
+pub(crate) fn default_address() -> Address {
+    Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![0u8; 33]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.server.host = "127.0.0.1".to_string();
+        config.server.port = 0;
+        config.server.shutdown_timeout = 1;
+        config.blockchain.initial_difficulty = 1;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_builder_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = NodeBuilder::new()
+            .config(test_config())
+            .storage_path(dir.path().join("db"))
+            .enable_api(false)
+            .build()
+            .unwrap();
+        assert!(!node.enable_api);
+        assert!(node.enable_mining, "mining follows config.mining.enabled by default");
+        assert_eq!(node.blockchain().read().await.height(), 1);
+
+        let mut config = test_config();
+        config.server.tcp_enabled = false;
+        assert!(NodeBuilder::new().config(config).storage_path(dir.path().join("other")).build().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_api_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut node = NodeBuilder::new()
+            .config(test_config())
+            .storage_path(dir.path().join("db"))
+            .enable_mining(false)
+            .build()
+            .unwrap();
+        let mut events = node.subscribe();
+        node.start().await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), NodeEvent::Started);
+        assert!(node.start().await.is_err());
+
+        let addr = node.local_addr().expect("TCP listener bound");
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        node.stop().await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), NodeEvent::Stopped);
+        assert!(node.stop().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_extra_listen_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config();
+        config.p2p.listen_addresses = vec!["127.0.0.1:0".parse().unwrap()];
+        config.p2p.external_address = Some("http://203.0.113.7:3000".to_string());
+        let mut node = NodeBuilder::new()
+            .config(config)
+            .storage_path(dir.path().join("db"))
+            .enable_mining(false)
+            .build()
+            .unwrap();
+        node.start().await.unwrap();
+
+        let addrs = node.local_addrs().to_vec();
+        assert_eq!(addrs.len(), 2);
+        assert_ne!(addrs[0], addrs[1]);
+        for addr in &addrs {
+            let client = ledgerdb_client::Client::new(format!("http://{}", addr));
+            let addresses = client.node_addresses().await.unwrap();
+            assert_eq!(addresses.listen, addrs.iter().map(SocketAddr::to_string).collect::<Vec<_>>());
+            assert_eq!(addresses.external.as_deref(), Some("http://203.0.113.7:3000"));
+            assert_eq!(addresses.upnp, None);
+        }
+        node.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_from_utxo_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("utxo.snapshot");
+        {
+            let node = NodeBuilder::new()
+                .config(test_config())
+                .storage_path(dir.path().join("db"))
+                .enable_api(false)
+                .enable_mining(false)
+                .build()
+                .unwrap();
+            let chain = node.blockchain();
+            let mut chain = chain.write().await;
+            for _ in 0..3 {
+                let block = chain.create_block(default_address()).unwrap();
+                chain.add_block(block).unwrap();
+            }
+            chain.utxo_snapshot(2).unwrap().write_to(&snapshot_path).unwrap();
+            node.storage().close().unwrap();
+        }
+
+        let mut config = test_config();
+        config.storage.utxo_snapshot = Some(snapshot_path);
+        let mut node = NodeBuilder::new()
+            .config(config)
+            .storage_path(dir.path().join("db"))
+            .enable_api(false)
+            .enable_mining(false)
+            .build()
+            .unwrap();
+        assert_eq!(node.blockchain().read().await.height(), 4);
+        let mut events = node.subscribe();
+        node.start().await.unwrap();
+
+        let validated = loop {
+            match events.recv().await.unwrap() {
+                event @ NodeEvent::SnapshotValidated { .. } => break event,
+                _ => continue,
+            }
+        };
+        assert_eq!(validated, NodeEvent::SnapshotValidated { height: 2, valid: true });
+        node.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_miner_publishes_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut node = NodeBuilder::new()
+            .config(test_config())
+            .storage_path(dir.path().join("db"))
+            .enable_api(false)
+            .enable_mining(true)
+            .build()
+            .unwrap();
+        let mut events = node.subscribe();
+        node.start().await.unwrap();
+
+        let mined = loop {
+            match events.recv().await.unwrap() {
+                NodeEvent::BlockMined { height, hash } => break (height, hash),
+                _ => continue,
+            }
+        };
+        let chain = node.blockchain();
+        assert_eq!(chain.read().await.get_block_by_index(mined.0).map(|b| b.hash()), Some(mined.1));
+
+        node.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+//! Reachability for other nodes.
+//!
+//! Nodes sync from each other over the HTTP API (see [`crate::sync`]), so a node
+//! is reachable when another can connect to one of its API listeners. Besides
+//! `server.host:server.port` the node listens on every `p2p.listen_addresses`
+//! entry. A node behind a home router can set `p2p.upnp` to have the router forward
+//! those ports to it; the `upnp` task renews the mappings halfway through each
+//! lease and removes them when the node stops. [`Reachability`] gathers the
+//! listeners, the external URL other nodes should use and the mapping for
+//! `/api/p2p/addresses`.
+
+use crate::api::AppState;
+use crate::config::P2pConfig;
+use crate::error::Result;
+use std::net::SocketAddr;
+use std::sync::RwLock;
+
+pub use ledgerdb_types::{NodeAddresses, UpnpMapping};
+
+/// Where other nodes can reach this one
+#[derive(Debug, Default)]
+pub struct Reachability {
+    addresses: RwLock<NodeAddresses>,
+}
+
+impl Reachability {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn addresses(&self) -> NodeAddresses {
+        self.addresses.read().unwrap().clone()
+    }
+
+    /// Record the bound TCP listeners and the configured external URL
+    pub(crate) fn set_listeners(&self, listeners: &[SocketAddr], external: Option<String>) {
+        self.update(|addresses| {
+            addresses.listen = listeners.iter().map(SocketAddr::to_string).collect();
+            addresses.external = external;
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut NodeAddresses)) {
+        change(&mut self.addresses.write().unwrap());
+    }
+}
+
+/// Keep the local router forwarding the ports of `listeners` to this node while it
+/// runs, if `config.upnp` is set. Loopback listeners are left out; the advertised
+/// URL uses the first port and `https` when `tls`.
+#[cfg(feature = "upnp")]
+pub fn spawn_upnp(state: &AppState, config: &P2pConfig, listeners: &[SocketAddr], tls: bool) -> Result<()> {
+    use crate::tasks::RestartPolicy;
+    use std::time::Duration;
+
+    if !config.upnp {
+        return Ok(());
+    }
+    let listeners: Vec<SocketAddr> = listeners.iter().copied().filter(|addr| !addr.ip().is_loopback()).collect();
+    if listeners.is_empty() {
+        tracing::warn!("p2p.upnp is set but the API only listens on loopback; nothing to forward");
+        return Ok(());
+    }
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(60),
+    };
+    let mapping = upnp::Mapping {
+        listeners,
+        lease_seconds: config.upnp_lease_seconds,
+        scheme: if tls { "https" } else { "http" },
+        advertise: config.external_address.is_none(),
+    };
+    let reachability = state.p2p.clone();
+    state.tasks.spawn("upnp", policy, move |shutdown| {
+        let (reachability, mapping) = (reachability.clone(), mapping.clone());
+        async move { mapping.run(&reachability, &shutdown).await }
+    })
+}
+
+#[cfg(not(feature = "upnp"))]
+pub fn spawn_upnp(_state: &AppState, config: &P2pConfig, _listeners: &[SocketAddr], _tls: bool) -> Result<()> {
+    if !config.upnp {
+        return Ok(());
+    }
+    Err(crate::error::LedgerError::Config(
+        "p2p.upnp needs ledgerdb built with the `upnp` feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "upnp")]
+mod upnp {
+    use super::{Reachability, UpnpMapping};
+    use crate::api::Shutdown;
+    use crate::error::{LedgerError, Result};
+    use chrono::Utc;
+    use igd_next::aio::tokio::search_gateway;
+    use igd_next::{PortMappingProtocol, SearchOptions};
+    use std::net::{IpAddr, SocketAddr, UdpSocket};
+    use std::time::Duration;
+    use tracing::{info, warn};
+
+    /// Ports to forward and how to advertise them
+    #[derive(Debug, Clone)]
+    pub(super) struct Mapping {
+        pub listeners: Vec<SocketAddr>,
+        pub lease_seconds: u32,
+        pub scheme: &'static str,
+        /// Advertise the router's external address, there being no configured one
+        pub advertise: bool,
+    }
+
+    impl Mapping {
+        /// Map the ports and renew them until `shutdown`, then remove them
+        pub async fn run(&self, reachability: &Reachability, shutdown: &Shutdown) -> Result<()> {
+            let gateway = search_gateway(SearchOptions::default())
+                .await
+                .map_err(|e| LedgerError::Network(format!("No UPnP gateway found: {}", e)))?;
+            let local_ip = local_ip_towards(gateway.addr)
+                .map_err(|e| LedgerError::Network(format!("No route to UPnP gateway {}: {}", gateway.addr, e)))?;
+            let ports: Vec<u16> = self.listeners.iter().map(SocketAddr::port).collect();
+
+            loop {
+                let external_ip = gateway
+                    .get_external_ip()
+                    .await
+                    .map_err(|e| LedgerError::Network(format!("UPnP gateway {} has no external address: {}", gateway.addr, e)))?;
+                for listener in &self.listeners {
+                    let ip = if listener.ip().is_unspecified() { local_ip } else { listener.ip() };
+                    gateway
+                        .add_port(PortMappingProtocol::TCP, listener.port(), SocketAddr::new(ip, listener.port()), self.lease_seconds, "ledgerdb")
+                        .await
+                        .map_err(|e| LedgerError::Network(format!("UPnP mapping of port {} failed: {}", listener.port(), e)))?;
+                }
+                reachability.update(|addresses| {
+                    if self.advertise {
+                        addresses.external = Some(format!("{}://{}", self.scheme, SocketAddr::new(external_ip, ports[0])));
+                    }
+                    addresses.upnp = Some(UpnpMapping {
+                        gateway: gateway.addr.to_string(),
+                        external_ip: external_ip.to_string(),
+                        ports: ports.clone(),
+                        expires_at: Utc::now() + chrono::Duration::seconds(self.lease_seconds as i64),
+                    });
+                });
+                info!("UPnP gateway {} forwards {}:{:?} to this node", gateway.addr, external_ip, ports);
+
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(self.lease_seconds as u64 / 2)) => {}
+                    _ = shutdown.wait() => break,
+                }
+            }
+
+            for port in &ports {
+                if let Err(e) = gateway.remove_port(PortMappingProtocol::TCP, *port).await {
+                    warn!("Failed to remove UPnP mapping of port {}: {}", port, e);
+                }
+            }
+            reachability.update(|addresses| {
+                addresses.upnp = None;
+                if self.advertise {
+                    addresses.external = None;
+                }
+            });
+            Ok(())
+        }
+    }
+
+    /// This host's address on the route to `gateway`, for listeners bound to all
+    /// interfaces; connecting a UDP socket sends nothing
+    fn local_ip_towards(gateway: SocketAddr) -> std::io::Result<IpAddr> {
+        let socket = UdpSocket::bind(SocketAddr::new(
+            if gateway.is_ipv4() { IpAddr::from([0u8; 4]) } else { IpAddr::from([0u16; 8]) },
+            0,
+        ))?;
+        socket.connect(gateway)?;
+        Ok(socket.local_addr()?.ip())
+    }
+}
@@ -0,0 +1,195 @@
+//! Peer address book.
+//!
+//! A [`PeerStore`] remembers every peer the node has heard of: when it was last
+//! seen, the latency of its last ping and a misbehavior score. Each offence adds to
+//! the score, and a peer whose score reaches [`BAN_SCORE_THRESHOLD`] is banned for
+//! [`MAX_BAN_TIME`]. Admins ban and unban peers by hand through `/admin/peers`.
+//! Entries are persisted, so bans outlive a restart.
+
+use crate::error::Result;
+use crate::storage::PersistentStorage;
+use crate::utils::constants::{BAN_SCORE_THRESHOLD, MAX_BAN_TIME};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+pub use ledgerdb_types::{BanRequest, PeerRecord};
+
+/// Known peers, their health and their bans
+#[derive(Debug)]
+pub struct PeerStore {
+    storage: Option<Arc<PersistentStorage>>,
+    peers: RwLock<HashMap<SocketAddr, PeerRecord>>,
+}
+
+impl PeerStore {
+    /// Load the address book from `storage`, or start empty without it
+    pub fn new(storage: Option<Arc<PersistentStorage>>) -> Result<Self> {
+        let mut peers = HashMap::new();
+        if let Some(storage) = &storage {
+            for peer in storage.load_peers()? {
+                match peer.address.parse() {
+                    Ok(address) => {
+                        peers.insert(address, peer);
+                    }
+                    Err(_) => tracing::warn!("ignoring stored peer with invalid address {}", peer.address),
+                }
+            }
+        }
+        Ok(Self { storage, peers: RwLock::new(peers) })
+    }
+
+    /// Note a successful contact with `address`, and the round trip of its last
+    /// ping if one was timed
+    pub fn record_seen(&self, address: SocketAddr, latency: Option<Duration>) -> Result<PeerRecord> {
+        self.update(address, |peer| {
+            peer.last_seen = Some(Utc::now());
+            if let Some(latency) = latency {
+                peer.latency_ms = Some(latency.as_millis() as u64);
+            }
+        })
+    }
+
+    /// Add `score` to `address`'s misbehavior score, banning it for
+    /// [`MAX_BAN_TIME`] once the score reaches [`BAN_SCORE_THRESHOLD`]
+    pub fn misbehaved(&self, address: SocketAddr, score: u32, reason: &str) -> Result<PeerRecord> {
+        let now = Utc::now();
+        self.update(address, |peer| {
+            if peer.is_banned_at(now) {
+                return;
+            }
+            peer.misbehavior_score = peer.misbehavior_score.saturating_add(score);
+            if peer.misbehavior_score >= BAN_SCORE_THRESHOLD {
+                tracing::info!("banning peer {}: {}", peer.address, reason);
+                ban(peer, now, MAX_BAN_TIME, Some(reason.to_string()));
+            }
+        })
+    }
+
+    /// Ban `address` for `seconds`, at most and by default [`MAX_BAN_TIME`]
+    pub fn ban(&self, address: SocketAddr, seconds: Option<u64>, reason: Option<String>) -> Result<PeerRecord> {
+        let seconds = seconds.unwrap_or(MAX_BAN_TIME).min(MAX_BAN_TIME);
+        self.update(address, |peer| ban(peer, Utc::now(), seconds, reason))
+    }
+
+    /// Lift `address`'s ban and clear its score; returns whether it was banned
+    pub fn unban(&self, address: SocketAddr) -> Result<bool> {
+        let mut peers = self.peers.write().unwrap();
+        let Some(peer) = peers.get(&address) else {
+            return Ok(false);
+        };
+        let was_banned = peer.is_banned_at(Utc::now());
+        let peer = PeerRecord { misbehavior_score: 0, banned_until: None, ban_reason: None, ..peer.clone() };
+        if let Some(storage) = &self.storage {
+            storage.store_peer(&peer)?;
+        }
+        peers.insert(address, peer);
+        Ok(was_banned)
+    }
+
+    /// Whether connections to and from `address` should be refused
+    pub fn is_banned(&self, address: &SocketAddr) -> bool {
+        self.peers.read().unwrap().get(address).is_some_and(|peer| peer.is_banned_at(Utc::now()))
+    }
+
+    pub fn get(&self, address: &SocketAddr) -> Option<PeerRecord> {
+        self.peers.read().unwrap().get(address).cloned()
+    }
+
+    /// Every known peer, ordered by address
+    pub fn list(&self) -> Vec<PeerRecord> {
+        let peers = self.peers.read().unwrap();
+        let mut addresses: Vec<&SocketAddr> = peers.keys().collect();
+        addresses.sort();
+        addresses.into_iter().map(|address| peers[address].clone()).collect()
+    }
+
+    /// Apply `change` to `address`'s entry, creating it if needed, and persist it
+    fn update(&self, address: SocketAddr, change: impl FnOnce(&mut PeerRecord)) -> Result<PeerRecord> {
+        let mut peers = self.peers.write().unwrap();
+        let mut peer = peers.get(&address).cloned().unwrap_or_else(|| PeerRecord {
+            address: address.to_string(),
+            last_seen: None,
+            latency_ms: None,
+            misbehavior_score: 0,
+            banned_until: None,
+            ban_reason: None,
+        });
+        change(&mut peer);
+        if let Some(storage) = &self.storage {
+            storage.store_peer(&peer)?;
+        }
+        peers.insert(address, peer.clone());
+        Ok(peer)
+    }
+}
+
+/// Ban `peer` for `seconds` from `now`, starting its score afresh
+fn ban(peer: &mut PeerRecord, now: DateTime<Utc>, seconds: u64, reason: Option<String>) {
+    peer.banned_until = Some(now + chrono::Duration::seconds(seconds as i64));
+    peer.ban_reason = reason;
+    peer.misbehavior_score = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_misbehavior_bans_at_threshold() {
+        let peers = PeerStore::new(None).unwrap();
+        let record = peers.misbehaved(peer(1), BAN_SCORE_THRESHOLD - 1, "invalid block").unwrap();
+        assert_eq!(record.misbehavior_score, BAN_SCORE_THRESHOLD - 1);
+        assert!(!peers.is_banned(&peer(1)));
+
+        let record = peers.misbehaved(peer(1), 1, "invalid block").unwrap();
+        assert!(peers.is_banned(&peer(1)));
+        assert_eq!(record.misbehavior_score, 0);
+        assert_eq!(record.ban_reason.as_deref(), Some("invalid block"));
+        let ban_time = record.banned_until.unwrap() - Utc::now();
+        assert!(ban_time <= chrono::Duration::seconds(MAX_BAN_TIME as i64));
+        assert!(!peers.is_banned(&peer(2)));
+
+        assert!(peers.unban(peer(1)).unwrap());
+        assert!(!peers.unban(peer(1)).unwrap());
+        assert!(!peers.is_banned(&peer(1)));
+    }
+
+    #[test]
+    fn test_ban_is_capped() {
+        let peers = PeerStore::new(None).unwrap();
+        let record = peers.ban(peer(1), Some(10 * MAX_BAN_TIME), None).unwrap();
+        let ban_time = record.banned_until.unwrap() - Utc::now();
+        assert!(ban_time <= chrono::Duration::seconds(MAX_BAN_TIME as i64));
+        assert!(peers.is_banned(&peer(1)));
+    }
+
+    #[test]
+    fn test_expired_ban_is_lifted() {
+        let peers = PeerStore::new(None).unwrap();
+        peers.ban(peer(1), Some(0), None).unwrap();
+        assert!(!peers.is_banned(&peer(1)));
+        assert!(!peers.unban(peer(1)).unwrap());
+    }
+
+    #[test]
+    fn test_peers_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(PersistentStorage::new(dir.path().join("db")).unwrap());
+        let peers = PeerStore::new(Some(storage.clone())).unwrap();
+        peers.record_seen(peer(2), Some(Duration::from_millis(42))).unwrap();
+        peers.ban(peer(1), None, Some("spam".to_string())).unwrap();
+
+        let reopened = PeerStore::new(Some(storage)).unwrap();
+        assert_eq!(reopened.list(), peers.list());
+        assert_eq!(reopened.list()[0].address, "10.0.0.1:1");
+        assert_eq!(reopened.get(&peer(2)).unwrap().latency_ms, Some(42));
+        assert!(reopened.is_banned(&peer(1)));
+    }
+}
@@ -0,0 +1,123 @@
+//! Compact block relay.
+//!
+//! When the chain gains blocks, the `block-relay` task announces them to every
+//! `sync.peers` node as [`CompactBlock`]s on `POST /api/blocks/compact`: the
+//! header and a short ID per transaction instead of the transactions themselves.
+//! A peer that shares this node's mempool rebuilds the block from its own pool; one
+//! that answers `incomplete` is sent the full block on `/api/blocks/submit`.
+//!
+//! Nothing is announced during the initial block download, and a tip change
+//! announces at most [`MAX_ANNOUNCED_BLOCKS`] blocks. Banned peers are skipped.
+
+use crate::api::{AppState, Shutdown};
+use crate::config::SyncConfig;
+use crate::core::{compact_block, Block, Blockchain, CompactBlock};
+use crate::crypto::Hash256;
+use crate::error::{LedgerError, Result};
+use crate::sync::{SyncPeer, SyncPhase};
+use crate::tasks::RestartPolicy;
+use futures_util::future::join_all;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How often the tip is checked for blocks to announce
+const RELAY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Blocks announced at most per tip change; a peer further behind catches up
+/// through its initial block download
+pub const MAX_ANNOUNCED_BLOCKS: usize = 16;
+
+/// Announce new blocks to `config.peers` in the background; does nothing without
+/// peers or with `config.announce_blocks` off
+pub fn spawn(state: &AppState, config: &SyncConfig) -> Result<()> {
+    if config.peers.is_empty() || !config.announce_blocks {
+        return Ok(());
+    }
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(30),
+    };
+    let (worker, config) = (state.clone(), config.clone());
+    state.tasks.spawn("block-relay", policy, move |shutdown| {
+        let (state, config) = (worker.clone(), config.clone());
+        async move { relay_blocks(&state, &config, &shutdown).await }
+    })
+}
+
+async fn relay_blocks(state: &AppState, config: &SyncConfig, shutdown: &Shutdown) -> Result<()> {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .build()
+        .map_err(|e| LedgerError::Network(e.to_string()))?;
+    let peers = join_all(config.peers.iter().map(|url| SyncPeer::new(url, http.clone()))).await;
+
+    // Blocks the node had before starting are the peers' to sync, not to announce
+    let mut announced = tip_hash(&*state.blockchain.read().await);
+    let mut ticker = tokio::time::interval(RELAY_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.wait() => return Ok(()),
+        }
+        let blocks = {
+            let blockchain = state.blockchain.read().await;
+            if matches!(state.sync.status().phase, SyncPhase::Headers | SyncPhase::Blocks) {
+                announced = tip_hash(&blockchain);
+                continue;
+            }
+            blocks_after(&blockchain, &announced)
+        };
+        let Some(tip) = blocks.last() else {
+            continue;
+        };
+        announced = tip.hash();
+
+        for block in &blocks {
+            let compact = compact_block(block, rand::random())?;
+            let peers = peers.iter().filter(|peer| !peer.is_banned(state));
+            join_all(peers.map(|peer| announce(peer, block, &compact))).await;
+        }
+    }
+}
+
+/// Announce `block` to `peer` as `compact`, sending it in full if the peer lacks
+/// some of its transactions
+async fn announce(peer: &SyncPeer, block: &Block, compact: &CompactBlock) {
+    let client = &peer.client;
+    let result = match client.submit_compact_block(compact).await {
+        Ok(response) if response.status == "incomplete" => {
+            debug!(
+                "{} lacks {} transactions of block {}; sending it in full",
+                client.base_url(),
+                response.missing_transactions.len(),
+                response.hash
+            );
+            client.submit_block(block).await.map(|_| ())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Announcing block {} to {} failed: {}", block.index, client.base_url(), e);
+    }
+}
+
+/// Main-chain blocks above the one hashing to `announced`, oldest first, at most
+/// [`MAX_ANNOUNCED_BLOCKS`] of the newest
+fn blocks_after(blockchain: &Blockchain, announced: &Hash256) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut next = blockchain.get_latest_block();
+    while let Some(block) = next {
+        if block.hash() == *announced || blocks.len() == MAX_ANNOUNCED_BLOCKS {
+            break;
+        }
+        blocks.push(block.clone());
+        next = block.index.checked_sub(1).and_then(|height| blockchain.get_block_by_index(height));
+    }
+    blocks.reverse();
+    blocks
+}
+
+fn tip_hash(blockchain: &Blockchain) -> Hash256 {
+    blockchain.get_latest_block().map(Block::hash).unwrap_or_else(Hash256::zero)
+}
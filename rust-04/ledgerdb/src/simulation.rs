@@ -0,0 +1,524 @@
+//! Synthetic load for demos and benchmark datasets.
+//!
+//! A [`Simulation`] creates random wallets and submits a stream of signed,
+//! valid transactions between them: arrivals are a Poisson process at
+//! `tx_rate` per second, amounts are log-normal around `median_amount` and
+//! fees are spread between the chain's minimum and `max_fee`. A block is mined
+//! every `block_interval` seconds, paying the first wallet, which is funded by
+//! mining [`COINBASE_MATURITY`] blocks before the run starts.
+//!
+//! Wallets only spend confirmed outputs that no pending transaction spends, and
+//! lock each payment to the next block with
+//! [`Blockchain::fee_sniping_lock_time`], so everything submitted passes
+//! [`Blockchain::check_transaction`]. [`run`] drives
+//! a simulation offline for `ledgerdb simulate`, on a simulated clock unless
+//! `realtime` is set; [`spawn`] runs one on a live node as a background task.
+
+use crate::api::{AppState, Shutdown};
+use crate::core::blockchain::{Blockchain, UtxoEntry, UtxoId};
+use crate::core::{PartiallySignedTransaction, Transaction, TransactionInput, TransactionOutput};
+use crate::crypto::{Address, KeyPair, MiningControl, SignatureAlgorithm};
+use crate::error::{LedgerError, Result};
+use crate::node;
+use crate::tasks::RestartPolicy;
+use crate::utils::constants::COINBASE_MATURITY;
+use crate::utils::{Clock, MockClock, SystemClock};
+use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+pub use ledgerdb_types::{SimulationParams, SimulationReport, SimulationStarted};
+
+/// Most wallets a simulation creates
+pub const MAX_WALLETS: usize = 10_000;
+
+/// What happens next in a simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// A wallet pays another
+    Transaction,
+    /// A block is mined
+    Block,
+}
+
+/// Check `params` for values a simulation cannot run with
+pub fn check_params(params: &SimulationParams) -> std::result::Result<(), String> {
+    if !(2..=MAX_WALLETS).contains(&params.wallets) {
+        return Err(format!("wallets must be between 2 and {}", MAX_WALLETS));
+    }
+    if !(params.tx_rate.is_finite() && params.tx_rate > 0.0) {
+        return Err("tx_rate must be a positive number".to_string());
+    }
+    if params.duration == 0 {
+        return Err("duration must be at least one second".to_string());
+    }
+    if params.block_interval == Some(0) {
+        return Err("block_interval must be at least one second".to_string());
+    }
+    if params.median_amount == 0 {
+        return Err("median_amount must be positive".to_string());
+    }
+    if !(params.amount_spread.is_finite() && params.amount_spread >= 0.0) {
+        return Err("amount_spread must be zero or more".to_string());
+    }
+    Ok(())
+}
+
+/// Wallets, draws and counters of one simulation run
+#[derive(Debug)]
+pub struct Simulation {
+    params: SimulationParams,
+    seed: u64,
+    rng: StdRng,
+    wallets: Vec<KeyPair>,
+    /// Outputs spent by transactions this simulation has in the mempool
+    reserved: HashSet<UtxoId>,
+    block_interval: f64,
+    min_fee: u64,
+    next_transaction: f64,
+    next_block: f64,
+    report: SimulationReport,
+}
+
+impl Simulation {
+    /// Create the wallets for a run against `chain`, drawing a seed unless `params` has one
+    pub fn new(params: SimulationParams, chain: &Blockchain) -> Result<Self> {
+        check_params(&params).map_err(LedgerError::InvalidInput)?;
+        let seed = params.seed.unwrap_or_else(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let wallets = (0..params.wallets)
+            .map(|_| KeyPair::generate(&mut rng, SignatureAlgorithm::EcdsaSecp256k1))
+            .collect::<Result<Vec<_>>>()?;
+        let block_interval = params.block_interval.unwrap_or(chain.config.target_block_time).max(1) as f64;
+        let min_fee = chain.config.min_transaction_fee;
+        let height = chain.height();
+
+        let mut simulation = Self {
+            report: SimulationReport {
+                seed,
+                wallets: wallets.len(),
+                submitted: 0,
+                rejected: 0,
+                unfunded: 0,
+                volume: 0,
+                fees: 0,
+                blocks: 0,
+                start_height: height,
+                end_height: height,
+                pending: 0,
+            },
+            params,
+            seed,
+            rng,
+            wallets,
+            reserved: HashSet::new(),
+            block_interval,
+            min_fee,
+            next_transaction: 0.0,
+            next_block: block_interval,
+        };
+        simulation.next_transaction = simulation.arrival_gap();
+        Ok(simulation)
+    }
+
+    /// Seed of this run's draws
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The wallet block rewards are paid to
+    pub fn miner_address(&self) -> &Address {
+        self.wallets[0].address()
+    }
+
+    /// Seconds of simulated time between blocks
+    pub fn block_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.block_interval)
+    }
+
+    /// The next step and the simulated time in seconds it happens at, or
+    /// `None` once the run's duration is over
+    pub fn next_step(&mut self) -> Option<(f64, Step)> {
+        let (at, step) = if self.next_transaction < self.next_block {
+            (self.next_transaction, Step::Transaction)
+        } else {
+            (self.next_block, Step::Block)
+        };
+        if at > self.params.duration as f64 {
+            return None;
+        }
+        match step {
+            Step::Transaction => self.next_transaction += self.arrival_gap(),
+            Step::Block => self.next_block += self.block_interval,
+        }
+        Some((at, step))
+    }
+
+    /// Build a payment between two random wallets and submit it to `chain`'s mempool
+    pub fn submit_transaction(&mut self, chain: &mut Blockchain) -> Result<()> {
+        let amount = self.draw_amount();
+        let fee = self.rng.random_range(self.min_fee..=self.params.max_fee.max(self.min_fee));
+
+        // Start from a random wallet and take the first that can pay anything
+        let start = self.rng.random_range(0..self.wallets.len());
+        let funded = (0..self.wallets.len())
+            .map(|offset| (start + offset) % self.wallets.len())
+            .find_map(|sender| {
+                let coins = self.spendable(chain, sender);
+                let available: u64 = coins.iter().map(|utxo| utxo.output.amount).sum();
+                (available > fee).then_some((sender, coins, available))
+            });
+        let Some((sender, coins, available)) = funded else {
+            self.report.unfunded += 1;
+            return Ok(());
+        };
+        let amount = amount.min(available - fee);
+
+        let mut recipient = self.rng.random_range(0..self.wallets.len() - 1);
+        if recipient >= sender {
+            recipient += 1;
+        }
+
+        let transaction = self.pay(sender, recipient, &coins, amount, fee, chain.fee_sniping_lock_time())?;
+        let validation = chain.check_transaction(&transaction);
+        if !validation.valid {
+            debug!("Simulated transaction {} refused: {}", validation.hash, validation.errors.join("; "));
+            self.report.rejected += 1;
+            return Ok(());
+        }
+        let spent: Vec<UtxoId> = transaction.inputs.iter()
+            .map(|input| UtxoId::new(input.previous_tx_hash.clone(), input.output_index))
+            .collect();
+        match chain.add_transaction_to_pool(transaction) {
+            Ok(()) => {
+                self.reserved.extend(spent);
+                self.report.submitted += 1;
+                self.report.volume += amount;
+                self.report.fees += fee;
+            }
+            Err(e) => {
+                debug!("Simulated transaction refused by the mempool: {}", e);
+                self.report.rejected += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Note a block this simulation mined on `chain`
+    pub fn block_mined(&mut self, chain: &Blockchain) {
+        self.report.blocks += 1;
+        self.release_confirmed(chain);
+    }
+
+    /// Stop reserving outputs whose spending transaction has left the mempool
+    fn release_confirmed(&mut self, chain: &Blockchain) {
+        self.reserved = chain
+            .get_pending_transactions()
+            .into_iter()
+            .flat_map(|tx| &tx.inputs)
+            .map(|input| UtxoId::new(input.previous_tx_hash.clone(), input.output_index))
+            .filter(|utxo_id| self.reserved.contains(utxo_id))
+            .collect();
+    }
+
+    /// Final counters, with `chain`'s height and what is still pending
+    pub fn finish(mut self, chain: &Blockchain) -> SimulationReport {
+        self.release_confirmed(chain);
+        self.report.end_height = chain.height();
+        let reserved = &self.reserved;
+        self.report.pending = chain
+            .get_pending_transactions()
+            .into_iter()
+            .filter(|tx| {
+                tx.inputs.iter().any(|input| reserved.contains(&UtxoId::new(input.previous_tx_hash.clone(), input.output_index)))
+            })
+            .count();
+        self.report
+    }
+
+    /// Exponentially distributed seconds until the next arrival
+    fn arrival_gap(&mut self) -> f64 {
+        -unit_open(&mut self.rng).ln() / self.params.tx_rate
+    }
+
+    /// Log-normal amount around `median_amount`, at least one unit
+    fn draw_amount(&mut self) -> u64 {
+        // Box-Muller transform of two uniform draws into a standard normal one
+        let (u1, u2) = (unit_open(&mut self.rng), self.rng.random::<f64>());
+        let normal = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+        let amount = self.params.median_amount as f64 * (self.params.amount_spread * normal).exp();
+        (amount.round() as u64).max(1)
+    }
+
+    /// Confirmed, mature outputs of `wallet` that nothing pending spends,
+    /// largest first
+    fn spendable<'a>(&self, chain: &'a Blockchain, wallet: usize) -> Vec<&'a UtxoEntry> {
+        let next_height = chain.height();
+        let mut coins: Vec<&UtxoEntry> = chain
+            .get_utxos_for_address(self.wallets[wallet].address())
+            .into_iter()
+            .filter(|utxo| !self.reserved.contains(&utxo.id()))
+            .filter(|utxo| {
+                next_height.saturating_sub(utxo.block_height) >= COINBASE_MATURITY
+                    || !chain
+                        .get_block_by_index(utxo.block_height)
+                        .and_then(|block| block.get_transaction(&utxo.tx_hash))
+                        .is_some_and(|tx| tx.is_coinbase())
+            })
+            .collect();
+        // Address indexes are unordered; sorting keeps seeded runs repeatable
+        coins.sort_by(|a, b| {
+            b.output.amount.cmp(&a.output.amount).then_with(|| (a.tx_hash.as_bytes(), a.output_index).cmp(&(b.tx_hash.as_bytes(), b.output_index)))
+        });
+        coins
+    }
+
+    /// Signed transaction paying `amount` from `sender` to `recipient` out of
+    /// the fewest of `coins` that cover it, with change back to `sender`
+    fn pay(
+        &self,
+        sender: usize,
+        recipient: usize,
+        coins: &[&UtxoEntry],
+        amount: u64,
+        fee: u64,
+        lock_time: u64,
+    ) -> Result<Transaction> {
+        let key = &self.wallets[sender];
+        let (mut inputs, mut spent, mut total) = (Vec::new(), Vec::new(), 0u64);
+        for utxo in coins {
+            if total >= amount + fee {
+                break;
+            }
+            inputs.push(TransactionInput::new(utxo.tx_hash.clone(), utxo.output_index, None, Some(key.public_key().clone())));
+            spent.push(utxo.output.clone());
+            total += utxo.output.amount;
+        }
+
+        let mut outputs = vec![TransactionOutput::new(amount, self.wallets[recipient].address().clone())];
+        let change = total - amount - fee;
+        if change > 0 {
+            outputs.push(TransactionOutput::new(change, key.address().clone()));
+        }
+
+        let transaction = Transaction::new(inputs, outputs).with_lock_time(lock_time);
+        let mut psbt = PartiallySignedTransaction::new(transaction, spent)?;
+        psbt.sign(key.private_key())?;
+        psbt.finalize()
+    }
+}
+
+/// Uniform draw in `(0, 1]`, safe to take the logarithm of
+fn unit_open(rng: &mut StdRng) -> f64 {
+    1.0 - rng.random::<f64>()
+}
+
+/// Mine one block on `chain` paying `miner_address`
+fn mine_block(chain: &mut Blockchain, miner_address: &Address) -> Result<()> {
+    let mut block = chain.create_block(miner_address.clone())?;
+    block.mine()?;
+    chain.add_block(block)
+}
+
+/// Run a whole simulation on `chain`, mining its blocks in this thread.
+///
+/// Unless `params.realtime` is set, the chain's clock follows simulated time
+/// for the run, starting far enough back that the last block lands near now,
+/// so block timestamps and difficulty retargets look like a real network's.
+pub fn run(chain: &mut Blockchain, params: SimulationParams) -> Result<SimulationReport> {
+    let realtime = params.realtime;
+    let mut simulation = Simulation::new(params, chain)?;
+    let miner_address = simulation.miner_address().clone();
+    let interval = chrono::Duration::from_std(simulation.block_interval())
+        .map_err(|e| LedgerError::InvalidInput(format!("block_interval is too long: {}", e)))?;
+
+    let clock = (!realtime).then(|| {
+        let span = interval * COINBASE_MATURITY as i32 + chrono::Duration::seconds(simulation.params.duration as i64);
+        let mut start = Utc::now() - span;
+        if let Some(tip) = chain.get_latest_block() {
+            start = start.max(tip.header.timestamp + chrono::Duration::seconds(1));
+        }
+        MockClock::new(start)
+    });
+    if let Some(clock) = &clock {
+        chain.set_clock(Arc::new(clock.clone()));
+    }
+
+    let result = (|| {
+        for _ in 0..COINBASE_MATURITY {
+            if let Some(clock) = &clock {
+                clock.advance(interval);
+            }
+            mine_block(chain, &miner_address)?;
+            simulation.block_mined(chain);
+        }
+
+        let (started, simulated_start) = (Instant::now(), clock.as_ref().map(Clock::now));
+        while let Some((at, step)) = simulation.next_step() {
+            let offset = Duration::from_secs_f64(at);
+            match (&clock, simulated_start) {
+                (Some(clock), Some(start)) => clock.set(start + chrono::Duration::from_std(offset).unwrap_or_default()),
+                _ => std::thread::sleep(offset.saturating_sub(started.elapsed())),
+            }
+            match step {
+                Step::Transaction => simulation.submit_transaction(chain)?,
+                Step::Block => {
+                    mine_block(chain, &miner_address)?;
+                    simulation.block_mined(chain);
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    if clock.is_some() {
+        chain.set_clock(Arc::new(SystemClock));
+    }
+    result.map(|()| simulation.finish(chain))
+}
+
+/// Start a simulation on a running node's chain as a background task.
+///
+/// Blocks are mined the way the node's own miner does, without holding the
+/// chain lock; the report is logged when the run ends.
+pub async fn spawn(state: &AppState, mut params: SimulationParams) -> Result<SimulationStarted> {
+    check_params(&params).map_err(LedgerError::InvalidInput)?;
+    let seed = *params.seed.get_or_insert_with(rand::random);
+    let task = format!("simulation-{}", Utc::now().format("%Y%m%dT%H%M%S%.3fZ"));
+
+    let worker = state.clone();
+    state.tasks.spawn(task.clone(), RestartPolicy::Never, move |shutdown| {
+        let (state, params) = (worker.clone(), params.clone());
+        async move {
+            let report = drive(&state, params, &shutdown).await?;
+            info!(
+                "Simulation {} finished: {} transactions submitted, {} rejected, {} blocks mined",
+                report.seed, report.submitted, report.rejected, report.blocks
+            );
+            Ok(())
+        }
+    })?;
+    Ok(SimulationStarted { task, seed })
+}
+
+async fn drive(state: &AppState, params: SimulationParams, shutdown: &Shutdown) -> Result<SimulationReport> {
+    let realtime = params.realtime;
+    let mut simulation = Simulation::new(params, &*state.blockchain.read().await)?;
+    let miner_address = simulation.miner_address().clone();
+    let control = MiningControl::new().with_events(state.miner_events.clone());
+
+    let mut funding_blocks = 0;
+    while funding_blocks < COINBASE_MATURITY && !shutdown.is_triggered() {
+        if node::mine_one(&state.blockchain, &state.storage, &miner_address, &control).await?.is_some() {
+            simulation.block_mined(&*state.blockchain.read().await);
+            funding_blocks += 1;
+        }
+    }
+
+    let started = tokio::time::Instant::now();
+    while let Some((at, step)) = simulation.next_step() {
+        if realtime {
+            tokio::select! {
+                _ = tokio::time::sleep_until(started + Duration::from_secs_f64(at)) => {}
+                _ = shutdown.wait() => break,
+            }
+        } else if shutdown.is_triggered() {
+            break;
+        } else {
+            tokio::task::yield_now().await;
+        }
+        match step {
+            Step::Transaction => simulation.submit_transaction(&mut *state.blockchain.write().await)?,
+            Step::Block => {
+                if node::mine_one(&state.blockchain, &state.storage, &miner_address, &control).await?.is_some() {
+                    simulation.block_mined(&*state.blockchain.read().await);
+                }
+            }
+        }
+    }
+    Ok(simulation.finish(&*state.blockchain.read().await))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::BlockchainConfig;
+    use crate::crypto::PrivateKey;
+
+    fn chain() -> Blockchain {
+        let genesis = PrivateKey::new(vec![7; 32], SignatureAlgorithm::EcdsaSecp256k1);
+        Blockchain::new(BlockchainConfig::default(), genesis.public_key().unwrap().to_address()).unwrap()
+    }
+
+    fn params() -> SimulationParams {
+        SimulationParams {
+            wallets: 5,
+            tx_rate: 2.0,
+            duration: 120,
+            block_interval: Some(10),
+            seed: Some(42),
+            realtime: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_params() {
+        assert!(check_params(&SimulationParams::default()).is_ok());
+        assert!(check_params(&SimulationParams { wallets: 1, ..Default::default() }).is_err());
+        assert!(check_params(&SimulationParams { tx_rate: 0.0, ..Default::default() }).is_err());
+        assert!(check_params(&SimulationParams { tx_rate: f64::NAN, ..Default::default() }).is_err());
+        assert!(check_params(&SimulationParams { block_interval: Some(0), ..Default::default() }).is_err());
+        assert!(check_params(&SimulationParams { amount_spread: -1.0, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_steps_follow_rate_and_block_interval() {
+        let mut simulation = Simulation::new(SimulationParams { duration: 10_000, ..params() }, &chain()).unwrap();
+        let (mut transactions, mut blocks, mut last) = (0, 0, 0.0);
+        while let Some((at, step)) = simulation.next_step() {
+            assert!(at >= last && at <= 10_000.0);
+            last = at;
+            match step {
+                Step::Transaction => transactions += 1,
+                Step::Block => blocks += 1,
+            }
+        }
+        assert_eq!(blocks, 1_000);
+        // 20,000 expected arrivals; a Poisson count stays well within 5% of that
+        assert!((19_000..21_000).contains(&transactions), "{} transactions", transactions);
+    }
+
+    #[test]
+    fn test_run_submits_valid_transactions() {
+        let mut chain = chain();
+        let report = run(&mut chain, params()).unwrap();
+        assert_eq!(report.start_height, 1);
+        assert_eq!(report.blocks, COINBASE_MATURITY + 12);
+        assert_eq!(report.end_height, report.start_height + report.blocks);
+        assert!(report.submitted > 100, "{:?}", report);
+        assert_eq!(report.rejected, 0);
+        assert!(report.fees >= report.submitted * chain.config.min_transaction_fee);
+        assert!(chain.verify_chain().is_ok());
+
+        // Every payment was locked against fee sniping to the block after the tip
+        // it was built on
+        for (height, tx) in (0..chain.height()).flat_map(|height| {
+            chain.get_block_by_index(height).unwrap().transactions().iter().filter(|tx| !tx.is_coinbase()).map(move |tx| (height, tx))
+        }) {
+            assert!(tx.lock_time > COINBASE_MATURITY && tx.lock_time <= height, "{} in block {}", tx.lock_time, height);
+        }
+
+        // Funds spread beyond the wallet collecting the rewards
+        let receivers = chain.address_balances().filter(|(_, balance)| *balance > 0).count();
+        assert!(receivers > 2);
+
+        // The same seed makes the same wallets and payments
+        let again = run(&mut self::chain(), params()).unwrap();
+        assert_eq!(again, report);
+    }
+}
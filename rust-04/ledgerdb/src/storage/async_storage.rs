@@ -0,0 +1,166 @@
+//! [`Storage`] for async code.
+//!
+//! Every backend blocks the calling thread on disk I/O. Called straight from a
+//! handler, each call stalls a tokio worker, and under heavy I/O every worker can end
+//! up waiting on disk. An [`AsyncStorage`] runs each call on tokio's blocking pool
+//! instead, with at most
+//! `storage.io_workers` in flight so a burst of requests queues rather than
+//! spawning a thread per request.
+
+use super::{BlockchainMetadata, IntegrityReport, PersistentStorage, Storage, StorageBatch, StorageStats};
+use crate::core::{Block, BlockFilter, Transaction, UtxoEntry, UtxoId};
+use crate::crypto::{Address, Hash256};
+use crate::error::{LedgerError, Result};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Async facade over the node's [`PersistentStorage`]; clones share the storage and
+/// the worker limit
+#[derive(Debug, Clone)]
+pub struct AsyncStorage {
+    storage: Arc<PersistentStorage>,
+    workers: Arc<Semaphore>,
+}
+
+impl AsyncStorage {
+    /// Run calls to `storage` on at most `workers` blocking threads at a time
+    pub fn new(storage: Arc<PersistentStorage>, workers: usize) -> Self {
+        Self { storage, workers: Arc::new(Semaphore::new(workers.max(1))) }
+    }
+
+    /// The storage itself, for code that is already off the async runtime
+    pub fn blocking(&self) -> &Arc<PersistentStorage> {
+        &self.storage
+    }
+
+    /// Run `f` on a blocking thread once a worker is free.
+    ///
+    /// For work that reaches the storage indirectly, such as a [`Blockchain`](crate::core::Blockchain)
+    /// method that persists what it changes; move an owned lock guard into `f`.
+    pub async fn spawn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let permit = self.workers.clone().acquire_owned().await
+            .map_err(|e| LedgerError::Internal(format!("Storage workers closed: {}", e)))?;
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            f()
+        })
+        .await
+        .map_err(|e| LedgerError::Internal(format!("Storage task failed: {}", e)))?
+    }
+
+    /// Run `f` against the storage on a blocking thread
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn Storage) -> Result<T> + Send + 'static,
+    {
+        let storage = self.storage.clone();
+        self.spawn(move || f(&**storage)).await
+    }
+
+    pub async fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        self.run(move |storage| storage.write_batch(batch)).await
+    }
+
+    pub async fn load_metadata(&self) -> Result<BlockchainMetadata> {
+        self.run(|storage| storage.load_metadata()).await
+    }
+
+    pub async fn load_block_by_hash(&self, block_hash: Hash256) -> Result<Block> {
+        self.run(move |storage| storage.load_block_by_hash(&block_hash)).await
+    }
+
+    pub async fn load_block_by_height(&self, height: u64) -> Result<Block> {
+        self.run(move |storage| storage.load_block_by_height(height)).await
+    }
+
+    pub async fn load_block_filter(&self, block_hash: Hash256) -> Result<BlockFilter> {
+        self.run(move |storage| storage.load_block_filter(&block_hash)).await
+    }
+
+    pub async fn load_transaction(&self, tx_hash: Hash256) -> Result<Transaction> {
+        self.run(move |storage| storage.load_transaction(&tx_hash)).await
+    }
+
+    pub async fn load_utxo(&self, utxo_id: UtxoId) -> Result<UtxoEntry> {
+        self.run(move |storage| storage.load_utxo(&utxo_id)).await
+    }
+
+    pub async fn load_utxos_for_address(&self, address: Address) -> Result<Vec<UtxoEntry>> {
+        self.run(move |storage| storage.load_utxos_for_address(&address)).await
+    }
+
+    pub async fn get_stats(&self) -> Result<StorageStats> {
+        self.run(|storage| storage.get_stats()).await
+    }
+
+    pub async fn compact(&self) -> Result<()> {
+        self.run(|storage| storage.compact()).await
+    }
+
+    pub async fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        self.run(move |storage| storage.check_integrity(repair)).await
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        self.run(|storage| storage.close()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TransactionOutput;
+    use crate::crypto::{PublicKey, SignatureAlgorithm};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn address() -> Address {
+        Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![1, 2, 3, 4, 5]))
+    }
+
+    #[tokio::test]
+    async fn test_calls_run_off_the_runtime() {
+        let storage = AsyncStorage::new(Arc::new(PersistentStorage::in_memory()), 2);
+        let block = Block::genesis(address(), 5000);
+        let tx_hash = block.transactions()[0].hash();
+        let entry = UtxoEntry::new(TransactionOutput::new(5000, address()), 0, tx_hash.clone(), 0);
+
+        let mut batch = StorageBatch::new();
+        batch.store_block(&block).store_utxo(&UtxoId::new(tx_hash.clone(), 0), &entry);
+        storage.write_batch(batch).await.unwrap();
+
+        assert_eq!(storage.load_block_by_height(0).await.unwrap().hash(), block.hash());
+        assert_eq!(storage.load_block_by_hash(block.hash()).await.unwrap().index, 0);
+        assert_eq!(storage.load_transaction(tx_hash.clone()).await.unwrap().hash(), tx_hash);
+        assert_eq!(storage.load_utxos_for_address(address()).await.unwrap().len(), 1);
+        assert_eq!(storage.get_stats().await.unwrap().blocks_count, 1);
+        assert!(storage.load_block_by_height(1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_workers_are_bounded() {
+        let storage = AsyncStorage::new(Arc::new(PersistentStorage::in_memory()), 2);
+        let (running, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let calls = (0..8).map(|_| {
+            let (storage, running, peak) = (storage.clone(), running.clone(), peak.clone());
+            tokio::spawn(async move {
+                storage.spawn(move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                }).await
+            })
+        });
+        for call in calls.collect::<Vec<_>>() {
+            call.await.unwrap().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+}
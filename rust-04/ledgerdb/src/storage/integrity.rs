@@ -0,0 +1,200 @@
+//! Cross-checks between the key-value trees, behind [`Storage::check_integrity`].
+//!
+//! Blocks and transactions must be stored under their own hashes, the height and
+//! transaction indexes must point at stored blocks, UTXOs must come from stored
+//! transactions and the address index must list only stored UTXOs. Repairing drops
+//! the dangling index entries; anything else is reported for `ledgerdb reindex`.
+//!
+//! [`Storage::check_integrity`]: super::Storage::check_integrity
+
+use super::{keys, IntegrityReport, StorageIssue, StorageIssueKind};
+use crate::core::{Block, Transaction, UtxoEntry, UtxoId};
+use crate::error::{Result, StorageError};
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+
+/// Raw access to the trees of a backend storing sled's keys and values
+pub(super) trait RawTrees {
+    /// Every entry of `tree`, in key order
+    fn entries(&self, tree: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> Result<()>;
+
+    fn remove(&self, tree: &str, key: &[u8]) -> Result<()>;
+}
+
+/// Entry keys as text where they are text, otherwise hex
+fn display_key(key: &[u8]) -> String {
+    match std::str::from_utf8(key) {
+        Ok(text) if !text.chars().any(char::is_control) => text.to_string(),
+        _ => hex::encode(key),
+    }
+}
+
+fn decode<T: DeserializeOwned>(data: &[u8]) -> Option<T> {
+    bincode::deserialize(data).ok()
+}
+
+struct Checker<'a, T: RawTrees + ?Sized> {
+    trees: &'a T,
+    repair: bool,
+    report: IntegrityReport,
+}
+
+impl<T: RawTrees + ?Sized> Checker<'_, T> {
+    fn entries(&mut self, tree: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let entries = self.trees.entries(tree)?;
+        self.report.entries_checked += entries.len() as u64;
+        Ok(entries)
+    }
+
+    /// Record an issue; dangling index entries are removed when repairing
+    fn issue(&mut self, tree: &str, key: &[u8], kind: StorageIssueKind, detail: String) -> Result<()> {
+        let repaired = self.repair && kind.is_dangling_index();
+        if repaired {
+            self.trees.remove(tree, key)?;
+        }
+        self.report.issues.push(StorageIssue {
+            tree: tree.to_string(),
+            key: display_key(key),
+            kind,
+            detail,
+            repaired,
+        });
+        Ok(())
+    }
+
+    /// Blocks by hash, with their heights
+    fn check_blocks(&mut self) -> Result<HashMap<String, u64>> {
+        let mut blocks = HashMap::new();
+        for (key, value) in self.entries(keys::BLOCKS)? {
+            let Some(block) = decode::<Block>(&value) else {
+                self.issue(keys::BLOCKS, &key, StorageIssueKind::Unreadable, "not a block".to_string())?;
+                continue;
+            };
+            let hash = block.hash().to_hex();
+            if hash.as_bytes() != key.as_slice() {
+                let detail = format!("block {} hashes to {}", block.index, hash);
+                self.issue(keys::BLOCKS, &key, StorageIssueKind::HashMismatch, detail)?;
+                continue;
+            }
+            blocks.insert(hash, block.index);
+        }
+        Ok(blocks)
+    }
+
+    fn check_block_index(&mut self, blocks: &HashMap<String, u64>) -> Result<()> {
+        for (key, value) in self.entries(keys::BLOCK_INDEX)? {
+            let height = <[u8; 8]>::try_from(key.as_slice()).ok().map(u64::from_be_bytes);
+            let hash = String::from_utf8_lossy(&value).into_owned();
+            let detail = match (height, blocks.get(&hash)) {
+                (None, _) => "key is not a height".to_string(),
+                (Some(_), None) => format!("names missing block {}", hash),
+                (Some(height), Some(&index)) if index != height => {
+                    format!("names block {} at height {}", hash, index)
+                }
+                _ => continue,
+            };
+            self.issue(keys::BLOCK_INDEX, &key, StorageIssueKind::DanglingBlockIndex, detail)?;
+        }
+        Ok(())
+    }
+
+    /// Hashes of the stored transactions
+    fn check_transactions(&mut self) -> Result<HashSet<String>> {
+        let mut transactions = HashSet::new();
+        for (key, value) in self.entries(keys::TRANSACTIONS)? {
+            let Some(transaction) = decode::<Transaction>(&value) else {
+                self.issue(keys::TRANSACTIONS, &key, StorageIssueKind::Unreadable, "not a transaction".to_string())?;
+                continue;
+            };
+            let hash = transaction.hash().to_hex();
+            if hash.as_bytes() != key.as_slice() {
+                let detail = format!("transaction hashes to {}", hash);
+                self.issue(keys::TRANSACTIONS, &key, StorageIssueKind::HashMismatch, detail)?;
+                continue;
+            }
+            transactions.insert(hash);
+        }
+        Ok(transactions)
+    }
+
+    fn check_tx_index(&mut self, blocks: &HashMap<String, u64>, transactions: &HashSet<String>) -> Result<()> {
+        for (key, value) in self.entries(keys::TX_INDEX)? {
+            let block_hash = String::from_utf8_lossy(&value).into_owned();
+            let detail = if !transactions.contains(&*String::from_utf8_lossy(&key)) {
+                "transaction is not stored".to_string()
+            } else if !blocks.contains_key(&block_hash) {
+                format!("names missing block {}", block_hash)
+            } else {
+                continue;
+            };
+            self.issue(keys::TX_INDEX, &key, StorageIssueKind::DanglingTxIndex, detail)?;
+        }
+        Ok(())
+    }
+
+    /// Ids of the stored UTXOs
+    fn check_utxos(&mut self, transactions: &HashSet<String>) -> Result<HashSet<UtxoId>> {
+        let mut utxos = HashSet::new();
+        for (key, value) in self.entries(keys::UTXOS)? {
+            let id = std::str::from_utf8(&key).ok().and_then(|key| UtxoId::from_string(key).ok());
+            let (Some(id), Some(entry)) = (id, decode::<UtxoEntry>(&value)) else {
+                self.issue(keys::UTXOS, &key, StorageIssueKind::Unreadable, "not a UTXO".to_string())?;
+                continue;
+            };
+            if !transactions.contains(&entry.tx_hash.to_hex()) {
+                let detail = format!("created by missing transaction {}", entry.tx_hash.to_hex());
+                self.issue(keys::UTXOS, &key, StorageIssueKind::OrphanUtxo, detail)?;
+            }
+            utxos.insert(id);
+        }
+        Ok(utxos)
+    }
+
+    fn check_address_index(&mut self, utxos: &HashSet<UtxoId>) -> Result<()> {
+        for (key, value) in self.entries(keys::ADDRESS_INDEX)? {
+            let Some(ids) = decode::<Vec<UtxoId>>(&value) else {
+                self.issue(keys::ADDRESS_INDEX, &key, StorageIssueKind::Unreadable, "not a UTXO list".to_string())?;
+                continue;
+            };
+            let (stored, missing): (Vec<UtxoId>, Vec<UtxoId>) = ids.into_iter().partition(|id| utxos.contains(id));
+            if missing.is_empty() {
+                continue;
+            }
+
+            // Only the missing ids are dropped, so the entry is rewritten rather than removed
+            let repaired = self.repair;
+            if repaired {
+                if stored.is_empty() {
+                    self.trees.remove(keys::ADDRESS_INDEX, &key)?;
+                } else {
+                    let value = bincode::serialize(&stored)
+                        .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+                    self.trees.insert(keys::ADDRESS_INDEX, &key, value)?;
+                }
+            }
+            let missing: Vec<String> = missing.iter().map(UtxoId::to_string).collect();
+            self.report.issues.push(StorageIssue {
+                tree: keys::ADDRESS_INDEX.to_string(),
+                key: display_key(&key),
+                kind: StorageIssueKind::DanglingAddressIndex,
+                detail: format!("lists missing UTXOs {}", missing.join(", ")),
+                repaired,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Check `trees` against each other, dropping dangling index entries if `repair`
+pub(super) fn check<T: RawTrees + ?Sized>(trees: &T, repair: bool) -> Result<IntegrityReport> {
+    let mut checker = Checker { trees, repair, report: IntegrityReport::default() };
+    let blocks = checker.check_blocks()?;
+    checker.check_block_index(&blocks)?;
+    let transactions = checker.check_transactions()?;
+    checker.check_tx_index(&blocks, &transactions)?;
+    let utxos = checker.check_utxos(&transactions)?;
+    checker.check_address_index(&utxos)?;
+    Ok(checker.report)
+}
@@ -0,0 +1,293 @@
+//! [`Storage`] held in process memory, for tests and throwaway nodes.
+
+use super::{
+    keys, BatchOp, BlockchainMetadata, IntegrityReport, Storage, StorageBatch, StorageIoStats, StorageIssue,
+    StorageIssueKind, StorageStats,
+};
+use crate::core::{Block, BlockFilter, Transaction, UtxoEntry, UtxoId};
+use crate::crypto::Hash256;
+use crate::error::{Result, StorageError};
+use crate::auth::UserRecord;
+use crate::peers::PeerRecord;
+use crate::watch::WatchedAddress;
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Debug, Clone, Default)]
+struct MemoryState {
+    metadata: Option<BlockchainMetadata>,
+    blocks: HashMap<Hash256, Block>,
+    /// Height -> block hash
+    block_index: BTreeMap<u64, Hash256>,
+    transactions: HashMap<Hash256, Transaction>,
+    utxos: HashMap<UtxoId, UtxoEntry>,
+    /// Address -> UTXOs paying it
+    address_index: HashMap<String, Vec<UtxoId>>,
+    block_filters: HashMap<Hash256, BlockFilter>,
+    watch_list: BTreeMap<String, WatchedAddress>,
+    users: BTreeMap<String, UserRecord>,
+    peers: BTreeMap<String, PeerRecord>,
+}
+
+impl MemoryState {
+    fn apply(&mut self, op: &BatchOp) -> Result<()> {
+        match op {
+            BatchOp::PutMetadata(metadata) => self.metadata = Some(metadata.clone()),
+            BatchOp::PutBlock(block) => {
+                let block_hash = block.hash();
+                for tx in block.transactions() {
+                    self.transactions.insert(tx.hash(), tx.clone());
+                }
+                self.block_index.insert(block.index, block_hash.clone());
+                self.blocks.insert(block_hash, block.clone());
+            }
+            BatchOp::UnindexBlock { block_hash, height } => {
+                if self.block_index.get(height) == Some(block_hash) {
+                    self.block_index.remove(height);
+                }
+            }
+            BatchOp::PutBlockFilter(filter) => {
+                self.block_filters.insert(filter.block_hash.clone(), filter.clone());
+            }
+            BatchOp::PutTransaction { transaction, .. } => {
+                self.transactions.insert(transaction.hash(), transaction.clone());
+            }
+            BatchOp::PutUtxo { utxo_id, entry } => {
+                let ids = self.address_index.entry(entry.output.recipient.to_hex()).or_default();
+                if !ids.contains(utxo_id) {
+                    ids.push(utxo_id.clone());
+                }
+                self.utxos.insert(utxo_id.clone(), entry.clone());
+            }
+            BatchOp::RemoveUtxo { utxo_id, .. } => {
+                let entry = self.utxos.remove(utxo_id)
+                    .ok_or_else(|| StorageError::NotFound(format!("UTXO {}", utxo_id)))?;
+                if let Some(ids) = self.address_index.get_mut(&entry.output.recipient.to_hex()) {
+                    ids.retain(|id| id != utxo_id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// In-memory storage implementation; nothing survives the process
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    state: RwLock<MemoryState>,
+}
+
+impl MemoryStorage {
+    /// Empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, MemoryState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MemoryState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        let mut state = self.write();
+        // Apply to a copy so a failing operation leaves nothing behind
+        let mut next = state.clone();
+        for op in batch.ops() {
+            next.apply(op)?;
+        }
+        *state = next;
+        Ok(())
+    }
+
+    fn load_metadata(&self) -> Result<BlockchainMetadata> {
+        self.read().metadata.clone()
+            .ok_or_else(|| StorageError::NotFound("blockchain metadata".to_string()).into())
+    }
+
+    fn load_block_by_hash(&self, block_hash: &Hash256) -> Result<Block> {
+        self.read().blocks.get(block_hash).cloned()
+            .ok_or_else(|| StorageError::NotFound(format!("block {}", block_hash.to_hex())).into())
+    }
+
+    fn load_block_by_height(&self, height: u64) -> Result<Block> {
+        let hash = self.read().block_index.get(&height).cloned()
+            .ok_or_else(|| StorageError::NotFound(format!("block at height {}", height)))?;
+        self.load_block_by_hash(&hash)
+    }
+
+    fn load_all_blocks(&self) -> Result<Vec<Block>> {
+        let state = self.read();
+        state.block_index
+            .values()
+            .map(|hash| {
+                state.blocks.get(hash).cloned()
+                    .ok_or_else(|| StorageError::NotFound(format!("block {}", hash.to_hex())).into())
+            })
+            .collect()
+    }
+
+    fn load_stored_blocks(&self) -> Result<Vec<Block>> {
+        let mut blocks: Vec<Block> = self.read().blocks.values().cloned().collect();
+        blocks.sort_by_key(|b| b.index);
+        Ok(blocks)
+    }
+
+    fn clear_indexes(&self) -> Result<()> {
+        let mut state = self.write();
+        state.block_index.clear();
+        state.transactions.clear();
+        state.utxos.clear();
+        state.address_index.clear();
+        state.block_filters.clear();
+        Ok(())
+    }
+
+    fn load_block_filter(&self, block_hash: &Hash256) -> Result<BlockFilter> {
+        self.read().block_filters.get(block_hash).cloned()
+            .ok_or_else(|| StorageError::NotFound(format!("filter for block {}", block_hash.to_hex())).into())
+    }
+
+    fn store_watched_address(&self, entry: &WatchedAddress) -> Result<()> {
+        self.write().watch_list.insert(entry.address.clone(), entry.clone());
+        Ok(())
+    }
+
+    fn remove_watched_address(&self, address: &str) -> Result<bool> {
+        Ok(self.write().watch_list.remove(address).is_some())
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>> {
+        Ok(self.read().watch_list.values().cloned().collect())
+    }
+
+    fn store_user(&self, user: &UserRecord) -> Result<()> {
+        self.write().users.insert(user.username.clone(), user.clone());
+        Ok(())
+    }
+
+    fn remove_user(&self, username: &str) -> Result<bool> {
+        Ok(self.write().users.remove(username).is_some())
+    }
+
+    fn load_users(&self) -> Result<Vec<UserRecord>> {
+        Ok(self.read().users.values().cloned().collect())
+    }
+
+    fn store_peer(&self, peer: &PeerRecord) -> Result<()> {
+        self.write().peers.insert(peer.address.clone(), peer.clone());
+        Ok(())
+    }
+
+    fn remove_peer(&self, address: &str) -> Result<bool> {
+        Ok(self.write().peers.remove(address).is_some())
+    }
+
+    fn load_peers(&self) -> Result<Vec<PeerRecord>> {
+        Ok(self.read().peers.values().cloned().collect())
+    }
+
+    fn load_transaction(&self, tx_hash: &Hash256) -> Result<Transaction> {
+        self.read().transactions.get(tx_hash).cloned()
+            .ok_or_else(|| StorageError::NotFound(format!("transaction {}", tx_hash.to_hex())).into())
+    }
+
+    fn load_utxo(&self, utxo_id: &UtxoId) -> Result<UtxoEntry> {
+        self.read().utxos.get(utxo_id).cloned()
+            .ok_or_else(|| StorageError::NotFound(format!("UTXO {}", utxo_id)).into())
+    }
+
+    fn load_utxos_for_address(&self, address: &crate::crypto::Address) -> Result<Vec<UtxoEntry>> {
+        let state = self.read();
+        Ok(state.address_index
+            .get(&address.to_hex())
+            .map(|ids| ids.iter().filter_map(|id| state.utxos.get(id).cloned()).collect())
+            .unwrap_or_default())
+    }
+
+    fn get_stats(&self) -> Result<StorageStats> {
+        let state = self.read();
+        let trees = [
+            (keys::BLOCKS, state.blocks.len()),
+            (keys::TRANSACTIONS, state.transactions.len()),
+            (keys::UTXOS, state.utxos.len()),
+            (keys::BLOCK_INDEX, state.block_index.len()),
+            (keys::ADDRESS_INDEX, state.address_index.len()),
+            (keys::BLOCK_FILTERS, state.block_filters.len()),
+            (keys::WATCH_LIST, state.watch_list.len()),
+            (keys::USERS, state.users.len()),
+            (keys::PEERS, state.peers.len()),
+        ];
+        Ok(StorageStats {
+            blocks_count: state.blocks.len(),
+            transactions_count: state.transactions.len(),
+            utxos_count: state.utxos.len(),
+            journal_entries: 0,
+            database_size: 0,
+            trees: trees.into_iter().map(|(name, len)| (name.to_string(), len)).collect(),
+            cache_capacity: 0,
+            io: StorageIoStats::default(),
+            last_updated: Utc::now(),
+        })
+    }
+
+    fn compact(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Records are held decoded under the keys they were written with, so only the
+    /// cross-references can break
+    fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        let mut state = self.write();
+        let mut report = IntegrityReport {
+            entries_checked: (state.blocks.len() + state.block_index.len() + state.transactions.len()
+                + state.utxos.len() + state.address_index.len()) as u64,
+            issues: Vec::new(),
+        };
+        let mut issue = |tree: &str, key: String, kind: StorageIssueKind, detail: String| {
+            let repaired = repair && kind.is_dangling_index();
+            report.issues.push(StorageIssue { tree: tree.to_string(), key, kind, detail, repaired });
+        };
+
+        let MemoryState { blocks, block_index, transactions, utxos, address_index, .. } = &mut *state;
+        block_index.retain(|height, hash| {
+            let detail = match blocks.get(hash) {
+                None => format!("names missing block {}", hash.to_hex()),
+                Some(block) if block.index != *height => {
+                    format!("names block {} at height {}", hash.to_hex(), block.index)
+                }
+                Some(_) => return true,
+            };
+            issue(keys::BLOCK_INDEX, height.to_string(), StorageIssueKind::DanglingBlockIndex, detail);
+            !repair
+        });
+        for (id, entry) in utxos.iter() {
+            if !transactions.contains_key(&entry.tx_hash) {
+                let detail = format!("created by missing transaction {}", entry.tx_hash.to_hex());
+                issue(keys::UTXOS, id.to_string(), StorageIssueKind::OrphanUtxo, detail);
+            }
+        }
+        address_index.retain(|address, ids| {
+            let missing: Vec<String> = ids.iter().filter(|id| !utxos.contains_key(*id)).map(UtxoId::to_string).collect();
+            if missing.is_empty() {
+                return true;
+            }
+            let detail = format!("lists missing UTXOs {}", missing.join(", "));
+            issue(keys::ADDRESS_INDEX, address.clone(), StorageIssueKind::DanglingAddressIndex, detail);
+            if repair {
+                ids.retain(|id| utxos.contains_key(id));
+            }
+            !ids.is_empty()
+        });
+        Ok(report)
+    }
+}
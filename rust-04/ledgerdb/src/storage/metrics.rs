@@ -0,0 +1,43 @@
+//! Counters behind [`StorageStats::io`](super::StorageStats::io).
+
+use super::StorageIoStats;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Flush and serialization counters a backend keeps from the moment it is opened
+#[derive(Debug, Default)]
+pub(super) struct IoCounters {
+    flushes: AtomicU64,
+    flush_micros: AtomicU64,
+    serializations: AtomicU64,
+    serialization_micros: AtomicU64,
+}
+
+fn timed<T>(count: &AtomicU64, micros: &AtomicU64, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    count.fetch_add(1, Ordering::Relaxed);
+    micros.fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+    result
+}
+
+impl IoCounters {
+    /// Run `flush`, counting it and the time it took
+    pub(super) fn flush<T>(&self, flush: impl FnOnce() -> T) -> T {
+        timed(&self.flushes, &self.flush_micros, flush)
+    }
+
+    /// Run `serialize` (encoding or decoding one record), counting it and the time it took
+    pub(super) fn serialize<T>(&self, serialize: impl FnOnce() -> T) -> T {
+        timed(&self.serializations, &self.serialization_micros, serialize)
+    }
+
+    pub(super) fn stats(&self) -> StorageIoStats {
+        StorageIoStats {
+            flushes: self.flushes.load(Ordering::Relaxed),
+            flush_micros: self.flush_micros.load(Ordering::Relaxed),
+            serializations: self.serializations.load(Ordering::Relaxed),
+            serialization_micros: self.serialization_micros.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,27 +1,82 @@
 //! Persistent storage layer for the blockchain.
 //!
-//! This module provides persistent storage capabilities using the `sled` embedded database,
-//! including block storage, transaction indexing, and UTXO set persistence.
-
-use crate::core::{Block, Transaction, UtxoEntry, UtxoId};
+//! [`Storage`] is what the chain needs from a database: blocks, transactions, UTXOs,
+//! metadata and an atomic [`StorageBatch`] of writes. The `sled` embedded database
+//! ([`SledStorage`]) is the default backend, `RocksDbStorage` is available with the
+//! `rocksdb` feature and [`MemoryStorage`] keeps everything in memory for tests;
+//! [`PersistentStorage::open`] picks one from the [`StorageConfig`]. With the
+//! `sqlite-index` feature and `storage.sqlite_index` set, a `SqliteIndex` of the
+//! stored blocks is kept alongside for explorer queries. Async code goes through an
+//! [`AsyncStorage`], which keeps the blocking calls off the tokio workers.
+
+use crate::config::{StorageBackend, StorageConfig};
+use crate::core::{Block, BlockFilter, Transaction, UtxoEntry, UtxoId};
 use crate::crypto::Hash256;
-use crate::error::{Result, StorageError};
+use crate::error::{LedgerError, Result};
+use crate::auth::UserRecord;
+use crate::peers::PeerRecord;
+use crate::watch::WatchedAddress;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sled::{Db, Tree};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
 use std::path::Path;
+#[cfg(feature = "sqlite-index")]
 use std::sync::Arc;
 
-/// Storage keys for different data types
+mod async_storage;
+mod integrity;
+mod memory;
+mod metrics;
+#[cfg(feature = "rocksdb")]
+mod rocksdb_storage;
+mod sled_storage;
+#[cfg(feature = "sqlite-index")]
+mod sqlite_index;
+
+pub use async_storage::AsyncStorage;
+pub use ledgerdb_types::{IntegrityReport, StorageIoStats, StorageIssue, StorageIssueKind};
+pub use memory::MemoryStorage;
+#[cfg(feature = "rocksdb")]
+pub use rocksdb_storage::RocksDbStorage;
+pub use sled_storage::SledStorage;
+#[cfg(feature = "sqlite-index")]
+pub use sqlite_index::{AddressHistory, AddressHistoryQuery, AddressTransaction, IndexedStorage, MemoQuery, MemoSearch, MemoTransaction, SqliteIndex};
+
+/// Names of the trees (column families) and fixed keys
 mod keys {
-    pub const BLOCKS: &[u8] = b"blocks";
-    pub const TRANSACTIONS: &[u8] = b"transactions";
-    pub const UTXOS: &[u8] = b"utxos";
-    pub const METADATA: &[u8] = b"metadata";
-    pub const JOURNAL: &[u8] = b"journal";
-    pub const BLOCK_INDEX: &[u8] = b"block_index";
-    pub const TX_INDEX: &[u8] = b"tx_index";
-    pub const ADDRESS_INDEX: &[u8] = b"address_index";
+    pub(super) const BLOCKS: &str = "blocks";
+    pub(super) const TRANSACTIONS: &str = "transactions";
+    pub(super) const UTXOS: &str = "utxos";
+    pub(super) const METADATA: &str = "metadata";
+    pub(super) const JOURNAL: &str = "journal";
+    pub(super) const BLOCK_INDEX: &str = "block_index";
+    pub(super) const TX_INDEX: &str = "tx_index";
+    pub(super) const ADDRESS_INDEX: &str = "address_index";
+    pub(super) const BLOCK_FILTERS: &str = "block_filters";
+    pub(super) const WATCH_LIST: &str = "watch_list";
+    pub(super) const USERS: &str = "users";
+    pub(super) const PEERS: &str = "peers";
+
+    /// Every tree, in the order stats list them
+    pub(super) const TREES: [&str; 12] = [
+        BLOCKS,
+        TRANSACTIONS,
+        UTXOS,
+        METADATA,
+        JOURNAL,
+        BLOCK_INDEX,
+        TX_INDEX,
+        ADDRESS_INDEX,
+        BLOCK_FILTERS,
+        WATCH_LIST,
+        USERS,
+        PEERS,
+    ];
+
+    /// Key of the single entry in the metadata tree
+    pub(super) const BLOCKCHAIN_METADATA: &[u8] = b"blockchain_metadata";
 }
 
 /// Blockchain metadata stored in the database
@@ -107,76 +162,178 @@ pub enum JournalOperation {
     },
 }
 
-/// Persistent storage implementation
-#[derive(Debug)]
-pub struct PersistentStorage {
-    /// Main database instance
-    db: Db,
-    /// Blocks tree
-    blocks: Tree,
-    /// Transactions tree
-    transactions: Tree,
-    /// UTXOs tree
-    utxos: Tree,
-    /// Metadata tree
-    metadata: Tree,
-    /// Journal tree for atomic operations
-    journal: Tree,
-    /// Block index (hash -> height)
-    block_index: Tree,
-    /// Transaction index (hash -> block_hash)
-    tx_index: Tree,
-    /// Address index (address -> [utxo_ids])
-    address_index: Tree,
-    /// Next journal ID
-    next_journal_id: u64,
+/// One write in a [`StorageBatch`]
+#[derive(Debug, Clone)]
+pub enum BatchOp {
+    /// Replace the blockchain metadata
+    PutMetadata(BlockchainMetadata),
+    /// Store a block, index it by height and store its transactions
+    PutBlock(Block),
+    /// Drop a block disconnected by a reorganization from the height index; the
+    /// block itself stays stored
+    UnindexBlock {
+        block_hash: Hash256,
+        height: u64,
+    },
+    /// Store the compact filter of a block
+    PutBlockFilter(BlockFilter),
+    /// Store a transaction included in `block_hash`
+    PutTransaction {
+        transaction: Transaction,
+        block_hash: Hash256,
+    },
+    /// Add an unspent output
+    PutUtxo {
+        utxo_id: UtxoId,
+        entry: UtxoEntry,
+    },
+    /// Spend an output; fails the batch if it is not stored
+    RemoveUtxo {
+        utxo_id: UtxoId,
+        spent_at_height: u64,
+    },
 }
 
-impl PersistentStorage {
-    /// Create a new persistent storage instance
-    pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
-        let db = sled::open(db_path)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        let blocks = db.open_tree(keys::BLOCKS)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let transactions = db.open_tree(keys::TRANSACTIONS)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let utxos = db.open_tree(keys::UTXOS)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let metadata = db.open_tree(keys::METADATA)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let journal = db.open_tree(keys::JOURNAL)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let block_index = db.open_tree(keys::BLOCK_INDEX)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let tx_index = db.open_tree(keys::TX_INDEX)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        let address_index = db.open_tree(keys::ADDRESS_INDEX)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        // Get next journal ID
-        let next_journal_id = journal.len() as u64;
-        
-        Ok(Self {
-            db,
-            blocks,
-            transactions,
-            utxos,
-            metadata,
-            journal,
-            block_index,
-            tx_index,
-            address_index,
-            next_journal_id,
-        })
+/// Writes applied together by [`Storage::write_batch`]: all of them or none
+#[derive(Debug, Clone, Default)]
+pub struct StorageBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl StorageBatch {
+    /// An empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The writes, in the order they were added
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
     }
 
+    pub fn push(&mut self, op: BatchOp) -> &mut Self {
+        self.ops.push(op);
+        self
+    }
+
+    pub fn store_metadata(&mut self, metadata: &BlockchainMetadata) -> &mut Self {
+        self.push(BatchOp::PutMetadata(metadata.clone()))
+    }
+
+    pub fn store_block(&mut self, block: &Block) -> &mut Self {
+        self.push(BatchOp::PutBlock(block.clone()))
+    }
+
+    pub fn unindex_block(&mut self, block: &Block) -> &mut Self {
+        self.push(BatchOp::UnindexBlock { block_hash: block.hash(), height: block.index })
+    }
+
+    pub fn store_block_filter(&mut self, filter: &BlockFilter) -> &mut Self {
+        self.push(BatchOp::PutBlockFilter(filter.clone()))
+    }
+
+    pub fn store_transaction(&mut self, transaction: &Transaction, block_hash: &Hash256) -> &mut Self {
+        self.push(BatchOp::PutTransaction { transaction: transaction.clone(), block_hash: block_hash.clone() })
+    }
+
+    pub fn store_utxo(&mut self, utxo_id: &UtxoId, entry: &UtxoEntry) -> &mut Self {
+        self.push(BatchOp::PutUtxo { utxo_id: utxo_id.clone(), entry: entry.clone() })
+    }
+
+    pub fn remove_utxo(&mut self, utxo_id: &UtxoId, spent_at_height: u64) -> &mut Self {
+        self.push(BatchOp::RemoveUtxo { utxo_id: utxo_id.clone(), spent_at_height })
+    }
+}
+
+/// A storage backend.
+///
+/// Backends implement the reads and [`Storage::write_batch`]; the single-record
+/// writes are one-operation batches.
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// Apply every write in `batch` atomically
+    fn write_batch(&self, batch: StorageBatch) -> Result<()>;
+
+    /// Load blockchain metadata
+    fn load_metadata(&self) -> Result<BlockchainMetadata>;
+
+    /// Load a block by hash
+    fn load_block_by_hash(&self, block_hash: &Hash256) -> Result<Block>;
+
+    /// Load a block by height
+    fn load_block_by_height(&self, height: u64) -> Result<Block>;
+
+    /// Load the blocks the height index points at, ordered by height (for
+    /// blockchain reconstruction)
+    fn load_all_blocks(&self) -> Result<Vec<Block>>;
+
+    /// Every stored block, whether or not the height index points at it, ordered by height
+    fn load_stored_blocks(&self) -> Result<Vec<Block>>;
+
+    /// Drop everything derived from the stored blocks: the height, transaction, UTXO
+    /// and address indexes and the block filters. The blocks themselves are kept.
+    fn clear_indexes(&self) -> Result<()>;
+
+    /// Load the compact filter of a block
+    fn load_block_filter(&self, block_hash: &Hash256) -> Result<BlockFilter>;
+
+    /// Add or replace a watch-list entry
+    fn store_watched_address(&self, entry: &WatchedAddress) -> Result<()>;
+
+    /// Remove a watch-list entry; returns whether it was there
+    fn remove_watched_address(&self, address: &str) -> Result<bool>;
+
+    /// Load every watch-list entry, ordered by address
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>>;
+
+    /// Add or replace a user account
+    fn store_user(&self, user: &UserRecord) -> Result<()>;
+
+    /// Remove a user account; returns whether it was there
+    fn remove_user(&self, username: &str) -> Result<bool>;
+
+    /// Load every user account, ordered by username
+    fn load_users(&self) -> Result<Vec<UserRecord>>;
+
+    /// Add or replace an address-book entry
+    fn store_peer(&self, peer: &PeerRecord) -> Result<()>;
+
+    /// Remove an address-book entry; returns whether it was there
+    fn remove_peer(&self, address: &str) -> Result<bool>;
+
+    /// Load every address-book entry, ordered by address
+    fn load_peers(&self) -> Result<Vec<PeerRecord>>;
+
+    /// Load a transaction by hash
+    fn load_transaction(&self, tx_hash: &Hash256) -> Result<Transaction>;
+
+    /// Load UTXO
+    fn load_utxo(&self, utxo_id: &UtxoId) -> Result<UtxoEntry>;
+
+    /// Load all UTXOs for an address
+    fn load_utxos_for_address(&self, address: &crate::crypto::Address) -> Result<Vec<UtxoEntry>>;
+
+    /// Get database statistics
+    fn get_stats(&self) -> Result<StorageStats>;
+
+    /// Reclaim space, e.g. old journal entries
+    fn compact(&self) -> Result<()>;
+
+    /// Flush outstanding writes before shutdown
+    fn close(&self) -> Result<()>;
+
+    /// Check the stored records against each other; with `repair`, drop index
+    /// entries pointing at records that are not stored
+    fn check_integrity(&self, repair: bool) -> Result<IntegrityReport>;
+
     /// Load or create blockchain metadata
-    pub fn load_or_create_blockchain(&self) -> Result<BlockchainMetadata> {
+    fn load_or_create_blockchain(&self) -> Result<BlockchainMetadata> {
         match self.load_metadata() {
             Ok(metadata) => Ok(metadata),
-            Err(StorageError::NotFound(_)) => {
+            Err(LedgerError::NotFound(_)) => {
                 let metadata = BlockchainMetadata::default();
                 self.store_metadata(&metadata)?;
                 Ok(metadata)
@@ -185,376 +342,123 @@ impl PersistentStorage {
         }
     }
 
-    /// Load blockchain metadata
-    pub fn load_metadata(&self) -> Result<BlockchainMetadata> {
-        let key = b"blockchain_metadata";
-        
-        match self.metadata.get(key)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(data) => {
-                bincode::deserialize(data.as_ref())
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))
-            }
-            None => Err(StorageError::NotFound("blockchain metadata".to_string())),
-        }
-    }
-
     /// Store blockchain metadata
-    pub fn store_metadata(&self, metadata: &BlockchainMetadata) -> Result<()> {
-        let key = b"blockchain_metadata";
-        let data = bincode::serialize(metadata)
-            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-        
-        self.metadata.insert(key, data)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        self.db.flush()
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(())
+    fn store_metadata(&self, metadata: &BlockchainMetadata) -> Result<()> {
+        self.write_batch(std::mem::take(StorageBatch::new().store_metadata(metadata)))
     }
 
     /// Store a block
-    pub fn store_block(&self, block: &Block) -> Result<()> {
-        let block_hash = block.hash();
-        let block_key = block_hash.to_hex();
-        
-        // Start journal entry
-        let journal_entry = self.create_journal_entry(JournalOperation::AddBlock {
-            block_hash: block_hash.clone(),
-            block_index: block.index,
-        })?;
-        
-        // Serialize block
-        let block_data = bincode::serialize(block)
-            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-        
-        // Store block
-        self.blocks.insert(block_key.as_bytes(), block_data)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        // Update block index
-        let height_key = block.index.to_be_bytes();
-        self.block_index.insert(&height_key, block_hash.to_hex().as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        // Store transactions
-        for tx in &block.transactions {
-            self.store_transaction(tx, &block_hash)?;
-        }
-        
-        // Commit journal entry
-        self.commit_journal_entry(journal_entry.id)?;
-        
-        // Flush to disk
-        self.db.flush()
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(())
+    fn store_block(&self, block: &Block) -> Result<()> {
+        self.write_batch(std::mem::take(StorageBatch::new().store_block(block)))
     }
 
-    /// Load a block by hash
-    pub fn load_block_by_hash(&self, block_hash: &Hash256) -> Result<Block> {
-        let block_key = block_hash.to_hex();
-        
-        match self.blocks.get(block_key.as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(data) => {
-                bincode::deserialize(data.as_ref())
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))
-            }
-            None => Err(StorageError::NotFound(format!("block {}", block_hash.to_hex()))),
-        }
+    /// Store the compact filter of a block
+    fn store_block_filter(&self, filter: &BlockFilter) -> Result<()> {
+        self.write_batch(std::mem::take(StorageBatch::new().store_block_filter(filter)))
     }
 
-    /// Load a block by height
-    pub fn load_block_by_height(&self, height: u64) -> Result<Block> {
-        let height_key = height.to_be_bytes();
-        
-        match self.block_index.get(&height_key)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(hash_data) => {
-                let hash_str = String::from_utf8(hash_data.to_vec())
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-                let block_hash = Hash256::from_hex(&hash_str)
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-                self.load_block_by_hash(&block_hash)
-            }
-            None => Err(StorageError::NotFound(format!("block at height {}", height))),
-        }
+    /// Store a transaction
+    fn store_transaction(&self, transaction: &Transaction, block_hash: &Hash256) -> Result<()> {
+        self.write_batch(std::mem::take(StorageBatch::new().store_transaction(transaction, block_hash)))
     }
 
-    /// Load all blocks (for blockchain reconstruction)
-    pub fn load_all_blocks(&self) -> Result<Vec<Block>> {
-        let mut blocks = Vec::new();
-        
-        // Iterate through block index in order
-        for result in self.block_index.iter() {
-            let (height_bytes, hash_bytes) = result
-                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-            
-            let hash_str = String::from_utf8(hash_bytes.to_vec())
-                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-            let block_hash = Hash256::from_hex(&hash_str)
-                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-            
-            let block = self.load_block_by_hash(&block_hash)?;
-            blocks.push(block);
-        }
-        
-        // Sort by height to ensure correct order
-        blocks.sort_by_key(|b| b.index);
-        
-        Ok(blocks)
+    /// Store UTXO
+    fn store_utxo(&self, utxo_id: &UtxoId, utxo_entry: &UtxoEntry) -> Result<()> {
+        self.write_batch(std::mem::take(StorageBatch::new().store_utxo(utxo_id, utxo_entry)))
     }
 
-    /// Store a transaction
-    pub fn store_transaction(&self, transaction: &Transaction, block_hash: &Hash256) -> Result<()> {
-        let tx_hash = transaction.hash();
-        let tx_key = tx_hash.to_hex();
-        
-        // Serialize transaction
-        let tx_data = bincode::serialize(transaction)
-            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-        
-        // Store transaction
-        self.transactions.insert(tx_key.as_bytes(), tx_data)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        // Update transaction index
-        self.tx_index.insert(tx_hash.to_hex().as_bytes(), block_hash.to_hex().as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(())
+    /// Remove UTXO (when spent)
+    fn remove_utxo(&self, utxo_id: &UtxoId, spent_at_height: u64) -> Result<()> {
+        self.write_batch(std::mem::take(StorageBatch::new().remove_utxo(utxo_id, spent_at_height)))
     }
+}
 
-    /// Load a transaction by hash
-    pub fn load_transaction(&self, tx_hash: &Hash256) -> Result<Transaction> {
-        let tx_key = tx_hash.to_hex();
-        
-        match self.transactions.get(tx_key.as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(data) => {
-                bincode::deserialize(data.as_ref())
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))
-            }
-            None => Err(StorageError::NotFound(format!("transaction {}", tx_hash.to_hex()))),
-        }
-    }
+/// The node's storage: whichever [`Storage`] backend was configured.
+///
+/// Derefs to the backend, so callers use the [`Storage`] methods directly.
+#[derive(Debug)]
+pub struct PersistentStorage {
+    backend: Box<dyn Storage>,
+    #[cfg(feature = "sqlite-index")]
+    index: Option<Arc<SqliteIndex>>,
+}
 
-    /// Store UTXO
-    pub fn store_utxo(&self, utxo_id: &UtxoId, utxo_entry: &UtxoEntry) -> Result<()> {
-        let utxo_key = utxo_id.to_string();
-        
-        // Create journal entry
-        let _journal_entry = self.create_journal_entry(JournalOperation::CreateUtxo {
-            utxo_id: utxo_id.clone(),
-            utxo_entry: utxo_entry.clone(),
-        })?;
-        
-        // Serialize UTXO
-        let utxo_data = bincode::serialize(utxo_entry)
-            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-        
-        // Store UTXO
-        self.utxos.insert(utxo_key.as_bytes(), utxo_data)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        // Update address index
-        self.update_address_index(&utxo_entry.output.recipient, utxo_id, true)?;
-        
-        Ok(())
+impl PersistentStorage {
+    /// Open sled storage at `db_path`, with the default [`StorageConfig`] otherwise
+    pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let config = StorageConfig { db_path: db_path.as_ref().to_path_buf(), ..StorageConfig::default() };
+        Ok(Self::from_backend(SledStorage::open(&config)?))
     }
 
-    /// Load UTXO
-    pub fn load_utxo(&self, utxo_id: &UtxoId) -> Result<UtxoEntry> {
-        let utxo_key = utxo_id.to_string();
-        
-        match self.utxos.get(utxo_key.as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(data) => {
-                bincode::deserialize(data.as_ref())
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))
-            }
-            None => Err(StorageError::NotFound(format!("UTXO {}", utxo_key))),
+    /// Open the backend selected by `config`
+    pub fn open(config: &StorageConfig) -> Result<Self> {
+        let storage = match config.backend {
+            StorageBackend::Sled => Self::from_backend(SledStorage::open(config)?),
+            StorageBackend::Memory => Self::in_memory(),
+            StorageBackend::RocksDb => Self::open_rocksdb(config)?,
+        };
+        match &config.sqlite_index {
+            Some(path) => storage.with_sqlite_index(path),
+            None => Ok(storage),
         }
     }
 
-    /// Remove UTXO (when spent)
-    pub fn remove_utxo(&self, utxo_id: &UtxoId, spent_at_height: u64) -> Result<()> {
-        let utxo_key = utxo_id.to_string();
-        
-        // Load UTXO first to get address for index update
-        let utxo_entry = self.load_utxo(utxo_id)?;
-        
-        // Create journal entry
-        let _journal_entry = self.create_journal_entry(JournalOperation::SpendUtxo {
-            utxo_id: utxo_id.clone(),
-            spent_at_height,
-        })?;
-        
-        // Remove UTXO
-        self.utxos.remove(utxo_key.as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        // Update address index
-        self.update_address_index(&utxo_entry.output.recipient, utxo_id, false)?;
-        
-        Ok(())
+    /// Keep a SQLite index at `path` alongside the backend, first indexing the
+    /// blocks it already holds
+    #[cfg(feature = "sqlite-index")]
+    pub fn with_sqlite_index<P: AsRef<Path>>(self, path: P) -> Result<Self> {
+        let index = Arc::new(SqliteIndex::open(path)?);
+        Ok(Self {
+            backend: Box::new(IndexedStorage::new(self.backend, index.clone())?),
+            index: Some(index),
+        })
     }
 
-    /// Load all UTXOs for an address
-    pub fn load_utxos_for_address(&self, address: &crate::crypto::Address) -> Result<Vec<UtxoEntry>> {
-        let address_key = address.to_string();
-        
-        match self.address_index.get(address_key.as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(data) => {
-                let utxo_ids: Vec<UtxoId> = bincode::deserialize(data.as_ref())
-                    .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-                
-                let mut utxos = Vec::new();
-                for utxo_id in utxo_ids {
-                    if let Ok(utxo) = self.load_utxo(&utxo_id) {
-                        utxos.push(utxo);
-                    }
-                }
-                
-                Ok(utxos)
-            }
-            None => Ok(Vec::new()),
-        }
+    #[cfg(not(feature = "sqlite-index"))]
+    pub fn with_sqlite_index<P: AsRef<Path>>(self, _path: P) -> Result<Self> {
+        Err(LedgerError::Config(
+            "storage.sqlite_index needs ledgerdb built with the `sqlite-index` feature".to_string(),
+        ))
     }
 
-    /// Update address index
-    fn update_address_index(
-        &self,
-        address: &crate::crypto::Address,
-        utxo_id: &UtxoId,
-        add: bool,
-    ) -> Result<()> {
-        let address_key = address.to_string();
-        
-        let mut utxo_ids: Vec<UtxoId> = match self.address_index.get(address_key.as_bytes())
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            Some(data) => bincode::deserialize(data.as_ref())
-                .map_err(|e| StorageError::SerializationError(e.to_string()))?,
-            None => Vec::new(),
-        };
-        
-        if add {
-            if !utxo_ids.contains(utxo_id) {
-                utxo_ids.push(utxo_id.clone());
-            }
-        } else {
-            utxo_ids.retain(|id| id != utxo_id);
-        }
-        
-        let data = bincode::serialize(&utxo_ids)
-            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-        
-        self.address_index.insert(address_key.as_bytes(), data)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(())
+    /// The SQLite index, when one is kept
+    #[cfg(feature = "sqlite-index")]
+    pub fn sqlite_index(&self) -> Option<&SqliteIndex> {
+        self.index.as_deref()
     }
 
-    /// Create a journal entry
-    fn create_journal_entry(&self, operation: JournalOperation) -> Result<JournalEntry> {
-        let entry = JournalEntry {
-            id: self.next_journal_id,
-            timestamp: Utc::now(),
-            operation,
-            committed: false,
-            block_height: 0, // Will be updated when committed
-        };
-        
-        let entry_data = bincode::serialize(&entry)
-            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-        
-        self.journal.insert(&entry.id.to_be_bytes(), entry_data)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(entry)
+    #[cfg(feature = "rocksdb")]
+    fn open_rocksdb(config: &StorageConfig) -> Result<Self> {
+        Ok(Self::from_backend(RocksDbStorage::open(config)?))
     }
 
-    /// Commit a journal entry
-    fn commit_journal_entry(&self, journal_id: u64) -> Result<()> {
-        let key = journal_id.to_be_bytes();
-        
-        if let Some(data) = self.journal.get(&key)
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
-            let mut entry: JournalEntry = bincode::deserialize(data.as_ref())
-                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-            
-            entry.committed = true;
-            
-            let updated_data = bincode::serialize(&entry)
-                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-            
-            self.journal.insert(&key, updated_data)
-                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        }
-        
-        Ok(())
+    #[cfg(not(feature = "rocksdb"))]
+    fn open_rocksdb(_config: &StorageConfig) -> Result<Self> {
+        Err(LedgerError::Config(
+            "storage backend 'rocksdb' needs ledgerdb built with the `rocksdb` feature".to_string(),
+        ))
     }
 
-    /// Get database statistics
-    pub fn get_stats(&self) -> Result<StorageStats> {
-        let blocks_count = self.blocks.len();
-        let transactions_count = self.transactions.len();
-        let utxos_count = self.utxos.len();
-        let journal_entries = self.journal.len();
-        
-        // Calculate approximate database size
-        let db_size = self.db.size_on_disk()
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(StorageStats {
-            blocks_count,
-            transactions_count,
-            utxos_count,
-            journal_entries,
-            database_size: db_size,
-            last_updated: Utc::now(),
-        })
+    /// Empty storage held in memory
+    pub fn in_memory() -> Self {
+        Self::from_backend(MemoryStorage::new())
     }
 
-    /// Compact the database
-    pub fn compact(&self) -> Result<()> {
-        // Clean up old journal entries
-        let mut to_remove = Vec::new();
-        
-        for result in self.journal.iter() {
-            let (key, data) = result
-                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-            
-            let entry: JournalEntry = bincode::deserialize(&data)
-                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
-            
-            // Remove committed entries older than 1 day
-            if entry.committed && entry.timestamp < Utc::now() - chrono::Duration::days(1) {
-                to_remove.push(key.to_vec());
-            }
-        }
-        
-        for key in to_remove {
-            self.journal.remove(&key)
-                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+    /// Wrap any other backend
+    pub fn from_backend(backend: impl Storage + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+            #[cfg(feature = "sqlite-index")]
+            index: None,
         }
-        
-        // Flush changes
-        self.db.flush()
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        
-        Ok(())
     }
+}
 
-    /// Close the database
-    pub fn close(&self) -> Result<()> {
-        self.db.flush()
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-        Ok(())
+impl Deref for PersistentStorage {
+    type Target = dyn Storage;
+
+    fn deref(&self) -> &Self::Target {
+        self.backend.as_ref()
     }
 }
 
@@ -571,6 +475,12 @@ pub struct StorageStats {
     pub journal_entries: usize,
     /// Database size on disk in bytes
     pub database_size: u64,
+    /// Entries in each tree (column family for RocksDB)
+    pub trees: BTreeMap<String, usize>,
+    /// Bytes of cache the backend was opened with, from `storage.cache_size_mb`
+    pub cache_capacity: u64,
+    /// Flushes and record (de)serialization since the storage was opened
+    pub io: StorageIoStats,
     /// Last updated timestamp
     pub last_updated: DateTime<Utc>,
 }
@@ -605,7 +515,7 @@ mod tests {
         
         let metadata = BlockchainMetadata {
             height: 100,
-            latest_block_hash: Hash256::from_hex("1234567890abcdef").unwrap(),
+            latest_block_hash: crate::crypto::hash_data(b"1234567890abcdef"),
             total_transactions: 500,
             db_version: 1,
             last_updated: Utc::now(),
@@ -625,7 +535,7 @@ mod tests {
     fn test_utxo_storage() {
         let (storage, _temp_dir) = create_test_storage();
         
-        let tx_hash = Hash256::from_hex("abcdef1234567890").unwrap();
+        let tx_hash = crate::crypto::hash_data(b"abcdef1234567890");
         let utxo_id = UtxoId::new(tx_hash.clone(), 0);
         let output = TransactionOutput::new(1000, create_test_address());
         let utxo_entry = UtxoEntry::new(output, 1, tx_hash, 0);
@@ -639,7 +549,7 @@ mod tests {
 
     #[test]
     fn test_utxo_id_string_conversion() {
-        let tx_hash = Hash256::from_hex("1234567890abcdef").unwrap();
+        let tx_hash = crate::crypto::hash_data(b"1234567890abcdef");
         let utxo_id = UtxoId::new(tx_hash, 5);
         
         let string_repr = utxo_id.to_string();
@@ -648,6 +558,109 @@ mod tests {
         assert_eq!(utxo_id, parsed_id);
     }
 
+    fn backends() -> Vec<(PersistentStorage, Option<TempDir>)> {
+        let (sled, temp_dir) = create_test_storage();
+        #[allow(unused_mut)]
+        let mut backends = vec![(sled, Some(temp_dir)), (PersistentStorage::in_memory(), None)];
+        #[cfg(feature = "rocksdb")]
+        {
+            let temp_dir = TempDir::new().unwrap();
+            let config = StorageConfig {
+                backend: StorageBackend::RocksDb,
+                db_path: temp_dir.path().to_path_buf(),
+                ..StorageConfig::default()
+            };
+            backends.push((PersistentStorage::open(&config).unwrap(), Some(temp_dir)));
+        }
+        backends
+    }
+
+    #[test]
+    fn test_batches_are_atomic() {
+        for (storage, _temp_dir) in backends() {
+            let block = Block::genesis(create_test_address(), 5000);
+            let tx_hash = block.transactions()[0].hash();
+            let utxo_id = UtxoId::new(tx_hash.clone(), 0);
+            let entry = UtxoEntry::new(block.transactions()[0].outputs[0].clone(), 0, tx_hash, 0);
+
+            let mut batch = StorageBatch::new();
+            batch.store_block(&block).store_utxo(&utxo_id, &entry);
+            storage.write_batch(batch).unwrap();
+            assert_eq!(storage.load_block_by_height(0).unwrap().hash(), block.hash());
+            assert_eq!(storage.load_utxos_for_address(&create_test_address()).unwrap().len(), 1);
+
+            // Spending an unknown output fails the whole batch
+            let missing = UtxoId::new(Hash256::zero(), 7);
+            let mut batch = StorageBatch::new();
+            batch.remove_utxo(&utxo_id, 1).remove_utxo(&missing, 1);
+            assert!(storage.write_batch(batch).is_err());
+            assert!(storage.load_utxo(&utxo_id).is_ok());
+
+            storage.remove_utxo(&utxo_id, 1).unwrap();
+            assert!(storage.load_utxos_for_address(&create_test_address()).unwrap().is_empty());
+            assert_eq!(storage.get_stats().unwrap().blocks_count, 1);
+        }
+    }
+
+    #[test]
+    fn test_check_integrity() {
+        for (storage, _temp_dir) in backends() {
+            let block = Block::genesis(create_test_address(), 5000);
+            let tx_hash = block.transactions()[0].hash();
+            let entry = UtxoEntry::new(block.transactions()[0].outputs[0].clone(), 0, tx_hash.clone(), 0);
+            let mut batch = StorageBatch::new();
+            batch.store_block(&block).store_utxo(&UtxoId::new(tx_hash, 0), &entry);
+            storage.write_batch(batch).unwrap();
+            assert!(storage.check_integrity(false).unwrap().is_clean());
+
+            // An output of a transaction that was never stored is reported but kept
+            let orphan_id = UtxoId::new(Hash256::zero(), 0);
+            let orphan = UtxoEntry::new(entry.output.clone(), 0, Hash256::zero(), 0);
+            storage.store_utxo(&orphan_id, &orphan).unwrap();
+            let report = storage.check_integrity(true).unwrap();
+            assert_eq!(report.issues.len(), 1);
+            assert_eq!(report.issues[0].kind, StorageIssueKind::OrphanUtxo);
+            assert!(!report.issues[0].repaired);
+            assert!(storage.load_utxo(&orphan_id).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_open_selects_backend() {
+        let config = StorageConfig { backend: StorageBackend::Memory, ..StorageConfig::default() };
+        let storage = PersistentStorage::open(&config).unwrap();
+        assert_eq!(storage.get_stats().unwrap().database_size, 0);
+        assert!(!config.db_path.exists());
+
+        #[cfg(not(feature = "rocksdb"))]
+        {
+            let config = StorageConfig { backend: StorageBackend::RocksDb, ..StorageConfig::default() };
+            assert!(PersistentStorage::open(&config).is_err());
+        }
+        #[cfg(not(feature = "sqlite-index"))]
+        {
+            let config = StorageConfig { sqlite_index: Some("index.sqlite".into()), ..config };
+            assert!(PersistentStorage::open(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_storage_metrics() {
+        let (storage, _temp_dir) = create_test_storage();
+        let stats = storage.get_stats().unwrap();
+        assert_eq!(stats.cache_capacity, 64 * 1024 * 1024);
+        assert_eq!(stats.trees.len(), keys::TREES.len());
+
+        storage.store_block(&Block::genesis(create_test_address(), 5000)).unwrap();
+        storage.load_block_by_height(0).unwrap();
+        let stats = storage.get_stats().unwrap();
+        assert_eq!(stats.trees["blocks"], 1);
+        assert_eq!(stats.trees["block_index"], 1);
+        assert!(stats.io.flushes >= 1);
+        // The block, its coinbase and journal entry written, then the block read back
+        assert!(stats.io.serializations >= 4);
+    }
+
     #[test]
     fn test_storage_stats() {
         let (storage, _temp_dir) = create_test_storage();
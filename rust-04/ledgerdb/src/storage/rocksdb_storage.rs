@@ -0,0 +1,473 @@
+//! [`Storage`] backed by RocksDB, behind the `rocksdb` feature.
+//!
+//! Each sled tree becomes a column family of the same name holding the same keys and
+//! values. A [`StorageBatch`] is collected into one RocksDB `WriteBatch`, which
+//! RocksDB applies atomically.
+
+use super::integrity::{self, RawTrees};
+use super::metrics::IoCounters;
+use super::{
+    keys, BatchOp, IntegrityReport, BlockchainMetadata, JournalEntry, JournalOperation, Storage, StorageBatch, StorageStats,
+};
+use crate::config::StorageConfig;
+use crate::core::{Block, BlockFilter, Transaction, UtxoEntry, UtxoId};
+use crate::crypto::Hash256;
+use crate::error::{Result, StorageError};
+use crate::auth::UserRecord;
+use crate::peers::PeerRecord;
+use crate::watch::WatchedAddress;
+use chrono::Utc;
+use rocksdb::{
+    BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, IteratorMode,
+    Options, WriteBatch, DB,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// RocksDB storage implementation
+pub struct RocksDbStorage {
+    db: DB,
+    /// Held while a batch is built and written: address index updates read the
+    /// current list before writing the new one
+    write_lock: Mutex<()>,
+    /// Next journal entry ID
+    next_journal_id: AtomicU64,
+    /// Block cache size in bytes
+    cache_capacity: u64,
+    io: IoCounters,
+}
+
+impl fmt::Debug for RocksDbStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RocksDbStorage").field("path", &self.db.path()).finish()
+    }
+}
+
+fn db_error(e: rocksdb::Error) -> StorageError {
+    StorageError::DatabaseError(e.to_string())
+}
+
+impl RocksDbStorage {
+    /// Open (or create) the database at `config.db_path`, with a block cache of
+    /// `config.cache_size_mb` and LZ4 compression if `config.enable_compression` is set
+    pub fn open(config: &StorageConfig) -> Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        options.set_compression_type(if config.enable_compression {
+            DBCompressionType::Lz4
+        } else {
+            DBCompressionType::None
+        });
+        let cache_capacity = config.cache_size_mb as u64 * 1024 * 1024;
+        let mut table = BlockBasedOptions::default();
+        table.set_block_cache(&Cache::new_lru_cache(cache_capacity as usize));
+        options.set_block_based_table_factory(&table);
+
+        let families = keys::TREES
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, options.clone()));
+        let db = DB::open_cf_descriptors(&options, &config.db_path, families).map_err(db_error)?;
+
+        let storage = Self {
+            db,
+            write_lock: Mutex::new(()),
+            next_journal_id: AtomicU64::new(0),
+            cache_capacity,
+            io: IoCounters::default(),
+        };
+        let last_journal_id = match storage.db.iterator_cf(storage.cf(keys::JOURNAL)?, IteratorMode::End).next() {
+            Some(entry) => {
+                let (key, _) = entry.map_err(db_error)?;
+                let id: [u8; 8] = key.as_ref().try_into()
+                    .map_err(|_| StorageError::SerializationError("malformed journal key".to_string()))?;
+                Some(u64::from_be_bytes(id))
+            }
+            None => None,
+        };
+        storage.next_journal_id.store(last_journal_id.map_or(0, |id| id + 1), Ordering::Relaxed);
+        Ok(storage)
+    }
+
+    fn cf(&self, name: &str) -> Result<&ColumnFamily> {
+        self.db.cf_handle(name)
+            .ok_or_else(|| StorageError::DatabaseError(format!("missing column family {}", name)).into())
+    }
+
+    fn get<T: DeserializeOwned>(&self, cf: &str, key: &[u8], what: impl FnOnce() -> String) -> Result<T> {
+        match self.db.get_cf(self.cf(cf)?, key).map_err(db_error)? {
+            Some(data) => self.decode(&data),
+            None => Err(StorageError::NotFound(what()).into()),
+        }
+    }
+
+    /// Every key and value in `cf`, in key order
+    fn entries(&self, cf: &str) -> Result<Vec<(Box<[u8]>, Box<[u8]>)>> {
+        self.db.iterator_cf(self.cf(cf)?, IteratorMode::Start)
+            .map(|entry| Ok(entry.map_err(db_error)?))
+            .collect()
+    }
+
+    fn values<T: DeserializeOwned>(&self, cf: &str) -> Result<Vec<T>> {
+        self.entries(cf)?.iter().map(|(_, data)| self.decode(data)).collect()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.io.flush(|| self.db.flush()).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(self.io.serialize(|| bincode::serialize(value))
+            .map_err(|e| StorageError::SerializationError(e.to_string()))?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        Ok(self.io.serialize(|| bincode::deserialize(data))
+            .map_err(|e| StorageError::SerializationError(e.to_string()))?)
+    }
+}
+
+/// A [`StorageBatch`] being turned into a `WriteBatch`
+struct BatchWriter<'a> {
+    storage: &'a RocksDbStorage,
+    batch: WriteBatch,
+    /// Values written earlier in the batch, which later reads must see
+    pending: HashMap<(&'static str, Vec<u8>), Option<Vec<u8>>>,
+}
+
+impl BatchWriter<'_> {
+    fn get(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.pending.get(&(cf, key.to_vec())) {
+            Some(value) => Ok(value.clone()),
+            None => Ok(self.storage.db.get_cf(self.storage.cf(cf)?, key).map_err(db_error)?),
+        }
+    }
+
+    fn put(&mut self, cf: &'static str, key: &[u8], value: Vec<u8>) -> Result<()> {
+        self.batch.put_cf(self.storage.cf(cf)?, key, &value);
+        self.pending.insert((cf, key.to_vec()), Some(value));
+        Ok(())
+    }
+
+    fn delete(&mut self, cf: &'static str, key: &[u8]) -> Result<()> {
+        self.batch.delete_cf(self.storage.cf(cf)?, key);
+        self.pending.insert((cf, key.to_vec()), None);
+        Ok(())
+    }
+
+    fn apply(&mut self, op: &BatchOp) -> Result<()> {
+        match op {
+            BatchOp::PutMetadata(metadata) => {
+                self.put(keys::METADATA, keys::BLOCKCHAIN_METADATA, self.storage.encode(metadata)?)?;
+            }
+            BatchOp::PutBlock(block) => {
+                let block_hash = block.hash();
+                self.put(keys::BLOCKS, block_hash.to_hex().as_bytes(), self.storage.encode(block)?)?;
+                self.put(keys::BLOCK_INDEX, &block.index.to_be_bytes(), block_hash.to_hex().into_bytes())?;
+                for tx in block.transactions() {
+                    self.put_transaction(tx, &block_hash)?;
+                }
+                self.journal(JournalOperation::AddBlock { block_hash, block_index: block.index }, block.index)?;
+            }
+            BatchOp::UnindexBlock { block_hash, height } => {
+                let key = height.to_be_bytes();
+                if self.get(keys::BLOCK_INDEX, &key)?.is_some_and(|hash| hash == block_hash.to_hex().as_bytes()) {
+                    self.delete(keys::BLOCK_INDEX, &key)?;
+                }
+                self.journal(JournalOperation::RemoveBlock { block_hash: block_hash.clone(), block_index: *height }, *height)?;
+            }
+            BatchOp::PutBlockFilter(filter) => {
+                self.put(keys::BLOCK_FILTERS, filter.block_hash.to_hex().as_bytes(), self.storage.encode(filter)?)?;
+            }
+            BatchOp::PutTransaction { transaction, block_hash } => {
+                self.put_transaction(transaction, block_hash)?;
+            }
+            BatchOp::PutUtxo { utxo_id, entry } => {
+                self.put(keys::UTXOS, utxo_id.to_string().as_bytes(), self.storage.encode(entry)?)?;
+                self.update_address_index(&entry.output.recipient, utxo_id, true)?;
+                self.journal(
+                    JournalOperation::CreateUtxo { utxo_id: utxo_id.clone(), utxo_entry: entry.clone() },
+                    entry.block_height,
+                )?;
+            }
+            BatchOp::RemoveUtxo { utxo_id, spent_at_height } => {
+                let key = utxo_id.to_string();
+                let data = self.get(keys::UTXOS, key.as_bytes())?
+                    .ok_or_else(|| StorageError::NotFound(format!("UTXO {}", key)))?;
+                let entry: UtxoEntry = self.storage.decode(&data)?;
+                self.delete(keys::UTXOS, key.as_bytes())?;
+                self.update_address_index(&entry.output.recipient, utxo_id, false)?;
+                self.journal(
+                    JournalOperation::SpendUtxo { utxo_id: utxo_id.clone(), spent_at_height: *spent_at_height },
+                    *spent_at_height,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn put_transaction(&mut self, transaction: &Transaction, block_hash: &Hash256) -> Result<()> {
+        let tx_key = transaction.hash().to_hex();
+        self.put(keys::TRANSACTIONS, tx_key.as_bytes(), self.storage.encode(transaction)?)?;
+        self.put(keys::TX_INDEX, tx_key.as_bytes(), block_hash.to_hex().into_bytes())
+    }
+
+    fn update_address_index(&mut self, address: &crate::crypto::Address, utxo_id: &UtxoId, add: bool) -> Result<()> {
+        let address_key = address.to_string();
+        let mut utxo_ids: Vec<UtxoId> = match self.get(keys::ADDRESS_INDEX, address_key.as_bytes())? {
+            Some(data) => self.storage.decode(&data)?,
+            None => Vec::new(),
+        };
+
+        if add {
+            if !utxo_ids.contains(utxo_id) {
+                utxo_ids.push(utxo_id.clone());
+            }
+        } else {
+            utxo_ids.retain(|id| id != utxo_id);
+        }
+
+        self.put(keys::ADDRESS_INDEX, address_key.as_bytes(), self.storage.encode(&utxo_ids)?)
+    }
+
+    /// Record `operation`; it commits with the rest of the batch
+    fn journal(&mut self, operation: JournalOperation, block_height: u64) -> Result<()> {
+        let entry = JournalEntry {
+            id: self.storage.next_journal_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: Utc::now(),
+            operation,
+            committed: true,
+            block_height,
+        };
+        self.put(keys::JOURNAL, &entry.id.to_be_bytes(), self.storage.encode(&entry)?)
+    }
+}
+
+impl Storage for RocksDbStorage {
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut writer = BatchWriter { storage: self, batch: WriteBatch::default(), pending: HashMap::new() };
+        for op in batch.ops() {
+            writer.apply(op)?;
+        }
+        self.db.write(writer.batch).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn load_metadata(&self) -> Result<BlockchainMetadata> {
+        self.get(keys::METADATA, keys::BLOCKCHAIN_METADATA, || "blockchain metadata".to_string())
+    }
+
+    fn load_block_by_hash(&self, block_hash: &Hash256) -> Result<Block> {
+        self.get(keys::BLOCKS, block_hash.to_hex().as_bytes(), || format!("block {}", block_hash.to_hex()))
+    }
+
+    fn load_block_by_height(&self, height: u64) -> Result<Block> {
+        match self.db.get_cf(self.cf(keys::BLOCK_INDEX)?, height.to_be_bytes()).map_err(db_error)? {
+            Some(hash_data) => self.load_block_by_hash(&parse_hash(&hash_data)?),
+            None => Err(StorageError::NotFound(format!("block at height {}", height)).into()),
+        }
+    }
+
+    fn load_all_blocks(&self) -> Result<Vec<Block>> {
+        // Big-endian height keys iterate in height order
+        self.entries(keys::BLOCK_INDEX)?
+            .iter()
+            .map(|(_, hash_data)| self.load_block_by_hash(&parse_hash(hash_data)?))
+            .collect()
+    }
+
+    fn load_stored_blocks(&self) -> Result<Vec<Block>> {
+        let mut blocks: Vec<Block> = self.values(keys::BLOCKS)?;
+        blocks.sort_by_key(|b| b.index);
+        Ok(blocks)
+    }
+
+    fn clear_indexes(&self) -> Result<()> {
+        let derived = [
+            keys::BLOCK_INDEX,
+            keys::TRANSACTIONS,
+            keys::TX_INDEX,
+            keys::UTXOS,
+            keys::ADDRESS_INDEX,
+            keys::BLOCK_FILTERS,
+        ];
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut batch = WriteBatch::default();
+        for name in derived {
+            let cf = self.cf(name)?;
+            for (key, _) in self.entries(name)? {
+                batch.delete_cf(cf, key);
+            }
+        }
+        self.db.write(batch).map_err(db_error)?;
+        self.flush()
+    }
+
+    fn load_block_filter(&self, block_hash: &Hash256) -> Result<BlockFilter> {
+        self.get(keys::BLOCK_FILTERS, block_hash.to_hex().as_bytes(), || {
+            format!("filter for block {}", block_hash.to_hex())
+        })
+    }
+
+    fn store_watched_address(&self, entry: &WatchedAddress) -> Result<()> {
+        self.db.put_cf(self.cf(keys::WATCH_LIST)?, entry.address.as_bytes(), self.encode(entry)?)
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove_watched_address(&self, address: &str) -> Result<bool> {
+        let cf = self.cf(keys::WATCH_LIST)?;
+        let existed = self.db.get_cf(cf, address.as_bytes()).map_err(db_error)?.is_some();
+        self.db.delete_cf(cf, address.as_bytes()).map_err(db_error)?;
+        Ok(existed)
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>> {
+        self.values(keys::WATCH_LIST)
+    }
+
+    fn store_user(&self, user: &UserRecord) -> Result<()> {
+        self.db.put_cf(self.cf(keys::USERS)?, user.username.as_bytes(), self.encode(user)?)
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove_user(&self, username: &str) -> Result<bool> {
+        let cf = self.cf(keys::USERS)?;
+        let existed = self.db.get_cf(cf, username.as_bytes()).map_err(db_error)?.is_some();
+        self.db.delete_cf(cf, username.as_bytes()).map_err(db_error)?;
+        Ok(existed)
+    }
+
+    fn load_users(&self) -> Result<Vec<UserRecord>> {
+        self.values(keys::USERS)
+    }
+
+    fn store_peer(&self, peer: &PeerRecord) -> Result<()> {
+        self.db.put_cf(self.cf(keys::PEERS)?, peer.address.as_bytes(), self.encode(peer)?)
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove_peer(&self, address: &str) -> Result<bool> {
+        let cf = self.cf(keys::PEERS)?;
+        let existed = self.db.get_cf(cf, address.as_bytes()).map_err(db_error)?.is_some();
+        self.db.delete_cf(cf, address.as_bytes()).map_err(db_error)?;
+        Ok(existed)
+    }
+
+    fn load_peers(&self) -> Result<Vec<PeerRecord>> {
+        self.values(keys::PEERS)
+    }
+
+    fn load_transaction(&self, tx_hash: &Hash256) -> Result<Transaction> {
+        self.get(keys::TRANSACTIONS, tx_hash.to_hex().as_bytes(), || format!("transaction {}", tx_hash.to_hex()))
+    }
+
+    fn load_utxo(&self, utxo_id: &UtxoId) -> Result<UtxoEntry> {
+        let utxo_key = utxo_id.to_string();
+        self.get(keys::UTXOS, utxo_key.as_bytes(), || format!("UTXO {}", utxo_key))
+    }
+
+    fn load_utxos_for_address(&self, address: &crate::crypto::Address) -> Result<Vec<UtxoEntry>> {
+        let cf = self.cf(keys::ADDRESS_INDEX)?;
+        let utxo_ids: Vec<UtxoId> = match self.db.get_cf(cf, address.to_hex().as_bytes()).map_err(db_error)? {
+            Some(data) => self.decode(&data)?,
+            None => return Ok(Vec::new()),
+        };
+        Ok(utxo_ids.iter().filter_map(|utxo_id| self.load_utxo(utxo_id).ok()).collect())
+    }
+
+    fn get_stats(&self) -> Result<StorageStats> {
+        let estimate = |name: &str, property: &str| -> Result<u64> {
+            Ok(self.db.property_int_value_cf(self.cf(name)?, property).map_err(db_error)?.unwrap_or(0))
+        };
+        let count = |name: &str| -> Result<usize> {
+            Ok(estimate(name, "rocksdb.estimate-num-keys")? as usize)
+        };
+        let mut database_size = 0;
+        let mut trees = BTreeMap::new();
+        for name in keys::TREES {
+            database_size += estimate(name, "rocksdb.total-sst-files-size")?;
+            trees.insert(name.to_string(), count(name)?);
+        }
+
+        Ok(StorageStats {
+            blocks_count: count(keys::BLOCKS)?,
+            transactions_count: count(keys::TRANSACTIONS)?,
+            utxos_count: count(keys::UTXOS)?,
+            journal_entries: count(keys::JOURNAL)?,
+            database_size,
+            trees,
+            cache_capacity: self.cache_capacity,
+            io: self.io.stats(),
+            last_updated: Utc::now(),
+        })
+    }
+
+    fn compact(&self) -> Result<()> {
+        // Remove committed journal entries older than 1 day
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        let journal = self.cf(keys::JOURNAL)?;
+        let mut batch = WriteBatch::default();
+        for (key, data) in self.entries(keys::JOURNAL)? {
+            let entry: JournalEntry = self.decode(&data)?;
+            if entry.committed && entry.timestamp < cutoff {
+                batch.delete_cf(journal, key);
+            }
+        }
+        self.db.write(batch).map_err(db_error)?;
+
+        for name in keys::TREES {
+            self.db.compact_range_cf(self.cf(name)?, None::<&[u8]>, None::<&[u8]>);
+        }
+        self.flush()
+    }
+
+    fn close(&self) -> Result<()> {
+        self.flush()
+    }
+
+    fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let report = integrity::check(self, repair)?;
+        self.flush()?;
+        Ok(report)
+    }
+}
+
+impl RawTrees for RocksDbStorage {
+    fn entries(&self, tree: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(RocksDbStorage::entries(self, tree)?
+            .into_iter()
+            .map(|(key, value)| (key.into_vec(), value.into_vec()))
+            .collect())
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> Result<()> {
+        self.db.put_cf(self.cf(tree)?, key, value).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> Result<()> {
+        self.db.delete_cf(self.cf(tree)?, key).map_err(db_error)?;
+        Ok(())
+    }
+}
+
+fn parse_hash(hash_data: &[u8]) -> Result<Hash256> {
+    let hash_str = std::str::from_utf8(hash_data)
+        .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+    Ok(Hash256::from_hex(hash_str)
+        .map_err(|e| StorageError::SerializationError(e.to_string()))?)
+}
@@ -0,0 +1,613 @@
+//! [`Storage`] backed by the `sled` embedded database.
+//!
+//! Every kind of record lives in its own tree; a [`StorageBatch`] is written in one
+//! sled transaction across the trees it touches, so it lands completely or not at all.
+//!
+//! sled fixes whether a database is zstd-compressed when it is created, so
+//! [`SledStorage::open`] refuses a database whose compression differs from
+//! `storage.enable_compression` rather than letting sled fail on it.
+
+use super::integrity::{self, RawTrees};
+use super::metrics::IoCounters;
+use super::{
+    keys, BatchOp, IntegrityReport, BlockchainMetadata, JournalEntry, JournalOperation, Storage, StorageBatch, StorageStats,
+};
+use crate::config::StorageConfig;
+use crate::core::{Block, BlockFilter, Transaction, UtxoEntry, UtxoId};
+use crate::crypto::Hash256;
+use crate::error::{LedgerError, Result, StorageError};
+use crate::auth::UserRecord;
+use crate::peers::PeerRecord;
+use crate::watch::WatchedAddress;
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sled::transaction::{
+    abort, ConflictableTransactionError, TransactionError, TransactionalTree,
+};
+use sled::{Db, Transactional, Tree};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+type TxResult<T> = std::result::Result<T, ConflictableTransactionError<StorageError>>;
+
+/// sled storage implementation
+#[derive(Debug)]
+pub struct SledStorage {
+    /// Main database instance
+    db: Db,
+    /// Blocks tree
+    blocks: Tree,
+    /// Transactions tree
+    transactions: Tree,
+    /// UTXOs tree
+    utxos: Tree,
+    /// Metadata tree
+    metadata: Tree,
+    /// Journal tree for atomic operations
+    journal: Tree,
+    /// Block index (hash -> height)
+    block_index: Tree,
+    /// Transaction index (hash -> block_hash)
+    tx_index: Tree,
+    /// Address index (address -> [utxo_ids])
+    address_index: Tree,
+    /// Compact block filters (block hash -> filter)
+    block_filters: Tree,
+    /// Watched addresses (hex address -> entry)
+    watch_list: Tree,
+    users: Tree,
+    /// Address book (ip:port -> peer)
+    peers: Tree,
+    /// Page cache size in bytes
+    cache_capacity: u64,
+    io: IoCounters,
+}
+
+/// How long [`open_unlocked`] waits for a just-closed database's lock
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+
+/// Open `config`, waiting out the file lock of a handle to the same database that was
+/// just dropped: sled's flusher thread keeps the lock until its next tick
+fn open_unlocked(config: &sled::Config) -> Result<sled::Db> {
+    let started = Instant::now();
+    loop {
+        match config.open() {
+            // sled reports a held lock as `ErrorKind::Other`, so only its message tells
+            Err(sled::Error::Io(e)) if e.to_string().starts_with("could not acquire lock") && started.elapsed() < LOCK_WAIT => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            result => return result.map_err(|e| StorageError::DatabaseError(e.to_string()).into()),
+        }
+    }
+}
+
+impl SledStorage {
+    /// Open (or create) the database at `config.db_path`, with a page cache of
+    /// `config.cache_size_mb`, compressed if `config.enable_compression` is set and
+    /// ledgerdb was built with the `sled-compression` feature
+    pub fn open(config: &StorageConfig) -> Result<Self> {
+        let compress = config.enable_compression && cfg!(feature = "sled-compression");
+        if config.enable_compression && !compress {
+            info!("sled compression needs the `sled-compression` feature; storing uncompressed");
+        }
+        check_compression(&config.db_path, compress)?;
+
+        let cache_capacity = config.cache_size_mb as u64 * 1024 * 1024;
+        let sled_config = sled::Config::new()
+            .path(&config.db_path)
+            .cache_capacity(cache_capacity)
+            .use_compression(compress);
+        let db = open_unlocked(&sled_config)?;
+        let tree = |name: &str| {
+            db.open_tree(name)
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))
+        };
+
+        Ok(Self {
+            blocks: tree(keys::BLOCKS)?,
+            transactions: tree(keys::TRANSACTIONS)?,
+            utxos: tree(keys::UTXOS)?,
+            metadata: tree(keys::METADATA)?,
+            journal: tree(keys::JOURNAL)?,
+            block_index: tree(keys::BLOCK_INDEX)?,
+            tx_index: tree(keys::TX_INDEX)?,
+            address_index: tree(keys::ADDRESS_INDEX)?,
+            block_filters: tree(keys::BLOCK_FILTERS)?,
+            watch_list: tree(keys::WATCH_LIST)?,
+            users: tree(keys::USERS)?,
+            peers: tree(keys::PEERS)?,
+            db,
+            cache_capacity,
+            io: IoCounters::default(),
+        })
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.io.flush(|| self.db.flush())
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn tree(&self, name: &str) -> Result<&Tree> {
+        let tree = match name {
+            keys::BLOCKS => &self.blocks,
+            keys::TRANSACTIONS => &self.transactions,
+            keys::UTXOS => &self.utxos,
+            keys::METADATA => &self.metadata,
+            keys::JOURNAL => &self.journal,
+            keys::BLOCK_INDEX => &self.block_index,
+            keys::TX_INDEX => &self.tx_index,
+            keys::ADDRESS_INDEX => &self.address_index,
+            keys::BLOCK_FILTERS => &self.block_filters,
+            keys::WATCH_LIST => &self.watch_list,
+            keys::USERS => &self.users,
+            keys::PEERS => &self.peers,
+            _ => return Err(StorageError::DatabaseError(format!("unknown tree {}", name)).into()),
+        };
+        Ok(tree)
+    }
+}
+
+impl RawTrees for SledStorage {
+    fn entries(&self, tree: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut entries = Vec::new();
+        for result in self.tree(tree)?.iter() {
+            let (key, value) = result
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            entries.push((key.to_vec(), value.to_vec()));
+        }
+        Ok(entries)
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> Result<()> {
+        self.tree(tree)?.insert(key, value)
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> Result<()> {
+        self.tree(tree)?.remove(key)
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Whether the database at `db_path` was created compressed, from the `conf` file
+/// sled writes there; `None` for a new database
+fn stored_compression(db_path: &Path) -> Result<Option<bool>> {
+    let conf = match std::fs::read(db_path.join("conf")) {
+        Ok(conf) => conf,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(StorageError::DatabaseError(format!("Failed to read sled config: {}", e)).into()),
+    };
+    // `key: value` lines followed by a binary checksum
+    Ok(String::from_utf8_lossy(&conf)
+        .lines()
+        .find_map(|line| line.strip_prefix("use_compression:"))
+        .and_then(|value| value.trim().parse().ok()))
+}
+
+/// Fail with migration steps if an existing database's compression is not `compress`
+fn check_compression(db_path: &Path, compress: bool) -> Result<()> {
+    let Some(stored) = stored_compression(db_path)? else {
+        return Ok(());
+    };
+    if stored == compress {
+        return Ok(());
+    }
+
+    let created = if stored { "with" } else { "without" };
+    let fix = if stored && !cfg!(feature = "sled-compression") {
+        "Opening it needs ledgerdb built with the `sled-compression` feature.".to_string()
+    } else {
+        format!(
+            "Set storage.enable_compression = {} to keep using it, or migrate: \
+             `LEDGER_ENABLE_COMPRESSION={} ledgerdb export -o chain.jsonl`, then \
+             `ledgerdb import chain.jsonl` into a new storage.db_path.",
+            stored, stored
+        )
+    };
+    Err(LedgerError::Config(format!(
+        "sled database {} was created {} compression. {}",
+        db_path.display(), created, fix
+    )))
+}
+
+fn encode<T: Serialize>(io: &IoCounters, value: &T) -> std::result::Result<Vec<u8>, StorageError> {
+    io.serialize(|| bincode::serialize(value)).map_err(|e| StorageError::SerializationError(e.to_string()))
+}
+
+fn decode<T: DeserializeOwned>(io: &IoCounters, data: &[u8]) -> std::result::Result<T, StorageError> {
+    io.serialize(|| bincode::deserialize(data)).map_err(|e| StorageError::SerializationError(e.to_string()))
+}
+
+fn get<T: DeserializeOwned>(io: &IoCounters, tree: &Tree, key: &[u8], what: impl FnOnce() -> String) -> Result<T> {
+    match tree.get(key)
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
+        Some(data) => Ok(decode(io, data.as_ref())?),
+        None => Err(StorageError::NotFound(what()).into()),
+    }
+}
+
+/// Every value in `tree`, in key order
+fn values<T: DeserializeOwned>(io: &IoCounters, tree: &Tree) -> Result<Vec<T>> {
+    let mut values = Vec::new();
+    for result in tree.iter() {
+        let (_, data) = result
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        values.push(decode(io, data.as_ref())?);
+    }
+    Ok(values)
+}
+
+/// The trees a batch writes to, inside one sled transaction
+struct BatchTrees<'a> {
+    blocks: &'a TransactionalTree,
+    block_index: &'a TransactionalTree,
+    transactions: &'a TransactionalTree,
+    tx_index: &'a TransactionalTree,
+    utxos: &'a TransactionalTree,
+    address_index: &'a TransactionalTree,
+    metadata: &'a TransactionalTree,
+    block_filters: &'a TransactionalTree,
+    journal: &'a TransactionalTree,
+    io: &'a IoCounters,
+}
+
+impl BatchTrees<'_> {
+    fn apply(&self, op: &BatchOp) -> TxResult<()> {
+        match op {
+            BatchOp::PutMetadata(metadata) => {
+                self.metadata.insert(keys::BLOCKCHAIN_METADATA, self.encode(metadata)?)?;
+            }
+            BatchOp::PutBlock(block) => {
+                let block_hash = block.hash();
+                self.blocks.insert(block_hash.to_hex().as_bytes(), self.encode(block)?)?;
+                self.block_index.insert(&block.index.to_be_bytes(), block_hash.to_hex().as_bytes())?;
+                for tx in block.transactions() {
+                    self.put_transaction(tx, &block_hash)?;
+                }
+                self.journal(JournalOperation::AddBlock { block_hash, block_index: block.index }, block.index)?;
+            }
+            BatchOp::UnindexBlock { block_hash, height } => {
+                let key = height.to_be_bytes();
+                if self.block_index.get(key)?.is_some_and(|hash| hash.as_ref() == block_hash.to_hex().as_bytes()) {
+                    self.block_index.remove(&key)?;
+                }
+                self.journal(JournalOperation::RemoveBlock { block_hash: block_hash.clone(), block_index: *height }, *height)?;
+            }
+            BatchOp::PutBlockFilter(filter) => {
+                self.block_filters.insert(filter.block_hash.to_hex().as_bytes(), self.encode(filter)?)?;
+            }
+            BatchOp::PutTransaction { transaction, block_hash } => {
+                self.put_transaction(transaction, block_hash)?;
+            }
+            BatchOp::PutUtxo { utxo_id, entry } => {
+                self.utxos.insert(utxo_id.to_string().as_bytes(), self.encode(entry)?)?;
+                self.update_address_index(&entry.output.recipient, utxo_id, true)?;
+                self.journal(
+                    JournalOperation::CreateUtxo { utxo_id: utxo_id.clone(), utxo_entry: entry.clone() },
+                    entry.block_height,
+                )?;
+            }
+            BatchOp::RemoveUtxo { utxo_id, spent_at_height } => {
+                let key = utxo_id.to_string();
+                let Some(data) = self.utxos.remove(key.as_bytes())? else {
+                    return abort(StorageError::NotFound(format!("UTXO {}", key)));
+                };
+                let entry: UtxoEntry = self.decode(data.as_ref())?;
+                self.update_address_index(&entry.output.recipient, utxo_id, false)?;
+                self.journal(
+                    JournalOperation::SpendUtxo { utxo_id: utxo_id.clone(), spent_at_height: *spent_at_height },
+                    *spent_at_height,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn put_transaction(&self, transaction: &Transaction, block_hash: &Hash256) -> TxResult<()> {
+        let tx_key = transaction.hash().to_hex();
+        self.transactions.insert(tx_key.as_bytes(), self.encode(transaction)?)?;
+        self.tx_index.insert(tx_key.as_bytes(), block_hash.to_hex().as_bytes())?;
+        Ok(())
+    }
+
+    fn update_address_index(
+        &self,
+        address: &crate::crypto::Address,
+        utxo_id: &UtxoId,
+        add: bool,
+    ) -> TxResult<()> {
+        let address_key = address.to_hex();
+        let mut utxo_ids: Vec<UtxoId> = match self.address_index.get(address_key.as_bytes())? {
+            Some(data) => self.decode(data.as_ref())?,
+            None => Vec::new(),
+        };
+
+        if add {
+            if !utxo_ids.contains(utxo_id) {
+                utxo_ids.push(utxo_id.clone());
+            }
+        } else {
+            utxo_ids.retain(|id| id != utxo_id);
+        }
+
+        self.address_index.insert(address_key.as_bytes(), self.encode(&utxo_ids)?)?;
+        Ok(())
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> TxResult<Vec<u8>> {
+        encode(self.io, value).map_err(ConflictableTransactionError::Abort)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> TxResult<T> {
+        decode(self.io, data).map_err(ConflictableTransactionError::Abort)
+    }
+
+    /// Record `operation`; it commits with the rest of the batch
+    fn journal(&self, operation: JournalOperation, block_height: u64) -> TxResult<()> {
+        let entry = JournalEntry {
+            id: self.journal.generate_id()?,
+            timestamp: Utc::now(),
+            operation,
+            committed: true,
+            block_height,
+        };
+        self.journal.insert(&entry.id.to_be_bytes(), self.encode(&entry)?)?;
+        Ok(())
+    }
+}
+
+impl Storage for SledStorage {
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let trees = (
+            &self.blocks,
+            &self.block_index,
+            &self.transactions,
+            &self.tx_index,
+            &self.utxos,
+            &self.address_index,
+            &self.metadata,
+            &self.block_filters,
+            &self.journal,
+        );
+        trees
+            .transaction(|(blocks, block_index, transactions, tx_index, utxos, address_index, metadata, block_filters, journal)| {
+                let trees = BatchTrees {
+                    blocks, block_index, transactions, tx_index, utxos, address_index, metadata, block_filters, journal,
+                    io: &self.io,
+                };
+                for op in batch.ops() {
+                    trees.apply(op)?;
+                }
+                Ok(())
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => StorageError::DatabaseError(e.to_string()),
+            })?;
+
+        self.flush()
+    }
+
+    fn load_metadata(&self) -> Result<BlockchainMetadata> {
+        get(&self.io, &self.metadata, keys::BLOCKCHAIN_METADATA, || "blockchain metadata".to_string())
+    }
+
+    fn load_block_by_hash(&self, block_hash: &Hash256) -> Result<Block> {
+        get(&self.io, &self.blocks, block_hash.to_hex().as_bytes(), || format!("block {}", block_hash.to_hex()))
+    }
+
+    fn load_block_by_height(&self, height: u64) -> Result<Block> {
+        match self.block_index.get(height.to_be_bytes())
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
+            Some(hash_data) => {
+                let hash_str = String::from_utf8(hash_data.to_vec())
+                    .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+                let block_hash = Hash256::from_hex(&hash_str)
+                    .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+                self.load_block_by_hash(&block_hash)
+            }
+            None => Err(StorageError::NotFound(format!("block at height {}", height)).into()),
+        }
+    }
+
+    fn load_all_blocks(&self) -> Result<Vec<Block>> {
+        let mut blocks = Vec::new();
+
+        // Big-endian height keys iterate in height order
+        for result in self.block_index.iter() {
+            let (_, hash_bytes) = result
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+            let hash_str = String::from_utf8(hash_bytes.to_vec())
+                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+            let block_hash = Hash256::from_hex(&hash_str)
+                .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+
+            blocks.push(self.load_block_by_hash(&block_hash)?);
+        }
+
+        blocks.sort_by_key(|b| b.index);
+        Ok(blocks)
+    }
+
+    fn load_stored_blocks(&self) -> Result<Vec<Block>> {
+        let mut blocks: Vec<Block> = values(&self.io, &self.blocks)?;
+        blocks.sort_by_key(|b| b.index);
+        Ok(blocks)
+    }
+
+    fn clear_indexes(&self) -> Result<()> {
+        let derived = [
+            &self.block_index,
+            &self.transactions,
+            &self.tx_index,
+            &self.utxos,
+            &self.address_index,
+            &self.block_filters,
+        ];
+        for tree in derived {
+            tree.clear()
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        }
+        self.flush()
+    }
+
+    fn load_block_filter(&self, block_hash: &Hash256) -> Result<BlockFilter> {
+        get(&self.io, &self.block_filters, block_hash.to_hex().as_bytes(), || {
+            format!("filter for block {}", block_hash.to_hex())
+        })
+    }
+
+    fn store_watched_address(&self, entry: &WatchedAddress) -> Result<()> {
+        self.watch_list.insert(entry.address.as_bytes(), encode(&self.io, entry)?)
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove_watched_address(&self, address: &str) -> Result<bool> {
+        let removed = self.watch_list.remove(address.as_bytes())
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(removed.is_some())
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>> {
+        values(&self.io, &self.watch_list)
+    }
+
+    fn store_user(&self, user: &UserRecord) -> Result<()> {
+        self.users.insert(user.username.as_bytes(), encode(&self.io, user)?)
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove_user(&self, username: &str) -> Result<bool> {
+        let removed = self.users.remove(username.as_bytes())
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(removed.is_some())
+    }
+
+    fn load_users(&self) -> Result<Vec<UserRecord>> {
+        values(&self.io, &self.users)
+    }
+
+    fn store_peer(&self, peer: &PeerRecord) -> Result<()> {
+        self.peers.insert(peer.address.as_bytes(), encode(&self.io, peer)?)
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove_peer(&self, address: &str) -> Result<bool> {
+        let removed = self.peers.remove(address.as_bytes())
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(removed.is_some())
+    }
+
+    fn load_peers(&self) -> Result<Vec<PeerRecord>> {
+        values(&self.io, &self.peers)
+    }
+
+    fn load_transaction(&self, tx_hash: &Hash256) -> Result<Transaction> {
+        get(&self.io, &self.transactions, tx_hash.to_hex().as_bytes(), || format!("transaction {}", tx_hash.to_hex()))
+    }
+
+    fn load_utxo(&self, utxo_id: &UtxoId) -> Result<UtxoEntry> {
+        let utxo_key = utxo_id.to_string();
+        get(&self.io, &self.utxos, utxo_key.as_bytes(), || format!("UTXO {}", utxo_key))
+    }
+
+    fn load_utxos_for_address(&self, address: &crate::crypto::Address) -> Result<Vec<UtxoEntry>> {
+        let utxo_ids: Vec<UtxoId> = match self.address_index.get(address.to_hex().as_bytes())
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))? {
+            Some(data) => decode(&self.io, data.as_ref())?,
+            None => return Ok(Vec::new()),
+        };
+        Ok(utxo_ids.iter().filter_map(|utxo_id| self.load_utxo(utxo_id).ok()).collect())
+    }
+
+    fn get_stats(&self) -> Result<StorageStats> {
+        let database_size = self.db.size_on_disk()
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let mut trees = BTreeMap::new();
+        for name in keys::TREES {
+            trees.insert(name.to_string(), self.tree(name)?.len());
+        }
+
+        Ok(StorageStats {
+            blocks_count: self.blocks.len(),
+            transactions_count: self.transactions.len(),
+            utxos_count: self.utxos.len(),
+            journal_entries: self.journal.len(),
+            database_size,
+            trees,
+            cache_capacity: self.cache_capacity,
+            io: self.io.stats(),
+            last_updated: Utc::now(),
+        })
+    }
+
+    fn compact(&self) -> Result<()> {
+        // Remove committed journal entries older than 1 day
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        let mut to_remove = Vec::new();
+        for result in self.journal.iter() {
+            let (key, data) = result
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            let entry: JournalEntry = decode(&self.io, &data)?;
+            if entry.committed && entry.timestamp < cutoff {
+                to_remove.push(key);
+            }
+        }
+
+        for key in to_remove {
+            self.journal.remove(key)
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        }
+
+        self.flush()
+    }
+
+    fn close(&self) -> Result<()> {
+        self.flush()
+    }
+
+    fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        let report = integrity::check(self, repair)?;
+        self.flush()?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compression_change_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig {
+            db_path: temp_dir.path().join("db"),
+            enable_compression: false,
+            ..StorageConfig::default()
+        };
+        assert_eq!(stored_compression(&config.db_path).unwrap(), None);
+
+        drop(SledStorage::open(&config).unwrap());
+        assert_eq!(stored_compression(&config.db_path).unwrap(), Some(false));
+        assert!(check_compression(&config.db_path, false).is_ok());
+        let error = check_compression(&config.db_path, true).unwrap_err().to_string();
+        assert!(error.contains("created without compression"), "{}", error);
+        assert!(error.contains("LEDGER_ENABLE_COMPRESSION=false ledgerdb export"), "{}", error);
+    }
+}
@@ -0,0 +1,762 @@
+//! SQLite index of the stored chain, behind the `sqlite-index` feature.
+//!
+//! The key-value trees answer lookups by hash, height or UTXO id. Explorer queries
+//! such as "every transaction of this address between two dates" need secondary
+//! indexes instead, so [`SqliteIndex`] keeps blocks, transactions, outputs and
+//! per-address activity in SQL tables. It is derived data: [`IndexedStorage`]
+//! updates it after each batch the primary backend commits, and
+//! [`SqliteIndex::catch_up`] re-indexes whatever it missed. Transaction memos
+//! that are valid UTF-8 are indexed too, so payments can be found by the invoice
+//! reference they carry.
+
+use super::{BatchOp, BlockchainMetadata, IntegrityReport, Storage, StorageBatch, StorageStats};
+use crate::core::{Block, BlockFilter, Transaction, UtxoEntry, UtxoId};
+use crate::crypto::{Address, Hash256};
+use crate::error::{Result, StorageError};
+use crate::auth::UserRecord;
+use crate::peers::PeerRecord;
+use crate::watch::WatchedAddress;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::warn;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS blocks (
+        height INTEGER PRIMARY KEY,
+        hash TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        tx_count INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS blocks_by_time ON blocks (timestamp);
+
+    CREATE TABLE IF NOT EXISTS transactions (
+        hash TEXT PRIMARY KEY,
+        block_height INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        is_coinbase INTEGER NOT NULL,
+        memo TEXT
+    );
+    CREATE INDEX IF NOT EXISTS transactions_by_block ON transactions (block_height, position);
+    CREATE INDEX IF NOT EXISTS transactions_by_memo ON transactions (memo);
+
+    CREATE TABLE IF NOT EXISTS outputs (
+        tx_hash TEXT NOT NULL,
+        output_index INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        block_height INTEGER NOT NULL,
+        spent_by TEXT,
+        spent_height INTEGER,
+        PRIMARY KEY (tx_hash, output_index)
+    );
+    CREATE INDEX IF NOT EXISTS outputs_by_address ON outputs (address);
+    CREATE INDEX IF NOT EXISTS outputs_by_spent_height ON outputs (spent_height);
+
+    CREATE TABLE IF NOT EXISTS address_activity (
+        address TEXT NOT NULL,
+        tx_hash TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        received INTEGER NOT NULL,
+        sent INTEGER NOT NULL,
+        PRIMARY KEY (address, tx_hash)
+    );
+    CREATE INDEX IF NOT EXISTS address_activity_by_time
+        ON address_activity (address, timestamp, block_height, position);
+    CREATE INDEX IF NOT EXISTS address_activity_by_height ON address_activity (block_height);
+";
+
+fn db_error(e: rusqlite::Error) -> StorageError {
+    StorageError::DatabaseError(e.to_string())
+}
+
+/// SQLite stores integers signed
+fn to_sql_int(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// One transaction of an address's history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressTransaction {
+    pub tx_hash: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    /// Timestamp of the block holding the transaction
+    pub timestamp: DateTime<Utc>,
+    /// Total the transaction paid to the address
+    pub received: u64,
+    /// Total the transaction spent from the address
+    pub sent: u64,
+    /// The transaction's memo, if it is text
+    pub memo: Option<String>,
+}
+
+/// A page of an address's history, newest first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressHistory {
+    pub transactions: Vec<AddressTransaction>,
+    /// Transactions matching the query across all pages
+    pub total: u64,
+}
+
+/// Which part of an address's history to return
+#[derive(Debug, Clone, Default)]
+pub struct AddressHistoryQuery {
+    /// Only blocks timestamped at or after this
+    pub from: Option<DateTime<Utc>>,
+    /// Only blocks timestamped before this
+    pub to: Option<DateTime<Utc>>,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+/// A confirmed transaction whose memo matched a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoTransaction {
+    pub tx_hash: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    /// Timestamp of the block holding the transaction
+    pub timestamp: DateTime<Utc>,
+    pub memo: String,
+}
+
+/// A page of memo search results, newest first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoSearch {
+    pub transactions: Vec<MemoTransaction>,
+    /// Transactions matching the query across all pages
+    pub total: u64,
+}
+
+/// What [`SqliteIndex::search_memos`] looks for
+#[derive(Debug, Clone, Default)]
+pub struct MemoQuery {
+    pub memo: String,
+    /// Match memos starting with `memo` rather than equal to it
+    pub prefix: bool,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+/// SQL tables indexing the stored blocks
+pub struct SqliteIndex {
+    conn: Mutex<Connection>,
+}
+
+impl fmt::Debug for SqliteIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteIndex").finish_non_exhaustive()
+    }
+}
+
+impl SqliteIndex {
+    /// Open or create the index database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_connection(Connection::open(path).map_err(db_error)?)
+    }
+
+    /// Empty index held in memory
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        // Indexes from before memos were indexed lack the column; the table must
+        // exist before the schema's index on it is created
+        let has_transactions: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'transactions'", [], |row| row.get(0))
+            .map_err(db_error)?;
+        let has_memo: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM pragma_table_info('transactions') WHERE name = 'memo'", [], |row| row.get(0))
+            .map_err(db_error)?;
+        if has_transactions && !has_memo {
+            conn.execute_batch("ALTER TABLE transactions ADD COLUMN memo TEXT").map_err(db_error)?;
+        }
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        let index = Self { conn: Mutex::new(conn) };
+        if has_transactions && !has_memo {
+            // Re-indexed from scratch by the next catch-up, picking up the memos
+            index.clear()?;
+        }
+        Ok(index)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Height of the highest indexed block
+    pub fn tip_height(&self) -> Result<Option<u64>> {
+        let height: Option<i64> = self.conn()
+            .query_row("SELECT MAX(height) FROM blocks", [], |row| row.get(0))
+            .map_err(db_error)?;
+        Ok(height.map(|h| h as u64))
+    }
+
+    /// Index `block`, first dropping anything indexed at its height or above, which
+    /// a reorg replaced
+    pub fn index_block(&self, block: &Block) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db_error)?;
+        Self::rewind(&tx, block.index)?;
+
+        let height = to_sql_int(block.index);
+        let timestamp = block.header.timestamp.timestamp_millis();
+        tx.execute(
+            "INSERT INTO blocks (height, hash, timestamp, tx_count) VALUES (?1, ?2, ?3, ?4)",
+            params![height, block.hash().to_hex(), timestamp, block.transactions().len() as i64],
+        ).map_err(db_error)?;
+
+        for (position, transaction) in block.transactions().iter().enumerate() {
+            let tx_hash = transaction.hash().to_hex();
+            let coinbase = transaction.is_coinbase();
+            // A coinbase memo is the node's own note, not a payment reference
+            let memo = transaction.memo_text().filter(|_| !coinbase);
+            tx.execute(
+                "INSERT OR REPLACE INTO transactions (hash, block_height, position, is_coinbase, memo)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![tx_hash, height, position as i64, coinbase, memo],
+            ).map_err(db_error)?;
+
+            // Address -> (received, sent)
+            let mut activity: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+            if !coinbase {
+                for input in &transaction.inputs {
+                    let spent: Option<(String, i64)> = tx
+                        .query_row(
+                            "SELECT address, amount FROM outputs WHERE tx_hash = ?1 AND output_index = ?2",
+                            params![input.previous_tx_hash.to_hex(), input.output_index],
+                            |row| Ok((row.get(0)?, row.get(1)?)),
+                        )
+                        .optional()
+                        .map_err(db_error)?;
+                    let Some((address, amount)) = spent else { continue };
+                    tx.execute(
+                        "UPDATE outputs SET spent_by = ?1, spent_height = ?2
+                         WHERE tx_hash = ?3 AND output_index = ?4",
+                        params![tx_hash, height, input.previous_tx_hash.to_hex(), input.output_index],
+                    ).map_err(db_error)?;
+                    activity.entry(address).or_default().1 += amount as u64;
+                }
+            }
+            for (output_index, output) in transaction.outputs.iter().enumerate() {
+                let address = output.recipient.to_hex();
+                tx.execute(
+                    "INSERT OR REPLACE INTO outputs (tx_hash, output_index, address, amount, block_height)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![tx_hash, output_index as i64, address, to_sql_int(output.amount), height],
+                ).map_err(db_error)?;
+                activity.entry(address).or_default().0 += output.amount;
+            }
+
+            for (address, (received, sent)) in activity {
+                tx.execute(
+                    "INSERT OR REPLACE INTO address_activity
+                     (address, tx_hash, block_height, position, timestamp, received, sent)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        address, tx_hash, height, position as i64, timestamp,
+                        to_sql_int(received), to_sql_int(sent),
+                    ],
+                ).map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Forget every block at `height` and above
+    fn rewind(tx: &rusqlite::Transaction<'_>, height: u64) -> Result<()> {
+        let height = to_sql_int(height);
+        tx.execute(
+            "UPDATE outputs SET spent_by = NULL, spent_height = NULL WHERE spent_height >= ?1",
+            [height],
+        ).map_err(db_error)?;
+        for table in ["outputs", "address_activity", "transactions"] {
+            tx.execute(&format!("DELETE FROM {} WHERE block_height >= ?1", table), [height])
+                .map_err(db_error)?;
+        }
+        tx.execute("DELETE FROM blocks WHERE height >= ?1", [height]).map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Drop everything indexed
+    pub fn clear(&self) -> Result<()> {
+        self.conn()
+            .execute_batch(
+                "DELETE FROM address_activity; DELETE FROM outputs;
+                 DELETE FROM transactions; DELETE FROM blocks;",
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Index the blocks of `storage` above the indexed tip, e.g. ones committed
+    /// while indexing failed or before the index was enabled. An index whose tip
+    /// `storage` no longer holds is rebuilt from scratch.
+    pub fn catch_up(&self, storage: &dyn Storage) -> Result<usize> {
+        let blocks = storage.load_all_blocks()?;
+        let mut tip = self.tip_height()?;
+        if let Some(height) = tip {
+            let indexed_hash: String = self.conn()
+                .query_row("SELECT hash FROM blocks WHERE height = ?1", [to_sql_int(height)], |row| row.get(0))
+                .map_err(db_error)?;
+            let stored = blocks.iter().find(|block| block.index == height);
+            if stored.map(|block| block.hash().to_hex()) != Some(indexed_hash) {
+                self.clear()?;
+                tip = None;
+            }
+        }
+
+        let mut indexed = 0;
+        for block in blocks.iter().filter(|block| tip.is_none_or(|tip| block.index > tip)) {
+            self.index_block(block)?;
+            indexed += 1;
+        }
+        Ok(indexed)
+    }
+
+    /// Confirmed transactions paying or spending from `address`, newest first
+    pub fn address_history(&self, address: &str, query: &AddressHistoryQuery) -> Result<AddressHistory> {
+        let from = query.from.map_or(i64::MIN, |t| t.timestamp_millis());
+        let to = query.to.map_or(i64::MAX, |t| t.timestamp_millis());
+        let conn = self.conn();
+
+        let total: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM address_activity
+                 WHERE address = ?1 AND timestamp >= ?2 AND timestamp < ?3",
+                params![address, from, to],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT a.tx_hash, a.block_height, b.hash, a.timestamp, a.received, a.sent, t.memo
+                 FROM address_activity a JOIN blocks b ON b.height = a.block_height
+                 JOIN transactions t ON t.hash = a.tx_hash
+                 WHERE a.address = ?1 AND a.timestamp >= ?2 AND a.timestamp < ?3
+                 ORDER BY a.timestamp DESC, a.block_height DESC, a.position DESC
+                 LIMIT ?4 OFFSET ?5",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(
+                params![address, from, to, to_sql_int(query.limit), to_sql_int(query.offset)],
+                |row| {
+                    Ok(AddressTransaction {
+                        tx_hash: row.get(0)?,
+                        block_height: row.get::<_, i64>(1)? as u64,
+                        block_hash: row.get(2)?,
+                        timestamp: DateTime::from_timestamp_millis(row.get::<_, i64>(3)?).unwrap_or_default(),
+                        received: row.get::<_, i64>(4)? as u64,
+                        sent: row.get::<_, i64>(5)? as u64,
+                        memo: row.get(6)?,
+                    })
+                },
+            )
+            .map_err(db_error)?;
+
+        Ok(AddressHistory {
+            transactions: rows.collect::<rusqlite::Result<_>>().map_err(db_error)?,
+            total: total as u64,
+        })
+    }
+
+    /// Every transaction of `address` in blocks above `above` (all blocks when
+    /// `None`), oldest first
+    pub fn address_activity_since(&self, address: &str, above: Option<u64>) -> Result<Vec<AddressTransaction>> {
+        let above = above.map_or(-1, to_sql_int);
+        let conn = self.conn();
+        let mut statement = conn
+            .prepare(
+                "SELECT a.tx_hash, a.block_height, b.hash, a.timestamp, a.received, a.sent, t.memo
+                 FROM address_activity a JOIN blocks b ON b.height = a.block_height
+                 JOIN transactions t ON t.hash = a.tx_hash
+                 WHERE a.address = ?1 AND a.block_height > ?2
+                 ORDER BY a.block_height, a.position",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![address, above], |row| {
+                Ok(AddressTransaction {
+                    tx_hash: row.get(0)?,
+                    block_height: row.get::<_, i64>(1)? as u64,
+                    block_hash: row.get(2)?,
+                    timestamp: DateTime::from_timestamp_millis(row.get::<_, i64>(3)?).unwrap_or_default(),
+                    received: row.get::<_, i64>(4)? as u64,
+                    sent: row.get::<_, i64>(5)? as u64,
+                    memo: row.get(6)?,
+                })
+            })
+            .map_err(db_error)?;
+        let activity = rows.collect::<rusqlite::Result<_>>().map_err(db_error)?;
+        Ok(activity)
+    }
+
+    /// Confirmed transactions whose memo equals, or with `prefix` starts with,
+    /// `query.memo`, newest first
+    pub fn search_memos(&self, query: &MemoQuery) -> Result<MemoSearch> {
+        // `substr` counts characters, as `length` does for text
+        let filter = "t.memo IS NOT NULL AND (t.memo = ?1 OR (?2 AND substr(t.memo, 1, length(?1)) = ?1))";
+        let conn = self.conn();
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM transactions t WHERE {}", filter),
+                params![query.memo, query.prefix],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT t.hash, t.block_height, b.hash, b.timestamp, t.memo
+                 FROM transactions t JOIN blocks b ON b.height = t.block_height
+                 WHERE {}
+                 ORDER BY t.block_height DESC, t.position DESC
+                 LIMIT ?3 OFFSET ?4",
+                filter,
+            ))
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(
+                params![query.memo, query.prefix, to_sql_int(query.limit), to_sql_int(query.offset)],
+                |row| {
+                    Ok(MemoTransaction {
+                        tx_hash: row.get(0)?,
+                        block_height: row.get::<_, i64>(1)? as u64,
+                        block_hash: row.get(2)?,
+                        timestamp: DateTime::from_timestamp_millis(row.get::<_, i64>(3)?).unwrap_or_default(),
+                        memo: row.get(4)?,
+                    })
+                },
+            )
+            .map_err(db_error)?;
+
+        Ok(MemoSearch {
+            transactions: rows.collect::<rusqlite::Result<_>>().map_err(db_error)?,
+            total: total as u64,
+        })
+    }
+}
+
+/// A backend with a [`SqliteIndex`] kept in step with the blocks it stores
+#[derive(Debug)]
+pub struct IndexedStorage {
+    inner: Box<dyn Storage>,
+    index: Arc<SqliteIndex>,
+}
+
+impl IndexedStorage {
+    /// Index whatever `inner` holds that `index` lacks, then keep it current
+    pub fn new(inner: Box<dyn Storage>, index: Arc<SqliteIndex>) -> Result<Self> {
+        index.catch_up(inner.as_ref())?;
+        Ok(Self { inner, index })
+    }
+}
+
+impl Storage for IndexedStorage {
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        let blocks: Vec<Block> = batch.ops()
+            .iter()
+            .filter_map(|op| match op {
+                BatchOp::PutBlock(block) => Some(block.clone()),
+                _ => None,
+            })
+            .collect();
+        self.inner.write_batch(batch)?;
+
+        // The batch is committed; a lagging index is rebuilt on the next open
+        for block in &blocks {
+            if let Err(e) = self.index.index_block(block) {
+                warn!("Failed to index block {} in SQLite: {}", block.index, e);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_metadata(&self) -> Result<BlockchainMetadata> {
+        self.inner.load_metadata()
+    }
+
+    fn load_block_by_hash(&self, block_hash: &Hash256) -> Result<Block> {
+        self.inner.load_block_by_hash(block_hash)
+    }
+
+    fn load_block_by_height(&self, height: u64) -> Result<Block> {
+        self.inner.load_block_by_height(height)
+    }
+
+    fn load_all_blocks(&self) -> Result<Vec<Block>> {
+        self.inner.load_all_blocks()
+    }
+
+    fn load_stored_blocks(&self) -> Result<Vec<Block>> {
+        self.inner.load_stored_blocks()
+    }
+
+    fn clear_indexes(&self) -> Result<()> {
+        self.inner.clear_indexes()?;
+        self.index.clear()
+    }
+
+    fn load_block_filter(&self, block_hash: &Hash256) -> Result<BlockFilter> {
+        self.inner.load_block_filter(block_hash)
+    }
+
+    fn store_watched_address(&self, entry: &WatchedAddress) -> Result<()> {
+        self.inner.store_watched_address(entry)
+    }
+
+    fn remove_watched_address(&self, address: &str) -> Result<bool> {
+        self.inner.remove_watched_address(address)
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>> {
+        self.inner.load_watched_addresses()
+    }
+
+    fn store_user(&self, user: &UserRecord) -> Result<()> {
+        self.inner.store_user(user)
+    }
+
+    fn remove_user(&self, username: &str) -> Result<bool> {
+        self.inner.remove_user(username)
+    }
+
+    fn load_users(&self) -> Result<Vec<UserRecord>> {
+        self.inner.load_users()
+    }
+
+    fn store_peer(&self, peer: &PeerRecord) -> Result<()> {
+        self.inner.store_peer(peer)
+    }
+
+    fn remove_peer(&self, address: &str) -> Result<bool> {
+        self.inner.remove_peer(address)
+    }
+
+    fn load_peers(&self) -> Result<Vec<PeerRecord>> {
+        self.inner.load_peers()
+    }
+
+    fn load_transaction(&self, tx_hash: &Hash256) -> Result<Transaction> {
+        self.inner.load_transaction(tx_hash)
+    }
+
+    fn load_utxo(&self, utxo_id: &UtxoId) -> Result<UtxoEntry> {
+        self.inner.load_utxo(utxo_id)
+    }
+
+    fn load_utxos_for_address(&self, address: &Address) -> Result<Vec<UtxoEntry>> {
+        self.inner.load_utxos_for_address(address)
+    }
+
+    fn get_stats(&self) -> Result<StorageStats> {
+        self.inner.get_stats()
+    }
+
+    fn compact(&self) -> Result<()> {
+        self.inner.compact()
+    }
+
+    fn close(&self) -> Result<()> {
+        self.inner.close()
+    }
+
+    fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        self.inner.check_integrity(repair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{TransactionInput, TransactionOutput};
+    use crate::crypto::{PublicKey, SignatureAlgorithm};
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+
+    fn address(seed: u8) -> Address {
+        Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![seed; 33]))
+    }
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()
+    }
+
+    fn block(index: u64, transactions: Vec<Transaction>, day: u32) -> Block {
+        Block::new_at(index, Hash256::zero(), transactions, 1, at(day))
+    }
+
+    /// Alice mines block 0 on day 1 and pays Bob 300 of it on day 2 for invoice
+    /// INV-7
+    fn chain() -> (Vec<Block>, Address, Address) {
+        let (alice, bob) = (address(1), address(2));
+        let coinbase = Transaction::coinbase(alice.clone(), 1000, 0);
+        let spend = TransactionInput::new(coinbase.hash(), 0, None, None);
+        let payment = Transaction::new(
+            vec![spend],
+            vec![TransactionOutput::new(300, bob.clone()), TransactionOutput::new(700, alice.clone())],
+        ).with_memo("INV-7");
+        let blocks = vec![
+            block(0, vec![coinbase], 1),
+            block(1, vec![Transaction::coinbase(bob.clone(), 1000, 1), payment], 2),
+        ];
+        (blocks, alice, bob)
+    }
+
+    fn everything() -> AddressHistoryQuery {
+        AddressHistoryQuery { limit: 100, ..AddressHistoryQuery::default() }
+    }
+
+    #[test]
+    fn test_address_history() {
+        let index = SqliteIndex::in_memory().unwrap();
+        let (blocks, alice, bob) = chain();
+        for block in &blocks {
+            index.index_block(block).unwrap();
+        }
+        assert_eq!(index.tip_height().unwrap(), Some(1));
+
+        let history = index.address_history(&alice.to_hex(), &everything()).unwrap();
+        assert_eq!(history.total, 2);
+        let payment = &history.transactions[0];
+        assert_eq!((payment.block_height, payment.received, payment.sent), (1, 700, 1000));
+        assert_eq!(payment.block_hash, blocks[1].hash().to_hex());
+        assert_eq!(payment.timestamp, at(2));
+        assert_eq!(payment.memo.as_deref(), Some("INV-7"));
+        assert_eq!(history.transactions[1].memo, None);
+
+        // Bob's coinbase comes before the payment within block 1
+        let history = index.address_history(&bob.to_hex(), &everything()).unwrap();
+        let received: Vec<u64> = history.transactions.iter().map(|tx| tx.received).collect();
+        assert_eq!(received, vec![300, 1000]);
+
+        let first_day = AddressHistoryQuery { to: Some(at(2)), ..everything() };
+        let history = index.address_history(&alice.to_hex(), &first_day).unwrap();
+        assert_eq!(history.total, 1);
+        assert_eq!(history.transactions[0].block_height, 0);
+
+        let page = AddressHistoryQuery { limit: 1, offset: 1, ..everything() };
+        let history = index.address_history(&bob.to_hex(), &page).unwrap();
+        assert_eq!((history.total, history.transactions.len()), (2, 1));
+        assert_eq!(history.transactions[0].received, 1000);
+    }
+
+    #[test]
+    fn test_address_activity_since() {
+        let index = SqliteIndex::in_memory().unwrap();
+        let (blocks, alice, bob) = chain();
+        for block in &blocks {
+            index.index_block(block).unwrap();
+        }
+
+        let activity = index.address_activity_since(&alice.to_hex(), None).unwrap();
+        let changes: Vec<(u64, u64, u64)> = activity.iter().map(|tx| (tx.block_height, tx.received, tx.sent)).collect();
+        assert_eq!(changes, vec![(0, 1000, 0), (1, 700, 1000)]);
+
+        // Oldest first within a block too: Bob's coinbase, then the payment
+        let activity = index.address_activity_since(&bob.to_hex(), Some(0)).unwrap();
+        let received: Vec<u64> = activity.iter().map(|tx| tx.received).collect();
+        assert_eq!(received, vec![1000, 300]);
+        assert!(index.address_activity_since(&bob.to_hex(), Some(1)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_memos() {
+        let index = SqliteIndex::in_memory().unwrap();
+        let (blocks, _, _) = chain();
+        for block in &blocks {
+            index.index_block(block).unwrap();
+        }
+        let search = |memo: &str, prefix| {
+            index.search_memos(&MemoQuery { memo: memo.to_string(), prefix, limit: 10, offset: 0 }).unwrap()
+        };
+
+        let found = search("INV-7", false);
+        assert_eq!(found.total, 1);
+        let payment = &found.transactions[0];
+        assert_eq!(payment.tx_hash, blocks[1].transactions()[1].hash().to_hex());
+        assert_eq!((payment.block_height, payment.memo.as_str(), payment.timestamp), (1, "INV-7", at(2)));
+
+        assert_eq!(search("INV", false).total, 0);
+        assert_eq!(search("INV", true).total, 1);
+        // Coinbase memos are not indexed
+        assert_eq!(search("Block", true).total, 0);
+    }
+
+    #[test]
+    fn test_index_without_memos_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.sqlite");
+        let old = Connection::open(&path).unwrap();
+        old.execute_batch(
+            "CREATE TABLE blocks (height INTEGER PRIMARY KEY, hash TEXT NOT NULL, timestamp INTEGER NOT NULL, tx_count INTEGER NOT NULL);
+             CREATE TABLE transactions (hash TEXT PRIMARY KEY, block_height INTEGER NOT NULL, position INTEGER NOT NULL, is_coinbase INTEGER NOT NULL);
+             INSERT INTO blocks VALUES (0, 'ab', 0, 1);",
+        ).unwrap();
+        drop(old);
+
+        let index = SqliteIndex::open(&path).unwrap();
+        assert_eq!(index.tip_height().unwrap(), None);
+        let (blocks, _, _) = chain();
+        let storage = MemoryStorage::new();
+        for block in &blocks {
+            storage.store_block(block).unwrap();
+        }
+        assert_eq!(index.catch_up(&storage).unwrap(), 2);
+        let query = MemoQuery { memo: "INV-7".to_string(), limit: 10, ..MemoQuery::default() };
+        assert_eq!(index.search_memos(&query).unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_reorg_rewinds_the_index() {
+        let index = SqliteIndex::in_memory().unwrap();
+        let (blocks, alice, bob) = chain();
+        for block in &blocks {
+            index.index_block(block).unwrap();
+        }
+
+        // A competing block 1 without the payment
+        let replacement = block(1, vec![Transaction::coinbase(alice.clone(), 1000, 1)], 3);
+        index.index_block(&replacement).unwrap();
+        assert!(index.address_history(&bob.to_hex(), &everything()).unwrap().transactions.is_empty());
+        let history = index.address_history(&alice.to_hex(), &everything()).unwrap();
+        assert!(history.transactions.iter().all(|tx| tx.sent == 0));
+        assert_eq!(history.total, 2);
+
+        // The output the payment spent is unspent again, so replaying it counts it
+        index.index_block(&blocks[1]).unwrap();
+        let history = index.address_history(&alice.to_hex(), &everything()).unwrap();
+        assert_eq!(history.transactions[0].sent, 1000);
+    }
+
+    #[test]
+    fn test_indexed_storage_keeps_up() {
+        let (blocks, alice, _) = chain();
+        let storage = MemoryStorage::new();
+        storage.store_block(&blocks[0]).unwrap();
+
+        // The block stored before the index existed is caught up on
+        let index = Arc::new(SqliteIndex::in_memory().unwrap());
+        let indexed = IndexedStorage::new(Box::new(storage), index.clone()).unwrap();
+        assert_eq!(index.tip_height().unwrap(), Some(0));
+
+        indexed.store_block(&blocks[1]).unwrap();
+        assert_eq!(index.address_history(&alice.to_hex(), &everything()).unwrap().total, 2);
+
+        indexed.clear_indexes().unwrap();
+        assert_eq!(index.tip_height().unwrap(), None);
+        assert_eq!(index.catch_up(&indexed).unwrap(), 0);
+    }
+}
@@ -0,0 +1,337 @@
+//! Headers-first initial block download.
+//!
+//! A fresh node configured with `sync.peers` asks each peer for its tip, downloads
+//! the header chain above its own tip from the peer with the highest one and checks
+//! it with [`verify_header_chain`]. Only then does it fetch the blocks under those
+//! headers, by hash, in batches of `sync.batch_size` spread over all the peers with
+//! at most `sync.max_in_flight` batches outstanding. Batches are connected in height
+//! order as they arrive, so the chain is written to storage as it grows and a
+//! restarted download resumes from the new tip. [`SyncProgress`] is served at
+//! `/api/sync/status`.
+//!
+//! Peers are not trusted: a response over [`MAX_P2P_MESSAGE_SIZE`] is dropped
+//! unread, and a peer that sends one, or data that does not decode or validate, is
+//! charged in the node's [`PeerStore`](crate::peers::PeerStore). Banned peers are
+//! skipped until their ban runs out.
+
+use crate::api::{AppState, Shutdown};
+use crate::config::SyncConfig;
+use crate::core::{verify_header_chain, Block, BlockHeader, BlockStatus};
+use crate::crypto::Hash256;
+use crate::error::{LedgerError, Result};
+use crate::utils::constants::{BAN_SCORE_THRESHOLD, MAX_P2P_MESSAGE_SIZE};
+use chrono::Utc;
+use futures_util::{future::join_all, StreamExt};
+use ledgerdb_client::{Client, ClientError};
+use ledgerdb_types::{BatchItem, BatchQuery, BatchRequest, BatchResponse, BlockView, RestartPolicy};
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use ledgerdb_types::{SyncPhase, SyncStatus};
+
+/// Misbehavior score for a response that is oversized or does not decode
+const MALFORMED_SCORE: u32 = 50;
+
+/// Misbehavior score for headers or blocks that fail validation, enough for a ban
+const INVALID_SCORE: u32 = BAN_SCORE_THRESHOLD;
+
+/// State of the initial block download
+#[derive(Debug, Default)]
+pub struct SyncProgress {
+    status: RwLock<SyncStatus>,
+}
+
+impl SyncProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    fn update(&self, change: impl FnOnce(&mut SyncStatus)) {
+        change(&mut self.status.write().unwrap());
+    }
+}
+
+/// Download the chain from `config.peers` in the background, retrying from the
+/// new tip after a failure; does nothing without peers
+pub fn spawn(state: &AppState, config: &SyncConfig) -> Result<()> {
+    if config.peers.is_empty() {
+        return Ok(());
+    }
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(30),
+    };
+    let (worker, config) = (state.clone(), config.clone());
+    state.tasks.spawn("initial-sync", policy, move |shutdown| {
+        let (state, config) = (worker.clone(), config.clone());
+        async move { sync_from_peers(&state, &config, &shutdown).await }
+    })
+}
+
+/// Bring the chain up to the highest tip among `config.peers`, recording the
+/// outcome in `state.sync`
+pub async fn sync_from_peers(state: &AppState, config: &SyncConfig, shutdown: &Shutdown) -> Result<()> {
+    let result = download(state, config, shutdown).await;
+    if let Err(e) = &result {
+        state.sync.update(|status| {
+            status.phase = SyncPhase::Failed;
+            status.in_flight = 0;
+            status.error = Some(e.to_string());
+        });
+    }
+    result
+}
+
+async fn download(state: &AppState, config: &SyncConfig, shutdown: &Shutdown) -> Result<()> {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .build()
+        .map_err(|e| LedgerError::Network(e.to_string()))?;
+    let peers = join_all(config.peers.iter().map(|url| SyncPeer::new(url, http.clone()))).await;
+
+    // The peer with the highest tip supplies the header chain
+    let tips = join_all(peers.iter().map(|peer| async move {
+        if peer.is_banned(state) {
+            return Err(LedgerError::Network(format!("{} is banned", peer.client.base_url())));
+        }
+        peer.client.blockchain_info().await.map_err(|e| peer.error(state, e))
+    }))
+    .await;
+    let (best, target_height) = tips
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, info)| match info {
+            Ok(info) => Some((i, info.height.saturating_sub(1))),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        })
+        .max_by_key(|&(_, height)| height)
+        .ok_or_else(|| LedgerError::Network("no sync peer answered".to_string()))?;
+    let peer = &peers[best];
+    let client = &peer.client;
+
+    let (next_height, mut tip_hash) = {
+        let blockchain = state.blockchain.read().await;
+        let tip = blockchain.get_latest_block().map(Block::hash).unwrap_or_else(Hash256::zero);
+        (blockchain.height(), tip)
+    };
+    let start_height = next_height.saturating_sub(1);
+    state.sync.update(|status| {
+        *status = SyncStatus {
+            phase: SyncPhase::Headers,
+            peer: Some(client.base_url().to_string()),
+            start_height,
+            target_height,
+            header_height: start_height,
+            block_height: start_height,
+            in_flight: 0,
+            started_at: Some(Utc::now()),
+            error: None,
+        };
+    });
+    info!("Syncing from {}: height {} to {}", client.base_url(), start_height, target_height);
+
+    let mut headers: Vec<BlockHeader> = Vec::new();
+    while next_height + (headers.len() as u64) <= target_height {
+        if shutdown.is_triggered() {
+            return Ok(());
+        }
+        let from = next_height + headers.len() as u64;
+        let response = client.headers(Some(from), None).await.map_err(|e| peer.error(state, e))?;
+        let batch = response
+            .headers
+            .iter()
+            .map(|header| {
+                let bytes = hex::decode(header).map_err(|e| LedgerError::Parse(format!("header from {}: {}", client.base_url(), e)))?;
+                BlockHeader::from_bytes(&bytes)
+            })
+            .collect::<Result<Vec<_>>>()
+            .inspect_err(|e| peer.misbehaved(state, MALFORMED_SCORE, &e.to_string()))?;
+        if batch.is_empty() {
+            break;
+        }
+        tip_hash = verify_header_chain(&tip_hash, &batch)
+            .map_err(|e| LedgerError::Validation(format!("header chain from {}: {}", client.base_url(), e)))
+            .inspect_err(|e| peer.misbehaved(state, INVALID_SCORE, &e.to_string()))?;
+        headers.extend(batch);
+        let header_height = start_height + headers.len() as u64;
+        state.sync.update(|status| status.header_height = header_height);
+    }
+
+    state.sync.update(|status| status.phase = SyncPhase::Blocks);
+    let batches: Vec<Vec<Hash256>> = headers
+        .chunks(config.batch_size)
+        .map(|chunk| chunk.iter().map(BlockHeader::hash).collect())
+        .collect();
+    let mut fetched = futures_util::stream::iter(batches.into_iter().enumerate())
+        .map(|(i, hashes)| {
+            let peers = &peers;
+            async move {
+                state.sync.update(|status| status.in_flight += 1);
+                fetch_blocks(state, peers, best + i, &hashes).await
+            }
+        })
+        .buffered(config.max_in_flight);
+    loop {
+        let blocks = tokio::select! {
+            next = fetched.next() => match next {
+                Some(blocks) => blocks?,
+                None => break,
+            },
+            _ = shutdown.wait() => return Ok(()),
+        };
+        let block_height = connect(state, blocks).await?;
+        state.sync.update(|status| {
+            status.block_height = block_height;
+            status.in_flight = status.in_flight.saturating_sub(1);
+        });
+    }
+
+    state.sync.update(|status| {
+        status.phase = SyncPhase::Synced;
+        status.in_flight = 0;
+    });
+    info!("Synced to height {}", state.sync.status().block_height);
+    Ok(())
+}
+
+/// Fetch the blocks with `hashes` in one batch request, asking each peer that is
+/// not banned in turn from `first` until one has them all
+async fn fetch_blocks(state: &AppState, peers: &[SyncPeer], first: usize, hashes: &[Hash256]) -> Result<Vec<Block>> {
+    let request = BatchRequest {
+        queries: hashes.iter().map(|hash| BatchQuery::Block { id: hash.to_hex() }).collect(),
+        verbosity: Some(0),
+    };
+    let mut last_error = None;
+    for peer in peers.iter().cycle().skip(first % peers.len()).take(peers.len()) {
+        if peer.is_banned(state) {
+            continue;
+        }
+        let blocks = match peer.client.batch(&request).await {
+            Ok(response) => decode_blocks(response, hashes).inspect_err(|e| match e {
+                LedgerError::Validation(reason) => peer.misbehaved(state, INVALID_SCORE, reason),
+                LedgerError::Parse(reason) | LedgerError::Serialization(reason) => peer.misbehaved(state, MALFORMED_SCORE, reason),
+                _ => {}
+            }),
+            Err(e) => Err(peer.error(state, e)),
+        };
+        match blocks {
+            Ok(blocks) => return Ok(blocks),
+            Err(e) => {
+                warn!("Block download from {} failed: {}", peer.client.base_url(), e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| LedgerError::Network("no sync peers".to_string())))
+}
+
+/// The raw blocks in a batch response, checked against the headers they were
+/// asked for by
+fn decode_blocks(response: BatchResponse, hashes: &[Hash256]) -> Result<Vec<Block>> {
+    if response.results.len() != hashes.len() {
+        return Err(LedgerError::Network(format!(
+            "asked for {} blocks, got {}",
+            hashes.len(),
+            response.results.len()
+        )));
+    }
+    response
+        .results
+        .into_iter()
+        .zip(hashes)
+        .map(|(result, hash)| {
+            let raw = match result.data {
+                Some(BatchItem::Block(BlockView::Raw(raw))) => raw,
+                _ => {
+                    let reason = result.error.map(|e| e.message).unwrap_or_else(|| "not a raw block".to_string());
+                    return Err(LedgerError::Network(format!("block {}: {}", hash.to_hex(), reason)));
+                }
+            };
+            let bytes = hex::decode(&raw.hex).map_err(|e| LedgerError::Parse(format!("block {}: {}", hash.to_hex(), e)))?;
+            let block = Block::from_bytes(&bytes)?;
+            if block.hash() != *hash {
+                return Err(LedgerError::Validation(format!("block {} does not match its header", hash.to_hex())));
+            }
+            Ok(block)
+        })
+        .collect()
+}
+
+/// Connect `blocks` in order; returns the height of the new tip
+async fn connect(state: &AppState, blocks: Vec<Block>) -> Result<u64> {
+    // Connecting validates and persists the blocks; keep that off the async workers
+    let mut blockchain = state.blockchain.clone().write_owned().await;
+    state
+        .storage
+        .spawn(move || {
+            for block in blocks {
+                let hash = block.hash();
+                if let BlockStatus::Orphaned { .. } = blockchain.process_block(block)? {
+                    return Err(LedgerError::Validation(format!("block {} does not extend the chain", hash.to_hex())));
+                }
+            }
+            Ok(blockchain.height().saturating_sub(1))
+        })
+        .await
+}
+
+/// A sync peer's API, and the address its misbehavior is charged to
+pub(crate) struct SyncPeer {
+    pub(crate) client: Client,
+    address: Option<SocketAddr>,
+}
+
+impl SyncPeer {
+    pub(crate) async fn new(url: &str, http: reqwest::Client) -> Self {
+        let client = Client::with_http_client(url, http).with_max_response_size(MAX_P2P_MESSAGE_SIZE);
+        let address = resolve(url).await;
+        if address.is_none() {
+            warn!("Cannot resolve sync peer {}; its misbehavior will not be recorded", url);
+        }
+        Self { client, address }
+    }
+
+    pub(crate) fn is_banned(&self, state: &AppState) -> bool {
+        self.address.is_some_and(|address| state.peers.is_banned(&address))
+    }
+
+    fn misbehaved(&self, state: &AppState, score: u32, reason: &str) {
+        let Some(address) = self.address else {
+            return;
+        };
+        if let Err(e) = state.peers.misbehaved(address, score, reason) {
+            warn!("Failed to record misbehavior of {}: {}", address, e);
+        }
+    }
+
+    /// `error` as a [`LedgerError`], charging the peer if its response was
+    /// oversized or did not decode
+    fn error(&self, state: &AppState, error: ClientError) -> LedgerError {
+        let message = format!("{}: {}", self.client.base_url(), error);
+        match error {
+            ClientError::TooLarge(_) | ClientError::Decode(_) => {
+                self.misbehaved(state, MALFORMED_SCORE, &message);
+                LedgerError::Parse(message)
+            }
+            _ => LedgerError::Network(message),
+        }
+    }
+}
+
+/// The socket address a peer URL points at
+async fn resolve(url: &str) -> Option<SocketAddr> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?.trim_start_matches('[').trim_end_matches(']').to_string();
+    let port = url.port_or_known_default()?;
+    let mut addresses = tokio::net::lookup_host((host.as_str(), port)).await.ok()?;
+    addresses.next()
+}
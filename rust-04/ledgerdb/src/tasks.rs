@@ -0,0 +1,324 @@
+//! Supervised background tasks.
+//!
+//! Mining, compaction, backups and (eventually) P2P sync run as long-lived tokio
+//! tasks. The [`TaskManager`] spawns them by name, restarts them according to a
+//! [`RestartPolicy`] when they fail or panic, records their health for the
+//! `/admin/tasks` endpoint, and stops them together when the node shuts down.
+
+use crate::api::Shutdown;
+use crate::config::ConfigReloader;
+use crate::error::{LedgerError, Result};
+use crate::storage::AsyncStorage;
+use chrono::Utc;
+use futures_util::FutureExt;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+pub use ledgerdb_types::{RestartPolicy, TaskHealth, TaskState};
+
+/// How often the storage journal is compacted in the background
+const STORAGE_COMPACTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+type HealthTable = Arc<Mutex<BTreeMap<String, TaskHealth>>>;
+
+/// Spawns and supervises named background tasks
+pub struct TaskManager {
+    health: HealthTable,
+    handles: Mutex<Vec<JoinHandle<()>>>,
+    shutdown: Shutdown,
+}
+
+impl TaskManager {
+    /// Create a manager whose tasks stop when `shutdown` is triggered
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self {
+            health: Arc::new(Mutex::new(BTreeMap::new())),
+            handles: Mutex::new(Vec::new()),
+            shutdown,
+        }
+    }
+
+    /// Spawn a supervised task.
+    ///
+    /// `task` is called once per run with the shutdown handle, which long-running
+    /// tasks should watch so they can exit cleanly. Names must be unique.
+    pub fn spawn<F, Fut>(&self, name: impl Into<String>, policy: RestartPolicy, task: F) -> Result<()>
+    where
+        F: Fn(Shutdown) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = name.into();
+        {
+            let mut health = self.health.lock().unwrap();
+            if health.contains_key(&name) {
+                return Err(LedgerError::InvalidInput(format!("Task {} is already registered", name)));
+            }
+            let now = Utc::now();
+            health.insert(
+                name.clone(),
+                TaskHealth {
+                    name: name.clone(),
+                    state: TaskState::Running,
+                    policy,
+                    restarts: 0,
+                    last_error: None,
+                    started_at: now,
+                    last_change: now,
+                },
+            );
+        }
+
+        let supervisor = supervise(name, policy, task, self.health.clone(), self.shutdown.clone());
+        self.handles.lock().unwrap().push(tokio::spawn(supervisor));
+        Ok(())
+    }
+
+    /// Health of every registered task, ordered by name
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.health.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Trigger shutdown and wait up to `grace` for tasks to exit, aborting stragglers
+    pub async fn shutdown(&self, grace: Duration) {
+        self.shutdown.trigger();
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        let aborts = handles.iter().map(|h| h.abort_handle()).collect::<Vec<_>>();
+
+        if tokio::time::timeout(grace, futures_util::future::join_all(handles)).await.is_err() {
+            warn!("Background tasks still running after {:?}; aborting them", grace);
+            for abort in aborts {
+                abort.abort();
+            }
+            let mut health = self.health.lock().unwrap();
+            for entry in health.values_mut() {
+                if matches!(entry.state, TaskState::Running | TaskState::Restarting) {
+                    entry.state = TaskState::Stopped;
+                    entry.last_change = Utc::now();
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TaskManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskManager")
+            .field("tasks", &self.health.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Periodically compact the storage journal until shutdown
+pub fn spawn_storage_compaction(tasks: &TaskManager, storage: AsyncStorage) -> Result<()> {
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(60),
+    };
+    tasks.spawn("storage-compaction", policy, move |shutdown| {
+        let storage = storage.clone();
+        async move {
+            let mut ticker = tokio::time::interval(STORAGE_COMPACTION_INTERVAL);
+            ticker.tick().await;
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown.wait() => return Ok(()),
+                }
+                storage.compact().await?;
+            }
+        }
+    })
+}
+
+/// Reload the configuration each time the process receives SIGHUP.
+///
+/// A rejected reload is logged and the running configuration kept. On platforms
+/// without SIGHUP the task just waits for shutdown.
+pub fn spawn_config_reload(tasks: &TaskManager, reloader: Arc<Mutex<ConfigReloader>>) -> Result<()> {
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(1),
+    };
+    tasks.spawn("config-reload", policy, move |shutdown| {
+        let reloader = reloader.clone();
+        async move {
+            #[cfg(unix)]
+            {
+                use tokio::signal::unix::{signal, SignalKind};
+                let mut hangup = signal(SignalKind::hangup())
+                    .map_err(|e| LedgerError::Internal(format!("Failed to listen for SIGHUP: {}", e)))?;
+                loop {
+                    tokio::select! {
+                        _ = hangup.recv() => {}
+                        _ = shutdown.wait() => return Ok(()),
+                    }
+                    match reloader.lock().unwrap().reload() {
+                        Ok(changed) if changed.is_empty() => info!("Configuration reloaded; nothing changed"),
+                        Ok(changed) => info!("Configuration reloaded; changed {}", changed.join(", ")),
+                        Err(e) => warn!("Configuration reload rejected: {}", e),
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = reloader;
+                shutdown.wait().await;
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Run `task` under `policy` until it finishes for good or shutdown is triggered
+async fn supervise<F, Fut>(name: String, policy: RestartPolicy, task: F, health: HealthTable, shutdown: Shutdown)
+where
+    F: Fn(Shutdown) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let update = |state: TaskState, error: Option<String>, restarted: bool| {
+        if let Some(entry) = health.lock().unwrap().get_mut(&name) {
+            entry.state = state;
+            entry.last_change = Utc::now();
+            if error.is_some() {
+                entry.last_error = error;
+            }
+            if restarted {
+                entry.restarts += 1;
+            }
+        }
+    };
+
+    let mut restarts = 0;
+    loop {
+        info!("Task {} started", name);
+        let outcome = match AssertUnwindSafe(task(shutdown.clone())).catch_unwind().await {
+            Ok(result) => result,
+            Err(_) => Err(LedgerError::Internal("task panicked".to_string())),
+        };
+
+        if shutdown.is_triggered() {
+            update(TaskState::Stopped, outcome.err().map(|e| e.to_string()), false);
+            return;
+        }
+
+        let (failed, error) = match outcome {
+            Ok(()) => (false, None),
+            Err(e) => {
+                error!("Task {} failed: {}", name, e);
+                (true, Some(e.to_string()))
+            }
+        };
+
+        let backoff = match policy {
+            RestartPolicy::Always { backoff } => Some(backoff),
+            RestartPolicy::OnFailure { max_restarts, backoff } if failed && restarts < max_restarts => Some(backoff),
+            _ => None,
+        };
+        let Some(backoff) = backoff else {
+            let state = if failed { TaskState::Failed } else { TaskState::Completed };
+            update(state, error, false);
+            return;
+        };
+
+        restarts += 1;
+        update(TaskState::Restarting, error, true);
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => update(TaskState::Running, None, false),
+            _ = shutdown.wait() => {
+                update(TaskState::Stopped, None, false);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    async fn wait_for_state(manager: &TaskManager, state: TaskState) -> TaskHealth {
+        loop {
+            let health = manager.health().remove(0);
+            if health.state == state {
+                return health;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_completes() {
+        let manager = TaskManager::new(Shutdown::new());
+        manager.spawn("once", RestartPolicy::Never, |_| async { Ok(()) }).unwrap();
+
+        let health = wait_for_state(&manager, TaskState::Completed).await;
+        assert_eq!(health.restarts, 0);
+        assert!(health.last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_task_restarts_until_limit() {
+        let manager = TaskManager::new(Shutdown::new());
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let policy = RestartPolicy::OnFailure { max_restarts: 2, backoff: Duration::from_millis(1) };
+        manager
+            .spawn("flaky", policy, move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Err(LedgerError::Internal("boom".to_string())) }
+            })
+            .unwrap();
+
+        let health = wait_for_state(&manager, TaskState::Failed).await;
+        assert_eq!(health.restarts, 2);
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert!(health.last_error.unwrap().contains("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_reported_as_failure() {
+        let manager = TaskManager::new(Shutdown::new());
+        manager
+            .spawn("panics", RestartPolicy::Never, |_| async {
+                if true {
+                    panic!("bad task");
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        let health = wait_for_state(&manager, TaskState::Failed).await;
+        assert!(health.last_error.unwrap().contains("panicked"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_name_rejected() {
+        let manager = TaskManager::new(Shutdown::new());
+        manager.spawn("dup", RestartPolicy::Never, |_| async { Ok(()) }).unwrap();
+        assert!(manager.spawn("dup", RestartPolicy::Never, |_| async { Ok(()) }).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_tasks() {
+        let manager = TaskManager::new(Shutdown::new());
+        let policy = RestartPolicy::Always { backoff: Duration::from_millis(1) };
+        manager
+            .spawn("worker", policy, |shutdown| async move {
+                shutdown.wait().await;
+                Ok(())
+            })
+            .unwrap();
+
+        manager.shutdown(Duration::from_secs(1)).await;
+        assert_eq!(manager.health()[0].state, TaskState::Stopped);
+    }
+}
@@ -0,0 +1,439 @@
+//! Independent chains hosted side by side in one node.
+//!
+//! With `tenants.enabled`, every subdirectory of `tenants.dir` is a tenant: a
+//! chain with its own genesis and settings (`tenant.json`), storage (`db/`),
+//! background tasks, miner and event channels. Each tenant is served by a router
+//! of its own, which the host API reaches under `/t/{name}/` (see
+//! [`split_tenant_path`]), so every endpoint works per tenant. Tenants share the
+//! host's API settings and user accounts.
+//!
+//! Archiving a tenant stops it and moves its directory under `archived/`, which
+//! frees the name for a new tenant.
+
+use crate::api::{self, ApiConfig, AppState};
+use crate::auth::UserStore;
+use crate::config::{self, Config};
+use crate::core::blockchain::Blockchain;
+use crate::crypto::Address;
+use crate::error::{LedgerError, Result};
+use crate::node::{self, NodeEvent};
+use crate::storage::PersistentStorage;
+use crate::tasks;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{info, warn};
+
+pub use ledgerdb_types::{CreateTenantRequest, TenantInfo};
+
+/// Path prefix the host API serves tenants under
+pub const ROUTE_PREFIX: &str = "/t";
+
+/// Subdirectory of `tenants.dir` that archived tenants are moved to
+pub const ARCHIVE_DIR: &str = "archived";
+
+/// Longest tenant name
+pub const MAX_NAME_LEN: usize = 32;
+
+/// Settings file in each tenant's directory
+const SPEC_FILE: &str = "tenant.json";
+
+/// The tenant name and the path below it, if `path` is under [`ROUTE_PREFIX`]
+///
+/// `/t/school/api/blocks` gives `("school", "/api/blocks")`, and `/t/school`
+/// gives `("school", "/")`.
+pub fn split_tenant_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(ROUTE_PREFIX)?.strip_prefix('/')?;
+    match rest.find('/') {
+        Some(end) => Some((&rest[..end], &rest[end..])),
+        None if !rest.is_empty() => Some((rest, "/")),
+        None => None,
+    }
+}
+
+/// Why `request` cannot create a tenant
+pub fn check_request(request: &CreateTenantRequest) -> std::result::Result<(), String> {
+    let name = &request.name;
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("Tenant name must be 1 to {} characters", MAX_NAME_LEN));
+    }
+    if !name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-') || name.starts_with('-') {
+        return Err("Tenant name may only contain lowercase letters, digits and '-', and may not start with '-'".to_string());
+    }
+    if name == ARCHIVE_DIR {
+        return Err(format!("'{}' is reserved", ARCHIVE_DIR));
+    }
+    if request.initial_difficulty == Some(0) {
+        return Err("initial_difficulty must be at least 1".to_string());
+    }
+    if request.target_block_time == Some(0) {
+        return Err("target_block_time must be at least 1 second".to_string());
+    }
+    if let Some(schedule) = &request.reward_schedule {
+        crate::core::RewardSchedule::from(schedule.clone())
+            .validate()
+            .map_err(|e| format!("reward_schedule: {}", e))?;
+    }
+    Ok(())
+}
+
+/// What a tenant was created with, kept as `tenant.json` in its directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TenantSpec {
+    #[serde(flatten)]
+    request: CreateTenantRequest,
+    created_at: DateTime<Utc>,
+}
+
+impl TenantSpec {
+    /// The host's chain settings with this tenant's overrides
+    ///
+    /// Checkpoints pin the host's chain, so tenants do without. The network is
+    /// always the host's: addresses are encoded for one network per process.
+    fn chain_config(&self, host: &config::BlockchainConfig) -> config::BlockchainConfig {
+        let request = &self.request;
+        config::BlockchainConfig {
+            reward_schedule: request.reward_schedule.clone().map(Into::into).or_else(|| host.reward_schedule.clone()),
+            initial_difficulty: request.initial_difficulty.unwrap_or(host.initial_difficulty),
+            target_block_time: request.target_block_time.unwrap_or(host.target_block_time),
+            checkpoints: BTreeMap::new(),
+            ..host.clone()
+        }
+    }
+
+    /// Paid by the genesis block and by the tenant's miner
+    fn genesis_address(&self) -> Address {
+        self.request.genesis_address.map(Address::from).unwrap_or_else(node::default_address)
+    }
+}
+
+/// A running tenant
+#[derive(Debug)]
+pub struct Tenant {
+    spec: TenantSpec,
+    state: AppState,
+    router: Router,
+    events: broadcast::Sender<NodeEvent>,
+}
+
+impl Tenant {
+    pub fn name(&self) -> &str {
+        &self.spec.request.name
+    }
+
+    /// The tenant's chain, storage and tasks
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    /// Router serving the tenant's API, with paths relative to `/t/{name}`
+    pub fn router(&self) -> Router {
+        self.router.clone()
+    }
+
+    /// Subscribe to the tenant's node events
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn info(&self) -> TenantInfo {
+        let chain = self.state.blockchain.read().await;
+        let genesis_hash = chain.get_block_by_index(0).map(|b| b.hash()).unwrap_or_default();
+        TenantInfo {
+            name: self.name().to_string(),
+            genesis_hash: ledgerdb_types::Hash256(*genesis_hash.as_bytes()),
+            height: chain.height(),
+            mining: self.spec.request.mining,
+            created_at: self.spec.created_at,
+        }
+    }
+
+    /// Close the tenant's WebSockets, stop its tasks and flush its storage
+    async fn stop(&self, grace: Duration) -> Result<()> {
+        self.state.shutdown.trigger();
+        self.state.tasks.shutdown(grace).await;
+        self.state.storage.close().await
+    }
+}
+
+/// The tenants a node hosts
+#[derive(Debug)]
+pub struct TenantManager {
+    dir: PathBuf,
+    /// Storage settings for each tenant's `db/`
+    storage: config::StorageConfig,
+    /// Chain settings tenants override
+    chain: config::BlockchainConfig,
+    api: ApiConfig,
+    users: Arc<UserStore>,
+    grace: Duration,
+    tenants: RwLock<BTreeMap<String, Arc<Tenant>>>,
+    /// Held while tenants are loaded, created or archived, so a name is claimed once
+    changes: Mutex<()>,
+}
+
+impl TenantManager {
+    /// Host tenants in `config.tenants.dir`, defaulting to the host's storage
+    /// backend and chain settings, and sharing its API settings and accounts
+    pub fn new(config: &Config, api: ApiConfig, users: Arc<UserStore>) -> Self {
+        let storage = config::StorageConfig {
+            backup_dir: None,
+            auto_backup_interval_hours: None,
+            utxo_snapshot: None,
+            sqlite_index: None,
+            replay_log: None,
+            ..config.storage.clone()
+        };
+        Self {
+            dir: config.tenants.dir.clone(),
+            storage,
+            chain: config.blockchain.clone(),
+            api,
+            users,
+            grace: Duration::from_secs(config.server.shutdown_timeout),
+            tenants: RwLock::new(BTreeMap::new()),
+            changes: Mutex::new(()),
+        }
+    }
+
+    /// Start every tenant in the directory; one that fails to open is logged and skipped
+    pub async fn load(&self) -> Result<()> {
+        let _changes = self.changes.lock().await;
+        let dir = self.dir.clone();
+        let specs = blocking(move || read_specs(&dir)).await?;
+        for spec in specs {
+            let name = spec.request.name.clone();
+            match self.open(spec).await {
+                Ok(tenant) => {
+                    self.tenants.write().unwrap().insert(name, tenant);
+                }
+                Err(e) => warn!("Tenant {} not loaded: {}", name, e),
+            }
+        }
+        info!("Hosting {} tenants from {}", self.tenants.read().unwrap().len(), self.dir.display());
+        Ok(())
+    }
+
+    /// Create and start a tenant; `None` if the name is taken
+    ///
+    /// Call [`check_request`] first.
+    pub async fn create(&self, request: CreateTenantRequest) -> Result<Option<TenantInfo>> {
+        let _changes = self.changes.lock().await;
+        if self.get(&request.name).is_some() {
+            return Ok(None);
+        }
+        let spec = TenantSpec { request, created_at: Utc::now() };
+        let (dir, written) = (self.dir.join(&spec.request.name), spec.clone());
+        let claimed = blocking(move || {
+            if dir.exists() {
+                return Ok(false);
+            }
+            fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+            let json = serde_json::to_vec_pretty(&written).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+            fs::write(dir.join(SPEC_FILE), json).map_err(|e| io_error(&dir, e))?;
+            Ok(true)
+        })
+        .await?;
+        if !claimed {
+            return Ok(None);
+        }
+
+        let name = spec.request.name.clone();
+        let tenant = match self.open(spec).await {
+            Ok(tenant) => tenant,
+            Err(e) => {
+                // Leave nothing behind that the next start would try to load
+                let dir = self.dir.join(&name);
+                blocking(move || fs::remove_dir_all(&dir).map_err(|e| io_error(&dir, e))).await?;
+                return Err(e);
+            }
+        };
+        let info = tenant.info().await;
+        self.tenants.write().unwrap().insert(name.clone(), tenant);
+        info!("Created tenant {} with genesis {}", name, info.genesis_hash);
+        Ok(Some(info))
+    }
+
+    /// Stop a tenant and move its directory under [`ARCHIVE_DIR`]; returns
+    /// whether there was one
+    pub async fn archive(&self, name: &str) -> Result<bool> {
+        let _changes = self.changes.lock().await;
+        let Some(tenant) = self.tenants.write().unwrap().remove(name) else {
+            return Ok(false);
+        };
+        tenant.stop(self.grace).await?;
+
+        let from = self.dir.join(name);
+        let archive = self.dir.join(ARCHIVE_DIR);
+        let to = archive.join(format!("{}-{}", name, Utc::now().format("%Y%m%dT%H%M%SZ")));
+        info!("Archiving tenant {} to {}", name, to.display());
+        blocking(move || {
+            fs::create_dir_all(&archive).map_err(|e| io_error(&archive, e))?;
+            fs::rename(&from, &to).map_err(|e| io_error(&from, e))
+        })
+        .await?;
+        Ok(true)
+    }
+
+    /// The running tenant called `name`
+    pub fn get(&self, name: &str) -> Option<Arc<Tenant>> {
+        self.tenants.read().unwrap().get(name).cloned()
+    }
+
+    /// Every running tenant, ordered by name
+    pub async fn list(&self) -> Vec<TenantInfo> {
+        let tenants: Vec<Arc<Tenant>> = self.tenants.read().unwrap().values().cloned().collect();
+        let mut infos = Vec::with_capacity(tenants.len());
+        for tenant in tenants {
+            infos.push(tenant.info().await);
+        }
+        infos
+    }
+
+    /// Stop every tenant, as when the host shuts down
+    pub async fn shutdown(&self) {
+        let _changes = self.changes.lock().await;
+        let tenants = std::mem::take(&mut *self.tenants.write().unwrap());
+        for (name, tenant) in tenants {
+            if let Err(e) = tenant.stop(self.grace).await {
+                warn!("Tenant {} did not stop cleanly: {}", name, e);
+            }
+        }
+    }
+
+    /// Open a tenant's storage and chain and start its tasks
+    async fn open(&self, spec: TenantSpec) -> Result<Arc<Tenant>> {
+        let mut storage_config = self.storage.clone();
+        storage_config.db_path = self.dir.join(&spec.request.name).join("db");
+        let chain_config = node::chain_config(&spec.chain_config(&self.chain))?;
+        let genesis_address = spec.genesis_address();
+        let (storage, blockchain) = blocking(move || {
+            let storage = Arc::new(PersistentStorage::open(&storage_config)?);
+            let blockchain = Blockchain::with_storage(chain_config, storage.clone(), genesis_address)?;
+            Ok((storage, blockchain))
+        })
+        .await?;
+
+        let (events, _) = broadcast::channel(node::EVENT_CHANNEL_CAPACITY);
+        let state = AppState::new(blockchain, storage, self.storage.io_workers, self.api.clone(), self.users.clone(), None)?;
+
+        tasks::spawn_storage_compaction(&state.tasks, state.storage.clone())?;
+        if spec.request.mining {
+            let block_interval = Duration::from_secs(spec.chain_config(&self.chain).target_block_time.max(1));
+            node::spawn_miner(&state, events.clone(), spec.genesis_address(), block_interval)?;
+        }
+        Ok(Arc::new(Tenant {
+            router: api::create_router(state.clone()),
+            spec,
+            state,
+            events,
+        }))
+    }
+}
+
+/// The tenants saved in `dir`, skipping [`ARCHIVE_DIR`]
+fn read_specs(dir: &Path) -> Result<Vec<TenantSpec>> {
+    fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+    let mut specs = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let path = entry.map_err(|e| io_error(dir, e))?.path();
+        if !path.is_dir() || path.file_name().is_some_and(|name| name == ARCHIVE_DIR) {
+            continue;
+        }
+        let spec_path = path.join(SPEC_FILE);
+        let spec = fs::read(&spec_path)
+            .map_err(|e| io_error(&spec_path, e))
+            .and_then(|json| {
+                serde_json::from_slice::<TenantSpec>(&json).map_err(|e| LedgerError::Serialization(e.to_string()))
+            });
+        match spec {
+            Ok(spec) if path.file_name().and_then(|name| name.to_str()) == Some(spec.request.name.as_str()) => specs.push(spec),
+            Ok(spec) => warn!("Skipping {}: it holds tenant {}", path.display(), spec.request.name),
+            Err(e) => warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+    specs.sort_by(|a, b| a.request.name.cmp(&b.request.name));
+    Ok(specs)
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> LedgerError {
+    LedgerError::Io(format!("{}: {}", path.display(), error))
+}
+
+/// Run filesystem and storage work off the async workers
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| LedgerError::Internal(format!("Tenant task failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str) -> CreateTenantRequest {
+        CreateTenantRequest {
+            name: name.to_string(),
+            genesis_address: None,
+            reward_schedule: None,
+            initial_difficulty: Some(1),
+            target_block_time: None,
+            mining: false,
+        }
+    }
+
+    #[test]
+    fn test_tenant_paths_and_names() {
+        assert_eq!(split_tenant_path("/t/school/api/blocks"), Some(("school", "/api/blocks")));
+        assert_eq!(split_tenant_path("/t/school"), Some(("school", "/")));
+        assert_eq!(split_tenant_path("/t/"), None);
+        assert_eq!(split_tenant_path("/tenants/school"), None);
+
+        assert!(check_request(&request("class-2024")).is_ok());
+        for name in ["", "Class", "-class", "class/1", ARCHIVE_DIR] {
+            assert!(check_request(&request(name)).is_err(), "{:?}", name);
+        }
+        let mut slow = request("class");
+        slow.target_block_time = Some(0);
+        assert!(check_request(&slow).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_reload_and_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.tenants.dir = dir.path().join("tenants");
+        config.server.shutdown_timeout = 1;
+        let users = Arc::new(UserStore::new(None, Duration::from_secs(60)).unwrap());
+        let manager = TenantManager::new(&config, ApiConfig::default(), users.clone());
+
+        let first = manager.create(request("first")).await.unwrap().unwrap();
+        assert!(manager.create(request("first")).await.unwrap().is_none());
+        let mut other = request("second");
+        other.genesis_address = Some(ledgerdb_types::Address(ledgerdb_types::Hash256([7; 32])));
+        let second = manager.create(other).await.unwrap().unwrap();
+        assert_eq!(first.height, 1);
+        assert_ne!(first.genesis_hash, second.genesis_hash, "a different genesis address is a different chain");
+        manager.shutdown().await;
+
+        let reloaded = TenantManager::new(&config, ApiConfig::default(), users);
+        reloaded.load().await.unwrap();
+        let names: Vec<String> = reloaded.list().await.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["first", "second"]);
+
+        assert!(reloaded.archive("first").await.unwrap());
+        assert!(!reloaded.archive("first").await.unwrap());
+        assert!(reloaded.get("first").is_none());
+        assert!(!config.tenants.dir.join("first").exists());
+        assert_eq!(fs::read_dir(config.tenants.dir.join(ARCHIVE_DIR)).unwrap().count(), 1);
+        // The name is free again
+        assert!(reloaded.create(request("first")).await.unwrap().is_some());
+        reloaded.shutdown().await;
+    }
+}
@@ -323,7 +323,6 @@ impl TableFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::crypto::Hash256;
     
     #[test]
     fn test_format_bytes() {
@@ -3,13 +3,7 @@
 //! This module provides various utility functions, constants, and helper
 //! types used throughout the application.
 
-use crate::crypto::Hash256;
 use crate::error::LedgerError;
-use serde::{Deserialize, Serialize};
-use std::{
-    fmt,
-    time::{Duration, SystemTime, UNIX_EPOCH},
-};
 
 /// Time utilities
 pub mod time;
@@ -47,9 +41,6 @@ pub mod constants {
     /// Minimum transaction fee (satoshis)
     pub const MIN_TRANSACTION_FEE: u64 = 1000;
     
-    /// Block reward (satoshis)
-    pub const BLOCK_REWARD: u64 = 50_000_000;
-    
     /// Target block time (seconds)
     pub const TARGET_BLOCK_TIME: u64 = 600; // 10 minutes
     
@@ -86,6 +77,9 @@ pub mod constants {
     /// Maximum script length
     pub const MAX_SCRIPT_LENGTH: usize = 10_000;
     
+    /// Maximum transaction memo length in bytes
+    pub const MAX_MEMO_SIZE: usize = 256;
+    
     /// Maximum number of inputs per transaction
     pub const MAX_TRANSACTION_INPUTS: usize = 1000;
     
@@ -101,6 +95,10 @@ pub mod constants {
     /// Coinbase maturity (blocks)
     pub const COINBASE_MATURITY: u64 = 100;
     
+    /// Oldest tip (seconds) wallets still lock new transactions to the next block
+    /// against fee sniping
+    pub const MAX_ANTI_FEE_SNIPING_TIP_AGE: u64 = 8 * 60 * 60; // 8 hours
+    
     /// Maximum orphan transactions
     pub const MAX_ORPHAN_TRANSACTIONS: usize = 10_000;
     
@@ -127,6 +125,9 @@ pub mod constants {
     
     /// Maximum ban time (seconds)
     pub const MAX_BAN_TIME: u64 = 24 * 60 * 60; // 24 hours
+    
+    /// Misbehavior score at which a peer is banned for `MAX_BAN_TIME`
+    pub const BAN_SCORE_THRESHOLD: u32 = 100;
 }
 
 /// Result type alias for convenience
@@ -241,8 +242,6 @@ pub mod bytes {
 
 /// Math utilities
 pub mod math {
-    use super::*;
-    
     /// Calculate percentage change
     pub fn percentage_change(old_value: f64, new_value: f64) -> f64 {
         if old_value == 0.0 {
@@ -264,7 +263,7 @@ pub mod math {
         
         let mut averages = Vec::new();
         for i in 0..values.len() {
-            let start = if i >= window_size - 1 { i - window_size + 1 } else { 0 };
+            let start = (i + 1).saturating_sub(window_size);
             let window = &values[start..=i];
             let avg = window.iter().sum::<f64>() / window.len() as f64;
             averages.push(avg);
@@ -312,7 +311,7 @@ pub mod math {
         values.sort_by(|a, b| a.partial_cmp(b).unwrap());
         let len = values.len();
         
-        if len % 2 == 0 {
+        if len.is_multiple_of(2) {
             (values[len / 2 - 1] + values[len / 2]) / 2.0
         } else {
             values[len / 2]
@@ -507,40 +506,40 @@ pub mod collections {
 
 /// Random utilities
 pub mod random {
-    use rand::prelude::*;
+    use rand::{prelude::*, rng};
 
 
     
     /// Generate random bytes
     pub fn random_bytes(length: usize) -> Vec<u8> {
-        let mut rng = thread_rng();
+        let mut rng = rng();
         (0..length).map(|_| rng.random()).collect()
     }
     
     /// Generate random u64
     pub fn random_u64() -> u64 {
-        thread_rng().random()
+        rng().random()
     }
     
     /// Generate random u32
     pub fn random_u32() -> u32 {
-        thread_rng().random()
+        rng().random()
     }
     
     /// Generate random f64 between 0.0 and 1.0
     pub fn random_f64() -> f64 {
-        thread_rng().random()
+        rng().random()
     }
     
     /// Generate random boolean
     pub fn random_bool() -> bool {
-        thread_rng().random()
+        rng().random()
     }
     
     /// Generate random string of specified length
     pub fn random_string(length: usize) -> String {
         const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
-        let mut rng = thread_rng();
+        let mut rng = rng();
         
         (0..length)
             .map(|_| {
@@ -551,14 +550,14 @@ pub mod random {
     }
     
     /// Shuffle a vector in place
-    pub fn shuffle<T>(vec: &mut Vec<T>) {
+    pub fn shuffle<T>(vec: &mut [T]) {
         use rand::seq::SliceRandom;
-        vec.shuffle(&mut thread_rng());
+        vec.shuffle(&mut rng());
     }
     
     /// Choose a random element from a slice
     pub fn choose<T>(slice: &[T]) -> Option<&T> {
-        slice.choose(&mut thread_rng())
+        slice.choose(&mut rng())
     }
     
     #[cfg(test)]
@@ -605,6 +604,16 @@ pub mod random {
 
 /// Logging utilities
 pub mod logging {
+    use crate::config::{LogRotation, LoggingConfig};
+    use crate::error::LedgerError;
+    use chrono::{DateTime, Utc};
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, Write};
+    use std::path::PathBuf;
+    use std::sync::{Mutex, OnceLock};
+    use tracing_subscriber::fmt::writer::BoxMakeWriter;
+    use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
+
     /// Initialize logging with default configuration
     pub fn init_logging() {
         init_logging_with_level("info")
@@ -623,6 +632,192 @@ pub mod logging {
         // In a real implementation, you would use tracing-subscriber with JSON format
         println!("Initializing JSON logging");
     }
+
+    static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+    /// Install a tracing subscriber at `config.level` (any `EnvFilter` directive) whose
+    /// level can be changed later with [`set_log_level`]. Events go to `config.file`,
+    /// rotated as configured, or to stdout.
+    pub fn init_reloadable_logging(config: &LoggingConfig) -> Result<(), LedgerError> {
+        let (filter, handle) = reload::Layer::new(parse_filter(&config.level)?);
+        let layer = fmt::layer().with_thread_ids(config.thread_ids);
+        let layer = match &config.file {
+            Some(path) => {
+                let max_bytes = config.max_file_size_mb.map(|mb| mb * 1024 * 1024);
+                let file = RollingFile::open(path, max_bytes, config.rotation, config.max_files).map_err(|e| {
+                    LedgerError::Internal(format!("Failed to open log file {}: {}", path.display(), e))
+                })?;
+                layer.with_ansi(false).with_writer(BoxMakeWriter::new(Mutex::new(file)))
+            }
+            None => layer.with_ansi(config.colored).with_writer(BoxMakeWriter::new(io::stdout)),
+        };
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(layer)
+            .try_init()
+            .map_err(|e| LedgerError::Internal(format!("Failed to install logger: {}", e)))?;
+        let _ = FILTER_HANDLE.set(handle);
+        Ok(())
+    }
+
+    /// The filter of the subscriber installed by [`init_reloadable_logging`], if it was installed
+    pub fn current_log_level() -> Option<String> {
+        FILTER_HANDLE.get()?.with_current(|filter| filter.to_string()).ok()
+    }
+
+    /// Change the level of the subscriber installed by [`init_reloadable_logging`];
+    /// does nothing if it was not installed
+    pub fn set_log_level(level: &str) -> Result<(), LedgerError> {
+        let filter = parse_filter(level)?;
+        match FILTER_HANDLE.get() {
+            Some(handle) => handle
+                .reload(filter)
+                .map_err(|e| LedgerError::Internal(format!("Failed to change log level: {}", e))),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_filter(level: &str) -> Result<EnvFilter, LedgerError> {
+        EnvFilter::try_new(level)
+            .map_err(|e| LedgerError::Config(format!("invalid log level '{}': {}", level, e)))
+    }
+
+    /// Log file that is moved to `<path>.1` (shifting older files up to
+    /// `<path>.<max_files>`) before a write would take it past `max_bytes`, or on the
+    /// first write in a new hour or day
+    pub struct RollingFile {
+        path: PathBuf,
+        file: File,
+        size: u64,
+        max_bytes: Option<u64>,
+        rotation: LogRotation,
+        max_files: usize,
+        /// Hour or day the current file was started in
+        period: String,
+    }
+
+    impl RollingFile {
+        /// Open `path` for appending, creating it and its directory if needed
+        pub fn open(
+            path: impl Into<PathBuf>,
+            max_bytes: Option<u64>,
+            rotation: LogRotation,
+            max_files: usize,
+        ) -> io::Result<Self> {
+            let path = path.into();
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let metadata = file.metadata()?;
+            // a file left from an earlier day is rotated on the first write
+            let modified = metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+            Ok(Self {
+                period: period_of(rotation, modified),
+                size: metadata.len(),
+                path,
+                file,
+                max_bytes,
+                rotation,
+                max_files,
+            })
+        }
+
+        fn write_at(&mut self, now: DateTime<Utc>, buf: &[u8]) -> io::Result<usize> {
+            let period = period_of(self.rotation, now);
+            let full = self.max_bytes.is_some_and(|max| self.size + buf.len() as u64 > max);
+            if self.size > 0 && (full || period != self.period) {
+                self.rotate()?;
+            }
+            self.period = period;
+            self.file.write_all(buf)?;
+            self.size += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn rotate(&mut self) -> io::Result<()> {
+            self.file.flush()?;
+            if self.max_files == 0 {
+                self.file.set_len(0)?;
+            } else {
+                let rotated = |n: usize| {
+                    let mut name = self.path.clone().into_os_string();
+                    name.push(format!(".{}", n));
+                    PathBuf::from(name)
+                };
+                if rotated(self.max_files).exists() {
+                    fs::remove_file(rotated(self.max_files))?;
+                }
+                for n in (1..self.max_files).rev() {
+                    if rotated(n).exists() {
+                        fs::rename(rotated(n), rotated(n + 1))?;
+                    }
+                }
+                fs::rename(&self.path, rotated(1))?;
+                self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            }
+            self.size = 0;
+            Ok(())
+        }
+    }
+
+    impl Write for RollingFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write_at(Utc::now(), buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.file.flush()
+        }
+    }
+
+    fn period_of(rotation: LogRotation, at: DateTime<Utc>) -> String {
+        match rotation {
+            LogRotation::Never => String::new(),
+            LogRotation::Hourly => at.format("%Y-%m-%d %H").to_string(),
+            LogRotation::Daily => at.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::TimeZone;
+
+        fn rotated(dir: &tempfile::TempDir, n: usize) -> String {
+            fs::read_to_string(dir.path().join(format!("node.log.{}", n))).unwrap()
+        }
+
+        #[test]
+        fn test_size_rotation_keeps_max_files() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("node.log");
+            let mut log = RollingFile::open(&path, Some(10), LogRotation::Never, 2).unwrap();
+            let now = Utc::now();
+            for line in ["aaaaaa\n", "bbbbbb\n", "cccccc\n", "dddddd\n"] {
+                log.write_at(now, line.as_bytes()).unwrap();
+            }
+
+            assert_eq!(fs::read_to_string(&path).unwrap(), "dddddd\n");
+            assert_eq!(rotated(&dir, 1), "cccccc\n");
+            assert_eq!(rotated(&dir, 2), "bbbbbb\n");
+            assert!(!dir.path().join("node.log.3").exists());
+        }
+
+        #[test]
+        fn test_daily_rotation() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("node.log");
+            let mut log = RollingFile::open(&path, None, LogRotation::Daily, 3).unwrap();
+            let day = Utc.with_ymd_and_hms(2024, 3, 1, 23, 0, 0).unwrap();
+            log.write_at(day, b"one\n").unwrap();
+            log.write_at(day + chrono::Duration::minutes(30), b"two\n").unwrap();
+            log.write_at(day + chrono::Duration::hours(2), b"three\n").unwrap();
+
+            assert_eq!(fs::read_to_string(&path).unwrap(), "three\n");
+            assert_eq!(rotated(&dir, 1), "one\ntwo\n");
+        }
+    }
 }
 
 /// Performance measurement utilities
@@ -327,7 +327,7 @@ impl MessageType {
     }
     
     /// Parse from string
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn parse(s: &str) -> Option<Self> {
         match s {
             "version" => Some(MessageType::Version),
             "verack" => Some(MessageType::VerAck),
@@ -584,8 +584,8 @@ mod tests {
     #[test]
     fn test_message_type() {
         assert_eq!(MessageType::Version.as_str(), "version");
-        assert_eq!(MessageType::from_str("version"), Some(MessageType::Version));
-        assert_eq!(MessageType::from_str("invalid"), None);
+        assert_eq!(MessageType::parse("version"), Some(MessageType::Version));
+        assert_eq!(MessageType::parse("invalid"), None);
     }
     
     #[test]
@@ -3,7 +3,11 @@
 //! This module provides time-related functions and utilities for working
 //! with timestamps, durations, and time formatting.
 
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
 use crate::error::LedgerError;
 
 /// Get current Unix timestamp in seconds
@@ -72,11 +76,7 @@ pub fn is_timestamp_valid(timestamp: u64, max_drift: Duration) -> bool {
 
 /// Calculate time difference between two timestamps
 pub fn time_diff(timestamp1: u64, timestamp2: u64) -> Duration {
-    let diff = if timestamp1 > timestamp2 {
-        timestamp1 - timestamp2
-    } else {
-        timestamp2 - timestamp1
-    };
+    let diff = timestamp1.abs_diff(timestamp2);
     Duration::from_secs(diff)
 }
 
@@ -191,11 +191,159 @@ impl RateLimiter {
     }
 }
 
+/// Source of the current time for chain logic.
+///
+/// Block templates, timestamp validation and pool expiry read the time through a
+/// `Clock` rather than calling `Utc::now()`, so tests can pin it with a [`MockClock`].
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to; clones share the same time
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// A clock stopped at `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: Arc::new(Mutex::new(now)) }
+    }
+
+    /// Move the clock to `now`, which may be in its past
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// Move the clock forward by `by`
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Peer clock samples needed before the local clock is corrected
+pub const MIN_TIME_SAMPLES: usize = 5;
+
+/// Most peer clock samples kept; older ones are dropped first
+pub const MAX_TIME_SAMPLES: usize = 200;
+
+/// Largest correction applied to the local clock, in seconds (70 minutes)
+pub const MAX_TIME_ADJUSTMENT: i64 = 70 * 60;
+
+/// Network-adjusted time: the local clock corrected by the median offset peers report.
+///
+/// A single peer with a wrong clock cannot move the median, and a median beyond
+/// [`MAX_TIME_ADJUSTMENT`] is ignored, since it more likely means our own clock is broken.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkTime {
+    offsets: VecDeque<i64>,
+}
+
+impl NetworkTime {
+    /// Create a tracker with no samples (offset 0)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a peer's clock as its offset from ours in seconds (peer time minus local time)
+    pub fn add_sample(&mut self, offset_secs: i64) {
+        if self.offsets.len() == MAX_TIME_SAMPLES {
+            self.offsets.pop_front();
+        }
+        self.offsets.push_back(offset_secs);
+    }
+
+    /// Number of samples recorded
+    pub fn sample_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Correction applied to the local clock, in seconds
+    pub fn offset(&self) -> i64 {
+        if self.offsets.len() < MIN_TIME_SAMPLES {
+            return 0;
+        }
+        let mut sorted: Vec<i64> = self.offsets.iter().copied().collect();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+        if median.abs() > MAX_TIME_ADJUSTMENT {
+            0
+        } else {
+            median
+        }
+    }
+
+    /// Current network-adjusted Unix timestamp in seconds
+    pub fn now(&self) -> i64 {
+        current_timestamp() as i64 + self.offset()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::time::Duration;
     
+    #[test]
+    fn test_network_time_offset() {
+        let mut time = NetworkTime::new();
+        for _ in 0..MIN_TIME_SAMPLES - 1 {
+            time.add_sample(600);
+        }
+        assert_eq!(time.offset(), 0, "too few samples");
+
+        time.add_sample(-5);
+        assert_eq!(time.offset(), 600);
+
+        // One wildly wrong peer does not move the median
+        time.add_sample(1_000_000);
+        assert_eq!(time.offset(), 600);
+
+        let mut skewed = NetworkTime::new();
+        for _ in 0..MIN_TIME_SAMPLES {
+            skewed.add_sample(MAX_TIME_ADJUSTMENT + 1);
+        }
+        assert_eq!(skewed.offset(), 0, "beyond the maximum adjustment");
+
+        for _ in 0..MAX_TIME_SAMPLES {
+            skewed.add_sample(-60);
+        }
+        assert_eq!(skewed.sample_count(), MAX_TIME_SAMPLES);
+        assert_eq!(skewed.offset(), -60);
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let start = DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        let clock = MockClock::new(start);
+        let shared = clock.clone();
+        assert_eq!(shared.now(), start);
+
+        clock.advance(chrono::Duration::seconds(90));
+        assert_eq!(shared.now(), start + chrono::Duration::seconds(90));
+
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+    
     #[test]
     fn test_current_timestamp() {
         let timestamp = current_timestamp();
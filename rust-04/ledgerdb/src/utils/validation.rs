@@ -150,7 +150,8 @@ pub fn validate_timestamp(timestamp: u64) -> Result<(), LedgerError> {
 /// Validate a difficulty value
 pub fn validate_difficulty(difficulty: u32) -> Result<(), LedgerError> {
     const MIN_DIFFICULTY: u32 = 1;
-    const MAX_DIFFICULTY: u32 = u32::MAX;
+    // Leading zero bits of a 256-bit hash
+    const MAX_DIFFICULTY: u32 = 256;
     
     if difficulty < MIN_DIFFICULTY {
         return Err(LedgerError::Validation(format!(
@@ -170,7 +171,7 @@ pub fn validate_difficulty(difficulty: u32) -> Result<(), LedgerError> {
 }
 
 /// Validate a nonce value
-pub fn validate_nonce(nonce: u64) -> Result<(), LedgerError> {
+pub fn validate_nonce(_nonce: u64) -> Result<(), LedgerError> {
     // Nonce can be any u64 value, so this is mostly a placeholder
     // In practice, you might want to check for specific patterns or ranges
     Ok(())
@@ -345,7 +346,7 @@ pub fn validate_ipv4(ip: &str) -> Result<(), LedgerError> {
     }
     
     for part in parts {
-        let num: u8 = part.parse().map_err(|_| {
+        let _: u8 = part.parse().map_err(|_| {
             LedgerError::Validation(format!("Invalid IPv4 part: {}", part))
         })?;
         
@@ -483,7 +484,7 @@ mod tests {
         assert!(validate_fee(500, 250).is_err());
         
         // Too high fee rate
-        assert!(validate_fee(250_000, 250).is_err());
+        assert!(validate_fee(300_000, 250).is_err());
     }
     
     #[test]
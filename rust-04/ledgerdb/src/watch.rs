@@ -0,0 +1,332 @@
+//! Address watch-list.
+//!
+//! Addresses registered through `/api/watch`, and those listed on webhook
+//! endpoints, are held in a [`WatchList`]. As the mempool and chain change it
+//! reports each payment to or from a watched address as [`AddressActivity`]: when
+//! the transaction enters the mempool, when it is mined, and again with every block
+//! on top until it has [`CONFIRMATION_TARGET`] confirmations. Activity is published
+//! to [`WatchList::subscribe`] receivers (the `watched_addresses` WebSocket topic)
+//! and returned to the caller, which forwards it to webhooks.
+
+use crate::core::blockchain::Blockchain;
+use crate::core::{Block, Transaction, UtxoId};
+use crate::crypto::{Address, Hash256};
+use crate::error::Result;
+use crate::storage::PersistentStorage;
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+
+pub use ledgerdb_types::{ActivityDirection, AddressActivity, WatchedAddress};
+
+/// Confirmations after which a transaction is no longer followed
+pub const CONFIRMATION_TARGET: u64 = 6;
+
+/// Watched addresses and the activity still being followed for them
+#[derive(Debug)]
+pub struct WatchList {
+    storage: Option<Arc<PersistentStorage>>,
+    addresses: RwLock<HashMap<Address, WatchedAddress>>,
+    progress: Mutex<Progress>,
+    activity: broadcast::Sender<AddressActivity>,
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// Mempool transactions already reported
+    pending: HashSet<Hash256>,
+    /// Mined activity short of the confirmation target
+    confirming: Vec<AddressActivity>,
+}
+
+impl WatchList {
+    /// Load the registered addresses from `storage`, or start empty without it
+    pub fn new(storage: Option<Arc<PersistentStorage>>) -> Result<Self> {
+        let mut addresses = HashMap::new();
+        if let Some(storage) = &storage {
+            for mut entry in storage.load_watched_addresses()? {
+                // Entries saved before addresses were encoded are keyed by the bare hash
+                let address = match Address::from_string(&entry.address) {
+                    Ok(address) => address,
+                    Err(_) => {
+                        let address = Address::from_hex(&entry.address)?;
+                        storage.remove_watched_address(&entry.address)?;
+                        entry.address = address.to_string();
+                        storage.store_watched_address(&entry)?;
+                        address
+                    }
+                };
+                addresses.insert(address, entry);
+            }
+        }
+        let (activity, _) = broadcast::channel(1000);
+        Ok(Self {
+            storage,
+            addresses: RwLock::new(addresses),
+            progress: Mutex::new(Progress::default()),
+            activity,
+        })
+    }
+
+    /// Watch `address`, replacing the label if it is already watched
+    pub fn add(&self, address: &Address, label: Option<String>) -> Result<WatchedAddress> {
+        let mut addresses = self.addresses.write().unwrap();
+        let entry = WatchedAddress {
+            address: address.to_string(),
+            label,
+            added_at: addresses.get(address).map(|e| e.added_at).unwrap_or_else(Utc::now),
+            configured: false,
+        };
+        if let Some(storage) = &self.storage {
+            storage.store_watched_address(&entry)?;
+        }
+        addresses.insert(address.clone(), entry.clone());
+        Ok(entry)
+    }
+
+    /// Watch `address` for this run only, unless it is already watched
+    pub fn add_configured(&self, address: &Address) {
+        self.addresses.write().unwrap().entry(address.clone()).or_insert_with(|| WatchedAddress {
+            address: address.to_string(),
+            label: None,
+            added_at: Utc::now(),
+            configured: true,
+        });
+    }
+
+    /// Stop watching `address`; returns whether it was watched
+    pub fn remove(&self, address: &Address) -> Result<bool> {
+        let mut addresses = self.addresses.write().unwrap();
+        if let Some(storage) = &self.storage {
+            storage.remove_watched_address(&address.to_string())?;
+        }
+        Ok(addresses.remove(address).is_some())
+    }
+
+    pub fn get(&self, address: &Address) -> Option<WatchedAddress> {
+        self.addresses.read().unwrap().get(address).cloned()
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.addresses.read().unwrap().contains_key(address)
+    }
+
+    /// Every watched address, ordered by address
+    pub fn list(&self) -> Vec<WatchedAddress> {
+        let mut entries: Vec<WatchedAddress> = self.addresses.read().unwrap().values().cloned().collect();
+        entries.sort_by(|a, b| a.address.cmp(&b.address));
+        entries
+    }
+
+    /// Receiver for all activity reported from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<AddressActivity> {
+        self.activity.subscribe()
+    }
+
+    /// Activity of mempool transactions not reported before
+    pub fn scan_pending(&self, blockchain: &Blockchain) -> Vec<AddressActivity> {
+        let pool = blockchain.get_pending_transactions();
+        let mut activity = Vec::new();
+        {
+            let mut progress = self.progress.lock().unwrap();
+            let in_pool: HashSet<Hash256> = pool.iter().map(|tx| tx.hash()).collect();
+            progress.pending.retain(|hash| in_pool.contains(hash));
+            for tx in pool {
+                if progress.pending.insert(tx.hash()) {
+                    activity.extend(self.transaction_activity(blockchain, tx, None, 0));
+                }
+            }
+        }
+        self.publish(activity)
+    }
+
+    /// Activity of the transactions in `block`, which `blockchain` holds
+    pub fn scan_block(&self, blockchain: &Blockchain, block: &Block) -> Vec<AddressActivity> {
+        let tip = blockchain.get_latest_block().map(|b| b.index).unwrap_or(block.index);
+        let confirmations = tip.saturating_sub(block.index) + 1;
+        let activity: Vec<AddressActivity> = block
+            .transactions()
+            .iter()
+            .flat_map(|tx| self.transaction_activity(blockchain, tx, Some(block), confirmations))
+            .collect();
+        {
+            let mut progress = self.progress.lock().unwrap();
+            for tx in block.transactions() {
+                progress.pending.remove(&tx.hash());
+            }
+            progress
+                .confirming
+                .extend(activity.iter().filter(|a| a.confirmations < CONFIRMATION_TARGET).cloned());
+        }
+        self.publish(activity)
+    }
+
+    /// New confirmation counts for mined activity still being followed.
+    ///
+    /// Activity whose block is no longer on the chain is reported once more with no
+    /// block and zero confirmations, then dropped.
+    pub fn update_confirmations(&self, blockchain: &Blockchain) -> Vec<AddressActivity> {
+        let tip = blockchain.get_latest_block().map(|b| b.index).unwrap_or(0);
+        let mut updates = Vec::new();
+        {
+            let mut progress = self.progress.lock().unwrap();
+            progress.confirming.retain_mut(|entry| {
+                let height = entry.block_height.unwrap_or(0);
+                let on_chain = blockchain
+                    .get_block_by_index(height)
+                    .is_some_and(|b| Some(b.hash().to_hex()) == entry.block_hash);
+                if !on_chain {
+                    updates.push(AddressActivity {
+                        block_height: None,
+                        block_hash: None,
+                        confirmations: 0,
+                        ..entry.clone()
+                    });
+                    return false;
+                }
+                let confirmations = tip.saturating_sub(height) + 1;
+                if confirmations > entry.confirmations {
+                    entry.confirmations = confirmations;
+                    updates.push(entry.clone());
+                }
+                entry.confirmations < CONFIRMATION_TARGET
+            });
+        }
+        self.publish(updates)
+    }
+
+    /// What `tx` moves to and from watched addresses
+    fn transaction_activity(
+        &self,
+        blockchain: &Blockchain,
+        tx: &Transaction,
+        block: Option<&Block>,
+        confirmations: u64,
+    ) -> Vec<AddressActivity> {
+        let addresses = self.addresses.read().unwrap();
+        if addresses.is_empty() {
+            return Vec::new();
+        }
+
+        let mut moved: BTreeMap<(String, u8), (ActivityDirection, u64)> = BTreeMap::new();
+        for input in tx.inputs.iter().filter(|input| !input.is_coinbase()) {
+            let utxo_id = UtxoId::new(input.previous_tx_hash.clone(), input.output_index);
+            let spent = match blockchain.get_utxo(&utxo_id) {
+                Some(entry) => Some(entry.output.clone()),
+                // Already spent, by this transaction's block
+                None => blockchain
+                    .get_transaction(&input.previous_tx_hash)
+                    .and_then(|prev| prev.outputs.get(input.output_index as usize).cloned()),
+            };
+            if let Some(output) = spent.filter(|o| addresses.contains_key(&o.recipient)) {
+                moved.entry((output.recipient.to_string(), 1)).or_insert((ActivityDirection::Spent, 0)).1 += output.amount;
+            }
+        }
+        for output in tx.outputs.iter().filter(|o| addresses.contains_key(&o.recipient)) {
+            moved.entry((output.recipient.to_string(), 0)).or_insert((ActivityDirection::Received, 0)).1 += output.amount;
+        }
+
+        let tx_hash = tx.hash().to_hex();
+        moved
+            .into_iter()
+            .map(|((address, _), (direction, amount))| AddressActivity {
+                address,
+                tx_hash: tx_hash.clone(),
+                direction,
+                amount,
+                block_height: block.map(|b| b.index),
+                block_hash: block.map(|b| b.hash().to_hex()),
+                confirmations,
+            })
+            .collect()
+    }
+
+    fn publish(&self, activity: Vec<AddressActivity>) -> Vec<AddressActivity> {
+        for entry in &activity {
+            // No receivers is fine; the activity is still returned
+            let _ = self.activity.send(entry.clone());
+        }
+        activity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::BlockchainConfig;
+    use crate::core::{TransactionInput, TransactionOutput};
+    use crate::crypto::{PublicKey, SignatureAlgorithm};
+
+    fn address(seed: u8) -> Address {
+        Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![seed; 33]))
+    }
+
+    fn mine(blockchain: &mut Blockchain, miner: &Address) -> Block {
+        let block = blockchain.create_block(miner.clone()).unwrap();
+        blockchain.add_block(block).unwrap();
+        blockchain.get_latest_block().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_watch_list_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(PersistentStorage::new(dir.path().join("db")).unwrap());
+        let watch = WatchList::new(Some(storage.clone())).unwrap();
+        watch.add(&address(1), Some("cold wallet".to_string())).unwrap();
+        watch.add(&address(2), None).unwrap();
+        watch.add_configured(&address(3));
+        assert!(watch.remove(&address(2)).unwrap());
+        assert!(!watch.remove(&address(2)).unwrap());
+        assert_eq!(watch.list().len(), 2);
+
+        let reopened = WatchList::new(Some(storage)).unwrap();
+        assert_eq!(reopened.list(), vec![watch.get(&address(1)).unwrap()]);
+        assert_eq!(reopened.get(&address(1)).unwrap().label.as_deref(), Some("cold wallet"));
+    }
+
+    #[test]
+    fn test_activity_follows_confirmations() {
+        let (miner, watched) = (address(1), address(2));
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), miner.clone()).unwrap();
+        let watch = WatchList::new(None).unwrap();
+        watch.add(&watched, None).unwrap();
+        let mut receiver = watch.subscribe();
+
+        let paid = mine(&mut blockchain, &watched);
+        let activity = watch.scan_block(&blockchain, &paid);
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].direction, ActivityDirection::Received);
+        assert_eq!(activity[0].confirmations, 1);
+        assert_eq!(receiver.try_recv().unwrap(), activity[0]);
+
+        let mut seen = vec![1];
+        for _ in 0..CONFIRMATION_TARGET + 2 {
+            mine(&mut blockchain, &miner);
+            seen.extend(watch.update_confirmations(&blockchain).iter().map(|a| a.confirmations));
+        }
+        assert_eq!(seen, (1..=CONFIRMATION_TARGET).collect::<Vec<_>>());
+
+        // Blocks for other addresses report nothing
+        let other = mine(&mut blockchain, &miner);
+        assert!(watch.scan_block(&blockchain, &other).is_empty());
+    }
+
+    #[test]
+    fn test_spending_is_reported() {
+        let watched = address(2);
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), address(1)).unwrap();
+        let paid = mine(&mut blockchain, &watched);
+        let watch = WatchList::new(None).unwrap();
+        watch.add(&watched, None).unwrap();
+
+        let coinbase = &paid.transactions()[0];
+        let spend = Transaction::new(
+            vec![TransactionInput::new(coinbase.hash(), 0, None, None)],
+            vec![TransactionOutput::new(10, address(3)), TransactionOutput::new(5, watched.clone())],
+        );
+        let activity = watch.transaction_activity(&blockchain, &spend, None, 0);
+        let moved: Vec<_> = activity.iter().map(|a| (a.direction, a.amount)).collect();
+        assert_eq!(moved, [(ActivityDirection::Received, 5), (ActivityDirection::Spent, coinbase.outputs[0].amount)]);
+    }
+}
@@ -0,0 +1,539 @@
+//! Webhook notifications for chain events.
+//!
+//! A supervised task watches the chain tip and mempool and turns every change into
+//! [`WebhookEvent`]s: one `block` event per block added, a `reorg` event when the
+//! tip moves to another branch, and a `transaction` event for each
+//! [`AddressActivity`] the [`WatchList`] reports. Each configured endpoint receives
+//! the events it asked for as JSON POSTs, in order, retried with exponential
+//! backoff until one returns a 2xx status or the retries run out.
+//!
+//! When an endpoint has a secret, requests carry `X-Ledger-Signature:
+//! sha256=<hex>`, the HMAC-SHA256 of the raw body under that secret, so receivers
+//! can check where a payload came from.
+
+use crate::api::Shutdown;
+use crate::config::{WebhookConfig, WebhookEndpoint, WebhookEventKind};
+use crate::core::blockchain::Blockchain;
+use crate::core::Block;
+use crate::crypto::{Address, Hash256};
+use crate::error::{LedgerError, Result};
+use crate::tasks::{RestartPolicy, TaskManager};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::watch::{AddressActivity, WatchList};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// How often the chain tip is checked for changes
+const CHAIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Recent blocks remembered to find the fork point of a reorg
+const REORG_WINDOW: usize = 100;
+
+/// A chain event delivered to webhook endpoints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum WebhookEvent {
+    /// A block was added to the chain
+    Block {
+        height: u64,
+        hash: String,
+        previous_hash: String,
+        timestamp: DateTime<Utc>,
+        transactions: usize,
+    },
+    /// The blocks above `fork_height` were replaced by another branch
+    Reorg {
+        fork_height: u64,
+        old_height: u64,
+        old_tip: String,
+        new_height: u64,
+        new_tip: String,
+    },
+    /// A transaction moved funds to or from a watched address, or gained a confirmation
+    Transaction(AddressActivity),
+}
+
+impl WebhookEvent {
+    /// The kind endpoints subscribe to
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            WebhookEvent::Block { .. } => WebhookEventKind::Block,
+            WebhookEvent::Reorg { .. } => WebhookEventKind::Reorg,
+            WebhookEvent::Transaction(_) => WebhookEventKind::Transaction,
+        }
+    }
+
+    fn for_block(block: &Block) -> Self {
+        WebhookEvent::Block {
+            height: block.index,
+            hash: block.hash().to_hex(),
+            previous_hash: block.header.previous_hash.to_hex(),
+            timestamp: block.header.timestamp,
+            transactions: block.transactions().len(),
+        }
+    }
+}
+
+/// Body of a webhook request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Unique per event; repeated unchanged on retries
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            event,
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of `body` under `secret`, as sent in `X-Ledger-Signature`
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Follows the chain tip and reports what changed since the last look
+#[derive(Debug, Default)]
+pub struct ChainWatcher {
+    /// `(height, hash)` of the most recent blocks seen, oldest first
+    recent: VecDeque<(u64, Hash256)>,
+}
+
+impl ChainWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for the changes to `blockchain` since the previous call, including the
+    /// activity `watch` reports for them.
+    ///
+    /// The first call only records the tip. A reorg deeper than the remembered
+    /// window is logged and the watcher starts over from the new tip.
+    pub fn poll(&mut self, blockchain: &Blockchain, watch: &WatchList) -> Vec<WebhookEvent> {
+        let mut events = self.chain_events(blockchain, watch);
+        let activity = watch.update_confirmations(blockchain).into_iter().chain(watch.scan_pending(blockchain));
+        events.extend(activity.map(WebhookEvent::Transaction));
+        events
+    }
+
+    fn chain_events(&mut self, blockchain: &Blockchain, watch: &WatchList) -> Vec<WebhookEvent> {
+        let Some(tip) = blockchain.get_latest_block() else {
+            return Vec::new();
+        };
+        let Some((last_height, last_hash)) = self.recent.back().cloned() else {
+            // Start from the blocks already there, so a reorg below the tip is seen
+            let start = (tip.index + 1).saturating_sub(REORG_WINDOW as u64);
+            for height in start..=tip.index {
+                if let Some(block) = blockchain.get_block_by_index(height) {
+                    self.remember(height, block.hash());
+                }
+            }
+            return Vec::new();
+        };
+        if tip.index == last_height && tip.hash() == last_hash {
+            return Vec::new();
+        }
+
+        let fork = self.recent.iter().rev().find(|(height, hash)| {
+            blockchain.get_block_by_index(*height).map(|b| b.hash()).as_ref() == Some(hash)
+        });
+        let Some(&(fork_height, _)) = fork else {
+            warn!(
+                "Chain tip moved to {} without a common block in the last {} seen; skipping to it",
+                tip.index, REORG_WINDOW
+            );
+            self.recent.clear();
+            self.remember(tip.index, tip.hash());
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        if fork_height < last_height {
+            events.push(WebhookEvent::Reorg {
+                fork_height,
+                old_height: last_height,
+                old_tip: last_hash.to_hex(),
+                new_height: tip.index,
+                new_tip: tip.hash().to_hex(),
+            });
+            self.recent.retain(|(height, _)| *height <= fork_height);
+        }
+        for height in fork_height + 1..=tip.index {
+            if let Some(block) = blockchain.get_block_by_index(height) {
+                events.push(WebhookEvent::for_block(block));
+                events.extend(watch.scan_block(blockchain, block).into_iter().map(WebhookEvent::Transaction));
+                self.remember(height, block.hash());
+            }
+        }
+        events
+    }
+
+    fn remember(&mut self, height: u64, hash: Hash256) {
+        self.recent.push_back((height, hash));
+        while self.recent.len() > REORG_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// An endpoint with its parsed address filter
+#[derive(Debug, Clone)]
+struct Subscriber {
+    endpoint: WebhookEndpoint,
+    /// Addresses whose activity the endpoint gets; empty means the whole watch-list
+    addresses: HashSet<Address>,
+}
+
+impl Subscriber {
+    fn new(endpoint: &WebhookEndpoint) -> Result<Self> {
+        let addresses = endpoint
+            .addresses
+            .iter()
+            .map(|a| Address::from_string(a))
+            .collect::<Result<_>>()?;
+        Ok(Self { endpoint: endpoint.clone(), addresses })
+    }
+
+    fn wants(&self, event: &WebhookEvent) -> bool {
+        if !self.endpoint.events.is_empty() && !self.endpoint.events.contains(&event.kind()) {
+            return false;
+        }
+        match event {
+            WebhookEvent::Transaction(activity) if !self.addresses.is_empty() => {
+                Address::from_string(&activity.address).is_ok_and(|a| self.addresses.contains(&a))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Sends payloads to one endpoint, retrying failures with exponential backoff
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    client: reqwest::Client,
+    endpoint: WebhookEndpoint,
+    max_retries: u32,
+    initial_backoff: Duration,
+}
+
+impl WebhookSender {
+    pub fn new(endpoint: WebhookEndpoint, config: &WebhookConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .map_err(|e| LedgerError::Network(format!("Failed to create webhook client: {}", e)))?;
+        Ok(Self {
+            client,
+            endpoint,
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+        })
+    }
+
+    /// POST `payload` until the endpoint accepts it; fails once every retry has
+    pub async fn deliver(&self, payload: &WebhookPayload) -> Result<()> {
+        let body = serde_json::to_vec(payload)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to encode webhook payload: {}", e)))?;
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let error = match self.post(payload, &body).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if attempt >= self.max_retries {
+                return Err(LedgerError::Network(format!(
+                    "Webhook {} failed after {} attempts: {}",
+                    self.endpoint.url,
+                    attempt + 1,
+                    error
+                )));
+            }
+            debug!("Webhook {} attempt {} failed: {}; retrying in {:?}", self.endpoint.url, attempt + 1, error, backoff);
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    async fn post(&self, payload: &WebhookPayload, body: &[u8]) -> std::result::Result<(), String> {
+        let mut request = self
+            .client
+            .post(&self.endpoint.url)
+            .header("Content-Type", "application/json")
+            .header("X-Ledger-Event", payload.event.kind().to_string())
+            .header("X-Ledger-Delivery", payload.id.to_string());
+        if let Some(secret) = &self.endpoint.secret {
+            request = request.header("X-Ledger-Signature", format!("sha256={}", sign_payload(secret, body)));
+        }
+        let response = request.body(body.to_vec()).send().await.map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("status {}", response.status()))
+        }
+    }
+}
+
+/// Follow `blockchain` until shutdown, reporting activity for `watch` and
+/// delivering events to the configured endpoints.
+///
+/// Addresses listed on endpoints are added to `watch` for this run.
+pub fn spawn_chain_events(
+    tasks: &TaskManager,
+    blockchain: Arc<RwLock<Blockchain>>,
+    watch: Arc<WatchList>,
+    config: &WebhookConfig,
+) -> Result<()> {
+    let subscribers = config.endpoints.iter().map(Subscriber::new).collect::<Result<Vec<_>>>()?;
+    let senders = config
+        .endpoints
+        .iter()
+        .map(|endpoint| WebhookSender::new(endpoint.clone(), config))
+        .collect::<Result<Vec<_>>>()?;
+    for address in subscribers.iter().flat_map(|s| &s.addresses) {
+        watch.add_configured(address);
+    }
+
+    let policy = RestartPolicy::OnFailure {
+        max_restarts: 5,
+        backoff: Duration::from_secs(5),
+    };
+    tasks.spawn("chain-events", policy, move |shutdown| {
+        run_chain_events(blockchain.clone(), watch.clone(), subscribers.clone(), senders.clone(), shutdown)
+    })
+}
+
+async fn run_chain_events(
+    blockchain: Arc<RwLock<Blockchain>>,
+    watch: Arc<WatchList>,
+    subscribers: Vec<Subscriber>,
+    senders: Vec<WebhookSender>,
+    shutdown: Shutdown,
+) -> Result<()> {
+    // One queue and worker per endpoint keeps each endpoint's events in order
+    // without a slow endpoint holding up the others
+    let queues: Vec<mpsc::UnboundedSender<WebhookPayload>> = senders
+        .into_iter()
+        .map(|sender| {
+            let (queue, mut pending) = mpsc::unbounded_channel::<WebhookPayload>();
+            tokio::spawn(async move {
+                while let Some(payload) = pending.recv().await {
+                    if let Err(e) = sender.deliver(&payload).await {
+                        warn!("Dropping webhook event {}: {}", payload.id, e);
+                    }
+                }
+            });
+            queue
+        })
+        .collect();
+
+    let mut watcher = ChainWatcher::new();
+    let mut ticker = tokio::time::interval(CHAIN_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.wait() => return Ok(()),
+        }
+        let events = watcher.poll(&*blockchain.read().await, &watch);
+        for event in events {
+            let payload = WebhookPayload::new(event);
+            for (subscriber, queue) in subscribers.iter().zip(&queues) {
+                if subscriber.wants(&payload.event) {
+                    let _ = queue.send(payload.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::BlockchainConfig;
+    use crate::crypto::{PublicKey, SignatureAlgorithm};
+    use crate::watch::ActivityDirection;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::Mutex;
+
+    fn address(seed: u8) -> Address {
+        Address::from_public_key(&PublicKey::new(SignatureAlgorithm::EcdsaSecp256k1, vec![seed; 33]))
+    }
+
+    fn mine(blockchain: &mut Blockchain, miner: &Address, blocks: usize) {
+        for _ in 0..blocks {
+            let block = blockchain.create_block(miner.clone()).unwrap();
+            blockchain.add_block(block).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_watcher_reports_blocks_and_payments() {
+        let (miner, watched) = (address(1), address(2));
+        let mut blockchain = Blockchain::new(BlockchainConfig::default(), miner.clone()).unwrap();
+        let mut watcher = ChainWatcher::new();
+        let watch = WatchList::new(None).unwrap();
+        watch.add(&watched, None).unwrap();
+        assert!(watcher.poll(&blockchain, &watch).is_empty());
+
+        mine(&mut blockchain, &miner, 1);
+        mine(&mut blockchain, &watched, 1);
+        let events = watcher.poll(&blockchain, &watch);
+        let kinds: Vec<_> = events.iter().map(WebhookEvent::kind).collect();
+        assert_eq!(kinds, [WebhookEventKind::Block, WebhookEventKind::Block, WebhookEventKind::Transaction]);
+        let WebhookEvent::Transaction(activity) = &events[2] else {
+            panic!("unexpected event {:?}", events[2]);
+        };
+        assert_eq!(activity.address, watched.to_string());
+        assert_eq!((activity.block_height, activity.confirmations), (Some(2), 1));
+        assert!(watcher.poll(&blockchain, &watch).is_empty());
+
+        // The next block confirms the payment again
+        mine(&mut blockchain, &miner, 1);
+        let events = watcher.poll(&blockchain, &watch);
+        assert!(matches!(&events[1], WebhookEvent::Transaction(a) if a.confirmations == 2));
+    }
+
+    #[test]
+    fn test_watcher_detects_reorg() {
+        let mut original = Blockchain::new(BlockchainConfig::default(), address(1)).unwrap();
+        mine(&mut original, &address(1), 3);
+
+        // A longer branch sharing genesis and block 1
+        let mut branch = Blockchain::new(BlockchainConfig::default(), address(1)).unwrap();
+        branch.add_block(original.get_block_by_index(1).unwrap().clone()).unwrap();
+        mine(&mut branch, &address(3), 3);
+
+        let mut watcher = ChainWatcher::new();
+        let watch = WatchList::new(None).unwrap();
+        watcher.poll(&original, &watch);
+        let events = watcher.poll(&branch, &watch);
+        assert_eq!(
+            events[0],
+            WebhookEvent::Reorg {
+                fork_height: 1,
+                old_height: 3,
+                old_tip: original.get_latest_block().unwrap().hash().to_hex(),
+                new_height: 4,
+                new_tip: branch.get_latest_block().unwrap().hash().to_hex(),
+            }
+        );
+        let heights: Vec<u64> = events[1..]
+            .iter()
+            .map(|e| match e {
+                WebhookEvent::Block { height, .. } => *height,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(heights, [2, 3, 4]);
+    }
+
+    #[test]
+    fn test_subscriber_filters() {
+        let watched = address(2);
+        let subscriber = Subscriber::new(&WebhookEndpoint {
+            url: "http://localhost/hook".to_string(),
+            events: vec![WebhookEventKind::Transaction],
+            addresses: vec![watched.to_string()],
+            secret: None,
+        })
+        .unwrap();
+        let payment = |address: &Address| {
+            WebhookEvent::Transaction(AddressActivity {
+                address: address.to_string(),
+                tx_hash: "00".repeat(32),
+                direction: ActivityDirection::Received,
+                amount: 5,
+                block_height: Some(1),
+                block_hash: Some("11".repeat(32)),
+                confirmations: 1,
+            })
+        };
+        assert!(subscriber.wants(&payment(&watched)));
+        assert!(!subscriber.wants(&payment(&address(3))));
+
+        // Without addresses of its own an endpoint gets the whole watch-list's activity
+        let everything = Subscriber::new(&WebhookEndpoint { addresses: Vec::new(), ..subscriber.endpoint.clone() }).unwrap();
+        assert!(everything.wants(&payment(&address(3))));
+        assert!(!subscriber.wants(&WebhookEvent::Reorg {
+            fork_height: 0,
+            old_height: 1,
+            old_tip: String::new(),
+            new_height: 1,
+            new_tip: String::new(),
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_and_signs() {
+        type Received = Arc<Mutex<Vec<(HeaderMap, Vec<u8>)>>>;
+        async fn hook(State(received): State<Received>, headers: HeaderMap, body: axum::body::Bytes) -> StatusCode {
+            let mut received = received.lock().unwrap();
+            received.push((headers, body.to_vec()));
+            // Fail the first attempt so the sender has to retry
+            if received.len() == 1 {
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            }
+        }
+
+        let received: Received = Arc::default();
+        let app = Router::new().route("/hook", post(hook)).with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let endpoint = WebhookEndpoint { url, events: Vec::new(), addresses: Vec::new(), secret: Some("key".to_string()) };
+        let config = WebhookConfig { initial_backoff_ms: 1, max_retries: 2, ..WebhookConfig::default() };
+        let sender = WebhookSender::new(endpoint.clone(), &config).unwrap();
+        let payload = WebhookPayload::new(WebhookEvent::Reorg {
+            fork_height: 4,
+            old_height: 6,
+            old_tip: "aa".repeat(32),
+            new_height: 7,
+            new_tip: "bb".repeat(32),
+        });
+        sender.deliver(&payload).await.unwrap();
+
+        let (headers, body) = {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 2);
+            received[1].clone()
+        };
+        assert_eq!(headers["x-ledger-event"], "reorg");
+        assert_eq!(headers["x-ledger-delivery"], payload.id.to_string());
+        assert_eq!(headers["x-ledger-signature"], format!("sha256={}", sign_payload("key", &body)));
+        let decoded: WebhookPayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(decoded, payload);
+
+        // An endpoint that never answers fails after its retries
+        let dead = WebhookEndpoint { url: "http://127.0.0.1:9/hook".to_string(), ..endpoint };
+        assert!(WebhookSender::new(dead, &config).unwrap().deliver(&payload).await.is_err());
+    }
+}